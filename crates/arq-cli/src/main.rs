@@ -1,15 +1,73 @@
+use arq_core::agent::{
+    check_scope, parse_unified_diff, untracked_changes, AgentProgress, AgentRunner,
+    ConformanceChecker, ConformanceReport, ConformanceStatus,
+};
+use arq_core::config::{secrets, DEFAULT_EMBEDDER_DAEMON_ADDR};
+use arq_core::events::{EventFormat, EventWriter, ProgressEvent};
+use arq_core::knowledge::indexer::{matches_pattern, GeneratedCodePolicy, DOCUMENT_ENTITY_TYPE};
+use arq_core::knowledge::ontology::nodes::Visibility;
+use arq_core::knowledge::ontology::{CallResolution, QualifiedName};
+use arq_core::knowledge::{
+    backup, cochange, parse_as_of, serve_embeddings, test_impact, ApiRelation, CallDirection,
+    CentralityMetric, CrossingCall, DocsFormat, Embedder, EntityFilter, ExportFormat, FastEmbedder,
+    FileChanges, FileWatcher, IndexTimings, LanguageStats, MineOptions, PathBlocklist,
+    RemoteEmbedder, SearchContext, TestImpactFormat, VerifyIssue, WatchFilter, EDGE_RELATIONS,
+};
+use arq_core::llm::{list_models, models_endpoint, ping_provider, JobQueue};
+use arq_core::planning::{scaffold_tests, TestConventions};
+use arq_core::prompts;
+use arq_core::research::{
+    offline_research, summarize_index, ComparedResearch, ContextCache, CostEstimate, CostLimit,
+    ResearchComparison, SentContextLog,
+};
+use arq_core::storage::PrivateFiles;
 use arq_core::{
-    Config, ContextBuilder, FileStorage, IndexProgress, IndexStats, KnowledgeGraph, KnowledgeStore,
-    Phase, Provider, ResearchRunner, SearchResult, TaskManager,
+    suggest_task_name, ArqError, Config, ContextBuilder, FileOrder, FileStorage, HookEvent, Hooks,
+    IndexProgress, IndexReport, IndexStats, KnowledgeError, KnowledgeGraph, KnowledgeStore,
+    LLMConfig, LLMError, Phase, Provider, ResearchProgress, ResearchRunner, SearchResult,
+    StreamChunk, Task, TaskManager, TaskStats, TaskSummary, TokenUsage, UsageRecord,
 };
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
 use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{IsTerminal, Write};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 mod banner;
+mod mcp;
 mod serve;
 mod tui;
 
+/// Column width for `arq research --compare` output.
+const COMPARE_COLUMN_WIDTH: usize = 58;
+
+/// Co-changed files listed by `arq graph impact`.
+const CO_CHANGE_LIMIT: usize = 10;
+
+/// Largest source files named in the research prompt breakdown.
+const PROMPT_STATS_TOP_FILES: usize = 3;
+
+/// Git's empty tree, which `arq check` diffs against before the first commit.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Error for task commands run before any task exists.
+const NO_CURRENT_TASK: &str = "No current task. Use 'arq new <prompt>' first.";
+
+/// Human-readable output, moved to stderr while `--events` owns stdout.
+macro_rules! say {
+    ($events:expr) => {
+        say!($events, "")
+    };
+    ($events:expr, $($arg:tt)*) => {
+        if $events.is_some() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
 #[derive(Parser)]
 #[command(name = "arq")]
 #[command(version)]
@@ -18,6 +76,18 @@ mod tui;
     about = "AI coding engine for deep codebase understanding and high-precision code generation"
 )]
 struct Cli {
+    /// Apply a `[profile.<name>]` section of the config file (or set ARQ_PROFILE)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Bypass the LLM response cache for this run (see llm.cache)
+    #[arg(long, global = true)]
+    no_llm_cache: bool,
+
+    /// Emit progress of `init` and `research` as events on stdout (ndjson)
+    #[arg(long, global = true, value_name = "FORMAT")]
+    events: Option<EventFormat>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -29,52 +99,217 @@ enum Commands {
         /// Description of what you want to build
         #[arg(required = true)]
         prompt: Vec<String>,
+        /// Task name (default: derived from the prompt)
+        #[arg(long)]
+        name: Option<String>,
+        /// Ask the LLM for a short task name instead of deriving it from the prompt
+        #[arg(long, conflicts_with = "name")]
+        suggest_name: bool,
     },
     /// Show current task status
     Status,
     /// List all tasks
-    List,
+    List {
+        /// Only list tasks with this label
+        #[arg(long)]
+        label: Option<String>,
+    },
     /// Delete a task
     Delete {
-        /// Task ID to delete
+        /// Task ID (or prefix) or part of its name
         id: String,
+        /// Don't ask before deleting a task matched by name
+        #[arg(long)]
+        yes: bool,
     },
+    /// Import tasks mirrored under .arq/tasks/ (see storage.mirror_to_repo)
+    Import,
     /// Switch to a different task
     Switch {
-        /// Task ID to switch to
+        /// Task ID (or prefix) or part of its name
+        id: String,
+    },
+    /// Rename a task
+    Rename {
+        /// Task ID (or prefix) or part of its name
         id: String,
+        /// New task name
+        #[arg(required = true)]
+        name: Vec<String>,
+    },
+    /// Add or remove free-form task labels
+    Label {
+        #[command(subcommand)]
+        action: LabelAction,
     },
     /// Run research phase for current task
-    Research,
+    Research {
+        /// Run the research against two providers concurrently and compare
+        /// the results (e.g. `anthropic,openai:gpt-4o`). Does not save the
+        /// research to the task.
+        #[arg(long, value_delimiter = ',', value_name = "PROVIDERS")]
+        compare: Vec<String>,
+        /// Skip the LLM and build a research skeleton from the knowledge
+        /// graph only (relevant files, call dependencies, structure outline)
+        #[arg(long, conflicts_with = "compare")]
+        offline: bool,
+        /// Reassemble context instead of reusing context cached by an
+        /// earlier run of this task
+        #[arg(long)]
+        no_cache: bool,
+        /// List the files about to be sent to the LLM and choose which to
+        /// leave out; the choice is kept for later runs of the task
+        #[arg(long, conflicts_with_all = ["compare", "offline"])]
+        review_context: bool,
+        /// Index the project into a throwaway in-memory knowledge graph for
+        /// this run instead of using .arq/knowledge.db
+        #[arg(long, conflicts_with_all = ["compare", "offline"])]
+        in_memory: bool,
+    },
     /// Advance to the next phase
     Advance,
+    /// Summarize activity across all tasks
+    Stats,
+    /// Check uncommitted changes against the current task's plan
+    Check {
+        /// Check staged changes only
+        #[arg(long)]
+        staged: bool,
+        /// Only check file scope, skip the LLM review
+        #[arg(long)]
+        no_llm: bool,
+    },
+    /// Work with the current task's plan
+    Plan {
+        #[command(subcommand)]
+        action: PlanAction,
+    },
+    /// Carry out the current task's plan
+    Agent {
+        #[command(subcommand)]
+        action: AgentAction,
+    },
     /// Index codebase into knowledge graph
     Init {
         /// Force re-indexing even if already indexed
         #[arg(short, long)]
         force: bool,
+        /// Re-index only the files that failed in the last run
+        #[arg(long, conflicts_with = "force")]
+        retry_failed: bool,
+        /// Index into memory and report the result without writing the
+        /// database, e.g. to check in CI that the project indexes cleanly
+        #[arg(long, conflicts_with_all = ["force", "retry_failed"])]
+        in_memory: bool,
+        /// Also index the markdown and reStructuredText files under these
+        /// folders as documentation (adds to `knowledge.docs`)
+        #[arg(long, num_args = 1.., value_name = "DIR")]
+        docs: Vec<String>,
+        /// Files to index at once (overrides `knowledge.index_jobs`; 0 for
+        /// one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<usize>,
     },
     /// Search code using semantic search
     Search {
-        /// Search query
-        #[arg(required = true)]
+        /// Search query (optional when filtering by function metadata)
+        #[arg(required_unless_present_any = ["returns", "param", "is_async", "visibility"])]
         query: Vec<String>,
         /// Maximum number of results
         #[arg(short, long, default_value = "10")]
         limit: usize,
+        /// Only return results from this workspace crate
+        #[arg(long = "crate", value_name = "NAME")]
+        crate_name: Option<String>,
+        /// Only functions whose return type contains this type (e.g. `Result<Plan>`)
+        #[arg(long, value_name = "TYPE")]
+        returns: Option<String>,
+        /// Only functions with a parameter whose type contains this type
+        #[arg(long, value_name = "TYPE")]
+        param: Option<String>,
+        /// Only async functions
+        #[arg(long = "async")]
+        is_async: bool,
+        /// Only functions with this visibility (pub, pub(crate), pub(super), private)
+        #[arg(long, value_name = "VIS", value_parser = parse_visibility)]
+        visibility: Option<Visibility>,
+        /// Show N lines around each hit and the signature of its enclosing function
+        #[arg(short = 'C', long, value_name = "N")]
+        context: Option<usize>,
     },
-    /// Show knowledge graph statistics
+    /// Regex search over indexed files, optionally expanded with the knowledge graph
+    Grep {
+        /// Regular expression matched against each line
+        pattern: String,
+        /// Show the function enclosing each hit and its direct callers
+        #[arg(long)]
+        semantic_expand: bool,
+        /// Maximum number of matching lines
+        #[arg(short, long, default_value = "100")]
+        limit: usize,
+    },
+    /// List the tests likely affected by changed files, following callers
+    /// in the knowledge graph back to test functions
+    TestImpact {
+        /// Changed files, relative to the project root
+        #[arg(required_unless_present = "git_diff")]
+        files: Vec<String>,
+        /// Use the files changed relative to this commit (default: HEAD)
+        #[arg(long, value_name = "BASE", num_args = 0..=1, default_missing_value = "HEAD")]
+        git_diff: Option<String>,
+        /// Most calls followed back from changed code
+        #[arg(long, default_value_t = test_impact::DEFAULT_MAX_DEPTH)]
+        depth: usize,
+        /// Output: list, files, cargo (`cargo test -- <filters>`), pytest (`pytest -k`) or json
+        #[arg(long, default_value = "list")]
+        format: TestImpactFormat,
+    },
+    /// Inspect and maintain the knowledge graph
+    Kg {
+        #[command(subcommand)]
+        action: KgAction,
+    },
+    /// Generate documentation from the knowledge graph
+    Docs {
+        #[command(subcommand)]
+        action: DocsAction,
+    },
+    /// Show knowledge graph statistics (alias for `kg status`)
+    #[command(hide = true)]
     KgStatus,
-    /// Clear the knowledge graph database
+    /// Clear the knowledge graph database (alias for `kg clear`)
+    #[command(hide = true)]
     KgClear,
     /// Query graph relationships (dependencies and impact)
     Graph {
         #[command(subcommand)]
         action: GraphAction,
     },
+    /// Manage configuration secrets
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Check LLM providers before a long run
+    Llm {
+        #[command(subcommand)]
+        action: LlmAction,
+    },
+    /// Discover the models offered by the configured LLM provider
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+    },
     /// Launch interactive TUI chat interface
     #[command(alias = "ui")]
-    Tui,
+    Tui {
+        /// Record the session's events and state to a JSON Lines file
+        #[arg(long, value_name = "FILE")]
+        record: Option<std::path::PathBuf>,
+        /// Replay a recorded session without calling the LLM
+        #[arg(long, value_name = "FILE", conflicts_with = "record")]
+        replay: Option<std::path::PathBuf>,
+    },
     /// Start visualization server for knowledge graph
     Serve {
         /// Port to run the server on
@@ -83,49 +318,363 @@ enum Commands {
         /// Don't automatically open browser
         #[arg(long)]
         no_open: bool,
+        /// Re-index changed files while serving and update the graph live
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Serve search and call-graph tools to external agents over the Model
+    /// Context Protocol (stdio)
+    Mcp,
+    /// Keep the knowledge graph current by re-indexing files as they change
+    Watch {
+        /// Wait this long after a change for more before re-indexing
+        #[arg(long, value_name = "MS", default_value = "500")]
+        debounce: u64,
+    },
+    /// Manage the warm embedder daemon
+    Embedder {
+        #[command(subcommand)]
+        action: EmbedderAction,
     },
+    /// Check the setup and report knowledge graph startup times
+    Doctor,
     /// Upgrade to the latest version
     Upgrade,
 }
 
+#[derive(Subcommand)]
+enum EmbedderAction {
+    /// Keep the embedding model loaded so other commands skip loading it
+    Serve {
+        /// Address to listen on (default: `knowledge.embedder_daemon`, else 127.0.0.1:7391)
+        #[arg(long)]
+        addr: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum KgAction {
+    /// Show knowledge graph statistics
+    Status {
+        /// Also report vector index size, process memory and search latency percentiles
+        #[arg(long)]
+        perf: bool,
+    },
+    /// Clear the knowledge graph database, or only the files matching `--path` or `--language`
+    Clear {
+        /// Only remove files matching this pattern (e.g. `src/legacy/**`, `*.py`); repeatable
+        #[arg(long, value_name = "GLOB")]
+        path: Vec<String>,
+        /// Only remove files in this language (e.g. `python`); repeatable
+        #[arg(long)]
+        language: Vec<String>,
+    },
+    /// Check node and edge integrity
+    Verify {
+        /// Prune dangling records that fail verification
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Rank functions by centrality in the calls graph (hotspots, god-functions)
+    Top {
+        /// Metric to rank by: in-degree, out-degree or betweenness
+        #[arg(long, default_value = "in-degree")]
+        metric: CentralityMetric,
+        /// Maximum number of functions to show
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
+    /// Find likely copy-paste duplication: chunks in different files with
+    /// near-identical embeddings
+    Dupes {
+        /// Minimum cosine similarity for two chunks to count as duplicates
+        #[arg(long, default_value = "0.95")]
+        threshold: f32,
+        /// Skip chunks shorter than this many lines
+        #[arg(long, default_value = "5")]
+        min_lines: u32,
+    },
+    /// Mine git history for files that frequently change together and store
+    /// them as weighted co-change edges
+    MineHistory {
+        /// Most recent commits to read
+        #[arg(long, default_value_t = cochange::DEFAULT_MAX_COMMITS)]
+        max_commits: usize,
+        /// Commits two files must share to count as co-changing
+        #[arg(long, default_value_t = cochange::DEFAULT_MIN_COUNT)]
+        min_count: u32,
+        /// Skip commits touching more files than this (bulk renames, reformatting)
+        #[arg(long, default_value_t = cochange::DEFAULT_MAX_FILES_PER_COMMIT)]
+        max_files: usize,
+    },
+    /// Summarize indexed files and then each directory with the LLM, so
+    /// research on large repositories starts from a map of the project
+    Summarize,
+    /// Archive the knowledge graph database, e.g. before risky operations
+    /// or to share an index with teammates
+    Backup {
+        /// Archive to write, compressed by suffix (e.g. `kg.tar.zst`, `kg.tar.gz`)
+        #[arg(value_name = "FILE")]
+        archive: std::path::PathBuf,
+    },
+    /// Replace the knowledge graph database with a backup
+    Restore {
+        /// Archive written by `arq kg backup`
+        #[arg(value_name = "FILE")]
+        archive: std::path::PathBuf,
+        /// Replace an existing knowledge graph
+        #[arg(long)]
+        force: bool,
+    },
+    /// Export entities and edges for querying with other tools
+    Export {
+        /// Export format
+        #[arg(long, default_value = "sqlite")]
+        format: ExportFormat,
+        /// File to write (default: arq-graph.<format> in the current directory)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DocsAction {
+    /// Write a site with a page per module: entities, signatures, doc
+    /// comments, call graphs and links between callers and callees
+    Generate {
+        /// Page format (markdown or html)
+        #[arg(long, default_value = "markdown")]
+        format: DocsFormat,
+        /// Directory to write the site to
+        #[arg(short, long, value_name = "DIR", default_value = "arq-docs")]
+        output: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the resolved configuration, merged from user, repository and
+    /// package config files
+    Show {
+        /// Annotate each value with the file, profile or environment
+        /// variable that set it
+        #[arg(long)]
+        origin: bool,
+    },
+    /// Store a provider's API key in the OS keyring instead of arq.toml
+    SetKey {
+        /// Provider the key is for (default: the configured provider)
+        provider: Option<String>,
+    },
+    /// Remove a provider's API key from the OS keyring
+    DeleteKey {
+        /// Provider the key is for (default: the configured provider)
+        provider: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum LabelAction {
+    /// Add labels to a task
+    Add {
+        /// Task ID (or prefix) or part of its name
+        id: String,
+        /// Labels to add
+        #[arg(required = true)]
+        labels: Vec<String>,
+    },
+    /// Remove labels from a task
+    Remove {
+        /// Task ID (or prefix) or part of its name
+        id: String,
+        /// Labels to remove
+        #[arg(required = true)]
+        labels: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum LlmAction {
+    /// Send a tiny prompt to each provider and report reachability, key
+    /// validity, latency and streaming support
+    Ping {
+        /// Providers to ping, as `provider` or `provider:model`
+        /// (default: the configured provider)
+        #[arg(long, value_delimiter = ',', value_name = "PROVIDER")]
+        provider: Vec<String>,
+        /// Seconds to wait for each reply
+        #[arg(long, default_value = "30")]
+        timeout: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum ModelsAction {
+    /// List available models and pick which to add to `available_models`
+    List {
+        /// Only print the list, without prompting to save
+        #[arg(long)]
+        no_save: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlanAction {
+    /// Draft failing test stubs for each plan step, following the project's
+    /// test conventions
+    GenTests {
+        /// Directory to write the stubs to (default: .arq/test-stubs)
+        #[arg(short, long, value_name = "DIR")]
+        out: Option<std::path::PathBuf>,
+        /// Print the stubs instead of writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AgentAction {
+    /// Generate the plan's file edits, show each as a diff and apply the
+    /// confirmed ones, then complete the task
+    Run {
+        /// Apply every edit without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum GraphAction {
     /// Show what a function depends on (calls)
     Deps {
         /// Function name to look up
         name: String,
+        /// Only show calls resolved to a single definition
+        #[arg(long)]
+        resolved_only: bool,
+        /// Show the calls at this time instead (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_name = "TIME", value_parser = parse_as_of)]
+        as_of: Option<DateTime<Utc>>,
     },
     /// Show what depends on a function (callers / impact)
     Impact {
         /// Function name to look up
         name: String,
+        /// Only show callers from this workspace crate
+        #[arg(long = "crate", value_name = "NAME")]
+        crate_name: Option<String>,
+        /// Show the callers at this time instead (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_name = "TIME", value_parser = parse_as_of, conflicts_with = "crate_name")]
+        as_of: Option<DateTime<Utc>>,
+    },
+    /// List the calls crossing two files or directories, in either
+    /// direction, to judge their coupling before extracting a module
+    Calls {
+        /// The two files or directories (relative to the project root)
+        #[arg(long, num_args = 2, value_names = ["A", "B"], required = true)]
+        between: Vec<String>,
+        /// Print the calls as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// List all indexed functions
     Functions {
         /// Maximum number to show
         #[arg(short, long, default_value = "20")]
         limit: usize,
+        /// Only list functions from this workspace crate
+        #[arg(long = "crate", value_name = "NAME")]
+        crate_name: Option<String>,
+    },
+    /// Show the types implementing a trait or interface, and the traits or
+    /// interfaces a type implements (including implicit Go interfaces)
+    Impls {
+        /// Trait, interface or type name
+        name: String,
+    },
+    /// Show API operations and resolvers that consume or produce a schema type
+    Api {
+        /// Schema type name (e.g. a GraphQL type or input)
+        type_name: String,
+    },
+    /// List edges of any relation (contains, imports, extends, ...)
+    Edges {
+        /// Relation to list
+        #[arg(long = "type", value_name = "RELATION", value_parser = clap::builder::PossibleValuesParser::new(EDGE_RELATIONS))]
+        relation: String,
+        /// Only edges leaving this entity ID (e.g. `file:src/main.rs`)
+        #[arg(long)]
+        from: Option<String>,
+        /// Maximum number to show
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+        /// Number of edges to skip
+        #[arg(long, default_value = "0")]
+        offset: usize,
+        /// List `calls` edges in effect at this time (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_name = "TIME", value_parser = parse_as_of)]
+        as_of: Option<DateTime<Utc>>,
+    },
+    /// Show the innermost entity containing a location, for editor integrations
+    At {
+        /// Location as `path:line` (path relative to the project root)
+        #[arg(value_parser = parse_location)]
+        location: (String, u32),
+        /// Print the entity as JSON (`null` when there is none)
+        #[arg(long)]
+        json: bool,
     },
 }
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
+    let cli = Cli::parse();
+    let events = cli.events.map(EventWriter::new);
+    if let Err(e) = run(cli).await {
+        if let Some(events) = events {
+            events.emit(&ProgressEvent::Error {
+                message: e.to_string(),
+            });
+        }
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+        if let Some(hint) = e.hint() {
+            eprintln!("Hint: {}", hint);
+        }
+        std::process::exit(e.exit_code());
     }
 }
 
-async fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    let config = Config::load().unwrap_or_default();
+async fn run(cli: Cli) -> Result<(), ArqError> {
+    let events = cli.events.map(EventWriter::new);
+    let mut config = load_config(cli.profile.as_deref())?;
+    if cli.no_llm_cache {
+        config.llm.cache = false;
+    }
     let storage = FileStorage::with_config(config.storage.clone());
     let mut manager = TaskManager::new(storage);
 
     match cli.command {
-        Commands::New { prompt } => {
+        Commands::New {
+            prompt,
+            name,
+            suggest_name,
+        } => {
             let prompt_str = prompt.join(" ");
-            let task = manager.create_task(&prompt_str)?;
+            // The suggestion waits on the LLM, so it only runs when asked for
+            let name = match name {
+                Some(name) => Some(name),
+                None if suggest_name => {
+                    match Provider::build_cached(&config.llm, &config.storage) {
+                        Ok(llm) => suggest_task_name(llm.as_ref(), &prompt_str).await,
+                        Err(_) => None,
+                    }
+                }
+                None => None,
+            };
+            let task = match name {
+                Some(name) => manager.create_named_task(&prompt_str, &name)?,
+                None => manager.create_task(&prompt_str)?,
+            };
             println!("Created new task: {}", task.name);
             println!("  ID: {}", task.id);
             println!("  Phase: {}", task.phase.display_name());
@@ -174,128 +723,329 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
-        Commands::List => {
-            let tasks = manager.list_tasks()?;
+        Commands::List { label } => {
+            let tasks = match label {
+                Some(ref label) => manager.list_tasks_with_label(label)?,
+                None => manager.list_tasks()?,
+            };
             if tasks.is_empty() {
-                println!("No tasks found. Use 'arq new <prompt>' to create one.");
+                match label {
+                    Some(label) => println!("No tasks labeled '{}'.", label),
+                    None => println!("No tasks found. Use 'arq new <prompt>' to create one."),
+                }
             } else {
                 println!("Tasks:\n");
                 for task in tasks {
+                    let labels = match task.labels.is_empty() {
+                        true => String::new(),
+                        false => format!(" [{}]", task.labels.join(", ")),
+                    };
                     println!(
-                        "  {} - {} ({})",
+                        "  {} - {} ({}){}",
                         &task.id[..8],
                         task.name,
-                        task.phase.display_name()
+                        task.phase.display_name(),
+                        labels
                     );
                 }
             }
         }
-        Commands::Delete { id } => {
-            let tasks = manager.list_tasks()?;
-            let matching: Vec<_> = tasks.iter().filter(|t| t.id.starts_with(&id)).collect();
-
-            match matching.len() {
-                0 => {
-                    println!("No task found with ID starting with '{}'", id);
-                }
-                1 => {
-                    let task_id = &matching[0].id;
-                    manager.delete_task(task_id)?;
-                    println!("Deleted task: {}", matching[0].name);
+        Commands::Import => {
+            let storage = FileStorage::with_config(config.storage.clone());
+            let imported = storage.import_mirrored_tasks()?;
+            if imported.is_empty() {
+                println!(
+                    "No new or updated tasks in {}",
+                    config.storage.mirror_tasks_path().display()
+                );
+            } else {
+                println!("Imported {} task(s):", imported.len());
+                for id in &imported {
+                    let task = manager.get_task(id)?;
+                    println!("  {} - {}", &task.id[..8.min(task.id.len())], task.name);
                 }
-                _ => {
-                    println!("Multiple tasks match '{}'. Be more specific:", id);
-                    for task in matching {
-                        println!("  {} - {}", &task.id[..8], task.name);
-                    }
+            }
+        }
+        Commands::Delete { id, yes } => {
+            if let Some(task) = resolve_task(&manager, &id)? {
+                // A name can match a task other than the one meant
+                if yes || task.id.starts_with(id.trim()) || confirm_delete(&task) {
+                    manager.delete_task(&task.id)?;
+                    println!("Deleted task: {}", task.name);
+                } else {
+                    println!("Not deleted. Pass the task ID, or --yes to delete it anyway.");
                 }
             }
         }
         Commands::Switch { id } => {
-            let tasks = manager.list_tasks()?;
-            let matching: Vec<_> = tasks.iter().filter(|t| t.id.starts_with(&id)).collect();
-
-            match matching.len() {
-                0 => {
-                    println!("No task found with ID starting with '{}'", id);
-                }
-                1 => {
-                    let task_id = &matching[0].id;
-                    manager.set_current_task(task_id)?;
-                    println!("Switched to task: {}", matching[0].name);
-                }
-                _ => {
-                    println!("Multiple tasks match '{}'. Be more specific:", id);
-                    for task in matching {
-                        println!("  {} - {}", &task.id[..8], task.name);
-                    }
+            if let Some(task) = resolve_task(&manager, &id)? {
+                manager.set_current_task(&task.id)?;
+                println!("Switched to task: {}", task.name);
+            }
+        }
+        Commands::Rename { id, name } => {
+            if let Some(task) = resolve_task(&manager, &id)? {
+                let renamed = manager.rename_task(&task.id, &name.join(" "))?;
+                println!("Renamed task: {} -> {}", task.name, renamed.name);
+            }
+        }
+        Commands::Label { action } => {
+            let (id, labels, add) = match action {
+                LabelAction::Add { id, labels } => (id, labels, true),
+                LabelAction::Remove { id, labels } => (id, labels, false),
+            };
+            if let Some(task) = resolve_task(&manager, &id)? {
+                let updated = match add {
+                    true => manager.add_labels(&task.id, &labels)?,
+                    false => manager.remove_labels(&task.id, &labels)?,
+                };
+                match updated.labels.is_empty() {
+                    true => println!("Task {} has no labels", updated.name),
+                    false => println!(
+                        "Task {} labels: {}",
+                        updated.name,
+                        updated.labels.join(", ")
+                    ),
                 }
             }
         }
-        Commands::Research => {
+        Commands::Research {
+            compare,
+            offline,
+            no_cache,
+            review_context,
+            in_memory,
+        } => {
             let task = manager
                 .get_current_task()?
-                .ok_or("No current task. Use 'arq new <prompt>' first.")?;
+                .ok_or_else(|| ArqError::usage(NO_CURRENT_TASK))?;
+
+            if !compare.is_empty() {
+                return compare_research(&config, &task, &compare).await;
+            }
 
             if task.phase != Phase::Research {
-                return Err(format!(
+                return Err(ArqError::usage(format!(
                     "Task is in {} phase, not Research phase.",
                     task.phase.display_name()
-                )
-                .into());
+                )));
             }
 
             if task.research_doc.is_some() {
-                println!("Research already complete for this task.");
-                println!("Run 'arq advance' to move to Planning phase.");
+                say!(events, "Research already complete for this task.");
+                say!(events, "Run 'arq advance' to move to Planning phase.");
                 return Ok(());
             }
 
-            println!("Starting research for: {}", task.prompt);
-            println!();
+            let hooks = Hooks::new(config.hooks.clone());
+            hooks.run(HookEvent::PreResearch, &task, &[]).await?;
 
-            // Create LLM client from config
-            let llm = Provider::from_config(&config.llm).build().map_err(|e| {
-                format!(
-                    "{}. Configure [llm] in arq.toml or set OPENAI_API_KEY or ANTHROPIC_API_KEY.",
-                    e
-                )
-            })?;
+            if offline {
+                return offline_research_command(&config, &mut manager, &hooks, &task).await;
+            }
+            if let Some(events) = events {
+                events.emit(&ProgressEvent::Started {
+                    command: "research".to_string(),
+                });
+            }
+
+            say!(events, "Starting research for: {}", task.prompt);
+            say!(events);
+
+            // Create LLM client from config, with the researcher persona
+            let persona = prompts::for_phase(Phase::Research, &config);
+            let llm = Provider::build_cached(&persona.llm_config(&config.llm), &config.storage)?;
 
             // Create context builder with config
             let context_builder = ContextBuilder::with_config(".", config.context.clone());
 
             // Check if knowledge graph is available
             let db_path = config.knowledge.db_full_path(&config.storage);
-            let runner = if db_path.exists() {
-                println!("Using knowledge graph for smart context...");
-                let kg = KnowledgeGraph::open(&db_path).await?;
+            let runner = if in_memory {
+                let (kg, stats) = index_in_memory(&config, events).await?;
+                say!(
+                    events,
+                    "Indexed {} files into memory for smart context...",
+                    stats.files
+                );
+                let context_builder = prioritize_by_centrality(context_builder, &kg, &config).await;
+                ResearchRunner::with_knowledge_store(llm, context_builder, std::sync::Arc::new(kg))
+            } else if db_path.exists() {
+                say!(events, "Using knowledge graph for smart context...");
+                let kg = KnowledgeGraph::open(&db_path)
+                    .await?
+                    .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+                    .with_model_cache(config.storage.model_cache_path());
+                let context_builder = prioritize_by_centrality(context_builder, &kg, &config).await;
                 ResearchRunner::with_knowledge_store(llm, context_builder, std::sync::Arc::new(kg))
             } else {
-                println!("Scanning codebase (run 'arq init' for faster semantic search)...");
+                say!(
+                    events,
+                    "Scanning codebase (run 'arq init' for faster semantic search)..."
+                );
                 ResearchRunner::new(llm, context_builder)
+            }
+            .with_content_guard(config.research.content_guard)
+            .with_rerank(config.research.rerank)
+            .with_self_critique(config.research.self_critique)
+            .with_correct_references(config.research.correct_references)
+            .with_output_language(config.research.output_language.clone())
+            .with_cost_limit(CostLimit::from_config(&config))
+            .with_include_tests(config.context.include_tests)
+            .with_private_paths(PathBlocklist::new(config.context.private_paths.clone()))
+            .with_system_prompt(persona.system_prompt);
+
+            let mut excluded = task.excluded_context.clone();
+            if review_context {
+                let candidates = runner.context_candidates(&task).await?;
+                excluded = review_context_files(&candidates, excluded)?;
+                manager.set_excluded_context(&task.id, excluded.clone())?;
+            }
+            let runner = runner.with_excluded_files(excluded).with_sent_context(
+                SentContextLog::new(config.storage.sent_context_path())
+                    .with_encryption(PrivateFiles::from_config(&config.storage)),
+            );
+            let runner = if no_cache {
+                runner
+            } else {
+                runner.with_context_cache(
+                    ContextCache::new(config.storage.context_cache_path())
+                        .with_encryption(PrivateFiles::from_config(&config.storage)),
+                )
             };
 
-            // Run research
-            let doc = runner.run(&task).await?;
+            // Run research, collecting token usage from progress events
+            let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+            let progress = tokio::spawn(async move {
+                let mut tokens = TokenUsage::default();
+                while let Some(progress) = progress_rx.recv().await {
+                    if let Some(events) = events {
+                        events.emit(&ProgressEvent::from(&progress));
+                    }
+                    match progress {
+                        ResearchProgress::TokenUsage(usage) => tokens += usage,
+                        ResearchProgress::PromptStats(stats) => {
+                            say!(events, "Prompt: {}", stats.summary(PROMPT_STATS_TOP_FILES))
+                        }
+                        ResearchProgress::ConfirmCost(confirmation) => {
+                            let estimate = confirmation.estimate.clone();
+                            let confirmed =
+                                tokio::task::spawn_blocking(move || confirm_cost(&estimate))
+                                    .await
+                                    .unwrap_or(false);
+                            confirmation.answer(confirmed);
+                        }
+                        ResearchProgress::ContextFromCache { count } => say!(
+                            events,
+                            "Reused cached context ({} sources; --no-cache to rebuild)",
+                            count
+                        ),
+                        ResearchProgress::PrivateContextWithheld { files } => say!(
+                            events,
+                            "Withheld {} private file(s) from the prompt (context.private_paths): {}",
+                            files.len(),
+                            files.join(", ")
+                        ),
+                        _ => {}
+                    }
+                }
+                tokens
+            });
+
+            let started = Instant::now();
+            let doc = match events {
+                // Stream the response so partial tokens reach the events
+                Some(events) => {
+                    let (stream_tx, mut stream_rx) =
+                        tokio::sync::mpsc::unbounded_channel::<StreamChunk>();
+                    let forward = tokio::spawn(async move {
+                        while let Some(chunk) = stream_rx.recv().await {
+                            if !chunk.text.is_empty() {
+                                events.emit(&ProgressEvent::Token { text: chunk.text });
+                            }
+                        }
+                    });
+                    let doc = runner.run_streaming(&task, progress_tx, stream_tx).await;
+                    let _ = forward.await;
+                    doc
+                }
+                None => runner.run_with_progress(&task, progress_tx).await,
+            };
+            let elapsed = started.elapsed();
+            let tokens = progress.await.unwrap_or_default();
+            let doc = doc?;
 
-            println!("Research complete!\n");
-            println!("## Summary\n");
-            println!("{}\n", doc.summary);
-            println!("## Suggested Approach\n");
-            println!("{}\n", doc.suggested_approach);
+            say!(events, "Research complete!\n");
+            say!(events, "## Summary\n");
+            say!(events, "{}\n", doc.summary);
+            say!(events, "## Suggested Approach\n");
+            say!(events, "{}\n", doc.suggested_approach);
+            if !doc.ungrounded.is_empty() {
+                say!(
+                    events,
+                    "Warning: {} cited file reference(s) don't match the codebase:",
+                    doc.ungrounded.len()
+                );
+                for issue in &doc.ungrounded {
+                    match issue.corrected_to {
+                        Some(ref corrected) => say!(
+                            events,
+                            "  {} ({}), replaced with {}",
+                            issue.reference,
+                            issue.problem.describe(),
+                            corrected
+                        ),
+                        None => say!(
+                            events,
+                            "  {} ({})",
+                            issue.reference,
+                            issue.problem.describe()
+                        ),
+                    }
+                }
+                say!(events);
+            }
 
-            // Save research doc
+            // Save research doc and its usage
             manager.set_research_doc(&task.id, doc)?;
+            let task = manager.record_usage(
+                &task.id,
+                UsageRecord::new(
+                    Phase::Research,
+                    &config.llm.provider,
+                    config.llm.model_or_default(),
+                    tokens,
+                    elapsed,
+                ),
+            )?;
 
             let research_path = config.storage.local_research_path();
-            println!("Research saved to {}", research_path.display());
-            println!("\nNext: Run 'arq advance' to move to Planning phase.");
+            say!(events, "Research saved to {}", research_path.display());
+            run_post_hook(&hooks, HookEvent::PostResearch, &task, &research_path).await;
+            say!(
+                events,
+                "\nNext: Run 'arq advance' to move to Planning phase."
+            );
+            if let Some(events) = events {
+                events.emit(&ProgressEvent::Complete {
+                    counts: Default::default(),
+                    output: Some(research_path.display().to_string()),
+                });
+            }
+        }
+        Commands::Stats => {
+            let tasks = manager.load_all_tasks()?;
+            if tasks.is_empty() {
+                println!("No tasks yet. Use 'arq new <prompt>' to create one.");
+                return Ok(());
+            }
+
+            print_task_stats(&TaskStats::from_tasks(&tasks));
         }
         Commands::Advance => {
             let task = manager
                 .get_current_task()?
-                .ok_or("No current task. Use 'arq new <prompt>' first.")?;
+                .ok_or_else(|| ArqError::usage(NO_CURRENT_TASK))?;
 
             if !task.can_advance() {
                 let hint = match task.phase {
@@ -304,167 +1054,625 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                     Phase::Agent => "Complete implementation first.",
                     Phase::Complete => "Task is already complete.",
                 };
-                return Err(format!(
+                return Err(ArqError::usage(format!(
                     "Cannot advance from {} phase. {}",
                     task.phase.display_name(),
                     hint
-                )
-                .into());
+                )));
+            }
+
+            // Leaving Research starts planning; leaving Planning approves the plan
+            let hooks = Hooks::new(config.hooks.clone());
+            if task.phase == Phase::Research {
+                hooks.run(HookEvent::PrePlan, &task, &[]).await?;
             }
 
             let new_phase = manager.advance_phase(&task.id)?;
             println!("Advanced to {} phase.", new_phase.display_name());
+
+            if task.phase == Phase::Planning {
+                let task = manager.get_task(&task.id)?;
+                let plan_path = config.storage.local_plan_path();
+                run_post_hook(&hooks, HookEvent::PostPlan, &task, &plan_path).await;
+            }
         }
-        Commands::Init { force } => {
-            let db_path = config.knowledge.db_full_path(&config.storage);
-            let project_dir = config.storage.project_dir();
+        Commands::Check { staged, no_llm } => {
+            let task = manager
+                .get_current_task()?
+                .ok_or_else(|| ArqError::usage(NO_CURRENT_TASK))?;
+            let plan = task
+                .plan
+                .as_ref()
+                .ok_or_else(|| ArqError::usage("Current task has no plan to check against."))?;
 
-            // Create project directory if it doesn't exist
-            std::fs::create_dir_all(&project_dir)?;
+            // Before the first commit there is no HEAD; everything is new
+            let has_head = std::process::Command::new("git")
+                .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+                .output()?
+                .status
+                .success();
+            let base = if has_head { "HEAD" } else { EMPTY_TREE };
+            let mut args = vec!["diff"];
+            if staged {
+                args.extend(["--cached", base]);
+            } else {
+                args.push(base);
+            }
+            let output = std::process::Command::new("git").args(&args).output()?;
+            if !output.status.success() {
+                return Err(ArqError::other(format!(
+                    "git diff failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
 
-            // Check if already initialized
-            if db_path.exists() && !force {
-                println!("Knowledge graph already initialized.");
-                println!("Use --force to re-index.");
+            let mut changes = parse_unified_diff(&String::from_utf8_lossy(&output.stdout));
+            if !staged {
+                // New files count once they exist, staged or not. Paths are
+                // relative to the repository root, like those of `git diff`
+                let root = std::process::Command::new("git")
+                    .args(["rev-parse", "--show-toplevel"])
+                    .output()?;
+                if !root.status.success() {
+                    return Err(ArqError::other(format!(
+                        "git rev-parse failed: {}",
+                        String::from_utf8_lossy(&root.stderr).trim()
+                    )));
+                }
+                let root = std::path::PathBuf::from(String::from_utf8_lossy(&root.stdout).trim());
+                let output = std::process::Command::new("git")
+                    .args([
+                        "ls-files",
+                        "--others",
+                        "--exclude-standard",
+                        "--full-name",
+                        "-z",
+                    ])
+                    .current_dir(&root)
+                    .output()?;
+                if !output.status.success() {
+                    return Err(ArqError::other(format!(
+                        "git ls-files failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                let untracked: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                    .split('\0')
+                    .filter(|path| !path.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                changes.extend(untracked_changes(&root, &untracked));
+            }
+            if changes.is_empty() {
+                println!("No changes to check.");
                 return Ok(());
             }
 
-            // Remove existing database if force re-indexing
-            if force && db_path.exists() {
-                let pb = ProgressBar::new_spinner();
-                pb.set_style(
-                    ProgressStyle::default_spinner()
-                        .template("{spinner:.cyan} {msg}")
-                        .unwrap(),
-                );
-                pb.set_message("Clearing existing knowledge graph...");
-                std::fs::remove_dir_all(&db_path)?;
-                pb.finish_with_message("Done");
-            }
+            let report = if no_llm {
+                check_scope(plan, &changes)
+            } else {
+                let persona = prompts::for_phase(Phase::Agent, &config);
+                let llm =
+                    Provider::build_cached(&persona.llm_config(&config.llm), &config.storage)?;
+                let db_path = config.knowledge.db_full_path(&config.storage);
+                let checker = if db_path.exists() {
+                    let kg = KnowledgeGraph::open(&db_path).await?;
+                    ConformanceChecker::with_knowledge_store(llm, std::sync::Arc::new(kg))
+                } else {
+                    ConformanceChecker::new(llm)
+                }
+                .with_system_prompt(persona.system_prompt)
+                .with_output_language(config.research.output_language.clone());
+                checker.check(plan, &changes).await?
+            };
 
-            // Step 1: Load embedding model (spinner - can't show progress for model loading)
-            let spinner = ProgressBar::new_spinner();
-            spinner.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.cyan} {msg}")
-                    .unwrap(),
-            );
-            spinner.enable_steady_tick(std::time::Duration::from_millis(100));
-            spinner.set_message("Loading embedding model (first run downloads ~50MB)...");
+            print_conformance_report(&report, changes.len());
 
-            let kg = KnowledgeGraph::open(&db_path).await?;
-            kg.initialize().await?;
-            spinner.finish_with_message("Embedding model loaded");
-
-            // Step 2: Count files to index
-            let spinner = ProgressBar::new_spinner();
-            spinner.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.cyan} {msg}")
-                    .unwrap(),
-            );
-            spinner.enable_steady_tick(std::time::Duration::from_millis(100));
-            spinner.set_message("Scanning codebase...");
-
-            let total_files = kg.count_indexable_files(Path::new("."));
-            spinner.finish_with_message(format!("Found {} files to index", total_files));
-
-            // Step 3: Index codebase with progress bar
-            let pb = ProgressBar::new(total_files as u64);
-            pb.set_style(
-                ProgressStyle::default_bar()
-                    .template("{spinner:.cyan} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-                    .unwrap()
-                    .progress_chars("=> "),
-            );
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            if !report.is_committable() {
+                return Err(ArqError::other("Changes do not conform to the plan."));
+            }
+        }
+        Commands::Plan {
+            action: PlanAction::GenTests { out, dry_run },
+        } => {
+            let task = manager
+                .get_current_task()?
+                .ok_or_else(|| ArqError::usage(NO_CURRENT_TASK))?;
+            let plan = task
+                .plan
+                .as_ref()
+                .ok_or_else(|| ArqError::usage("Current task has no plan to draft tests from."))?;
 
-            let stats: IndexStats = kg
-                .index_directory_with_progress(Path::new("."), |progress: IndexProgress| {
-                    pb.set_position(progress.files_done as u64);
-                    // Show just the filename, not full path
-                    let filename = progress
-                        .current_file
-                        .rsplit('/')
-                        .next()
-                        .unwrap_or(&progress.current_file);
-                    pb.set_message(filename.to_string());
-                })
-                .await?;
-            pb.finish_with_message("Complete");
+            // Existing test layout comes from the knowledge graph when indexed
+            let db_path = config.knowledge.db_full_path(&config.storage);
+            let indexed_files = if db_path.exists() {
+                KnowledgeGraph::open(&db_path)
+                    .await?
+                    .list_indexed_files()
+                    .await?
+            } else {
+                Vec::new()
+            };
+            let conventions = TestConventions::detect(Path::new("."), &indexed_files);
+            let stubs = scaffold_tests(plan, &conventions);
+
+            if stubs.is_empty() {
+                println!("No plan steps with testable source files.");
+                return Ok(());
+            }
 
-            println!("\nKnowledge graph initialized!");
-            println!("  Files indexed: {}", stats.files);
-            println!("  Code chunks: {}", stats.chunks);
-            println!("  Total size: {} KB", stats.total_size / 1024);
-            println!("\nDatabase: {}", db_path.display());
+            let out = out.unwrap_or_else(|| config.storage.test_stubs_path());
+            if let Some(stub) = stubs.iter().find(|s| !s.has_safe_path()) {
+                return Err(ArqError::other(format!(
+                    "Plan step '{}' maps to a test path outside {}: {}",
+                    stub.source,
+                    out.display(),
+                    stub.path
+                )));
+            }
+            for stub in &stubs {
+                if dry_run {
+                    println!("==> {} ({})\n", stub.path, stub.framework.as_str());
+                    println!("{}", stub.code);
+                } else {
+                    let path = out.join(&stub.path);
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&path, &stub.code)?;
+                    println!("  {} ({})", path.display(), stub.framework.as_str());
+                }
+            }
+            if !dry_run {
+                println!(
+                    "\nDrafted {} test files under {}; move them into place once reviewed.",
+                    stubs.len(),
+                    out.display()
+                );
+            }
         }
-        Commands::Search { query, limit } => {
+        Commands::Agent {
+            action: AgentAction::Run { yes },
+        } => {
+            run_agent(&config, &mut manager, yes).await?;
+        }
+        Commands::Init {
+            force,
+            retry_failed,
+            in_memory,
+            docs,
+            jobs,
+        } => {
             let db_path = config.knowledge.db_full_path(&config.storage);
-
-            if !db_path.exists() {
-                return Err("Knowledge graph not initialized. Run 'arq init' first.".into());
+            if let Some(jobs) = jobs {
+                config.knowledge.index_jobs = jobs;
+            }
+            for dir in &docs {
+                if !config.knowledge.docs.contains(dir) {
+                    config.knowledge.docs.push(dir.clone());
+                }
             }
 
-            let kg = KnowledgeGraph::open(&db_path).await?;
+            if in_memory {
+                if let Some(events) = events {
+                    events.emit(&ProgressEvent::Started {
+                        command: "init".to_string(),
+                    });
+                }
+                let (_, stats) = index_in_memory(&config, events).await?;
+                say!(events, "\nIndexed into memory, nothing was written.");
+                say!(events, "  Files indexed: {}", stats.files);
+                if stats.documents > 0 {
+                    say!(events, "  Documents indexed: {}", stats.documents);
+                }
+                say!(events, "  Code chunks: {}", stats.chunks);
+                say!(events, "  Total size: {} KB", stats.total_size / 1024);
+                report_throughput(events, &stats.timings);
+                report_index_result(events, &stats);
+                return Ok(());
+            }
+            let report_path = config.storage.index_report_path();
+
+            // Create project directory if it doesn't exist
+            config.storage.create_project_dir()?;
+
+            if retry_failed {
+                if !db_path.exists() {
+                    return Err(KnowledgeError::NotInitialized.into());
+                }
+                let report = IndexReport::load(&report_path)?;
+                if report.is_empty() {
+                    say!(events, "No failed files to retry.");
+                    return Ok(());
+                }
+
+                let kg = KnowledgeGraph::open(&db_path)
+                    .await?
+                    .with_insert_batch_size(config.knowledge.insert_batch_size)
+                    .with_index_jobs(config.knowledge.index_jobs)
+                    .with_history_retention(config.knowledge.history_retention_days)
+                    .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+                    .with_model_cache(config.storage.model_cache_path())
+                    .with_generated_code(GeneratedCodePolicy::from_config(&config.knowledge))
+                    .with_size_limit(
+                        config.knowledge.max_file_size,
+                        config.knowledge.oversized_files,
+                    )
+                    .with_docs(config.knowledge.docs.clone())
+                    .with_parsers(&config.parsers)
+                    .with_languages(
+                        config.knowledge.disabled_languages.clone(),
+                        config.knowledge.extension_overrides.clone(),
+                    );
+
+                if let Some(events) = events {
+                    events.emit(&ProgressEvent::Started {
+                        command: "init".to_string(),
+                    });
+                    events.emit(&ProgressEvent::phase("retrying_failed"));
+                }
+
+                let pb = ProgressBar::new(report.failures.len() as u64);
+                pb.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{spinner:.cyan} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                        .unwrap()
+                        .progress_chars("=> "),
+                );
+                pb.enable_steady_tick(std::time::Duration::from_millis(100));
+
+                let stats = kg
+                    .retry_failed(Path::new("."), &report, |progress: IndexProgress| {
+                        pb.set_position(progress.files_done as u64);
+                        pb.set_message(progress.current_file.clone());
+                        if let Some(events) = events {
+                            events.emit(&progress.into());
+                        }
+                    })
+                    .await?;
+                pb.finish_with_message("Complete");
+                stats.report.save(&report_path)?;
+
+                say!(
+                    events,
+                    "\nRetried {} files: {} indexed",
+                    report.failures.len(),
+                    stats.files
+                );
+                report_throughput(events, &stats.timings);
+                report_index_result(events, &stats);
+                return Ok(());
+            }
+
+            // Add documentation to an existing graph without re-indexing the code
+            if db_path.exists() && !force && !docs.is_empty() {
+                if let Some(events) = events {
+                    events.emit(&ProgressEvent::Started {
+                        command: "init".to_string(),
+                    });
+                }
+                let kg = KnowledgeGraph::open(&db_path)
+                    .await?
+                    .with_insert_batch_size(config.knowledge.insert_batch_size)
+                    .with_index_jobs(config.knowledge.index_jobs)
+                    .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+                    .with_model_cache(config.storage.model_cache_path())
+                    .with_docs(config.knowledge.docs.clone());
+                let stats = index_documents(&kg, events).await?;
+
+                say!(events, "\nDocumentation indexed!");
+                say!(events, "  Documents indexed: {}", stats.documents);
+                say!(events, "  Chunks: {}", stats.chunks);
+                say!(events, "\nDatabase: {}", db_path.display());
+                report_throughput(events, &stats.timings);
+                report_index_result(events, &stats);
+                return Ok(());
+            }
+
+            // Check if already initialized
+            if db_path.exists() && !force {
+                say!(events, "Knowledge graph already initialized.");
+                say!(events, "Use --force to re-index.");
+                return Ok(());
+            }
+
+            // Remove existing database if force re-indexing
+            if force && db_path.exists() {
+                let pb = ProgressBar::new_spinner();
+                pb.set_style(
+                    ProgressStyle::default_spinner()
+                        .template("{spinner:.cyan} {msg}")
+                        .unwrap(),
+                );
+                pb.set_message("Clearing existing knowledge graph...");
+                std::fs::remove_dir_all(&db_path)?;
+                pb.finish_with_message("Done");
+            }
+
+            if let Some(events) = events {
+                events.emit(&ProgressEvent::Started {
+                    command: "init".to_string(),
+                });
+            }
+
+            let kg = KnowledgeGraph::open(&db_path)
+                .await?
+                .with_insert_batch_size(config.knowledge.insert_batch_size)
+                .with_index_jobs(config.knowledge.index_jobs)
+                .with_history_retention(config.knowledge.history_retention_days)
+                .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+                .with_model_cache(config.storage.model_cache_path())
+                .with_generated_code(GeneratedCodePolicy::from_config(&config.knowledge))
+                .with_size_limit(
+                    config.knowledge.max_file_size,
+                    config.knowledge.oversized_files,
+                )
+                .with_docs(config.knowledge.docs.clone())
+                .with_parsers(&config.parsers)
+                .with_languages(
+                    config.knowledge.disabled_languages.clone(),
+                    config.knowledge.extension_overrides.clone(),
+                );
+            let stats = index_project(&kg, events).await?;
+            stats.report.save(&report_path)?;
+
+            say!(events, "\nKnowledge graph initialized!");
+            say!(events, "  Files indexed: {}", stats.files);
+            if stats.documents > 0 {
+                say!(events, "  Documents indexed: {}", stats.documents);
+            }
+            if stats.generated_skipped > 0 {
+                say!(
+                    events,
+                    "  Generated files skipped: {}",
+                    stats.generated_skipped
+                );
+            }
+            if stats.signatures_only > 0 {
+                say!(
+                    events,
+                    "  Oversized files, signatures only: {}",
+                    stats.signatures_only
+                );
+            }
+            if stats.oversized_skipped > 0 {
+                say!(
+                    events,
+                    "  Oversized files skipped: {}",
+                    stats.oversized_skipped
+                );
+            }
+            say!(events, "  Code chunks: {}", stats.chunks);
+            say!(events, "  Total size: {} KB", stats.total_size / 1024);
+            say!(events, "\nDatabase: {}", db_path.display());
+            report_throughput(events, &stats.timings);
+            report_index_result(events, &stats);
+        }
+        Commands::Search {
+            query,
+            limit,
+            crate_name,
+            returns,
+            param,
+            is_async,
+            visibility,
+            context,
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path)
+                .await?
+                .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+                .with_model_cache(config.storage.model_cache_path());
 
             let query_str = query.join(" ");
-            println!("Searching for: {}\n", query_str);
+            let filter = EntityFilter {
+                returns,
+                param_type: param,
+                is_async: is_async.then_some(true),
+                visibility,
+                crate_name: None,
+            };
 
-            let results: Vec<SearchResult> = kg.search_code(&query_str, limit).await?;
+            let mut results: Vec<SearchResult> = if filter.is_empty() {
+                println!("Searching for: {}\n", query_str);
+                match crate_name {
+                    Some(ref crate_name) => {
+                        kg.search_code_in_crate(&query_str, crate_name, limit)
+                            .await?
+                    }
+                    None => kg.search_code(&query_str, limit).await?,
+                }
+            } else {
+                let filter = EntityFilter {
+                    crate_name,
+                    ..filter
+                };
+                if query_str.is_empty() {
+                    println!("Searching functions by metadata\n");
+                } else {
+                    println!("Searching for: {}\n", query_str);
+                }
+                kg.search_code_filtered(&query_str, &filter, limit).await?
+            };
+            if let Some(lines) = context {
+                kg.add_search_context(&mut results, lines, Path::new("."))
+                    .await?;
+            }
 
             if results.is_empty() {
                 println!("No results found.");
             } else {
                 println!("Found {} results:\n", results.len());
                 for (i, result) in results.iter().enumerate() {
+                    let label = if result.entity_type == DOCUMENT_ENTITY_TYPE {
+                        "[doc] "
+                    } else {
+                        ""
+                    };
                     println!(
-                        "{}. {} (lines {}-{}) - score: {:.2}",
+                        "{}. {}{} (lines {}-{}) - score: {:.2}",
                         i + 1,
+                        label,
                         result.path,
                         result.start_line,
                         result.end_line,
                         result.score
                     );
-                    if let Some(ref preview) = result.preview {
-                        for line in preview.lines().take(3) {
-                            println!("   {}", line);
+                    match result.context {
+                        Some(ref context) => print_search_context(result, context),
+                        None => {
+                            if let Some(ref preview) = result.preview {
+                                for line in preview.lines().take(3) {
+                                    println!("   {}", line);
+                                }
+                            }
                         }
                     }
                     println!();
                 }
             }
         }
-        Commands::KgStatus => {
+        Commands::TestImpact {
+            mut files,
+            git_diff,
+            depth,
+            format,
+        } => {
             let db_path = config.knowledge.db_full_path(&config.storage);
 
             if !db_path.exists() {
-                println!("Knowledge graph not initialized.");
-                println!("Run 'arq init' to index your codebase.");
-                return Ok(());
+                return Err(KnowledgeError::NotInitialized.into());
             }
 
+            if let Some(base) = git_diff {
+                files.extend(test_impact::git_changed_files(Path::new("."), &base)?);
+            }
             let kg = KnowledgeGraph::open(&db_path).await?;
-            let stats = kg.get_extended_stats().await?;
+            let impact = kg.test_impact(&files, depth).await?;
 
-            println!("Knowledge Graph Status\n");
-            println!("  Files indexed: {}", stats.files);
-            println!("  Code chunks: {}", stats.chunks);
-            println!();
-            println!("  Rich Ontology:");
-            println!("    Functions: {}", stats.functions);
-            println!("    Structs: {}", stats.structs);
-            println!("    Traits: {}", stats.traits);
-            println!("    Impls: {}", stats.impls);
-            println!("    Enums: {}", stats.enums);
-            println!("    Constants: {}", stats.constants);
-            println!();
-            println!("  Relations:");
-            println!("    Calls: {}", stats.calls);
-            println!("    Implements: {}", stats.implements);
-            println!("\nDatabase path: {}", db_path.display());
+            match format {
+                TestImpactFormat::List => {
+                    println!(
+                        "{} changed files, {} functions; {} affected tests",
+                        impact.changed_files.len(),
+                        impact.changed_functions,
+                        impact.tests.len()
+                    );
+                    for test in &impact.tests {
+                        match test.reaches {
+                            Some(ref function) => println!(
+                                "  {}  {} ({} calls to {})",
+                                test.file_path, test.qualified_name, test.distance, function
+                            ),
+                            None => println!(
+                                "  {}  {} (in a changed file)",
+                                test.file_path, test.qualified_name
+                            ),
+                        }
+                    }
+                    for file in &impact.changed_test_files {
+                        if !impact.tests.iter().any(|t| &t.file_path == file) {
+                            println!("  {}  (changed test file)", file);
+                        }
+                    }
+                }
+                TestImpactFormat::Files => {
+                    for file in impact.test_files() {
+                        println!("{}", file);
+                    }
+                }
+                TestImpactFormat::Cargo => println!("{}", impact.cargo_filters()),
+                TestImpactFormat::Pytest => println!("{}", impact.pytest_expression()),
+                TestImpactFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&impact).map_err(std::io::Error::other)?
+                ),
+            }
+        }
+        Commands::Grep {
+            pattern,
+            semantic_expand,
+            limit,
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let hits = kg
+                .grep(&pattern, Path::new("."), semantic_expand, limit)
+                .await?;
+
+            if hits.is_empty() {
+                println!("No matches found.");
+            }
+            let mut last_entity = None;
+            for hit in &hits {
+                println!("{}:{}: {}", hit.path, hit.line, hit.text.trim_end());
+                let Some(ref entity) = hit.entity else {
+                    continue;
+                };
+                // Consecutive hits in one function share its details
+                if last_entity == Some(&entity.id) {
+                    continue;
+                }
+                last_entity = Some(&entity.id);
+                println!(
+                    "   in: {} (lines {}-{})",
+                    entity.signature.lines().next().unwrap_or_default(),
+                    entity.start_line,
+                    entity.end_line
+                );
+                if !entity.callers.is_empty() {
+                    println!("   called by: {}", entity.callers.join(", "));
+                }
+            }
+            if hits.len() == limit {
+                println!(
+                    "\n(showing the first {} matches; raise --limit for more)",
+                    limit
+                );
+            }
+        }
+        Commands::KgStatus => kg_status(&config, false).await?,
+        Commands::Kg {
+            action: KgAction::Status { perf },
+        } => kg_status(&config, perf).await?,
+        Commands::Kg {
+            action: KgAction::Clear { path, language },
+        } if !(path.is_empty() && language.is_empty()) => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                println!("Knowledge graph not initialized. Nothing to clear.");
+                return Ok(());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let removed = kg.clear_files(&path, &language).await?;
+            if removed.is_empty() {
+                println!("No indexed files matched.");
+            } else {
+                for file in &removed {
+                    println!("  removed {}", file);
+                }
+                println!(
+                    "\nCleared {} files from the knowledge graph.",
+                    removed.len()
+                );
+            }
         }
-        Commands::KgClear => {
+        Commands::KgClear
+        | Commands::Kg {
+            action: KgAction::Clear { .. },
+        } => {
             let db_path = config.knowledge.db_full_path(&config.storage);
 
             if !db_path.exists() {
@@ -476,17 +1684,302 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
             println!("Knowledge graph cleared.");
             println!("Run 'arq init' to re-index your codebase.");
         }
+        Commands::Kg {
+            action: KgAction::Verify { fix },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let report = kg.verify().await?;
+
+            println!("Knowledge Graph Verification\n");
+            print_verify_section("Dangling calls", &report.dangling_calls);
+            print_verify_section("Orphan contains edges", &report.orphan_contains);
+            print_verify_section("Chunks without files", &report.orphan_chunks);
+            print_verify_section(
+                "Embedding dimension mismatches",
+                &report.dimension_mismatches,
+            );
+
+            if report.is_clean() {
+                println!("\nNo issues found.");
+            } else if fix {
+                let removed = kg.prune(&report).await?;
+                println!("\nRemoved {} dangling records.", removed);
+                if !report.dimension_mismatches.is_empty() {
+                    println!("Run 'arq init --force' to re-embed affected files.");
+                }
+            } else {
+                return Err(KnowledgeError::Corrupted(format!(
+                    "{} consistency issues found",
+                    report.issue_count()
+                ))
+                .into());
+            }
+        }
+        Commands::Kg {
+            action: KgAction::Top { metric, limit },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let top = kg.top_centrality(metric, limit).await?;
+
+            println!("Top functions by {}\n", metric);
+            if top.is_empty() {
+                println!("  No call edges indexed.");
+            }
+            for (i, entry) in top.iter().enumerate() {
+                let score = match metric {
+                    CentralityMetric::Betweenness => format!("{:.1}", entry.score),
+                    _ => format!("{}", entry.score as usize),
+                };
+                println!("{:>3}. {:<40} {:>8}", i + 1, entry.name, score);
+                println!("     {}", entry.entity_id);
+            }
+        }
+        Commands::Kg {
+            action:
+                KgAction::Dupes {
+                    threshold,
+                    min_lines,
+                },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let clusters = kg.find_duplicates(threshold, min_lines).await?;
+
+            if clusters.is_empty() {
+                println!("No duplicates found (similarity >= {}).", threshold);
+                return Ok(());
+            }
+
+            println!(
+                "Found {} duplicate clusters (similarity >= {})\n",
+                clusters.len(),
+                threshold
+            );
+            for (i, cluster) in clusters.iter().enumerate() {
+                println!(
+                    "{:>3}. {} chunks, similarity {:.3}",
+                    i + 1,
+                    cluster.chunks.len(),
+                    cluster.similarity
+                );
+                for chunk in &cluster.chunks {
+                    match chunk.entity_id {
+                        Some(ref entity) => println!(
+                            "     {}:{}-{}  {}",
+                            chunk.path, chunk.start_line, chunk.end_line, entity
+                        ),
+                        None => println!(
+                            "     {}:{}-{}",
+                            chunk.path, chunk.start_line, chunk.end_line
+                        ),
+                    }
+                }
+            }
+        }
+        Commands::Kg {
+            action:
+                KgAction::MineHistory {
+                    max_commits,
+                    min_count,
+                    max_files,
+                },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let options = MineOptions {
+                max_commits,
+                min_count,
+                max_files_per_commit: max_files,
+            };
+            let report = kg.mine_history(Path::new("."), &options).await?;
+
+            println!(
+                "Read {} commits ({} skipped for touching more than {} files)",
+                report.commits, report.skipped_commits, max_files
+            );
+            println!(
+                "Stored {} co-change edges between files changed together in at least {} commits",
+                report.pairs, min_count
+            );
+            if report.pairs > 0 {
+                println!(
+                    "
+See them with 'arq graph impact <function>' or in 'arq serve'."
+                );
+            }
+        }
+        Commands::Kg {
+            action: KgAction::Summarize,
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let llm = Provider::build_cached(&config.llm, &config.storage)?;
+
+            let pb = ProgressBar::new_spinner();
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb.set_message("Summarizing files and directories...");
+            let progress = pb.clone();
+            let queue = JobQueue::from_config(&config.enrichment).with_progress(move |p| {
+                progress.inc(1);
+                progress.set_message(format!("{}/{} in this pass", p.done, p.total));
+            });
+            let report = summarize_index(
+                &llm,
+                &kg,
+                Path::new("."),
+                &queue,
+                config.research.content_guard,
+            )
+            .await?;
+            pb.finish_and_clear();
+
+            println!(
+                "Summarized {} files and {} directories ({} unchanged, {} removed)",
+                report.files, report.directories, report.unchanged, report.removed
+            );
+            for (path, error) in &report.failures {
+                eprintln!("  Failed: {}: {}", path, error);
+            }
+            if !report.failures.is_empty() {
+                println!("Run 'arq kg summarize' again to retry the failed entries.");
+            }
+        }
+        Commands::Kg {
+            action: KgAction::Export { format, output },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let output = output.unwrap_or_else(|| {
+                std::path::PathBuf::from(format!("arq-graph.{}", format.extension()))
+            });
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let summary = kg.export(format, &output).await?;
+
+            println!(
+                "Exported {} entities and {} edges to {}",
+                summary.entities,
+                summary.edges,
+                output.display()
+            );
+        }
+        Commands::Kg {
+            action: KgAction::Backup { archive },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+            let summary = backup::backup(&db_path, &archive).await?;
+            println!(
+                "Backed up {} files to {} ({:.1} MB)",
+                summary.files,
+                summary.archive.display(),
+                summary.bytes as f64 / 1_048_576.0
+            );
+        }
+        Commands::Kg {
+            action: KgAction::Restore { archive, force },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+            let summary = backup::restore(&archive, &db_path, force).await?;
+            println!(
+                "Restored {} files from {} to {}",
+                summary.files,
+                summary.archive.display(),
+                db_path.display()
+            );
+        }
+        Commands::Docs {
+            action: DocsAction::Generate { format, output },
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open(&db_path).await?;
+            let summary = kg.generate_docs(format, &output).await?;
+
+            println!(
+                "Documented {} entities on {} pages in {}",
+                summary.entities,
+                summary.pages,
+                output.display()
+            );
+            println!(
+                "Start at {}",
+                output
+                    .join(format!("index.{}", format.extension()))
+                    .display()
+            );
+        }
         Commands::Graph { action } => {
             let db_path = config.knowledge.db_full_path(&config.storage);
 
             if !db_path.exists() {
-                return Err("Knowledge graph not initialized. Run 'arq init' first.".into());
+                return Err(KnowledgeError::NotInitialized.into());
             }
 
             let kg = KnowledgeGraph::open(&db_path).await?;
 
             match action {
-                GraphAction::Deps { name } => {
+                GraphAction::Deps {
+                    name,
+                    resolved_only,
+                    as_of: Some(as_of),
+                } => {
+                    // Functions since removed keep their history, so skip the lookup
+                    println!(
+                        "Dependencies for '{}' as of {}\n",
+                        name,
+                        format_as_of(as_of)
+                    );
+                    let deps = kg
+                        .get_dependencies_as_of(&name, resolved_only, as_of)
+                        .await?;
+                    if deps.is_empty() {
+                        println!("'{}' had no outgoing calls recorded then.", name);
+                    } else {
+                        println!("'{}' called:", name);
+                        for dep in &deps {
+                            println!("  → {}", dep);
+                        }
+                    }
+                }
+                GraphAction::Deps {
+                    name,
+                    resolved_only,
+                    as_of: None,
+                } => {
                     println!("Dependencies for '{}'\n", name);
 
                     // Find function by name first
@@ -495,7 +1988,11 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                     match func {
                         Some(f) => {
                             // Use function name directly for the call lookup
-                            let deps = kg.get_dependencies(&name).await?;
+                            let deps = if resolved_only {
+                                kg.get_resolved_dependencies(&name).await?
+                            } else {
+                                kg.get_dependencies(&name).await?
+                            };
 
                             if deps.is_empty() {
                                 println!("'{}' has no outgoing calls recorded.", name);
@@ -513,7 +2010,31 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
-                GraphAction::Impact { name } => {
+                GraphAction::Impact {
+                    name,
+                    as_of: Some(as_of),
+                    ..
+                } => {
+                    println!(
+                        "Impact analysis for '{}' as of {}\n",
+                        name,
+                        format_as_of(as_of)
+                    );
+                    let callers = kg.get_impact_as_of(&name, as_of).await?;
+                    if callers.is_empty() {
+                        println!("'{}' had no incoming calls recorded then.", name);
+                    } else {
+                        println!("'{}' was called by:", name);
+                        for caller in &callers {
+                            println!("  ← {}", caller);
+                        }
+                    }
+                }
+                GraphAction::Impact {
+                    name,
+                    crate_name,
+                    as_of: None,
+                } => {
                     println!("Impact analysis for '{}'\n", name);
 
                     // Find function by name first
@@ -522,7 +2043,12 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                     match func {
                         Some(f) => {
                             // Use function name directly for the call lookup
-                            let callers = kg.get_impact(&name).await?;
+                            let callers = match crate_name {
+                                Some(ref crate_name) => {
+                                    kg.get_impact_in_crate(&name, crate_name).await?
+                                }
+                                None => kg.get_impact(&name).await?,
+                            };
 
                             if callers.is_empty() {
                                 println!("'{}' has no incoming calls recorded.", name);
@@ -533,6 +2059,19 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                                     println!("  ← {}", caller);
                                 }
                             }
+
+                            let co_changes = kg.co_changes_with(&f.file_path).await?;
+                            if !co_changes.is_empty() {
+                                println!("\nFiles that historically change with {}:", f.file_path);
+                                for pair in co_changes.iter().take(CO_CHANGE_LIMIT) {
+                                    println!(
+                                        "  ~ {} (together in {} of {} commits)",
+                                        pair.partner_of(&f.file_path).unwrap_or_default(),
+                                        pair.count,
+                                        pair.commits_of(&f.file_path).unwrap_or_default()
+                                    );
+                                }
+                            }
                         }
                         None => {
                             println!("Function '{}' not found in the index.", name);
@@ -540,10 +2079,27 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
-                GraphAction::Functions { limit } => {
+                GraphAction::Calls { between, json } => {
+                    let (a, b) = (&between[0], &between[1]);
+                    let calls = kg.calls_between(a, b).await?;
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&calls).map_err(std::io::Error::other)?
+                        );
+                    } else {
+                        print_calls_between(a, b, &calls);
+                    }
+                }
+                GraphAction::Functions { limit, crate_name } => {
                     println!("Indexed functions (showing up to {}):\n", limit);
 
-                    let all_functions = kg.list_functions(limit).await?;
+                    let all_functions = match crate_name {
+                        Some(ref crate_name) => {
+                            kg.list_functions_in_crate(crate_name, limit).await?
+                        }
+                        None => kg.list_functions(limit).await?,
+                    };
                     let functions: Vec<_> = all_functions.into_iter().take(limit).collect();
 
                     if functions.is_empty() {
@@ -560,29 +2116,375 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
                         println!("\n  Total: {} functions", functions.len());
                     }
                 }
-            }
-        }
-        Commands::Tui => {
-            tui::run(config, manager).await?;
-        }
-        Commands::Serve { port, no_open } => {
-            let db_path = config.knowledge.db_full_path(&config.storage);
+                GraphAction::Impls { name } => {
+                    let edges = kg.implementations_of(&name).await?;
+                    if edges.is_empty() {
+                        println!("No implementations found for '{}'.", name);
+                    }
 
-            if !db_path.exists() {
-                println!("Knowledge graph not initialized.");
-                println!("Run 'arq init' to index your codebase first.");
-                return Ok(());
-            }
+                    let is_named = |id: &str| QualifiedName::from_entity_id(id).name() == name;
+                    let implementors: Vec<_> =
+                        edges.iter().filter(|e| is_named(&e.trait_id)).collect();
+                    let implemented: Vec<_> =
+                        edges.iter().filter(|e| is_named(&e.impl_id)).collect();
+                    if !implementors.is_empty() {
+                        println!("Implementations of '{}':", name);
+                        for edge in implementors {
+                            println!("  {}", edge.impl_id);
+                        }
+                    }
+                    if !implemented.is_empty() {
+                        println!("'{}' implements:", name);
+                        for edge in implemented {
+                            println!("  {}", edge.trait_id);
+                        }
+                    }
+                }
+                GraphAction::Api { type_name } => {
+                    println!("API impact for '{}'\n", type_name);
 
-            let serve_config = serve::ServeConfig {
-                port,
-                open_browser: !no_open,
-                project_path: std::env::current_dir()?,
-                db_path: db_path.clone(),
-            };
+                    let usages = kg.get_api_impact(&type_name).await?;
 
-            serve::start_server(serve_config).await?;
-        }
+                    if usages.is_empty() {
+                        println!("No API operations consume or produce '{}'.", type_name);
+                        println!(
+                            "\nTip: GraphQL schemas (.graphql, .gql) are indexed by 'arq init'."
+                        );
+                    } else {
+                        for (label, resolvers) in [("Operations", false), ("Resolvers", true)] {
+                            let group: Vec<_> = usages
+                                .iter()
+                                .filter(|u| u.is_resolver() == resolvers)
+                                .collect();
+                            if group.is_empty() {
+                                continue;
+                            }
+                            println!("{}:", label);
+                            for usage in group {
+                                let arrow = match usage.relation {
+                                    ApiRelation::Consumes => "consumes",
+                                    ApiRelation::Produces => "produces",
+                                };
+                                println!("  {} {} ({})", arrow, usage.name, usage.entity_id);
+                            }
+                        }
+                    }
+                }
+                GraphAction::Edges {
+                    relation,
+                    from,
+                    limit,
+                    offset,
+                    as_of,
+                } => {
+                    // Fetch one extra edge to tell whether another page follows
+                    let mut edges = kg
+                        .list_edges(&relation, from.as_deref(), offset, limit + 1, as_of)
+                        .await?;
+                    let has_more = edges.len() > limit;
+                    edges.truncate(limit);
+
+                    if edges.is_empty() {
+                        match from {
+                            Some(ref from) => println!("No '{}' edges from {}.", relation, from),
+                            None => println!("No '{}' edges recorded.", relation),
+                        }
+                    } else {
+                        for edge in &edges {
+                            println!("  {} -[{}]-> {}", edge.source, relation, edge.target);
+                        }
+                        println!("\n  Showing {}-{}", offset + 1, offset + edges.len());
+                        if has_more {
+                            println!("  More edges: --offset {}", offset + edges.len());
+                        }
+                    }
+                }
+                GraphAction::At {
+                    location: (path, line),
+                    json,
+                } => {
+                    let entity = kg.entity_at(&path, line).await?;
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&entity).map_err(std::io::Error::other)?
+                        );
+                    } else if let Some(entity) = entity {
+                        println!(
+                            "{} {}",
+                            entity.kind,
+                            entity.qualified_name.as_deref().unwrap_or(&entity.name)
+                        );
+                        println!(
+                            "  {}:{}-{}",
+                            path,
+                            entity.start_line.unwrap_or(line),
+                            entity.end_line.unwrap_or(line)
+                        );
+                        if let Some(signature) = entity.signature.filter(|s| !s.is_empty()) {
+                            println!("  {}", signature);
+                        }
+                        println!("  ID: {}", entity.id);
+                    } else {
+                        println!("No indexed entity contains {}:{}.", path, line);
+                    }
+                }
+            }
+        }
+        Commands::Config {
+            action: ConfigAction::Show { origin },
+        } => {
+            let (config, origins) = Config::load_with_origins(cli.profile.as_deref())?;
+            let files = Config::config_files()?;
+            if files.is_empty() {
+                println!("# No config files found; using defaults");
+            } else {
+                println!("# Merged from, lowest priority first:");
+                for file in &files {
+                    println!("#   {}", file.display());
+                }
+            }
+            println!();
+            print!("{}", config.render(origin.then_some(&origins)));
+        }
+        Commands::Config {
+            action: ConfigAction::SetKey { provider },
+        } => {
+            let provider = provider.unwrap_or_else(|| config.llm.provider.clone());
+            let key = read_secret(&format!("API key for {}: ", provider))?;
+            if key.is_empty() {
+                return Err(ArqError::other("No key entered; nothing stored."));
+            }
+
+            secrets::set_api_key(&provider, &key)?;
+            println!(
+                "Stored the {} API key in the OS keyring.",
+                secrets::keyring_account(&provider)
+            );
+            if Config::file_sets_api_key("arq.toml") {
+                println!("arq.toml still sets `api_key`, which takes precedence; remove it.");
+            }
+        }
+        Commands::Config {
+            action: ConfigAction::DeleteKey { provider },
+        } => {
+            let provider = provider.unwrap_or_else(|| config.llm.provider.clone());
+            let account = secrets::keyring_account(&provider);
+            if secrets::delete_api_key(&provider)? {
+                println!("Removed the {} API key from the OS keyring.", account);
+            } else {
+                println!("No {} API key stored in the OS keyring.", account);
+            }
+        }
+        Commands::Llm {
+            action: LlmAction::Ping { provider, timeout },
+        } => {
+            let configs: Vec<LLMConfig> = match provider.is_empty() {
+                true => vec![config.llm.clone()],
+                false => provider
+                    .iter()
+                    .map(|spec| config.llm.for_provider(spec))
+                    .collect(),
+            };
+            println!("Pinging {} provider(s)...\n", configs.len());
+
+            let timeout = std::time::Duration::from_secs(timeout);
+            let mut failed = 0;
+            for llm_config in &configs {
+                let report = ping_provider(llm_config, timeout).await;
+                let name = format!("{}:{}", report.provider, report.model);
+                if !report.is_ok() {
+                    failed += 1;
+                    println!(
+                        "  ✗ {}  {}: {}",
+                        name,
+                        report.status.label(),
+                        report.error.as_deref().unwrap_or_default()
+                    );
+                    continue;
+                }
+
+                let ms = |d: std::time::Duration| format!("{} ms", d.as_millis());
+                let streaming = match (report.streaming, report.first_chunk) {
+                    (Some(true), Some(first_chunk)) => {
+                        format!("yes (first chunk {})", ms(first_chunk))
+                    }
+                    _ => "no".to_string(),
+                };
+                println!(
+                    "  ✓ {}  latency {}, streaming {}",
+                    name,
+                    report.latency.map(ms).unwrap_or_default(),
+                    streaming
+                );
+                if let Some(ref error) = report.error {
+                    println!("      {}", error);
+                }
+            }
+
+            if failed > 0 {
+                return Err(LLMError::RequestFailed(format!(
+                    "{} of {} providers failed the health check",
+                    failed,
+                    configs.len()
+                ))
+                .into());
+            }
+        }
+        Commands::Models {
+            action: ModelsAction::List { no_save },
+        } => {
+            println!(
+                "Fetching models from {} ({})...\n",
+                config.llm.provider,
+                models_endpoint(&config.llm)
+            );
+
+            let models = list_models(&config.llm).await?;
+            if models.is_empty() {
+                println!("The provider returned no models.");
+                return Ok(());
+            }
+
+            let current = config.llm.model_or_default();
+            for (i, model) in models.iter().enumerate() {
+                let configured = config.llm.available_models.contains(model);
+                let marker = match (model == &current, configured) {
+                    (true, _) => " (current)",
+                    (false, true) => " (configured)",
+                    _ => "",
+                };
+                println!("{:>4}. {}{}", i + 1, model, marker);
+            }
+
+            if no_save || !std::io::stdin().is_terminal() {
+                return Ok(());
+            }
+
+            let path = Config::save_path()?;
+            println!(
+                "\nSelect models to add to `available_models` in {}",
+                path.display()
+            );
+            print!("(e.g. 1,3-5 or 'all'; Enter to skip): ");
+            std::io::stdout().flush()?;
+
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            let selected = parse_selection(&input, models.len())?;
+            if selected.is_empty() {
+                println!("Nothing saved.");
+                return Ok(());
+            }
+
+            let mut available = config.llm.available_models.clone();
+            for index in selected {
+                if !available.contains(&models[index]) {
+                    available.push(models[index].clone());
+                }
+            }
+
+            Config::save_available_models(&path, &available)?;
+            println!(
+                "Saved {} models to {}. Press [m] in the TUI to switch.",
+                available.len(),
+                path.display()
+            );
+        }
+        Commands::Tui { record, replay } => {
+            tui::run(config, manager, record.as_deref(), replay.as_deref())
+                .await
+                .map_err(|e| ArqError::other(e.to_string()))?;
+        }
+        Commands::Serve {
+            port,
+            no_open,
+            watch,
+        } => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+
+            if !db_path.exists() {
+                println!("Knowledge graph not initialized.");
+                println!("Run 'arq init' to index your codebase first.");
+                return Ok(());
+            }
+
+            let serve_config = serve::ServeConfig {
+                port,
+                open_browser: !no_open,
+                project_path: std::env::current_dir()?,
+                db_path: db_path.clone(),
+                data_dir: config.storage.project_dir(),
+                model_cache: config.storage.model_cache_path(),
+                watch,
+                knowledge: config.knowledge.clone(),
+                parsers: config.parsers.clone(),
+                blocklist: PathBlocklist::new(config.serve.blocked_paths.clone()),
+            };
+
+            serve::start_server(serve_config)
+                .await
+                .map_err(|e| ArqError::other(e.to_string()))?;
+        }
+        Commands::Mcp => {
+            let db_path = config.knowledge.db_full_path(&config.storage);
+            if !db_path.exists() {
+                return Err(KnowledgeError::NotInitialized.into());
+            }
+
+            let kg = KnowledgeGraph::open_with_warmup(&db_path, config.knowledge.warmup)
+                .await?
+                .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+                .with_model_cache(config.storage.model_cache_path());
+            let server = mcp::McpServer::new(
+                kg,
+                std::env::current_dir()?,
+                PathBlocklist::new(config.context.private_paths.clone()),
+            );
+            server
+                .run()
+                .await
+                .map_err(|e| ArqError::other(e.to_string()))?;
+        }
+        Commands::Watch { debounce } => {
+            watch_project(&config, Duration::from_millis(debounce)).await?;
+        }
+        Commands::Embedder {
+            action: EmbedderAction::Serve { addr },
+        } => {
+            let addr = addr
+                .or_else(|| config.knowledge.embedder_daemon.clone())
+                .unwrap_or_else(|| DEFAULT_EMBEDDER_DAEMON_ADDR.to_string());
+
+            println!("Loading embedding model...");
+            let started = Instant::now();
+            let cache_dir = config.storage.model_cache_path();
+            let embedder =
+                tokio::task::spawn_blocking(move || FastEmbedder::with_cache_dir(cache_dir))
+                    .await
+                    .map_err(|e| ArqError::other(e.to_string()))??;
+            println!(
+                "Loaded {} in {}",
+                embedder.model_name(),
+                format_duration(started.elapsed())
+            );
+
+            let listener = std::net::TcpListener::bind(&addr)?;
+            println!("Serving embeddings on {} (Ctrl+C to stop)", addr);
+            if config.knowledge.embedder_daemon.as_deref() != Some(addr.as_str()) {
+                println!("\nTo use it, set in arq.toml:");
+                println!("  [knowledge]\n  embedder_daemon = \"{}\"", addr);
+            }
+
+            tokio::task::spawn_blocking(move || {
+                serve_embeddings(listener, std::sync::Arc::new(embedder))
+            })
+            .await
+            .map_err(|e| ArqError::other(e.to_string()))??;
+        }
+        Commands::Doctor => {
+            doctor(&config).await?;
+        }
         Commands::Upgrade => {
             let current_version = env!("CARGO_PKG_VERSION");
             println!("Current version: {}", current_version);
@@ -607,3 +2509,1281 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Report how long knowledge graph startup takes, split into its stages.
+/// Load the embedding model, then index the current directory into `kg`
+/// with progress bars.
+async fn index_project(
+    kg: &KnowledgeGraph,
+    events: Option<EventWriter>,
+) -> Result<IndexStats, ArqError> {
+    for (extension, language) in kg.unmapped_extension_overrides() {
+        eprintln!(
+            "Warning: knowledge.extension_overrides maps .{} to '{}', which has no enabled parser",
+            extension, language
+        );
+    }
+    if let Some(events) = events {
+        events.emit(&ProgressEvent::phase("loading_model"));
+    }
+
+    // Step 1: Load embedding model (spinner - can't show progress for model loading)
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    spinner.set_message("Loading embedding model (first run downloads ~50MB)...");
+    kg.initialize().await?;
+    kg.warm_up()?;
+    spinner.finish_with_message("Embedding model loaded");
+
+    // Step 2: Count files to index
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    spinner.set_message("Scanning codebase...");
+    if let Some(events) = events {
+        events.emit(&ProgressEvent::phase("scanning"));
+    }
+
+    let total_files = kg.count_indexable_files(Path::new("."));
+    spinner.finish_with_message(format!("Found {} files to index", total_files));
+    if let Some(events) = events {
+        events.emit(&ProgressEvent::count("files_to_index", total_files));
+        events.emit(&ProgressEvent::phase("indexing"));
+    }
+
+    // Step 3: Index codebase with progress bar
+    let pb = ProgressBar::new(total_files as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.cyan} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let stats: IndexStats = kg
+        .index_directory_with_progress(Path::new("."), |progress: IndexProgress| {
+            pb.set_position(progress.files_done as u64);
+            // Show just the filename, not full path
+            let filename = progress
+                .current_file
+                .rsplit('/')
+                .next()
+                .unwrap_or(&progress.current_file);
+            pb.set_message(filename.to_string());
+            if let Some(events) = events {
+                events.emit(&progress.into());
+            }
+        })
+        .await?;
+    pb.finish_with_message("Complete");
+    Ok(stats)
+}
+
+/// Index the documentation folders of the current directory into an
+/// existing knowledge graph.
+async fn index_documents(
+    kg: &KnowledgeGraph,
+    events: Option<EventWriter>,
+) -> Result<IndexStats, ArqError> {
+    if let Some(events) = events {
+        events.emit(&ProgressEvent::phase("loading_model"));
+    }
+
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    spinner.set_message("Loading embedding model (first run downloads ~50MB)...");
+    // Graphs created before documentation support lack its table
+    kg.initialize().await?;
+    kg.warm_up()?;
+    spinner.finish_with_message("Embedding model loaded");
+
+    if let Some(events) = events {
+        events.emit(&ProgressEvent::phase("indexing"));
+    }
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.cyan} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let stats = kg
+        .index_documents_with_progress(Path::new("."), |progress: IndexProgress| {
+            pb.set_length(progress.files_total as u64);
+            pb.set_position(progress.files_done as u64);
+            pb.set_message(progress.current_file.clone());
+            if let Some(events) = events {
+                events.emit(&progress.into());
+            }
+        })
+        .await?;
+    pb.finish_with_message("Complete");
+    Ok(stats)
+}
+
+/// Pack the files most called in the knowledge graph first when scanned
+/// context has to be cut off.
+///
+/// Best effort: without call data, files keep the default priority order.
+async fn prioritize_by_centrality(
+    context_builder: ContextBuilder,
+    kg: &KnowledgeGraph,
+    config: &Config,
+) -> ContextBuilder {
+    if config.context.file_order != FileOrder::Priority {
+        return context_builder;
+    }
+    match kg.file_centrality(CentralityMetric::InDegree).await {
+        Ok(priorities) => context_builder.with_priorities(priorities),
+        Err(_) => context_builder,
+    }
+}
+
+/// Index the current directory into a knowledge graph held in memory.
+async fn index_in_memory(
+    config: &Config,
+    events: Option<EventWriter>,
+) -> Result<(KnowledgeGraph, IndexStats), ArqError> {
+    let kg = KnowledgeGraph::open_in_memory()
+        .await?
+        .with_insert_batch_size(config.knowledge.insert_batch_size)
+        .with_index_jobs(config.knowledge.index_jobs)
+        .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+        .with_model_cache(config.storage.model_cache_path())
+        .with_generated_code(GeneratedCodePolicy::from_config(&config.knowledge))
+        .with_size_limit(
+            config.knowledge.max_file_size,
+            config.knowledge.oversized_files,
+        )
+        .with_docs(config.knowledge.docs.clone())
+        .with_parsers(&config.parsers)
+        .with_languages(
+            config.knowledge.disabled_languages.clone(),
+            config.knowledge.extension_overrides.clone(),
+        );
+    let stats = index_project(&kg, events).await?;
+    Ok((kg, stats))
+}
+
+/// Re-index files as they change, until interrupted (`arq watch`).
+///
+/// The knowledge graph is only opened while a batch of changes is indexed,
+/// so other commands can use it in between. A batch that fails, e.g. while
+/// another command holds the graph, is retried along with the next one.
+async fn watch_project(config: &Config, debounce: Duration) -> Result<(), ArqError> {
+    let db_path = config.knowledge.db_full_path(&config.storage);
+    if !db_path.exists() {
+        return Err(KnowledgeError::NotInitialized.into());
+    }
+    let root = std::env::current_dir()?;
+    let mut watcher = FileWatcher::new(&root, WatchFilter::from_config(&config.context), debounce)?;
+    println!(
+        "Watching {} for changes (Ctrl+C to stop)...",
+        root.display()
+    );
+
+    // Built once: the graph is reopened for every batch
+    let parsers = KnowledgeGraph::external_parsers(&config.parsers);
+    let mut pending = FileChanges::default();
+    while let Some(changes) = watcher.next_changes().await {
+        pending.merge(changes);
+        match reindex_changes(config, &db_path, &root, &pending, &parsers).await {
+            Ok(stats) => {
+                if stats.files > 0 {
+                    println!("Re-indexed {} changed files", stats.files);
+                }
+                for failure in &stats.report.failures {
+                    eprintln!("Failed to index {}: {}", failure.file, failure.error);
+                }
+                pending = FileChanges::default();
+            }
+            Err(e) => eprintln!("Re-indexing failed, will retry on the next change: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Open the knowledge graph at `db_path` to apply `changes` under `root`.
+async fn reindex_changes(
+    config: &Config,
+    db_path: &Path,
+    root: &Path,
+    changes: &FileChanges,
+    parsers: &[std::sync::Arc<dyn arq_core::knowledge::parser::Parser>],
+) -> Result<IndexStats, KnowledgeError> {
+    KnowledgeGraph::open(db_path)
+        .await?
+        .with_insert_batch_size(config.knowledge.insert_batch_size)
+        .with_index_jobs(config.knowledge.index_jobs)
+        .with_history_retention(config.knowledge.history_retention_days)
+        .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+        .with_model_cache(config.storage.model_cache_path())
+        .with_generated_code(GeneratedCodePolicy::from_config(&config.knowledge))
+        .with_size_limit(
+            config.knowledge.max_file_size,
+            config.knowledge.oversized_files,
+        )
+        .with_docs(config.knowledge.docs.clone())
+        .with_external_parsers(parsers.to_vec())
+        .with_languages(
+            config.knowledge.disabled_languages.clone(),
+            config.knowledge.extension_overrides.clone(),
+        )
+        .apply_changes(root, changes)
+        .await
+}
+
+async fn doctor(config: &Config) -> Result<(), ArqError> {
+    println!("Arq Doctor\n");
+
+    let db_path = config.knowledge.db_full_path(&config.storage);
+    if !db_path.exists() {
+        println!("  Knowledge graph: not initialized (run 'arq init')");
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    let kg = KnowledgeGraph::open(&db_path)
+        .await?
+        .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+        .with_model_cache(config.storage.model_cache_path());
+    println!(
+        "  Knowledge graph open: {}",
+        format_duration(started.elapsed())
+    );
+
+    let started = Instant::now();
+    let stats = kg.get_stats().await?;
+    println!(
+        "  Graph query (stats, {} chunks): {}",
+        stats.chunks,
+        format_duration(started.elapsed())
+    );
+
+    match &config.knowledge.embedder_daemon {
+        Some(addr) => match RemoteEmbedder::connect(addr) {
+            Ok(_) => println!("  Embedder daemon: running at {}", addr),
+            Err(e) => println!("  Embedder daemon: not reachable ({})", e),
+        },
+        None => println!("  Embedder daemon: not configured"),
+    }
+
+    let warm_up = kg.warm_up();
+    let metrics = kg.embedder_metrics();
+    let init_time = metrics.init_time.map(format_duration).unwrap_or_default();
+    if let Err(e) = warm_up {
+        println!("  Embedder: failed after {} ({})", init_time, e);
+        return Ok(());
+    }
+    println!(
+        "  Embedder ready ({}): {}",
+        metrics.backend.as_deref().unwrap_or("local"),
+        init_time
+    );
+
+    let started = Instant::now();
+    kg.search_code("main entry point", 1).await?;
+    println!(
+        "  First semantic search: {}",
+        format_duration(started.elapsed())
+    );
+
+    if metrics.backend.as_deref() == Some("local") {
+        println!("\nTip: run 'arq embedder serve' and set knowledge.embedder_daemon");
+        println!("     to skip loading the model in every command.");
+    }
+    Ok(())
+}
+
+/// Build the current task's research from the knowledge graph without an LLM.
+async fn offline_research_command(
+    config: &Config,
+    manager: &mut TaskManager<FileStorage>,
+    hooks: &Hooks,
+    task: &Task,
+) -> Result<(), ArqError> {
+    let db_path = config.knowledge.db_full_path(&config.storage);
+    if !db_path.exists() {
+        return Err(KnowledgeError::NotInitialized.into());
+    }
+
+    println!("Building offline research skeleton for: {}", task.prompt);
+    println!();
+
+    let kg = KnowledgeGraph::open(&db_path).await?;
+    let doc = offline_research(task, &kg, &config.research.rerank).await?;
+
+    println!("Research complete (offline)!\n");
+    println!("## Summary\n");
+    println!("{}\n", doc.summary);
+    println!("## Relevant Files\n");
+    for source in &doc.sources {
+        println!("  {}", source.location);
+    }
+    println!();
+
+    let task = manager.set_research_doc(&task.id, doc)?;
+
+    let research_path = config.storage.local_research_path();
+    println!("Research saved to {}", research_path.display());
+    run_post_hook(hooks, HookEvent::PostResearch, &task, &research_path).await;
+    println!("\nNext: Run 'arq advance' to move to Planning phase.");
+    Ok(())
+}
+
+/// Run a post-phase hook, reporting failure as a warning since the phase already finished.
+async fn run_post_hook(hooks: &Hooks, event: HookEvent, task: &Task, artifact: &Path) {
+    let env = [("ARQ_ARTIFACT_PATH", artifact.to_string_lossy().into_owned())];
+    if let Err(e) = hooks.run(event, task, &env).await {
+        eprintln!("Warning: {}", e);
+    }
+}
+
+/// Generate the current task's plan edits one file at a time, apply the
+/// confirmed ones and complete the task.
+///
+/// Edits already applied stay applied if a later one fails; the agent log
+/// and usage are saved either way. Items the agent log records as applied,
+/// by an earlier run that was stopped or failed, are not generated again.
+async fn run_agent(
+    config: &Config,
+    manager: &mut TaskManager<FileStorage>,
+    yes: bool,
+) -> Result<(), ArqError> {
+    let task = manager
+        .get_current_task()?
+        .ok_or_else(|| ArqError::usage(NO_CURRENT_TASK))?;
+    let plan = task.plan.clone().ok_or_else(|| {
+        ArqError::usage("Current task has no plan. Generate and approve one in 'arq tui' first.")
+    })?;
+
+    // A plan approved in the TUI leaves the task in Planning
+    let task = match task.phase {
+        Phase::Agent => task,
+        Phase::Planning => {
+            manager.advance_phase(&task.id)?;
+            println!("Advanced to Agent phase.");
+            manager.get_task(&task.id)?
+        }
+        phase => {
+            return Err(ArqError::usage(format!(
+                "Task is in {} phase, not Agent phase.",
+                phase.display_name()
+            )))
+        }
+    };
+    if !yes && !std::io::stdin().is_terminal() {
+        return Err(ArqError::usage(
+            "Confirming edits needs a terminal; pass --yes to apply them unasked.",
+        ));
+    }
+
+    let persona = prompts::for_phase(Phase::Agent, config);
+    let llm = Provider::build_cached(&persona.llm_config(&config.llm), &config.storage)?;
+    let runner = AgentRunner::new(llm, plan.clone(), ".")
+        .with_private_paths(PathBlocklist::new(config.context.private_paths.clone()));
+    let hooks = Hooks::new(config.hooks.clone());
+    let items = runner.items();
+    let storage = FileStorage::with_config(config.storage.clone());
+    let applied = storage.load_agent_log(&task.id)?;
+
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    let started = Instant::now();
+    let mut tokens = TokenUsage::default();
+    let mut results = Vec::new();
+    let mut failure = None;
+    let mut stopped = false;
+    for (i, item) in items.iter().enumerate() {
+        println!("[{}/{}] {}", i + 1, items.len(), item.path());
+        if applied.iter().any(|result| result.item == *item) {
+            println!("  Already applied.\n");
+            continue;
+        }
+        let edit = runner.generate_edit(item, &progress_tx).await;
+        while let Ok(progress) = progress_rx.try_recv() {
+            if let AgentProgress::TokenUsage(usage) = progress {
+                tokens += usage;
+            }
+        }
+        let edit = match edit {
+            Ok(edit) => edit,
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        };
+        if edit.is_unchanged() {
+            println!("  No changes.\n");
+            continue;
+        }
+
+        let result = edit.result(&plan);
+        println!("\n{}", result.generated_code);
+        for deviation in &result.deviations {
+            println!("  Warning: {}", deviation);
+        }
+        let Some(apply) = (if yes { Some(true) } else { confirm_edit() }) else {
+            stopped = true;
+            break;
+        };
+        if !apply {
+            println!("  Skipped {}\n", edit.path());
+            continue;
+        }
+        if let Err(e) = edit.apply(Path::new(".")) {
+            failure = Some(e);
+            break;
+        }
+        println!("  Applied {}\n", edit.path());
+        run_post_hook(
+            &hooks,
+            HookEvent::PostAgentStep,
+            &task,
+            Path::new(edit.path()),
+        )
+        .await;
+        results.push(result);
+    }
+
+    storage.save_agent_log(&task.id, &results)?;
+    if tokens.total() > 0 {
+        manager.record_usage(
+            &task.id,
+            UsageRecord::new(
+                Phase::Agent,
+                &config.llm.provider,
+                config.llm.model_or_default(),
+                tokens,
+                started.elapsed(),
+            ),
+        )?;
+    }
+    if let Some(e) = failure {
+        return Err(e.into());
+    }
+    if stopped {
+        println!("Stopped after applying {} edit(s).", results.len());
+        println!("Run 'arq agent run' again to continue with the rest.");
+        return Ok(());
+    }
+
+    manager.advance_phase(&task.id)?;
+    println!(
+        "Applied {} of {} edit(s). Task complete.",
+        applied.len() + results.len(),
+        items.len()
+    );
+    println!("\nNext: Review the changes with 'arq check'.");
+    Ok(())
+}
+
+/// Ask whether to apply the edit just shown: `Some(true)` to apply,
+/// `Some(false)` to skip it, `None` to stop.
+fn confirm_edit() -> Option<bool> {
+    eprint!("Apply this edit? [y/N/q] ");
+    let _ = std::io::stderr().flush();
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input).is_err() {
+        return None;
+    }
+    match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => Some(true),
+        "q" | "quit" => None,
+        _ => Some(false),
+    }
+}
+
+/// Load the configuration for a command.
+///
+/// A config file that can't be read or parsed falls back to the defaults
+/// with a warning, so commands that don't depend on it still run. A profile
+/// passed with `--profile` must load.
+fn load_config(profile: Option<&str>) -> Result<Config, ArqError> {
+    match Config::load_with_profile(profile) {
+        Ok(config) => Ok(config),
+        Err(e) if profile.is_some() => Err(e.into()),
+        Err(e) => {
+            eprintln!("Warning: {}; using the default configuration", e);
+            Ok(Config::default())
+        }
+    }
+}
+
+/// Run the current task's research against two providers and compare them.
+async fn compare_research(
+    config: &Config,
+    task: &Task,
+    providers: &[String],
+) -> Result<(), ArqError> {
+    let [left, right] = providers else {
+        return Err(ArqError::usage(
+            "--compare takes exactly two providers, e.g. --compare anthropic,openai",
+        ));
+    };
+
+    println!(
+        "Comparing research from {} and {} for: {}",
+        left, right, task.prompt
+    );
+    println!();
+
+    let persona = prompts::for_phase(Phase::Research, config);
+    let left_llm = Provider::build_cached(
+        &persona.llm_config(&config.llm.for_provider(left)),
+        &config.storage,
+    )?;
+    let right_llm = Provider::build_cached(
+        &persona.llm_config(&config.llm.for_provider(right)),
+        &config.storage,
+    )?;
+
+    let db_path = config.knowledge.db_full_path(&config.storage);
+    let kg: Option<std::sync::Arc<dyn KnowledgeStore>> = if db_path.exists() {
+        println!("Using knowledge graph for smart context...");
+        Some(std::sync::Arc::new(
+            KnowledgeGraph::open(&db_path)
+                .await?
+                .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+                .with_model_cache(config.storage.model_cache_path()),
+        ))
+    } else {
+        None
+    };
+
+    let build_runner = |llm| {
+        let context_builder = ContextBuilder::with_config(".", config.context.clone());
+        match &kg {
+            Some(kg) => ResearchRunner::with_knowledge_store(llm, context_builder, kg.clone()),
+            None => ResearchRunner::new(llm, context_builder),
+        }
+        .with_content_guard(config.research.content_guard)
+        .with_rerank(config.research.rerank)
+        .with_self_critique(config.research.self_critique)
+        .with_correct_references(config.research.correct_references)
+        .with_output_language(config.research.output_language.clone())
+        .with_include_tests(config.context.include_tests)
+        .with_private_paths(PathBlocklist::new(config.context.private_paths.clone()))
+        .with_excluded_files(task.excluded_context.clone())
+        .with_system_prompt(persona.system_prompt.clone())
+    };
+    let left_runner = build_runner(left_llm);
+    let right_runner = build_runner(right_llm);
+
+    let (left_result, right_result) = tokio::join!(left_runner.run(task), right_runner.run(task));
+
+    let left_doc = left_result.inspect_err(|_| eprintln!("Research with {} failed.", left))?;
+    let right_doc = right_result.inspect_err(|_| eprintln!("Research with {} failed.", right))?;
+
+    let comparison = ResearchComparison::new(
+        ComparedResearch {
+            label: left.clone(),
+            doc: left_doc,
+        },
+        ComparedResearch {
+            label: right.clone(),
+            doc: right_doc,
+        },
+    );
+
+    println!("{}", comparison.render_side_by_side(COMPARE_COLUMN_WIDTH));
+
+    let path = config.storage.local_arq_dir().join("research-compare.md");
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, comparison.to_markdown())?;
+    println!("\nComparison saved to {}", path.display());
+    println!("Run 'arq research' to save research for the task.");
+
+    Ok(())
+}
+
+/// Print a conformance report for `arq check`.
+fn print_conformance_report(report: &ConformanceReport, files_checked: usize) {
+    let status = match report.status {
+        ConformanceStatus::Passed => "passed",
+        ConformanceStatus::Warning => "passed with warnings",
+        ConformanceStatus::Failed => "failed",
+    };
+
+    println!("Conformance check {} ({} files)\n", status, files_checked);
+    if !report.summary.is_empty() {
+        println!("{}\n", report.summary);
+    }
+
+    for deviation in &report.deviations {
+        let marker = match deviation.severity {
+            ConformanceStatus::Failed => "✗",
+            _ => "!",
+        };
+        println!("  {} {}: {}", marker, deviation.path, deviation.message);
+    }
+
+    if !report.impact.is_empty() {
+        println!("\nImpacted callers:");
+        for impacted in &report.impact {
+            println!(
+                "  {} ({}) <- {}",
+                impacted.symbol,
+                impacted.path,
+                impacted.callers.join(", ")
+            );
+        }
+    }
+}
+
+/// Read a secret from the terminal without echoing it.
+///
+/// Piped input is read as a single line, e.g. `echo $KEY | arq config set-key`.
+fn read_secret(prompt: &str) -> std::io::Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::terminal;
+
+    if !std::io::stdin().is_terminal() {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        return Ok(line.trim().to_string());
+    }
+
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+
+    terminal::enable_raw_mode()?;
+    let mut secret = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Esc => break Err(std::io::ErrorKind::Interrupted.into()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(std::io::ErrorKind::Interrupted.into())
+                }
+                KeyCode::Backspace => {
+                    secret.pop();
+                }
+                KeyCode::Char(c) => secret.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    println!();
+
+    result.map(|()| secret.trim().to_string())
+}
+
+/// Resolve a task by ID prefix or name, asking which one is meant when
+/// several match. Returns `None` after telling the user if none was chosen.
+fn resolve_task(
+    manager: &TaskManager<FileStorage>,
+    query: &str,
+) -> Result<Option<TaskSummary>, ArqError> {
+    let mut matching = manager.find_tasks(query)?;
+    let label = |task: &TaskSummary| {
+        format!(
+            "{} - {} ({})",
+            &task.id[..8.min(task.id.len())],
+            task.name,
+            task.phase.display_name()
+        )
+    };
+
+    match matching.len() {
+        0 => {
+            println!("No task found with ID or name matching '{}'", query);
+            Ok(None)
+        }
+        1 => Ok(matching.pop()),
+        _ if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() => {
+            println!("Multiple tasks match '{}'. Be more specific:", query);
+            for task in &matching {
+                println!("  {}", label(task));
+            }
+            Ok(None)
+        }
+        _ => {
+            let items: Vec<String> = matching.iter().map(label).collect();
+            let prompt = format!(
+                "Multiple tasks match '{}' (↑/↓ to move, Enter to select, Esc to cancel):",
+                query
+            );
+            match select_item(&prompt, &items)? {
+                Some(index) => Ok(Some(matching.swap_remove(index))),
+                None => {
+                    println!("Cancelled.");
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+/// Ask whether to delete `task`, matched by its name. Without a terminal to
+/// ask, it isn't deleted.
+fn confirm_delete(task: &TaskSummary) -> bool {
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+
+    eprint!(
+        "Delete task {} - {}? [y/N] ",
+        &task.id[..8.min(task.id.len())],
+        task.name
+    );
+    let _ = std::io::stderr().flush();
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Let the user pick one of `items` with the arrow keys (or its number).
+///
+/// Returns `None` if the selection was cancelled with Esc, `q` or Ctrl+C.
+fn select_item(prompt: &str, items: &[String]) -> std::io::Result<Option<usize>> {
+    use crossterm::cursor::MoveUp;
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::queue;
+    use crossterm::terminal::{self, Clear, ClearType};
+
+    let mut stdout = std::io::stdout();
+    let mut draw = |selected: usize, redraw: bool| -> std::io::Result<()> {
+        if redraw {
+            queue!(stdout, MoveUp(items.len() as u16))?;
+        }
+        for (i, item) in items.iter().enumerate() {
+            let marker = if i == selected { ">" } else { " " };
+            queue!(stdout, Clear(ClearType::CurrentLine))?;
+            write!(stdout, "{} {}. {}\r\n", marker, i + 1, item)?;
+        }
+        stdout.flush()
+    };
+
+    println!("{}", prompt);
+    terminal::enable_raw_mode()?;
+    let mut selected = 0;
+    // Leave raw mode before returning, whatever the outcome
+    let result = (|| -> std::io::Result<Option<usize>> {
+        draw(selected, false)?;
+        loop {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => {
+                    selected = selected.checked_sub(1).unwrap_or(items.len() - 1)
+                }
+                KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1) % items.len(),
+                KeyCode::Char(c @ '1'..='9') if (c as usize - '1' as usize) < items.len() => {
+                    return Ok(Some(c as usize - '1' as usize))
+                }
+                KeyCode::Enter => return Ok(Some(selected)),
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(None)
+                }
+                _ => continue,
+            }
+            draw(selected, true)?;
+        }
+    })();
+    terminal::disable_raw_mode()?;
+
+    result
+}
+
+/// Print knowledge graph statistics, and vector index performance with `perf`.
+async fn kg_status(config: &Config, perf: bool) -> Result<(), ArqError> {
+    let db_path = config.knowledge.db_full_path(&config.storage);
+
+    if !db_path.exists() {
+        println!("Knowledge graph not initialized.");
+        println!("Run 'arq init' to index your codebase.");
+        return Ok(());
+    }
+
+    let kg = KnowledgeGraph::open(&db_path).await?;
+    let stats = kg.get_extended_stats().await?;
+
+    println!("Knowledge Graph Status\n");
+    println!("  Files indexed: {}", stats.files);
+    println!("  Code chunks: {}", stats.chunks);
+    println!();
+    println!("  Rich Ontology:");
+    println!("    Functions: {}", stats.functions);
+    println!("    Structs: {}", stats.structs);
+    println!("    Traits: {}", stats.traits);
+    println!("    Impls: {}", stats.impls);
+    println!("    Enums: {}", stats.enums);
+    println!("    Constants: {}", stats.constants);
+    if stats.schemas + stats.operations > 0 {
+        println!("    API Schemas: {}", stats.schemas);
+        println!("    API Operations: {}", stats.operations);
+    }
+    println!();
+    println!("  Relations:");
+    println!("    Calls: {}", stats.calls);
+    println!("    Implements: {}", stats.implements);
+    if stats.consumes + stats.produces > 0 {
+        println!("    Consumes: {}", stats.consumes);
+        println!("    Produces: {}", stats.produces);
+    }
+    if !stats.languages.is_empty() {
+        println!();
+        println!("  Languages:");
+        println!(
+            "    {:<14} {:>7} {:>9} {:>9} {:>9} {:>9}",
+            "Language", "Files", "Code", "Comments", "Blanks", "Functions"
+        );
+        let total = stats.languages.iter().fold(
+            LanguageStats {
+                language: "Total".to_string(),
+                ..LanguageStats::default()
+            },
+            |mut total, lang| {
+                total.files += lang.files;
+                total.lines += lang.lines;
+                total.code += lang.code;
+                total.comments += lang.comments;
+                total.blanks += lang.blanks;
+                total.functions += lang.functions;
+                total
+            },
+        );
+        for lang in stats.languages.iter().chain(std::iter::once(&total)) {
+            println!(
+                "    {:<14} {:>7} {:>9} {:>9} {:>9} {:>9}",
+                lang.language, lang.files, lang.code, lang.comments, lang.blanks, lang.functions
+            );
+        }
+    }
+    if perf {
+        let perf = kg.perf_stats(&db_path).await?;
+        println!();
+        println!("  Vector Index Performance:");
+        println!("    Database size: {}", format_bytes(perf.database_bytes));
+        println!(
+            "    Vectors: {} x {} dimensions (~{} raw)",
+            perf.chunks,
+            perf.dimension,
+            format_bytes(perf.vector_bytes())
+        );
+        if let Some(resident) = perf.resident_bytes {
+            println!("    Process memory: {} resident", format_bytes(resident));
+        }
+        println!("    Probe search: {}", format_duration(perf.probe));
+        if let Some(warmup) = perf.last_warmup {
+            println!("    Last warmup: {}", format_duration(warmup));
+        }
+        match perf.search_latency {
+            Some(latency) => println!(
+                "    Search latency (last {}): p50 {}, p90 {}, p99 {}, max {}",
+                latency.samples,
+                format_duration(latency.p50),
+                format_duration(latency.p90),
+                format_duration(latency.p99),
+                format_duration(latency.max)
+            ),
+            None => println!("    Search latency: no searches recorded yet"),
+        }
+    }
+    println!("\nDatabase path: {}", db_path.display());
+    Ok(())
+}
+
+/// Format a byte count with a binary unit (`512 B`, `3.2 MB`).
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Format a duration in whole milliseconds (`42ms`), for latency reports.
+fn format_duration(duration: std::time::Duration) -> String {
+    format!("{:.0}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Format an `--as-of` time for headings.
+fn format_as_of(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+/// Parse a `--visibility` value, rejecting unknown levels.
+fn parse_visibility(value: &str) -> Result<Visibility, String> {
+    match value.to_lowercase().as_str() {
+        "pub" | "public" => Ok(Visibility::Public),
+        "pub(crate)" | "crate" => Ok(Visibility::PublicCrate),
+        "pub(super)" | "super" => Ok(Visibility::PublicSuper),
+        "private" | "priv" => Ok(Visibility::Private),
+        _ => Err(format!(
+            "unknown visibility '{}' (expected pub, pub(crate), pub(super) or private)",
+            value
+        )),
+    }
+}
+
+/// Parse a `graph at` location of the form `path:line`, with a 1-based
+/// line. A leading `./` is stripped so the path matches indexed paths.
+fn parse_location(value: &str) -> Result<(String, u32), String> {
+    let (path, line) = value
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected path:line, got '{}'", value))?;
+    let line = line
+        .parse::<u32>()
+        .ok()
+        .filter(|line| *line > 0)
+        .ok_or_else(|| format!("invalid line number '{}'", line))?;
+    Ok((path.trim_start_matches("./").to_string(), line))
+}
+
+/// Parse a model selection like `1,3-5` or `all` into zero-based indices.
+fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>, ArqError> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("all") {
+        return Ok((0..count).collect());
+    }
+
+    let parse = |n: &str| -> Result<usize, ArqError> {
+        match n.trim().parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => Ok(n - 1),
+            _ => Err(ArqError::usage(format!(
+                "Invalid selection '{}': expected 1-{}",
+                n.trim(),
+                count
+            ))),
+        }
+    };
+
+    let mut selected = Vec::new();
+    for part in input.split(',').filter(|p| !p.trim().is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => selected.extend(parse(start)?..=parse(end)?),
+            None => selected.push(parse(part)?),
+        }
+    }
+    selected.sort_unstable();
+    selected.dedup();
+    Ok(selected)
+}
+
+/// List the files research context would be gathered from and let the
+/// developer toggle which to exclude, returning the new exclusion list.
+///
+/// Exclusions that aren't one of the listed files (e.g. glob patterns) are
+/// kept as they are.
+fn review_context_files(
+    candidates: &[String],
+    mut excluded: Vec<String>,
+) -> Result<Vec<String>, ArqError> {
+    if candidates.is_empty() {
+        eprintln!("No context files found.");
+        return Ok(excluded);
+    }
+
+    let is_excluded = |excluded: &[String], path: &str| {
+        excluded
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path))
+    };
+    eprintln!("Files to be sent to the LLM ([x] = excluded):");
+    for (i, path) in candidates.iter().enumerate() {
+        let mark = if is_excluded(&excluded, path) {
+            "x"
+        } else {
+            " "
+        };
+        eprintln!("{:>4}. [{}] {}", i + 1, mark, path);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Ok(excluded);
+    }
+
+    eprint!("\nToggle files to exclude (e.g. 1,3-5; Enter to continue): ");
+    std::io::stderr().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+
+    for index in parse_selection(&input, candidates.len())? {
+        let path = &candidates[index];
+        if !is_excluded(&excluded, path) {
+            excluded.push(path.clone());
+        } else if excluded.contains(path) {
+            excluded.retain(|p| p != path);
+        } else {
+            eprintln!("{} is excluded by a pattern and stays excluded.", path);
+        }
+    }
+
+    let count = candidates
+        .iter()
+        .filter(|path| is_excluded(&excluded, path))
+        .count();
+    eprintln!("Excluding {} of {} files.\n", count, candidates.len());
+    Ok(excluded)
+}
+
+/// Show the estimate of a research prompt over `research.confirm_cost_above`
+/// and ask whether to send it. Without a terminal to ask, it isn't sent.
+fn confirm_cost(estimate: &CostEstimate) -> bool {
+    eprintln!();
+    for line in estimate.breakdown() {
+        eprintln!("{}", line);
+    }
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+
+    eprint!("Send this prompt? [y/N] ");
+    let _ = std::io::stderr().flush();
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Print the calls crossing `a` and `b`, grouped by direction.
+fn print_calls_between(a: &str, b: &str, calls: &[CrossingCall]) {
+    println!("Calls between {} and {}\n", a, b);
+    if calls.is_empty() {
+        println!("No calls cross {} and {}.", a, b);
+        return;
+    }
+
+    for (direction, from, to) in [
+        (CallDirection::Outgoing, a, b),
+        (CallDirection::Incoming, b, a),
+    ] {
+        let crossing: Vec<_> = calls.iter().filter(|c| c.direction == direction).collect();
+        if crossing.is_empty() {
+            continue;
+        }
+        println!("{} → {} ({}):", from, to, crossing.len());
+        for call in crossing {
+            let ambiguous = if call.resolution == CallResolution::Ambiguous {
+                " (ambiguous)"
+            } else {
+                ""
+            };
+            println!(
+                "  {} ({}:{}) → {} ({}:{}){}",
+                call.caller.qualified_name,
+                call.caller.file_path,
+                call.caller.line,
+                call.callee.qualified_name,
+                call.callee.file_path,
+                call.callee.line,
+                ambiguous
+            );
+        }
+        println!();
+    }
+
+    let outgoing = calls
+        .iter()
+        .filter(|c| c.direction == CallDirection::Outgoing)
+        .count();
+    println!(
+        "{} calls cross: {} from {}, {} from {}.",
+        calls.len(),
+        outgoing,
+        a,
+        calls.len() - outgoing,
+        b
+    );
+}
+
+/// Print a search hit between its surrounding lines, numbered, with the
+/// start of the hit marked by `>`.
+fn print_search_context(result: &SearchResult, context: &SearchContext) {
+    if let Some(ref signature) = context.signature {
+        println!("   in: {}", signature.lines().next().unwrap_or_default());
+    }
+
+    let start = result.start_line as usize;
+    let before_start = start.saturating_sub(context.before.len());
+    for (i, line) in context.before.iter().enumerate() {
+        println!("   {:>5} | {}", before_start + i, line);
+    }
+
+    let preview: Vec<&str> = result
+        .preview
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .take(3)
+        .collect();
+    for (i, line) in preview.iter().enumerate() {
+        println!("  >{:>5} | {}", start + i, line);
+    }
+    if start + preview.len() <= result.end_line as usize {
+        println!("   {:>5} | ...", "");
+    }
+
+    for (i, line) in context.after.iter().enumerate() {
+        println!("   {:>5} | {}", result.end_line as usize + 1 + i, line);
+    }
+}
+
+/// Print the `arq stats` summary.
+fn print_task_stats(stats: &TaskStats) {
+    println!("Task Statistics\n");
+    println!("  Tasks: {}", stats.total_tasks);
+    for (phase, count) in &stats.tasks_per_phase {
+        println!("    {}: {}", phase.display_name(), count);
+    }
+
+    println!();
+    match stats.average_research_duration {
+        Some(avg) => println!(
+            "  Average research duration: {:.1}s ({} runs)",
+            avg.as_secs_f64(),
+            stats.research_runs
+        ),
+        None => println!("  Average research duration: no runs recorded"),
+    }
+
+    if !stats.task_usage.is_empty() {
+        println!("\n  Usage per task (estimated):");
+        for usage in &stats.task_usage {
+            println!(
+                "    {} [{}]: {} tokens, {}",
+                usage.name,
+                usage.phase.display_name(),
+                usage.tokens.total(),
+                format_cost(usage.cost)
+            );
+        }
+        println!(
+            "    Total: {} tokens, {}",
+            stats.total_tokens().total(),
+            format_cost(stats.total_cost())
+        );
+    }
+
+    if !stats.top_files.is_empty() {
+        println!("\n  Most referenced files:");
+        for (file, count) in &stats.top_files {
+            println!("    {:>3}  {}", count, file);
+        }
+    }
+}
+
+/// Format an estimated cost, or `n/a` when pricing is unknown.
+fn format_cost(cost: Option<f64>) -> String {
+    cost.map_or_else(|| "cost n/a".to_string(), |c| format!("~${:.2}", c))
+}
+
+/// Print how fast a run indexed and where its time went, to tune `--jobs`.
+fn report_throughput(events: Option<EventWriter>, timings: &IndexTimings) {
+    if timings.files == 0 {
+        return;
+    }
+    let seconds = |duration: std::time::Duration| format!("{:.1}s", duration.as_secs_f64());
+    say!(
+        events,
+        "\nThroughput ({} {}): {:.1} files/sec, {:.1} chunks/sec over {}",
+        timings.jobs,
+        if timings.jobs == 1 { "job" } else { "jobs" },
+        timings.files_per_sec(),
+        timings.chunks_per_sec(),
+        seconds(timings.elapsed)
+    );
+    say!(
+        events,
+        "  Parsing {}, embedding {}, database {} (summed over jobs)",
+        seconds(timings.parsing),
+        seconds(timings.embedding),
+        seconds(timings.database)
+    );
+}
+
+/// Report the files that failed to index and the final counts: as events
+/// with `--events`, else as a summary of the failures.
+fn report_index_result(events: Option<EventWriter>, stats: &IndexStats) {
+    const MAX_SHOWN: usize = 10;
+
+    if let Some(events) = events {
+        for failure in &stats.report.failures {
+            events.emit(&ProgressEvent::FileFailed(failure.clone()));
+        }
+        events.emit(&ProgressEvent::Complete {
+            counts: [
+                ("files", stats.files),
+                ("documents", stats.documents),
+                ("failed", stats.report.failures.len()),
+                ("generated_skipped", stats.generated_skipped),
+                ("oversized_skipped", stats.oversized_skipped),
+                ("signatures_only", stats.signatures_only),
+                ("chunks", stats.chunks),
+                ("structs", stats.structs),
+                ("functions", stats.functions),
+            ]
+            .into_iter()
+            .map(|(name, count)| (name.to_string(), count))
+            .collect(),
+            output: None,
+        });
+        return;
+    }
+
+    let report = &stats.report;
+    if report.is_empty() {
+        return;
+    }
+    println!("\n{} files failed to index:", report.failures.len());
+    for failure in report.failures.iter().take(MAX_SHOWN) {
+        match &failure.parser {
+            Some(parser) => println!("  - {} ({}): {}", failure.file, parser, failure.error),
+            None => println!("  - {}: {}", failure.file, failure.error),
+        }
+    }
+    if report.failures.len() > MAX_SHOWN {
+        println!("  ... and {} more", report.failures.len() - MAX_SHOWN);
+    }
+    println!("Run 'arq init --retry-failed' to index just these files again.");
+}
+
+/// Print one category of a `kg verify` report, listing the first few offenders.
+fn print_verify_section(label: &str, issues: &[VerifyIssue]) {
+    const MAX_SHOWN: usize = 5;
+
+    println!("  {}: {}", label, issues.len());
+    for issue in issues.iter().take(MAX_SHOWN) {
+        println!("    - {}", issue.detail);
+    }
+    if issues.len() > MAX_SHOWN {
+        println!("    ... and {} more", issues.len() - MAX_SHOWN);
+    }
+}
@@ -0,0 +1,322 @@
+//! Model Context Protocol server for the knowledge graph.
+//!
+//! `arq mcp` lets external agents (Claude Desktop, IDE assistants) use the
+//! indexed codebase as tools: semantic search, what a function calls, what
+//! calls it, and the functions of a file or directory. It speaks JSON-RPC
+//! 2.0 over stdio, one message per line, as MCP's stdio transport expects:
+//! requests arrive on stdin and responses leave on stdout, so anything meant
+//! for a person goes to stderr.
+//!
+//! An MCP client hands what it gets to a model, so code under
+//! `context.private_paths` is left out of every result, as it is from
+//! research prompts.
+//!
+//! # Module Structure
+//!
+//! - `tools` - Tool definitions and the knowledge graph queries behind them
+
+mod tools;
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use arq_core::knowledge::{KnowledgeGraph, PathBlocklist};
+
+use tools::ToolError;
+
+/// MCP revision implemented; clients asking for another get this one and
+/// decide whether to continue.
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC request, or a notification when it has no `id`.
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A JSON-RPC error, sent instead of a result.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Serves the tools of one project's knowledge graph.
+pub struct McpServer {
+    kg: KnowledgeGraph,
+    /// Project root that indexed paths are relative to
+    root: PathBuf,
+    /// Paths whose code is never sent to the client
+    private_paths: PathBlocklist,
+}
+
+impl McpServer {
+    pub fn new(kg: KnowledgeGraph, root: PathBuf, private_paths: PathBlocklist) -> Self {
+        Self {
+            kg,
+            root,
+            private_paths,
+        }
+    }
+
+    /// Answer requests from stdin until it closes.
+    pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut stdout = tokio::io::stdout();
+        eprintln!(
+            "Serving the knowledge graph of {} over MCP (stdio)",
+            self.root.display()
+        );
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line).await {
+                let mut out = serde_json::to_vec(&response)?;
+                out.push(b'\n');
+                stdout.write_all(&out).await?;
+                stdout.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The response to one message; none for notifications.
+    async fn handle(&self, line: &str) -> Option<Value> {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, format!("Invalid JSON-RPC message: {}", e));
+                return Some(response(Value::Null, Err(error)));
+            }
+        };
+        // Notifications (`notifications/initialized`, cancellations) need no reply
+        let id = request.id?;
+
+        let result = match request.method.as_str() {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "arq", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools::definitions() })),
+            "tools/call" => self.call_tool(request.params).await,
+            method => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method {}", method),
+            )),
+        };
+        Some(response(id, result))
+    }
+
+    /// Run a tool. Failed queries are reported in the result, so the model
+    /// sees them; unknown tools and bad arguments are protocol errors.
+    async fn call_tool(&self, params: Value) -> Result<Value, RpcError> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing tool name"))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let (text, is_error) = match self.call(name, arguments).await {
+            Ok(text) => (text, false),
+            Err(ToolError::Failed(message)) => (message, true),
+            Err(ToolError::UnknownTool(name)) => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("Unknown tool {}", name),
+                ))
+            }
+            Err(ToolError::InvalidArguments(e)) => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("Invalid arguments for {}: {}", name, e),
+                ))
+            }
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+}
+
+/// A JSON-RPC response carrying `result` or its error.
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What a message should get back.
+    enum Expected {
+        NoReply,
+        Result,
+        Error(i64),
+        /// A result reporting the tool's failure to the model
+        ToolFailure,
+    }
+
+    async fn server() -> McpServer {
+        McpServer::new(
+            KnowledgeGraph::open_in_memory().await.unwrap(),
+            PathBuf::from("."),
+            PathBlocklist::new(vec!["secrets/".to_string()]),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handle_messages() {
+        let server = server().await;
+        let cases = [
+            (
+                "initialize",
+                r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#,
+                Expected::Result,
+            ),
+            (
+                "initialized notification",
+                r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+                Expected::NoReply,
+            ),
+            (
+                "cancellation notification",
+                r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":1}}"#,
+                Expected::NoReply,
+            ),
+            (
+                "ping",
+                r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#,
+                Expected::Result,
+            ),
+            (
+                "tools/list",
+                r#"{"jsonrpc":"2.0","id":3,"method":"tools/list"}"#,
+                Expected::Result,
+            ),
+            (
+                "unknown method",
+                r#"{"jsonrpc":"2.0","id":4,"method":"resources/list"}"#,
+                Expected::Error(METHOD_NOT_FOUND),
+            ),
+            (
+                "unknown tool",
+                r#"{"jsonrpc":"2.0","id":5,"method":"tools/call","params":{"name":"rm_rf","arguments":{}}}"#,
+                Expected::Error(INVALID_PARAMS),
+            ),
+            (
+                "missing tool name",
+                r#"{"jsonrpc":"2.0","id":6,"method":"tools/call","params":{}}"#,
+                Expected::Error(INVALID_PARAMS),
+            ),
+            (
+                "invalid arguments",
+                r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"search_code","arguments":{}}}"#,
+                Expected::Error(INVALID_PARAMS),
+            ),
+            (
+                "blocked entity dependencies",
+                r#"{"jsonrpc":"2.0","id":8,"method":"tools/call","params":{"name":"get_dependencies","arguments":{"entity":"fn:secrets/keys.rs:load_key"}}}"#,
+                Expected::ToolFailure,
+            ),
+            (
+                "blocked entity impact",
+                r#"{"jsonrpc":"2.0","id":9,"method":"tools/call","params":{"name":"get_impact","arguments":{"entity":"fn:secrets/keys.rs:load_key"}}}"#,
+                Expected::ToolFailure,
+            ),
+            (
+                "visible entity",
+                r#"{"jsonrpc":"2.0","id":10,"method":"tools/call","params":{"name":"get_impact","arguments":{"entity":"fn:src/lib.rs:run"}}}"#,
+                Expected::Result,
+            ),
+            ("parse error", "not json", Expected::Error(PARSE_ERROR)),
+        ];
+
+        for (name, line, expected) in cases {
+            let reply = server.handle(line).await;
+            match expected {
+                Expected::NoReply => assert!(reply.is_none(), "{}: expected no reply", name),
+                Expected::Result => {
+                    let reply = reply.unwrap_or_else(|| panic!("{}: no reply", name));
+                    assert!(reply.get("error").is_none(), "{}: {}", name, reply);
+                    assert_ne!(reply["result"]["isError"], true, "{}: {}", name, reply);
+                }
+                Expected::Error(code) => {
+                    let reply = reply.unwrap_or_else(|| panic!("{}: no reply", name));
+                    assert_eq!(reply["error"]["code"], code, "{}: {}", name, reply);
+                }
+                Expected::ToolFailure => {
+                    let reply = reply.unwrap_or_else(|| panic!("{}: no reply", name));
+                    assert_eq!(reply["result"]["isError"], true, "{}: {}", name, reply);
+                    let text = reply["result"]["content"][0]["text"].as_str().unwrap();
+                    assert!(text.contains("private_paths"), "{}: {}", name, text);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_initialize_and_list_tools() {
+        let server = server().await;
+
+        let reply = server
+            .handle(r#"{"jsonrpc":"2.0","id":"init","method":"initialize"}"#)
+            .await
+            .unwrap();
+        assert_eq!(reply["id"], "init");
+        assert_eq!(reply["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert!(reply["result"]["capabilities"]["tools"].is_object());
+
+        let reply = server
+            .handle(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+        let names: Vec<&str> = reply["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "search_code",
+                "get_dependencies",
+                "get_impact",
+                "list_functions"
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Tools offered by `arq mcp`.
+//!
+//! Each tool wraps a knowledge graph query and returns its result as
+//! pretty-printed JSON text, which models read as well as structured
+//! content. Functions are named the way the graph stores calls: by name,
+//! `Type::method`, or an entity ID from `search_code`.
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use arq_core::knowledge::ontology::QualifiedName;
+use arq_core::knowledge::KnowledgeStore; // For the call graph
+
+use super::McpServer;
+
+/// Results `search_code` returns unless asked for another number.
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Most results `search_code` returns.
+const MAX_SEARCH_LIMIT: usize = 50;
+
+/// Most lines of context around a search result.
+const MAX_CONTEXT_LINES: usize = 50;
+
+/// Functions `list_functions` returns unless asked for another number.
+const DEFAULT_FUNCTION_LIMIT: usize = 100;
+
+/// Most functions `list_functions` returns.
+const MAX_FUNCTION_LIMIT: usize = 1000;
+
+/// Why a tool call produced no result.
+#[derive(Debug)]
+pub enum ToolError {
+    UnknownTool(String),
+    InvalidArguments(serde_json::Error),
+    /// The query failed; reported to the model rather than as a protocol error
+    Failed(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchArgs {
+    query: String,
+    limit: Option<usize>,
+    context_lines: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityArgs {
+    entity: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListFunctionsArgs {
+    path: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Names, descriptions and input schemas of the tools, for `tools/list`.
+pub fn definitions() -> Value {
+    json!([
+        {
+            "name": "search_code",
+            "description": "Semantic search over the indexed codebase. Returns matching code chunks with their file, line range, score, a preview and the ID of the function or type they belong to.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "What to look for, in natural language or as a symbol name" },
+                    "limit": { "type": "integer", "description": "Maximum results (default 10, at most 50)" },
+                    "context_lines": { "type": "integer", "description": "Lines of source to include around each result, with the enclosing function's signature (at most 50)" }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "get_dependencies",
+            "description": "Functions called by a function, from the knowledge graph's call edges.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "entity": { "type": "string", "description": "Function name, Type::method, or an entity ID from search_code" }
+                },
+                "required": ["entity"]
+            }
+        },
+        {
+            "name": "get_impact",
+            "description": "Functions that call a function: what a change to it may affect.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "entity": { "type": "string", "description": "Function name, Type::method, or an entity ID from search_code" }
+                },
+                "required": ["entity"]
+            }
+        },
+        {
+            "name": "list_functions",
+            "description": "Indexed functions with their file, line range and signature, optionally only those under a path.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Only functions in files under this path, relative to the project root (e.g. src/auth/)" },
+                    "limit": { "type": "integer", "description": "Maximum functions (default 100, at most 1000)" }
+                }
+            }
+        }
+    ])
+}
+
+impl McpServer {
+    /// Run the tool `name` with `arguments`, returning its result as text.
+    pub(super) async fn call(&self, name: &str, arguments: Value) -> Result<String, ToolError> {
+        let result = match name {
+            "search_code" => self.search_code(parse(arguments)?).await?,
+            "get_dependencies" => {
+                let args: EntityArgs = parse(arguments)?;
+                let calls = self
+                    .kg
+                    .get_dependencies(&args.entity)
+                    .await
+                    .map_err(failed)?;
+                let calls = self.visible(&args.entity, calls).await?;
+                json!({ "entity": args.entity, "calls": calls })
+            }
+            "get_impact" => {
+                let args: EntityArgs = parse(arguments)?;
+                let callers = self.kg.get_impact(&args.entity).await.map_err(failed)?;
+                let callers = self.visible(&args.entity, callers).await?;
+                json!({ "entity": args.entity, "called_by": callers })
+            }
+            "list_functions" => self.list_functions(parse(arguments)?).await?,
+            _ => return Err(ToolError::UnknownTool(name.to_string())),
+        };
+        serde_json::to_string_pretty(&result).map_err(failed)
+    }
+
+    async fn search_code(&self, args: SearchArgs) -> Result<Value, ToolError> {
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .clamp(1, MAX_SEARCH_LIMIT);
+        let mut results = self
+            .kg
+            .search_code_where(&args.query, limit, |r| !self.private_paths.blocks(&r.path))
+            .await
+            .map_err(failed)?;
+        if let Some(lines) = args.context_lines {
+            // Results are still useful without context
+            let _ = self
+                .kg
+                .add_search_context(&mut results, lines.min(MAX_CONTEXT_LINES), &self.root)
+                .await;
+        }
+        serde_json::to_value(results).map_err(failed)
+    }
+
+    async fn list_functions(&self, args: ListFunctionsArgs) -> Result<Value, ToolError> {
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_FUNCTION_LIMIT)
+            .clamp(1, MAX_FUNCTION_LIMIT);
+        let prefix = args.path.as_deref().unwrap_or("").trim_start_matches("./");
+        let functions = self.kg.list_all_functions().await.map_err(failed)?;
+        let listed: Vec<Value> = functions
+            .into_iter()
+            .filter(|f| f.file_path.starts_with(prefix) && !self.private_paths.blocks(&f.file_path))
+            .take(limit)
+            .map(|f| {
+                json!({
+                    "id": f.id,
+                    "name": f.qualified_name,
+                    "file": f.file_path,
+                    "start_line": f.start_line,
+                    "end_line": f.end_line,
+                    "signature": f.signature,
+                })
+            })
+            .collect();
+        Ok(Value::Array(listed))
+    }
+
+    /// `names` without functions defined only under private paths. Fails
+    /// if `entity` itself is one of them.
+    async fn visible(&self, entity: &str, names: Vec<String>) -> Result<Vec<String>, ToolError> {
+        if self.private_paths.is_empty() {
+            return Ok(names);
+        }
+        let functions = self.kg.list_all_functions().await.map_err(failed)?;
+        let blocked: HashSet<&str> = self.private_paths.blocked_names(&functions);
+        let qualified = QualifiedName::from_entity_id(entity);
+        if self.private_paths.blocks_entity(entity) || blocked.contains(qualified.name()) {
+            return Err(ToolError::Failed(format!(
+                "{} is under context.private_paths and is never sent to the client",
+                entity
+            )));
+        }
+        Ok(names
+            .into_iter()
+            .filter(|name| !blocked.contains(name.as_str()))
+            .collect())
+    }
+}
+
+/// Tool arguments from their JSON object.
+fn parse<T: serde::de::DeserializeOwned>(arguments: Value) -> Result<T, ToolError> {
+    serde_json::from_value(arguments).map_err(ToolError::InvalidArguments)
+}
+
+fn failed(e: impl std::fmt::Display) -> ToolError {
+    ToolError::Failed(e.to_string())
+}
@@ -5,9 +5,12 @@
 
 use std::collections::{HashMap, HashSet};
 
-use arq_core::knowledge::KnowledgeGraph;
+use arq_core::knowledge::ontology::nodes::ComplexityMetrics;
+use arq_core::knowledge::{KnowledgeGraph, PathBlocklist};
 
-use super::models::{EdgeAttributes, GraphData, GraphEdge, GraphNode, NodeAttributes};
+use super::models::{
+    EdgeAttributes, GraphData, GraphDelta, GraphEdge, GraphNode, Hotspot, NodeAttributes,
+};
 
 // =============================================================================
 // Node Styling (Language-Agnostic)
@@ -22,6 +25,7 @@ fn get_category_color(category: &str) -> &'static str {
         "trait" | "interface" => "#9a6700",     // Yellow/Orange
         "enum" => "#cf222e",                    // Red
         "impl" | "implementation" => "#8250df", // Purple
+        "file" => "#bc4c00",                    // Brown
         _ => "#57606a",                         // Gray (default)
     }
 }
@@ -30,7 +34,7 @@ fn get_category_color(category: &str) -> &'static str {
 fn get_category_size(category: &str) -> u32 {
     match category {
         "struct" | "class" | "trait" | "interface" | "enum" => 12,
-        "impl" | "implementation" => 10,
+        "impl" | "implementation" | "file" => 10,
         "function" | "method" => 8,
         _ => 6,
     }
@@ -47,6 +51,11 @@ pub struct GraphBuilder {
     seen_keys: HashSet<String>,
     seen_edges: HashSet<String>,
     id_to_key: HashMap<String, String>,
+    /// Distinct callers and callees per node key, from call edges.
+    fan_in: HashMap<String, u32>,
+    fan_out: HashMap<String, u32>,
+    /// Paths whose nodes are left out, along with their edges.
+    blocklist: PathBlocklist,
 }
 
 impl GraphBuilder {
@@ -58,9 +67,18 @@ impl GraphBuilder {
             seen_keys: HashSet::new(),
             seen_edges: HashSet::new(),
             id_to_key: HashMap::new(),
+            fan_in: HashMap::new(),
+            fan_out: HashMap::new(),
+            blocklist: PathBlocklist::default(),
         }
     }
 
+    /// Leave out nodes in files matching `blocklist`.
+    pub fn with_blocklist(mut self, blocklist: PathBlocklist) -> Self {
+        self.blocklist = blocklist;
+        self
+    }
+
     /// Build graph data from a knowledge graph.
     pub async fn build_from_kg(self, kg: &KnowledgeGraph) -> GraphData {
         let mut builder = self;
@@ -79,6 +97,13 @@ impl GraphBuilder {
         builder.load_call_edges(kg).await;
         builder.load_impl_trait_edges(kg).await;
         builder.load_method_edges(kg).await;
+        builder.load_co_change_edges(kg).await;
+
+        // Annotate nodes with call degrees for the complexity heatmap
+        for node in &mut builder.nodes {
+            node.attributes.fan_in = builder.fan_in.get(&node.key).copied().unwrap_or(0);
+            node.attributes.fan_out = builder.fan_out.get(&node.key).copied().unwrap_or(0);
+        }
 
         GraphData {
             nodes: builder.nodes,
@@ -92,13 +117,16 @@ impl GraphBuilder {
             for func in functions {
                 let key = Self::make_key("fn", &func.file_path, func.start_line, &func.name);
                 self.add_node(
-                    key,
+                    key.clone(),
                     func.name,
                     "function",
                     Some(func.file_path),
                     Some(func.start_line),
                     Some(func.end_line),
                 );
+                if let Some(ref metrics) = func.complexity {
+                    self.set_complexity(&key, metrics);
+                }
             }
         }
     }
@@ -292,6 +320,65 @@ impl GraphBuilder {
         }
     }
 
+    /// Load co-change edges mined from git history between file nodes.
+    ///
+    /// File nodes are added only for files with co-changes, linked to the
+    /// entities they contain so they sit next to their code in the layout.
+    async fn load_co_change_edges(&mut self, kg: &KnowledgeGraph) {
+        let Ok(pairs) = kg.list_co_changes().await else {
+            return;
+        };
+
+        for pair in pairs {
+            if self.blocklist.blocks(&pair.file_a) || self.blocklist.blocks(&pair.file_b) {
+                continue;
+            }
+            let file_a = self.add_file_node(&pair.file_a);
+            let file_b = self.add_file_node(&pair.file_b);
+            let edge_key = format!("{}:co_change:{}", file_a, file_b);
+            if self.seen_edges.insert(edge_key) {
+                self.edges.push(GraphEdge {
+                    source: file_a,
+                    target: file_b,
+                    attributes: Some(EdgeAttributes {
+                        relationship: "co_change".to_string(),
+                        weight: Some(pair.weight),
+                        count: Some(pair.count),
+                    }),
+                });
+            }
+        }
+    }
+
+    /// Add a node for `path` and `contains` edges to its entities, returning
+    /// the node key.
+    fn add_file_node(&mut self, path: &str) -> String {
+        let key = format!("file:{}", path);
+        if self.seen_keys.contains(&key) {
+            return key;
+        }
+
+        let entities: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|n| n.attributes.file.as_deref() == Some(path))
+            .map(|n| n.key.clone())
+            .collect();
+        let label = path.rsplit('/').next().unwrap_or(path).to_string();
+        self.add_node(
+            key.clone(),
+            label,
+            "file",
+            Some(path.to_string()),
+            None,
+            None,
+        );
+        for entity in entities {
+            self.add_edge(key.clone(), entity, "contains");
+        }
+        key
+    }
+
     // =========================================================================
     // Helper Methods
     // =========================================================================
@@ -332,18 +419,24 @@ impl GraphBuilder {
             let edge_key = format!("{}:{}:{}", source, relationship, target);
 
             if self.seen_edges.insert(edge_key) {
+                if relationship == "calls" {
+                    *self.fan_in.entry(target.clone()).or_default() += 1;
+                    *self.fan_out.entry(source.clone()).or_default() += 1;
+                }
                 self.edges.push(GraphEdge {
                     source,
                     target,
                     attributes: Some(EdgeAttributes {
                         relationship: relationship.to_string(),
+                        weight: None,
+                        count: None,
                     }),
                 });
             }
         }
     }
 
-    /// Add a node if not already present.
+    /// Add a node if not already present and its file isn't blocked.
     fn add_node(
         &mut self,
         key: String,
@@ -353,6 +446,9 @@ impl GraphBuilder {
         start_line: Option<u32>,
         end_line: Option<u32>,
     ) {
+        if file.as_deref().is_some_and(|f| self.blocklist.blocks(f)) {
+            return;
+        }
         if self.seen_keys.insert(key.clone()) {
             self.nodes.push(GraphNode {
                 key,
@@ -364,10 +460,145 @@ impl GraphBuilder {
                     file,
                     start_line,
                     end_line,
+                    cyclomatic: None,
+                    cognitive: None,
+                    loc: None,
+                    fan_in: 0,
+                    fan_out: 0,
                 },
             });
         }
     }
+
+    /// Attach complexity metrics to the most recently added node with `key`.
+    fn set_complexity(&mut self, key: &str, metrics: &ComplexityMetrics) {
+        if let Some(node) = self.nodes.last_mut().filter(|n| n.key == key) {
+            node.attributes.cyclomatic = Some(metrics.cyclomatic);
+            node.attributes.cognitive = metrics.cognitive;
+            node.attributes.loc = Some(metrics.loc);
+        }
+    }
+}
+
+// =============================================================================
+// Live Updates
+// =============================================================================
+
+/// What changed from `previous` to `current`, attributed to `files`.
+pub fn diff(previous: &GraphData, current: &GraphData, files: Vec<String>) -> GraphDelta {
+    let previous_nodes: HashMap<&str, &GraphNode> =
+        previous.nodes.iter().map(|n| (n.key.as_str(), n)).collect();
+    let current_keys: HashSet<&str> = current.nodes.iter().map(|n| n.key.as_str()).collect();
+    let previous_edges: HashSet<String> = previous.edges.iter().map(edge_id).collect();
+    let current_edges: HashSet<String> = current.edges.iter().map(edge_id).collect();
+
+    GraphDelta {
+        files,
+        upserted_nodes: current
+            .nodes
+            .iter()
+            .filter(|n| previous_nodes.get(n.key.as_str()) != Some(n))
+            .cloned()
+            .collect(),
+        removed_nodes: previous
+            .nodes
+            .iter()
+            .filter(|n| !current_keys.contains(n.key.as_str()))
+            .map(|n| n.key.clone())
+            .collect(),
+        added_edges: current
+            .edges
+            .iter()
+            .filter(|e| !previous_edges.contains(&edge_id(e)))
+            .cloned()
+            .collect(),
+        removed_edges: previous
+            .edges
+            .iter()
+            .filter(|e| !current_edges.contains(&edge_id(e)))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Identity of an edge: its endpoints and relationship.
+fn edge_id(edge: &GraphEdge) -> String {
+    let relationship = edge
+        .attributes
+        .as_ref()
+        .map_or("", |a| a.relationship.as_str());
+    format!("{}:{}:{}", edge.source, relationship, edge.target)
+}
+
+// =============================================================================
+// Hotspots
+// =============================================================================
+
+/// Rank functions by risk: cyclomatic complexity weighted by fan-in.
+///
+/// A complex function with many callers is both likely to contain bugs and
+/// costly to change, so `risk = cyclomatic * (1 + ln(1 + fan_in))`.
+pub fn hotspots(graph: &GraphData) -> Vec<Hotspot> {
+    let mut hotspots: Vec<Hotspot> = graph
+        .nodes
+        .iter()
+        .filter_map(|node| {
+            let attrs = &node.attributes;
+            let cyclomatic = attrs.cyclomatic?;
+            Some(Hotspot {
+                key: node.key.clone(),
+                name: attrs.label.clone(),
+                file: attrs.file.clone(),
+                start_line: attrs.start_line,
+                cyclomatic,
+                cognitive: attrs.cognitive,
+                loc: attrs.loc,
+                fan_in: attrs.fan_in,
+                fan_out: attrs.fan_out,
+                risk: risk_score(cyclomatic, attrs.fan_in),
+            })
+        })
+        .collect();
+
+    hotspots.sort_by(|a, b| b.risk.total_cmp(&a.risk).then_with(|| a.key.cmp(&b.key)));
+    hotspots
+}
+
+/// Risk score of a function from its complexity and number of callers.
+fn risk_score(cyclomatic: u32, fan_in: u32) -> f64 {
+    let score = cyclomatic as f64 * (1.0 + (1.0 + fan_in as f64).ln());
+    (score * 100.0).round() / 100.0
+}
+
+/// Render hotspots as CSV, one row per function.
+pub fn hotspots_csv(hotspots: &[Hotspot]) -> String {
+    let mut csv =
+        String::from("name,file,start_line,cyclomatic,cognitive,loc,fan_in,fan_out,risk\n");
+    for h in hotspots {
+        let opt = |v: Option<u32>| v.map(|v| v.to_string()).unwrap_or_default();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&h.name),
+            csv_field(h.file.as_deref().unwrap_or_default()),
+            opt(h.start_line),
+            h.cyclomatic,
+            opt(h.cognitive),
+            opt(h.loc),
+            h.fan_in,
+            h.fan_out,
+            h.risk
+        ));
+    }
+    csv
+}
+
+/// Quote a CSV field if it contains separators, quotes or newlines.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
 }
 
 impl Default for GraphBuilder {
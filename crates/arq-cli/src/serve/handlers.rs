@@ -2,21 +2,32 @@
 //!
 //! This module contains the request handlers for each API endpoint.
 //! Handlers are kept thin, delegating business logic to other modules.
+//!
+//! Every handler returning entities, edges or code drops what lies under
+//! the server's blocked paths (see [`AppState::blocklist`]).
 
 use std::sync::Arc;
 
 use axum::{
     extract::{Path, Query, State},
-    response::Html,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
     Json,
 };
 
-use super::graph::GraphBuilder;
-use super::models::{GraphData, NodeDetails, SearchQuery, SearchResult};
+use super::graph::{hotspots, hotspots_csv};
+use super::models::{
+    EdgePage, EdgesQuery, GrepQuery, Hotspot, NodeDetails, SavedView, SearchQuery, SearchResult,
+    MAX_GREP_HITS, MAX_SEARCH_CONTEXT_LINES,
+};
 use super::templates;
 use super::AppState;
 
-use arq_core::knowledge::KnowledgeStore; // For search_code method
+use arq_core::knowledge::{parse_as_of, GrepHit, LanguageStats};
+use arq_core::KnowledgeError;
+
+/// Largest page `/api/edges` returns.
+const MAX_EDGES_PAGE: usize = 1000;
 
 // =============================================================================
 // Page Handlers
@@ -40,17 +51,52 @@ pub async fn index(State(state): State<Arc<AppState>>) -> Html<String> {
 ///   "edges": [{"source": "fn:...", "target": "fn:..."}]
 /// }
 /// ```
-pub async fn api_graph(State(state): State<Arc<AppState>>) -> Json<GraphData> {
+pub async fn api_graph(State(state): State<Arc<AppState>>) -> Response {
+    let kg = state.kg.read().await;
+    let graph_data = state.graph(&kg).await;
+    Json(&*graph_data).into_response()
+}
+
+/// GET `/api/hotspots` - Functions ranked by complexity weighted by fan-in.
+pub async fn api_hotspots(State(state): State<Arc<AppState>>) -> Json<Vec<Hotspot>> {
+    let kg = state.kg.read().await;
+    let graph_data = state.graph(&kg).await;
+    Json(hotspots(&graph_data))
+}
+
+/// GET `/api/hotspots.csv` - Hotspots as a downloadable CSV file.
+pub async fn api_hotspots_csv(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let kg = state.kg.read().await;
+    let graph_data = state.graph(&kg).await;
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"arq-hotspots.csv\"",
+            ),
+        ],
+        hotspots_csv(&hotspots(&graph_data)),
+    )
+}
+
+/// GET `/api/languages` - Files, line counts and functions per language,
+/// over the files outside the blocked paths.
+pub async fn api_languages(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<LanguageStats>>, (StatusCode, String)> {
     let kg = state.kg.read().await;
-    let graph_data = GraphBuilder::new().build_from_kg(&kg).await;
-    Json(graph_data)
+    kg.language_stats_where(|path| !state.blocklist.blocks(path))
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
 /// GET `/api/node/{id}` - Get details for a specific node.
 ///
 /// The node ID format is `type:file:line:name` (e.g., `fn:src/main.rs:42:process`).
 pub async fn api_node(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> Json<Option<NodeDetails>> {
     // Parse the node ID to extract type and remaining parts
@@ -65,6 +111,10 @@ pub async fn api_node(
     } else {
         return Json(None);
     };
+    let file = rest.split(':').next().unwrap_or(rest);
+    if state.blocklist.blocks(file) {
+        return Json(None);
+    }
 
     // Extract label from the rest (file:line:name -> name)
     let label = rest.rsplit(':').next().unwrap_or(rest);
@@ -86,16 +136,27 @@ pub async fn api_node(
 /// Query parameters:
 /// - `q`: Search query string (required)
 /// - `limit`: Maximum results (default: 20)
+/// - `context`: Lines of source to include before and after each hit, with
+///   the enclosing function's signature (at most 50)
 pub async fn api_search(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SearchQuery>,
 ) -> Json<Vec<SearchResult>> {
     let kg = state.kg.read().await;
 
-    let results = kg
-        .search_code(&params.q, params.limit)
+    let mut results = kg
+        .search_code_where(&params.q, params.limit, |r| {
+            !state.blocklist.blocks(&r.path)
+        })
         .await
         .unwrap_or_default();
+    if let Some(lines) = params.context {
+        let lines = lines.min(MAX_SEARCH_CONTEXT_LINES);
+        // Hits are still useful without context
+        let _ = kg
+            .add_search_context(&mut results, lines, &state.project_path)
+            .await;
+    }
 
     let search_results: Vec<SearchResult> = results
         .into_iter()
@@ -112,9 +173,187 @@ pub async fn api_search(
                 node_type: node_type.to_string(),
                 file: Some(r.path),
                 score: r.score,
+                start_line: r.start_line,
+                end_line: r.end_line,
+                context: r.context,
             }
         })
         .collect();
 
     Json(search_results)
 }
+
+/// GET `/api/edges` - One page of edges of any relation.
+///
+/// Query parameters:
+/// - `type`: Relation table, e.g. `contains`, `imports`, `extends` (required)
+/// - `from`: Only edges leaving this entity ID
+/// - `offset`: Edges to skip (default: 0)
+/// - `limit`: Maximum edges (default: 100, at most 1000)
+/// - `as_of`: List `calls` edges in effect at this time, to show how
+///   dependencies evolved (`YYYY-MM-DD` or RFC 3339)
+pub async fn api_edges(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<EdgesQuery>,
+) -> Result<Json<EdgePage>, (StatusCode, String)> {
+    let limit = params.limit.min(MAX_EDGES_PAGE);
+    let as_of = params
+        .as_of
+        .as_deref()
+        .map(parse_as_of)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let kg = state.kg.read().await;
+
+    // Fetch one extra edge to tell whether another page follows
+    let mut edges = kg
+        .list_edges(
+            &params.relation,
+            params.from.as_deref(),
+            params.offset,
+            limit + 1,
+            as_of,
+        )
+        .await
+        .map_err(|e| match e {
+            KnowledgeError::UnknownRelation(_) | KnowledgeError::NoHistory(_) => {
+                (StatusCode::BAD_REQUEST, e.to_string())
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+    let has_more = edges.len() > limit;
+    edges.truncate(limit);
+    // Blocked edges leave gaps in the page rather than shifting offsets
+    edges.retain(|e| {
+        !state.blocklist.blocks_entity(&e.source) && !state.blocklist.blocks_entity(&e.target)
+    });
+
+    Ok(Json(EdgePage {
+        relation: params.relation,
+        offset: params.offset,
+        edges,
+        has_more,
+    }))
+}
+
+/// GET `/api/grep` - Regex search over indexed files.
+///
+/// Query parameters:
+/// - `pattern`: Regular expression matched against each line (required)
+/// - `expand`: Attach the enclosing function and its direct callers to each hit
+/// - `limit`: Maximum matching lines (default: 100, at most 1000)
+pub async fn api_grep(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<GrepQuery>,
+) -> Result<Json<Vec<GrepHit>>, (StatusCode, String)> {
+    let kg = state.kg.read().await;
+    let mut hits = kg
+        .grep(
+            &params.pattern,
+            &state.project_path,
+            params.expand,
+            params.limit.min(MAX_GREP_HITS),
+        )
+        .await
+        .map_err(|e| match e {
+            KnowledgeError::InvalidPattern(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    if !state.blocklist.is_empty() {
+        hits.retain(|hit| !state.blocklist.blocks(&hit.path));
+        if params.expand {
+            // Callers are listed by name; drop those only defined in blocked files
+            let functions = kg
+                .list_all_functions()
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let blocked = state.blocklist.blocked_names(&functions);
+            for entity in hits.iter_mut().filter_map(|hit| hit.entity.as_mut()) {
+                entity
+                    .callers
+                    .retain(|caller| !blocked.contains(caller.as_str()));
+            }
+        }
+    }
+    Ok(Json(hits))
+}
+
+/// GET `/api/views` - List saved views, sorted by name.
+pub async fn api_views(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<SavedView>>, (StatusCode, String)> {
+    state.views.list().await.map(Json).map_err(view_error)
+}
+
+/// PUT `/api/views` - Save a view, replacing any view with the same name.
+///
+/// Returns the updated list of views.
+pub async fn api_save_view(
+    State(state): State<Arc<AppState>>,
+    Json(view): Json<SavedView>,
+) -> Result<Json<Vec<SavedView>>, (StatusCode, String)> {
+    state.views.save(view).await.map(Json).map_err(view_error)
+}
+
+/// DELETE `/api/views/{name}` - Delete a saved view.
+///
+/// Returns the remaining views.
+pub async fn api_delete_view(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<SavedView>>, (StatusCode, String)> {
+    state
+        .views
+        .delete(&name)
+        .await
+        .map(Json)
+        .map_err(view_error)
+}
+
+fn view_error(error: std::io::Error) -> (StatusCode, String) {
+    let status = match error.kind() {
+        std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        std::io::ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use arq_core::knowledge::PathBlocklist;
+    use arq_core::{KnowledgeGraph, KnowledgeStore};
+    use tokio::sync::{broadcast, Mutex, RwLock};
+
+    use crate::serve::views::ViewStore;
+
+    async fn state() -> Arc<AppState> {
+        Arc::new(AppState {
+            kg: Arc::new(RwLock::new(KnowledgeGraph::open_in_memory().await.unwrap())),
+            project_path: PathBuf::from("."),
+            views: ViewStore::new(PathBuf::from("views.json")),
+            live: broadcast::channel(1).0,
+            blocklist: PathBlocklist::default(),
+            graph_cache: Mutex::new(None),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_graph_is_built_once_per_index_generation() {
+        let state = state().await;
+        let response = api_graph(State(Arc::clone(&state))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let kg = state.kg.read().await;
+        let cached = state.graph(&kg).await;
+        assert!(Arc::ptr_eq(&cached, &state.graph(&kg).await));
+
+        kg.remove_file("src/gone.rs").await.unwrap();
+        assert!(!Arc::ptr_eq(&cached, &state.graph(&kg).await));
+    }
+}
@@ -0,0 +1,134 @@
+//! Live graph updates over WebSocket.
+//!
+//! Every change to the index is picked up from the knowledge graph's update
+//! channel, the graph is rebuilt and compared with the previous version, and
+//! the difference is pushed to all `/ws` clients so the browser patches its
+//! graph in place. With `--watch`, the server also polls the project and
+//! re-indexes changed files itself.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+
+use arq_core::knowledge::ChangeScanner;
+
+use super::graph::diff;
+use super::models::LiveMessage;
+use super::AppState;
+
+/// Pause between scans of the project for changed files.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Rebuild the graph after each index update and broadcast the delta.
+pub async fn publish_deltas(state: Arc<AppState>) {
+    let (mut updates, mut previous) = {
+        let kg = state.kg.read().await;
+        (kg.subscribe(), state.graph(&kg).await)
+    };
+
+    loop {
+        let mut files = match updates.recv().await {
+            Ok(update) => update.files,
+            Err(RecvError::Lagged(_)) => Vec::new(),
+            Err(RecvError::Closed) => return,
+        };
+        // Updates that arrived while the last delta was built share one rebuild
+        loop {
+            match updates.try_recv() {
+                Ok(update) => files.extend(update.files),
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        files.retain(|file| !state.blocklist.blocks(file));
+        files.sort();
+        files.dedup();
+
+        let current = state.graph(&*state.kg.read().await).await;
+        let delta = diff(&previous, &current, files);
+        previous = current;
+        if !delta.is_empty() {
+            // Fails only when no client is connected
+            let _ = state.live.send(Arc::new(LiveMessage::Delta(delta)));
+        }
+    }
+}
+
+/// Poll the project for changed files and re-index them.
+pub async fn watch_project(state: Arc<AppState>) {
+    let root = state.project_path.clone();
+    let scan_root = root.clone();
+    let Ok(mut scanner) = tokio::task::spawn_blocking(move || ChangeScanner::new(&scan_root)).await
+    else {
+        return;
+    };
+
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Ok((returned, changes)) = tokio::task::spawn_blocking(move || {
+            let changes = scanner.scan();
+            (scanner, changes)
+        })
+        .await
+        else {
+            return;
+        };
+        scanner = returned;
+        if changes.is_empty() {
+            continue;
+        }
+
+        match state.kg.read().await.apply_changes(&root, &changes).await {
+            Ok(stats) => {
+                if stats.files > 0 {
+                    println!("Re-indexed {} changed files", stats.files);
+                }
+                for failure in &stats.report.failures {
+                    eprintln!("Failed to index {}: {}", failure.file, failure.error);
+                }
+            }
+            Err(e) => eprintln!("Re-indexing failed: {}", e),
+        }
+    }
+}
+
+/// GET `/ws` - Streams [`LiveMessage`]s as JSON text frames.
+pub async fn ws(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
+    ws.on_upgrade(move |socket| stream_updates(socket, state))
+}
+
+async fn stream_updates(mut socket: WebSocket, state: Arc<AppState>) {
+    let mut messages = state.live.subscribe();
+    loop {
+        tokio::select! {
+            message = messages.recv() => {
+                let message = match message {
+                    Ok(message) => message,
+                    // Deltas were dropped, so the client's graph can't be patched
+                    Err(RecvError::Lagged(_)) => Arc::new(LiveMessage::Reload),
+                    Err(RecvError::Closed) => return,
+                };
+                let Ok(json) = serde_json::to_string(&*message) else {
+                    continue;
+                };
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    return;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                // Clients have nothing to say; pings are answered by axum
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
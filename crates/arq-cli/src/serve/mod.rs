@@ -4,27 +4,45 @@
 //! and Graphology (graph data structure) with ForceAtlas2 layout algorithm
 //! to explore the indexed codebase structure and relationships.
 //!
+//! Entities under `[serve] blocked_paths` are left out of every response,
+//! including live updates, so an instance can be shared with a team without
+//! exposing sensitive code.
+//!
 //! # Module Structure
 //!
 //! - `handlers` - HTTP route handlers
+//! - `live` - Live graph updates over WebSocket
 //! - `models` - API request/response types (DTOs)
 //! - `graph` - Graph building logic
 //! - `templates` - HTML/CSS/JS template rendering
+//! - `views` - Named views persisted in the project data directory
 
 mod graph;
 mod handlers;
+mod live;
 mod models;
 mod templates;
+mod views;
 
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use axum::{routing::get, Router};
-use tokio::sync::RwLock;
+use axum::{
+    routing::{delete, get},
+    Router,
+};
+use tokio::sync::{broadcast, Mutex, RwLock};
 use tower_http::cors::{Any, CorsLayer};
 
-use arq_core::knowledge::KnowledgeGraph;
+use arq_core::knowledge::indexer::GeneratedCodePolicy;
+use arq_core::knowledge::{KnowledgeGraph, PathBlocklist};
+use arq_core::{KnowledgeConfig, ParsersConfig};
+
+use models::{GraphData, LiveMessage};
+
+/// Live messages buffered per WebSocket client before it has to reload.
+const LIVE_CHANNEL_CAPACITY: usize = 16;
 
 // =============================================================================
 // Application State
@@ -36,6 +54,37 @@ pub struct AppState {
     pub kg: Arc<RwLock<KnowledgeGraph>>,
     /// Path to the project being visualized.
     pub project_path: PathBuf,
+    /// Saved named views.
+    pub views: views::ViewStore,
+    /// Graph deltas for WebSocket clients.
+    pub live: broadcast::Sender<Arc<LiveMessage>>,
+    /// Paths whose entities are never sent to clients.
+    pub blocklist: PathBlocklist,
+    /// Last graph built, with the index generation it was built from.
+    pub graph_cache: Mutex<Option<(u64, Arc<GraphData>)>>,
+}
+
+impl AppState {
+    /// A graph builder that leaves out blocked paths.
+    pub fn graph_builder(&self) -> graph::GraphBuilder {
+        graph::GraphBuilder::new().with_blocklist(self.blocklist.clone())
+    }
+
+    /// The graph of `kg`, built once per index generation.
+    pub async fn graph(&self, kg: &KnowledgeGraph) -> Arc<GraphData> {
+        let generation = kg.generation();
+        let mut cache = self.graph_cache.lock().await;
+        if let Some((built, graph)) = cache.as_ref() {
+            if *built == generation {
+                return Arc::clone(graph);
+            }
+        }
+
+        // A change made while building only makes the next request rebuild
+        let graph = Arc::new(self.graph_builder().build_from_kg(kg).await);
+        *cache = Some((generation, Arc::clone(&graph)));
+        graph
+    }
 }
 
 // =============================================================================
@@ -52,6 +101,18 @@ pub struct ServeConfig {
     pub project_path: PathBuf,
     /// Path to the knowledge graph database.
     pub db_path: PathBuf,
+    /// Directory where saved views are stored (the project data directory).
+    pub data_dir: PathBuf,
+    /// Where the embedding model is cached.
+    pub model_cache: PathBuf,
+    /// Whether to re-index changed files while serving.
+    pub watch: bool,
+    /// Indexing settings used when re-indexing.
+    pub knowledge: KnowledgeConfig,
+    /// External parsers used when re-indexing.
+    pub parsers: ParsersConfig,
+    /// Paths whose entities and chunks are omitted from responses.
+    pub blocklist: PathBlocklist,
 }
 
 impl Default for ServeConfig {
@@ -60,7 +121,13 @@ impl Default for ServeConfig {
             port: 3333,
             open_browser: true,
             project_path: PathBuf::from("."),
-            db_path: PathBuf::from(".arq/knowledge"),
+            db_path: PathBuf::from(".arq/data/knowledge.db"),
+            data_dir: PathBuf::from(".arq/data"),
+            model_cache: PathBuf::from(".arq/data/cache"),
+            watch: false,
+            knowledge: KnowledgeConfig::default(),
+            parsers: ParsersConfig::default(),
+            blocklist: PathBlocklist::default(),
         }
     }
 }
@@ -72,13 +139,43 @@ impl Default for ServeConfig {
 /// Start the visualization server.
 pub async fn start_server(config: ServeConfig) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize knowledge graph from the database
-    let kg = KnowledgeGraph::new(&config.db_path).await?;
+    let kg = KnowledgeGraph::new(&config.db_path)
+        .await?
+        .with_root(config.project_path.clone())
+        .with_insert_batch_size(config.knowledge.insert_batch_size)
+        .with_history_retention(config.knowledge.history_retention_days)
+        .with_embedder_daemon(config.knowledge.embedder_daemon.clone())
+        .with_model_cache(config.model_cache.clone())
+        .with_generated_code(GeneratedCodePolicy::from_config(&config.knowledge))
+        .with_size_limit(
+            config.knowledge.max_file_size,
+            config.knowledge.oversized_files,
+        )
+        .with_docs(config.knowledge.docs.clone())
+        .with_parsers(&config.parsers)
+        .with_languages(
+            config.knowledge.disabled_languages.clone(),
+            config.knowledge.extension_overrides.clone(),
+        );
+    if config.knowledge.warmup {
+        // Best effort; without it the first search is just slower
+        let _ = kg.warm_up_index().await;
+    }
 
     let state = Arc::new(AppState {
         kg: Arc::new(RwLock::new(kg)),
         project_path: config.project_path.clone(),
+        views: views::ViewStore::new(config.data_dir.join(views::VIEWS_FILE)),
+        live: broadcast::channel(LIVE_CHANNEL_CAPACITY).0,
+        blocklist: config.blocklist.clone(),
+        graph_cache: Mutex::new(None),
     });
 
+    tokio::spawn(live::publish_deltas(Arc::clone(&state)));
+    if config.watch {
+        tokio::spawn(live::watch_project(Arc::clone(&state)));
+    }
+
     // Build router with API endpoints
     let app = Router::new()
         // Main page - Sigma.js graph visualization
@@ -87,6 +184,18 @@ pub async fn start_server(config: ServeConfig) -> Result<(), Box<dyn std::error:
         .route("/api/graph", get(handlers::api_graph))
         .route("/api/node/{id}", get(handlers::api_node))
         .route("/api/search", get(handlers::api_search))
+        .route("/api/edges", get(handlers::api_edges))
+        .route("/api/grep", get(handlers::api_grep))
+        .route("/api/hotspots", get(handlers::api_hotspots))
+        .route("/api/hotspots.csv", get(handlers::api_hotspots_csv))
+        .route("/api/languages", get(handlers::api_languages))
+        .route(
+            "/api/views",
+            get(handlers::api_views).put(handlers::api_save_view),
+        )
+        .route("/api/views/{name}", delete(handlers::api_delete_view))
+        // Live graph updates
+        .route("/ws", get(live::ws))
         // CORS for API access
         .layer(CorsLayer::new().allow_origin(Any))
         .with_state(state);
@@ -96,6 +205,12 @@ pub async fn start_server(config: ServeConfig) -> Result<(), Box<dyn std::error:
 
     println!("Starting Arq visualization server...");
     println!("Dashboard: {}", url);
+    if config.watch {
+        println!("Watching {} for changes", config.project_path.display());
+    }
+    if !config.blocklist.is_empty() {
+        println!("Hiding entities under blocked paths");
+    }
     println!("Press Ctrl+C to stop\n");
 
     // Open browser if requested
@@ -3,6 +3,9 @@
 //! These are Data Transfer Objects (DTOs) that define the shape of
 //! JSON responses sent to the frontend.
 
+use std::collections::BTreeMap;
+
+use arq_core::knowledge::{EdgeInfo, SearchContext};
 use serde::{Deserialize, Serialize};
 
 // =============================================================================
@@ -17,7 +20,7 @@ pub struct GraphData {
 }
 
 /// A node in the graph visualization.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct GraphNode {
     /// Unique identifier for the node.
     pub key: String,
@@ -26,7 +29,7 @@ pub struct GraphNode {
 }
 
 /// Node attributes for rendering and display.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct NodeAttributes {
     /// Display label for the node.
     pub label: String,
@@ -46,10 +49,23 @@ pub struct NodeAttributes {
     /// End line number in source.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub end_line: Option<u32>,
+    /// Cyclomatic complexity (functions only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cyclomatic: Option<u32>,
+    /// Cognitive complexity (functions only, when the parser computes it).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cognitive: Option<u32>,
+    /// Lines of code (functions only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loc: Option<u32>,
+    /// Number of distinct callers (incoming call edges).
+    pub fan_in: u32,
+    /// Number of distinct callees (outgoing call edges).
+    pub fan_out: u32,
 }
 
 /// An edge (relationship) in the graph visualization.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct GraphEdge {
     /// Source node key.
     pub source: String,
@@ -61,11 +77,54 @@ pub struct GraphEdge {
 }
 
 /// Edge attributes for rendering.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct EdgeAttributes {
     /// Type of relationship (e.g., "Direct", "Method").
     /// Note: Using "relationship" instead of "type" to avoid conflict with Sigma's edge type.
     pub relationship: String,
+    /// Strength of a `co_change` edge: share of the commits touching either
+    /// file that touched both.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f32>,
+    /// Commits changing both files of a `co_change` edge.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+// =============================================================================
+// Live Update Models (for `/ws`)
+// =============================================================================
+
+/// A message pushed to WebSocket clients.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage {
+    /// The graph changed; apply the delta.
+    Delta(GraphDelta),
+    /// Updates were missed; fetch the whole graph again.
+    Reload,
+}
+
+/// Difference between two versions of the graph.
+#[derive(Debug, Default, Serialize)]
+pub struct GraphDelta {
+    /// Files whose re-indexing caused the change; empty after a full run.
+    pub files: Vec<String>,
+    /// New nodes, and nodes whose attributes changed.
+    pub upserted_nodes: Vec<GraphNode>,
+    /// Keys of nodes no longer in the graph.
+    pub removed_nodes: Vec<String>,
+    pub added_edges: Vec<GraphEdge>,
+    pub removed_edges: Vec<GraphEdge>,
+}
+
+impl GraphDelta {
+    pub fn is_empty(&self) -> bool {
+        self.upserted_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
 }
 
 // =============================================================================
@@ -105,12 +164,17 @@ pub struct SearchQuery {
     /// Maximum number of results.
     #[serde(default = "default_limit")]
     pub limit: usize,
+    /// Lines of surrounding source to include around each hit.
+    pub context: Option<usize>,
 }
 
 fn default_limit() -> usize {
     20
 }
 
+/// Most lines of context returned on each side of a search hit.
+pub const MAX_SEARCH_CONTEXT_LINES: usize = 50;
+
 /// A single search result.
 #[derive(Debug, Serialize)]
 pub struct SearchResult {
@@ -124,4 +188,146 @@ pub struct SearchResult {
     pub file: Option<String>,
     /// Relevance score.
     pub score: f32,
+    /// First line of the hit.
+    pub start_line: u32,
+    /// Last line of the hit.
+    pub end_line: u32,
+    /// Surrounding lines and enclosing signature, when `context` was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<SearchContext>,
+}
+
+// =============================================================================
+// Edge Models (for `/api/edges`)
+// =============================================================================
+
+/// Query parameters for the grep endpoint.
+#[derive(Debug, Deserialize)]
+pub struct GrepQuery {
+    /// Regular expression matched against each line.
+    pub pattern: String,
+    /// Attach the enclosing function and its direct callers to each hit.
+    #[serde(default)]
+    pub expand: bool,
+    /// Maximum number of matching lines.
+    #[serde(default = "default_grep_limit")]
+    pub limit: usize,
+}
+
+fn default_grep_limit() -> usize {
+    100
+}
+
+/// Most matching lines `/api/grep` returns.
+pub const MAX_GREP_HITS: usize = 1000;
+
+/// Query parameters for the edges endpoint.
+#[derive(Debug, Deserialize)]
+pub struct EdgesQuery {
+    /// Relation table to list (e.g. `contains`, `imports`).
+    #[serde(rename = "type")]
+    pub relation: String,
+    /// Only edges leaving this entity ID.
+    pub from: Option<String>,
+    /// Number of edges to skip.
+    #[serde(default)]
+    pub offset: usize,
+    /// Maximum number of edges.
+    #[serde(default = "default_edges_limit")]
+    pub limit: usize,
+    /// List `calls` edges in effect at this time (`YYYY-MM-DD` or RFC 3339).
+    pub as_of: Option<String>,
+}
+
+fn default_edges_limit() -> usize {
+    100
+}
+
+/// One page of edges.
+#[derive(Debug, Serialize)]
+pub struct EdgePage {
+    /// Relation table listed.
+    pub relation: String,
+    /// Offset of the first edge.
+    pub offset: usize,
+    /// Edges on this page.
+    pub edges: Vec<EdgeInfo>,
+    /// Whether more edges follow this page.
+    pub has_more: bool,
+}
+
+// =============================================================================
+// Hotspot Models (for `/api/hotspots`)
+// =============================================================================
+
+/// A function ranked by complexity and how much code depends on it.
+#[derive(Debug, Serialize)]
+pub struct Hotspot {
+    /// Graph node key, for selecting the node in the UI.
+    pub key: String,
+    /// Function name.
+    pub name: String,
+    /// Source file path.
+    pub file: Option<String>,
+    /// Start line number.
+    pub start_line: Option<u32>,
+    /// Cyclomatic complexity.
+    pub cyclomatic: u32,
+    /// Cognitive complexity, if computed.
+    pub cognitive: Option<u32>,
+    /// Lines of code.
+    pub loc: Option<u32>,
+    /// Number of distinct callers.
+    pub fan_in: u32,
+    /// Number of distinct callees.
+    pub fan_out: u32,
+    /// Risk score: cyclomatic complexity weighted by fan-in.
+    pub risk: f64,
+}
+
+// =============================================================================
+// Saved View Models (for `/api/views`)
+// =============================================================================
+
+/// A named, restorable view of the graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedView {
+    /// Unique view name.
+    pub name: String,
+    /// Search query used to dim non-matching nodes.
+    #[serde(default)]
+    pub search: String,
+    /// Node categories that are visible.
+    #[serde(default)]
+    pub visible_categories: Vec<String>,
+    /// Camera position when the view was saved.
+    #[serde(default)]
+    pub camera: CameraState,
+    /// Node positions from the layout, keyed by node key.
+    ///
+    /// Restoring them keeps the camera meaningful, since ForceAtlas2 starts
+    /// from random positions on every load.
+    #[serde(default)]
+    pub positions: BTreeMap<String, [f64; 2]>,
+}
+
+/// Sigma.js camera state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraState {
+    pub x: f64,
+    pub y: f64,
+    pub ratio: f64,
+    #[serde(default)]
+    pub angle: f64,
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        Self {
+            x: 0.5,
+            y: 0.5,
+            ratio: 1.0,
+            angle: 0.0,
+        }
+    }
 }
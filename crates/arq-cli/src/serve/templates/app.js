@@ -16,8 +16,35 @@ let layoutRunning = true;
 let selectedNode = null;
 let hoveredNode = null;
 let searchQuery = '';
+let searchHits = new Map(); // Node key -> rank of semantic search hits
+let searchSeq = 0; // Ignores responses of superseded searches
+let searchTimer = null;
+let nodesByFile = new Map(); // File path -> node keys, to place search hits
 let visibleTypes = new Set(); // Populated dynamically from graph data
 let nodeTypeColors = new Map(); // Maps category -> color
+let savedViews = []; // Named views from /api/views
+let heatmapMode = false; // Color by complexity, size by callers
+let hotspots = null; // Lazily loaded from /api/hotspots
+let languages = null; // Lazily loaded from /api/languages
+let hotspotSort = { key: 'risk', desc: true };
+let coChangeCanvas = null; // Overlay drawing co-change edges dashed
+
+// Heatmap scale: cyclomatic complexity at or above this is fully red
+const HEATMAP_MAX_COMPLEXITY = 20;
+
+// Number of rows shown in the hotspots table (CSV export has all)
+const HOTSPOTS_TABLE_ROWS = 100;
+
+// Co-change edges (from `arq kg mine-history`) are drawn dashed on an overlay
+const CO_CHANGE_COLOR = 'rgba(188, 76, 0, 0.6)';
+const CO_CHANGE_DASH = [6, 4];
+
+// Semantic search: results requested and pause in typing before searching
+const SEARCH_LIMIT = 20;
+const SEARCH_DEBOUNCE_MS = 300;
+
+// Live updates: pause before reconnecting a closed WebSocket
+const LIVE_RECONNECT_MS = 3000;
 
 // =============================================================================
 // Initialization
@@ -70,9 +97,8 @@ async function init() {
             }
         });
 
-        // Update stats display
-        document.getElementById('node-count').textContent = graph.order;
-        document.getElementById('edge-count').textContent = graph.size;
+        indexNodesByFile();
+        updateCounts();
 
         // Hide loading indicator
         document.getElementById('loading').classList.add('hidden');
@@ -92,12 +118,132 @@ async function init() {
         // Setup event handlers
         setupEventHandlers();
 
+        // Patch the graph as files are re-indexed
+        connectLiveUpdates();
+
+        // Load saved views and restore one named in the URL (?view=name)
+        await loadViews();
+        const viewName = new URLSearchParams(window.location.search).get('view');
+        const view = savedViews.find(v => v.name === viewName);
+        if (view) {
+            applyView(view);
+        }
+
     } catch (error) {
         console.error('Failed to load graph:', error);
         showError(error.message);
     }
 }
 
+/**
+ * Index nodes by file so search hits can be placed in the graph.
+ */
+function indexNodesByFile() {
+    nodesByFile = new Map();
+    graph.forEachNode((node, attrs) => {
+        if (attrs.file) {
+            if (!nodesByFile.has(attrs.file)) nodesByFile.set(attrs.file, []);
+            nodesByFile.get(attrs.file).push(node);
+        }
+    });
+}
+
+/**
+ * Update the node and edge counts in the stats display.
+ */
+function updateCounts() {
+    document.getElementById('node-count').textContent = graph.order;
+    document.getElementById('edge-count').textContent = graph.size;
+}
+
+// =============================================================================
+// Live Updates
+// =============================================================================
+
+/**
+ * Receive graph deltas from `/ws`, reconnecting when the connection drops.
+ */
+function connectLiveUpdates() {
+    const protocol = window.location.protocol === 'https:' ? 'wss' : 'ws';
+    const socket = new WebSocket(`${protocol}://${window.location.host}/ws`);
+
+    socket.onmessage = (event) => {
+        const message = JSON.parse(event.data);
+        if (message.type === 'reload') {
+            window.location.reload();
+        } else if (message.type === 'delta') {
+            applyDelta(message);
+        }
+    };
+    socket.onclose = () => setTimeout(connectLiveUpdates, LIVE_RECONNECT_MS);
+}
+
+/**
+ * Apply a graph delta in place, keeping the layout of unchanged nodes.
+ */
+function applyDelta(delta) {
+    const relationship = (edge) => (edge.attributes || {}).relationship;
+
+    delta.removed_edges.forEach(edge => {
+        if (!graph.hasNode(edge.source) || !graph.hasNode(edge.target)) return;
+        graph.edges(edge.source, edge.target)
+            .filter(key => graph.getEdgeAttribute(key, 'relationship') === relationship(edge))
+            .forEach(key => graph.dropEdge(key));
+    });
+    delta.removed_nodes.forEach(key => {
+        if (graph.hasNode(key)) graph.dropNode(key);
+    });
+
+    let newCategory = false;
+    delta.upserted_nodes.forEach(node => {
+        if (graph.hasNode(node.key)) {
+            graph.replaceNodeAttributes(node.key, {
+                ...node.attributes,
+                x: graph.getNodeAttribute(node.key, 'x'),
+                y: graph.getNodeAttribute(node.key, 'y')
+            });
+        } else {
+            graph.addNode(node.key, {
+                ...node.attributes,
+                x: Math.random() * 100,
+                y: Math.random() * 100
+            });
+        }
+        const category = node.attributes.category;
+        if (category && !nodeTypeColors.has(category)) {
+            nodeTypeColors.set(category, node.attributes.color);
+            visibleTypes.add(category);
+            newCategory = true;
+        }
+    });
+
+    delta.added_edges.forEach(edge => {
+        if (graph.hasNode(edge.source) && graph.hasNode(edge.target)) {
+            try {
+                graph.addEdge(edge.source, edge.target, edge.attributes || {});
+            } catch (e) {
+                // Skip duplicate edges
+            }
+        }
+    });
+
+    if (newCategory) buildFilterUI();
+    indexNodesByFile();
+    updateCounts();
+
+    // Derived panels are stale until reopened
+    hotspots = null;
+    languages = null;
+    if (selectedNode && !graph.hasNode(selectedNode)) {
+        selectedNode = null;
+        hideNodeDetails();
+    }
+    renderer.refresh();
+
+    console.log('Applied graph update:', delta.files.length, 'files,',
+        delta.upserted_nodes.length, 'nodes changed,', delta.removed_nodes.length, 'removed');
+}
+
 /**
  * Show empty state message.
  */
@@ -156,7 +302,7 @@ function buildFilterUI() {
         const checkbox = document.createElement('input');
         checkbox.type = 'checkbox';
         checkbox.dataset.type = category;
-        checkbox.checked = true;
+        checkbox.checked = visibleTypes.has(category);
         checkbox.addEventListener('change', (e) => {
             if (e.target.checked) {
                 visibleTypes.add(category);
@@ -206,6 +352,51 @@ function createRenderer() {
         nodeReducer: nodeReducer,
         edgeReducer: edgeReducer
     });
+
+    // Sigma has no dashed edges, so co-change edges are drawn on an overlay
+    coChangeCanvas = document.createElement('canvas');
+    coChangeCanvas.id = 'co-change-layer';
+    container.appendChild(coChangeCanvas);
+    renderer.on('afterRender', drawCoChangeEdges);
+}
+
+/**
+ * Draw co-change edges as dashed lines, thicker for files changing
+ * together more often.
+ */
+function drawCoChangeEdges() {
+    const { width, height } = renderer.getDimensions();
+    const ratio = window.devicePixelRatio || 1;
+    if (coChangeCanvas.width !== width * ratio || coChangeCanvas.height !== height * ratio) {
+        coChangeCanvas.width = width * ratio;
+        coChangeCanvas.height = height * ratio;
+        coChangeCanvas.style.width = width + 'px';
+        coChangeCanvas.style.height = height + 'px';
+    }
+
+    const ctx = coChangeCanvas.getContext('2d');
+    ctx.setTransform(ratio, 0, 0, ratio, 0, 0);
+    ctx.clearRect(0, 0, width, height);
+    ctx.setLineDash(CO_CHANGE_DASH);
+    ctx.strokeStyle = CO_CHANGE_COLOR;
+
+    graph.forEachEdge((edge, attrs, source, target) => {
+        if (attrs.relationship !== 'co_change') return;
+        if (!visibleTypes.has('file')) return;
+        if (hoveredNode && source !== hoveredNode && target !== hoveredNode) return;
+
+        const from = renderer.getNodeDisplayData(source);
+        const to = renderer.getNodeDisplayData(target);
+        if (!from || !to || from.hidden || to.hidden) return;
+
+        const a = renderer.framedGraphToViewport(from);
+        const b = renderer.framedGraphToViewport(to);
+        ctx.lineWidth = 1 + 3 * (attrs.weight || 0);
+        ctx.beginPath();
+        ctx.moveTo(a.x, a.y);
+        ctx.lineTo(b.x, b.y);
+        ctx.stroke();
+    });
 }
 
 /**
@@ -251,8 +442,23 @@ function nodeReducer(node, data) {
         return res;
     }
 
-    // Search filtering - dim non-matching nodes
-    if (searchQuery && !data.label.toLowerCase().includes(searchQuery.toLowerCase())) {
+    // Heatmap - color functions by complexity, size by callers, fade the rest
+    if (heatmapMode) {
+        if (data.cyclomatic != null) {
+            res.color = heatColor(data.cyclomatic);
+            res.size = heatSize(data.fan_in || 0);
+        } else {
+            res.color = '#e1e4e8';
+            res.label = '';
+        }
+    }
+
+    // Search filtering - highlight semantic hits, dim nodes matching neither
+    // the hits nor the label
+    if (searchHits.has(node)) {
+        res.highlighted = true;
+        res.zIndex = 1;
+    } else if (searchQuery && !data.label.toLowerCase().includes(searchQuery.toLowerCase())) {
         res.color = '#e1e4e8';
         res.label = '';
     }
@@ -283,6 +489,12 @@ function nodeReducer(node, data) {
 function edgeReducer(edge, data) {
     const res = { ...data };
 
+    // Co-change edges are drawn dashed by drawCoChangeEdges()
+    if (data.relationship === 'co_change') {
+        res.hidden = true;
+        return res;
+    }
+
     // Highlight edges connected to hovered node
     if (hoveredNode) {
         const source = graph.source(edge);
@@ -343,11 +555,24 @@ function setupEventHandlers() {
         renderer.refresh();
     });
 
-    // Search input
-    document.getElementById('search').addEventListener('input', (e) => {
+    // Search input - filter labels as you type, then search semantically
+    const searchInput = document.getElementById('search');
+    searchInput.addEventListener('input', (e) => {
         searchQuery = e.target.value;
         renderer.refresh();
+        clearTimeout(searchTimer);
+        searchTimer = setTimeout(() => runSearch(searchQuery), SEARCH_DEBOUNCE_MS);
     });
+    searchInput.addEventListener('keydown', (e) => {
+        if (e.key === 'Enter') {
+            clearTimeout(searchTimer);
+            runSearch(searchQuery);
+        } else if (e.key === 'Escape') {
+            searchInput.value = '';
+            clearSearch();
+        }
+    });
+    document.getElementById('search-close-btn').addEventListener('click', clearSearch);
 
     // Note: Type filter checkboxes are created dynamically in buildFilterUI()
 
@@ -358,6 +583,29 @@ function setupEventHandlers() {
     document.getElementById('reset-btn').addEventListener('click', () => {
         renderer.getCamera().animatedReset();
     });
+
+    // Saved views
+    document.getElementById('view-select').addEventListener('change', (e) => {
+        const view = savedViews.find(v => v.name === e.target.value);
+        document.getElementById('delete-view-btn').disabled = !view;
+        if (view) {
+            applyView(view);
+        }
+    });
+    document.getElementById('save-view-btn').addEventListener('click', saveCurrentView);
+    document.getElementById('delete-view-btn').addEventListener('click', deleteSelectedView);
+
+    // Complexity heatmap and hotspots table
+    document.getElementById('heatmap-btn').addEventListener('click', toggleHeatmap);
+    document.getElementById('hotspots-btn').addEventListener('click', toggleHotspots);
+    document.getElementById('hotspots-close-btn').addEventListener('click', toggleHotspots);
+    document.querySelectorAll('#hotspots-table th[data-sort]').forEach(th => {
+        th.addEventListener('click', () => sortHotspots(th.dataset.sort));
+    });
+
+    // Line counts per language
+    document.getElementById('languages-btn').addEventListener('click', toggleLanguages);
+    document.getElementById('languages-close-btn').addEventListener('click', toggleLanguages);
 }
 
 // =============================================================================
@@ -375,6 +623,10 @@ function showNodeDetails(nodeKey) {
     document.getElementById('detail-file').textContent = attrs.file || '-';
     document.getElementById('detail-lines').textContent =
         attrs.start_line ? attrs.start_line + '-' + attrs.end_line : '-';
+    document.getElementById('detail-complexity').textContent =
+        attrs.cyclomatic != null ? attrs.cyclomatic : '-';
+    document.getElementById('detail-fan-in').textContent =
+        attrs.category === 'function' ? attrs.fan_in : '-';
     document.getElementById('detail-outgoing').textContent = graph.outDegree(nodeKey);
     document.getElementById('detail-incoming').textContent = graph.inDegree(nodeKey);
 
@@ -388,6 +640,460 @@ function hideNodeDetails() {
     document.getElementById('node-details').classList.remove('visible');
 }
 
+// =============================================================================
+// Complexity Heatmap & Hotspots
+// =============================================================================
+
+/**
+ * Toggle heatmap mode (color by complexity, size by callers).
+ */
+function toggleHeatmap() {
+    heatmapMode = !heatmapMode;
+    document.getElementById('heatmap-btn').classList.toggle('active', heatmapMode);
+    renderer.refresh();
+}
+
+/**
+ * Color for a cyclomatic complexity: green (simple) through yellow to red.
+ */
+function heatColor(cyclomatic) {
+    const t = Math.min(Math.max(cyclomatic - 1, 0) / (HEATMAP_MAX_COMPLEXITY - 1), 1);
+    const r = Math.round(t < 0.5 ? 46 + t * 2 * (230 - 46) : 230 - (t - 0.5) * 2 * (230 - 207));
+    const g = Math.round(t < 0.5 ? 160 + t * 2 * (180 - 160) : 180 - (t - 0.5) * 2 * (180 - 34));
+    const b = Math.round(t < 0.5 ? 67 - t * 2 * (67 - 40) : 40 - (t - 0.5) * 2 * (40 - 46));
+    return `rgb(${r}, ${g}, ${b})`;
+}
+
+/**
+ * Node size for a number of callers (sub-linear so hubs stay readable).
+ */
+function heatSize(fanIn) {
+    return Math.min(4 + 3 * Math.sqrt(fanIn), 24);
+}
+
+/**
+ * Show or hide the hotspots table, loading it on first open.
+ */
+async function toggleHotspots() {
+    const panel = document.getElementById('hotspots-panel');
+    const visible = panel.classList.toggle('visible');
+    if (visible) {
+        document.getElementById('search-panel').classList.remove('visible');
+    }
+    document.getElementById('hotspots-btn').classList.toggle('active', visible);
+
+    if (visible && hotspots === null) {
+        try {
+            const response = await fetch('/api/hotspots');
+            hotspots = await response.json();
+        } catch (error) {
+            console.error('Failed to load hotspots:', error);
+            hotspots = [];
+        }
+        renderHotspots();
+    }
+}
+
+/**
+ * Sort the hotspots table by a column; clicking again reverses the order.
+ */
+function sortHotspots(key) {
+    if (hotspotSort.key === key) {
+        hotspotSort.desc = !hotspotSort.desc;
+    } else {
+        // Names read best A-Z, metrics highest first
+        hotspotSort = { key, desc: key !== 'name' };
+    }
+    renderHotspots();
+}
+
+/**
+ * Render the hotspots table in the current sort order.
+ */
+function renderHotspots() {
+    const { key, desc } = hotspotSort;
+    const rows = [...(hotspots || [])].sort((a, b) => {
+        const av = a[key] ?? -1;
+        const bv = b[key] ?? -1;
+        const cmp = typeof av === 'string' ? av.localeCompare(bv) : av - bv;
+        return desc ? -cmp : cmp;
+    });
+
+    document.querySelectorAll('#hotspots-table th[data-sort]').forEach(th => {
+        th.classList.toggle('sorted-asc', th.dataset.sort === key && !desc);
+        th.classList.toggle('sorted-desc', th.dataset.sort === key && desc);
+    });
+
+    const tbody = document.querySelector('#hotspots-table tbody');
+    tbody.innerHTML = '';
+    rows.slice(0, HOTSPOTS_TABLE_ROWS).forEach(h => {
+        const tr = document.createElement('tr');
+        tr.title = h.file ? `${h.file}:${h.start_line}` : h.name;
+
+        const name = document.createElement('td');
+        const swatch = document.createElement('span');
+        swatch.className = 'swatch';
+        swatch.style.background = heatColor(h.cyclomatic);
+        name.append(swatch, h.name);
+        tr.appendChild(name);
+
+        [h.cyclomatic, h.loc ?? '-', h.fan_in, h.risk].forEach(value => {
+            const td = document.createElement('td');
+            td.textContent = value;
+            tr.appendChild(td);
+        });
+
+        tr.addEventListener('click', () => focusNode(h.key));
+        tbody.appendChild(tr);
+    });
+}
+
+/**
+ * Select a node and move the camera to it.
+ */
+function focusNode(nodeKey) {
+    if (!graph.hasNode(nodeKey)) {
+        return;
+    }
+    selectedNode = nodeKey;
+    showNodeDetails(nodeKey);
+    zoomToNode(nodeKey);
+    renderer.refresh();
+}
+
+/**
+ * Move the camera to a node.
+ */
+function zoomToNode(nodeKey) {
+    const display = renderer.getNodeDisplayData(nodeKey);
+    if (display) {
+        renderer.getCamera().animate({ x: display.x, y: display.y, ratio: 0.3 }, { duration: 500 });
+    }
+}
+
+// =============================================================================
+// Semantic Search
+// =============================================================================
+
+/**
+ * Search code semantically via /api/search, highlight the hits in the
+ * graph, zoom to the best one and list all results in the sidebar.
+ */
+async function runSearch(query) {
+    const seq = ++searchSeq;
+    if (!query.trim()) {
+        clearSearch();
+        return;
+    }
+
+    let results;
+    try {
+        const params = new URLSearchParams({ q: query, limit: SEARCH_LIMIT });
+        const response = await fetch('/api/search?' + params);
+        if (!response.ok) throw new Error(await response.text());
+        results = await response.json();
+    } catch (error) {
+        console.error('Search failed:', error);
+        results = [];
+    }
+    // A newer search started while this one was in flight
+    if (seq !== searchSeq) return;
+
+    results.forEach(r => {
+        r.node = nodeForHit(r);
+    });
+    searchHits = new Map();
+    results.forEach((r, rank) => {
+        if (r.node && !searchHits.has(r.node)) searchHits.set(r.node, rank);
+    });
+
+    renderSearchResults(query, results);
+    const best = results.find(r => r.node);
+    if (best) {
+        zoomToNode(best.node);
+    }
+    renderer.refresh();
+}
+
+/**
+ * The graph node a search hit falls in: the smallest node of the hit's
+ * file whose lines overlap the hit.
+ */
+function nodeForHit(hit) {
+    let best = null;
+    let bestSpan = Infinity;
+    (nodesByFile.get(hit.file) || []).forEach(node => {
+        const attrs = graph.getNodeAttributes(node);
+        const start = attrs.start_line || 0;
+        const end = attrs.end_line || start;
+        if (start <= hit.end_line && end >= hit.start_line && end - start < bestSpan) {
+            best = node;
+            bestSpan = end - start;
+        }
+    });
+    return best;
+}
+
+/**
+ * List search results in the sidebar; clicking one focuses its node.
+ */
+function renderSearchResults(query, results) {
+    document.getElementById('hotspots-panel').classList.remove('visible');
+    document.getElementById('hotspots-btn').classList.remove('active');
+    document.getElementById('languages-panel').classList.remove('visible');
+    document.getElementById('languages-btn').classList.remove('active');
+
+    document.getElementById('search-summary').textContent =
+        results.length === 0
+            ? `No results for "${query}".`
+            : `${results.length} results for "${query}", ${searchHits.size} in the graph.`;
+
+    const list = document.getElementById('search-results');
+    list.textContent = '';
+    results.forEach(r => {
+        const li = document.createElement('li');
+        li.title = r.label;
+
+        const label = document.createElement('div');
+        label.className = 'result-label';
+        label.textContent = r.node
+            ? graph.getNodeAttributes(r.node).label
+            : (r.label.split('\n').find(line => line.trim()) || r.file || r.key).trim();
+
+        const location = document.createElement('div');
+        location.className = 'result-location';
+        location.textContent = `${r.file}:${r.start_line}-${r.end_line} · ${r.score.toFixed(2)}`;
+
+        li.append(label, location);
+        if (r.node) {
+            li.addEventListener('click', () => focusNode(r.node));
+        } else {
+            li.classList.add('unplaced');
+        }
+        list.appendChild(li);
+    });
+
+    document.getElementById('search-panel').classList.add('visible');
+}
+
+/**
+ * Clear the search, its highlights and the results sidebar.
+ */
+function clearSearch() {
+    clearTimeout(searchTimer);
+    searchSeq++;
+    searchQuery = '';
+    searchHits = new Map();
+    document.getElementById('search').value = '';
+    document.getElementById('search-panel').classList.remove('visible');
+    renderer.refresh();
+}
+
+// =============================================================================
+// Languages
+// =============================================================================
+
+/**
+ * Show or hide the languages table, loading it on first open.
+ */
+async function toggleLanguages() {
+    const panel = document.getElementById('languages-panel');
+    const visible = panel.classList.toggle('visible');
+    if (visible) {
+        document.getElementById('search-panel').classList.remove('visible');
+    }
+    document.getElementById('languages-btn').classList.toggle('active', visible);
+
+    if (visible && languages === null) {
+        try {
+            const response = await fetch('/api/languages');
+            languages = response.ok ? await response.json() : [];
+        } catch (error) {
+            console.error('Failed to load languages:', error);
+            languages = [];
+        }
+        renderLanguages();
+    }
+}
+
+/**
+ * Render the languages table with a total row.
+ */
+function renderLanguages() {
+    const columns = ['files', 'code', 'comments', 'blanks', 'functions'];
+    const total = { language: 'Total' };
+    columns.forEach(c => {
+        total[c] = languages.reduce((sum, lang) => sum + lang[c], 0);
+    });
+
+    const tbody = document.querySelector('#languages-table tbody');
+    tbody.innerHTML = '';
+    [...languages, total].forEach(lang => {
+        const tr = document.createElement('tr');
+        [lang.language, ...columns.map(c => lang[c].toLocaleString())].forEach(value => {
+            const td = document.createElement('td');
+            td.textContent = value;
+            tr.appendChild(td);
+        });
+        if (lang === total) {
+            tr.className = 'total';
+        }
+        tbody.appendChild(tr);
+    });
+}
+
+// =============================================================================
+// Saved Views
+// =============================================================================
+
+/**
+ * Fetch saved views from the server and refresh the dropdown.
+ */
+async function loadViews() {
+    try {
+        const response = await fetch('/api/views');
+        if (!response.ok) throw new Error(await response.text());
+        savedViews = await response.json();
+    } catch (error) {
+        console.error('Failed to load saved views:', error);
+        savedViews = [];
+    }
+    renderViewOptions();
+}
+
+/**
+ * Rebuild the saved views dropdown, keeping the selection if possible.
+ */
+function renderViewOptions(selected = '') {
+    const select = document.getElementById('view-select');
+    const placeholder = select.options[0];
+    select.textContent = '';
+    select.appendChild(placeholder);
+
+    savedViews.forEach(view => {
+        const option = document.createElement('option');
+        option.value = view.name;
+        option.textContent = view.name;
+        select.appendChild(option);
+    });
+
+    select.value = savedViews.some(v => v.name === selected) ? selected : '';
+    document.getElementById('delete-view-btn').disabled = !select.value;
+}
+
+/**
+ * Capture the current search, filters, camera and layout as a view.
+ */
+function captureView(name) {
+    const camera = renderer.getCamera().getState();
+    const positions = {};
+    graph.forEachNode((node, attrs) => {
+        positions[node] = [attrs.x, attrs.y];
+    });
+
+    return {
+        name: name,
+        search: searchQuery,
+        visible_categories: Array.from(visibleTypes),
+        camera: { x: camera.x, y: camera.y, ratio: camera.ratio, angle: camera.angle },
+        positions: positions
+    };
+}
+
+/**
+ * Prompt for a name and save the current view.
+ */
+async function saveCurrentView() {
+    const current = document.getElementById('view-select').value;
+    const name = window.prompt('Save view as:', current);
+    if (!name || !name.trim()) return;
+
+    try {
+        const response = await fetch('/api/views', {
+            method: 'PUT',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify(captureView(name.trim()))
+        });
+        if (!response.ok) throw new Error(await response.text());
+        savedViews = await response.json();
+        renderViewOptions(name.trim());
+        setViewParam(name.trim());
+    } catch (error) {
+        window.alert('Failed to save view: ' + error.message);
+    }
+}
+
+/**
+ * Delete the view selected in the dropdown.
+ */
+async function deleteSelectedView() {
+    const name = document.getElementById('view-select').value;
+    if (!name || !window.confirm('Delete view "' + name + '"?')) return;
+
+    try {
+        const response = await fetch('/api/views/' + encodeURIComponent(name), { method: 'DELETE' });
+        if (!response.ok) throw new Error(await response.text());
+        savedViews = await response.json();
+        renderViewOptions();
+        setViewParam(null);
+    } catch (error) {
+        window.alert('Failed to delete view: ' + error.message);
+    }
+}
+
+/**
+ * Restore a saved view: layout, filters, search and camera.
+ */
+function applyView(view) {
+    // Saved positions replace the running layout
+    const positions = view.positions || {};
+    if (Object.keys(positions).length > 0) {
+        if (layoutRunning) stopLayout();
+        graph.forEachNode(node => {
+            const pos = positions[node];
+            if (pos) {
+                graph.mergeNodeAttributes(node, { x: pos[0], y: pos[1] });
+            }
+        });
+    }
+
+    // Categories not present when the view was saved stay visible
+    const saved = new Set(view.visible_categories || []);
+    visibleTypes = new Set(
+        Array.from(nodeTypeColors.keys()).filter(c => saved.size === 0 || saved.has(c))
+    );
+    document.querySelectorAll('#node-type-filters input[type="checkbox"]').forEach(checkbox => {
+        checkbox.checked = visibleTypes.has(checkbox.dataset.type);
+    });
+
+    searchQuery = view.search || '';
+    document.getElementById('search').value = searchQuery;
+    searchHits = new Map();
+    document.getElementById('search-panel').classList.remove('visible');
+
+    if (view.camera) {
+        renderer.getCamera().animate(view.camera, { duration: 500 });
+    }
+
+    document.getElementById('view-select').value = view.name;
+    document.getElementById('delete-view-btn').disabled = false;
+    setViewParam(view.name);
+    renderer.refresh();
+}
+
+/**
+ * Reflect the active view in the URL so it can be bookmarked or shared.
+ */
+function setViewParam(name) {
+    const url = new URL(window.location.href);
+    if (name) {
+        url.searchParams.set('view', name);
+    } else {
+        url.searchParams.delete('view');
+    }
+    window.history.replaceState(null, '', url);
+}
+
 // =============================================================================
 // Layout Control
 // =============================================================================
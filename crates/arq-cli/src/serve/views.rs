@@ -0,0 +1,114 @@
+//! Named graph views persisted in the project data directory.
+//!
+//! A view captures what the user is looking at — search query, visible
+//! categories and camera position — so recurring discussions can jump back
+//! to the same picture. Views are stored as JSON in `views.json`.
+
+use std::io;
+use std::path::PathBuf;
+
+use tokio::sync::Mutex;
+
+use arq_core::storage::write_atomic;
+use arq_core::StorageError;
+
+use super::models::SavedView;
+
+/// File name of the saved views, inside the project data directory.
+pub const VIEWS_FILE: &str = "views.json";
+
+/// Maximum length of a view name.
+const MAX_VIEW_NAME_LEN: usize = 80;
+
+/// File-backed store of named views.
+pub struct ViewStore {
+    path: PathBuf,
+    /// Serializes read-modify-write cycles between concurrent requests.
+    lock: Mutex<()>,
+}
+
+impl ViewStore {
+    /// Creates a store backed by the given JSON file.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// All saved views, sorted by name.
+    pub async fn list(&self) -> io::Result<Vec<SavedView>> {
+        let _guard = self.lock.lock().await;
+        self.read()
+    }
+
+    /// Save a view, replacing any existing view with the same name.
+    pub async fn save(&self, mut view: SavedView) -> io::Result<Vec<SavedView>> {
+        view.name = validate_name(&view.name)?;
+
+        let _guard = self.lock.lock().await;
+        let mut views = self.read()?;
+        views.retain(|v| v.name != view.name);
+        views.push(view);
+        views.sort_by(|a, b| a.name.cmp(&b.name));
+        self.write(&views)?;
+        Ok(views)
+    }
+
+    /// Delete a view by name. Returns the remaining views.
+    pub async fn delete(&self, name: &str) -> io::Result<Vec<SavedView>> {
+        let _guard = self.lock.lock().await;
+        let mut views = self.read()?;
+        let before = views.len();
+        views.retain(|v| v.name != name);
+        if views.len() == before {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No view named '{}'", name),
+            ));
+        }
+        self.write(&views)?;
+        Ok(views)
+    }
+
+    fn read(&self) -> io::Result<Vec<SavedView>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&self, views: &[SavedView]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(views)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // Replace atomically so a crash never leaves a truncated file
+        write_atomic(&self.path, json.as_bytes()).map_err(|e| match e {
+            StorageError::Io { source, .. } => source,
+            e => io::Error::other(e),
+        })
+    }
+}
+
+/// Trim a view name and reject empty or overly long names.
+fn validate_name(name: &str) -> io::Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "View name cannot be empty",
+        ));
+    }
+    if name.chars().count() > MAX_VIEW_NAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("View name is longer than {} characters", MAX_VIEW_NAME_LEN),
+        ));
+    }
+    Ok(name.to_string())
+}
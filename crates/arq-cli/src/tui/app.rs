@@ -1,18 +1,44 @@
 //! Application state and main event loop.
 
 use chrono::{DateTime, Utc};
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::prelude::*;
+use std::future::Future;
 use std::io::Stdout;
-use tokio::sync::mpsc;
-
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, OnceCell};
+use tokio::task::JoinHandle;
+
+use arq_core::agent::{
+    AgentExecutor, AgentProgress, AgentRunner, ExecutionItem, ExecutionResult, FileEdit,
+};
+use arq_core::knowledge::PathBlocklist;
+use arq_core::llm::{estimate_tokens, CancellationToken};
+use arq_core::planning::PlanningMessage;
+use arq_core::prompts;
+use arq_core::research::{
+    ContextCache, CostConfirmation, CostLimit, Refinement, SentContextLog, Severity,
+};
+use arq_core::storage::PrivateFiles;
 use arq_core::{
-    Config, ContextBuilder, FileStorage, KnowledgeGraph, KnowledgeStore, ResearchDoc,
-    ResearchProgress, ResearchRunner, Task, TaskManager,
+    suggest_task_name, Config, ContextBuilder, FileStorage, HookEvent, Hooks, KnowledgeError,
+    KnowledgeGraph, KnowledgeStore, Phase, Plan, PlanningProgress, PlanningRunner, Provider,
+    ResearchDoc, ResearchProgress, ResearchRunner, Task, TaskManager, TokenUsage, UsageRecord,
 };
 
-use super::event::{Event, EventHandler, ResearchResult};
+use super::commands::SlashCommand;
+use super::event::{Event, EventHandler, PlanResult, ResearchResult};
+use super::mouse::{self, HitAreas, Selection};
+use super::session::{Recorder, Replayer, Snapshot};
 use super::ui;
+use super::viewer::{ContextReview, FileViewer, LocationPicker, SourceLocation};
+
+/// Knowledge graph opened on first use and shared with background tasks.
+///
+/// RocksDB allows a single handle per process, so every lookup goes through this.
+pub type SharedKnowledgeGraph = Arc<OnceCell<Arc<KnowledgeGraph>>>;
 
 /// The selected tab in the TUI.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -55,6 +81,15 @@ impl SelectedTab {
             Self::Agent => 2,
         }
     }
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        match index {
+            0 => Some(Self::Researcher),
+            1 => Some(Self::Planner),
+            2 => Some(Self::Agent),
+            _ => None,
+        }
+    }
 }
 
 /// Input mode for the application.
@@ -63,6 +98,10 @@ pub enum InputMode {
     #[default]
     Normal,
     Editing,
+    /// Typing a semantic search query for the file picker
+    Search,
+    /// Editing the current task's name
+    Rename,
 }
 
 /// Research validation state.
@@ -82,6 +121,46 @@ pub enum ResearchState {
     Refining,
 }
 
+/// Planner state.
+#[derive(Debug, Clone, Default)]
+pub enum PlanningState {
+    /// No planner run in progress
+    #[default]
+    Idle,
+    /// Replying to a message about the plan structure
+    Chatting,
+    /// Generating the structured plan
+    Generating,
+    /// Plan generated, awaiting user approval or further discussion
+    AwaitingApproval { task_id: String, pending_plan: Plan },
+}
+
+impl PlanningState {
+    /// Whether a planner run is in progress.
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Chatting | Self::Generating)
+    }
+}
+
+/// Agent state.
+#[derive(Debug, Clone, Default)]
+pub enum AgentState {
+    /// No agent run in progress
+    #[default]
+    Idle,
+    /// Generating the edit of the plan item at `index`
+    Generating { index: usize },
+    /// Edit of the plan item at `index` shown, awaiting apply or skip
+    AwaitingConfirmation { index: usize, edit: FileEdit },
+}
+
+impl AgentState {
+    /// Whether an agent run is in progress, including one awaiting confirmation.
+    pub fn is_active(&self) -> bool {
+        !matches!(self, Self::Idle)
+    }
+}
+
 /// A chat message in the conversation.
 #[derive(Debug, Clone)]
 pub struct ChatMessage {
@@ -161,6 +240,10 @@ impl ProgressStatus {
 pub struct ProgressItem {
     pub label: String,
     pub status: ProgressStatus,
+    /// Why the step failed
+    pub detail: Option<String>,
+    /// Whether the detail is shown, toggled by clicking the item
+    pub expanded: bool,
 }
 
 impl ProgressItem {
@@ -168,10 +251,104 @@ impl ProgressItem {
         Self {
             label: label.into(),
             status: ProgressStatus::Pending,
+            detail: None,
+            expanded: false,
+        }
+    }
+}
+
+/// Estimated LLM usage for the current session.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionUsage {
+    pub tokens: TokenUsage,
+    /// Estimated cost in USD; `None` once a call used a model without known pricing
+    pub cost: Option<f64>,
+}
+
+impl Default for SessionUsage {
+    fn default() -> Self {
+        Self {
+            tokens: TokenUsage::default(),
+            cost: Some(0.0),
+        }
+    }
+}
+
+impl SessionUsage {
+    /// Add one call, priced for the model that served it.
+    pub fn record(&mut self, usage: TokenUsage, provider: &str, model: &str) {
+        self.tokens += usage;
+        self.cost = self
+            .cost
+            .zip(usage.estimated_cost(provider, model))
+            .map(|(total, call)| total + call);
+    }
+}
+
+/// Usage of the research run awaiting approval, including refinements.
+#[derive(Debug, Default)]
+struct PendingRun {
+    tokens: TokenUsage,
+    started: Option<Instant>,
+    elapsed: Duration,
+    /// When the first streamed chunk of the current run arrived
+    stream_started: Option<Instant>,
+    /// Estimated tokens streamed so far in the current run
+    streamed_tokens: usize,
+}
+
+impl PendingRun {
+    /// Start timing a research or refinement run.
+    fn start(&mut self) {
+        self.started = Some(Instant::now());
+        self.stream_started = None;
+        self.streamed_tokens = 0;
+    }
+
+    /// Count a streamed chunk towards the token rate.
+    fn record_stream(&mut self, text: &str) {
+        self.stream_started.get_or_insert_with(Instant::now);
+        self.streamed_tokens += estimate_tokens(text);
+    }
+
+    /// Time spent on the current run so far.
+    fn current_elapsed(&self) -> Option<Duration> {
+        self.started.map(|started| started.elapsed())
+    }
+
+    /// Rough time left, from the streaming token rate and expected output size.
+    ///
+    /// `None` until a rate is known or once the expected size is exceeded.
+    fn eta(&self, expected_tokens: usize) -> Option<Duration> {
+        let streaming_for = self.stream_started?.elapsed().as_secs_f64();
+        if streaming_for < ETA_MIN_SAMPLE_SECS || self.streamed_tokens == 0 {
+            return None;
+        }
+        let remaining = expected_tokens.checked_sub(self.streamed_tokens)?;
+        let rate = self.streamed_tokens as f64 / streaming_for;
+        Some(Duration::from_secs_f64(remaining as f64 / rate))
+    }
+
+    /// Stop timing the current run.
+    fn finish(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.elapsed += started.elapsed();
         }
     }
 }
 
+/// Output tokens assumed for a research response before any run has finished.
+const ETA_DEFAULT_OUTPUT_TOKENS: usize = 1500;
+
+/// Seconds of streaming needed before the token rate is trusted for an ETA.
+const ETA_MIN_SAMPLE_SECS: f64 = 2.0;
+
+/// Lines moved by PageUp/PageDown in the file viewer.
+const VIEWER_PAGE_LINES: usize = 20;
+
+/// Largest source files named in the research prompt breakdown.
+const PROMPT_STATS_TOP_FILES: usize = 3;
+
 /// Status messages shown while researching.
 const THINKING_MESSAGES: &[&str] = &[
     "Thinking...",
@@ -200,10 +377,17 @@ pub struct App {
     pub is_streaming: bool,
     /// Current streaming buffer
     pub stream_buffer: String,
+    /// Research document assembled from the sections streamed so far,
+    /// shown instead of the raw response
+    pub streamed_doc: Option<ResearchDoc>,
     /// Whether the app should quit
     pub should_quit: bool,
     /// Scroll offset for chat
     pub scroll_offset: usize,
+    /// Clickable areas of the last frame
+    pub hit_areas: HitAreas,
+    /// Chat text selected with the mouse
+    pub selection: Option<Selection>,
     /// Configuration
     pub config: Config,
     /// Task manager for persistence
@@ -218,9 +402,62 @@ pub struct App {
     pub selected_model_index: usize,
     /// Tick counter for cycling messages
     pub tick_count: usize,
-    /// Knowledge graph for semantic search (initialized lazily, for future TUI integration)
-    #[allow(dead_code)]
-    pub knowledge_graph: Option<std::sync::Arc<KnowledgeGraph>>,
+    /// Knowledge graph for semantic search and jump-to-definition
+    pub knowledge_graph: SharedKnowledgeGraph,
+    /// Whether a knowledge graph database exists on disk (checked at startup)
+    pub kg_on_disk: bool,
+    /// Estimated tokens and cost for this session
+    pub session_usage: SessionUsage,
+    /// Open file, shown in place of the chat
+    pub viewer: Option<FileViewer>,
+    /// Location picker for citations and search results
+    pub picker: Option<LocationPicker>,
+    /// Review of the files the current task's research would send to the LLM
+    pub context_review: Option<ContextReview>,
+    /// First key of a two-key viewer command (`gg`, `gd`, `gs`)
+    pending_key: Option<char>,
+    /// Usage recorded on the task once research is approved
+    pending_run: PendingRun,
+    /// Spawned research or refinement run, aborted on cancel
+    research_task: Option<JoinHandle<()>>,
+    /// Cancels the LLM request of the running research, closing its stream
+    research_cancel: Option<CancellationToken>,
+    /// Output tokens of the last finished run, for the ETA
+    last_output_tokens: Option<usize>,
+    /// Minimum severity of findings shown while awaiting validation
+    finding_filter: Severity,
+    /// Index of the chat message showing the pending research document
+    pending_doc_message: Option<usize>,
+    /// Planner state
+    pub planning_state: PlanningState,
+    /// Planner conversation sent to the LLM with the pinned research
+    planning_history: Vec<PlanningMessage>,
+    /// Task whose research is pinned in the planner conversation
+    pinned_research: Option<String>,
+    /// Usage recorded on the task once the plan is approved
+    pending_plan_run: PendingRun,
+    /// Spawned planner chat turn or plan generation, aborted on cancel
+    planning_task: Option<JoinHandle<()>>,
+    /// Cancels the LLM request of the running planner run
+    planning_cancel: Option<CancellationToken>,
+    /// Agent state
+    pub agent_state: AgentState,
+    /// Plan items of the agent run, in the order they are generated
+    agent_items: Vec<ExecutionItem>,
+    /// Edits applied in the agent run, saved to the agent log when it ends
+    agent_results: Vec<ExecutionResult>,
+    /// Usage recorded on the task when the agent run ends
+    pending_agent_run: PendingRun,
+    /// Spawned edit generation, aborted on cancel
+    agent_task: Option<JoinHandle<()>>,
+    /// Cancels the LLM request of the running edit generation
+    agent_cancel: Option<CancellationToken>,
+    /// Research prompt over the cost limit, waiting to be confirmed
+    pub cost_confirmation: Option<CostConfirmation>,
+    /// Session being recorded (`arq tui --record`)
+    recorder: Option<Recorder>,
+    /// Session being replayed (`arq tui --replay`)
+    replay: Option<Replayer>,
 }
 
 impl App {
@@ -241,6 +478,8 @@ impl App {
             0
         };
 
+        let kg_on_disk = config.knowledge.db_full_path(&config.storage).exists();
+
         let mut app = Self {
             selected_tab: SelectedTab::Researcher,
             input_mode: InputMode::Normal,
@@ -249,8 +488,11 @@ impl App {
             progress_items: Vec::new(),
             is_streaming: false,
             stream_buffer: String::new(),
+            streamed_doc: None,
             should_quit: false,
             scroll_offset: 0,
+            hit_areas: HitAreas::default(),
+            selection: None,
             config,
             manager,
             current_task: current_task.clone(),
@@ -258,7 +500,34 @@ impl App {
             research_state: ResearchState::Idle,
             selected_model_index,
             tick_count: 0,
-            knowledge_graph: None, // Initialized lazily during first research
+            knowledge_graph: SharedKnowledgeGraph::default(),
+            kg_on_disk,
+            session_usage: SessionUsage::default(),
+            viewer: None,
+            picker: None,
+            context_review: None,
+            pending_key: None,
+            pending_run: PendingRun::default(),
+            research_task: None,
+            research_cancel: None,
+            finding_filter: Severity::Info,
+            pending_doc_message: None,
+            last_output_tokens: None,
+            planning_state: PlanningState::Idle,
+            planning_history: Vec::new(),
+            pinned_research: None,
+            pending_plan_run: PendingRun::default(),
+            planning_task: None,
+            planning_cancel: None,
+            agent_state: AgentState::Idle,
+            agent_items: Vec::new(),
+            agent_results: Vec::new(),
+            pending_agent_run: PendingRun::default(),
+            agent_task: None,
+            agent_cancel: None,
+            cost_confirmation: None,
+            recorder: None,
+            replay: None,
         };
 
         // Add welcome message
@@ -280,6 +549,89 @@ impl App {
         app
     }
 
+    /// Record the session's events to `recorder`.
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Drive the session with the recorded events of `replayer` instead of
+    /// live input and background work.
+    pub fn replay(&mut self, replayer: Replayer) {
+        self.replay = Some(replayer);
+    }
+
+    /// Whether a recorded session is being replayed.
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// How the replay went, if one is running.
+    pub fn replay_summary(&self) -> Option<String> {
+        self.replay.as_ref().map(Replayer::summary)
+    }
+
+    /// Lightweight state recorded with each event of a session.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tab: self.selected_tab.title().to_string(),
+            input_mode: format!("{:?}", self.input_mode),
+            research: match self.research_state {
+                ResearchState::Idle => "idle",
+                ResearchState::Researching => "researching",
+                ResearchState::AwaitingValidation { .. } => "awaiting_validation",
+                ResearchState::Refining => "refining",
+            }
+            .to_string(),
+            planning: match self.planning_state {
+                PlanningState::Idle => "idle",
+                PlanningState::Chatting => "chatting",
+                PlanningState::Generating => "generating",
+                PlanningState::AwaitingApproval { .. } => "awaiting_approval",
+            }
+            .to_string(),
+            agent: match self.agent_state {
+                AgentState::Idle => "idle",
+                AgentState::Generating { .. } => "generating",
+                AgentState::AwaitingConfirmation { .. } => "awaiting_confirmation",
+            }
+            .to_string(),
+            task: self.current_task.as_ref().map(|t| t.id.clone()),
+            phase: self
+                .current_task
+                .as_ref()
+                .map(|t| t.phase.display_name().to_string()),
+            messages: self.chat_messages.len(),
+            streaming: self.is_streaming,
+            viewer: self.viewer.as_ref().map(|v| v.location()),
+        }
+    }
+
+    /// Spawn background work, such as an LLM call or a lookup.
+    ///
+    /// A replay spawns nothing: the recorded events bring the results.
+    fn spawn<F>(&self, work: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if self.replay.is_some() {
+            tokio::spawn(async {})
+        } else {
+            tokio::spawn(work)
+        }
+    }
+
+    /// Mark the step in progress as failed because of `detail`.
+    fn fail_progress(&mut self, detail: impl Into<String>) {
+        if let Some(item) = self
+            .progress_items
+            .iter_mut()
+            .find(|item| item.status == ProgressStatus::InProgress)
+        {
+            item.status = ProgressStatus::Failed;
+            item.detail = Some(detail.into());
+        }
+    }
+
     /// Reset progress items based on current tab.
     fn reset_progress_items(&mut self) {
         self.progress_items = match self.selected_tab {
@@ -292,15 +644,15 @@ impl App {
             ],
             SelectedTab::Planner => vec![
                 ProgressItem::new("Loading research"),
-                ProgressItem::new("Generating approaches"),
-                ProgressItem::new("Building specification"),
-                ProgressItem::new("Checking complexity"),
+                ProgressItem::new("Calling LLM"),
+                ProgressItem::new("Building plan"),
+                ProgressItem::new("Saving plan"),
             ],
             SelectedTab::Agent => vec![
                 ProgressItem::new("Loading plan"),
                 ProgressItem::new("Generating code"),
                 ProgressItem::new("Checking conformance"),
-                ProgressItem::new("Running tests"),
+                ProgressItem::new("Applying changes"),
             ],
         };
     }
@@ -312,47 +664,50 @@ impl App {
     ) -> Result<(), Box<dyn std::error::Error>> {
         let mut events = EventHandler::new();
 
+        // Page the vector index in while the first prompt is typed
+        if self.kg_on_disk && self.config.knowledge.warmup {
+            let kg = Arc::clone(&self.knowledge_graph);
+            let db_path = self.config.knowledge.db_full_path(&self.config.storage);
+            self.spawn(async move {
+                let _ = kg
+                    .get_or_try_init(|| async {
+                        KnowledgeGraph::open_with_warmup(&db_path, true)
+                            .await
+                            .map(Arc::new)
+                    })
+                    .await;
+            });
+        }
+
         loop {
             // Draw UI
             terminal.draw(|frame| ui::render(self, frame))?;
 
             // Handle events
-            if let Some(event) = events.next().await {
-                match event {
-                    Event::Key(key) => self.handle_key_event(key, events.sender()),
-                    Event::Tick => {
-                        // Update cycling status messages during research
-                        self.tick_count = self.tick_count.wrapping_add(1);
-                        if matches!(
-                            self.research_state,
-                            ResearchState::Researching | ResearchState::Refining
-                        ) {
-                            // Cycle message every ~8 ticks (about 2 seconds at 250ms tick rate)
-                            let msg_index = (self.tick_count / 8) % THINKING_MESSAGES.len();
-                            self.status_message = Some(THINKING_MESSAGES[msg_index].to_string());
-                        }
-                    }
-                    Event::StreamChunk(text) => {
-                        self.stream_buffer.push_str(&text);
-                    }
-                    Event::StreamComplete => {
-                        if !self.stream_buffer.is_empty() {
-                            self.chat_messages
-                                .push(ChatMessage::assistant(std::mem::take(
-                                    &mut self.stream_buffer,
-                                )));
+            let next = match self.replay.as_mut() {
+                Some(replay) => tokio::select! {
+                    event = replay.next() => Some((event, true)),
+                    event = events.next() => event.map(|event| (event, false)),
+                },
+                None => events.next().await.map(|event| (event, false)),
+            };
+
+            if let Some((event, replayed)) = next {
+                if self.replay.is_some() && !replayed {
+                    // The recording drives a replay; only ticks and quitting are live
+                    match event {
+                        Event::Tick => self.handle_event(event, events.sender()),
+                        Event::Key(key)
+                            if key.code == KeyCode::Char('q')
+                                || (key.code == KeyCode::Char('c')
+                                    && key.modifiers.contains(KeyModifiers::CONTROL)) =>
+                        {
+                            self.should_quit = true;
                         }
-                        self.is_streaming = false;
-                    }
-                    Event::ResearchProgress(progress) => {
-                        self.handle_research_progress(progress);
-                    }
-                    Event::ResearchComplete(result) => {
-                        self.handle_research_complete(result);
-                    }
-                    Event::ResearchFailed(error) => {
-                        self.handle_research_failed(error);
+                        _ => {}
                     }
+                } else {
+                    self.handle_recorded_event(event, replayed, events.sender());
                 }
             }
 
@@ -364,6 +719,171 @@ impl App {
         Ok(())
     }
 
+    /// Handle an event, recording it or checking it against the replayed
+    /// recording.
+    fn handle_recorded_event(
+        &mut self,
+        event: Event,
+        replayed: bool,
+        event_tx: mpsc::UnboundedSender<Event>,
+    ) {
+        let recorded =
+            (self.recorder.is_some() && !matches!(event, Event::Tick)).then(|| event.clone());
+        self.handle_event(event, event_tx);
+
+        if let Some(event) = recorded {
+            let state = self.snapshot();
+            if let Some(Err(e)) = self.recorder.as_mut().map(|r| r.record(event, state)) {
+                self.recorder = None;
+                self.status_message = Some(format!("Recording stopped: {}", e));
+            }
+        }
+        if replayed {
+            let state = self.snapshot();
+            if let Some(replay) = self.replay.as_mut() {
+                replay.check(&state);
+                if replay.is_finished() {
+                    self.status_message = Some(replay.summary());
+                }
+            }
+        }
+    }
+
+    /// Handle an event from the terminal or a background task.
+    fn handle_event(&mut self, event: Event, event_tx: mpsc::UnboundedSender<Event>) {
+        match event {
+            Event::Key(key) => self.handle_key_event(key, event_tx),
+            Event::Mouse(mouse) => self.handle_mouse_event(mouse),
+            Event::Tick => {
+                // Update cycling status messages during research
+                self.tick_count = self.tick_count.wrapping_add(1);
+                if matches!(
+                    self.research_state,
+                    ResearchState::Researching | ResearchState::Refining
+                ) || self.planning_state.is_running()
+                    || matches!(self.agent_state, AgentState::Generating { .. })
+                {
+                    // Cycle message every ~8 ticks (about 2 seconds at 250ms tick rate)
+                    let msg_index = (self.tick_count / 8) % THINKING_MESSAGES.len();
+                    self.status_message = Some(THINKING_MESSAGES[msg_index].to_string());
+                }
+            }
+            // Research and planning events still queued after a cancel are dropped
+            Event::StreamChunk(_) | Event::StreamComplete
+                if matches!(self.research_state, ResearchState::Idle)
+                    && !self.planning_state.is_running() => {}
+            Event::ResearchProgress(_) if matches!(self.research_state, ResearchState::Idle) => {}
+            Event::ResearchComplete(_) | Event::ResearchFailed(_)
+                if self.research_task.is_none() => {}
+            Event::PlanningProgress(_)
+            | Event::PlanningReply(_)
+            | Event::PlanGenerated(_)
+            | Event::PlanningFailed(_)
+                if self.planning_task.is_none() => {}
+            Event::AgentProgress(_) | Event::AgentEditGenerated(_) | Event::AgentFailed(_)
+                if self.agent_task.is_none() => {}
+            Event::StreamChunk(text) => {
+                self.pending_run.record_stream(&text);
+                self.stream_buffer.push_str(&text);
+            }
+            Event::StreamComplete => {
+                if let Some(doc) = self.streamed_doc.take() {
+                    // Replaced by the parsed document once research completes
+                    self.stream_buffer.clear();
+                    self.pending_doc_message = Some(self.chat_messages.len());
+                    self.chat_messages
+                        .push(ChatMessage::assistant(doc.to_markdown()));
+                } else if !self.stream_buffer.is_empty() {
+                    self.chat_messages
+                        .push(ChatMessage::assistant(std::mem::take(
+                            &mut self.stream_buffer,
+                        )));
+                }
+                self.is_streaming = false;
+            }
+            Event::ResearchProgress(progress) => {
+                self.handle_research_progress(progress);
+            }
+            Event::ResearchComplete(result) => {
+                self.handle_research_complete(result);
+            }
+            Event::ResearchFailed(error) => {
+                self.handle_research_failed(error);
+            }
+            Event::PlanningProgress(progress) => {
+                self.handle_planning_progress(progress);
+            }
+            Event::PlanningReply(reply) => {
+                self.handle_planning_reply(reply);
+            }
+            Event::AgentProgress(progress) => {
+                self.handle_agent_progress(progress);
+            }
+            Event::AgentEditGenerated(edit) => {
+                self.handle_agent_edit(edit, event_tx);
+            }
+            Event::AgentFailed(error) => {
+                self.handle_agent_failed(error);
+            }
+            Event::PlanGenerated(result) => {
+                self.handle_plan_generated(result);
+            }
+            Event::PlanningFailed(error) => {
+                self.handle_planning_failed(error);
+            }
+            Event::SearchResults { query, results } => {
+                if results.is_empty() {
+                    self.status_message = Some(format!("No results for '{}'", query));
+                } else {
+                    self.picker = Some(LocationPicker::from_search(&query, &results));
+                }
+            }
+            Event::DefinitionFound { symbol, location } => {
+                self.handle_definition_found(symbol, location);
+            }
+            Event::EntityFound { location, entity } => {
+                self.status_message = Some(match entity {
+                    Some(entity) => format!(
+                        "{}:{} is in {} {} (lines {}-{})",
+                        location.path,
+                        location.line,
+                        entity.kind,
+                        entity.qualified_name.as_deref().unwrap_or(&entity.name),
+                        entity.start_line.unwrap_or_default(),
+                        entity.end_line.unwrap_or_default()
+                    ),
+                    None => format!(
+                        "No indexed entity contains {}:{}",
+                        location.path, location.line
+                    ),
+                });
+            }
+            Event::ContextCandidates { task_id, files } => {
+                let excluded = self
+                    .current_task
+                    .as_ref()
+                    .filter(|t| t.id == task_id)
+                    .map(|t| t.excluded_context.clone())
+                    .unwrap_or_default();
+                self.status_message = None;
+                self.context_review = Some(ContextReview::new(task_id, files, &excluded));
+            }
+            Event::LookupFailed(error) => {
+                self.status_message = Some(error);
+            }
+            Event::HookFailed(error) => {
+                self.status_message = Some(format!("Hook failed: {}", error));
+            }
+            Event::TaskNamed { task_id, name } => {
+                self.apply_task_name(&task_id, &name);
+            }
+            Event::CommandOutput(output) => {
+                self.status_message = None;
+                self.chat_messages.push(ChatMessage::system(output));
+            }
+        }
+    }
+
     /// Handle research progress updates.
     fn handle_research_progress(&mut self, progress: ResearchProgress) {
         match progress {
@@ -381,16 +901,67 @@ impl App {
                 self.set_progress_status(1, ProgressStatus::Complete);
                 self.status_message = Some(format!("Found {} relevant code segments", count));
             }
+            ResearchProgress::ContextFromCache { count } => {
+                self.set_progress_status(0, ProgressStatus::Complete);
+                self.set_progress_status(1, ProgressStatus::Complete);
+                self.status_message = Some(format!("Reused cached context ({} sources)", count));
+            }
+            ResearchProgress::PrivateContextWithheld { files } => {
+                self.chat_messages.push(ChatMessage::system(format!(
+                    "Withheld {} private file(s) from the prompt (context.private_paths): {}",
+                    files.len(),
+                    files.join(", ")
+                )));
+            }
             ResearchProgress::CallingLLM => {
                 // Mark context gathering complete (in case we skipped knowledge graph)
                 self.set_progress_status(0, ProgressStatus::Complete);
                 self.set_progress_status(1, ProgressStatus::Complete);
                 self.set_progress_status(2, ProgressStatus::InProgress);
             }
+            ResearchProgress::PromptStats(stats) => {
+                self.status_message =
+                    Some(format!("Prompt: {}", stats.summary(PROMPT_STATS_TOP_FILES)));
+            }
+            ResearchProgress::ConfirmCost(confirmation) => {
+                self.status_message = Some(format!(
+                    "Estimated cost ${:.2} is over the ${:.2} limit",
+                    confirmation.estimate.cost, confirmation.estimate.threshold
+                ));
+                self.cost_confirmation = Some(confirmation);
+            }
+            ResearchProgress::Section(section) => {
+                let task_name = self
+                    .current_task
+                    .as_ref()
+                    .map(|t| t.name.clone())
+                    .unwrap_or_default();
+                let doc = self
+                    .streamed_doc
+                    .get_or_insert_with(|| ResearchDoc::new(task_name));
+                section.apply_to(doc);
+                self.status_message = Some(format!(
+                    "Streaming research: {} findings so far",
+                    doc.codebase_analysis.len()
+                ));
+            }
             ResearchProgress::ParsingResponse => {
                 self.set_progress_status(2, ProgressStatus::Complete);
                 self.set_progress_status(3, ProgressStatus::InProgress);
             }
+            ResearchProgress::SelfCritique => {
+                self.status_message = Some("Reviewing research for gaps...".to_string());
+            }
+            ResearchProgress::FollowUpQuestions => {
+                self.status_message = Some("Drafting follow-up questions...".to_string());
+            }
+            ResearchProgress::TokenUsage(usage) => {
+                self.last_output_tokens = Some(usage.output_tokens);
+                let model = self.current_model();
+                self.session_usage
+                    .record(usage, &self.config.llm.provider, &model);
+                self.pending_run.tokens += usage;
+            }
             ResearchProgress::Complete => {
                 self.set_progress_status(3, ProgressStatus::Complete);
                 self.set_progress_status(4, ProgressStatus::Complete);
@@ -398,13 +969,7 @@ impl App {
             ResearchProgress::Error(msg) => {
                 self.chat_messages
                     .push(ChatMessage::system(format!("Error: {}", msg)));
-                // Mark current item as failed
-                for item in &mut self.progress_items {
-                    if item.status == ProgressStatus::InProgress {
-                        item.status = ProgressStatus::Failed;
-                        break;
-                    }
-                }
+                self.fail_progress(msg);
             }
         }
     }
@@ -419,10 +984,25 @@ impl App {
     /// Handle research completion - await user validation before saving.
     fn handle_research_complete(&mut self, result: ResearchResult) {
         self.is_streaming = false;
+        self.research_task = None;
+        self.research_cancel = None;
+        self.pending_run.finish();
 
         // Use the document's built-in markdown formatting for complete display
         let content = result.doc.to_markdown();
-        self.chat_messages.push(ChatMessage::assistant(&content));
+        self.finding_filter = Severity::Info;
+        // The document streamed in section by section is already shown
+        match self
+            .pending_doc_message
+            .and_then(|index| self.chat_messages.get_mut(index))
+        {
+            Some(message) => message.content = content,
+            None => {
+                self.pending_doc_message = Some(self.chat_messages.len());
+                self.chat_messages.push(ChatMessage::assistant(&content));
+            }
+        }
+        let quick_replies = follow_up_replies(&result.doc);
 
         // Set awaiting validation state (DON'T save yet - wait for approval)
         self.research_state = ResearchState::AwaitingValidation {
@@ -431,34 +1011,122 @@ impl App {
         };
 
         // Prompt user for validation
-        self.chat_messages.push(ChatMessage::system(
-            "Is this understanding correct?\n\
-             Press [a] to approve and save, or type corrections.",
-        ));
-        self.status_message =
-            Some("Awaiting approval... [a] approve, [i] type corrections".to_string());
+        match quick_replies {
+            Some(replies) => {
+                self.chat_messages.push(ChatMessage::system(format!(
+                    "Before approving, a few open questions:\n{}\n\
+                     Press a number to answer, [i] to answer in your own words, \
+                     or [a] to approve and save.",
+                    replies
+                )));
+                self.status_message = Some(
+                    "Awaiting approval... [1-9] answer, [a] approve, [i] type corrections"
+                        .to_string(),
+                );
+            }
+            None => {
+                self.chat_messages.push(ChatMessage::system(
+                    "Is this understanding correct?\n\
+                     Press [a] to approve and save, or type corrections.",
+                ));
+                self.status_message =
+                    Some("Awaiting approval... [a] approve, [i] type corrections".to_string());
+            }
+        }
+    }
+
+    /// Refine the pending research with the `number`th suggested answer to
+    /// its follow-up questions, counting from 1 across all questions.
+    fn answer_follow_up(&mut self, number: usize, event_tx: mpsc::UnboundedSender<Event>) {
+        let ResearchState::AwaitingValidation {
+            ref pending_doc, ..
+        } = self.research_state
+        else {
+            return;
+        };
+        let Some(correction) = pending_doc
+            .follow_ups
+            .iter()
+            .flat_map(|f| f.answers.iter().map(move |answer| f.correction(answer)))
+            .nth(number.saturating_sub(1))
+        else {
+            return;
+        };
+
+        self.chat_messages.push(ChatMessage::user(&correction));
+        if let ResearchState::AwaitingValidation {
+            task_id,
+            pending_doc,
+        } = std::mem::replace(&mut self.research_state, ResearchState::Refining)
+        {
+            self.refine_research(task_id, pending_doc, correction, event_tx);
+        }
+    }
+
+    /// Raise the minimum severity of findings shown in the pending research,
+    /// wrapping back to all findings after `high`.
+    fn cycle_finding_filter(&mut self) {
+        let ResearchState::AwaitingValidation {
+            ref pending_doc, ..
+        } = self.research_state
+        else {
+            return;
+        };
+
+        self.finding_filter = self.finding_filter.next();
+        let shown = pending_doc.findings_by_severity(self.finding_filter).len();
+        let total = pending_doc.codebase_analysis.len();
+        let content = pending_doc.to_markdown_filtered(self.finding_filter);
+
+        if let Some(message) = self
+            .pending_doc_message
+            .and_then(|index| self.chat_messages.get_mut(index))
+        {
+            message.content = content;
+        }
+        self.status_message = Some(match self.finding_filter {
+            Severity::Info => format!("Showing all {} findings", total),
+            min => format!(
+                "Showing {} of {} findings ({} and above)",
+                shown,
+                total,
+                min.as_str()
+            ),
+        });
     }
 
     /// Handle research failure.
     fn handle_research_failed(&mut self, error: String) {
         self.is_streaming = false;
+        self.research_task = None;
+        self.research_cancel = None;
+        self.pending_run.finish();
         self.research_state = ResearchState::Idle;
         self.chat_messages
             .push(ChatMessage::system(format!("Research failed: {}", error)));
-
-        // Mark progress as failed
-        for item in &mut self.progress_items {
-            if item.status == ProgressStatus::InProgress {
-                item.status = ProgressStatus::Failed;
-                break;
-            }
-        }
+        self.fail_progress(error);
     }
 
     /// Approve research and save - called when user presses 'a' during validation.
-    fn approve_research(&mut self, task_id: String, doc: ResearchDoc) {
+    fn approve_research(
+        &mut self,
+        task_id: String,
+        doc: ResearchDoc,
+        event_tx: mpsc::UnboundedSender<Event>,
+    ) {
         match self.manager.set_research_doc(&task_id, doc.clone()) {
             Ok(task) => {
+                let run = std::mem::take(&mut self.pending_run);
+                let record = UsageRecord::new(
+                    Phase::Research,
+                    &self.config.llm.provider,
+                    self.current_model(),
+                    run.tokens,
+                    run.elapsed,
+                );
+                let task = self.manager.record_usage(&task_id, record).unwrap_or(task);
+                let research_path = self.config.storage.local_research_path();
+                self.run_post_hook(HookEvent::PostResearch, &task, research_path, event_tx);
                 self.current_task = Some(task);
                 self.status_message = Some("Research saved to .arq/research-doc.md".to_string());
                 self.chat_messages.push(ChatMessage::system(
@@ -481,35 +1149,66 @@ impl App {
         }
     }
 
+    /// Run a non-blocking post-phase hook in the background, reporting failures.
+    fn run_post_hook(
+        &self,
+        event: HookEvent,
+        task: &Task,
+        artifact_path: PathBuf,
+        event_tx: mpsc::UnboundedSender<Event>,
+    ) {
+        let hooks = Hooks::new(self.config.hooks.clone());
+        if !hooks.is_configured(event) {
+            return;
+        }
+        let task = task.clone();
+
+        self.spawn(async move {
+            let env = [(
+                "ARQ_ARTIFACT_PATH",
+                artifact_path.to_string_lossy().into_owned(),
+            )];
+            if let Err(e) = hooks.run(event, &task, &env).await {
+                let _ = event_tx.send(Event::HookFailed(e.to_string()));
+            }
+        });
+    }
+
     /// Handle a key event.
     fn handle_key_event(&mut self, key: KeyEvent, event_tx: mpsc::UnboundedSender<Event>) {
         match self.input_mode {
-            InputMode::Normal => self.handle_normal_mode_key(key),
+            _ if self.cost_confirmation.is_some() => self.handle_cost_confirmation_key(key),
+            InputMode::Normal if self.context_review.is_some() => {
+                self.handle_context_review_key(key)
+            }
+            InputMode::Normal if self.picker.is_some() => self.handle_picker_key(key),
+            InputMode::Normal if self.viewer.is_some() => self.handle_viewer_key(key, event_tx),
+            InputMode::Normal => self.handle_normal_mode_key(key, event_tx),
             InputMode::Editing => self.handle_editing_mode_key(key, event_tx),
+            InputMode::Search => self.handle_search_mode_key(key, event_tx),
+            InputMode::Rename => self.handle_rename_mode_key(key),
         }
     }
 
     /// Handle key in normal mode.
-    fn handle_normal_mode_key(&mut self, key: KeyEvent) {
+    fn handle_normal_mode_key(&mut self, key: KeyEvent, event_tx: mpsc::UnboundedSender<Event>) {
         match key.code {
             KeyCode::Char('q') => self.should_quit = true,
             KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.should_quit = true;
             }
-            KeyCode::Tab | KeyCode::Right => {
-                let next = self.selected_tab.next();
-                if self.can_switch_to_tab(&next) {
-                    self.selected_tab = next;
-                    self.reset_progress_items();
-                }
+            KeyCode::Esc => {
+                self.cancel_research();
+                self.cancel_planning();
+                self.stop_agent();
             }
-            KeyCode::BackTab | KeyCode::Left => {
-                let prev = self.selected_tab.previous();
-                if self.can_switch_to_tab(&prev) {
-                    self.selected_tab = prev;
-                    self.reset_progress_items();
-                }
+            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.cancel_research();
+                self.cancel_planning();
+                self.stop_agent();
             }
+            KeyCode::Tab | KeyCode::Right => self.switch_tab(self.selected_tab.next()),
+            KeyCode::BackTab | KeyCode::Left => self.switch_tab(self.selected_tab.previous()),
             KeyCode::Char('i') | KeyCode::Enter => {
                 self.input_mode = InputMode::Editing;
             }
@@ -519,6 +1218,25 @@ impl App {
             KeyCode::Char('k') | KeyCode::Up => {
                 self.scroll_up();
             }
+            KeyCode::Char('a') if self.selected_tab == SelectedTab::Planner => {
+                // Approve the plan if awaiting approval
+                if let PlanningState::AwaitingApproval {
+                    task_id,
+                    pending_plan,
+                } = std::mem::replace(&mut self.planning_state, PlanningState::Idle)
+                {
+                    self.approve_plan(task_id, pending_plan, event_tx);
+                }
+            }
+            KeyCode::Char('a') if self.selected_tab == SelectedTab::Agent => {
+                self.confirm_agent_edit(true, event_tx);
+            }
+            KeyCode::Char('n') if self.selected_tab == SelectedTab::Agent => {
+                self.confirm_agent_edit(false, event_tx);
+            }
+            KeyCode::Char('g') if self.selected_tab == SelectedTab::Agent => {
+                self.start_agent(event_tx);
+            }
             KeyCode::Char('a') => {
                 // Approve research if awaiting validation
                 if let ResearchState::AwaitingValidation {
@@ -526,62 +1244,453 @@ impl App {
                     pending_doc,
                 } = std::mem::replace(&mut self.research_state, ResearchState::Idle)
                 {
-                    self.approve_research(task_id, pending_doc);
+                    self.approve_research(task_id, pending_doc, event_tx);
                 }
             }
+            KeyCode::Char('g') if self.selected_tab == SelectedTab::Planner => {
+                self.generate_plan(event_tx);
+            }
+            KeyCode::Char(digit @ '1'..='9') if self.selected_tab == SelectedTab::Researcher => {
+                self.answer_follow_up(digit as usize - '0' as usize, event_tx);
+            }
             KeyCode::Char('m') => {
                 // Cycle through available models
                 self.cycle_model();
             }
+            KeyCode::Char('s') => {
+                self.cycle_finding_filter();
+            }
+            KeyCode::Char('f') => {
+                self.open_citations();
+            }
+            KeyCode::Char('c') if self.selected_tab == SelectedTab::Researcher => {
+                self.review_context(event_tx);
+            }
+            KeyCode::Char('/') => {
+                self.input_buffer.clear();
+                self.input_mode = InputMode::Search;
+            }
+            KeyCode::Char('r') => {
+                if let Some(task) = &self.current_task {
+                    self.input_buffer = task.name.clone();
+                    self.input_mode = InputMode::Rename;
+                }
+            }
             _ => {}
         }
     }
 
-    /// Handle key in editing mode.
-    fn handle_editing_mode_key(&mut self, key: KeyEvent, event_tx: mpsc::UnboundedSender<Event>) {
+    /// Handle key while the location picker is open.
+    fn handle_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+
         match key.code {
-            KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-            }
-            KeyCode::Enter => {
-                self.submit_input(event_tx);
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
             }
-            KeyCode::Char(c) => {
-                self.input_buffer.push(c);
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.picker = None;
             }
-            KeyCode::Backspace => {
-                self.input_buffer.pop();
+            KeyCode::Char('j') | KeyCode::Down => picker.next(),
+            KeyCode::Char('k') | KeyCode::Up => picker.previous(),
+            KeyCode::Enter => {
+                if let Some(location) = picker.selected_location().cloned() {
+                    self.picker = None;
+                    self.open_location(&location);
+                }
             }
             _ => {}
         }
     }
 
-    /// Submit the current input.
-    fn submit_input(&mut self, event_tx: mpsc::UnboundedSender<Event>) {
-        if self.input_buffer.is_empty() || self.is_streaming {
-            return;
+    /// Handle key while a research prompt over the cost limit awaits
+    /// confirmation. Declining fails the run.
+    fn handle_cost_confirmation_key(&mut self, key: KeyEvent) {
+        let send = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+            _ => return,
+        };
+        if let Some(confirmation) = self.cost_confirmation.take() {
+            confirmation.answer(send);
         }
+        self.status_message = None;
+    }
 
-        let input = std::mem::take(&mut self.input_buffer);
-        self.chat_messages.push(ChatMessage::user(&input));
+    /// Handle key while the context review is open.
+    fn handle_context_review_key(&mut self, key: KeyEvent) {
+        let Some(review) = self.context_review.as_mut() else {
+            return;
+        };
 
-        match self.selected_tab {
-            SelectedTab::Researcher => {
-                // Check current state to decide action
-                match &self.research_state {
-                    ResearchState::Idle => {
-                        // New research
-                        self.start_research(input, event_tx);
-                    }
-                    ResearchState::AwaitingValidation { .. } => {
-                        // User is providing correction - extract values and refine
-                        if let ResearchState::AwaitingValidation {
-                            task_id,
-                            pending_doc,
-                        } = std::mem::replace(&mut self.research_state, ResearchState::Refining)
-                        {
-                            self.refine_research(task_id, pending_doc, input, event_tx);
-                        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.context_review = None;
+            }
+            KeyCode::Char('j') | KeyCode::Down => review.next(),
+            KeyCode::Char('k') | KeyCode::Up => review.previous(),
+            KeyCode::Char(' ') => {
+                if !review.toggle() {
+                    self.status_message = Some(
+                        "Excluded by a pattern; edit it with `arq research --review-context`"
+                            .to_string(),
+                    );
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(review) = self.context_review.take() {
+                    self.save_context_exclusions(&review);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Persist the exclusions chosen in a context review.
+    fn save_context_exclusions(&mut self, review: &ContextReview) {
+        let excluded = review.excluded();
+        let count = excluded.len();
+        match self.manager.set_excluded_context(&review.task_id, excluded) {
+            Ok(task) => {
+                if self.current_task.as_ref().is_some_and(|t| t.id == task.id) {
+                    self.current_task = Some(task);
+                }
+                self.status_message = Some(format!(
+                    "{} context exclusions saved for later research runs",
+                    count
+                ));
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Failed to save exclusions: {}", e));
+            }
+        }
+    }
+
+    /// Handle key while the file viewer is open.
+    fn handle_viewer_key(&mut self, key: KeyEvent, event_tx: mpsc::UnboundedSender<Event>) {
+        let Some(viewer) = self.viewer.as_mut() else {
+            return;
+        };
+
+        // Two-key commands
+        if let Some(first) = self.pending_key.take() {
+            match (first, key.code) {
+                ('g', KeyCode::Char('g')) => viewer.goto_top(),
+                ('g', KeyCode::Char('d')) => self.goto_definition(event_tx),
+                ('g', KeyCode::Char('s')) => self.show_entity_at_cursor(event_tx),
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if !viewer.back() {
+                    self.status_message = Some("Jump history is empty".to_string());
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.viewer = None;
+            }
+            KeyCode::Char('j') | KeyCode::Down => viewer.move_down(1),
+            KeyCode::Char('k') | KeyCode::Up => viewer.move_up(1),
+            KeyCode::Char('h') | KeyCode::Left => viewer.move_left(),
+            KeyCode::Char('l') | KeyCode::Right => viewer.move_right(),
+            KeyCode::PageDown => viewer.move_down(VIEWER_PAGE_LINES),
+            KeyCode::PageUp => viewer.move_up(VIEWER_PAGE_LINES),
+            KeyCode::Char('G') => viewer.goto_bottom(),
+            KeyCode::Char('g') => self.pending_key = Some('g'),
+            KeyCode::Char('f') => self.open_citations(),
+            KeyCode::Char('/') => {
+                self.input_buffer.clear();
+                self.input_mode = InputMode::Search;
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle key while typing a search query.
+    fn handle_search_mode_key(&mut self, key: KeyEvent, event_tx: mpsc::UnboundedSender<Event>) {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_buffer.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                let query = std::mem::take(&mut self.input_buffer);
+                self.input_mode = InputMode::Normal;
+                if !query.trim().is_empty() {
+                    self.search(query, event_tx);
+                }
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle key while editing the current task's name.
+    fn handle_rename_mode_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_buffer.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                let name = std::mem::take(&mut self.input_buffer);
+                self.input_mode = InputMode::Normal;
+                if let Some(task_id) = self.current_task.as_ref().map(|t| t.id.clone()) {
+                    self.apply_task_name(&task_id, &name);
+                }
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Rename a task and refresh the current task if it is the one renamed.
+    fn apply_task_name(&mut self, task_id: &str, name: &str) {
+        match self.manager.rename_task(task_id, name) {
+            Ok(task) => {
+                if let Some(current) = self.current_task.as_mut().filter(|t| t.id == task.id) {
+                    current.name = task.name.clone();
+                    current.updated_at = task.updated_at;
+                }
+                self.status_message = Some(format!("Task renamed: {}", task.name));
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Failed to rename task: {}", e));
+            }
+        }
+    }
+
+    /// Ask the LLM for a friendlier name for a new task in the background.
+    fn suggest_name(&self, task: &Task, event_tx: mpsc::UnboundedSender<Event>) {
+        let Ok(llm) = Provider::build_cached(&self.config.llm, &self.config.storage) else {
+            return;
+        };
+        let task_id = task.id.clone();
+        let prompt = task.prompt.clone();
+
+        self.spawn(async move {
+            if let Some(name) = suggest_task_name(llm.as_ref(), &prompt).await {
+                let _ = event_tx.send(Event::TaskNamed { task_id, name });
+            }
+        });
+    }
+
+    /// Open the picker with files cited by the current research.
+    fn open_citations(&mut self) {
+        let doc = match &self.research_state {
+            ResearchState::AwaitingValidation { pending_doc, .. } => Some(pending_doc),
+            _ => self
+                .current_task
+                .as_ref()
+                .and_then(|t| t.research_doc.as_ref()),
+        };
+
+        match doc {
+            Some(doc) => self.picker = Some(LocationPicker::from_citations(doc)),
+            None => {
+                self.status_message = Some("No research citations yet".to_string());
+            }
+        }
+    }
+
+    /// Open a file in the viewer, or jump within the open one.
+    fn open_location(&mut self, location: &SourceLocation) {
+        let result = match self.viewer.as_mut() {
+            Some(viewer) => viewer.jump_to(location),
+            None => FileViewer::open(location).map(|viewer| self.viewer = Some(viewer)),
+        };
+
+        if let Err(e) = result {
+            self.status_message = Some(format!("Cannot open {}: {}", location.path, e));
+        }
+    }
+
+    /// Run a semantic search in the background and show results in the picker.
+    fn search(&mut self, query: String, event_tx: mpsc::UnboundedSender<Event>) {
+        let kg = Arc::clone(&self.knowledge_graph);
+        let db_path = self.config.knowledge.db_full_path(&self.config.storage);
+        let limit = self.config.knowledge.search_limit;
+        self.status_message = Some(format!("Searching for '{}'...", query));
+
+        self.spawn(async move {
+            let event = match open_knowledge_graph(&kg, &db_path).await {
+                Ok(kg) => match kg.search_code(&query, limit).await {
+                    Ok(results) => Event::SearchResults { query, results },
+                    Err(e) => Event::LookupFailed(format!("Search failed: {}", e)),
+                },
+                Err(e) => Event::LookupFailed(e.to_string()),
+            };
+            let _ = event_tx.send(event);
+        });
+    }
+
+    /// List the files the current task's research would use, for review.
+    fn review_context(&mut self, event_tx: mpsc::UnboundedSender<Event>) {
+        let Some(task) = self.current_task.clone() else {
+            self.status_message = Some("No current task".to_string());
+            return;
+        };
+        if matches!(
+            self.research_state,
+            ResearchState::Researching | ResearchState::Refining
+        ) {
+            return;
+        }
+
+        let config = self.config.clone();
+        let kg = Arc::clone(&self.knowledge_graph);
+        self.status_message = Some("Gathering context files...".to_string());
+
+        self.spawn(async move {
+            let event = match context_candidates(&task, &config, &kg).await {
+                Ok(files) => Event::ContextCandidates {
+                    task_id: task.id,
+                    files,
+                },
+                Err(e) => Event::LookupFailed(format!("Cannot list context files: {}", e)),
+            };
+            let _ = event_tx.send(event);
+        });
+    }
+
+    /// Look up the identifier under the viewer cursor in the knowledge graph.
+    fn goto_definition(&mut self, event_tx: mpsc::UnboundedSender<Event>) {
+        let Some(symbol) = self.viewer.as_ref().and_then(|v| v.word_under_cursor()) else {
+            self.status_message = Some("No identifier under cursor".to_string());
+            return;
+        };
+
+        let kg = Arc::clone(&self.knowledge_graph);
+        let db_path = self.config.knowledge.db_full_path(&self.config.storage);
+
+        self.spawn(async move {
+            let event = match open_knowledge_graph(&kg, &db_path).await {
+                Ok(kg) => match kg.find_function_by_name(&symbol).await {
+                    Ok(func) => Event::DefinitionFound {
+                        symbol,
+                        location: func
+                            .map(|f| SourceLocation::new(f.file_path, f.start_line as usize)),
+                    },
+                    Err(e) => Event::LookupFailed(format!("Lookup failed: {}", e)),
+                },
+                Err(e) => Event::LookupFailed(e.to_string()),
+            };
+            let _ = event_tx.send(event);
+        });
+    }
+
+    /// Look up the innermost entity containing the viewer cursor.
+    fn show_entity_at_cursor(&mut self, event_tx: mpsc::UnboundedSender<Event>) {
+        let Some(location) = self.viewer.as_ref().map(|v| v.location()) else {
+            return;
+        };
+
+        let kg = Arc::clone(&self.knowledge_graph);
+        let db_path = self.config.knowledge.db_full_path(&self.config.storage);
+
+        self.spawn(async move {
+            let event = match open_knowledge_graph(&kg, &db_path).await {
+                Ok(kg) => match kg.entity_at(&location.path, location.line as u32).await {
+                    Ok(entity) => Event::EntityFound { location, entity },
+                    Err(e) => Event::LookupFailed(format!("Lookup failed: {}", e)),
+                },
+                Err(e) => Event::LookupFailed(e.to_string()),
+            };
+            let _ = event_tx.send(event);
+        });
+    }
+
+    /// Jump to a resolved definition.
+    fn handle_definition_found(&mut self, symbol: String, location: Option<SourceLocation>) {
+        match location {
+            Some(location) => {
+                self.open_location(&location);
+                self.status_message = Some(format!(
+                    "{} -> {}:{}  [Ctrl-O] back",
+                    symbol, location.path, location.line
+                ));
+            }
+            None => {
+                self.status_message = Some(format!("No definition found for '{}'", symbol));
+            }
+        }
+    }
+
+    /// Handle key in editing mode.
+    fn handle_editing_mode_key(&mut self, key: KeyEvent, event_tx: mpsc::UnboundedSender<Event>) {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                self.submit_input(event_tx);
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Submit the current input.
+    fn submit_input(&mut self, event_tx: mpsc::UnboundedSender<Event>) {
+        if self.input_buffer.is_empty() || self.is_streaming {
+            return;
+        }
+
+        let input = std::mem::take(&mut self.input_buffer);
+        self.chat_messages.push(ChatMessage::user(&input));
+
+        if let Some(command) = SlashCommand::parse(&input) {
+            match command {
+                Ok(command) => self.run_command(command, event_tx),
+                Err(e) => self.chat_messages.push(ChatMessage::system(e)),
+            }
+            self.input_mode = InputMode::Normal;
+            return;
+        }
+
+        match self.selected_tab {
+            SelectedTab::Researcher => {
+                // Check current state to decide action
+                match &self.research_state {
+                    ResearchState::Idle => {
+                        // New research
+                        self.start_research(input, event_tx);
+                    }
+                    ResearchState::AwaitingValidation { .. } => {
+                        // User is providing correction - extract values and refine
+                        if let ResearchState::AwaitingValidation {
+                            task_id,
+                            pending_doc,
+                        } = std::mem::replace(&mut self.research_state, ResearchState::Refining)
+                        {
+                            self.refine_research(task_id, pending_doc, input, event_tx);
+                        }
                     }
                     ResearchState::Researching | ResearchState::Refining => {
                         // Already streaming, ignore input
@@ -589,111 +1698,869 @@ impl App {
                 }
             }
             SelectedTab::Planner => {
+                if !self.planning_state.is_running() {
+                    self.chat_plan(input, event_tx);
+                }
+            }
+            SelectedTab::Agent => {
+                self.chat_messages.push(ChatMessage::system(
+                    "The agent follows the approved plan. Press [g] to generate its edits, \
+                     or discuss changes to the plan in the Planner tab.",
+                ));
+            }
+        }
+
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Run a slash-command; lookups answer through [`Event::CommandOutput`].
+    fn run_command(&mut self, command: SlashCommand, event_tx: mpsc::UnboundedSender<Event>) {
+        match command {
+            SlashCommand::Search(query) => self.search_to_chat(query, event_tx),
+            SlashCommand::Deps(name) => self.deps_to_chat(name, event_tx),
+            SlashCommand::Switch(query) => self.switch_task(&query),
+            SlashCommand::Model(Some(model)) => self.set_model(model),
+            SlashCommand::Model(None) => {
+                let current = self.current_model();
+                let models = &self.config.llm.available_models;
+                let listing = if models.is_empty() {
+                    format!("Model: {} (no available_models configured)", current)
+                } else {
+                    let lines: Vec<String> = models
+                        .iter()
+                        .map(|m| match *m == current {
+                            true => format!("* {}", m),
+                            false => format!("  {}", m),
+                        })
+                        .collect();
+                    format!("Models:\n{}", lines.join("\n"))
+                };
+                self.chat_messages.push(ChatMessage::system(listing));
+            }
+            SlashCommand::Help => {
                 self.chat_messages
-                    .push(ChatMessage::system("Planning phase not yet implemented."));
+                    .push(ChatMessage::system(SlashCommand::help()));
+            }
+        }
+    }
+
+    /// Run a semantic search in the background and list the results in the chat.
+    fn search_to_chat(&mut self, query: String, event_tx: mpsc::UnboundedSender<Event>) {
+        let kg = Arc::clone(&self.knowledge_graph);
+        let db_path = self.config.knowledge.db_full_path(&self.config.storage);
+        let limit = self.config.knowledge.search_limit;
+        self.status_message = Some(format!("Searching for '{}'...", query));
+
+        self.spawn(async move {
+            let output = match open_knowledge_graph(&kg, &db_path).await {
+                Ok(kg) => match kg.search_code(&query, limit).await {
+                    Ok(results) if results.is_empty() => format!("No results for '{}'", query),
+                    Ok(results) => {
+                        let lines: Vec<String> = results
+                            .iter()
+                            .map(|r| {
+                                format!(
+                                    "  {}:{}-{} ({:.2})",
+                                    r.path, r.start_line, r.end_line, r.score
+                                )
+                            })
+                            .collect();
+                        format!("Results for '{}':\n{}", query, lines.join("\n"))
+                    }
+                    Err(e) => format!("Search failed: {}", e),
+                },
+                Err(e) => e.to_string(),
+            };
+            let _ = event_tx.send(Event::CommandOutput(output));
+        });
+    }
+
+    /// Look up what a function calls in the background and list it in the chat.
+    fn deps_to_chat(&mut self, name: String, event_tx: mpsc::UnboundedSender<Event>) {
+        let kg = Arc::clone(&self.knowledge_graph);
+        let db_path = self.config.knowledge.db_full_path(&self.config.storage);
+
+        self.spawn(async move {
+            let output = describe_dependencies(&kg, &db_path, &name)
+                .await
+                .unwrap_or_else(|e| e.to_string());
+            let _ = event_tx.send(Event::CommandOutput(output));
+        });
+    }
+
+    /// Make the task matching `query` (ID prefix or part of its name) current.
+    fn switch_task(&mut self, query: &str) {
+        if !matches!(self.research_state, ResearchState::Idle) || self.planning_state.is_running() {
+            self.chat_messages.push(ChatMessage::system(
+                "Finish or cancel the current run before switching tasks.",
+            ));
+            return;
+        }
+
+        let message = match self.manager.find_tasks(query) {
+            Ok(tasks) if tasks.is_empty() => format!("No task matches '{}'", query),
+            Ok(tasks) if tasks.len() > 1 => {
+                let lines: Vec<String> = tasks
+                    .iter()
+                    .map(|t| format!("  {} - {}", &t.id[..8.min(t.id.len())], t.name))
+                    .collect();
+                format!(
+                    "'{}' matches {} tasks, be more specific:\n{}",
+                    query,
+                    tasks.len(),
+                    lines.join("\n")
+                )
+            }
+            Ok(tasks) => match self
+                .manager
+                .set_current_task(&tasks[0].id)
+                .and_then(|_| self.manager.get_task(&tasks[0].id))
+            {
+                Ok(task) => {
+                    let message = format!(
+                        "Switched to task: {} ({})",
+                        task.name,
+                        task.phase.display_name()
+                    );
+                    self.current_task = Some(task);
+                    self.pin_research();
+                    message
+                }
+                Err(e) => format!("Failed to switch task: {}", e),
+            },
+            Err(e) => format!("Failed to find tasks: {}", e),
+        };
+        self.chat_messages.push(ChatMessage::system(message));
+    }
+
+    /// Start a research task with streaming.
+    fn start_research(&mut self, prompt: String, event_tx: mpsc::UnboundedSender<Event>) {
+        self.is_streaming = true;
+        self.stream_buffer.clear();
+        self.streamed_doc = None;
+        self.pending_doc_message = None;
+        self.reset_progress_items();
+        self.status_message = Some("Starting research...".to_string());
+        self.pending_run = PendingRun::default();
+        self.pending_run.start();
+
+        // Create task via manager (persists immediately)
+        let task = match self.manager.create_task(&prompt) {
+            Ok(task) => task,
+            Err(e) => {
+                self.chat_messages
+                    .push(ChatMessage::system(format!("Failed to create task: {}", e)));
+                self.is_streaming = false;
+                return;
+            }
+        };
+
+        let task_id = task.id.clone();
+        self.current_task = Some(task.clone());
+        self.suggest_name(&task, event_tx.clone());
+
+        // Get config values we need
+        let config = self.config.clone();
+
+        // Get the knowledge graph db path for semantic search
+        let kg_db_path = config.knowledge.db_full_path(&config.storage);
+        let kg = Arc::clone(&self.knowledge_graph);
+
+        // Add message to show research is starting
+        self.chat_messages.push(ChatMessage::system(format!(
+            "Researching: {} ...",
+            task.prompt
+        )));
+
+        // Spawn the research task
+        let cancel = CancellationToken::new();
+        self.research_cancel = Some(cancel.clone());
+        self.research_task = Some(self.spawn(async move {
+            match run_research_task(
+                task,
+                config,
+                kg,
+                kg_db_path,
+                Vec::new(),
+                None,
+                cancel,
+                event_tx.clone(),
+            )
+            .await
+            {
+                Ok(doc) => {
+                    let _ = event_tx.send(Event::ResearchComplete(ResearchResult { task_id, doc }));
+                }
+                Err(error) => {
+                    let _ = event_tx.send(Event::ResearchFailed(error));
+                }
+            }
+        }));
+
+        // Set state to Researching
+        self.research_state = ResearchState::Researching;
+    }
+
+    /// Refine research based on user correction.
+    fn refine_research(
+        &mut self,
+        task_id: String,
+        original_doc: ResearchDoc,
+        correction: String,
+        event_tx: mpsc::UnboundedSender<Event>,
+    ) {
+        self.is_streaming = true;
+        self.stream_buffer.clear();
+        self.streamed_doc = None;
+        self.pending_doc_message = None;
+        self.reset_progress_items();
+        self.research_state = ResearchState::Refining;
+        self.pending_run.start();
+
+        // The refinement prompt carries the previous research and the
+        // correction, plus only the context not sent for the task yet
+        let mut task = self
+            .current_task
+            .clone()
+            .filter(|t| t.id == task_id)
+            .unwrap_or_else(|| Task::new(&original_doc.task_name));
+        task.id = task_id.clone();
+
+        // Retrieve with both the original prompt and the correction, so code
+        // that only the correction mentions is found too
+        let context_queries = vec![task.prompt.clone(), correction.clone()];
+        let refinement = Refinement::new(original_doc, correction);
+
+        let config = self.config.clone();
+        let task_id_clone = task_id.clone();
+        let kg_db_path = config.knowledge.db_full_path(&config.storage);
+        let kg = Arc::clone(&self.knowledge_graph);
+
+        // Spawn the refinement task (reuses run_research_task)
+        let cancel = CancellationToken::new();
+        self.research_cancel = Some(cancel.clone());
+        self.research_task = Some(self.spawn(async move {
+            match run_research_task(
+                task,
+                config,
+                kg,
+                kg_db_path,
+                context_queries,
+                Some(refinement),
+                cancel,
+                event_tx.clone(),
+            )
+            .await
+            {
+                Ok(doc) => {
+                    // Return with original task_id so we save to the right task
+                    let _ = event_tx.send(Event::ResearchComplete(ResearchResult {
+                        task_id: task_id_clone,
+                        doc,
+                    }));
+                }
+                Err(error) => {
+                    let _ = event_tx.send(Event::ResearchFailed(error));
+                }
+            }
+        }));
+    }
+
+    /// Abort the running research or refinement and return to Idle.
+    fn cancel_research(&mut self) {
+        if !matches!(
+            self.research_state,
+            ResearchState::Researching | ResearchState::Refining
+        ) {
+            return;
+        }
+        self.cost_confirmation = None;
+        // Cancel the LLM request first so the provider stops generating
+        if let Some(cancel) = self.research_cancel.take() {
+            cancel.cancel();
+        }
+        if let Some(handle) = self.research_task.take() {
+            handle.abort();
+        }
+
+        self.is_streaming = false;
+        self.stream_buffer.clear();
+        self.streamed_doc = None;
+        self.pending_run = PendingRun::default();
+        self.research_state = ResearchState::Idle;
+        self.fail_progress("Cancelled");
+        self.chat_messages
+            .push(ChatMessage::system("Research cancelled."));
+        self.status_message = Some("Research cancelled".to_string());
+    }
+
+    /// Pin the current task's approved research in the planner conversation.
+    ///
+    /// Starts a fresh conversation the first time the Planner tab is opened
+    /// for a task.
+    fn pin_research(&mut self) {
+        if self.selected_tab != SelectedTab::Planner {
+            return;
+        }
+        let Some(task) = &self.current_task else {
+            return;
+        };
+        let Some(doc) = &task.research_doc else {
+            return;
+        };
+        if self.pinned_research.as_deref() == Some(task.id.as_str()) {
+            return;
+        }
+
+        self.pinned_research = Some(task.id.clone());
+        self.planning_history.clear();
+        self.planning_state = PlanningState::Idle;
+        self.pending_plan_run = PendingRun::default();
+        self.chat_messages.push(ChatMessage::system(format!(
+            "Research pinned for planning:\n{}\n\n\
+             Discuss the plan structure, then press [g] to generate the plan.",
+            doc.summary
+        )));
+        self.set_progress_status(0, ProgressStatus::Complete);
+    }
+
+    /// Send a message about the plan structure to the planner.
+    fn chat_plan(&mut self, message: String, event_tx: mpsc::UnboundedSender<Event>) {
+        // Discussing a generated plan discards it; generate again when done
+        if matches!(self.planning_state, PlanningState::AwaitingApproval { .. }) {
+            self.chat_messages.push(ChatMessage::system(
+                "Plan discarded. Press [g] to generate it again when ready.",
+            ));
+        }
+
+        let job = PlanningJob::Chat {
+            history: self.planning_history.clone(),
+            message: message.clone(),
+        };
+        if self.start_planning_run(job, event_tx) {
+            self.planning_history.push(PlanningMessage::user(message));
+            self.planning_state = PlanningState::Chatting;
+        }
+    }
+
+    /// Generate the structured plan from the research and the conversation.
+    fn generate_plan(&mut self, event_tx: mpsc::UnboundedSender<Event>) {
+        if self.planning_state.is_running() || self.is_streaming {
+            return;
+        }
+        // Regenerating a plan that wasn't approved asks the model again
+        let job = PlanningJob::Generate {
+            history: self.planning_history.clone(),
+            refresh: matches!(self.planning_state, PlanningState::AwaitingApproval { .. }),
+        };
+        if self.start_planning_run(job, event_tx) {
+            self.planning_state = PlanningState::Generating;
+            self.status_message = Some("Generating plan...".to_string());
+        }
+    }
+
+    /// Spawn a planner run for the current task; false if it has no research.
+    fn start_planning_run(
+        &mut self,
+        job: PlanningJob,
+        event_tx: mpsc::UnboundedSender<Event>,
+    ) -> bool {
+        let Some(task) = self
+            .current_task
+            .clone()
+            .filter(|t| t.research_doc.is_some())
+        else {
+            self.chat_messages.push(ChatMessage::system(
+                "No approved research to plan from. Complete research first.",
+            ));
+            return false;
+        };
+
+        self.is_streaming = true;
+        self.stream_buffer.clear();
+        self.reset_progress_items();
+        self.pending_plan_run.start();
+
+        let task_id = task.id.clone();
+        let config = self.config.clone();
+        let cancel = CancellationToken::new();
+        self.planning_cancel = Some(cancel.clone());
+        self.planning_task = Some(self.spawn(async move {
+            let event = match run_planning_task(task, config, job, cancel, event_tx.clone()).await {
+                Ok(PlanningOutcome::Reply(reply)) => Event::PlanningReply(reply),
+                Ok(PlanningOutcome::Plan(plan)) => {
+                    Event::PlanGenerated(PlanResult { task_id, plan })
+                }
+                Err(error) => Event::PlanningFailed(error),
+            };
+            let _ = event_tx.send(event);
+        }));
+        true
+    }
+
+    /// Handle planning progress updates.
+    fn handle_planning_progress(&mut self, progress: PlanningProgress) {
+        match progress {
+            PlanningProgress::Started => {
+                self.set_progress_status(0, ProgressStatus::Complete);
+            }
+            PlanningProgress::CallingLLM => {
+                self.set_progress_status(1, ProgressStatus::InProgress);
+            }
+            PlanningProgress::TokenUsage(usage) => {
+                let model = self.current_model();
+                self.session_usage
+                    .record(usage, &self.config.llm.provider, &model);
+                self.pending_plan_run.tokens += usage;
+            }
+            PlanningProgress::ParsingResponse => {
+                self.set_progress_status(1, ProgressStatus::Complete);
+                self.set_progress_status(2, ProgressStatus::InProgress);
+            }
+            PlanningProgress::Complete => {
+                for item in &mut self.progress_items {
+                    if item.status == ProgressStatus::InProgress {
+                        item.status = ProgressStatus::Complete;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finish a planner run, moving the task into the Planning phase on its
+    /// first successful run (the pre-plan hook has passed by then).
+    fn finish_planning_run(&mut self) {
+        self.is_streaming = false;
+        self.planning_task = None;
+        self.planning_cancel = None;
+        self.pending_plan_run.finish();
+
+        let Some(task) = &self.current_task else {
+            return;
+        };
+        if task.phase != Phase::Research {
+            return;
+        }
+        let task_id = task.id.clone();
+        match self
+            .manager
+            .advance_phase(&task_id)
+            .and_then(|_| self.manager.get_task(&task_id))
+        {
+            Ok(task) => self.current_task = Some(task),
+            Err(e) => {
+                self.status_message = Some(format!("Failed to start planning: {}", e));
+            }
+        }
+    }
+
+    /// Handle the end of a planner chat turn.
+    fn handle_planning_reply(&mut self, reply: String) {
+        self.finish_planning_run();
+        self.planning_state = PlanningState::Idle;
+        self.planning_history
+            .push(PlanningMessage::assistant(reply));
+        self.status_message = Some("[i] continue discussing, [g] generate plan".to_string());
+    }
+
+    /// Handle a generated plan - await user approval before saving.
+    fn handle_plan_generated(&mut self, result: PlanResult) {
+        self.finish_planning_run();
+
+        let content = match result.plan.to_yaml() {
+            Ok(yaml) => format!("```yaml\n{}```", yaml),
+            Err(e) => format!("Generated plan could not be displayed: {}", e),
+        };
+        self.chat_messages.push(ChatMessage::assistant(content));
+
+        self.planning_state = PlanningState::AwaitingApproval {
+            task_id: result.task_id,
+            pending_plan: result.plan,
+        };
+        self.chat_messages.push(ChatMessage::system(
+            "Does this plan look right?\n\
+             Press [a] to approve and save, [g] to regenerate, or type changes to keep refining.",
+        ));
+        self.status_message =
+            Some("Awaiting approval... [a] approve, [g] regenerate, [i] discuss".to_string());
+    }
+
+    /// Handle planner failure.
+    fn handle_planning_failed(&mut self, error: String) {
+        self.is_streaming = false;
+        self.planning_task = None;
+        self.planning_cancel = None;
+        self.pending_plan_run.finish();
+        self.planning_state = PlanningState::Idle;
+        self.chat_messages
+            .push(ChatMessage::system(format!("Planning failed: {}", error)));
+        self.fail_progress(error);
+    }
+
+    /// Approve the plan and save - called when user presses 'a' on a generated plan.
+    fn approve_plan(
+        &mut self,
+        task_id: String,
+        plan: Plan,
+        event_tx: mpsc::UnboundedSender<Event>,
+    ) {
+        match self.manager.set_plan(&task_id, plan.clone()) {
+            Ok(task) => {
+                let run = std::mem::take(&mut self.pending_plan_run);
+                let record = UsageRecord::new(
+                    Phase::Planning,
+                    &self.config.llm.provider,
+                    self.current_model(),
+                    run.tokens,
+                    run.elapsed,
+                );
+                let task = self.manager.record_usage(&task_id, record).unwrap_or(task);
+                let plan_path = self.config.storage.local_plan_path();
+                self.run_post_hook(HookEvent::PostPlan, &task, plan_path, event_tx);
+                self.current_task = Some(task);
+                self.status_message = Some("Plan saved to .arq/plan.yaml".to_string());
+                self.chat_messages.push(ChatMessage::system(
+                    "Plan approved and saved. Switch to the Agent tab and press [g] to carry it out.",
+                ));
+                self.set_progress_status(3, ProgressStatus::Complete);
             }
-            SelectedTab::Agent => {
+            Err(e) => {
                 self.chat_messages
-                    .push(ChatMessage::system("Agent phase not yet implemented."));
+                    .push(ChatMessage::system(format!("Failed to save plan: {}", e)));
+                // Restore state for retry
+                self.planning_state = PlanningState::AwaitingApproval {
+                    task_id,
+                    pending_plan: plan,
+                };
             }
         }
-
-        self.input_mode = InputMode::Normal;
     }
 
-    /// Start a research task with streaming.
-    fn start_research(&mut self, prompt: String, event_tx: mpsc::UnboundedSender<Event>) {
-        self.is_streaming = true;
+    /// Abort the running planner chat turn or plan generation.
+    fn cancel_planning(&mut self) {
+        if !self.planning_state.is_running() {
+            return;
+        }
+        if let Some(cancel) = self.planning_cancel.take() {
+            cancel.cancel();
+        }
+        if let Some(handle) = self.planning_task.take() {
+            handle.abort();
+        }
+
+        // A cancelled turn is dropped from the conversation
+        if matches!(self.planning_state, PlanningState::Chatting) {
+            self.planning_history.pop();
+        }
+        self.is_streaming = false;
         self.stream_buffer.clear();
-        self.reset_progress_items();
-        self.status_message = Some("Starting research...".to_string());
+        self.pending_plan_run.finish();
+        self.planning_state = PlanningState::Idle;
+        self.fail_progress("Cancelled");
+        self.chat_messages
+            .push(ChatMessage::system("Planning cancelled."));
+        self.status_message = Some("Planning cancelled".to_string());
+    }
 
-        // Create task via manager (persists immediately)
-        let task = match self.manager.create_task(&prompt) {
-            Ok(task) => task,
-            Err(e) => {
-                self.chat_messages
-                    .push(ChatMessage::system(format!("Failed to create task: {}", e)));
-                self.is_streaming = false;
+    /// Start carrying out the current task's plan, moving the task into the
+    /// Agent phase.
+    fn start_agent(&mut self, event_tx: mpsc::UnboundedSender<Event>) {
+        if self.agent_state.is_active() || self.is_streaming {
+            return;
+        }
+        let Some(task) = self.current_task.clone() else {
+            return;
+        };
+        let Some(plan) = task.plan.clone() else {
+            self.chat_messages.push(ChatMessage::system(
+                "No approved plan to carry out. Complete planning first.",
+            ));
+            return;
+        };
+        match task.phase {
+            Phase::Agent => {}
+            // A plan approved in the TUI leaves the task in Planning
+            Phase::Planning => match self
+                .manager
+                .advance_phase(&task.id)
+                .and_then(|_| self.manager.get_task(&task.id))
+            {
+                Ok(task) => self.current_task = Some(task),
+                Err(e) => {
+                    self.status_message = Some(format!("Failed to start the agent: {}", e));
+                    return;
+                }
+            },
+            phase => {
+                self.chat_messages.push(ChatMessage::system(format!(
+                    "Task is in {} phase, not Agent phase.",
+                    phase.display_name()
+                )));
                 return;
             }
+        }
+
+        self.agent_items = AgentExecutor::new(plan).items();
+        self.agent_results.clear();
+        self.pending_agent_run = PendingRun::default();
+        self.reset_progress_items();
+        self.set_progress_status(0, ProgressStatus::Complete);
+        self.chat_messages.push(ChatMessage::system(format!(
+            "Carrying out the plan: {} file(s) to write.",
+            self.agent_items.len()
+        )));
+        self.generate_agent_edit(0, event_tx);
+    }
+
+    /// Generate the edit of the plan item at `index`, or finish the run
+    /// after the last one.
+    fn generate_agent_edit(&mut self, index: usize, event_tx: mpsc::UnboundedSender<Event>) {
+        let Some(item) = self.agent_items.get(index).cloned() else {
+            self.finish_agent(true);
+            return;
+        };
+        let Some(plan) = self.current_task.as_ref().and_then(|t| t.plan.clone()) else {
+            self.finish_agent(false);
+            return;
         };
 
-        let task_id = task.id.clone();
-        self.current_task = Some(task.clone());
+        self.agent_state = AgentState::Generating { index };
+        self.pending_agent_run.start();
+        if let Some(progress) = self.progress_items.get_mut(1) {
+            progress.status = ProgressStatus::InProgress;
+            progress.detail = Some(format!(
+                "{}/{} {}",
+                index + 1,
+                self.agent_items.len(),
+                item.path()
+            ));
+        }
+        self.set_progress_status(2, ProgressStatus::Pending);
+        self.set_progress_status(3, ProgressStatus::Pending);
 
-        // Get config values we need
         let config = self.config.clone();
+        let cancel = CancellationToken::new();
+        self.agent_cancel = Some(cancel.clone());
+        self.agent_task = Some(self.spawn(async move {
+            let event = match run_agent_step(plan, item, config, cancel, event_tx.clone()).await {
+                Ok(edit) => Event::AgentEditGenerated(edit),
+                Err(error) => Event::AgentFailed(error),
+            };
+            let _ = event_tx.send(event);
+        }));
+    }
 
-        // Get the knowledge graph db path for semantic search
-        let kg_db_path = config.knowledge.db_full_path(&config.storage);
+    /// Handle agent progress updates.
+    fn handle_agent_progress(&mut self, progress: AgentProgress) {
+        match progress {
+            AgentProgress::GeneratingEdit { path } => {
+                self.status_message = Some(format!("Generating {}...", path));
+            }
+            AgentProgress::TokenUsage(usage) => {
+                let model = self.current_model();
+                self.session_usage
+                    .record(usage, &self.config.llm.provider, &model);
+                self.pending_agent_run.tokens += usage;
+            }
+        }
+    }
 
-        // Add message to show research is starting
+    /// Show a generated edit as a diff and wait for it to be applied or skipped.
+    fn handle_agent_edit(&mut self, edit: FileEdit, event_tx: mpsc::UnboundedSender<Event>) {
+        self.agent_task = None;
+        self.agent_cancel = None;
+        self.pending_agent_run.finish();
+        let AgentState::Generating { index } = self.agent_state else {
+            return;
+        };
+        self.set_progress_status(1, ProgressStatus::Complete);
+
+        if edit.is_unchanged() {
+            self.chat_messages.push(ChatMessage::system(format!(
+                "{} needs no changes.",
+                edit.path()
+            )));
+            self.generate_agent_edit(index + 1, event_tx);
+            return;
+        }
+
+        self.chat_messages.push(ChatMessage::assistant(format!(
+            "```diff\n{}```",
+            edit.change().diff
+        )));
+        if let Some(plan) = self.current_task.as_ref().and_then(|t| t.plan.as_ref()) {
+            let result = edit.result(plan);
+            if !result.deviations.is_empty() {
+                self.chat_messages.push(ChatMessage::system(format!(
+                    "Deviations from the plan:\n- {}",
+                    result.deviations.join("\n- ")
+                )));
+            }
+        }
+        self.set_progress_status(2, ProgressStatus::Complete);
+        self.set_progress_status(3, ProgressStatus::InProgress);
         self.chat_messages.push(ChatMessage::system(format!(
-            "Researching: {} ...",
-            task.prompt
+            "[{}/{}] Apply this edit to {}?\n\
+             Press [a] to apply, [n] to skip, or Esc to stop.",
+            index + 1,
+            self.agent_items.len(),
+            edit.path()
         )));
+        self.status_message =
+            Some("Awaiting confirmation... [a] apply, [n] skip, [Esc] stop".to_string());
+        self.agent_state = AgentState::AwaitingConfirmation { index, edit };
+    }
 
-        // Spawn the research task
-        tokio::spawn(async move {
-            match run_research_task(task, config, kg_db_path, event_tx.clone()).await {
-                Ok(doc) => {
-                    let _ = event_tx.send(Event::ResearchComplete(ResearchResult { task_id, doc }));
-                }
-                Err(error) => {
-                    let _ = event_tx.send(Event::ResearchFailed(error));
+    /// Apply or skip the edit awaiting confirmation, then generate the next.
+    fn confirm_agent_edit(&mut self, apply: bool, event_tx: mpsc::UnboundedSender<Event>) {
+        let AgentState::AwaitingConfirmation { index, edit } =
+            std::mem::replace(&mut self.agent_state, AgentState::Idle)
+        else {
+            return;
+        };
+
+        if apply {
+            // A replay leaves the working tree alone
+            let applied = match self.replay {
+                Some(_) => Ok(()),
+                None => edit.apply(Path::new(".")),
+            };
+            if let Err(e) = applied {
+                self.chat_messages.push(ChatMessage::system(format!(
+                    "Failed to apply {}: {}",
+                    edit.path(),
+                    e
+                )));
+                self.agent_state = AgentState::AwaitingConfirmation { index, edit };
+                return;
+            }
+            if let Some(task) = self.current_task.clone() {
+                if let Some(plan) = &task.plan {
+                    self.agent_results.push(edit.result(plan));
                 }
+                let path = PathBuf::from(edit.path());
+                self.run_post_hook(HookEvent::PostAgentStep, &task, path, event_tx.clone());
             }
-        });
-
-        // Set state to Researching
-        self.research_state = ResearchState::Researching;
+            self.chat_messages
+                .push(ChatMessage::system(format!("Applied {}.", edit.path())));
+        } else {
+            self.chat_messages
+                .push(ChatMessage::system(format!("Skipped {}.", edit.path())));
+        }
+        self.generate_agent_edit(index + 1, event_tx);
     }
 
-    /// Refine research based on user correction.
-    fn refine_research(
-        &mut self,
-        task_id: String,
-        original_doc: ResearchDoc,
-        correction: String,
-        event_tx: mpsc::UnboundedSender<Event>,
-    ) {
-        self.is_streaming = true;
-        self.stream_buffer.clear();
-        self.reset_progress_items();
-        self.research_state = ResearchState::Refining;
+    /// Handle a failed edit generation, ending the run.
+    fn handle_agent_failed(&mut self, error: String) {
+        self.chat_messages
+            .push(ChatMessage::system(format!("Agent failed: {}", error)));
+        self.fail_progress(error);
+        self.finish_agent(false);
+    }
 
-        // Build refinement prompt that includes original findings + correction
-        let refinement_prompt = format!(
-            "Previous research findings:\n\n## Summary\n{}\n\n## Suggested Approach\n{}\n\n---\n\n\
-             User correction/feedback:\n{}\n\n\
-             Please update the research based on this feedback. \
-             Address the user's concerns and provide corrected findings.",
-            original_doc.summary, original_doc.suggested_approach, correction
-        );
+    /// Stop the agent run, keeping the edits applied so far.
+    fn stop_agent(&mut self) {
+        if !self.agent_state.is_active() {
+            return;
+        }
+        if let Some(cancel) = self.agent_cancel.take() {
+            cancel.cancel();
+        }
+        if let Some(handle) = self.agent_task.take() {
+            handle.abort();
+        }
+        self.fail_progress("Stopped");
+        self.finish_agent(false);
+    }
 
-        // Create a temporary task for the refinement (uses refinement prompt)
-        let task = Task::new(&refinement_prompt);
+    /// End the agent run: save the agent log and usage, and complete the task
+    /// if every plan item was handled.
+    fn finish_agent(&mut self, completed: bool) {
+        self.agent_state = AgentState::Idle;
+        self.agent_task = None;
+        self.agent_cancel = None;
+        self.pending_agent_run.finish();
+        let Some(mut task) = self.current_task.clone() else {
+            return;
+        };
 
-        let config = self.config.clone();
-        let task_id_clone = task_id.clone();
-        let kg_db_path = config.knowledge.db_full_path(&config.storage);
+        let results = std::mem::take(&mut self.agent_results);
+        let storage = FileStorage::with_config(self.config.storage.clone());
+        if let Err(e) = storage.save_agent_log(&task.id, &results) {
+            self.chat_messages.push(ChatMessage::system(format!(
+                "Failed to save the agent log: {}",
+                e
+            )));
+        }
+        let run = std::mem::take(&mut self.pending_agent_run);
+        if run.tokens.total() > 0 {
+            let record = UsageRecord::new(
+                Phase::Agent,
+                &self.config.llm.provider,
+                self.current_model(),
+                run.tokens,
+                run.elapsed,
+            );
+            task = self.manager.record_usage(&task.id, record).unwrap_or(task);
+        }
 
-        // Spawn the refinement task (reuses run_research_task)
-        tokio::spawn(async move {
-            match run_research_task(task, config, kg_db_path, event_tx.clone()).await {
-                Ok(doc) => {
-                    // Return with original task_id so we save to the right task
-                    let _ = event_tx.send(Event::ResearchComplete(ResearchResult {
-                        task_id: task_id_clone,
-                        doc,
-                    }));
-                }
-                Err(error) => {
-                    let _ = event_tx.send(Event::ResearchFailed(error));
-                }
+        if !completed {
+            self.current_task = Some(task);
+            self.chat_messages.push(ChatMessage::system(format!(
+                "Agent stopped after applying {} edit(s). Press [g] to run it again.",
+                results.len()
+            )));
+            self.status_message = Some("Agent stopped".to_string());
+            return;
+        }
+        match self
+            .manager
+            .advance_phase(&task.id)
+            .and_then(|_| self.manager.get_task(&task.id))
+        {
+            Ok(task) => self.current_task = Some(task),
+            Err(e) => {
+                self.current_task = Some(task);
+                self.status_message = Some(format!("Failed to complete the task: {}", e));
+                return;
             }
-        });
+        }
+        for item in &mut self.progress_items {
+            item.status = ProgressStatus::Complete;
+        }
+        self.chat_messages.push(ChatMessage::system(format!(
+            "Applied {} of {} edit(s). Task complete; review the changes with `arq check`.",
+            results.len(),
+            self.agent_items.len()
+        )));
+        self.status_message = Some("Task complete".to_string());
+    }
+
+    /// Elapsed time and rough ETA of the running research, for the progress panel.
+    pub fn research_timing(&self) -> Option<(Duration, Option<Duration>)> {
+        if !matches!(
+            self.research_state,
+            ResearchState::Researching | ResearchState::Refining
+        ) {
+            return None;
+        }
+        let expected = self
+            .last_output_tokens
+            .unwrap_or(ETA_DEFAULT_OUTPUT_TOKENS)
+            .min(self.config.llm.max_tokens as usize);
+        Some((
+            self.pending_run.current_elapsed()?,
+            self.pending_run.eta(expected),
+        ))
+    }
+
+    /// Switch to `tab` if its phase is unlocked.
+    fn switch_tab(&mut self, tab: SelectedTab) {
+        if tab != self.selected_tab && self.can_switch_to_tab(&tab) {
+            self.selected_tab = tab;
+            self.reset_progress_items();
+            self.pin_research();
+        }
     }
 
     /// Check if we can switch to the given tab.
@@ -715,7 +2582,7 @@ impl App {
                 true
             }
             SelectedTab::Agent => {
-                // Requires saved plan (not implemented yet, allow for now)
+                // Requires saved plan
                 let has_plan = self
                     .current_task
                     .as_ref()
@@ -730,6 +2597,68 @@ impl App {
         }
     }
 
+    /// Handle a click, drag or wheel step.
+    fn handle_mouse_event(&mut self, event: MouseEvent) {
+        let (column, row) = (event.column, event.row);
+        match event.kind {
+            MouseEventKind::ScrollUp => match self.viewer.as_mut() {
+                Some(viewer) => viewer.move_up(mouse::SCROLL_LINES),
+                None => {
+                    self.scroll_offset = self.scroll_offset.saturating_add(mouse::SCROLL_LINES);
+                    self.selection = None;
+                }
+            },
+            MouseEventKind::ScrollDown => match self.viewer.as_mut() {
+                Some(viewer) => viewer.move_down(mouse::SCROLL_LINES),
+                None => {
+                    self.scroll_offset = self.scroll_offset.saturating_sub(mouse::SCROLL_LINES);
+                    self.selection = None;
+                }
+            },
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.selection = None;
+                if let Some(tab) = self
+                    .hit_areas
+                    .tab_at(column, row)
+                    .and_then(SelectedTab::from_index)
+                {
+                    self.switch_tab(tab);
+                } else if let Some(index) = self.hit_areas.progress_item_at(column, row) {
+                    if let Some(item) = self.progress_items.get_mut(index) {
+                        if item.detail.is_some() {
+                            item.expanded = !item.expanded;
+                        }
+                    }
+                } else if self.viewer.is_none() {
+                    self.selection = self
+                        .hit_areas
+                        .chat_position(column, row)
+                        .map(Selection::new);
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(selection) = self.selection.as_mut() {
+                    selection.head = self.hit_areas.clamped_chat_position(column, row);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                let Some(selection) = self.selection else {
+                    return;
+                };
+                if selection.is_empty() {
+                    self.selection = None;
+                    return;
+                }
+                let text = self.hit_areas.selected_text(&selection);
+                self.status_message = Some(match mouse::copy_to_clipboard(&text) {
+                    Ok(()) => format!("Copied {} characters", text.chars().count()),
+                    Err(e) => format!("Copy failed: {}", e),
+                });
+            }
+            _ => {}
+        }
+    }
+
     /// Scroll chat up.
     fn scroll_up(&mut self) {
         self.scroll_offset = self.scroll_offset.saturating_add(1);
@@ -763,57 +2692,180 @@ impl App {
         ));
     }
 
+    /// Use `model` for later LLM calls.
+    fn set_model(&mut self, model: String) {
+        let models = &self.config.llm.available_models;
+        if let Some(index) = models.iter().position(|m| *m == model) {
+            self.selected_model_index = index;
+        }
+        self.config.llm.model = Some(model.clone());
+        self.chat_messages
+            .push(ChatMessage::system(format!("Model: {}", model)));
+    }
+
     /// Get the current model name for display.
     pub fn current_model(&self) -> String {
         self.config.llm.model_or_default()
     }
 }
 
+/// The follow-up questions of `doc`, with their suggested answers numbered
+/// across questions as quick replies, if it has any.
+fn follow_up_replies(doc: &ResearchDoc) -> Option<String> {
+    let mut number = 0;
+    let lines: Vec<String> = doc
+        .follow_ups
+        .iter()
+        .map(|follow_up| {
+            let answers: Vec<String> = follow_up
+                .answers
+                .iter()
+                .map(|answer| {
+                    number += 1;
+                    format!("[{}] {}", number, answer)
+                })
+                .collect();
+            if answers.is_empty() {
+                format!("- {}", follow_up.question)
+            } else {
+                format!("- {}\n    {}", follow_up.question, answers.join("  "))
+            }
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The functions `name` calls, as listed by `/deps`.
+async fn describe_dependencies(
+    kg: &SharedKnowledgeGraph,
+    db_path: &Path,
+    name: &str,
+) -> Result<String, KnowledgeError> {
+    let kg = open_knowledge_graph(kg, db_path).await?;
+    let Some(func) = kg.find_function_by_name(name).await? else {
+        return Ok(format!("No function named '{}' is indexed", name));
+    };
+    let location = format!("{}:{}", func.file_path, func.start_line);
+    let deps = kg.get_dependencies(name).await?;
+    if deps.is_empty() {
+        return Ok(format!(
+            "'{}' ({}) has no outgoing calls recorded",
+            name, location
+        ));
+    }
+    let lines: Vec<String> = deps.iter().map(|d| format!("  → {}", d)).collect();
+    Ok(format!(
+        "'{}' ({}) calls:\n{}",
+        name,
+        location,
+        lines.join("\n")
+    ))
+}
+
+/// Open the shared knowledge graph, refusing to create one that was never indexed.
+async fn open_knowledge_graph(
+    kg: &SharedKnowledgeGraph,
+    db_path: &Path,
+) -> Result<Arc<KnowledgeGraph>, KnowledgeError> {
+    if !db_path.exists() {
+        return Err(KnowledgeError::NotInitialized);
+    }
+    kg.get_or_try_init(|| async { KnowledgeGraph::open(db_path).await.map(Arc::new) })
+        .await
+        .cloned()
+}
+
+/// Files the research context for `task` would be gathered from.
+///
+/// Uses the knowledge graph when `arq init` has built one, otherwise scans
+/// files, as research does.
+async fn context_candidates(
+    task: &Task,
+    config: &Config,
+    kg: &SharedKnowledgeGraph,
+) -> Result<Vec<String>, String> {
+    let llm = Provider::build_from_config(&config.llm).map_err(|e| e.to_string())?;
+    let context_builder = ContextBuilder::with_config(".", config.context.clone());
+    let db_path = config.knowledge.db_full_path(&config.storage);
+    let runner = match open_knowledge_graph(kg, &db_path).await {
+        Ok(kg) => ResearchRunner::with_knowledge_store(
+            llm,
+            context_builder,
+            kg as Arc<dyn KnowledgeStore>,
+        ),
+        Err(_) => ResearchRunner::new(llm, context_builder),
+    }
+    .with_rerank(config.research.rerank);
+
+    runner
+        .context_candidates(task)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Run a research task with streaming and progress updates.
+/// `context_queries` overrides the semantic search queries (empty: the task prompt).
+/// Cancelling `cancel` stops the in-flight LLM request.
 /// Returns the full ResearchDoc for persistence.
 async fn run_research_task(
     task: Task,
     config: Config,
+    kg: SharedKnowledgeGraph,
     kg_db_path: std::path::PathBuf,
+    context_queries: Vec<String>,
+    refinement: Option<Refinement>,
+    cancel: CancellationToken,
     event_tx: mpsc::UnboundedSender<Event>,
 ) -> Result<arq_core::ResearchDoc, String> {
     use arq_core::{ClaudeClient, OpenAIClient, StreamChunk};
     use std::env;
-    use std::sync::Arc;
+
+    // A failing pre-research hook aborts the run
+    Hooks::new(config.hooks.clone())
+        .run(HookEvent::PreResearch, &task, &[])
+        .await
+        .map_err(|e| e.to_string())?;
 
     // Create context builder with config
     let cwd = env::current_dir().map_err(|e| format!("Failed to get current directory: {}", e))?;
     let context_builder = ContextBuilder::with_config(cwd.clone(), config.context.clone());
 
     // Try to initialize knowledge graph for semantic search
-    let knowledge_store: Option<Arc<dyn KnowledgeStore>> =
-        match KnowledgeGraph::open(&kg_db_path).await {
-            Ok(kg) => {
-                // Check if initialized, if not initialize and index
-                let kg = Arc::new(kg);
-                if !kg.is_initialized().await.unwrap_or(false) {
-                    if let Err(e) = kg.initialize().await {
-                        eprintln!("Failed to initialize knowledge graph: {}", e);
-                        None
-                    } else {
-                        // Index the codebase on first run
-                        let _ = event_tx.send(Event::ResearchProgress(
-                            ResearchProgress::SearchingKnowledgeGraph,
-                        ));
-                        if let Err(e) = kg.index_directory(&cwd).await {
-                            eprintln!("Failed to index codebase: {}", e);
+    // (created on first run, unlike viewer lookups which require `arq init`)
+    let knowledge_store: Option<Arc<dyn KnowledgeStore>> = match kg
+        .get_or_try_init(|| async { KnowledgeGraph::open(&kg_db_path).await.map(Arc::new) })
+        .await
+        .cloned()
+    {
+        Ok(kg) => {
+            // Check if initialized, if not initialize and index
+            if !kg.is_initialized().await.unwrap_or(false) {
+                if let Err(e) = kg.initialize().await {
+                    eprintln!("Failed to initialize knowledge graph: {}", e);
+                    None
+                } else {
+                    // Index the codebase on first run
+                    let _ = event_tx.send(Event::ResearchProgress(
+                        ResearchProgress::SearchingKnowledgeGraph,
+                    ));
+                    match kg.index_directory(&cwd).await {
+                        // Kept for `arq init --retry-failed`
+                        Ok(stats) => {
+                            let _ = stats.report.save(&config.storage.index_report_path());
                         }
-                        Some(kg as Arc<dyn KnowledgeStore>)
+                        Err(e) => eprintln!("Failed to index codebase: {}", e),
                     }
-                } else {
                     Some(kg as Arc<dyn KnowledgeStore>)
                 }
+            } else {
+                Some(kg as Arc<dyn KnowledgeStore>)
             }
-            Err(e) => {
-                eprintln!("Failed to open knowledge graph: {}", e);
-                None
-            }
-        };
+        }
+        Err(e) => {
+            eprintln!("Failed to open knowledge graph: {}", e);
+            None
+        }
+    };
 
     // Create channels for progress and streaming
     let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<ResearchProgress>();
@@ -843,11 +2895,13 @@ async fn run_research_task(
     // (ResearchRunner is generic, so we handle each provider type separately)
     let provider = config.llm.provider.as_str();
     let model = config.llm.model_or_default();
+    let persona = prompts::for_phase(Phase::Research, &config);
+    let temperature = persona.llm_config(&config.llm).temperature;
 
     // Helper macro to create runner with or without knowledge store
     macro_rules! create_runner {
-        ($client:expr) => {
-            if let Some(ref kg) = knowledge_store {
+        ($client:expr) => {{
+            let runner = (if let Some(ref kg) = knowledge_store {
                 ResearchRunner::with_knowledge_store(
                     $client,
                     context_builder.clone(),
@@ -855,8 +2909,33 @@ async fn run_research_task(
                 )
             } else {
                 ResearchRunner::new($client, context_builder.clone())
+            })
+            .with_content_guard(config.research.content_guard)
+            .with_rerank(config.research.rerank)
+            .with_self_critique(config.research.self_critique)
+            .with_follow_ups(config.research.follow_up_questions)
+            .with_correct_references(config.research.correct_references)
+            .with_output_language(config.research.output_language.clone())
+            .with_cost_limit(CostLimit::from_config(&config))
+            .with_include_tests(config.context.include_tests)
+            .with_private_paths(PathBlocklist::new(config.context.private_paths.clone()))
+            .with_context_queries(context_queries.clone())
+            .with_excluded_files(task.excluded_context.clone())
+            .with_system_prompt(persona.system_prompt.clone())
+            .with_context_cache(
+                ContextCache::new(config.storage.context_cache_path())
+                    .with_encryption(PrivateFiles::from_config(&config.storage)),
+            )
+            .with_sent_context(
+                SentContextLog::new(config.storage.sent_context_path())
+                    .with_encryption(PrivateFiles::from_config(&config.storage)),
+            )
+            .with_cancellation(cancel.clone());
+            match refinement.clone() {
+                Some(refinement) => runner.with_refinement(refinement),
+                None => runner,
             }
-        };
+        }};
     }
 
     let doc = match provider {
@@ -865,7 +2944,9 @@ async fn run_research_task(
                 .llm
                 .api_key_or_env()
                 .ok_or_else(|| "ANTHROPIC_API_KEY not set".to_string())?;
-            let client = ClaudeClient::new(api_key).with_model(&model);
+            let client = ClaudeClient::new(api_key)
+                .with_model(&model)
+                .with_temperature(temperature);
             let runner = create_runner!(client);
             runner
                 .run_streaming(&task, progress_tx, stream_tx)
@@ -874,7 +2955,7 @@ async fn run_research_task(
         }
         "ollama" => {
             let base_url = config.llm.base_url_or_default();
-            let client = OpenAIClient::new(&base_url, "", &model);
+            let client = OpenAIClient::new(&base_url, "", &model).with_temperature(temperature);
             let runner = create_runner!(client);
             runner
                 .run_streaming(&task, progress_tx, stream_tx)
@@ -885,7 +2966,8 @@ async fn run_research_task(
             // OpenAI or OpenAI-compatible (use non-streaming for compatibility)
             let base_url = config.llm.base_url_or_default();
             let api_key = config.llm.api_key_or_env().unwrap_or_default();
-            let client = OpenAIClient::new(&base_url, &api_key, &model);
+            let client =
+                OpenAIClient::new(&base_url, &api_key, &model).with_temperature(temperature);
             let runner = create_runner!(client);
             // Use non-streaming for better compatibility with various providers
             let doc = runner
@@ -900,3 +2982,132 @@ async fn run_research_task(
 
     Ok(doc)
 }
+
+/// What a planner run does.
+enum PlanningJob {
+    /// Reply to a message, given the conversation before it
+    Chat {
+        history: Vec<PlanningMessage>,
+        message: String,
+    },
+    /// Generate the structured plan from the conversation, bypassing the
+    /// response cache on `refresh`
+    Generate {
+        history: Vec<PlanningMessage>,
+        refresh: bool,
+    },
+}
+
+/// Generate the edit of one plan item.
+/// Cancelling `cancel` stops the in-flight LLM request.
+async fn run_agent_step(
+    plan: Plan,
+    item: ExecutionItem,
+    config: Config,
+    cancel: CancellationToken,
+    event_tx: mpsc::UnboundedSender<Event>,
+) -> Result<FileEdit, String> {
+    let persona = prompts::for_phase(Phase::Agent, &config);
+    let llm = Provider::build_cached(&persona.llm_config(&config.llm), &config.storage)
+        .map_err(|e| e.to_string())?;
+    let runner = AgentRunner::new(llm, plan, ".")
+        .with_private_paths(PathBlocklist::new(config.context.private_paths.clone()))
+        .with_cancellation(cancel);
+
+    // Forward progress events to TUI
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<AgentProgress>();
+    let progress_forwarder = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            let _ = event_tx.send(Event::AgentProgress(progress));
+        }
+    });
+
+    let edit = runner.generate_edit(&item, &progress_tx).await;
+    drop(progress_tx);
+    let _ = progress_forwarder.await;
+
+    edit.map_err(|e| e.to_string())
+}
+
+/// Result of a planner run.
+enum PlanningOutcome {
+    Reply(String),
+    Plan(Plan),
+}
+
+/// Run a planner chat turn or plan generation with the task's research pinned.
+/// The pre-plan hook runs while the task is still in the Research phase.
+/// Cancelling `cancel` stops the in-flight LLM request.
+async fn run_planning_task(
+    task: Task,
+    config: Config,
+    job: PlanningJob,
+    cancel: CancellationToken,
+    event_tx: mpsc::UnboundedSender<Event>,
+) -> Result<PlanningOutcome, String> {
+    use arq_core::StreamChunk;
+
+    let research = task
+        .research_doc
+        .clone()
+        .ok_or_else(|| "Task has no approved research".to_string())?;
+
+    // A failing pre-plan hook aborts the run
+    if task.phase == Phase::Research {
+        Hooks::new(config.hooks.clone())
+            .run(HookEvent::PrePlan, &task, &[])
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let persona = prompts::for_phase(Phase::Planning, &config);
+    let llm = Provider::build_cached(&persona.llm_config(&config.llm), &config.storage)
+        .map_err(|e| e.to_string())?;
+    let refresh = matches!(job, PlanningJob::Generate { refresh: true, .. });
+    let runner = PlanningRunner::new(llm, research)
+        .with_system_prompt(persona.system_prompt)
+        .with_output_language(config.research.output_language.clone())
+        .with_constraints(config.planning.clone())
+        .with_refresh(refresh)
+        .with_cancellation(cancel);
+
+    // Forward progress events to TUI
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<PlanningProgress>();
+    let event_tx_progress = event_tx.clone();
+    let progress_forwarder = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            let _ = event_tx_progress.send(Event::PlanningProgress(progress));
+        }
+    });
+
+    let outcome = match job {
+        PlanningJob::Chat { history, message } => {
+            // Forward stream chunks to TUI
+            let (stream_tx, mut stream_rx) = mpsc::unbounded_channel::<StreamChunk>();
+            let event_tx_stream = event_tx.clone();
+            let stream_forwarder = tokio::spawn(async move {
+                while let Some(chunk) = stream_rx.recv().await {
+                    if chunk.is_final {
+                        let _ = event_tx_stream.send(Event::StreamComplete);
+                    } else {
+                        let _ = event_tx_stream.send(Event::StreamChunk(chunk.text));
+                    }
+                }
+            });
+
+            let reply = runner
+                .chat_streaming(&history, &message, progress_tx, stream_tx)
+                .await;
+            // The reply event must follow the streamed text it completes
+            let _ = stream_forwarder.await;
+            reply.map(PlanningOutcome::Reply)
+        }
+        PlanningJob::Generate { history, .. } => runner
+            .generate_plan(&task.name, &history, progress_tx)
+            .await
+            .map(PlanningOutcome::Plan),
+    };
+    let _ = progress_forwarder.await;
+
+    outcome.map_err(|e| e.to_string())
+}
@@ -0,0 +1,88 @@
+//! Slash-commands typed into the chat input.
+//!
+//! Common actions are reachable by name as well as by key: `/search`,
+//! `/deps`, `/switch`, `/model` and `/help`. Their output is shown as system
+//! messages in the chat.
+
+/// A parsed slash-command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlashCommand {
+    /// Semantic search, results listed in the chat
+    Search(String),
+    /// Functions called by a function
+    Deps(String),
+    /// Make another task current
+    Switch(String),
+    /// Use another model, or list the configured ones
+    Model(Option<String>),
+    /// List the commands
+    Help,
+}
+
+/// Commands with their usage, in the order `/help` lists them.
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        "/search <query>",
+        "semantic search over the knowledge graph",
+    ),
+    ("/deps <function>", "functions called by a function"),
+    ("/switch <task>", "switch to a task by ID prefix or name"),
+    (
+        "/model [name]",
+        "use another model, or list the configured ones",
+    ),
+    ("/help", "show this list"),
+];
+
+impl SlashCommand {
+    /// Parse `input` as a slash-command.
+    ///
+    /// Returns `None` for input not starting with `/`, which is sent to the
+    /// LLM as usual, and an error for unknown commands or missing arguments.
+    pub fn parse(input: &str) -> Option<Result<Self, String>> {
+        let input = input.trim().strip_prefix('/')?;
+        let (name, argument) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let argument = argument.trim().to_string();
+        let required = |command: fn(String) -> Self, usage: &str| {
+            if argument.is_empty() {
+                Err(format!("Usage: {}", usage))
+            } else {
+                Ok(command(argument.clone()))
+            }
+        };
+
+        Some(match name {
+            "search" | "s" => required(Self::Search, "/search <query>"),
+            "deps" => required(Self::Deps, "/deps <function>"),
+            "switch" => required(Self::Switch, "/switch <task>"),
+            "model" => Ok(Self::Model(
+                Some(argument.clone()).filter(|a| !a.is_empty()),
+            )),
+            "help" | "?" => Ok(Self::Help),
+            _ => Err(format!(
+                "Unknown command '/{}'. Type /help for a list.",
+                name
+            )),
+        })
+    }
+
+    /// The command list shown by `/help`.
+    pub fn help() -> String {
+        let width = COMMANDS
+            .iter()
+            .map(|(usage, _)| usage.len())
+            .max()
+            .unwrap_or(0);
+        let mut help = String::from("Commands:\n");
+        for (usage, description) in COMMANDS {
+            help.push_str(&format!(
+                "  {:width$}  {}\n",
+                usage,
+                description,
+                width = width
+            ));
+        }
+        help.push_str("\nKeys: [/] search picker, [f] citations, [m] next model, [r] rename task");
+        help
+    }
+}
@@ -5,7 +5,7 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Paragraph},
 };
 
-use crate::tui::app::{App, MessageRole};
+use crate::tui::app::{App, MessageRole, SelectedTab};
 
 /// Wrap text to fit within a given width.
 fn wrap_text(text: &str, width: usize) -> Vec<String> {
@@ -64,8 +64,16 @@ fn wrap_text(text: &str, width: usize) -> Vec<String> {
 pub fn render(app: &App, frame: &mut Frame, area: Rect) {
     let tab_title = app.selected_tab.title();
 
+    // The planner works from the approved research, pinned for the whole chat
+    let title = match (&app.selected_tab, &app.current_task) {
+        (SelectedTab::Planner, Some(task)) if task.research_doc.is_some() => {
+            format!(" {} Chat · research pinned: {} ", tab_title, task.name)
+        }
+        _ => format!(" {} Chat ", tab_title),
+    };
+
     let block = Block::default()
-        .title(format!(" {} Chat ", tab_title))
+        .title(title)
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .border_style(Style::default().fg(Color::DarkGray));
@@ -121,9 +129,13 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
         }
     }
 
-    // Add streaming buffer if active
-    if app.is_streaming && !app.stream_buffer.is_empty() {
-        let wrapped_lines = wrap_text(&app.stream_buffer, text_width);
+    // Add streaming buffer if active, as a document once its sections arrive
+    let streamed = match &app.streamed_doc {
+        Some(doc) => doc.to_markdown(),
+        None => app.stream_buffer.clone(),
+    };
+    if app.is_streaming && !streamed.is_empty() {
+        let wrapped_lines = wrap_text(&streamed, text_width);
 
         for (i, line) in wrapped_lines.into_iter().enumerate() {
             if i == 0 {
@@ -170,9 +182,35 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
         .take(visible_height)
         .collect();
 
+    // Remember the text on screen for mouse selection
+    let line_texts: Vec<String> = visible_lines
+        .iter()
+        .map(|line| {
+            line.spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        })
+        .collect();
+
     let paragraph = Paragraph::new(visible_lines);
     frame.render_widget(paragraph, inner_area);
 
+    if let Some(selection) = &app.selection {
+        let buffer = frame.buffer_mut();
+        for (index, text) in line_texts.iter().enumerate() {
+            let Some((from, to)) = selection.columns(index, text.chars().count()) else {
+                continue;
+            };
+            let y = inner_area.y + index as u16;
+            for column in from..to.min(inner_area.width as usize) {
+                buffer[(inner_area.x + column as u16, y)]
+                    .set_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+        }
+    }
+    app.hit_areas.set_chat(inner_area, line_texts);
+
     // Show scroll indicators if there's more content
     if has_more_above {
         let indicator = Paragraph::new("▲ more above (k to scroll up)")
@@ -0,0 +1,50 @@
+//! Confirmation modal for research prompts over the cost limit.
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
+};
+
+use arq_core::research::CostConfirmation;
+
+/// Render the estimate of a prompt over `research.confirm_cost_above` in a
+/// box centered on `area`, asking whether to send it.
+pub fn render(confirmation: &CostConfirmation, frame: &mut Frame, area: Rect) {
+    let block = Block::default()
+        .title(" Send research prompt? ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let mut lines: Vec<Line> = confirmation
+        .estimate
+        .breakdown()
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    if let Some(first) = lines.first_mut() {
+        first.style = Style::default().add_modifier(Modifier::BOLD);
+    }
+    lines.push(Line::default());
+    lines.push(Line::styled(
+        "[y] Send  [n/Esc] Cancel",
+        Style::default().fg(Color::DarkGray),
+    ));
+
+    let width = area.width.saturating_sub(4).min(80);
+    let height = (lines.len() as u16 + 4).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        popup,
+    );
+}
@@ -12,10 +12,18 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
     let border_style = match app.input_mode {
         InputMode::Normal => Style::default().fg(Color::DarkGray),
         InputMode::Editing => Style::default().fg(Color::Yellow),
+        InputMode::Search => Style::default().fg(Color::Cyan),
+        InputMode::Rename => Style::default().fg(Color::Magenta),
+    };
+
+    let title = match app.input_mode {
+        InputMode::Search => " Search ",
+        InputMode::Rename => " Rename Task ",
+        _ => " Input ",
     };
 
     let block = Block::default()
-        .title(" Input ")
+        .title(title)
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .border_style(border_style);
@@ -23,7 +31,12 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
     let inner_area = block.inner(area);
 
     // Build input text with cursor
-    let input_text = if app.input_mode == InputMode::Editing {
+    let is_typing = matches!(
+        app.input_mode,
+        InputMode::Editing | InputMode::Search | InputMode::Rename
+    );
+
+    let input_text = if is_typing {
         format!("{}_", app.input_buffer)
     } else if app.input_buffer.is_empty() {
         "Press 'i' to type...".to_string()
@@ -31,7 +44,7 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
         app.input_buffer.clone()
     };
 
-    let text_style = if app.input_mode == InputMode::Editing {
+    let text_style = if is_typing {
         Style::default().fg(Color::White)
     } else {
         Style::default().fg(Color::DarkGray)
@@ -41,8 +54,8 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
 
     frame.render_widget(input, area);
 
-    // Set cursor position in editing mode
-    if app.input_mode == InputMode::Editing {
+    // Set cursor position while typing
+    if is_typing {
         let cursor_x = inner_area.x + app.input_buffer.len() as u16;
         let cursor_y = inner_area.y;
         frame.set_cursor_position(Position::new(cursor_x, cursor_y));
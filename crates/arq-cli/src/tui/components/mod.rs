@@ -1,6 +1,9 @@
 //! UI components for the TUI.
 
 pub mod chat;
+pub mod cost;
 pub mod input;
 pub mod progress;
+pub mod status;
 pub mod tabs;
+pub mod viewer;
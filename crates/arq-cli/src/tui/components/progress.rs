@@ -1,5 +1,7 @@
 //! Progress checklist component.
 
+use std::time::Duration;
+
 use ratatui::{
     prelude::*,
     widgets::{Block, BorderType, Borders, List, ListItem},
@@ -7,10 +9,23 @@ use ratatui::{
 
 use crate::tui::app::{App, ProgressStatus};
 
+/// Indent of an expanded failure detail, under the item label.
+const DETAIL_INDENT: &str = "   ";
+
 /// Render the progress checklist.
 pub fn render(app: &App, frame: &mut Frame, area: Rect) {
+    let title = match app.research_timing() {
+        Some((elapsed, Some(eta))) => format!(
+            " Progress {}  ETA ~{} ",
+            format_duration(elapsed),
+            format_duration(eta)
+        ),
+        Some((elapsed, None)) => format!(" Progress {} ", format_duration(elapsed)),
+        None => " Progress ".to_string(),
+    };
+
     let block = Block::default()
-        .title(" Progress ")
+        .title(title)
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .border_style(Style::default().fg(Color::DarkGray));
@@ -41,15 +56,65 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
                 ),
             };
 
-            let line = Line::from(vec![
+            let mut spans = vec![
                 Span::styled(format!(" {} ", item.status.icon()), icon_style),
                 Span::styled(&item.label, label_style),
-            ]);
+            ];
+            // Failed steps can be clicked to show why
+            if item.detail.is_some() {
+                let marker = if item.expanded { " ▾" } else { " ▸" };
+                spans.push(Span::styled(marker, Style::default().fg(Color::DarkGray)));
+            }
+            let mut lines = vec![Line::from(spans)];
+
+            if let (true, Some(detail)) = (item.expanded, &item.detail) {
+                let width = (inner_area.width as usize).saturating_sub(DETAIL_INDENT.len());
+                lines.extend(wrap_detail(detail, width).into_iter().map(|text| {
+                    Line::from(Span::styled(
+                        format!("{}{}", DETAIL_INDENT, text),
+                        Style::default().fg(Color::DarkGray),
+                    ))
+                }));
+            }
 
-            ListItem::new(line)
+            ListItem::new(lines)
         })
         .collect();
 
+    // Rows of each item, for clicks
+    let mut y = inner_area.y;
+    let item_areas = items
+        .iter()
+        .map(|item| {
+            let height = item.height() as u16;
+            let item_area =
+                Rect::new(inner_area.x, y, inner_area.width, height).intersection(inner_area);
+            y = y.saturating_add(height);
+            item_area
+        })
+        .collect();
+    app.hit_areas.set_progress(item_areas);
+
     let list = List::new(items);
     frame.render_widget(list, inner_area);
 }
+
+/// Wrap a failure detail into lines of at most `width` characters.
+fn wrap_detail(detail: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in detail.lines() {
+        let chars: Vec<char> = paragraph.chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(chars.chunks(width).map(|chunk| chunk.iter().collect()));
+    }
+    lines
+}
+
+/// Format a duration as `m:ss`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,68 @@
+//! Session status bar component.
+
+use ratatui::{prelude::*, widgets::Paragraph};
+
+use crate::tui::app::App;
+
+/// Render the session status bar: provider, model, usage, task phase and
+/// labels, and graph state.
+pub fn render(app: &App, frame: &mut Frame, area: Rect) {
+    let label = Style::default().fg(Color::DarkGray);
+    let value = Style::default().fg(Color::Gray);
+    let separator = Span::styled("  │  ", label);
+
+    let usage = &app.session_usage;
+    let cost = usage
+        .cost
+        .map_or_else(|| "n/a".to_string(), |c| format!("~${:.2}", c));
+
+    let phase = app
+        .current_task
+        .as_ref()
+        .map_or("No task", |t| t.phase.display_name());
+
+    let (graph, graph_style) = if app.knowledge_graph.initialized() {
+        ("loaded", Style::default().fg(Color::Green))
+    } else if app.kg_on_disk {
+        ("on disk", value)
+    } else {
+        ("none", Style::default().fg(Color::Yellow))
+    };
+
+    let mut line = Line::from(vec![
+        Span::styled(
+            format!("{}/{}", app.config.llm.provider, app.current_model()),
+            Style::default().fg(Color::Cyan),
+        ),
+        separator.clone(),
+        Span::styled(format_tokens(usage.tokens.total()), value),
+        Span::styled(" tokens ", label),
+        Span::styled(cost, value),
+        separator.clone(),
+        Span::styled("phase ", label),
+        Span::styled(phase, value),
+    ]);
+    if let Some(task) = app.current_task.as_ref().filter(|t| !t.labels.is_empty()) {
+        line.spans.extend([
+            separator.clone(),
+            Span::styled("labels ", label),
+            Span::styled(task.labels.join(", "), value),
+        ]);
+    }
+    line.spans.extend([
+        separator,
+        Span::styled("graph ", label),
+        Span::styled(graph, graph_style),
+    ]);
+
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+/// Format a token count compactly (`850`, `12.3k`, `1.2M`).
+fn format_tokens(count: usize) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}k", count as f64 / 1_000.0),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    }
+}
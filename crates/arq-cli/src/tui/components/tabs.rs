@@ -7,17 +7,21 @@ use ratatui::{
 
 use crate::tui::app::App;
 
+/// Separator between tab titles.
+const DIVIDER: &str = " | ";
+
 /// Render the tab bar.
 pub fn render(app: &App, frame: &mut Frame, area: Rect) {
     let titles = vec!["Researcher", "Planner", "Agent"];
+    let block = Block::default()
+        .title(" Arq ")
+        .borders(Borders::BOTTOM)
+        .border_style(Style::default().fg(Color::DarkGray));
+    app.hit_areas
+        .set_tabs(title_areas(&titles, block.inner(area)));
 
     let tabs = RataTabs::new(titles)
-        .block(
-            Block::default()
-                .title(" Arq ")
-                .borders(Borders::BOTTOM)
-                .border_style(Style::default().fg(Color::DarkGray)),
-        )
+        .block(block)
         .select(app.selected_tab.index())
         .style(Style::default().fg(Color::DarkGray))
         .highlight_style(
@@ -25,7 +29,22 @@ pub fn render(app: &App, frame: &mut Frame, area: Rect) {
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
         )
-        .divider(" | ");
+        .divider(DIVIDER);
 
     frame.render_widget(tabs, area);
 }
+
+/// Where each title lands, laid out like `Tabs` does: one cell of padding
+/// on each side, separated by the divider.
+fn title_areas(titles: &[&str], inner: Rect) -> Vec<Rect> {
+    let mut x = inner.x;
+    titles
+        .iter()
+        .map(|title| {
+            let width = title.len() as u16 + 2;
+            let title_area = Rect::new(x, inner.y, width, 1).intersection(inner);
+            x = x.saturating_add(width + DIVIDER.len() as u16);
+            title_area
+        })
+        .collect()
+}
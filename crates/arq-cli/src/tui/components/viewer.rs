@@ -0,0 +1,382 @@
+//! File viewer, location picker and context review components.
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph},
+};
+
+use crate::tui::viewer::{ContextReview, FileViewer, LocationPicker};
+
+/// Render an open file with line numbers and syntax highlighting.
+pub fn render(viewer: &FileViewer, frame: &mut Frame, area: Rect) {
+    let block = Block::default()
+        .title(format!(" {}:{} ", viewer.path, viewer.cursor_line + 1))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let inner_area = block.inner(area);
+    frame.render_widget(block, area);
+
+    let height = inner_area.height as usize;
+    if height == 0 {
+        return;
+    }
+
+    let gutter_width = viewer.lines.len().to_string().len() + 1;
+    let text_width = (inner_area.width as usize).saturating_sub(gutter_width + 1);
+
+    // Keep the cursor vertically centered where possible
+    let max_start = viewer.lines.len().saturating_sub(height);
+    let start = viewer.cursor_line.saturating_sub(height / 2).min(max_start);
+
+    // Scroll horizontally when the cursor moves past the right edge
+    let h_offset = (viewer.cursor_col + 1).saturating_sub(text_width);
+
+    let syntax = Syntax::for_extension(viewer.extension());
+
+    let lines: Vec<Line> = viewer
+        .lines
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(height)
+        .map(|(i, text)| {
+            let is_cursor_line = i == viewer.cursor_line;
+            let number_style = if is_cursor_line {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+
+            let visible: String = text.chars().skip(h_offset).take(text_width).collect();
+            let mut spans = vec![Span::styled(
+                format!("{:>width$} ", i + 1, width = gutter_width),
+                number_style,
+            )];
+            spans.extend(highlight(&visible, &syntax));
+
+            let line = Line::from(spans);
+            if is_cursor_line {
+                line.style(Style::default().bg(Color::Rgb(40, 40, 40)))
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(lines), inner_area);
+
+    let cursor_x =
+        inner_area.x + (gutter_width + 1 + viewer.cursor_col.saturating_sub(h_offset)) as u16;
+    let cursor_y = inner_area.y + (viewer.cursor_line - start) as u16;
+    frame.set_cursor_position(Position::new(cursor_x, cursor_y));
+}
+
+/// Render the location picker.
+pub fn render_picker(picker: &LocationPicker, frame: &mut Frame, area: Rect) {
+    let block = Block::default()
+        .title(format!(" {} ({}) ", picker.title, picker.entries.len()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    if picker.entries.is_empty() {
+        let empty = Paragraph::new("Nothing to open.")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = picker
+        .entries
+        .iter()
+        .map(|e| ListItem::new(e.label.as_str()))
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Render the context review as a checkbox list.
+pub fn render_context_review(review: &ContextReview, frame: &mut Frame, area: Rect) {
+    let excluded = review.entries.iter().filter(|e| e.excluded).count();
+    let block = Block::default()
+        .title(format!(
+            " Context files ({} of {} excluded) ",
+            excluded,
+            review.entries.len()
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    if review.entries.is_empty() {
+        let empty = Paragraph::new("No context files found.")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = review
+        .entries
+        .iter()
+        .map(|e| {
+            let (mark, style) = if e.excluded {
+                ("[ ]", Style::default().fg(Color::DarkGray))
+            } else {
+                ("[x]", Style::default())
+            };
+            ListItem::new(format!("{} {}", mark, e.path)).style(style)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default().with_selected(Some(review.selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Highlighting rules for a language.
+struct Syntax {
+    keywords: &'static [&'static str],
+    comment: &'static str,
+    /// Single quotes delimit char literals only (Rust lifetimes are not strings)
+    char_literals: bool,
+}
+
+impl Syntax {
+    fn for_extension(extension: &str) -> Self {
+        Self {
+            keywords: keywords_for(extension),
+            comment: comment_prefix(extension),
+            char_literals: extension == "rs",
+        }
+    }
+}
+
+/// Split a line into styled spans: keywords, strings, numbers and comments.
+///
+/// This is a lightweight lexer, not a parser. It only needs to be good enough
+/// to make source easy to scan.
+fn highlight(line: &str, syntax: &Syntax) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut rest = line;
+
+    while !rest.is_empty() {
+        if rest.starts_with(syntax.comment) {
+            spans.push(Span::styled(
+                rest.to_string(),
+                Style::default().fg(Color::DarkGray),
+            ));
+            break;
+        }
+
+        let first = rest.chars().next().unwrap_or(' ');
+        let (token_len, style) = if first == '"' || first == '\'' {
+            let len = match string_len(rest, first) {
+                Some(len) if first == '\'' && syntax.char_literals && len > 4 => 1,
+                Some(len) => len,
+                None if first == '\'' && syntax.char_literals => 1,
+                None => rest.len(),
+            };
+            (len, Style::default().fg(Color::Green))
+        } else if first.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '_')
+                .unwrap_or(rest.len());
+            (len, Style::default().fg(Color::Magenta))
+        } else if first.is_alphabetic() || first == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let style = if syntax.keywords.contains(&&rest[..len]) {
+                Style::default()
+                    .fg(Color::Blue)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            (len, style)
+        } else {
+            (first.len_utf8(), Style::default().fg(Color::Gray))
+        };
+
+        let (token, tail) = rest.split_at(token_len);
+        spans.push(Span::styled(token.to_string(), style));
+        rest = tail;
+    }
+
+    spans
+}
+
+/// Byte length of a quoted string starting at the beginning of `text`.
+///
+/// Returns `None` if the string is not terminated on this line.
+fn string_len(text: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return Some(i + c.len_utf8());
+        }
+    }
+    None
+}
+
+fn comment_prefix(extension: &str) -> &'static str {
+    match extension {
+        "py" | "toml" | "yaml" | "yml" | "sh" => "#",
+        _ => "//",
+    }
+}
+
+fn keywords_for(extension: &str) -> &'static [&'static str] {
+    match extension {
+        "rs" => &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        "ts" | "tsx" | "js" | "jsx" | "mjs" => &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "else",
+            "enum",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "implements",
+            "import",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "type",
+            "undefined",
+            "var",
+            "while",
+        ],
+        "py" => &[
+            "and", "as", "async", "await", "break", "class", "continue", "def", "elif", "else",
+            "except", "False", "finally", "for", "from", "if", "import", "in", "is", "lambda",
+            "None", "not", "or", "pass", "raise", "return", "self", "True", "try", "while", "with",
+            "yield",
+        ],
+        "go" => &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "false",
+            "for",
+            "func",
+            "go",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "nil",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "true",
+            "type",
+            "var",
+        ],
+        "java" | "cs" => &[
+            "abstract",
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "else",
+            "enum",
+            "extends",
+            "false",
+            "final",
+            "finally",
+            "for",
+            "foreach",
+            "if",
+            "implements",
+            "import",
+            "interface",
+            "namespace",
+            "new",
+            "null",
+            "override",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "static",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "using",
+            "var",
+            "virtual",
+            "void",
+            "while",
+        ],
+        _ => &[],
+    }
+}
@@ -1,14 +1,19 @@
 //! Event handling for the TUI.
 
-use crossterm::event::{KeyEvent, KeyEventKind};
+use crossterm::event::{KeyEvent, KeyEventKind, MouseEvent, MouseEventKind};
 use futures::{FutureExt, StreamExt};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::sync::mpsc;
 
-use arq_core::{ResearchDoc, ResearchProgress};
+use arq_core::agent::{AgentProgress, FileEdit};
+use arq_core::knowledge::EntityInfo;
+use arq_core::{Plan, PlanningProgress, ResearchDoc, ResearchProgress, SearchResult};
+
+use super::viewer::SourceLocation;
 
 /// Result of a completed research task.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResearchResult {
     /// The task ID for persistence
     pub task_id: String,
@@ -16,11 +21,25 @@ pub struct ResearchResult {
     pub doc: ResearchDoc,
 }
 
+/// Result of a generated plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanResult {
+    /// The task ID for persistence
+    pub task_id: String,
+    /// The generated plan
+    pub plan: Plan,
+}
+
 /// Events that can occur in the application.
-#[derive(Debug, Clone)]
+///
+/// Serializable so sessions can be recorded and replayed (see
+/// [`super::session`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
     /// A key was pressed
     Key(KeyEvent),
+    /// The mouse was clicked, dragged or scrolled (with `[tui] mouse`)
+    Mouse(MouseEvent),
     /// A tick occurred (for animations/updates)
     Tick,
     /// A chunk of streaming text arrived
@@ -33,6 +52,46 @@ pub enum Event {
     ResearchComplete(ResearchResult),
     /// Research failed with error message
     ResearchFailed(String),
+    /// Planning progress update
+    PlanningProgress(PlanningProgress),
+    /// The planner finished replying, with the full reply
+    PlanningReply(String),
+    /// A plan was generated and awaits approval
+    PlanGenerated(PlanResult),
+    /// A planner chat turn or plan generation failed with error message
+    PlanningFailed(String),
+    /// Agent progress update
+    AgentProgress(AgentProgress),
+    /// The agent generated the edit of a plan item, to be confirmed
+    AgentEditGenerated(FileEdit),
+    /// Generating an edit failed with error message
+    AgentFailed(String),
+    /// Semantic search results for the file picker
+    SearchResults {
+        query: String,
+        results: Vec<SearchResult>,
+    },
+    /// Definition lookup finished (`None` if the symbol is not indexed)
+    DefinitionFound {
+        symbol: String,
+        location: Option<SourceLocation>,
+    },
+    /// Entity lookup at the viewer cursor finished (`None` if no indexed
+    /// entity contains the line)
+    EntityFound {
+        location: SourceLocation,
+        entity: Option<EntityInfo>,
+    },
+    /// Files a task's research context would be gathered from, for review
+    ContextCandidates { task_id: String, files: Vec<String> },
+    /// A knowledge graph lookup failed
+    LookupFailed(String),
+    /// A post-phase hook command failed
+    HookFailed(String),
+    /// The LLM suggested a name for a newly created task
+    TaskNamed { task_id: String, name: String },
+    /// Output of a slash-command, shown as a system message
+    CommandOutput(String),
 }
 
 /// Handles events from various sources.
@@ -61,13 +120,23 @@ impl EventHandler {
                     maybe_event = crossterm_event => {
                         match maybe_event {
                             Some(Ok(evt)) => {
-                                if let crossterm::event::Event::Key(key) = evt {
+                                let event = match evt {
                                     // Only handle key press events, not release
-                                    if key.kind == KeyEventKind::Press
-                                        && event_tx.send(Event::Key(key)).is_err()
+                                    crossterm::event::Event::Key(key)
+                                        if key.kind == KeyEventKind::Press =>
+                                    {
+                                        Event::Key(key)
+                                    }
+                                    // Plain pointer movement is reported too and needs no redraw
+                                    crossterm::event::Event::Mouse(mouse)
+                                        if mouse.kind != MouseEventKind::Moved =>
                                     {
-                                        break;
+                                        Event::Mouse(mouse)
                                     }
+                                    _ => continue,
+                                };
+                                if event_tx.send(event).is_err() {
+                                    break;
                                 }
                             }
                             Some(Err(_)) => {}
@@ -5,29 +5,62 @@
 //! - Chat input/output display
 //! - Progress checklist
 //! - Streaming LLM responses
+//! - File viewer with jump-to-definition
+//! - Slash-commands (`/search`, `/deps`, `/switch`, `/model`, `/help`)
+//! - Mouse: click tabs and progress items, scroll and select chat text
+//! - Session recording and replay (`--record`, `--replay`)
 
 mod app;
+mod commands;
 mod components;
 mod event;
+mod mouse;
+mod session;
 mod ui;
+mod viewer;
 
 use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
 use std::io::stdout;
+use std::path::Path;
 
 use app::App;
 use arq_core::{Config, FileStorage, TaskManager};
+use session::{Recorder, Replayer, Sandbox};
 
 use crate::banner;
 
-/// Run the TUI application.
+/// Run the TUI application, recording the session to `record` or replaying
+/// the one recorded in `replay`.
 pub async fn run(
-    config: Config,
-    manager: TaskManager<FileStorage>,
+    mut config: Config,
+    mut manager: TaskManager<FileStorage>,
+    record: Option<&Path>,
+    replay: Option<&Path>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Open session files before taking over the terminal, so errors show
+    let recorder = match record {
+        Some(path) => Some(Recorder::create(
+            path,
+            manager.get_current_task().ok().flatten().as_ref(),
+        )?),
+        None => None,
+    };
+    // Replayed tasks are saved to a scratch directory, removed on return
+    let (replayer, _sandbox) = match replay {
+        Some(path) => {
+            let (replayer, task) = Replayer::open(path)?;
+            let (sandbox, sandbox_manager) = Sandbox::create(&mut config, task.as_ref())?;
+            manager = sandbox_manager;
+            (Some(replayer), Some(sandbox))
+        }
+        None => (None, None),
+    };
+
     // Show banner before entering TUI
     banner::print_banner();
 
@@ -35,19 +68,36 @@ pub async fn run(
     enable_raw_mode()?;
     let mut stdout = stdout();
     execute!(stdout, EnterAlternateScreen)?;
+    let mouse = config.tui.mouse;
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Create app state
     let mut app = App::new(config, manager);
+    if let Some(recorder) = recorder {
+        app.record(recorder);
+    }
+    if let Some(replayer) = replayer {
+        app.replay(replayer);
+    }
 
     // Run the main loop
     let result = app.run(&mut terminal).await;
 
     // Restore terminal
     disable_raw_mode()?;
+    if mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
+    if let Some(summary) = app.replay_summary() {
+        eprintln!("{}", summary);
+    }
+
     result
 }
@@ -0,0 +1,168 @@
+//! Mouse support.
+//!
+//! Rendering records where the tabs, chat lines and progress items ended up
+//! in [`HitAreas`], and mouse events are matched against the last frame.
+//! Dragging over the chat selects text, which is copied to the clipboard
+//! with an OSC 52 escape sequence when the button is released, so copying
+//! works over SSH too. `[tui] mouse = false` leaves the mouse to the
+//! terminal.
+
+use std::cell::RefCell;
+use std::io::Write;
+
+use base64::Engine;
+use ratatui::layout::{Position, Rect};
+
+/// Lines scrolled per mouse wheel step.
+pub const SCROLL_LINES: usize = 3;
+
+/// A character cell in the visible chat lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChatPosition {
+    /// Visible line, from the top of the chat
+    pub line: usize,
+    /// Character in the line
+    pub column: usize,
+}
+
+/// Text selected by dragging over the chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    /// Where the drag started
+    pub anchor: ChatPosition,
+    /// Where the pointer is now
+    pub head: ChatPosition,
+}
+
+impl Selection {
+    pub fn new(position: ChatPosition) -> Self {
+        Self {
+            anchor: position,
+            head: position,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    /// Start and end of the selection, in reading order.
+    fn bounds(&self) -> (ChatPosition, ChatPosition) {
+        if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        }
+    }
+
+    /// Selected columns of `line` (end exclusive), if any.
+    pub fn columns(&self, line: usize, line_len: usize) -> Option<(usize, usize)> {
+        let (start, end) = self.bounds();
+        if line < start.line || line > end.line {
+            return None;
+        }
+        let from = if line == start.line { start.column } else { 0 };
+        let to = if line == end.line {
+            (end.column + 1).min(line_len)
+        } else {
+            line_len
+        };
+        (from < to).then_some((from, to))
+    }
+
+    /// The selected text of `lines`, one line per row.
+    pub fn text(&self, lines: &[String]) -> String {
+        let (start, end) = self.bounds();
+        let mut selected = Vec::new();
+        for (index, line) in lines.iter().enumerate().skip(start.line) {
+            if index > end.line {
+                break;
+            }
+            let chars: Vec<char> = line.chars().collect();
+            let part = match self.columns(index, chars.len()) {
+                Some((from, to)) => chars[from..to].iter().collect(),
+                None => String::new(),
+            };
+            selected.push(part.trim_end().to_string());
+        }
+        selected.join("\n")
+    }
+}
+
+/// Screen areas of the last rendered frame that react to clicks.
+#[derive(Debug, Default)]
+pub struct HitAreas {
+    tabs: RefCell<Vec<Rect>>,
+    chat: RefCell<Rect>,
+    chat_lines: RefCell<Vec<String>>,
+    progress: RefCell<Vec<Rect>>,
+}
+
+impl HitAreas {
+    /// Record the title area of each tab, in tab order.
+    pub fn set_tabs(&self, tabs: Vec<Rect>) {
+        *self.tabs.borrow_mut() = tabs;
+    }
+
+    /// Record the chat area and the text of its visible lines.
+    pub fn set_chat(&self, area: Rect, lines: Vec<String>) {
+        *self.chat.borrow_mut() = area;
+        *self.chat_lines.borrow_mut() = lines;
+    }
+
+    /// Record the rows of each progress item, in item order.
+    pub fn set_progress(&self, items: Vec<Rect>) {
+        *self.progress.borrow_mut() = items;
+    }
+
+    /// Index of the tab at a screen cell.
+    pub fn tab_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.tabs
+            .borrow()
+            .iter()
+            .position(|area| area.contains(Position::new(column, row)))
+    }
+
+    /// Index of the progress item at a screen cell.
+    pub fn progress_item_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.progress
+            .borrow()
+            .iter()
+            .position(|area| area.contains(Position::new(column, row)))
+    }
+
+    /// Chat position at a screen cell, if it is over the chat.
+    pub fn chat_position(&self, column: u16, row: u16) -> Option<ChatPosition> {
+        let area = *self.chat.borrow();
+        area.contains(Position::new(column, row))
+            .then(|| self.clamped_chat_position(column, row))
+    }
+
+    /// Chat position nearest to a screen cell, for drags leaving the chat.
+    pub fn clamped_chat_position(&self, column: u16, row: u16) -> ChatPosition {
+        let area = *self.chat.borrow();
+        let line = row
+            .clamp(area.top(), area.bottom().saturating_sub(1))
+            .saturating_sub(area.top());
+        let column = column
+            .clamp(area.left(), area.right().saturating_sub(1))
+            .saturating_sub(area.left());
+        ChatPosition {
+            line: line as usize,
+            column: column as usize,
+        }
+    }
+
+    /// Text of the selection in the visible chat lines.
+    pub fn selected_text(&self, selection: &Selection) -> String {
+        selection.text(&self.chat_lines.borrow())
+    }
+}
+
+/// Copy `text` to the clipboard of the terminal (OSC 52).
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
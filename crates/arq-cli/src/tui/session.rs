@@ -0,0 +1,452 @@
+//! Session recording and replay.
+//!
+//! `arq tui --record session.jsonl` writes every event the TUI handles to a
+//! JSON Lines file: keys, mouse input, and the results of LLM calls and
+//! lookups (ticks are left out). Each entry carries the time since the
+//! session started and a lightweight [`Snapshot`] of the state after the
+//! event was handled. The first line is a header with the task that was
+//! current when recording started.
+//!
+//! `arq tui --replay session.jsonl` feeds the events back at their recorded
+//! pace. Nothing is sent to the LLM, the knowledge graph or hooks: the
+//! recorded results stand in for them, and tasks are saved to a scratch
+//! directory seeded with the recorded task and removed afterwards. After
+//! each event the state is compared with the recorded snapshot, and the
+//! first difference is reported when the replay ends. Mouse events hit
+//! whatever is drawn where they land, so replay in a terminal of the
+//! recorded size.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use tempfile::TempDir;
+use tokio::time::{Duration, Instant};
+
+use arq_core::{Config, FileStorage, Storage, Task, TaskManager};
+
+use super::event::{Event, PlanResult, ResearchResult};
+use super::viewer::SourceLocation;
+
+/// Version of the session file format.
+const SESSION_VERSION: u32 = 1;
+
+/// First line of a session file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    started_at: DateTime<Utc>,
+    /// Task that was current when recording started
+    task: Option<Task>,
+}
+
+/// An event and the state after handling it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// Milliseconds since the session started
+    at_ms: u64,
+    event: Event,
+    state: Snapshot,
+}
+
+/// State compared between a recording and its replay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tab: String,
+    pub input_mode: String,
+    pub research: String,
+    pub planning: String,
+    /// Missing from sessions recorded before the Agent tab worked
+    #[serde(default = "idle")]
+    pub agent: String,
+    /// ID of the current task
+    pub task: Option<String>,
+    pub phase: Option<String>,
+    pub messages: usize,
+    pub streaming: bool,
+    /// Cursor of the open file viewer
+    pub viewer: Option<SourceLocation>,
+}
+
+fn idle() -> String {
+    "idle".to_string()
+}
+
+impl Snapshot {
+    /// Fields that differ in `replayed`, e.g. `research "idle" (recorded "researching")`.
+    fn differences(&self, replayed: &Snapshot) -> Vec<String> {
+        let (Ok(serde_json::Value::Object(recorded)), Ok(serde_json::Value::Object(replayed))) =
+            (serde_json::to_value(self), serde_json::to_value(replayed))
+        else {
+            return Vec::new();
+        };
+        recorded
+            .iter()
+            .filter(|(field, value)| replayed.get(*field) != Some(value))
+            .map(|(field, value)| {
+                format!(
+                    "{} {} (recorded {})",
+                    field,
+                    replayed.get(field).unwrap_or(&serde_json::Value::Null),
+                    value
+                )
+            })
+            .collect()
+    }
+}
+
+/// Name of an event's variant, e.g. `Key` or `ResearchComplete`.
+fn event_kind(event: &Event) -> String {
+    match serde_json::to_value(event) {
+        Ok(serde_json::Value::Object(map)) => map.keys().next().cloned().unwrap_or_default(),
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// Writes the events of a session to a file as they are handled.
+pub struct Recorder {
+    out: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Start recording to `path`, replacing it.
+    pub fn create(path: &Path, task: Option<&Task>) -> Result<Self, Box<dyn Error>> {
+        let file = File::create(path)
+            .map_err(|e| format!("Cannot create session file {}: {}", path.display(), e))?;
+        let mut recorder = Self {
+            out: BufWriter::new(file),
+            start: Instant::now(),
+        };
+        recorder.write_line(&Header {
+            version: SESSION_VERSION,
+            started_at: Utc::now(),
+            task: task.cloned(),
+        })?;
+        Ok(recorder)
+    }
+
+    /// Append an event and the state after handling it.
+    ///
+    /// Flushed right away, so the recording survives a crash.
+    pub fn record(&mut self, event: Event, state: Snapshot) -> Result<(), Box<dyn Error>> {
+        let entry = Entry {
+            at_ms: self.start.elapsed().as_millis() as u64,
+            event,
+            state,
+        };
+        self.write_line(&entry)
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(&mut self.out, value)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Feeds the events of a recorded session back and checks the state after
+/// each.
+pub struct Replayer {
+    entries: VecDeque<Entry>,
+    total: usize,
+    /// Set when the first event is requested
+    start: Option<Instant>,
+    /// Snapshot expected after the event being handled, and its kind
+    expected: Option<(Snapshot, String)>,
+    /// Recorded IDs of tasks created during the session, and their IDs in the replay
+    task_ids: HashMap<String, String>,
+    /// Task the session started with
+    initial_task: Option<String>,
+    divergence: Option<String>,
+}
+
+impl Replayer {
+    /// Read the session recorded in `path`, with the task it started with.
+    pub fn open(path: &Path) -> Result<(Self, Option<Task>), Box<dyn Error>> {
+        let file = File::open(path)
+            .map_err(|e| format!("Cannot open session file {}: {}", path.display(), e))?;
+        let mut lines = BufReader::new(file).lines();
+        let invalid = |line: usize, e: &dyn Error| format!("{}:{}: {}", path.display(), line, e);
+
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(|e| invalid(1, &e))?,
+            None => return Err(format!("{} is empty", path.display()).into()),
+        };
+        if header.version != SESSION_VERSION {
+            return Err(format!(
+                "{} has session format version {}, expected {}",
+                path.display(),
+                header.version,
+                SESSION_VERSION
+            )
+            .into());
+        }
+
+        let mut entries = VecDeque::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push_back(serde_json::from_str(&line).map_err(|e| invalid(index + 2, &e))?);
+        }
+
+        let replayer = Self {
+            total: entries.len(),
+            entries,
+            start: None,
+            expected: None,
+            task_ids: HashMap::new(),
+            initial_task: header.task.as_ref().map(|t| t.id.clone()),
+            divergence: None,
+        };
+        Ok((replayer, header.task))
+    }
+
+    /// The next recorded event, once its time has come. Pending forever
+    /// after the last one.
+    ///
+    /// Cancel-safe: the event stays queued until its time has come.
+    pub async fn next(&mut self) -> Event {
+        let Some(at_ms) = self.entries.front().map(|entry| entry.at_ms) else {
+            return std::future::pending().await;
+        };
+        let start = *self.start.get_or_insert_with(Instant::now);
+        tokio::time::sleep_until(start + Duration::from_millis(at_ms)).await;
+
+        let Some(entry) = self.entries.pop_front() else {
+            return std::future::pending().await;
+        };
+        let mut event = entry.event;
+        self.translate(&mut event);
+        self.expected = Some((entry.state, event_kind(&event)));
+        event
+    }
+
+    /// Compare the state after handling the last event with the recording.
+    pub fn check(&mut self, replayed: &Snapshot) {
+        let Some((mut recorded, kind)) = self.expected.take() else {
+            return;
+        };
+
+        // Tasks created during the replay get new IDs
+        if let (Some(from), Some(to)) = (&recorded.task, &replayed.task) {
+            let created = self.initial_task.as_ref() != Some(to)
+                && !self.task_ids.values().any(|id| id == to);
+            if from != to && created && !self.task_ids.contains_key(from) {
+                self.task_ids.insert(from.clone(), to.clone());
+            }
+        }
+        recorded.task = recorded
+            .task
+            .map(|id| self.task_ids.get(&id).cloned().unwrap_or(id));
+
+        if self.divergence.is_none() {
+            let differences = recorded.differences(replayed);
+            if !differences.is_empty() {
+                self.divergence = Some(format!(
+                    "Replay diverged at event {} ({}): {}",
+                    self.total - self.entries.len(),
+                    kind,
+                    differences.join(", ")
+                ));
+            }
+        }
+    }
+
+    /// Whether every recorded event has been handled.
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty() && self.expected.is_none()
+    }
+
+    /// How the replay went so far.
+    pub fn summary(&self) -> String {
+        match &self.divergence {
+            Some(divergence) => divergence.clone(),
+            None if self.is_finished() => {
+                format!("Replay finished: {} events matched", self.total)
+            }
+            None => format!(
+                "Replayed {} of {} events",
+                self.total - self.entries.len(),
+                self.total
+            ),
+        }
+    }
+
+    /// Point task IDs at the tasks created during the replay.
+    fn translate(&self, event: &mut Event) {
+        let task_id = match event {
+            Event::ResearchComplete(ResearchResult { task_id, .. })
+            | Event::PlanGenerated(PlanResult { task_id, .. })
+            | Event::ContextCandidates { task_id, .. }
+            | Event::TaskNamed { task_id, .. } => task_id,
+            _ => return,
+        };
+        if let Some(id) = self.task_ids.get(task_id) {
+            *task_id = id.clone();
+        }
+    }
+}
+
+/// Scratch task storage for a replay, removed when dropped.
+pub struct Sandbox {
+    dir: TempDir,
+}
+
+impl Sandbox {
+    /// Point `config` at a scratch directory holding only `task`, made
+    /// current, so replayed approvals and renames leave real tasks alone.
+    pub fn create(
+        config: &mut Config,
+        task: Option<&Task>,
+    ) -> Result<(Self, TaskManager<FileStorage>), Box<dyn Error>> {
+        let sandbox = Self {
+            dir: tempfile::Builder::new().prefix("arq-replay-").tempdir()?,
+        };
+
+        let storage = &mut config.storage;
+        storage.data_dir = sandbox
+            .dir
+            .path()
+            .join("data")
+            .to_string_lossy()
+            .into_owned();
+        storage.project_root = Some(sandbox.dir.path().to_path_buf());
+        storage.mirror_to_repo = false;
+        storage.encrypt = false;
+        storage.shared = false;
+
+        let files = FileStorage::with_config(config.storage.clone());
+        if let Some(task) = task {
+            files.save_task(task)?;
+            files.set_current_task_id(Some(&task.id))?;
+        }
+        Ok((sandbox, TaskManager::new(files)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            tab: "Research".to_string(),
+            input_mode: "Normal".to_string(),
+            research: "idle".to_string(),
+            planning: "idle".to_string(),
+            agent: "idle".to_string(),
+            task: None,
+            phase: Some("research".to_string()),
+            messages: 0,
+            streaming: false,
+            viewer: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_differences() {
+        assert!(snapshot().differences(&snapshot()).is_empty());
+
+        let mut replayed = snapshot();
+        replayed.research = "researching".to_string();
+        replayed.messages = 2;
+        let mut differences = snapshot().differences(&replayed);
+        differences.sort();
+        assert_eq!(
+            differences,
+            vec![
+                "messages 2 (recorded 0)",
+                "research \"researching\" (recorded \"idle\")"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let task = Task::new("Recorded task");
+
+        let mut recorder = Recorder::create(&path, Some(&task)).unwrap();
+        recorder
+            .record(Event::CommandOutput("hello".to_string()), snapshot())
+            .unwrap();
+        let mut streamed = snapshot();
+        streamed.messages = 1;
+        recorder.record(Event::StreamComplete, streamed).unwrap();
+        drop(recorder);
+
+        let (mut replayer, initial) = Replayer::open(&path).unwrap();
+        assert_eq!(initial.map(|t| t.id), Some(task.id));
+        assert_eq!(replayer.summary(), "Replayed 0 of 2 events");
+
+        let event = replayer.next().await;
+        assert!(matches!(event, Event::CommandOutput(ref text) if text == "hello"));
+        replayer.check(&snapshot());
+        assert!(matches!(replayer.next().await, Event::StreamComplete));
+        replayer.check(&snapshot());
+
+        assert!(replayer.is_finished());
+        assert_eq!(
+            replayer.summary(),
+            "Replay diverged at event 2 (StreamComplete): messages 0 (recorded 1)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_translates_created_task_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+
+        let mut created = snapshot();
+        created.task = Some("recorded-id".to_string());
+        let mut recorder = Recorder::create(&path, None).unwrap();
+        recorder
+            .record(Event::CommandOutput("created".to_string()), created.clone())
+            .unwrap();
+        let named = Event::TaskNamed {
+            task_id: "recorded-id".to_string(),
+            name: "auth".to_string(),
+        };
+        recorder.record(named, created).unwrap();
+        drop(recorder);
+
+        // The task created during the replay has another ID
+        let (mut replayer, _) = Replayer::open(&path).unwrap();
+        let mut replayed = snapshot();
+        replayed.task = Some("replayed-id".to_string());
+        replayer.next().await;
+        replayer.check(&replayed);
+
+        let event = replayer.next().await;
+        assert!(matches!(event, Event::TaskNamed { ref task_id, .. } if task_id == "replayed-id"));
+        replayer.check(&replayed);
+        assert_eq!(replayer.summary(), "Replay finished: 2 events matched");
+    }
+
+    #[test]
+    fn test_sandbox_is_removed() {
+        let mut config = Config::default();
+        let task = Task::new("Sandboxed task");
+
+        let (sandbox, manager) = Sandbox::create(&mut config, Some(&task)).unwrap();
+        let dir = sandbox.dir.path().to_path_buf();
+        assert_eq!(config.storage.project_root.as_deref(), Some(dir.as_path()));
+        assert_eq!(
+            manager.get_current_task().unwrap().map(|t| t.id),
+            Some(task.id)
+        );
+
+        drop(manager);
+        drop(sandbox);
+        assert!(!dir.exists());
+    }
+}
@@ -2,21 +2,22 @@
 
 use ratatui::{prelude::*, widgets::Paragraph};
 
-use super::app::{App, InputMode, ResearchState};
-use super::components::{chat, input, progress, tabs};
+use super::app::{AgentState, App, InputMode, PlanningState, ResearchState, SelectedTab};
+use super::components::{chat, cost, input, progress, status, tabs, viewer};
 
 /// Render the entire UI.
 pub fn render(app: &App, frame: &mut Frame) {
     let area = frame.area();
 
-    // Main layout: tabs, content, input, status
+    // Main layout: tabs, content, input, session status, key hints
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Tab bar
             Constraint::Min(10),   // Main content
             Constraint::Length(3), // Input
-            Constraint::Length(1), // Status bar
+            Constraint::Length(1), // Session status
+            Constraint::Length(1), // Key hints
         ])
         .split(area);
 
@@ -29,8 +30,9 @@ pub fn render(app: &App, frame: &mut Frame) {
     // Render input
     input::render(app, frame, chunks[2]);
 
-    // Render status bar
-    render_status_bar(app, frame, chunks[3]);
+    // Render session status and key hints
+    status::render(app, frame, chunks[3]);
+    render_status_bar(app, frame, chunks[4]);
 }
 
 /// Render the main content area (chat and progress side by side).
@@ -43,41 +45,88 @@ fn render_main_content(app: &App, frame: &mut Frame, area: Rect) {
         ])
         .split(area);
 
-    // Render chat
-    chat::render(app, frame, chunks[0]);
+    // Render picker, file viewer or chat
+    if let Some(ref review) = app.context_review {
+        viewer::render_context_review(review, frame, chunks[0]);
+    } else if let Some(ref picker) = app.picker {
+        viewer::render_picker(picker, frame, chunks[0]);
+    } else if let Some(ref file) = app.viewer {
+        viewer::render(file, frame, chunks[0]);
+    } else {
+        chat::render(app, frame, chunks[0]);
+    }
 
     // Render progress
     progress::render(app, frame, chunks[1]);
+
+    if let Some(ref confirmation) = app.cost_confirmation {
+        cost::render(confirmation, frame, area);
+    }
 }
 
-/// Render the status bar.
+/// Render the key hints and latest status message.
 fn render_status_bar(app: &App, frame: &mut Frame, area: Rect) {
-    // Context-aware key bindings based on research state
+    // Context-aware key bindings based on research and planning state
     let mode_str = match (&app.input_mode, &app.research_state) {
+        _ if app.cost_confirmation.is_some() => "[y] Send  [n/Esc] Cancel",
         (InputMode::Editing, _) => "[Enter] Send  [Esc] Cancel",
+        (InputMode::Search, _) => "[Enter] Search  [Esc] Cancel",
+        (InputMode::Rename, _) => "[Enter] Rename  [Esc] Cancel",
+        (InputMode::Normal, _) if app.context_review.is_some() => {
+            "[j/k] Select  [Space] Include/exclude  [Enter] Save  [Esc] Cancel"
+        }
+        (InputMode::Normal, _) if app.picker.is_some() => "[j/k] Select  [Enter] Open  [Esc] Close",
+        (InputMode::Normal, _) if app.viewer.is_some() => {
+            "[hjkl] Move  [gd] Definition  [gs] Symbol  [Ctrl-O] Back  [/] Search  [Esc] Close"
+        }
+        (InputMode::Normal, _) if app.selected_tab == SelectedTab::Planner => {
+            match app.planning_state {
+                PlanningState::AwaitingApproval { .. } => {
+                    "[a] Approve  [g] Regenerate  [i] Discuss  [Tab] Switch  [q] Quit"
+                }
+                PlanningState::Chatting | PlanningState::Generating => {
+                    "Planning...  [Esc/Ctrl+X] Cancel  [q] Quit"
+                }
+                PlanningState::Idle => {
+                    "[i] Discuss  [g] Generate plan  [m] Model  [Tab] Switch  [q] Quit"
+                }
+            }
+        }
+        (InputMode::Normal, _) if app.selected_tab == SelectedTab::Agent => match app.agent_state {
+            AgentState::AwaitingConfirmation { .. } => {
+                "[a] Apply  [n] Skip  [Esc] Stop  [j/k] Scroll  [q] Quit"
+            }
+            AgentState::Generating { .. } => "Generating...  [Esc/Ctrl+X] Stop  [q] Quit",
+            AgentState::Idle => "[g] Carry out plan  [m] Model  [Tab] Switch  [q] Quit",
+        },
+        (InputMode::Normal, ResearchState::AwaitingValidation { pending_doc, .. })
+            if !pending_doc.follow_ups.is_empty() =>
+        {
+            "[1-9] Answer  [a] Approve  [i] Edit corrections  [s] Severity  [c] Context  [Tab] Switch  [q] Quit"
+        }
         (InputMode::Normal, ResearchState::AwaitingValidation { .. }) => {
-            "[a] Approve  [i] Edit corrections  [Tab] Switch  [q] Quit"
+            "[a] Approve  [i] Edit corrections  [s] Severity  [c] Context  [Tab] Switch  [q] Quit"
         }
         (InputMode::Normal, ResearchState::Researching | ResearchState::Refining) => {
-            "Researching...  [q] Quit"
+            "Researching...  [Esc/Ctrl+X] Cancel  [q] Quit"
         }
         (InputMode::Normal, ResearchState::Idle) => {
-            "[i] Edit  [m] Model  [Tab] Switch  [j/k] Scroll  [q] Quit"
+            "[i] Edit (/help: commands)  [r] Rename  [m] Model  [/] Search  [f] Files  [c] Context  [Tab] Switch  [q] Quit"
         }
     };
 
-    // Model indicator
-    let model_info = format!("[{}]", app.current_model());
-
-    let task_info = app.current_task.as_ref().map_or_else(
-        || "No active task".to_string(),
-        |t| format!("{} ({})", t.name, t.phase.display_name()),
-    );
-
-    // Show status_message if present, otherwise show task_info
-    let right_side = app.status_message.as_deref().unwrap_or(&task_info);
-
-    let status = format!("{}  |  {}  |  {}", mode_str, model_info, right_side);
+    // Show status_message if present, otherwise the task name
+    let right_side = app
+        .status_message
+        .as_deref()
+        .or(app.current_task.as_ref().map(|t| t.name.as_str()))
+        .unwrap_or("No active task");
+
+    let status = if app.is_replaying() {
+        format!("Replay [q] Quit  |  {}  |  {}", mode_str, right_side)
+    } else {
+        format!("{}  |  {}", mode_str, right_side)
+    };
 
     let status_bar = Paragraph::new(status).style(Style::default().fg(Color::DarkGray));
 
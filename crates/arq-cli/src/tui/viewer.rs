@@ -0,0 +1,360 @@
+//! File viewer and location picker state.
+//!
+//! The viewer replaces the chat pane while open. Locations come from
+//! research citations or knowledge graph search results, `gd` jumps to the
+//! definition of the identifier under the cursor and `gs` names the entity
+//! the cursor is in. The context review
+//! list lets the developer exclude files from a task's research context.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use arq_core::knowledge::indexer::matches_pattern;
+use arq_core::research::SourceType;
+use arq_core::{ResearchDoc, SearchResult};
+
+/// Width used when expanding tabs so cursor columns match rendered columns.
+const TAB_WIDTH: usize = 4;
+
+/// A line in a source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    /// Path relative to the project root
+    pub path: String,
+    /// 1-based line number
+    pub line: usize,
+}
+
+impl SourceLocation {
+    pub fn new(path: impl Into<String>, line: usize) -> Self {
+        Self {
+            path: path.into(),
+            line: line.max(1),
+        }
+    }
+
+    /// Parse a citation of the form `path`, `path:line` or `path:start-end`.
+    pub fn parse(citation: &str) -> Self {
+        let citation = citation.trim().trim_matches('`');
+        if let Some((path, suffix)) = citation.rsplit_once(':') {
+            let start = suffix.split('-').next().unwrap_or(suffix);
+            if let Ok(line) = start.parse::<usize>() {
+                return Self::new(path, line);
+            }
+        }
+        Self::new(citation, 1)
+    }
+}
+
+/// An entry in the location picker.
+#[derive(Debug, Clone)]
+pub struct PickerEntry {
+    pub location: SourceLocation,
+    pub label: String,
+}
+
+/// A selectable list of locations (citations or search results).
+#[derive(Debug, Clone)]
+pub struct LocationPicker {
+    pub title: String,
+    pub entries: Vec<PickerEntry>,
+    pub selected: usize,
+}
+
+impl LocationPicker {
+    /// Build a picker from the files cited in a research document.
+    pub fn from_citations(doc: &ResearchDoc) -> Self {
+        let mut entries: Vec<PickerEntry> = Vec::new();
+
+        let cited = doc
+            .codebase_analysis
+            .iter()
+            .flat_map(|f| f.related_files.iter().map(move |p| (p, f.title.as_str())))
+            .chain(
+                doc.sources
+                    .iter()
+                    .filter(|s| {
+                        matches!(
+                            s.source_type,
+                            SourceType::File
+                                | SourceType::KnowledgeGraph
+                                | SourceType::Documentation
+                        )
+                    })
+                    .map(|s| (&s.location, s.source_type.as_str())),
+            );
+
+        for (citation, context) in cited {
+            let location = SourceLocation::parse(citation);
+            if entries.iter().any(|e| e.location == location) {
+                continue;
+            }
+            entries.push(PickerEntry {
+                label: format!("{}:{}  {}", location.path, location.line, context),
+                location,
+            });
+        }
+
+        Self {
+            title: "Citations".to_string(),
+            entries,
+            selected: 0,
+        }
+    }
+
+    /// Build a picker from semantic search results.
+    pub fn from_search(query: &str, results: &[SearchResult]) -> Self {
+        let entries = results
+            .iter()
+            .map(|r| PickerEntry {
+                location: SourceLocation::new(&r.path, r.start_line as usize),
+                label: format!(
+                    "{}:{}-{}  ({:.2})",
+                    r.path, r.start_line, r.end_line, r.score
+                ),
+            })
+            .collect();
+
+        Self {
+            title: format!("Search: {}", query),
+            entries,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + 1) % self.entries.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+        }
+    }
+
+    pub fn selected_location(&self) -> Option<&SourceLocation> {
+        self.entries.get(self.selected).map(|e| &e.location)
+    }
+}
+
+/// A file the research context would include, and whether it is excluded.
+#[derive(Debug, Clone)]
+pub struct ReviewEntry {
+    pub path: String,
+    pub excluded: bool,
+}
+
+/// Checkbox list of the files research would send to the LLM.
+#[derive(Debug, Clone)]
+pub struct ContextReview {
+    pub task_id: String,
+    pub entries: Vec<ReviewEntry>,
+    pub selected: usize,
+    /// Exclusions that aren't one of the listed files, e.g. glob patterns
+    patterns: Vec<String>,
+}
+
+impl ContextReview {
+    /// Build the list from candidate files and the task's current exclusions.
+    pub fn new(task_id: impl Into<String>, files: Vec<String>, excluded: &[String]) -> Self {
+        let patterns = excluded
+            .iter()
+            .filter(|pattern| !files.contains(pattern))
+            .cloned()
+            .collect();
+        let entries = files
+            .into_iter()
+            .map(|path| ReviewEntry {
+                excluded: excluded.iter().any(|p| matches_pattern(p, &path)),
+                path,
+            })
+            .collect();
+
+        Self {
+            task_id: task_id.into(),
+            entries,
+            selected: 0,
+            patterns,
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + 1) % self.entries.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+        }
+    }
+
+    /// Toggle whether the selected file is excluded.
+    ///
+    /// Files excluded by a pattern stay excluded; returns false for them.
+    pub fn toggle(&mut self) -> bool {
+        let patterns = &self.patterns;
+        let Some(entry) = self.entries.get_mut(self.selected) else {
+            return false;
+        };
+        if entry.excluded && patterns.iter().any(|p| matches_pattern(p, &entry.path)) {
+            return false;
+        }
+        entry.excluded = !entry.excluded;
+        true
+    }
+
+    /// The exclusion list to save to the task.
+    pub fn excluded(&self) -> Vec<String> {
+        let mut excluded = self.patterns.clone();
+        excluded.extend(
+            self.entries
+                .iter()
+                .filter(|e| {
+                    e.excluded && !self.patterns.iter().any(|p| matches_pattern(p, &e.path))
+                })
+                .map(|e| e.path.clone()),
+        );
+        excluded
+    }
+}
+
+/// An open file with a cursor and jump history.
+#[derive(Debug, Clone)]
+pub struct FileViewer {
+    pub path: String,
+    pub lines: Vec<String>,
+    /// 0-based cursor line
+    pub cursor_line: usize,
+    /// 0-based cursor column (in characters)
+    pub cursor_col: usize,
+    /// Locations to return to with Ctrl-O
+    history: Vec<SourceLocation>,
+}
+
+impl FileViewer {
+    /// Open a file with the cursor on the given line.
+    pub fn open(location: &SourceLocation) -> std::io::Result<Self> {
+        let mut viewer = Self {
+            path: String::new(),
+            lines: Vec::new(),
+            cursor_line: 0,
+            cursor_col: 0,
+            history: Vec::new(),
+        };
+        viewer.load(location)?;
+        Ok(viewer)
+    }
+
+    /// Current cursor location.
+    pub fn location(&self) -> SourceLocation {
+        SourceLocation::new(&self.path, self.cursor_line + 1)
+    }
+
+    /// Jump to a location, remembering the current one.
+    pub fn jump_to(&mut self, location: &SourceLocation) -> std::io::Result<()> {
+        let previous = self.location();
+        self.load(location)?;
+        self.history.push(previous);
+        Ok(())
+    }
+
+    /// Return to the location before the last jump.
+    pub fn back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(location) => self.load(&location).is_ok(),
+            None => false,
+        }
+    }
+
+    /// File extension, used to select highlighting rules.
+    pub fn extension(&self) -> &str {
+        Path::new(&self.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+    }
+
+    pub fn move_up(&mut self, count: usize) {
+        self.cursor_line = self.cursor_line.saturating_sub(count);
+        self.clamp_col();
+    }
+
+    pub fn move_down(&mut self, count: usize) {
+        let last = self.lines.len().saturating_sub(1);
+        self.cursor_line = (self.cursor_line + count).min(last);
+        self.clamp_col();
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor_col = self.cursor_col.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor_col += 1;
+        self.clamp_col();
+    }
+
+    pub fn goto_top(&mut self) {
+        self.cursor_line = 0;
+        self.clamp_col();
+    }
+
+    pub fn goto_bottom(&mut self) {
+        self.cursor_line = self.lines.len().saturating_sub(1);
+        self.clamp_col();
+    }
+
+    /// The identifier under the cursor, if any.
+    pub fn word_under_cursor(&self) -> Option<String> {
+        let chars: Vec<char> = self.lines.get(self.cursor_line)?.chars().collect();
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+
+        if !chars.get(self.cursor_col).copied().is_some_and(is_ident) {
+            return None;
+        }
+
+        let start = chars[..self.cursor_col]
+            .iter()
+            .rposition(|c| !is_ident(*c))
+            .map_or(0, |i| i + 1);
+        let end = chars[self.cursor_col..]
+            .iter()
+            .position(|c| !is_ident(*c))
+            .map_or(chars.len(), |i| self.cursor_col + i);
+
+        Some(chars[start..end].iter().collect())
+    }
+
+    fn load(&mut self, location: &SourceLocation) -> std::io::Result<()> {
+        if location.path != self.path {
+            let content = std::fs::read_to_string(&location.path)?;
+            self.lines = content
+                .lines()
+                .map(|l| l.replace('\t', &" ".repeat(TAB_WIDTH)))
+                .collect();
+            if self.lines.is_empty() {
+                self.lines.push(String::new());
+            }
+            self.path = location.path.clone();
+        }
+        self.cursor_line = location.line.saturating_sub(1).min(self.lines.len() - 1);
+        self.cursor_col = first_non_blank(&self.lines[self.cursor_line]);
+        Ok(())
+    }
+
+    fn clamp_col(&mut self) {
+        let len = self
+            .lines
+            .get(self.cursor_line)
+            .map_or(0, |l| l.chars().count());
+        self.cursor_col = self.cursor_col.min(len.saturating_sub(1));
+    }
+}
+
+fn first_non_blank(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::DEFAULT_CONFORMANCE_SYSTEM_PROMPT;
+use crate::knowledge::{KnowledgeError, KnowledgeStore};
+use crate::llm::{LLMError, LLM};
+use crate::planning::Plan;
+use crate::prompts::localize;
+use crate::research::extract_json;
+
+use super::executor::ConformanceStatus;
+use super::prompts::build_conformance_prompt;
+use super::runner::unified_diff;
+
+/// Maximum number of callers listed per changed symbol.
+const MAX_CALLERS_PER_SYMBOL: usize = 10;
+
+/// How a file was changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// An actual change made to a file, taken from a unified diff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Path relative to the repository root
+    pub path: String,
+    /// Whether the file was added, modified or deleted
+    pub kind: ChangeKind,
+    /// The unified diff for this file
+    pub diff: String,
+}
+
+impl FileChange {
+    /// Lines added by this change (without the leading `+`).
+    pub fn added_lines(&self) -> impl Iterator<Item = &str> {
+        self.diff
+            .lines()
+            .filter(|l| l.starts_with('+') && !l.starts_with("+++"))
+            .map(|l| &l[1..])
+    }
+
+    /// Names of functions and types whose definitions or bodies were touched.
+    ///
+    /// Uses definitions on changed lines plus the enclosing scope git prints
+    /// in hunk headers (`@@ -1,2 +1,3 @@ fn enclosing()`).
+    pub fn changed_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = Vec::new();
+
+        for line in self.diff.lines() {
+            let text = if line.starts_with("+++") || line.starts_with("---") {
+                continue;
+            } else if line.starts_with('+') || line.starts_with('-') {
+                &line[1..]
+            } else if let Some(header) = line.strip_prefix("@@") {
+                header.split_once("@@").map_or("", |(_, scope)| scope)
+            } else {
+                continue;
+            };
+
+            for caps in definition_regex().captures_iter(text) {
+                let name = caps[1].to_string();
+                if !symbols.contains(&name) {
+                    symbols.push(name);
+                }
+            }
+        }
+
+        symbols
+    }
+}
+
+/// Regex matching a function or type definition and capturing its name.
+fn definition_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"\b(?:fn|def|func|function|class|struct|interface|trait|enum)\s+([A-Za-z_][A-Za-z0-9_]*)",
+        )
+        .expect("valid definition regex")
+    })
+}
+
+/// Parse `git diff` output into per-file changes.
+pub fn parse_unified_diff(diff: &str) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+    let mut current: Option<FileChange> = None;
+    let mut old_path: Option<String> = None;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            changes.extend(current.take());
+            old_path = None;
+            // Fallback path from the header in case there are no ---/+++ lines (binary files)
+            let path = line
+                .rsplit_once(" b/")
+                .map(|(_, p)| p.to_string())
+                .unwrap_or_default();
+            current = Some(FileChange {
+                path,
+                kind: ChangeKind::Modified,
+                diff: String::new(),
+            });
+        }
+
+        let Some(change) = current.as_mut() else {
+            continue;
+        };
+
+        if line.starts_with("new file mode") {
+            change.kind = ChangeKind::Added;
+        } else if line.starts_with("deleted file mode") {
+            change.kind = ChangeKind::Deleted;
+        } else if let Some(path) = line.strip_prefix("--- a/") {
+            old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("+++ b/") {
+            change.path = path.to_string();
+        } else if line == "+++ /dev/null" {
+            if let Some(path) = old_path.take() {
+                change.path = path;
+            }
+        }
+
+        change.diff.push_str(line);
+        change.diff.push('\n');
+    }
+
+    changes.extend(current);
+    changes
+}
+
+/// Category of a conformance deviation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviationKind {
+    /// A file was changed that the plan does not mention
+    OutOfScope,
+    /// A plan step has no corresponding change
+    MissingStep,
+    /// A file was created where the plan expected a modification, or vice versa
+    KindMismatch,
+    /// A declared export was not found in the created file
+    MissingExport,
+    /// Raised by the LLM review against the plan's acceptance criteria
+    Review,
+}
+
+/// A single way in which the changes deviate from the plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deviation {
+    pub path: String,
+    pub kind: DeviationKind,
+    pub severity: ConformanceStatus,
+    pub message: String,
+}
+
+/// A changed symbol together with the code that calls it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactedSymbol {
+    pub path: String,
+    pub symbol: String,
+    pub callers: Vec<String>,
+}
+
+/// Result of checking changes against a plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    /// Overall status (the worst of all deviations)
+    pub status: ConformanceStatus,
+    /// Short description of overall conformance
+    pub summary: String,
+    /// Deviations found by the scope check and the LLM review
+    pub deviations: Vec<Deviation>,
+    /// Changed symbols with callers elsewhere in the codebase
+    pub impact: Vec<ImpactedSymbol>,
+}
+
+impl ConformanceReport {
+    /// Whether the changes may be committed (no failed deviations).
+    pub fn is_committable(&self) -> bool {
+        self.status != ConformanceStatus::Failed
+    }
+
+    /// Deviations as plain messages, as stored on an `ExecutionResult`.
+    pub fn deviation_messages(&self) -> Vec<String> {
+        self.deviations
+            .iter()
+            .map(|d| format!("{}: {}", d.path, d.message))
+            .collect()
+    }
+
+    fn add(&mut self, deviation: Deviation) {
+        self.status = worst(self.status, deviation.severity);
+        self.deviations.push(deviation);
+    }
+}
+
+/// Returns the more severe of two statuses.
+fn worst(a: ConformanceStatus, b: ConformanceStatus) -> ConformanceStatus {
+    use ConformanceStatus::*;
+    match (a, b) {
+        (Failed, _) | (_, Failed) => Failed,
+        (Warning, _) | (_, Warning) => Warning,
+        _ => Passed,
+    }
+}
+
+/// Changes for files `git diff` doesn't list because they are untracked
+/// (`git ls-files --others --exclude-standard`), as new-file diffs.
+///
+/// `paths` are relative to `root`. Files that aren't UTF-8 text get a diff
+/// without hunks, like git's binary diffs. Arq's own files are skipped.
+pub fn untracked_changes(root: &Path, paths: &[String]) -> Vec<FileChange> {
+    paths
+        .iter()
+        .filter(|path| !is_arq_path(path) && root.join(path).is_file())
+        .map(|path| {
+            let content = std::fs::read_to_string(root.join(path)).unwrap_or_default();
+            FileChange {
+                path: path.clone(),
+                kind: ChangeKind::Added,
+                diff: unified_diff(path, None, &content),
+            }
+        })
+        .collect()
+}
+
+fn normalize_path(path: &str) -> &str {
+    path.trim().trim_start_matches("./")
+}
+
+/// Whether `path` is in a `.arq` directory: research and plan outputs,
+/// mirrored tasks, test stubs or local-layout data. They change as Arq
+/// works, not as part of the plan.
+fn is_arq_path(path: &str) -> bool {
+    Path::new(normalize_path(path))
+        .components()
+        .any(|c| c.as_os_str() == ".arq")
+}
+
+/// Check which changed files the plan declares, without calling the LLM.
+///
+/// Flags out-of-scope files, unimplemented steps, created/modified mismatches
+/// and declared exports missing from created files. Changes to Arq's own
+/// files are ignored.
+pub fn check_scope(plan: &Plan, changes: &[FileChange]) -> ConformanceReport {
+    let mut report = ConformanceReport {
+        status: ConformanceStatus::Passed,
+        summary: String::new(),
+        deviations: Vec::new(),
+        impact: Vec::new(),
+    };
+
+    let created: HashMap<&str, _> = plan
+        .files_to_create
+        .iter()
+        .map(|f| (normalize_path(&f.path), f))
+        .collect();
+    let modified: HashMap<&str, _> = plan
+        .files_to_modify
+        .iter()
+        .map(|f| (normalize_path(&f.path), f))
+        .collect();
+
+    for change in changes.iter().filter(|c| !is_arq_path(&c.path)) {
+        let path = normalize_path(&change.path);
+
+        if let Some(spec) = created.get(path) {
+            if change.kind != ChangeKind::Added {
+                report.add(Deviation {
+                    path: path.to_string(),
+                    kind: DeviationKind::KindMismatch,
+                    severity: ConformanceStatus::Warning,
+                    message: "Plan expected a new file, but an existing file was changed"
+                        .to_string(),
+                });
+            }
+
+            let added: String = change.added_lines().collect::<Vec<_>>().join("\n");
+            for export in &spec.exports {
+                if !added.contains(&export.name) {
+                    report.add(Deviation {
+                        path: path.to_string(),
+                        kind: DeviationKind::MissingExport,
+                        severity: ConformanceStatus::Failed,
+                        message: format!("Declared export `{}` was not found", export.name),
+                    });
+                }
+            }
+        } else if modified.contains_key(path) {
+            if change.kind == ChangeKind::Added {
+                report.add(Deviation {
+                    path: path.to_string(),
+                    kind: DeviationKind::KindMismatch,
+                    severity: ConformanceStatus::Warning,
+                    message: "Plan expected a modification, but the file was created".to_string(),
+                });
+            }
+        } else {
+            report.add(Deviation {
+                path: path.to_string(),
+                kind: DeviationKind::OutOfScope,
+                severity: ConformanceStatus::Failed,
+                message: "File is not part of the plan".to_string(),
+            });
+        }
+    }
+
+    let changed: Vec<&str> = changes.iter().map(|c| normalize_path(&c.path)).collect();
+    for path in created.keys().chain(modified.keys()) {
+        if !changed.contains(path) {
+            report.add(Deviation {
+                path: path.to_string(),
+                kind: DeviationKind::MissingStep,
+                severity: ConformanceStatus::Warning,
+                message: "Plan step has no corresponding change".to_string(),
+            });
+        }
+    }
+
+    report
+}
+
+/// Checks agent changes against the plan using scope rules, graph impact
+/// analysis and an LLM review of the acceptance criteria.
+pub struct ConformanceChecker<L: LLM> {
+    llm: L,
+    knowledge_store: Option<Arc<dyn KnowledgeStore>>,
+    system_prompt: String,
+    output_language: Option<String>,
+}
+
+impl<L: LLM> ConformanceChecker<L> {
+    /// Creates a new conformance checker.
+    pub fn new(llm: L) -> Self {
+        Self {
+            llm,
+            knowledge_store: None,
+            system_prompt: DEFAULT_CONFORMANCE_SYSTEM_PROMPT.to_string(),
+            output_language: None,
+        }
+    }
+
+    /// Creates a new conformance checker that also reports impacted callers.
+    pub fn with_knowledge_store(llm: L, knowledge_store: Arc<dyn KnowledgeStore>) -> Self {
+        Self {
+            llm,
+            knowledge_store: Some(knowledge_store),
+            system_prompt: DEFAULT_CONFORMANCE_SYSTEM_PROMPT.to_string(),
+            output_language: None,
+        }
+    }
+
+    /// Replaces the review system prompt, e.g. with the agent persona from
+    /// [`crate::prompts::for_phase`].
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = prompt.into();
+        self
+    }
+
+    /// Has the model write the review summary and deviations in `language`.
+    pub fn with_output_language(mut self, language: Option<String>) -> Self {
+        self.output_language = language;
+        self
+    }
+
+    /// Checks the given changes against the plan.
+    pub async fn check(
+        &self,
+        plan: &Plan,
+        changes: &[FileChange],
+    ) -> Result<ConformanceReport, ConformanceError> {
+        let changes: Vec<FileChange> = changes
+            .iter()
+            .filter(|c| !is_arq_path(&c.path))
+            .cloned()
+            .collect();
+        let changes = changes.as_slice();
+
+        // 1. Scope rules
+        let mut report = check_scope(plan, changes);
+
+        // 2. Graph impact of changed symbols
+        if let Some(ref kg) = self.knowledge_store {
+            report.impact = self.impact_analysis(kg, changes).await?;
+        }
+
+        // 3. LLM review against acceptance criteria
+        let prompt = build_conformance_prompt(plan, changes, &report);
+        let system_prompt = localize(&self.system_prompt, self.output_language.as_deref());
+        let response = self
+            .llm
+            .complete_with_system(&system_prompt, &prompt)
+            .await?;
+        let review = parse_review(&response)?;
+
+        report.summary = review.summary;
+        report.status = worst(report.status, review.status.into());
+        for deviation in review.deviations {
+            report.add(Deviation {
+                path: deviation.path,
+                kind: DeviationKind::Review,
+                severity: deviation.severity.into(),
+                message: deviation.message,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Looks up callers of every symbol touched by the changes.
+    async fn impact_analysis(
+        &self,
+        kg: &Arc<dyn KnowledgeStore>,
+        changes: &[FileChange],
+    ) -> Result<Vec<ImpactedSymbol>, ConformanceError> {
+        let mut impact = Vec::new();
+
+        for change in changes {
+            // New files have no existing callers
+            if change.kind == ChangeKind::Added {
+                continue;
+            }
+
+            for symbol in change.changed_symbols() {
+                let callers = kg.get_impact(&symbol).await?;
+                if !callers.is_empty() {
+                    impact.push(ImpactedSymbol {
+                        path: change.path.clone(),
+                        symbol,
+                        callers: callers.into_iter().take(MAX_CALLERS_PER_SYMBOL).collect(),
+                    });
+                }
+            }
+        }
+
+        Ok(impact)
+    }
+}
+
+/// Parses the LLM review response.
+fn parse_review(response: &str) -> Result<ReviewResponse, ConformanceError> {
+    let json_str = extract_json(response);
+    serde_json::from_str(json_str).map_err(|e| {
+        let excerpt = match json_str.char_indices().nth(500) {
+            Some((end, _)) => &json_str[..end],
+            None => json_str,
+        };
+        ConformanceError::ParseError(format!(
+            "Failed to parse conformance review as JSON: {}. Response: {}",
+            e, excerpt
+        ))
+    })
+}
+
+/// Review structure from LLM.
+#[derive(Debug, Deserialize)]
+struct ReviewResponse {
+    status: ReviewStatus,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    deviations: Vec<ReviewDeviation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewDeviation {
+    path: String,
+    severity: ReviewStatus,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReviewStatus {
+    Passed,
+    Warning,
+    Failed,
+}
+
+impl From<ReviewStatus> for ConformanceStatus {
+    fn from(status: ReviewStatus) -> Self {
+        match status {
+            ReviewStatus::Passed => ConformanceStatus::Passed,
+            ReviewStatus::Warning => ConformanceStatus::Warning,
+            ReviewStatus::Failed => ConformanceStatus::Failed,
+        }
+    }
+}
+
+/// Errors that can occur during a conformance check.
+#[derive(Debug, Error)]
+pub enum ConformanceError {
+    #[error("LLM error: {0}")]
+    LLM(#[from] LLMError),
+
+    #[error("Knowledge graph error: {0}")]
+    Knowledge(#[from] KnowledgeError),
+
+    #[error("Parse error: {0}")]
+    ParseError(String),
+}
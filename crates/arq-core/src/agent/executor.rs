@@ -92,7 +92,7 @@ impl AgentExecutor {
 }
 
 /// An item to be executed by the agent.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExecutionItem {
     /// Create a new file
     Create { path: String, description: String },
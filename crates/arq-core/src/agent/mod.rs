@@ -1,3 +1,13 @@
+mod conformance;
 mod executor;
+pub mod prompts;
+mod runner;
 
+pub use conformance::{
+    check_scope, parse_unified_diff, untracked_changes, ChangeKind, ConformanceChecker,
+    ConformanceError, ConformanceReport, Deviation, DeviationKind, FileChange, ImpactedSymbol,
+};
 pub use executor::{AgentExecutor, ConformanceStatus, ExecutionItem, ExecutionResult};
+pub use runner::{
+    parse_file_content, unified_diff, AgentError, AgentProgress, AgentRunner, FileEdit,
+};
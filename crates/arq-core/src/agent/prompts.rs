@@ -0,0 +1,136 @@
+use super::conformance::{ConformanceReport, FileChange};
+use super::executor::ExecutionItem;
+use crate::planning::Plan;
+
+/// Maximum diff characters included per file, to keep the prompt bounded.
+const MAX_DIFF_CHARS_PER_FILE: usize = 8000;
+
+/// Builds the user prompt for the conformance review.
+///
+/// `scope` carries the deviations and impact found before the LLM is called,
+/// so the review can confirm or explain them.
+pub fn build_conformance_prompt(
+    plan: &Plan,
+    changes: &[FileChange],
+    scope: &ConformanceReport,
+) -> String {
+    let plan_yaml = plan
+        .to_yaml()
+        .unwrap_or_else(|_| format!("approach: {}", plan.approach));
+
+    let diffs = changes
+        .iter()
+        .map(|c| {
+            let diff = if c.diff.len() > MAX_DIFF_CHARS_PER_FILE {
+                let end = floor_char_boundary(&c.diff, MAX_DIFF_CHARS_PER_FILE);
+                format!("{}\n... (diff truncated)", &c.diff[..end])
+            } else {
+                c.diff.clone()
+            };
+            format!("### {} ({:?})\n\n```diff\n{}```", c.path, c.kind, diff)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mut prompt = format!(
+        r#"## Approved Plan
+
+```yaml
+{plan_yaml}```
+
+## Actual Changes
+
+{diffs}
+"#
+    );
+
+    if !scope.deviations.is_empty() {
+        prompt.push_str("\n## Scope Check Findings\n\n");
+        for d in &scope.deviations {
+            prompt.push_str(&format!("- {} ({:?}): {}\n", d.path, d.kind, d.message));
+        }
+    }
+
+    if !scope.impact.is_empty() {
+        prompt.push_str("\n## Impact Analysis (callers of changed code)\n\n");
+        for i in &scope.impact {
+            prompt.push_str(&format!(
+                "- `{}` in {} is called by: {}\n",
+                i.symbol,
+                i.path,
+                i.callers.join(", ")
+            ));
+        }
+    }
+
+    prompt.push_str(
+        "\nIMPORTANT: Judge the changes ONLY against the plan above. Flag anything the plan does not call for, and any plan step whose acceptance criteria are not met.",
+    );
+
+    prompt
+}
+
+/// System prompt for generating a file in the Agent phase.
+pub const AGENT_SYSTEM_PROMPT: &str = r#"You are implementing an approved plan in a codebase, one file at a time. You are given the plan, the file to write, and its current content if it exists.
+
+Write the complete new content of the file, implementing exactly what the plan calls for in it and nothing else. Keep the existing code, style and comments that the plan does not change. Do not touch other files; they are written separately.
+
+Respond with the complete file in a single fenced code block and nothing else."#;
+
+/// Builds the user prompt for generating the file of `item`.
+///
+/// `current` is the file's content, `None` if it doesn't exist yet.
+pub fn build_edit_prompt(plan: &Plan, item: &ExecutionItem, current: Option<&str>) -> String {
+    let plan_yaml = plan
+        .to_yaml()
+        .unwrap_or_else(|_| format!("approach: {}", plan.approach));
+    let (action, description) = match item {
+        ExecutionItem::Create { description, .. } => ("Create", description),
+        ExecutionItem::Modify { description, .. } => ("Modify", description),
+    };
+    let path = item.path();
+
+    let mut prompt = format!(
+        r#"## Approved Plan
+
+```yaml
+{plan_yaml}```
+
+## File
+
+{action} `{path}`: {description}
+"#
+    );
+
+    let steps: Vec<_> = plan
+        .steps
+        .iter()
+        .filter(|s| s.targets.iter().any(|t| t == path))
+        .collect();
+    if !steps.is_empty() {
+        prompt.push_str("\n## Steps for This File\n\n");
+        for step in steps {
+            prompt.push_str(&format!("- {}: {}\n", step.id, step.description));
+        }
+    }
+
+    match current {
+        Some(content) => prompt.push_str(&format!(
+            "\n## Current Content\n\n```\n{}{}```\n",
+            content,
+            if content.ends_with('\n') { "" } else { "\n" }
+        )),
+        None => prompt.push_str("\nThe file does not exist yet.\n"),
+    }
+
+    prompt.push_str(&format!("\nWrite the complete new content of `{path}`."));
+    prompt
+}
+
+/// Largest char boundary at or below `index`.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    (0..=index.min(s.len()))
+        .rev()
+        .find(|&i| s.is_char_boundary(i))
+        .unwrap_or(0)
+}
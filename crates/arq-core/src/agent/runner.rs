@@ -0,0 +1,325 @@
+//! Code generation for the Agent phase.
+//!
+//! [`AgentRunner`] works through the items of an approved plan, files to
+//! create first, then files to modify. For each it sends the plan, the
+//! item and the file's current content to the LLM and asks for the complete
+//! new content. The result is a [`FileEdit`]: its unified diff is shown to
+//! the developer, who decides whether [`FileEdit::apply`] writes it to the
+//! working tree. Each edit is checked against the plan's scope like
+//! `arq check` does, and recorded as an [`ExecutionResult`] for the agent
+//! log.
+//!
+//! Existing files under `context.private_paths` are never sent; editing one
+//! fails with [`AgentError::PrivatePath`].
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use crate::knowledge::PathBlocklist;
+use crate::llm::{CancellationToken, LLMError, TokenUsage, LLM};
+use crate::planning::Plan;
+use crate::research::compare::{diff_lines, DiffLine};
+
+use super::conformance::{check_scope, ChangeKind, DeviationKind, FileChange};
+use super::executor::{AgentExecutor, ConformanceStatus, ExecutionItem, ExecutionResult};
+use super::prompts::{build_edit_prompt, AGENT_SYSTEM_PROMPT};
+
+/// Unchanged lines shown around each change in a diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Progress events while generating an edit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AgentProgress {
+    /// Asking the LLM for the new content of a file
+    GeneratingEdit { path: String },
+    /// Estimated tokens used by the LLM call
+    TokenUsage(TokenUsage),
+}
+
+/// New content generated for one plan item, not yet written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEdit {
+    pub item: ExecutionItem,
+    /// Content of the file before the edit; `None` if it doesn't exist
+    pub original: Option<String>,
+    /// Complete new content
+    pub content: String,
+}
+
+impl FileEdit {
+    /// Path of the file, relative to the project root.
+    pub fn path(&self) -> &str {
+        self.item.path()
+    }
+
+    /// The edit as a unified diff, as `git diff` would show it once applied.
+    pub fn change(&self) -> FileChange {
+        FileChange {
+            path: self.path().to_string(),
+            kind: if self.original.is_some() {
+                ChangeKind::Modified
+            } else {
+                ChangeKind::Added
+            },
+            diff: unified_diff(self.path(), self.original.as_deref(), &self.content),
+        }
+    }
+
+    /// Whether applying the edit would leave the file as it is.
+    pub fn is_unchanged(&self) -> bool {
+        self.original.as_deref() == Some(self.content.as_str())
+    }
+
+    /// Write the new content under `root`, creating missing directories.
+    pub fn apply(&self, root: &Path) -> Result<(), AgentError> {
+        let path = root.join(self.path());
+        let io = |source| AgentError::Io {
+            path: path.clone(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io)?;
+        }
+        std::fs::write(&path, &self.content).map_err(io)
+    }
+
+    /// The edit checked against the scope of `plan`, for the agent log.
+    ///
+    /// Only deviations of this file count; the plan's other items are
+    /// checked when their own edits are made.
+    pub fn result(&self, plan: &Plan) -> ExecutionResult {
+        let change = self.change();
+        let report = check_scope(plan, std::slice::from_ref(&change));
+        let deviations: Vec<_> = report
+            .deviations
+            .into_iter()
+            .filter(|d| d.kind != DeviationKind::MissingStep)
+            .collect();
+        let conformance = deviations
+            .iter()
+            .map(|d| d.severity)
+            .max_by_key(|severity| match severity {
+                ConformanceStatus::Passed => 0,
+                ConformanceStatus::Warning => 1,
+                ConformanceStatus::Failed => 2,
+            })
+            .unwrap_or(ConformanceStatus::Passed);
+        ExecutionResult {
+            item: self.item.clone(),
+            generated_code: change.diff,
+            conformance,
+            deviations: deviations.into_iter().map(|d| d.message).collect(),
+        }
+    }
+}
+
+/// Generates the file edits of an approved plan.
+pub struct AgentRunner<L: LLM> {
+    llm: L,
+    plan: Plan,
+    root: PathBuf,
+    private_paths: PathBlocklist,
+    cancel: CancellationToken,
+}
+
+impl<L: LLM> AgentRunner<L> {
+    /// Creates a runner for `plan`, editing files under `root`.
+    pub fn new(llm: L, plan: Plan, root: impl Into<PathBuf>) -> Self {
+        Self {
+            llm,
+            plan,
+            root: root.into(),
+            private_paths: PathBlocklist::default(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Refuses to send existing files under `private_paths` to the LLM.
+    pub fn with_private_paths(mut self, private_paths: PathBlocklist) -> Self {
+        self.private_paths = private_paths;
+        self
+    }
+
+    /// Stops the LLM call, and the request billing, when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// The plan being carried out.
+    pub fn plan(&self) -> &Plan {
+        &self.plan
+    }
+
+    /// The plan's items, in the order they are generated.
+    pub fn items(&self) -> Vec<ExecutionItem> {
+        AgentExecutor::new(self.plan.clone()).items()
+    }
+
+    /// Generates the new content of the file of `item`.
+    pub async fn generate_edit(
+        &self,
+        item: &ExecutionItem,
+        progress_tx: &mpsc::UnboundedSender<AgentProgress>,
+    ) -> Result<FileEdit, AgentError> {
+        let path = item.path();
+        if !is_relative_within(path) {
+            return Err(AgentError::InvalidPath(path.to_string()));
+        }
+
+        let full_path = self.root.join(path);
+        let original = if full_path.is_file() {
+            if self.private_paths.blocks(path) {
+                return Err(AgentError::PrivatePath(path.to_string()));
+            }
+            let content = std::fs::read_to_string(&full_path).map_err(|source| AgentError::Io {
+                path: full_path.clone(),
+                source,
+            })?;
+            Some(content)
+        } else {
+            None
+        };
+
+        let _ = progress_tx.send(AgentProgress::GeneratingEdit {
+            path: path.to_string(),
+        });
+        let prompt = build_edit_prompt(&self.plan, item, original.as_deref());
+        let response = tokio::select! {
+            _ = self.cancel.cancelled() => Err(LLMError::Cancelled),
+            response = self.llm.complete_with_system(AGENT_SYSTEM_PROMPT, &prompt) => response,
+        }?;
+        let _ = progress_tx.send(AgentProgress::TokenUsage(TokenUsage::estimate(
+            AGENT_SYSTEM_PROMPT,
+            &prompt,
+            &response,
+        )));
+
+        Ok(FileEdit {
+            item: item.clone(),
+            original,
+            content: parse_file_content(&response)?,
+        })
+    }
+}
+
+/// The file content in an LLM response: the first fenced code block, or the
+/// whole response without one. Ends with a newline.
+pub fn parse_file_content(response: &str) -> Result<String, AgentError> {
+    let mut content = match response.split_once("```") {
+        Some((_, rest)) => {
+            // Skip the language tag after the opening fence
+            let body = rest.split_once('\n').map_or("", |(_, body)| body);
+            match body.rfind("```") {
+                Some(end) if body[end..].trim() == "```" => &body[..end],
+                _ => body.split("\n```").next().unwrap_or(body),
+            }
+        }
+        None => response.trim(),
+    }
+    .to_string();
+
+    if content.trim().is_empty() {
+        return Err(AgentError::EmptyResponse);
+    }
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Whether `path` is relative and stays under the directory it is joined to.
+fn is_relative_within(path: &str) -> bool {
+    !path.trim().is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Unified diff turning `original` into `content`, with git's headers.
+pub fn unified_diff(path: &str, original: Option<&str>, content: &str) -> String {
+    let lines = diff_lines(original.unwrap_or(""), content);
+    let mut out = format!("diff --git a/{path} b/{path}\n");
+    match original {
+        Some(_) => out.push_str(&format!("--- a/{path}\n")),
+        None => out.push_str("new file mode 100644\n--- /dev/null\n"),
+    }
+    out.push_str(&format!("+++ b/{path}\n"));
+
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+    let in_old = |line: &&DiffLine| !matches!(line, DiffLine::Right(_));
+    let in_new = |line: &&DiffLine| !matches!(line, DiffLine::Left(_));
+    let range = |start: usize, len: usize| match len {
+        0 => format!("{},0", start),
+        len => format!("{},{}", start + 1, len),
+    };
+
+    let mut i = 0;
+    while i < changed.len() {
+        // Changes closer than twice the context share a hunk
+        let first = changed[i];
+        let mut last = first;
+        while i + 1 < changed.len() && changed[i + 1] - last <= 2 * DIFF_CONTEXT_LINES {
+            i += 1;
+            last = changed[i];
+        }
+        let start = first.saturating_sub(DIFF_CONTEXT_LINES);
+        let end = (last + DIFF_CONTEXT_LINES + 1).min(lines.len());
+        let hunk = &lines[start..end];
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(
+                lines[..start].iter().filter(in_old).count(),
+                hunk.iter().filter(in_old).count()
+            ),
+            range(
+                lines[..start].iter().filter(in_new).count(),
+                hunk.iter().filter(in_new).count()
+            ),
+        ));
+        for line in hunk {
+            let (prefix, text) = match line {
+                DiffLine::Same(text) => (' ', text),
+                DiffLine::Left(text) => ('-', text),
+                DiffLine::Right(text) => ('+', text),
+            };
+            out.push(prefix);
+            out.push_str(text);
+            out.push('\n');
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Errors that can occur while generating or applying edits.
+#[derive(Debug, Error)]
+pub enum AgentError {
+    #[error("LLM error: {0}")]
+    LLM(#[from] LLMError),
+
+    #[error("The model returned no file content")]
+    EmptyResponse,
+
+    #[error("Plan path {0} is not a relative path inside the project")]
+    InvalidPath(String),
+
+    #[error("{0} is under context.private_paths and is never sent to the LLM")]
+    PrivatePath(String),
+
+    #[error("IO error at {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
@@ -2,15 +2,34 @@
 //!
 //! Configuration is loaded from multiple sources with the following priority:
 //! 1. Environment variables (highest priority)
-//! 2. Project-local `arq.toml` file
-//! 3. User config `~/.config/arq/config.toml`
-//! 4. Built-in defaults (lowest priority)
+//! 2. `arq.toml` in the current directory (e.g. a package in a monorepo)
+//! 3. `arq.toml` files in parent directories, up to the repository root
+//! 4. User config `~/.config/arq/config.toml`
+//! 5. Built-in defaults (lowest priority)
+//!
+//! Files are merged key by key, so a package `arq.toml` only needs the
+//! settings that differ from the repository root's. A file with top-level
+//! `root = true` stops inheritance: files above it and the user config are
+//! ignored. `arq config show --origin` prints where each value came from.
+//!
+//! A config file can hold named profiles (`[profile.<name>.llm]`, ...) that
+//! override its sections when selected with `arq --profile <name>` or
+//! `ARQ_PROFILE`.
+//!
+//! API keys are best kept out of config files: `arq config set-key` stores
+//! them in the OS keyring (see [`secrets`]), which is consulted before
+//! provider environment variables.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::llm::headers;
+
 mod defaults;
+pub mod secrets;
 
 pub use defaults::*;
 
@@ -25,8 +44,39 @@ pub enum ConfigError {
 
     #[error("Invalid configuration: {0}")]
     Invalid(String),
+
+    #[error("Keyring error: {0}")]
+    Keyring(String),
 }
 
+/// Name of the project config file.
+pub const CONFIG_FILE_NAME: &str = "arq.toml";
+
+/// Where a resolved config value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// A config file
+    File(PathBuf),
+    /// A `[profile.<name>]` table in a config file
+    Profile { name: String, path: PathBuf },
+    /// An environment variable
+    Env(&'static str),
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Profile { name, path } => write!(f, "{} (profile {})", path.display(), name),
+            Self::Env(var) => write!(f, "${}", var),
+        }
+    }
+}
+
+/// Origins of the values set by config files, profiles or the environment,
+/// keyed by dotted path (`llm.model`). Values not listed are defaults.
+pub type ConfigOrigins = BTreeMap<String, ConfigOrigin>;
+
 /// Main configuration structure.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
@@ -43,8 +93,29 @@ pub struct Config {
     /// Research phase configuration.
     pub research: ResearchConfig,
 
+    /// Planning phase constraints.
+    pub planning: PlanningConfig,
+
+    /// Shared job queue for LLM-based enrichment.
+    pub enrichment: EnrichmentConfig,
+
+    /// System prompt and temperature overrides per phase.
+    pub prompts: PromptsConfig,
+
     /// Knowledge graph configuration.
     pub knowledge: KnowledgeConfig,
+
+    /// Lifecycle hook commands.
+    pub hooks: HooksConfig,
+
+    /// Additional language parsers.
+    pub parsers: ParsersConfig,
+
+    /// Terminal UI behaviour.
+    pub tui: TuiConfig,
+
+    /// Visualization server (`arq serve`) settings.
+    pub serve: ServeConfig,
 }
 
 impl Config {
@@ -54,53 +125,242 @@ impl Config {
     /// 1. `./arq.toml` (project local)
     /// 2. `~/.config/arq/config.toml` (user config)
     /// 3. Falls back to defaults
+    ///
+    /// The profile named by `ARQ_PROFILE`, if set, is applied.
     pub fn load() -> Result<Self, ConfigError> {
-        // Try project-local config first
-        if Path::new("arq.toml").exists() {
-            return Self::from_file("arq.toml");
-        }
+        Self::load_with_profile(None)
+    }
 
-        // Try user config
-        if let Some(config_dir) = dirs::config_dir() {
-            let user_config = config_dir.join("arq").join("config.toml");
-            if user_config.exists() {
-                return Self::from_file(&user_config);
+    /// Load configuration from default locations with a named profile applied.
+    ///
+    /// Without an explicit profile, `ARQ_PROFILE` is used when set.
+    pub fn load_with_profile(profile: Option<&str>) -> Result<Self, ConfigError> {
+        Self::load_with_origins(profile).map(|(config, _)| config)
+    }
+
+    /// Load configuration like [`Config::load_with_profile`], also returning
+    /// where each value that isn't a default came from.
+    pub fn load_with_origins(profile: Option<&str>) -> Result<(Self, ConfigOrigins), ConfigError> {
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| std::env::var("ARQ_PROFILE").ok().filter(|p| !p.is_empty()));
+
+        let files = Self::config_files()?;
+        if files.is_empty() {
+            if let Some(name) = profile {
+                return Err(ConfigError::Invalid(format!(
+                    "profile '{}' requested but no arq.toml or ~/.config/arq/config.toml found",
+                    name
+                )));
             }
         }
+        Self::from_files_with_profile(&files, profile.as_deref())
+    }
 
-        // Use defaults
-        Ok(Self::default())
+    /// Config files [`Config::load`] merges, lowest priority first.
+    pub fn config_files() -> Result<Vec<PathBuf>, ConfigError> {
+        let cwd = std::env::current_dir()?;
+        let user_config = dirs::config_dir().map(|dir| dir.join("arq").join("config.toml"));
+        Self::discover_files(&cwd, user_config.as_deref())
+    }
+
+    /// Config files applying to `dir`, lowest priority first.
+    ///
+    /// Collects `arq.toml` from `dir` and its parents up to the repository
+    /// root (the first directory containing `.git`), then `user_config`.
+    /// A file with `root = true` ends the search.
+    pub fn discover_files(
+        dir: &Path,
+        user_config: Option<&Path>,
+    ) -> Result<Vec<PathBuf>, ConfigError> {
+        let mut files = Vec::new();
+        let mut inherit = true;
+        for dir in dir.ancestors() {
+            let path = dir.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                let is_root = read_table(&path)?
+                    .get("root")
+                    .and_then(toml::Value::as_bool)
+                    .unwrap_or(false);
+                files.push(path);
+                if is_root {
+                    inherit = false;
+                    break;
+                }
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        if inherit {
+            if let Some(user_config) = user_config.filter(|p| p.is_file()) {
+                files.push(user_config.to_path_buf());
+            }
+        }
+        files.reverse();
+        Ok(files)
     }
 
     /// Load configuration from a specific file.
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
-        let content = std::fs::read_to_string(path)?;
-        let mut config: Config = toml::from_str(&content)?;
+        Self::from_file_with_profile(path, None)
+    }
+
+    /// Load configuration from a specific file with a named profile applied.
+    ///
+    /// A `[profile.<name>]` table overrides the top-level sections key by key,
+    /// so `[profile.local.llm]` only needs the LLM settings that differ:
+    ///
+    /// ```toml
+    /// [llm]
+    /// provider = "anthropic"
+    ///
+    /// [profile.local.llm]
+    /// provider = "ollama"
+    /// model = "qwen2.5-coder"
+    /// ```
+    pub fn from_file_with_profile(
+        path: impl AsRef<Path>,
+        profile: Option<&str>,
+    ) -> Result<Self, ConfigError> {
+        Self::from_files_with_profile(&[path.as_ref().to_path_buf()], profile)
+            .map(|(config, _)| config)
+    }
+
+    /// Merge config files, lowest priority first, and apply a named profile.
+    ///
+    /// Later files override earlier ones key by key. Profiles are looked up
+    /// across all files, so a package can select a profile the repository
+    /// root defines. Returns the config and where its values came from.
+    pub fn from_files_with_profile(
+        files: &[PathBuf],
+        profile: Option<&str>,
+    ) -> Result<(Self, ConfigOrigins), ConfigError> {
+        let mut table = toml::Table::new();
+        let mut profiles = toml::Table::new();
+        let mut profile_paths: BTreeMap<String, PathBuf> = BTreeMap::new();
+        let mut origins = ConfigOrigins::new();
+
+        for path in files {
+            let mut layer = read_table(path)?;
+            layer.remove("root");
+            if let Some(toml::Value::Table(layer_profiles)) = layer.remove("profile") {
+                for name in layer_profiles.keys() {
+                    profile_paths.insert(name.clone(), path.clone());
+                }
+                merge_tables(&mut profiles, &layer_profiles);
+            }
+            record_origins(&layer, "", &ConfigOrigin::File(path.clone()), &mut origins);
+            merge_tables(&mut table, &layer);
+        }
+
+        if let Some(name) = profile {
+            let overrides = profiles
+                .get(name)
+                .and_then(|p| p.as_table())
+                .ok_or_else(|| {
+                    let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                    ConfigError::Invalid(if available.is_empty() {
+                        format!("unknown profile '{}': no [profile.<name>] tables", name)
+                    } else {
+                        format!(
+                            "unknown profile '{}' (available: {})",
+                            name,
+                            available.join(", ")
+                        )
+                    })
+                })?;
+            let origin = ConfigOrigin::Profile {
+                name: name.to_string(),
+                path: profile_paths.get(name).cloned().unwrap_or_default(),
+            };
+            record_origins(overrides, "", &origin, &mut origins);
+            merge_tables(&mut table, overrides);
+        }
+
+        let mut config: Config = toml::Value::Table(table).try_into()?;
 
         // Apply environment variable overrides
-        config.apply_env_overrides();
+        for (key, var) in config.apply_env_overrides() {
+            origins.insert(key.to_string(), ConfigOrigin::Env(var));
+        }
+
+        Ok((config, origins))
+    }
+
+    /// Set `llm.available_models` in a config file, creating it if needed.
+    ///
+    /// Other settings in the file, its comments and formatting are kept as
+    /// they are.
+    pub fn save_available_models(
+        path: impl AsRef<Path>,
+        models: &[String],
+    ) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let mut doc: toml_edit::DocumentMut = if path.exists() {
+            std::fs::read_to_string(path)?
+                .parse()
+                .map_err(|e: toml_edit::TomlError| ConfigError::Invalid(e.to_string()))?
+        } else {
+            toml_edit::DocumentMut::new()
+        };
+
+        let llm = doc
+            .entry("llm")
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+            .ok_or_else(|| ConfigError::Invalid("[llm] must be a table".to_string()))?;
+        let models: toml_edit::Array = models.iter().map(String::as_str).collect();
+        llm.insert("available_models", toml_edit::value(models));
+
+        std::fs::write(path, doc.to_string())?;
+        Ok(())
+    }
 
-        Ok(config)
+    /// The config file settings are saved to: the one [`Config::load`] gives
+    /// priority, or a new `arq.toml` in the current directory.
+    pub fn save_path() -> Result<PathBuf, ConfigError> {
+        Ok(Self::config_files()?
+            .pop()
+            .unwrap_or_else(|| PathBuf::from(CONFIG_FILE_NAME)))
+    }
+
+    /// Whether a config file sets `[llm] api_key` in plain text.
+    pub fn file_sets_api_key(path: impl AsRef<Path>) -> bool {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| content.parse::<toml::Table>().ok())
+            .and_then(|table| table.get("llm")?.get("api_key").cloned())
+            .is_some()
     }
 
     /// Apply environment variable overrides.
-    fn apply_env_overrides(&mut self) {
+    ///
+    /// Returns the keys that were overridden and the variables setting them.
+    fn apply_env_overrides(&mut self) -> Vec<(&'static str, &'static str)> {
+        let mut applied = Vec::new();
+
         // LLM overrides
         if let Ok(provider) = std::env::var("ARQ_LLM_PROVIDER") {
             self.llm.provider = provider;
+            applied.push(("llm.provider", "ARQ_LLM_PROVIDER"));
         }
         if let Ok(model) = std::env::var("ARQ_LLM_MODEL") {
             self.llm.model = Some(model);
+            applied.push(("llm.model", "ARQ_LLM_MODEL"));
         }
         if let Ok(url) = std::env::var("ARQ_LLM_BASE_URL") {
             self.llm.base_url = Some(url);
+            applied.push(("llm.base_url", "ARQ_LLM_BASE_URL"));
         }
         if let Ok(key) = std::env::var("ARQ_LLM_API_KEY") {
             self.llm.api_key = Some(key);
+            applied.push(("llm.api_key", "ARQ_LLM_API_KEY"));
         }
         if let Ok(tokens) = std::env::var("ARQ_LLM_MAX_TOKENS") {
             if let Ok(n) = tokens.parse() {
                 self.llm.max_tokens = n;
+                applied.push(("llm.max_tokens", "ARQ_LLM_MAX_TOKENS"));
             }
         }
 
@@ -108,17 +368,54 @@ impl Config {
         if let Ok(size) = std::env::var("ARQ_MAX_FILE_SIZE") {
             if let Ok(n) = size.parse() {
                 self.context.max_file_size = n;
+                applied.push(("context.max_file_size", "ARQ_MAX_FILE_SIZE"));
             }
         }
         if let Ok(size) = std::env::var("ARQ_MAX_TOTAL_SIZE") {
             if let Ok(n) = size.parse() {
                 self.context.max_total_size = n;
+                applied.push(("context.max_total_size", "ARQ_MAX_TOTAL_SIZE"));
             }
         }
 
         // Storage overrides
         if let Ok(dir) = std::env::var("ARQ_DATA_DIR") {
             self.storage.data_dir = dir;
+            applied.push(("storage.data_dir", "ARQ_DATA_DIR"));
+        }
+        if let Ok(layout) = std::env::var("ARQ_STORAGE_LAYOUT") {
+            if let Ok(layout) = layout.parse() {
+                self.storage.layout = layout;
+                applied.push(("storage.layout", "ARQ_STORAGE_LAYOUT"));
+            }
+        }
+        if let Ok(shared) = std::env::var("ARQ_SHARED_DATA_DIR") {
+            self.storage.shared = matches!(shared.as_str(), "1" | "true" | "yes");
+            applied.push(("storage.shared", "ARQ_SHARED_DATA_DIR"));
+        }
+
+        applied
+    }
+
+    /// Render the config as TOML with the API key masked.
+    ///
+    /// With `origins`, each value is followed by a comment naming the file,
+    /// profile or environment variable that set it, or `default`.
+    pub fn render(&self, origins: Option<&ConfigOrigins>) -> String {
+        let mut table = toml::Table::try_from(self).unwrap_or_default();
+        if let Some(llm) = table.get_mut("llm").and_then(toml::Value::as_table_mut) {
+            if llm.contains_key("api_key") {
+                llm.insert("api_key".to_string(), "********".into());
+            }
+        }
+
+        match origins {
+            Some(origins) => {
+                let mut out = String::new();
+                render_with_origins(&table, "", origins, &mut out);
+                out
+            }
+            None => toml::to_string_pretty(&table).unwrap_or_default(),
         }
     }
 
@@ -129,6 +426,83 @@ impl Config {
     }
 }
 
+/// Read a config file as a TOML table.
+fn read_table(path: &Path) -> Result<toml::Table, ConfigError> {
+    Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+/// Record `origin` for every value in `table`, by dotted key path.
+fn record_origins(
+    table: &toml::Table,
+    prefix: &str,
+    origin: &ConfigOrigin,
+    origins: &mut ConfigOrigins,
+) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::Table(table) => record_origins(table, &path, origin, origins),
+            _ => {
+                origins.insert(path, origin.clone());
+            }
+        }
+    }
+}
+
+/// Render `table` as TOML sections, commenting each value with its origin.
+fn render_with_origins(
+    table: &toml::Table,
+    prefix: &str,
+    origins: &ConfigOrigins,
+    out: &mut String,
+) {
+    use std::fmt::Write;
+
+    let path = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+
+    let (tables, values): (Vec<_>, Vec<_>) = table.iter().partition(|(_, v)| v.is_table());
+    if !prefix.is_empty() && !values.is_empty() {
+        let _ = writeln!(out, "[{}]", prefix);
+        for (key, value) in values {
+            let origin = origins
+                .get(&path(key))
+                .map_or_else(|| "default".to_string(), ToString::to_string);
+            let _ = writeln!(out, "{} = {}  # {}", key, value, origin);
+        }
+        out.push('\n');
+    }
+    for (key, value) in tables {
+        if let Some(table) = value.as_table() {
+            render_with_origins(table, &path(key), origins, out);
+        }
+    }
+}
+
+/// Merge `overrides` into `base`: nested tables merge key by key, any
+/// other value (including arrays) replaces the base value.
+fn merge_tables(base: &mut toml::Table, overrides: &toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => {
+                merge_tables(base, value)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
 /// Context gathering configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -147,6 +521,28 @@ pub struct ContextConfig {
 
     /// Additional file patterns to exclude (glob patterns).
     pub exclude_patterns: Vec<String>,
+
+    /// Deepest directory level shown in the structure tree.
+    pub tree_max_depth: usize,
+
+    /// Entries shown per directory in the structure tree; the rest are counted.
+    pub tree_max_entries: usize,
+
+    /// What the structure tree lists.
+    pub tree_source: TreeSource,
+
+    /// Include test files in research context. Tests are still indexed and
+    /// searchable either way.
+    pub include_tests: bool,
+
+    /// Path patterns (syntax of `knowledge.generated_patterns`) whose code
+    /// is indexed and searchable locally but never sent to the LLM, e.g.
+    /// `["secrets/", "*.pem"]` (default: none).
+    pub private_paths: Vec<String>,
+
+    /// Order files are packed into context in, which decides what is left
+    /// out once `max_total_size` is reached.
+    pub file_order: FileOrder,
 }
 
 impl Default for ContextConfig {
@@ -160,10 +556,40 @@ impl Default for ContextConfig {
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
+            tree_max_depth: DEFAULT_TREE_MAX_DEPTH,
+            tree_max_entries: DEFAULT_TREE_MAX_ENTRIES,
+            tree_source: TreeSource::default(),
+            include_tests: false,
+            private_paths: Vec::new(),
+            file_order: FileOrder::default(),
         }
     }
 }
 
+/// Order in which files are packed into context.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileOrder {
+    /// Entry points (`main.rs`, `lib.rs`, `index.ts`, ...) first, then the
+    /// files most central in the knowledge graph, then smaller files first.
+    #[default]
+    Priority,
+    /// Directory walk order.
+    Walk,
+}
+
+/// Source of the directory structure tree included in context.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TreeSource {
+    /// Every directory entry, except hidden and excluded directories.
+    #[default]
+    Directory,
+    /// Only the files context is gathered from (respects `.gitignore` and
+    /// `include_extensions`), so the tree matches the file contents.
+    Gathered,
+}
+
 /// LLM provider configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -184,12 +610,30 @@ pub struct LLMConfig {
     /// Maximum tokens for response.
     pub max_tokens: u32,
 
+    /// Sampling temperature (unset: the provider's default). Usually set per
+    /// phase in `[prompts.<phase>]` instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
     /// API version (for Anthropic).
     pub api_version: Option<String>,
 
     /// Available models to choose from (for TUI model selection).
     #[serde(default)]
     pub available_models: Vec<String>,
+
+    /// Extra HTTP headers sent with every request, e.g.
+    /// `OpenAI-Organization` or OpenRouter's `X-Title`.
+    /// An empty value removes a well-known header of the same name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_headers: BTreeMap<String, String>,
+
+    /// Answer repeated identical calls from a response cache in the project
+    /// data directory (disable per run with `--no-llm-cache`).
+    pub cache: bool,
+
+    /// Age after which cached responses expire, in seconds (0 = never).
+    pub cache_ttl_secs: u64,
 }
 
 impl Default for LLMConfig {
@@ -200,13 +644,26 @@ impl Default for LLMConfig {
             base_url: None, // Use provider default
             api_key: None,  // Load from env
             max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: None,
             api_version: Some(DEFAULT_ANTHROPIC_API_VERSION.to_string()),
             available_models: Vec::new(),
+            extra_headers: BTreeMap::new(),
+            cache: false,
+            cache_ttl_secs: DEFAULT_LLM_CACHE_TTL_SECS,
         }
     }
 }
 
 impl LLMConfig {
+    /// Headers sent with every request: the provider's well-known headers
+    /// (see [`crate::llm::headers`]) merged with `extra_headers`.
+    pub fn request_headers(&self) -> Vec<(String, String)> {
+        headers::merge_headers(
+            headers::well_known_headers(&self.provider),
+            &self.extra_headers,
+        )
+    }
+
     /// Get the model name, falling back to provider defaults.
     pub fn model_or_default(&self) -> String {
         self.model
@@ -230,10 +687,11 @@ impl LLMConfig {
             })
     }
 
-    /// Get API key from config or environment.
+    /// Get API key from config, the OS keyring or environment, in that order.
     pub fn api_key_or_env(&self) -> Option<String> {
         self.api_key
             .clone()
+            .or_else(|| secrets::get_api_key(&self.provider))
             .or_else(|| std::env::var("ARQ_LLM_API_KEY").ok())
             .or_else(|| match self.provider.as_str() {
                 "anthropic" | "claude" => std::env::var("ANTHROPIC_API_KEY").ok(),
@@ -242,6 +700,29 @@ impl LLMConfig {
                 _ => std::env::var("OPENAI_API_KEY").ok(),
             })
     }
+
+    /// Derive the config for another provider from a `provider[:model]` spec.
+    ///
+    /// The base URL and API key are only kept when the provider is the one
+    /// already configured; otherwise provider defaults and env vars apply.
+    pub fn for_provider(&self, spec: &str) -> LLMConfig {
+        let (provider, model) = match spec.trim().split_once(':') {
+            Some((provider, model)) => (provider.trim(), Some(model.trim().to_string())),
+            None => (spec.trim(), None),
+        };
+
+        let mut config = self.clone();
+        if provider != self.provider {
+            config.provider = provider.to_string();
+            config.base_url = None;
+            config.api_key = None;
+            config.model = None;
+        }
+        if model.is_some() {
+            config.model = model;
+        }
+        config
+    }
 }
 
 /// Storage configuration.
@@ -263,6 +744,25 @@ pub struct StorageConfig {
     /// Plan file name.
     pub plan_file: String,
 
+    /// Whether `data_dir` is shared between users (e.g. on NFS).
+    /// Project data is then namespaced per user under `users/{user}/`.
+    pub shared: bool,
+
+    /// Time to wait for another process's write lock (in milliseconds).
+    pub lock_timeout_ms: u64,
+
+    /// Also write each task's metadata, research, plan and agent log under
+    /// `.arq/tasks/{id}/` in the project, so the lifecycle can be committed.
+    pub mirror_to_repo: bool,
+
+    /// Encrypt task data under `data_dir` with AES-256-GCM, using the key
+    /// from `ARQ_STORAGE_KEY` or the OS keyring (generated on first use).
+    pub encrypt: bool,
+
+    /// Where internal data lives: under `data_dir` (default) or in the
+    /// project's `.arq/data/`.
+    pub layout: StorageLayout,
+
     /// Project root override (for testing). If None, uses current_dir().
     #[serde(skip)]
     pub project_root: Option<PathBuf>,
@@ -276,11 +776,45 @@ impl Default for StorageConfig {
             task_file: DEFAULT_TASK_FILE.to_string(),
             research_file: DEFAULT_RESEARCH_FILE.to_string(),
             plan_file: DEFAULT_PLAN_FILE.to_string(),
+            shared: false,
+            lock_timeout_ms: DEFAULT_LOCK_TIMEOUT_MS,
+            mirror_to_repo: false,
+            encrypt: false,
+            layout: StorageLayout::default(),
             project_root: None,
         }
     }
 }
 
+/// Where a project's internal data (knowledge graph, task metadata, caches)
+/// is stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageLayout {
+    /// Under `data_dir`, one directory per project (`~/.arq/projects/{hash}/`).
+    #[default]
+    Home,
+    /// In the project under `.arq/data/`, for CI runners and servers where
+    /// the home directory isn't writable. The embedding model is cached
+    /// there too.
+    Local,
+}
+
+impl std::str::FromStr for StorageLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "home" => Ok(Self::Home),
+            "local" => Ok(Self::Local),
+            other => Err(format!(
+                "unknown storage layout '{}' (expected home or local)",
+                other
+            )),
+        }
+    }
+}
+
 impl StorageConfig {
     /// Resolve the data directory path, expanding ~ to home directory.
     pub fn resolve_data_dir(&self) -> PathBuf {
@@ -307,13 +841,58 @@ impl StorageConfig {
         root.join(".arq")
     }
 
-    /// Get the project-specific directory in ~/.arq based on current working directory.
-    /// Uses a hash of the absolute path to create unique project folders.
-    /// This is where internal data (knowledge.db, task metadata) is stored.
+    /// Get the project-specific directory for internal data (knowledge.db,
+    /// task metadata, caches); every other internal path is derived from it.
+    ///
+    /// With the home layout this is a directory in ~/.arq named by a hash of
+    /// the current working directory's absolute path; with the local layout
+    /// it is `.arq/data/` in the project.
+    ///
+    /// When `shared` is set, the directory is namespaced by user:
+    /// `{data_dir}/users/{user}/projects/{hash}`, or `.arq/data/users/{user}`.
     pub fn project_dir(&self) -> PathBuf {
-        let base = self.resolve_data_dir();
-        let project_hash = Self::compute_project_hash();
-        base.join("projects").join(project_hash)
+        let base = match self.layout {
+            StorageLayout::Home => self.resolve_data_dir(),
+            StorageLayout::Local => self.local_arq_dir().join(DEFAULT_LOCAL_DATA_DIR),
+        };
+        let base = if self.shared {
+            base.join("users").join(current_user())
+        } else {
+            base
+        };
+        match self.layout {
+            StorageLayout::Home => base.join("projects").join(Self::compute_project_hash()),
+            StorageLayout::Local => base,
+        }
+    }
+
+    /// Create [`Self::project_dir`] if missing and return it. With the local
+    /// layout, a `.gitignore` in `.arq/data/` keeps the database, caches and
+    /// model out of the repository.
+    pub fn create_project_dir(&self) -> std::io::Result<PathBuf> {
+        let dir = self.project_dir();
+        std::fs::create_dir_all(&dir)?;
+        if self.layout == StorageLayout::Local {
+            let ignore = self
+                .local_arq_dir()
+                .join(DEFAULT_LOCAL_DATA_DIR)
+                .join(".gitignore");
+            if !ignore.exists() {
+                std::fs::write(&ignore, "*\n")?;
+            }
+        }
+        Ok(dir)
+    }
+
+    /// Get the embedding model cache: `cache/` in `data_dir`, or in
+    /// `.arq/data/` with the local layout. Shared by all projects in the
+    /// home layout.
+    pub fn model_cache_path(&self) -> PathBuf {
+        let base = match self.layout {
+            StorageLayout::Home => self.resolve_data_dir(),
+            StorageLayout::Local => self.local_arq_dir().join(DEFAULT_LOCAL_DATA_DIR),
+        };
+        base.join(DEFAULT_MODEL_CACHE_DIR)
     }
 
     /// Compute a short hash of the current working directory for project isolation.
@@ -331,16 +910,36 @@ impl StorageConfig {
         hex::encode(&hash[..4])
     }
 
-    /// Get the full path to the tasks directory (in ~/.arq/projects/{hash}/).
+    /// Get the full path to the tasks directory (in the project data directory).
     pub fn tasks_path(&self) -> PathBuf {
         self.project_dir().join(&self.tasks_dir)
     }
 
-    /// Get the full path to a task's metadata directory (in ~/.arq/).
+    /// Get the full path to a task's metadata directory (in the project data directory).
     pub fn task_path(&self, task_id: &str) -> PathBuf {
         self.tasks_path().join(task_id)
     }
 
+    /// Get the path to the research context cache (in the project data directory).
+    pub fn context_cache_path(&self) -> PathBuf {
+        self.project_dir().join(DEFAULT_CONTEXT_CACHE_DIR)
+    }
+
+    /// Get the path to the log of context sent per task (in the project data directory).
+    pub fn sent_context_path(&self) -> PathBuf {
+        self.project_dir().join(DEFAULT_SENT_CONTEXT_DIR)
+    }
+
+    /// Get the path to the LLM response cache (in the project data directory).
+    pub fn llm_cache_path(&self) -> PathBuf {
+        self.project_dir().join(DEFAULT_LLM_CACHE_DIR)
+    }
+
+    /// Get the path to the report of files that failed to index (in the project data directory).
+    pub fn index_report_path(&self) -> PathBuf {
+        self.project_dir().join(DEFAULT_INDEX_REPORT_FILE)
+    }
+
     /// Get the path to research-doc.md in the local .arq directory.
     pub fn local_research_path(&self) -> PathBuf {
         self.local_arq_dir().join(&self.research_file)
@@ -350,6 +949,135 @@ impl StorageConfig {
     pub fn local_plan_path(&self) -> PathBuf {
         self.local_arq_dir().join(&self.plan_file)
     }
+
+    /// Get the directory test stubs are drafted into (`.arq/test-stubs/`).
+    pub fn test_stubs_path(&self) -> PathBuf {
+        self.local_arq_dir().join(DEFAULT_TEST_STUBS_DIR)
+    }
+
+    /// Get the repository mirror of the tasks directory (`.arq/tasks/`).
+    pub fn mirror_tasks_path(&self) -> PathBuf {
+        self.local_arq_dir().join(&self.tasks_dir)
+    }
+
+    /// Get a task's directory in the repository mirror (`.arq/tasks/{id}/`).
+    pub fn mirror_task_path(&self, task_id: &str) -> PathBuf {
+        self.mirror_tasks_path().join(task_id)
+    }
+}
+
+/// Lifecycle hook configuration.
+///
+/// Each hook is a shell command run with task metadata in `ARQ_*` env vars.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Runs before research; a failure aborts the research run.
+    pub pre_research: Option<String>,
+
+    /// Runs after research is saved to the task.
+    pub post_research: Option<String>,
+
+    /// Runs before planning; a failure aborts planning.
+    pub pre_plan: Option<String>,
+
+    /// Runs after the plan is approved.
+    pub post_plan: Option<String>,
+
+    /// Runs after each agent execution step.
+    pub post_agent_step: Option<String>,
+
+    /// Maximum time a hook may run (in seconds).
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_research: None,
+            post_research: None,
+            pre_plan: None,
+            post_plan: None,
+            post_agent_step: None,
+            timeout_secs: DEFAULT_HOOK_TIMEOUT_SECS,
+        }
+    }
+}
+
+/// Additional language parsers.
+///
+/// ```toml
+/// [[parsers.external]]
+/// command = "zig-arq-parser"
+/// extensions = ["zig"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParsersConfig {
+    /// Parsers run as external processes.
+    pub external: Vec<ExternalParserConfig>,
+}
+
+/// A parser run as an external process.
+///
+/// The command receives the file content on stdin and its relative path in
+/// `ARQ_FILE_PATH`, and prints a parse result as JSON on stdout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalParserConfig {
+    /// Program to run (looked up on `PATH`).
+    pub command: String,
+
+    /// Arguments passed to the program.
+    pub args: Vec<String>,
+
+    /// File extensions handled by this parser, without the dot.
+    pub extensions: Vec<String>,
+
+    /// Language name shown in listings; defaults to the command name.
+    pub language: Option<String>,
+
+    /// Maximum time the parser may take per file (in seconds).
+    pub timeout_secs: u64,
+}
+
+impl Default for ExternalParserConfig {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            args: Vec::new(),
+            extensions: Vec::new(),
+            language: None,
+            timeout_secs: DEFAULT_EXTERNAL_PARSER_TIMEOUT_SECS,
+        }
+    }
+}
+
+/// Name of the user running arq, sanitized for use as a path component.
+///
+/// Read from `USER`, `USERNAME` or `LOGNAME`; falls back to `"unknown"`.
+pub fn current_user() -> String {
+    let user: String = ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|u| !u.trim().is_empty())
+        .unwrap_or_default()
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if user.is_empty() || user.chars().all(|c| c == '.') {
+        "unknown".to_string()
+    } else {
+        user
+    }
 }
 
 /// Research phase configuration.
@@ -362,6 +1090,35 @@ pub struct ResearchConfig {
 
     /// Maximum length of error context in messages.
     pub error_context_length: usize,
+
+    /// How retrieved code is guarded against prompt injection.
+    pub content_guard: ContentGuard,
+
+    /// Weights for re-ranking semantic search results by entity metadata.
+    pub rerank: RerankWeights,
+
+    /// Have the model review its research against a rubric in a second call,
+    /// fixing what it can and appending "Confidence & Gaps" (default: false).
+    pub self_critique: bool,
+
+    /// After research in the TUI, ask the model for up to three follow-up
+    /// questions about requirements the task leaves open, offered as quick
+    /// replies while the research awaits validation (default: true).
+    pub follow_up_questions: bool,
+
+    /// Replace files and line ranges cited by findings that don't exist in
+    /// the codebase with the nearest knowledge graph match; without it they
+    /// are only flagged (default: false).
+    pub correct_references: bool,
+
+    /// Language to write research, plans and reviews in, as a code (`de`,
+    /// `ja`) or a name. Code identifiers and paths are never translated
+    /// (default: unset, English).
+    pub output_language: Option<String>,
+
+    /// Ask before sending a research prompt estimated to cost more than this
+    /// many USD, showing the breakdown (default: unset, never ask).
+    pub confirm_cost_above: Option<f64>,
 }
 
 impl Default for ResearchConfig {
@@ -369,10 +1126,225 @@ impl Default for ResearchConfig {
         Self {
             system_prompt: None, // Use built-in default
             error_context_length: DEFAULT_ERROR_CONTEXT_LENGTH,
+            content_guard: ContentGuard::default(),
+            rerank: RerankWeights::default(),
+            self_critique: false,
+            follow_up_questions: true,
+            correct_references: false,
+            output_language: None,
+            confirm_cost_above: None,
         }
     }
 }
 
+/// Constraints every generated plan must respect.
+///
+/// ```toml
+/// [planning]
+/// protected_paths = ["migrations/", "*.lock"]
+/// conventions = ["All new endpoints need OpenAPI entries"]
+/// max_steps = 8
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlanningConfig {
+    /// Path patterns a plan may never create or modify, in the syntax of
+    /// `knowledge.generated_patterns` (`*.lock`, `migrations/`, `src/gen/*.rs`).
+    pub protected_paths: Vec<String>,
+
+    /// Conventions every plan must follow, given to the planner verbatim.
+    pub conventions: Vec<String>,
+
+    /// Most steps (files created or modified) a plan may have.
+    pub max_steps: Option<usize>,
+}
+
+impl PlanningConfig {
+    /// Whether no constraints are configured.
+    pub fn is_empty(&self) -> bool {
+        self.protected_paths.is_empty() && self.conventions.is_empty() && self.max_steps.is_none()
+    }
+}
+
+/// Per-phase persona overrides (see [`crate::prompts`]).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptsConfig {
+    /// Researcher persona.
+    pub research: PhasePromptConfig,
+
+    /// Planner persona, for the planning conversation.
+    pub planning: PhasePromptConfig,
+
+    /// Agent persona, for reviewing changes against the plan.
+    pub agent: PhasePromptConfig,
+}
+
+/// System prompt and temperature for one phase.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhasePromptConfig {
+    /// Replaces the phase's built-in system prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Sampling temperature (unset: the provider's default).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+/// Limits for LLM-based enrichment jobs (summaries, plan risk, file overviews).
+///
+/// ```toml
+/// [enrichment]
+/// concurrency = 4
+/// max_retries = 3
+/// batch_size = 20
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnrichmentConfig {
+    /// Most enrichment LLM calls in flight at once (default: 4).
+    pub concurrency: usize,
+
+    /// Retries of a call failing with a transient error (default: 3).
+    pub max_retries: u32,
+
+    /// Items enriched per LLM call by pipelines that batch (default: 20).
+    pub batch_size: usize,
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_ENRICHMENT_CONCURRENCY,
+            max_retries: DEFAULT_ENRICHMENT_MAX_RETRIES,
+            batch_size: DEFAULT_ENRICHMENT_BATCH_SIZE,
+        }
+    }
+}
+
+/// Terminal UI behaviour.
+///
+/// ```toml
+/// [tui]
+/// mouse = false
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Capture the mouse for clicking tabs and progress items, scrolling
+    /// and selecting chat text (default: true). Turn off where mouse
+    /// reporting gets in the way, e.g. to keep the terminal's own selection.
+    pub mouse: bool,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        Self { mouse: true }
+    }
+}
+
+/// Visualization server settings, for instances shared with a team.
+///
+/// ```toml
+/// [serve]
+/// blocked_paths = ["secrets/", "internal/billing/"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServeConfig {
+    /// Path patterns, in the syntax of `knowledge.generated_patterns`,
+    /// whose entities and chunks the server leaves out of every response.
+    pub blocked_paths: Vec<String>,
+}
+
+/// Score adjustments applied to semantic search results in smart context.
+///
+/// Added to (or subtracted from) the cosine similarity of each chunk, so that
+/// trivial getters and tests don't crowd out core logic. Set all to 0 to
+/// rank by similarity alone.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RerankWeights {
+    /// Boost for chunks containing public functions.
+    pub public_boost: f32,
+
+    /// Boost per cyclomatic complexity point above 1 (capped at 10 points).
+    pub complexity_boost: f32,
+
+    /// Penalty for test code.
+    pub test_penalty: f32,
+
+    /// Penalty for chunks made only of trivial functions.
+    pub trivial_penalty: f32,
+}
+
+impl Default for RerankWeights {
+    fn default() -> Self {
+        Self {
+            public_boost: DEFAULT_RERANK_PUBLIC_BOOST,
+            complexity_boost: DEFAULT_RERANK_COMPLEXITY_BOOST,
+            test_penalty: DEFAULT_RERANK_TEST_PENALTY,
+            trivial_penalty: DEFAULT_RERANK_TRIVIAL_PENALTY,
+        }
+    }
+}
+
+impl RerankWeights {
+    /// Whether any weight is set.
+    pub fn is_enabled(&self) -> bool {
+        [
+            self.public_boost,
+            self.complexity_boost,
+            self.test_penalty,
+            self.trivial_penalty,
+        ]
+        .iter()
+        .any(|w| *w != 0.0)
+    }
+}
+
+/// Prompt injection protection for code included in LLM prompts.
+///
+/// Indexed code is untrusted: comments and strings can contain text that
+/// reads like instructions to the model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentGuard {
+    /// Include code as-is.
+    Off,
+    /// Wrap code in delimited untrusted blocks and redact suspicious lines.
+    #[default]
+    Standard,
+    /// Like `Standard`, but drop any chunk that contains suspicious lines.
+    Strict,
+}
+
+/// What the indexer does with generated or vendored files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GeneratedCodeHandling {
+    /// Leave generated files out of the index.
+    #[default]
+    Skip,
+    /// Index code entities for the graph, but not embeddings for search.
+    GraphOnly,
+    /// Index generated files like any other file.
+    Index,
+}
+
+/// What the indexer does with files above `knowledge.max_file_size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OversizedFileHandling {
+    /// Leave oversized files out of the index.
+    Skip,
+    /// Index code entities and edges for the graph, but no chunks or embeddings.
+    #[default]
+    Signatures,
+}
+
 /// Knowledge graph configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -394,6 +1366,50 @@ pub struct KnowledgeConfig {
 
     /// File extensions to index (uses context.include_extensions if empty).
     pub extensions: Vec<String>,
+
+    /// Rows per multi-row insert while indexing (default: 500).
+    pub insert_batch_size: usize,
+
+    /// Files indexed at once by `arq init` (default: 1, 0 for one per CPU).
+    pub index_jobs: usize,
+
+    /// Handling of generated/vendored files (default: skip).
+    pub generated_code: GeneratedCodeHandling,
+
+    /// Extra path patterns treated as generated (e.g. `"*.gen.rs"`, `"build/"`).
+    pub generated_patterns: Vec<String>,
+
+    /// Path patterns never treated as generated, overriding all heuristics.
+    pub generated_allow: Vec<String>,
+
+    /// Size in bytes above which a file is oversized (default: 1 MB, 0 for no limit).
+    pub max_file_size: u64,
+
+    /// Handling of oversized files (default: signatures).
+    pub oversized_files: OversizedFileHandling,
+
+    /// Days superseded call edges are kept for `--as-of` queries (default: 180, 0 keeps none).
+    pub history_retention_days: u64,
+
+    /// Address of a warm embedder daemon (`arq embedder serve`) to use before
+    /// loading the model in-process (default: none).
+    pub embedder_daemon: Option<String>,
+
+    /// Page the vector index in when the TUI or `arq serve` opens the graph,
+    /// so the first search isn't slow (default: false).
+    pub warmup: bool,
+
+    /// Documentation folders whose markdown and reStructuredText files are
+    /// indexed as documents, e.g. `["docs", "rfcs"]` (default: none).
+    pub docs: Vec<String>,
+
+    /// Languages whose parsers are turned off and whose files aren't
+    /// indexed, e.g. `["csharp"]` (default: none).
+    pub disabled_languages: Vec<String>,
+
+    /// Extensions (without the dot) parsed by a language's parser, e.g.
+    /// `{ "pyi" = "python" }` (default: none).
+    pub extension_overrides: BTreeMap<String, String>,
 }
 
 impl Default for KnowledgeConfig {
@@ -405,6 +1421,19 @@ impl Default for KnowledgeConfig {
             chunk_overlap: DEFAULT_CHUNK_OVERLAP,
             search_limit: DEFAULT_SEARCH_LIMIT,
             extensions: Vec::new(), // Use context.include_extensions by default
+            insert_batch_size: DEFAULT_INSERT_BATCH_SIZE,
+            index_jobs: DEFAULT_INDEX_JOBS,
+            generated_code: GeneratedCodeHandling::default(),
+            generated_patterns: Vec::new(),
+            generated_allow: Vec::new(),
+            max_file_size: DEFAULT_INDEX_MAX_FILE_SIZE,
+            oversized_files: OversizedFileHandling::default(),
+            history_retention_days: DEFAULT_HISTORY_RETENTION_DAYS,
+            embedder_daemon: None,
+            warmup: false,
+            docs: Vec::new(),
+            disabled_languages: Vec::new(),
+            extension_overrides: BTreeMap::new(),
         }
     }
 }
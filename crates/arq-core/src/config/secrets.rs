@@ -0,0 +1,209 @@
+//! API keys stored in the OS keyring.
+//!
+//! Keys live in the platform credential store (macOS Keychain, Windows
+//! Credential Manager, Secret Service on Linux) under the `arq` service,
+//! one entry per provider, so they never have to be written to `arq.toml`.
+//! Set `ARQ_NO_KEYRING=1` to skip keyring lookups, e.g. on headless CI.
+//!
+//! The keyring is behind the `keyring` feature; without it nothing is found
+//! and storing a key fails. Tests use an in-memory store instead.
+
+use super::{ConfigError, KEYRING_SERVICE};
+
+/// Keyring account name for a provider (`claude` shares `anthropic`'s key).
+pub fn keyring_account(provider: &str) -> String {
+    match provider.trim().to_lowercase().as_str() {
+        "claude" => "anthropic".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Whether keyring lookups are disabled through `ARQ_NO_KEYRING`.
+fn keyring_disabled() -> bool {
+    std::env::var("ARQ_NO_KEYRING").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Read a provider's API key from the keyring.
+///
+/// Missing entries, a disabled or unavailable keyring all yield `None`, so
+/// callers fall through to environment variables.
+pub fn get_api_key(provider: &str) -> Option<String> {
+    if keyring_disabled() {
+        return None;
+    }
+    imp::get(&keyring_account(provider))
+        .ok()
+        .flatten()
+        .filter(|key| !key.is_empty())
+}
+
+/// Keyring account holding the storage encryption key.
+pub const STORAGE_KEY_ACCOUNT: &str = "storage-key";
+
+/// Read the storage encryption key (hex) from the keyring.
+pub fn get_storage_key() -> Option<String> {
+    if keyring_disabled() {
+        return None;
+    }
+    imp::get(STORAGE_KEY_ACCOUNT)
+        .ok()
+        .flatten()
+        .filter(|key| !key.is_empty())
+}
+
+/// Store the storage encryption key (hex) in the keyring.
+pub fn set_storage_key(key: &str) -> Result<(), ConfigError> {
+    if keyring_disabled() {
+        return Err(ConfigError::Keyring(
+            "keyring lookups are disabled by ARQ_NO_KEYRING".to_string(),
+        ));
+    }
+    imp::set(STORAGE_KEY_ACCOUNT, key)
+}
+
+/// Store a provider's API key in the keyring, replacing any previous key.
+pub fn set_api_key(provider: &str, key: &str) -> Result<(), ConfigError> {
+    imp::set(&keyring_account(provider), key)
+}
+
+/// Remove a provider's API key from the keyring.
+///
+/// Returns whether a key was stored.
+pub fn delete_api_key(provider: &str) -> Result<bool, ConfigError> {
+    imp::delete(&keyring_account(provider))
+}
+
+#[cfg(all(feature = "keyring", not(test)))]
+mod imp {
+    use super::{ConfigError, KEYRING_SERVICE};
+
+    fn entry(account: &str) -> Result<keyring::Entry, ConfigError> {
+        keyring::Entry::new(KEYRING_SERVICE, account).map_err(keyring_error)
+    }
+
+    fn keyring_error(e: keyring::Error) -> ConfigError {
+        ConfigError::Keyring(e.to_string())
+    }
+
+    pub fn get(account: &str) -> Result<Option<String>, ConfigError> {
+        match entry(account)?.get_password() {
+            Ok(key) => Ok(Some(key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+
+    pub fn set(account: &str, key: &str) -> Result<(), ConfigError> {
+        entry(account)?.set_password(key).map_err(keyring_error)
+    }
+
+    pub fn delete(account: &str) -> Result<bool, ConfigError> {
+        match entry(account)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+}
+
+#[cfg(all(not(feature = "keyring"), not(test)))]
+mod imp {
+    use super::ConfigError;
+
+    fn unsupported() -> ConfigError {
+        ConfigError::Keyring("arq was built without keyring support".to_string())
+    }
+
+    pub fn get(_account: &str) -> Result<Option<String>, ConfigError> {
+        Ok(None)
+    }
+
+    pub fn set(_account: &str, _key: &str) -> Result<(), ConfigError> {
+        Err(unsupported())
+    }
+
+    pub fn delete(_account: &str) -> Result<bool, ConfigError> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod imp {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::ConfigError;
+
+    static ENTRIES: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+    pub fn get(account: &str) -> Result<Option<String>, ConfigError> {
+        let entries = ENTRIES.lock().unwrap();
+        Ok(entries.as_ref().and_then(|e| e.get(account).cloned()))
+    }
+
+    pub fn set(account: &str, key: &str) -> Result<(), ConfigError> {
+        let mut entries = ENTRIES.lock().unwrap();
+        entries
+            .get_or_insert_with(HashMap::new)
+            .insert(account.to_string(), key.to_string());
+        Ok(())
+    }
+
+    pub fn delete(account: &str) -> Result<bool, ConfigError> {
+        let mut entries = ENTRIES.lock().unwrap();
+        Ok(entries
+            .as_mut()
+            .is_some_and(|e| e.remove(account).is_some()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LLMConfig;
+
+    // One test, as `ARQ_NO_KEYRING` is process-wide
+    #[test]
+    fn test_keys_round_trip_and_fall_back() {
+        std::env::remove_var("ARQ_NO_KEYRING");
+
+        // `claude` shares `anthropic`'s entry
+        set_api_key("anthropic", "sk-ant").unwrap();
+        assert_eq!(get_api_key("claude"), Some("sk-ant".to_string()));
+        set_api_key("claude", "sk-ant-2").unwrap();
+        assert_eq!(get_api_key("anthropic"), Some("sk-ant-2".to_string()));
+
+        // The keyring comes after the config file and before env vars
+        let config = LLMConfig {
+            provider: "anthropic".to_string(),
+            ..LLMConfig::default()
+        };
+        assert_eq!(config.api_key_or_env(), Some("sk-ant-2".to_string()));
+        let configured = LLMConfig {
+            api_key: Some("sk-file".to_string()),
+            ..config.clone()
+        };
+        assert_eq!(configured.api_key_or_env(), Some("sk-file".to_string()));
+
+        // Empty entries count as missing
+        set_api_key("openrouter", "").unwrap();
+        assert_eq!(get_api_key("openrouter"), None);
+
+        set_storage_key("00ff").unwrap();
+        assert_eq!(get_storage_key(), Some("00ff".to_string()));
+
+        assert!(delete_api_key("anthropic").unwrap());
+        assert!(!delete_api_key("anthropic").unwrap());
+        assert_eq!(get_api_key("anthropic"), None);
+
+        // Disabled, lookups find nothing and the storage key isn't stored
+        std::env::set_var("ARQ_NO_KEYRING", "1");
+        assert_eq!(get_storage_key(), None);
+        assert!(matches!(
+            set_storage_key("11ee"),
+            Err(ConfigError::Keyring(_))
+        ));
+        std::env::remove_var("ARQ_NO_KEYRING");
+        assert_eq!(get_storage_key(), Some("00ff".to_string()));
+    }
+}
@@ -1,15 +1,41 @@
 use ignore::WalkBuilder;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::thread;
 use thiserror::Error;
 
-use crate::config::ContextConfig;
+use crate::config::{ContextConfig, FileOrder, TreeSource};
+
+/// Files read per worker thread in each batch.
+///
+/// Bounds how far reading runs ahead of the total-size cutoff.
+const READS_PER_WORKER: usize = 8;
+
+/// File names packed first under [`FileOrder::Priority`].
+const ENTRY_POINTS: &[&str] = &[
+    "main.rs",
+    "lib.rs",
+    "main.go",
+    "main.py",
+    "__main__.py",
+    "__init__.py",
+    "app.py",
+    "index.ts",
+    "index.js",
+    "main.ts",
+    "main.js",
+    "Main.java",
+];
 
 /// Builds context from a codebase for LLM analysis.
 #[derive(Clone)]
 pub struct ContextBuilder {
     root_path: PathBuf,
     config: ContextConfig,
+    /// Importance of files by relative path, e.g. knowledge graph centrality.
+    priorities: HashMap<String, f64>,
 }
 
 impl ContextBuilder {
@@ -18,6 +44,7 @@ impl ContextBuilder {
         Self {
             root_path: root_path.into(),
             config: ContextConfig::default(),
+            priorities: HashMap::new(),
         }
     }
 
@@ -26,9 +53,15 @@ impl ContextBuilder {
         Self {
             root_path: root_path.into(),
             config,
+            priorities: HashMap::new(),
         }
     }
 
+    /// Directory context is gathered from.
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
     /// Sets the maximum file size.
     pub fn max_file_size(mut self, size: u64) -> Self {
         self.config.max_file_size = size;
@@ -41,6 +74,15 @@ impl ContextBuilder {
         self
     }
 
+    /// Sets how important files are, by path relative to the root.
+    ///
+    /// Under [`FileOrder::Priority`], files with a higher score are packed
+    /// before others once entry points are in.
+    pub fn with_priorities(mut self, priorities: HashMap<String, f64>) -> Self {
+        self.priorities = priorities;
+        self
+    }
+
     /// Adds an extension to include.
     pub fn include_extension(mut self, ext: impl Into<String>) -> Self {
         self.config.include_extensions.push(ext.into());
@@ -61,137 +103,385 @@ impl ContextBuilder {
         Ok(Context { structure, files })
     }
 
+    /// Hash of the files context would be gathered from and the limits applied.
+    ///
+    /// Based on paths, sizes and modification times, so it is cheap to compute
+    /// and changes whenever a relevant file is added, removed or edited.
+    pub fn codebase_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(
+            format!(
+                "{}\0{}\0{:?}\0",
+                self.config.max_file_size, self.config.max_total_size, self.config.file_order
+            )
+            .as_bytes(),
+        );
+        let mut priorities: Vec<(&String, &f64)> = self.priorities.iter().collect();
+        priorities.sort_by(|a, b| a.0.cmp(b.0));
+        for (path, score) in priorities {
+            hasher.update(format!("{}\0{}\0", path, score).as_bytes());
+        }
+
+        for path in self.candidate_paths() {
+            let relative = path.strip_prefix(&self.root_path).unwrap_or(&path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            if let Ok(metadata) = fs::metadata(&path) {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_nanos());
+                hasher.update(format!("\0{}\0{}\0", metadata.len(), modified).as_bytes());
+            }
+        }
+
+        hex::encode(hasher.finalize())
+    }
+
     /// Builds a directory tree string.
+    ///
+    /// Lists the filesystem or only the gathered files, depending on
+    /// `tree_source`. Directories deeper than `tree_max_depth` are not
+    /// expanded, and entries past `tree_max_entries` are counted instead.
     fn build_tree(&self) -> Result<String, ContextError> {
         let mut tree = String::new();
-        self.build_tree_recursive(&self.root_path, "", &mut tree)?;
+        match self.config.tree_source {
+            TreeSource::Directory => {
+                // Only an unreadable root is an error; subdirectories are marked
+                fs::read_dir(&self.root_path)
+                    .map_err(|e| ContextError::IoError(self.root_path.clone(), e.to_string()))?;
+                self.render_tree(&self.root_path, "", 1, &mut tree, &|dir| {
+                    self.read_tree_dir(dir)
+                });
+            }
+            TreeSource::Gathered => {
+                let listing = self.gathered_listing();
+                self.render_tree(&self.root_path, "", 1, &mut tree, &|dir| {
+                    Some(listing.get(dir).cloned().unwrap_or_default())
+                });
+            }
+        }
         Ok(tree)
     }
 
-    fn build_tree_recursive(
+    /// Lists a directory for the tree, skipping hidden and excluded entries.
+    ///
+    /// Returns `None` when the directory cannot be read.
+    fn read_tree_dir(&self, dir: &Path) -> Option<Vec<TreeEntry>> {
+        let entries = fs::read_dir(dir).ok()?;
+        Some(
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    let name = e.file_name();
+                    let name = name.to_string_lossy();
+                    !name.starts_with('.') && !self.config.exclude_dirs.iter().any(|d| *d == name)
+                })
+                .map(|e| TreeEntry {
+                    is_dir: e.file_type().map(|t| t.is_dir()).unwrap_or(false),
+                    name: e.file_name(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Directory listings containing only the files context is gathered from.
+    fn gathered_listing(&self) -> HashMap<PathBuf, Vec<TreeEntry>> {
+        let mut listing: HashMap<PathBuf, Vec<TreeEntry>> = HashMap::new();
+
+        for path in self.candidate_paths() {
+            let Ok(relative) = path.strip_prefix(&self.root_path) else {
+                continue;
+            };
+            let mut dir = self.root_path.clone();
+            let components: Vec<_> = relative.components().collect();
+            for (i, component) in components.iter().enumerate() {
+                let name = component.as_os_str().to_os_string();
+                let entries = listing.entry(dir.clone()).or_default();
+                if !entries.iter().any(|e| e.name == name) {
+                    entries.push(TreeEntry {
+                        is_dir: i + 1 < components.len(),
+                        name: name.clone(),
+                    });
+                }
+                dir.push(name);
+            }
+        }
+
+        listing
+    }
+
+    /// Appends the tree below `dir`, listing each directory with `list`.
+    fn render_tree(
         &self,
-        path: &Path,
+        dir: &Path,
         prefix: &str,
+        depth: usize,
         tree: &mut String,
-    ) -> Result<(), ContextError> {
-        let exclude_dirs = &self.config.exclude_dirs;
-
-        let entries: Vec<_> = fs::read_dir(path)
-            .map_err(|e| ContextError::IoError(path.to_path_buf(), e.to_string()))?
-            .filter_map(|e| e.ok())
-            .filter(|e| {
-                let name = e.file_name().to_string_lossy().to_string();
-                !name.starts_with('.') && !exclude_dirs.contains(&name)
-            })
-            .collect();
-
-        let mut sorted_entries = entries;
-        sorted_entries.sort_by_key(|e| {
-            let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
-            (!is_dir, e.file_name())
+        list: &dyn Fn(&Path) -> Option<Vec<TreeEntry>>,
+    ) {
+        let Some(mut entries) = list(dir) else {
+            tree.push_str(prefix);
+            tree.push_str("└── [unreadable]\n");
+            return;
+        };
+
+        // Directories first, then case-insensitive name order
+        entries.sort_by_cached_key(|e| {
+            let name = display_name(&e.name);
+            (!e.is_dir, name.to_lowercase(), name)
         });
 
-        for (i, entry) in sorted_entries.iter().enumerate() {
-            let is_last = i == sorted_entries.len() - 1;
+        let shown = entries.len().min(self.config.tree_max_entries);
+        let hidden = entries.len() - shown;
+
+        for (i, entry) in entries.iter().take(shown).enumerate() {
+            let is_last = i + 1 == shown && hidden == 0;
             let connector = if is_last { "└── " } else { "├── " };
-            let name = entry.file_name().to_string_lossy().to_string();
 
             tree.push_str(prefix);
             tree.push_str(connector);
-            tree.push_str(&name);
+            tree.push_str(&display_name(&entry.name));
 
-            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                tree.push('/');
+            if !entry.is_dir {
+                tree.push('\n');
+                continue;
+            }
+            tree.push('/');
+            if depth >= self.config.tree_max_depth {
+                tree.push_str(" …\n");
+                continue;
             }
             tree.push('\n');
 
-            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                let new_prefix = if is_last {
-                    format!("{}    ", prefix)
-                } else {
-                    format!("{}│   ", prefix)
-                };
-                self.build_tree_recursive(&entry.path(), &new_prefix, tree)?;
-            }
+            let new_prefix = if is_last {
+                format!("{}    ", prefix)
+            } else {
+                format!("{}│   ", prefix)
+            };
+            self.render_tree(&dir.join(&entry.name), &new_prefix, depth + 1, tree, list);
         }
 
-        Ok(())
+        if hidden > 0 {
+            tree.push_str(prefix);
+            tree.push_str(&format!(
+                "└── … {} more {}\n",
+                hidden,
+                if hidden == 1 { "entry" } else { "entries" }
+            ));
+        }
     }
 
     /// Gathers relevant files from the codebase.
+    ///
+    /// Metadata checks and reads run in parallel, a batch at a time. Files are
+    /// then accepted in `file_order`, so the result and the total-size cutoff
+    /// are the same as reading them one by one, and the files left out at the
+    /// cutoff are the least important ones.
     fn gather_files(&self) -> Result<Vec<FileContent>, ContextError> {
+        let candidates = self.ordered_candidates();
+        let workers = thread::available_parallelism().map_or(1, |n| n.get());
+        let batch_size = workers * READS_PER_WORKER;
+
         let mut files = Vec::new();
         let mut total_size: u64 = 0;
 
-        let walker = WalkBuilder::new(&self.root_path)
-            .hidden(true)
-            .git_ignore(true)
-            .build();
+        for batch in candidates.chunks(batch_size) {
+            for (path, read) in batch.iter().zip(self.read_batch(batch, workers)) {
+                let Some((size, content)) = read? else {
+                    // Larger than max_file_size
+                    continue;
+                };
 
-        for entry in walker.flatten() {
-            let path = entry.path();
+                // Check total size limit
+                if total_size + size > self.config.max_total_size {
+                    return Ok(files);
+                }
 
-            // Skip directories
-            if path.is_dir() {
-                continue;
-            }
+                let relative_path = path
+                    .strip_prefix(&self.root_path)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .to_string();
 
-            // Check extension
-            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                total_size += size;
 
-            if !self
-                .config
-                .include_extensions
-                .iter()
-                .any(|e| e == extension)
-            {
-                continue;
+                files.push(FileContent {
+                    path: relative_path,
+                    content,
+                });
             }
+        }
 
-            // Check if in excluded directory
-            let path_str = path.to_string_lossy();
-            if self
-                .config
-                .exclude_dirs
-                .iter()
-                .any(|d| path_str.contains(d.as_str()))
-            {
-                continue;
-            }
+        Ok(files)
+    }
 
-            // Check file size
-            let metadata = fs::metadata(path)
-                .map_err(|e| ContextError::IoError(path.to_path_buf(), e.to_string()))?;
+    /// Candidate files in the order they are packed into context.
+    fn ordered_candidates(&self) -> Vec<PathBuf> {
+        let candidates = self.candidate_paths();
+        if self.config.file_order == FileOrder::Walk {
+            return candidates;
+        }
 
-            if metadata.len() > self.config.max_file_size {
-                continue;
-            }
+        let mut keyed: Vec<(bool, f64, u64, PathBuf)> = candidates
+            .into_iter()
+            .map(|path| {
+                let is_entry_point = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| ENTRY_POINTS.contains(&n));
+                let relative = path
+                    .strip_prefix(&self.root_path)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let priority = self.priorities.get(&relative).copied().unwrap_or(0.0);
+                let size = fs::metadata(&path).map_or(u64::MAX, |m| m.len());
+                (is_entry_point, priority, size, path)
+            })
+            .collect();
+        // Stable, so ties keep walk order
+        keyed.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.total_cmp(&a.1)).then(a.2.cmp(&b.2)));
+        keyed.into_iter().map(|(_, _, _, path)| path).collect()
+    }
 
-            // Check total size limit
-            if total_size + metadata.len() > self.config.max_total_size {
-                break;
-            }
+    /// Walks the codebase and returns files with an included extension
+    /// outside excluded directories, in walk order.
+    fn candidate_paths(&self) -> Vec<PathBuf> {
+        let walker = WalkBuilder::new(&self.root_path)
+            .hidden(true)
+            .git_ignore(true)
+            .build();
 
-            // Read file content
-            let content = fs::read_to_string(path)
-                .map_err(|e| ContextError::IoError(path.to_path_buf(), e.to_string()))?;
+        walker
+            .flatten()
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                // Skip directories
+                if path.is_dir() {
+                    return false;
+                }
+
+                // Check extension
+                let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                if !self
+                    .config
+                    .include_extensions
+                    .iter()
+                    .any(|e| e == extension)
+                {
+                    return false;
+                }
+
+                // Check if in excluded directory
+                let path_str = path.to_string_lossy();
+                !self
+                    .config
+                    .exclude_dirs
+                    .iter()
+                    .any(|d| path_str.contains(d.as_str()))
+            })
+            .collect()
+    }
 
-            let relative_path = path
-                .strip_prefix(&self.root_path)
-                .unwrap_or(path)
-                .to_string_lossy()
-                .to_string();
+    /// Reads a batch of files on up to `workers` threads.
+    ///
+    /// Results are in the same order as `paths`. Files over `max_file_size`
+    /// yield `None` without being read.
+    fn read_batch(
+        &self,
+        paths: &[PathBuf],
+        workers: usize,
+    ) -> Vec<Result<Option<(u64, String)>, ContextError>> {
+        let per_thread = paths.len().div_ceil(workers.max(1)).max(1);
+        let max_file_size = self.config.max_file_size;
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = paths
+                .chunks(per_thread)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|path| read_if_small(path, max_file_size))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("context reader thread panicked"))
+                .collect()
+        })
+    }
+}
 
-            total_size += metadata.len();
+/// A directory entry in the structure tree.
+#[derive(Debug, Clone)]
+struct TreeEntry {
+    name: OsString,
+    is_dir: bool,
+}
 
-            files.push(FileContent {
-                path: relative_path,
-                content,
-            });
+/// Displays a file name without losing information.
+///
+/// Bytes that are not valid UTF-8 are shown as `\xNN` escapes and control
+/// characters (e.g. a newline in a file name) as Rust-style escapes, so
+/// every name stays on one line and distinct names stay distinct.
+fn display_name(name: &OsStr) -> String {
+    let mut out = String::new();
+    let push_str = |out: &mut String, text: &str| {
+        for c in text.chars() {
+            if c.is_control() || c == '\\' {
+                out.extend(c.escape_default());
+            } else {
+                out.push(c);
+            }
+        }
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        for chunk in name.as_bytes().utf8_chunks() {
+            push_str(&mut out, chunk.valid());
+            for byte in chunk.invalid() {
+                out.push_str(&format!("\\x{:02x}", byte));
+            }
         }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        for unit in char::decode_utf16(name.encode_wide()) {
+            match unit {
+                Ok(c) => push_str(&mut out, c.encode_utf8(&mut [0; 4])),
+                Err(e) => out.push_str(&format!("\\u{{{:04x}}}", e.unpaired_surrogate())),
+            }
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    push_str(&mut out, &name.to_string_lossy());
 
-        Ok(files)
+    out
+}
+
+/// Reads a file unless it is larger than `max_file_size`.
+fn read_if_small(path: &Path, max_file_size: u64) -> Result<Option<(u64, String)>, ContextError> {
+    let metadata =
+        fs::metadata(path).map_err(|e| ContextError::IoError(path.to_path_buf(), e.to_string()))?;
+
+    if metadata.len() > max_file_size {
+        return Ok(None);
     }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| ContextError::IoError(path.to_path_buf(), e.to_string()))?;
+
+    Ok(Some((metadata.len(), content)))
 }
 
 /// Context gathered from a codebase.
@@ -0,0 +1,231 @@
+//! Top-level error taxonomy for Arq frontends.
+//!
+//! Every subsystem has its own error type. [`ArqError`] folds them into a
+//! single enum so the CLI can map each failure class to a distinct process
+//! exit code and print a remediation hint.
+
+use thiserror::Error;
+
+use crate::agent::{AgentError, ConformanceError};
+use crate::config::ConfigError;
+use crate::context::ContextError;
+use crate::hooks::HookError;
+use crate::knowledge::KnowledgeError;
+use crate::llm::LLMError;
+use crate::manager::ManagerError;
+use crate::research::ResearchError;
+use crate::storage::StorageError;
+
+/// Process exit codes used by the `arq` binary.
+///
+/// Scripts and CI jobs can branch on these to tell failure classes apart.
+pub mod exit_code {
+    /// Unclassified failure.
+    pub const GENERAL: i32 = 1;
+    /// Invalid arguments or a command that does not apply to the current task state.
+    pub const USAGE: i32 = 2;
+    /// Configuration could not be read or is invalid.
+    pub const CONFIG: i32 = 3;
+    /// LLM provider failure (missing key, API error, rate limit, network).
+    pub const LLM: i32 = 4;
+    /// Knowledge graph failure (not initialized, database, embeddings).
+    pub const KNOWLEDGE: i32 = 5;
+    /// Task storage failure (missing task, unreadable task files).
+    pub const STORAGE: i32 = 6;
+    /// Filesystem failure outside of task storage.
+    pub const IO: i32 = 7;
+    /// A lifecycle hook command failed or timed out.
+    pub const HOOK: i32 = 8;
+}
+
+/// Unified error type for Arq commands.
+#[derive(Debug, Error)]
+pub enum ArqError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error(transparent)]
+    LLM(#[from] LLMError),
+
+    #[error(transparent)]
+    Knowledge(#[from] KnowledgeError),
+
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error(transparent)]
+    Manager(#[from] ManagerError),
+
+    #[error(transparent)]
+    Research(#[from] ResearchError),
+
+    #[error(transparent)]
+    Context(#[from] ContextError),
+
+    #[error(transparent)]
+    Conformance(#[from] ConformanceError),
+
+    #[error(transparent)]
+    Agent(#[from] AgentError),
+
+    #[error(transparent)]
+    Hook(#[from] HookError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The command cannot run in the current state (no task, wrong phase, ...).
+    #[error("{0}")]
+    Usage(String),
+
+    /// Any other failure.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl ArqError {
+    /// Creates an error for a command that cannot run in the current state
+    /// or with the given arguments.
+    pub fn usage(message: impl Into<String>) -> Self {
+        ArqError::Usage(message.into())
+    }
+
+    /// Creates an error for a failure that fits no other category.
+    pub fn other(message: impl Into<String>) -> Self {
+        ArqError::Other(message.into())
+    }
+
+    /// Returns the process exit code for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ArqError::Config(_) => exit_code::CONFIG,
+            ArqError::LLM(_) => exit_code::LLM,
+            ArqError::Knowledge(_) => exit_code::KNOWLEDGE,
+            ArqError::Storage(_) => exit_code::STORAGE,
+            ArqError::Manager(ManagerError::Storage(_)) => exit_code::STORAGE,
+            ArqError::Manager(_) => exit_code::USAGE,
+            ArqError::Research(ResearchError::LLM(_)) => exit_code::LLM,
+            ArqError::Research(ResearchError::Knowledge(_)) => exit_code::KNOWLEDGE,
+            ArqError::Research(ResearchError::Context(_)) => exit_code::IO,
+            ArqError::Research(ResearchError::ParseError(_)) => exit_code::LLM,
+            ArqError::Research(ResearchError::CostDeclined { .. }) => exit_code::USAGE,
+            ArqError::Context(_) => exit_code::IO,
+            ArqError::Conformance(ConformanceError::Knowledge(_)) => exit_code::KNOWLEDGE,
+            ArqError::Conformance(_) => exit_code::LLM,
+            ArqError::Agent(AgentError::LLM(_) | AgentError::EmptyResponse) => exit_code::LLM,
+            ArqError::Agent(AgentError::Io { .. }) => exit_code::IO,
+            ArqError::Agent(_) => exit_code::USAGE,
+            ArqError::Hook(_) => exit_code::HOOK,
+            ArqError::Io(_) => exit_code::IO,
+            ArqError::Usage(_) => exit_code::USAGE,
+            ArqError::Other(_) => exit_code::GENERAL,
+        }
+    }
+
+    /// Returns a user-facing hint describing how to fix the problem, if one applies.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ArqError::Config(ConfigError::ParseError(_)) => {
+                Some("Check the syntax of arq.toml (or ~/.config/arq/config.toml).")
+            }
+            ArqError::Config(_) => {
+                Some("Check that arq.toml is readable and its values are valid.")
+            }
+            ArqError::LLM(e)
+            | ArqError::Research(ResearchError::LLM(e))
+            | ArqError::Conformance(ConformanceError::LLM(e))
+            | ArqError::Agent(AgentError::LLM(e)) => llm_hint(e),
+            ArqError::Research(ResearchError::ParseError(_))
+            | ArqError::Conformance(ConformanceError::ParseError(_))
+            | ArqError::Agent(AgentError::EmptyResponse) => Some(
+                "The model returned malformed output. Retry, or pick a stronger model in [llm].",
+            ),
+            ArqError::Research(ResearchError::CostDeclined { .. }) => Some(
+                "Shrink the context with 'arq research --review-context' or [context] limits, or raise research.confirm_cost_above.",
+            ),
+            ArqError::Agent(AgentError::InvalidPath(_)) => {
+                Some("Revise the plan so it only names files inside the project.")
+            }
+            ArqError::Agent(AgentError::PrivatePath(_)) => Some(
+                "Make this change by hand, or remove the file from context.private_paths.",
+            ),
+            ArqError::Knowledge(e)
+            | ArqError::Research(ResearchError::Knowledge(e))
+            | ArqError::Conformance(ConformanceError::Knowledge(e)) => knowledge_hint(e),
+            ArqError::Storage(e) | ArqError::Manager(ManagerError::Storage(e)) => storage_hint(e),
+            ArqError::Hook(HookError::Spawn { .. }) => {
+                Some("Check the command configured in the [hooks] section of arq.toml.")
+            }
+            ArqError::Hook(_) => {
+                Some("A pre-phase hook rejected the task. Fix the reported problem or adjust [hooks] in arq.toml.")
+            }
+            ArqError::Manager(ManagerError::CannotAdvance { .. }) => {
+                Some("Run 'arq status' to see what the current phase still needs.")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Plain messages are unclassified failures; usage errors are built with
+/// [`ArqError::usage`].
+impl From<String> for ArqError {
+    fn from(message: String) -> Self {
+        ArqError::Other(message)
+    }
+}
+
+impl From<&str> for ArqError {
+    fn from(message: &str) -> Self {
+        ArqError::Other(message.to_string())
+    }
+}
+
+fn llm_hint(error: &LLMError) -> Option<&'static str> {
+    match error {
+        LLMError::MissingApiKey | LLMError::MissingConfig(_) => {
+            Some("Configure [llm] in arq.toml or set OPENAI_API_KEY or ANTHROPIC_API_KEY.")
+        }
+        LLMError::RateLimited => Some("The provider is rate limiting requests. Wait and retry."),
+        LLMError::Network(_) | LLMError::RequestFailed(_) => {
+            Some("Check your network connection and the [llm] base_url setting.")
+        }
+        LLMError::ApiError { status: 401, .. } | LLMError::ApiError { status: 403, .. } => {
+            Some("The provider rejected the API key. Check that it is valid.")
+        }
+        LLMError::UnknownProvider(_) => Some("Supported providers: openai, anthropic, ollama."),
+        _ => None,
+    }
+}
+
+fn knowledge_hint(error: &KnowledgeError) -> Option<&'static str> {
+    match error {
+        KnowledgeError::NotInitialized => Some("Run 'arq init' to index your codebase."),
+        KnowledgeError::Corrupted(_) => Some(
+            "Run 'arq kg verify --fix' to prune dangling records, or 'arq init --force' to rebuild.",
+        ),
+        KnowledgeError::Database(_) => {
+            Some("Run 'arq init --force' to rebuild the knowledge graph.")
+        }
+        KnowledgeError::Embedding(_) => {
+            Some("The embedding model could not be loaded. Check ~/.arq/cache and retry.")
+        }
+        _ => None,
+    }
+}
+
+fn storage_hint(error: &StorageError) -> Option<&'static str> {
+    match error {
+        StorageError::TaskNotFound(_) => Some("Run 'arq list' to see available tasks."),
+        StorageError::Json(_) | StorageError::Yaml(_) => {
+            Some("A task file is corrupted. Delete the task with 'arq delete <id>'.")
+        }
+        StorageError::Locked { .. } => Some(
+            "Another arq process is writing to this data directory. Retry once it finishes.",
+        ),
+        StorageError::NotOwner { .. } => Some(
+            "The data directory is shared. Set [storage] shared = true or ARQ_SHARED_DATA_DIR=1 to keep data per user.",
+        ),
+        _ => None,
+    }
+}
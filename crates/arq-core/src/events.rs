@@ -0,0 +1,243 @@
+//! Machine-readable progress events for automation.
+//!
+//! With `--events ndjson`, long-running commands write one JSON object per
+//! line to stdout, so IDE integrations can render progress without scraping
+//! the human-readable output (which moves to stderr). Every event has an
+//! `event` field naming its kind:
+//!
+//! ```text
+//! {"event":"started","command":"init"}
+//! {"event":"file","file":"src/lib.rs","done":0,"total":42}
+//! {"event":"token","text":"The task"}
+//! {"event":"complete","counts":{"files":42}}
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::knowledge::{IndexFailure, IndexProgress};
+use crate::llm::TokenUsage;
+use crate::research::{PromptStats, ResearchProgress};
+
+/// A format progress events can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventFormat {
+    /// Newline-delimited JSON, one event per line
+    Ndjson,
+}
+
+impl EventFormat {
+    /// Name used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl fmt::Display for EventFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EventFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            other => Err(format!(
+                "unknown event format '{}' (expected ndjson)",
+                other
+            )),
+        }
+    }
+}
+
+/// A progress event of a long-running command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// The command started
+    Started { command: String },
+    /// The command moved on to a new phase, e.g. `indexing` or `calling_llm`
+    Phase { phase: String },
+    /// A file is being processed; `done` files are finished
+    File {
+        file: String,
+        done: usize,
+        total: usize,
+    },
+    /// A file could not be indexed
+    FileFailed(IndexFailure),
+    /// Something was counted, e.g. knowledge graph results
+    Count { name: String, count: usize },
+    /// Part of the LLM response as it streams in
+    Token { text: String },
+    /// A section of the research document streamed in completely, e.g.
+    /// `summary` or `findings` (once per finding)
+    Section { section: String },
+    /// Estimated tokens used by an LLM call
+    Usage(TokenUsage),
+    /// Estimated composition of an LLM prompt
+    PromptStats(PromptStats),
+    /// The command finished
+    Complete {
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        counts: BTreeMap<String, usize>,
+        /// File the command wrote its result to, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    /// The command failed
+    Error { message: String },
+}
+
+impl ProgressEvent {
+    /// A phase change.
+    pub fn phase(phase: impl Into<String>) -> Self {
+        Self::Phase {
+            phase: phase.into(),
+        }
+    }
+
+    /// A count of `name`.
+    pub fn count(name: impl Into<String>, count: usize) -> Self {
+        Self::Count {
+            name: name.into(),
+            count,
+        }
+    }
+}
+
+impl From<IndexProgress> for ProgressEvent {
+    fn from(progress: IndexProgress) -> Self {
+        Self::File {
+            file: progress.current_file,
+            done: progress.files_done,
+            total: progress.files_total,
+        }
+    }
+}
+
+impl From<&ResearchProgress> for ProgressEvent {
+    fn from(progress: &ResearchProgress) -> Self {
+        match progress {
+            ResearchProgress::Started => Self::phase("started"),
+            ResearchProgress::GatheringContext => Self::phase("gathering_context"),
+            ResearchProgress::SearchingKnowledgeGraph => Self::phase("searching_knowledge_graph"),
+            ResearchProgress::KnowledgeGraphResults { count } => {
+                Self::count("knowledge_graph_results", *count)
+            }
+            ResearchProgress::ContextFromCache { count } => {
+                Self::count("cached_context_sources", *count)
+            }
+            ResearchProgress::PrivateContextWithheld { files } => {
+                Self::count("private_files_withheld", files.len())
+            }
+            ResearchProgress::CallingLLM => Self::phase("calling_llm"),
+            ResearchProgress::PromptStats(stats) => Self::PromptStats(stats.clone()),
+            ResearchProgress::ConfirmCost(_) => Self::phase("confirming_cost"),
+            ResearchProgress::TokenUsage(usage) => Self::Usage(*usage),
+            ResearchProgress::Section(section) => Self::Section {
+                section: section.name().to_string(),
+            },
+            ResearchProgress::ParsingResponse => Self::phase("parsing_response"),
+            ResearchProgress::SelfCritique => Self::phase("self_critique"),
+            ResearchProgress::FollowUpQuestions => Self::phase("follow_up_questions"),
+            ResearchProgress::Complete => Self::phase("complete"),
+            ResearchProgress::Error(message) => Self::Error {
+                message: message.clone(),
+            },
+        }
+    }
+}
+
+/// Writes progress events to stdout in a given format.
+#[derive(Debug, Clone, Copy)]
+pub struct EventWriter {
+    format: EventFormat,
+}
+
+impl EventWriter {
+    pub fn new(format: EventFormat) -> Self {
+        Self { format }
+    }
+
+    /// Write one event, flushed so readers see it immediately.
+    ///
+    /// Events are best effort: a closed stdout doesn't fail the command.
+    pub fn emit(&self, event: &ProgressEvent) {
+        let _ = self.write(&mut std::io::stdout().lock(), event);
+    }
+
+    /// Write one event to `out`.
+    pub fn write(&self, out: &mut impl Write, event: &ProgressEvent) -> std::io::Result<()> {
+        match self.format {
+            EventFormat::Ndjson => {
+                serde_json::to_writer(&mut *out, event)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ndjson_lines() {
+        let writer = EventWriter::new(EventFormat::Ndjson);
+        let mut out = Vec::new();
+        writer
+            .write(
+                &mut out,
+                &ProgressEvent::Started {
+                    command: "init".to_string(),
+                },
+            )
+            .unwrap();
+        writer
+            .write(
+                &mut out,
+                &ProgressEvent::from(&ResearchProgress::TokenUsage(TokenUsage {
+                    input_tokens: 10,
+                    output_tokens: 2,
+                })),
+            )
+            .unwrap();
+        writer
+            .write(
+                &mut out,
+                &ProgressEvent::Complete {
+                    counts: BTreeMap::from([("files".to_string(), 3)]),
+                    output: None,
+                },
+            )
+            .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"event":"started","command":"init"}"#,
+                r#"{"event":"usage","input_tokens":10,"output_tokens":2}"#,
+                r#"{"event":"complete","counts":{"files":3}}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("NDJSON".parse::<EventFormat>(), Ok(EventFormat::Ndjson));
+        assert!("xml".parse::<EventFormat>().is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Lifecycle hooks that run shell commands around phases.
+//!
+//! Hooks are configured in `arq.toml`:
+//!
+//! ```toml
+//! [hooks]
+//! pre_research = "./scripts/check-clean-tree.sh"
+//! post_plan = "notify-send \"Plan ready: $ARQ_TASK_NAME\""
+//! ```
+//!
+//! Commands run through the shell in the current directory, with task
+//! metadata passed in `ARQ_*` environment variables. A failing `pre_*` hook
+//! aborts the phase; callers decide how to report failing `post_*` hooks.
+
+use std::fmt;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::process::Command;
+
+use crate::config::HooksConfig;
+use crate::task::Task;
+
+/// A point in the task lifecycle where a hook can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// Before a research run starts
+    PreResearch,
+    /// After research is saved to the task
+    PostResearch,
+    /// Before planning starts
+    PrePlan,
+    /// After the plan is approved
+    PostPlan,
+    /// After each agent execution step
+    PostAgentStep,
+}
+
+impl HookEvent {
+    /// Config key for this hook (e.g. `pre_research`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PreResearch => "pre_research",
+            HookEvent::PostResearch => "post_research",
+            HookEvent::PrePlan => "pre_plan",
+            HookEvent::PostPlan => "post_plan",
+            HookEvent::PostAgentStep => "post_agent_step",
+        }
+    }
+
+    /// Whether a failure of this hook should abort the phase.
+    pub fn is_blocking(&self) -> bool {
+        matches!(self, HookEvent::PreResearch | HookEvent::PrePlan)
+    }
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Errors from running a hook command.
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("Failed to start {event} hook: {source}")]
+    Spawn {
+        event: HookEvent,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{event} hook exited with status {status}{}", stderr_suffix(.stderr))]
+    Failed {
+        event: HookEvent,
+        status: i32,
+        stderr: String,
+    },
+
+    #[error("{event} hook timed out after {}s", .timeout.as_secs())]
+    Timeout { event: HookEvent, timeout: Duration },
+}
+
+impl HooksConfig {
+    /// Command configured for an event, if any.
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        let command = match event {
+            HookEvent::PreResearch => &self.pre_research,
+            HookEvent::PostResearch => &self.post_research,
+            HookEvent::PrePlan => &self.pre_plan,
+            HookEvent::PostPlan => &self.post_plan,
+            HookEvent::PostAgentStep => &self.post_agent_step,
+        };
+        command.as_deref().filter(|c| !c.trim().is_empty())
+    }
+}
+
+/// Runs configured hook commands.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    config: HooksConfig,
+}
+
+impl Hooks {
+    /// Creates a hook runner from configuration.
+    pub fn new(config: HooksConfig) -> Self {
+        Self { config }
+    }
+
+    /// Whether a command is configured for an event.
+    pub fn is_configured(&self, event: HookEvent) -> bool {
+        self.config.command(event).is_some()
+    }
+
+    /// Run the hook for `event`, if configured.
+    ///
+    /// `extra_env` adds event-specific variables (e.g. the research doc path).
+    /// Returns `Ok(false)` when no hook is configured.
+    pub async fn run(
+        &self,
+        event: HookEvent,
+        task: &Task,
+        extra_env: &[(&str, String)],
+    ) -> Result<bool, HookError> {
+        let Some(command) = self.config.command(event) else {
+            return Ok(false);
+        };
+
+        let mut cmd = shell_command(command);
+        cmd.envs(hook_env(event, task))
+            .envs(extra_env.iter().map(|(k, v)| (*k, v.as_str())))
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true);
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let output = tokio::time::timeout(timeout, cmd.output())
+            .await
+            .map_err(|_| HookError::Timeout { event, timeout })?
+            .map_err(|source| HookError::Spawn { event, source })?;
+
+        if output.status.success() {
+            Ok(true)
+        } else {
+            Err(HookError::Failed {
+                event,
+                status: output.status.code().unwrap_or(-1),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+}
+
+/// Environment variables describing the task, passed to every hook.
+pub fn hook_env(event: HookEvent, task: &Task) -> Vec<(&'static str, String)> {
+    vec![
+        ("ARQ_HOOK", event.as_str().to_string()),
+        ("ARQ_TASK_ID", task.id.clone()),
+        ("ARQ_TASK_NAME", task.name.clone()),
+        ("ARQ_TASK_PROMPT", task.prompt.clone()),
+        ("ARQ_TASK_PHASE", task.phase.display_name().to_string()),
+    ]
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(not(unix))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+fn stderr_suffix(stderr: &str) -> String {
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", stderr)
+    }
+}
@@ -0,0 +1,89 @@
+//! Path blocklist for knowledge graph data served to others.
+//!
+//! When `arq serve` is shared with a team, `[serve] blocked_paths` keeps
+//! entities and chunks under sensitive paths (`secrets/`,
+//! `internal/billing/`) out of every API response. The server checks each
+//! node, edge, search result and grep hit against the [`PathBlocklist`]
+//! before it leaves the handler; the index itself is unchanged.
+//!
+//! Research uses one for `context.private_paths` as well: code there is
+//! indexed and searchable locally, but never sent to the LLM.
+
+use std::collections::HashSet;
+
+use super::db::split_entity_id;
+use super::indexer::matches_pattern;
+use super::ontology::nodes::FunctionEntity;
+
+/// Path patterns whose entities must not be shown, in the syntax of
+/// `knowledge.generated_patterns` (`secrets/`, `*.pem`, `internal/billing/`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathBlocklist {
+    patterns: Vec<String>,
+}
+
+impl PathBlocklist {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+
+    /// The patterns, as configured.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether no path is blocked.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether `path`, relative to the project root, is blocked.
+    pub fn blocks(&self, path: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path))
+    }
+
+    /// Whether the file of an entity ID (`kind:path:name`) is blocked.
+    /// Unresolved references carry no path and are never blocked.
+    pub fn blocks_entity(&self, id: &str) -> bool {
+        split_entity_id(id).is_some_and(|(path, _)| self.blocks(path))
+    }
+
+    /// Names of the functions defined only in blocked files, for filtering
+    /// results that refer to functions by name (such as callers).
+    pub fn blocked_names<'a>(&self, functions: &'a [FunctionEntity]) -> HashSet<&'a str> {
+        let (blocked, visible): (Vec<_>, Vec<_>) = functions
+            .iter()
+            .partition(|function| self.blocks(&function.file_path));
+        let visible: HashSet<&str> = visible.iter().map(|f| f.name.as_str()).collect();
+        blocked
+            .iter()
+            .map(|f| f.name.as_str())
+            .filter(|name| !visible.contains(name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_blocklist() {
+        let blocklist = PathBlocklist::new(vec![
+            "secrets/".to_string(),
+            "internal/billing/".to_string(),
+        ]);
+        assert!(blocklist.blocks("secrets/keys.rs"));
+        assert!(blocklist.blocks("config/secrets/prod.rs"));
+        assert!(blocklist.blocks("internal/billing/invoice.rs"));
+        assert!(!blocklist.blocks("internal/auth.rs"));
+        assert!(!blocklist.blocks("src/secrets.rs"));
+
+        assert!(blocklist.blocks_entity("function:secrets/keys.rs:load"));
+        assert!(!blocklist.blocks_entity("function:src/lib.rs:load"));
+        assert!(!blocklist.blocks_entity("function:?:load"));
+        assert!(PathBlocklist::default().is_empty());
+    }
+}
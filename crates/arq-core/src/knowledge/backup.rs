@@ -0,0 +1,342 @@
+//! Backup and restore of the knowledge graph database.
+//!
+//! Indexing a large monorepo can take hours, so the RocksDB directory is
+//! worth saving before risky operations or handing to teammates.
+//!
+//! A backup or restore first takes a lock file next to the database, which
+//! [`KnowledgeGraph`](super::KnowledgeGraph) checks before opening it. It
+//! then opens the database, which fails while another process (`arq serve`,
+//! the TUI, an indexing run) already holds it, and closes it again: the
+//! directory is only archived once no handle is open, so RocksDB can't flush
+//! or compact while `tar` reads it.
+//!
+//! Archives are written with the system `tar`, compressed according to the
+//! file suffix (`.tar.zst`, `.tar.gz`, ...); a suffix whose compressor isn't
+//! installed fails before anything is written. A restore extracts next to
+//! the database and only swaps the directories once extraction succeeded.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::storage::{StorageError, StorageLock};
+
+use super::db::KnowledgeDb;
+use super::error::KnowledgeError;
+
+/// File RocksDB keeps in every database directory.
+const ROCKSDB_MARKER: &str = "CURRENT";
+
+/// Outcome of a backup or restore.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupSummary {
+    /// The archive written or read.
+    pub archive: PathBuf,
+    /// Size of the archive in bytes.
+    pub bytes: u64,
+    /// Files in the database directory.
+    pub files: usize,
+}
+
+/// How often the lock file is refreshed while `tar` runs, well within
+/// [`DEFAULT_LOCK_STALE_SECS`](crate::config::DEFAULT_LOCK_STALE_SECS).
+const LOCK_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Compressors `tar -a` runs for archive suffixes, other than gzip.
+const COMPRESSORS: &[(&str, &str)] = &[
+    (".zst", "zstd"),
+    (".tzst", "zstd"),
+    (".xz", "xz"),
+    (".txz", "xz"),
+    (".bz2", "bzip2"),
+    (".tbz2", "bzip2"),
+];
+
+/// Archive the database at `db_path` into `archive`.
+pub async fn backup(db_path: &Path, archive: &Path) -> Result<BackupSummary, KnowledgeError> {
+    if !db_path.exists() {
+        return Err(KnowledgeError::NotInitialized);
+    }
+    check_compressor(archive)?;
+    // Held until the archive is written, keeping other processes out
+    let lock = lock(db_path)?;
+    drop(open_exclusive(db_path).await?);
+
+    if let Some(parent) = archive.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|source| KnowledgeError::Io {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+    let mut tar = Command::new("tar");
+    tar.arg("-caf").arg(archive).arg("-C").arg(db_path).arg(".");
+    run_tar_locked(tar, &lock).await?;
+
+    summary(archive, db_path)
+}
+
+/// Replace the database at `db_path` with the contents of `archive`.
+///
+/// An existing database is only replaced with `force`, and never while it is
+/// in use.
+pub async fn restore(
+    archive: &Path,
+    db_path: &Path,
+    force: bool,
+) -> Result<BackupSummary, KnowledgeError> {
+    if !archive.is_file() {
+        return Err(KnowledgeError::Backup(format!(
+            "no archive at {}",
+            archive.display()
+        )));
+    }
+    if db_path.exists() && !force {
+        return Err(KnowledgeError::Backup(format!(
+            "a knowledge graph already exists at {}; pass --force to replace it",
+            db_path.display()
+        )));
+    }
+    let lock = lock(db_path)?;
+    if db_path.exists() {
+        drop(open_exclusive(db_path).await?);
+    }
+
+    let staging = sibling(db_path, "restore");
+    remove_dir(&staging)?;
+    std::fs::create_dir_all(&staging).map_err(|source| KnowledgeError::Io {
+        path: staging.clone(),
+        source,
+    })?;
+    let mut tar = Command::new("tar");
+    tar.arg("-xf").arg(archive).arg("-C").arg(&staging);
+    if let Err(e) = run_tar_locked(tar, &lock).await {
+        let _ = remove_dir(&staging);
+        return Err(e);
+    }
+    if !staging.join(ROCKSDB_MARKER).exists() {
+        let _ = remove_dir(&staging);
+        return Err(KnowledgeError::Backup(format!(
+            "{} is not a knowledge graph backup",
+            archive.display()
+        )));
+    }
+
+    let previous = sibling(db_path, "old");
+    remove_dir(&previous)?;
+    if db_path.exists() {
+        rename(db_path, &previous)?;
+    }
+    rename(&staging, db_path)?;
+    remove_dir(&previous)?;
+
+    summary(archive, db_path)
+}
+
+/// Fail if a backup or restore of the database at `db_path` is running.
+pub(crate) fn check_not_locked(db_path: &Path) -> Result<(), KnowledgeError> {
+    if StorageLock::is_held(&lock_path(db_path)) {
+        return Err(KnowledgeError::Backup(format!(
+            "{} is being backed up or restored; try again once that finishes",
+            db_path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Take the lock file of the database at `db_path`.
+fn lock(db_path: &Path) -> Result<StorageLock, KnowledgeError> {
+    StorageLock::acquire(lock_path(db_path), Duration::ZERO).map_err(|e| match e {
+        StorageError::Locked { holder, .. } => KnowledgeError::Backup(format!(
+            "another backup or restore of {} is running ({})",
+            db_path.display(),
+            holder
+        )),
+        e => KnowledgeError::Backup(e.to_string()),
+    })
+}
+
+fn lock_path(db_path: &Path) -> PathBuf {
+    sibling(db_path, "backup-lock")
+}
+
+/// Fail if compressing to `archive` needs a program that isn't installed.
+///
+/// GNU tar runs an external compressor for the suffix; bsdtar compresses
+/// in-process and needs none.
+fn check_compressor(archive: &Path) -> Result<(), KnowledgeError> {
+    let name = archive.to_string_lossy();
+    let Some((suffix, program)) = COMPRESSORS
+        .iter()
+        .find(|(suffix, _)| name.ends_with(suffix))
+    else {
+        return Ok(());
+    };
+    let gnu_tar = Command::new("tar")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("GNU tar"));
+    let installed = Command::new(program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if gnu_tar && !installed {
+        return Err(KnowledgeError::Backup(format!(
+            "{} archives need the `{}` command, which was not found; install it or \
+             use a .tar.gz archive",
+            suffix, program
+        )));
+    }
+    Ok(())
+}
+
+/// Open the database, reporting a held lock as the database being in use.
+async fn open_exclusive(db_path: &Path) -> Result<KnowledgeDb, KnowledgeError> {
+    KnowledgeDb::open(db_path).await.map_err(|e| match e {
+        KnowledgeError::Database(message) if is_lock_error(&message) => {
+            KnowledgeError::Backup(format!(
+                "{} is in use by another process; stop `arq serve`, the TUI or \
+                 indexing and try again",
+                db_path.display()
+            ))
+        }
+        e => e,
+    })
+}
+
+/// Whether a database error comes from RocksDB's lock file being held.
+fn is_lock_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("lock") && (message.contains("hold") || message.contains("unavailable"))
+}
+
+/// A directory next to `path`, named after it with `suffix` appended.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn run_tar(command: &mut Command) -> Result<(), KnowledgeError> {
+    let output = command
+        .output()
+        .map_err(|e| KnowledgeError::Backup(format!("failed to run tar: {}", e)))?;
+    if !output.status.success() {
+        return Err(KnowledgeError::Backup(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Run `tar`, refreshing `lock` until it exits: archiving a large database
+/// outlasts the age at which an untouched lock is considered abandoned.
+async fn run_tar_locked(mut command: Command, lock: &StorageLock) -> Result<(), KnowledgeError> {
+    let mut tar = tokio::task::spawn_blocking(move || run_tar(&mut command));
+    let mut refresh = tokio::time::interval(LOCK_REFRESH_INTERVAL);
+    loop {
+        tokio::select! {
+            result = &mut tar => {
+                return result.map_err(|e| KnowledgeError::Backup(format!("tar did not finish: {}", e)))?;
+            }
+            _ = refresh.tick() => lock.refresh(),
+        }
+    }
+}
+
+fn rename(from: &Path, to: &Path) -> Result<(), KnowledgeError> {
+    std::fs::rename(from, to).map_err(|source| KnowledgeError::Io {
+        path: from.to_path_buf(),
+        source,
+    })
+}
+
+fn remove_dir(path: &Path) -> Result<(), KnowledgeError> {
+    if !path.exists() {
+        return Ok(());
+    }
+    std::fs::remove_dir_all(path).map_err(|source| KnowledgeError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn summary(archive: &Path, db_path: &Path) -> Result<BackupSummary, KnowledgeError> {
+    let bytes = std::fs::metadata(archive)
+        .map_err(|source| KnowledgeError::Io {
+            path: archive.to_path_buf(),
+            source,
+        })?
+        .len();
+    Ok(BackupSummary {
+        archive: archive.to_path_buf(),
+        bytes,
+        files: count_files(db_path),
+    })
+}
+
+fn count_files(dir: &Path) -> usize {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| {
+                    let path = entry.path();
+                    if path.is_dir() {
+                        count_files(&path)
+                    } else {
+                        1
+                    }
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staging_paths_and_lock_errors() {
+        assert_eq!(
+            sibling(Path::new(".arq/knowledge.db"), "restore"),
+            PathBuf::from(".arq/knowledge.db.restore")
+        );
+        assert_eq!(
+            lock_path(Path::new(".arq/knowledge.db")),
+            PathBuf::from(".arq/knowledge.db.backup-lock")
+        );
+        assert!(is_lock_error(
+            "IO error: While lock file: knowledge.db/LOCK: Resource temporarily unavailable"
+        ));
+        assert!(is_lock_error("IO error: lock hold by current process"));
+        assert!(!is_lock_error("Corruption: bad block"));
+    }
+
+    #[test]
+    fn test_open_refused_while_locked() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("knowledge.db");
+        assert!(check_not_locked(&db_path).is_ok());
+
+        let held = lock(&db_path).unwrap();
+        assert!(matches!(
+            check_not_locked(&db_path),
+            Err(KnowledgeError::Backup(_))
+        ));
+        assert!(matches!(lock(&db_path), Err(KnowledgeError::Backup(_))));
+
+        drop(held);
+        assert!(check_not_locked(&db_path).is_ok());
+    }
+
+    #[test]
+    fn test_gzip_needs_no_compressor() {
+        assert!(check_compressor(Path::new("kg.tar.gz")).is_ok());
+        assert!(check_compressor(Path::new("kg.tar")).is_ok());
+    }
+}
@@ -0,0 +1,384 @@
+//! Centrality analytics over the calls graph.
+//!
+//! Ranks functions by how many call them (in-degree), how many they call
+//! (out-degree) or how many shortest call paths pass through them
+//! (betweenness), to surface god-functions and hotspots.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use super::db::{split_entity_id, CallInfo};
+use super::ontology::CallResolution;
+
+/// Graphs with at most this many functions get exact betweenness.
+const BETWEENNESS_EXACT_NODES: usize = 500;
+
+/// Source functions sampled for approximate betweenness on larger graphs.
+const BETWEENNESS_SAMPLES: usize = 256;
+
+/// A centrality metric over the calls graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CentralityMetric {
+    /// Number of distinct callers
+    InDegree,
+    /// Number of distinct callees
+    OutDegree,
+    /// Share of shortest call paths passing through a function
+    Betweenness,
+}
+
+impl CentralityMetric {
+    /// All metrics, in display order.
+    pub const ALL: [CentralityMetric; 3] = [Self::InDegree, Self::OutDegree, Self::Betweenness];
+
+    /// Name used on the command line and in the metadata cache.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InDegree => "in-degree",
+            Self::OutDegree => "out-degree",
+            Self::Betweenness => "betweenness",
+        }
+    }
+
+    /// Metadata key under which computed scores are cached.
+    pub(crate) fn cache_key(&self) -> String {
+        format!("centrality:{}", self.as_str())
+    }
+}
+
+impl fmt::Display for CentralityMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CentralityMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "in-degree" | "in" | "fan-in" => Ok(Self::InDegree),
+            "out-degree" | "out" | "fan-out" => Ok(Self::OutDegree),
+            "betweenness" => Ok(Self::Betweenness),
+            other => Err(format!(
+                "unknown metric '{}' (expected in-degree, out-degree or betweenness)",
+                other
+            )),
+        }
+    }
+}
+
+/// Centrality score of one function.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CentralityScore {
+    /// Entity ID of the function
+    pub entity_id: String,
+    /// Function name
+    pub name: String,
+    /// Score under the computed metric
+    pub score: f64,
+}
+
+/// Scores cached in the metadata table, valid while the calls graph is unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedCentrality {
+    pub signature: String,
+    pub scores: Vec<CentralityScore>,
+}
+
+/// Call edges that count towards centrality: those resolved to a function
+/// in the graph. Unresolved and ambiguous callees keep a `:?:` name rather
+/// than a function's ID, which also catches records written before
+/// resolution tracking.
+fn graph_edges(calls: &[CallInfo]) -> impl Iterator<Item = &CallInfo> {
+    calls.iter().filter(|c| {
+        c.resolution == CallResolution::Resolved
+            && !c.callee_id.contains(":?:")
+            && c.caller_id != c.callee_id
+    })
+}
+
+/// Fingerprint of the calls graph, used to invalidate cached scores.
+pub fn graph_signature(calls: &[CallInfo]) -> String {
+    let mut edges: Vec<(&str, &str)> = graph_edges(calls)
+        .map(|c| (c.caller_id.as_str(), c.callee_id.as_str()))
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+
+    let mut hasher = Sha256::new();
+    for (caller, callee) in edges {
+        hasher.update(caller.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(callee.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Compute a centrality metric for every function in the calls graph.
+///
+/// Scores are sorted highest first; functions scoring zero are omitted.
+pub fn compute(calls: &[CallInfo], metric: CentralityMetric) -> Vec<CentralityScore> {
+    let graph = CallGraph::build(calls);
+    let scores = match metric {
+        CentralityMetric::InDegree => graph.in_degree(),
+        CentralityMetric::OutDegree => graph.out_degree(),
+        CentralityMetric::Betweenness => graph.betweenness(),
+    };
+
+    let mut ranked: Vec<CentralityScore> = scores
+        .into_iter()
+        .enumerate()
+        .filter(|(_, score)| *score > 0.0)
+        .map(|(i, score)| CentralityScore {
+            entity_id: graph.ids[i].clone(),
+            name: graph.names[i].clone(),
+            score,
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.entity_id.cmp(&b.entity_id))
+    });
+    ranked
+}
+
+/// Sum function scores per file, keyed by path relative to the project root.
+///
+/// Unresolved functions, which have no file, are left out.
+pub fn file_scores(scores: &[CentralityScore]) -> HashMap<String, f64> {
+    let mut files: HashMap<String, f64> = HashMap::new();
+    for score in scores {
+        if let Some((path, _)) = split_entity_id(&score.entity_id) {
+            *files.entry(path.to_string()).or_default() += score.score;
+        }
+    }
+    files
+}
+
+/// Directed calls graph with deduplicated edges and dense node indices.
+struct CallGraph {
+    ids: Vec<String>,
+    names: Vec<String>,
+    /// Callees of each node
+    out_edges: Vec<Vec<usize>>,
+    /// Callers of each node
+    in_edges: Vec<Vec<usize>>,
+}
+
+impl CallGraph {
+    fn build(calls: &[CallInfo]) -> Self {
+        let mut graph = Self {
+            ids: Vec::new(),
+            names: Vec::new(),
+            out_edges: Vec::new(),
+            in_edges: Vec::new(),
+        };
+        let mut index: HashMap<String, usize> = HashMap::new();
+
+        let mut node = |graph: &mut Self, id: &str, name: &str| -> usize {
+            *index.entry(id.to_string()).or_insert_with(|| {
+                graph.ids.push(id.to_string());
+                graph.names.push(name.to_string());
+                graph.out_edges.push(Vec::new());
+                graph.in_edges.push(Vec::new());
+                graph.ids.len() - 1
+            })
+        };
+
+        for call in graph_edges(calls) {
+            let caller = node(&mut graph, &call.caller_id, &call.caller_name);
+            let callee = node(&mut graph, &call.callee_id, &call.callee_name);
+            if !graph.out_edges[caller].contains(&callee) {
+                graph.out_edges[caller].push(callee);
+                graph.in_edges[callee].push(caller);
+            }
+        }
+        graph
+    }
+
+    fn in_degree(&self) -> Vec<f64> {
+        self.in_edges.iter().map(|e| e.len() as f64).collect()
+    }
+
+    fn out_degree(&self) -> Vec<f64> {
+        self.out_edges.iter().map(|e| e.len() as f64).collect()
+    }
+
+    /// Betweenness by Brandes' algorithm, from every node on small graphs
+    /// and from an evenly spaced sample of sources (scaled up) on large ones.
+    fn betweenness(&self) -> Vec<f64> {
+        let n = self.ids.len();
+        let mut centrality = vec![0.0; n];
+        if n == 0 {
+            return centrality;
+        }
+
+        // Sample sources in a stable order so repeated runs agree
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| self.ids[a].cmp(&self.ids[b]));
+        let sources: Vec<usize> = if n <= BETWEENNESS_EXACT_NODES {
+            order
+        } else {
+            let step = n as f64 / BETWEENNESS_SAMPLES as f64;
+            (0..BETWEENNESS_SAMPLES)
+                .map(|i| order[(i as f64 * step) as usize])
+                .collect()
+        };
+        let scale = n as f64 / sources.len() as f64;
+
+        let mut sigma = vec![0.0f64; n];
+        let mut distance = vec![-1i64; n];
+        let mut delta = vec![0.0f64; n];
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+
+        for &source in &sources {
+            sigma.iter_mut().for_each(|s| *s = 0.0);
+            distance.iter_mut().for_each(|d| *d = -1);
+            delta.iter_mut().for_each(|d| *d = 0.0);
+            predecessors.iter_mut().for_each(Vec::clear);
+
+            sigma[source] = 1.0;
+            distance[source] = 0;
+            let mut visited = Vec::new();
+            let mut queue = VecDeque::from([source]);
+
+            while let Some(v) = queue.pop_front() {
+                visited.push(v);
+                for &w in &self.out_edges[v] {
+                    if distance[w] < 0 {
+                        distance[w] = distance[v] + 1;
+                        queue.push_back(w);
+                    }
+                    if distance[w] == distance[v] + 1 {
+                        sigma[w] += sigma[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            // Accumulate dependencies in order of decreasing distance
+            while let Some(w) = visited.pop() {
+                for &v in &predecessors[w] {
+                    delta[v] += sigma[v] / sigma[w] * (1.0 + delta[w]);
+                }
+                if w != source {
+                    centrality[w] += delta[w];
+                }
+            }
+        }
+
+        centrality.iter_mut().for_each(|c| *c *= scale);
+        centrality
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(caller: &str, callee: &str) -> CallInfo {
+        CallInfo {
+            caller_id: caller.to_string(),
+            callee_id: callee.to_string(),
+            caller_name: caller.to_string(),
+            callee_name: callee.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_degrees() {
+        let calls = vec![
+            call("a", "log"),
+            call("b", "log"),
+            call("c", "log"),
+            call("a", "b"),
+            call("a", "b"),
+        ];
+
+        let top = compute(&calls, CentralityMetric::InDegree);
+        assert_eq!(top[0].entity_id, "log");
+        assert_eq!(top[0].score, 3.0);
+
+        let top = compute(&calls, CentralityMetric::OutDegree);
+        assert_eq!(top[0].entity_id, "a");
+        assert_eq!(top[0].score, 2.0);
+    }
+
+    #[test]
+    fn test_file_scores() {
+        let calls = vec![
+            call("function:src/a.rs:run", "function:src/log.rs:log"),
+            call("function:src/b.rs:go", "function:src/log.rs:log"),
+            call("function:src/b.rs:go", "function:src/log.rs:flush"),
+            call("function:src/b.rs:go", "function:?:external"),
+        ];
+        let files = file_scores(&compute(&calls, CentralityMetric::InDegree));
+        assert_eq!(files.get("src/log.rs"), Some(&3.0));
+        assert_eq!(files.get("src/a.rs"), None);
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_betweenness_finds_bridge() {
+        // a -> hub -> {x, y}; b -> hub
+        let calls = vec![
+            call("a", "hub"),
+            call("b", "hub"),
+            call("hub", "x"),
+            call("hub", "y"),
+        ];
+        let top = compute(&calls, CentralityMetric::Betweenness);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].entity_id, "hub");
+        assert_eq!(top[0].score, 4.0);
+    }
+
+    #[test]
+    fn test_unresolved_calls_ignored() {
+        let mut unresolved = call("a", ":?:unwrap");
+        unresolved.resolution = CallResolution::Unresolved;
+        let calls = vec![unresolved, call("a", "b")];
+
+        let top = compute(&calls, CentralityMetric::InDegree);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].entity_id, "b");
+        assert_eq!(graph_signature(&calls), graph_signature(&[call("a", "b")]));
+    }
+
+    #[test]
+    fn test_ambiguous_and_legacy_calls_ignored() {
+        let mut ambiguous = call("a", "function:?:log");
+        ambiguous.resolution = CallResolution::Ambiguous;
+        // Records from before resolution tracking read as resolved
+        let legacy = call("a", "function:?:unwrap");
+        let calls = vec![ambiguous, legacy, call("a", "b")];
+
+        let top = compute(&calls, CentralityMetric::InDegree);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].entity_id, "b");
+        let top = compute(&calls, CentralityMetric::OutDegree);
+        assert_eq!(top[0].score, 1.0);
+        assert_eq!(graph_signature(&calls), graph_signature(&[call("a", "b")]));
+    }
+
+    #[test]
+    fn test_parse_metric() {
+        assert_eq!(
+            "in-degree".parse::<CentralityMetric>(),
+            Ok(CentralityMetric::InDegree)
+        );
+        assert_eq!(
+            "out_degree".parse::<CentralityMetric>(),
+            Ok(CentralityMetric::OutDegree)
+        );
+        assert!("pagerank".parse::<CentralityMetric>().is_err());
+    }
+}
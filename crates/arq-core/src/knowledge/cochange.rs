@@ -0,0 +1,253 @@
+//! Co-change analysis from git history.
+//!
+//! Files that keep changing in the same commits are coupled even when no
+//! call or import connects them: a handler and its template, a schema and
+//! its migration. `arq kg mine-history` reads the git log, counts how often
+//! each pair of indexed files changed together, and stores the pairs that
+//! did so at least a few times as weighted co-change edges.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::Command;
+
+use super::error::KnowledgeError;
+
+/// Commits read from the log by default.
+pub const DEFAULT_MAX_COMMITS: usize = 1000;
+
+/// Commits two files must share to count as co-changing, by default.
+pub const DEFAULT_MIN_COUNT: u32 = 3;
+
+/// Commits touching more files than this are skipped by default: bulk
+/// renames and reformatting would couple everything with everything.
+pub const DEFAULT_MAX_FILES_PER_COMMIT: usize = 50;
+
+/// Two files that changed together in past commits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoChange {
+    /// First file, relative to the project root (ordered before `file_b`).
+    pub file_a: String,
+    pub file_b: String,
+    /// Commits changing both files.
+    pub count: u32,
+    /// Commits changing `file_a`.
+    pub commits_a: u32,
+    /// Commits changing `file_b`.
+    pub commits_b: u32,
+    /// Share of the commits touching either file that touched both (0-1).
+    pub weight: f32,
+}
+
+impl CoChange {
+    /// The file paired with `path`, if this pair includes it.
+    pub fn partner_of(&self, path: &str) -> Option<&str> {
+        if self.file_a == path {
+            Some(&self.file_b)
+        } else if self.file_b == path {
+            Some(&self.file_a)
+        } else {
+            None
+        }
+    }
+
+    /// Commits changing `path`, if this pair includes it.
+    pub fn commits_of(&self, path: &str) -> Option<u32> {
+        if self.file_a == path {
+            Some(self.commits_a)
+        } else if self.file_b == path {
+            Some(self.commits_b)
+        } else {
+            None
+        }
+    }
+}
+
+/// Options for mining co-changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MineOptions {
+    /// Most recent commits to read.
+    pub max_commits: usize,
+    /// Commits two files must share.
+    pub min_count: u32,
+    /// Skip commits touching more files than this.
+    pub max_files_per_commit: usize,
+}
+
+impl Default for MineOptions {
+    fn default() -> Self {
+        Self {
+            max_commits: DEFAULT_MAX_COMMITS,
+            min_count: DEFAULT_MIN_COUNT,
+            max_files_per_commit: DEFAULT_MAX_FILES_PER_COMMIT,
+        }
+    }
+}
+
+/// Outcome of mining the history.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MineReport {
+    /// Commits read from the log.
+    pub commits: usize,
+    /// Commits skipped for touching too many files.
+    pub skipped_commits: usize,
+    /// Co-change pairs stored.
+    pub pairs: usize,
+}
+
+/// Files changed by each of the last `max_commits` non-merge commits, most
+/// recent first, with paths relative to `root`.
+///
+/// Paths are read NUL-separated, so git leaves names with non-ASCII
+/// characters, spaces or quotes as they are instead of quoting them.
+pub fn read_git_history(
+    root: &Path,
+    max_commits: usize,
+) -> Result<Vec<Vec<String>>, KnowledgeError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "log",
+            "--no-merges",
+            "--relative",
+            "--name-only",
+            "-z",
+            "--format=%x1e",
+        ])
+        .arg(format!("-n{}", max_commits))
+        .output()
+        .map_err(|e| KnowledgeError::Git(format!("failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(KnowledgeError::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(parse_git_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `git log --name-only -z --format=%x1e` output into the files of
+/// each commit.
+pub fn parse_git_log(log: &str) -> Vec<Vec<String>> {
+    log.split('\x1e')
+        .map(|commit| {
+            commit
+                .split('\0')
+                .map(|path| path.trim_matches('\n'))
+                .filter(|path| !path.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .filter(|files| !files.is_empty())
+        .collect()
+}
+
+/// Count the file pairs changing together in `commits`.
+///
+/// Only files accepted by `include` are counted, and pairs sharing fewer
+/// than `options.min_count` commits are dropped. Pairs are ordered by count,
+/// then weight, highest first.
+pub fn co_changes(
+    commits: &[Vec<String>],
+    options: &MineOptions,
+    include: impl Fn(&str) -> bool,
+) -> (Vec<CoChange>, usize) {
+    let mut changes: HashMap<&str, u32> = HashMap::new();
+    let mut together: HashMap<(&str, &str), u32> = HashMap::new();
+    let mut skipped = 0;
+
+    for commit in commits {
+        let mut files: Vec<&str> = commit
+            .iter()
+            .map(String::as_str)
+            .filter(|f| include(f))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if files.len() > options.max_files_per_commit {
+            skipped += 1;
+            continue;
+        }
+        files.sort_unstable();
+        for (i, &a) in files.iter().enumerate() {
+            *changes.entry(a).or_default() += 1;
+            for &b in &files[i + 1..] {
+                *together.entry((a, b)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<CoChange> = together
+        .into_iter()
+        .filter(|(_, count)| *count >= options.min_count)
+        .map(|((a, b), count)| {
+            let (commits_a, commits_b) = (changes[a], changes[b]);
+            CoChange {
+                file_a: a.to_string(),
+                file_b: b.to_string(),
+                count,
+                commits_a,
+                commits_b,
+                weight: count as f32 / (commits_a + commits_b - count) as f32,
+            }
+        })
+        .collect();
+    pairs.sort_by(|x, y| {
+        y.count
+            .cmp(&x.count)
+            .then(y.weight.total_cmp(&x.weight))
+            .then_with(|| (&x.file_a, &x.file_b).cmp(&(&y.file_a, &y.file_b)))
+    });
+    (pairs, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(files: &[&str]) -> Vec<String> {
+        files.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_co_changes() {
+        let log = "\x1e\0\nsrc/a.rs\0src/b.rs\0\x1e\0\nsrc/a.rs\0src/b.rs\0README.md\0\x1e\0\nsrc/a.rs\0src/c.rs\0";
+        let commits = parse_git_log(log);
+        assert_eq!(commits.len(), 3);
+        assert_eq!(commits[1], commit(&["src/a.rs", "src/b.rs", "README.md"]));
+
+        let options = MineOptions {
+            min_count: 2,
+            max_files_per_commit: 2,
+            ..MineOptions::default()
+        };
+        let mut commits = commits;
+        commits.push(commit(&["src/a.rs", "src/b.rs", "src/c.rs"]));
+        let (pairs, skipped) = co_changes(&commits, &options, |f| f.ends_with(".rs"));
+
+        // The last commit touches three files and is skipped
+        assert_eq!(skipped, 1);
+        assert_eq!(pairs.len(), 1);
+        let pair = &pairs[0];
+        assert_eq!(
+            (pair.file_a.as_str(), pair.file_b.as_str()),
+            ("src/a.rs", "src/b.rs")
+        );
+        assert_eq!((pair.count, pair.commits_a, pair.commits_b), (2, 3, 2));
+        assert_eq!(pair.weight, 2.0 / 3.0);
+        assert_eq!(pair.partner_of("src/b.rs"), Some("src/a.rs"));
+        assert_eq!(pair.commits_of("src/a.rs"), Some(3));
+    }
+
+    #[test]
+    fn test_parse_git_log_keeps_unusual_names() {
+        let log = "\x1e\0\nsrc/ünï.rs\0docs/read me.md\0\x1e\0\n\"quoted\".rs\0";
+        assert_eq!(
+            parse_git_log(log),
+            vec![
+                commit(&["src/ünï.rs", "docs/read me.md"]),
+                commit(&["\"quoted\".rs"]),
+            ]
+        );
+    }
+}
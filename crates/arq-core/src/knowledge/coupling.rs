@@ -0,0 +1,226 @@
+//! Call coupling between two parts of a codebase.
+//!
+//! `arq graph calls --between src/auth src/billing` lists every call that
+//! crosses two files or directories, in either direction, to judge how
+//! entangled they are before extracting one into its own module or crate.
+//! Functions are placed by the file path of their `fn_node`, and a `calls`
+//! edge crosses when its caller and callee are placed on different sides.
+//! Unresolved calls name no definition and are left out.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::db::CallInfo;
+use super::ontology::CallResolution;
+
+/// A file or directory, relative to the project root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathScope(String);
+
+impl PathScope {
+    /// Scope of `path`; `./` and trailing slashes are ignored and `.` is the
+    /// whole project.
+    pub fn new(path: &str) -> Self {
+        let path = path.trim().trim_start_matches("./").trim_end_matches('/');
+        Self(if path == "." {
+            String::new()
+        } else {
+            path.to_string()
+        })
+    }
+
+    /// The path, empty for the whole project.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether `file_path` is the scope's file or lies under its directory.
+    pub fn contains(&self, file_path: &str) -> bool {
+        self.0.is_empty()
+            || file_path
+                .strip_prefix(self.0.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// Which way a call crosses, relative to the first scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallDirection {
+    /// The first scope calls into the second
+    Outgoing,
+    /// The second scope calls into the first
+    Incoming,
+}
+
+/// A function at one end of a call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionRef {
+    pub name: String,
+    pub qualified_name: String,
+    pub file_path: String,
+    pub line: u32,
+}
+
+impl FunctionRef {
+    /// The entity ID `calls` edges refer to the function by.
+    pub fn entity_id(&self) -> String {
+        format!("function:{}:{}", self.file_path, self.qualified_name)
+    }
+}
+
+/// A call from one scope into the other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossingCall {
+    pub direction: CallDirection,
+    pub caller: FunctionRef,
+    pub callee: FunctionRef,
+    pub resolution: CallResolution,
+    pub confidence: f32,
+}
+
+/// Calls among `functions` that cross between `a` and `b`, outgoing first,
+/// then by caller location.
+///
+/// When one scope contains the other, functions in the inner scope belong
+/// to it, so `src/` and `src/auth/` give the calls between `src/auth/` and
+/// the rest of `src/`.
+pub fn crossing_calls(
+    a: &PathScope,
+    b: &PathScope,
+    functions: &[FunctionRef],
+    calls: &[CallInfo],
+) -> Vec<CrossingCall> {
+    // Side of each function: true for `a`, false for `b`
+    let mut sides: HashMap<String, (bool, &FunctionRef)> = HashMap::new();
+    for function in functions {
+        let side = match (
+            a.contains(&function.file_path),
+            b.contains(&function.file_path),
+        ) {
+            (true, true) => a.as_str().len() >= b.as_str().len(),
+            (true, false) => true,
+            (false, true) => false,
+            (false, false) => continue,
+        };
+        sides.insert(function.entity_id(), (side, function));
+    }
+
+    let mut seen = HashSet::new();
+    let mut crossing: Vec<CrossingCall> = calls
+        .iter()
+        .filter_map(|call| {
+            let (caller_side, caller) = sides.get(&call.caller_id)?;
+            let (callee_side, callee) = sides.get(&call.callee_id)?;
+            if caller_side == callee_side || !seen.insert((&call.caller_id, &call.callee_id)) {
+                return None;
+            }
+            Some(CrossingCall {
+                direction: if *caller_side {
+                    CallDirection::Outgoing
+                } else {
+                    CallDirection::Incoming
+                },
+                caller: (*caller).clone(),
+                callee: (*callee).clone(),
+                resolution: call.resolution,
+                confidence: call.confidence,
+            })
+        })
+        .collect();
+    crossing.sort_by(|x, y| {
+        (
+            x.direction,
+            &x.caller.file_path,
+            x.caller.line,
+            &x.callee.name,
+        )
+            .cmp(&(
+                y.direction,
+                &y.caller.file_path,
+                y.caller.line,
+                &y.callee.name,
+            ))
+    });
+    crossing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(file_path: &str, name: &str, line: u32) -> FunctionRef {
+        FunctionRef {
+            name: name.to_string(),
+            qualified_name: name.to_string(),
+            file_path: file_path.to_string(),
+            line,
+        }
+    }
+
+    fn call(caller: &FunctionRef, callee: &FunctionRef) -> CallInfo {
+        CallInfo {
+            caller_id: caller.entity_id(),
+            callee_id: callee.entity_id(),
+            caller_name: caller.name.clone(),
+            callee_name: callee.name.clone(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_path_scope() {
+        let scope = PathScope::new("./src/auth/");
+        assert_eq!(scope.as_str(), "src/auth");
+        assert!(scope.contains("src/auth"));
+        assert!(scope.contains("src/auth/session.rs"));
+        assert!(!scope.contains("src/authz.rs"));
+        assert!(PathScope::new(".").contains("src/lib.rs"));
+    }
+
+    #[test]
+    fn test_crossing_calls() {
+        let login = function("src/auth/session.rs", "login", 10);
+        let check = function("src/auth/session.rs", "check", 30);
+        let charge = function("src/billing.rs", "charge", 5);
+        let main = function("src/main.rs", "main", 1);
+        let calls = vec![
+            call(&charge, &check),
+            call(&login, &charge),
+            call(&login, &charge),
+            call(&login, &check),
+            call(&main, &login),
+        ];
+        let functions = [login, check, charge, main];
+
+        let crossing = crossing_calls(
+            &PathScope::new("src/auth"),
+            &PathScope::new("src/billing.rs"),
+            &functions,
+            &calls,
+        );
+        let described: Vec<_> = crossing
+            .iter()
+            .map(|c| (c.direction, c.caller.name.as_str(), c.callee.name.as_str()))
+            .collect();
+        assert_eq!(
+            described,
+            vec![
+                (CallDirection::Outgoing, "login", "charge"),
+                (CallDirection::Incoming, "charge", "check"),
+            ]
+        );
+
+        // The inner scope takes its functions from the outer one
+        let crossing = crossing_calls(
+            &PathScope::new("src"),
+            &PathScope::new("src/auth"),
+            &functions,
+            &calls,
+        );
+        assert_eq!(crossing.len(), 3);
+        assert_eq!(crossing[0].caller.name, "charge");
+        assert_eq!(crossing[1].caller.name, "main");
+        assert_eq!(crossing[2].direction, CallDirection::Incoming);
+    }
+}
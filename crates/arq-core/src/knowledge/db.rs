@@ -1,11 +1,231 @@
 //! SurrealDB embedded database for the knowledge graph.
 
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use surrealdb::engine::local::{Db, RocksDb};
+use surrealdb::engine::local::{Db, Mem, RocksDb};
 use surrealdb::Surreal;
 
+use super::cochange::CoChange;
+use super::coupling::{crossing_calls, CrossingCall, FunctionRef, PathScope};
 use super::error::KnowledgeError;
+use super::filter::EntityFilter;
+use super::indexer::language_name;
 use super::models::{CodeChunk, FileNode, IndexStats, SearchResult};
+use super::name_search::{EXACT_MATCH_SCORE, MIN_PREFIX_LEN, PREFIX_MATCH_SCORE};
+use super::ontology::{CallResolution, QualifiedName};
+use super::summary::{Summary, SummaryKind};
+use super::workspace::{crate_ident, CrateMap};
+
+/// Filter for call records in effect now; closed records are history.
+const CURRENT_CALLS: &str = "valid_to IS NONE";
+
+/// Filter for call records in effect at `$as_of`. Records written before
+/// history tracking have no `valid_from` and count as always valid.
+const CALLS_AS_OF: &str = "(valid_from IS NONE OR valid_from <= <datetime> $as_of) AND (valid_to IS NONE OR valid_to > <datetime> $as_of)";
+
+/// Filter for call records in effect at `as_of`, or now if `None`.
+fn calls_in_effect(as_of: Option<DateTime<Utc>>) -> &'static str {
+    match as_of {
+        Some(_) => CALLS_AS_OF,
+        None => CURRENT_CALLS,
+    }
+}
+
+/// Dimension of the `chunk_embedding` vector index.
+const INDEX_DIMENSION: usize = 384;
+
+/// Tables holding API edges, stored as records like `calls`.
+const API_EDGE_TABLES: [&str; 3] = ["consumes", "produces", "maps_to"];
+
+/// Confidence of a call resolved to a function in one of the caller's dependencies.
+const DEPENDENCY_CALL_CONFIDENCE: f32 = 0.8;
+
+/// Edge tables stored as records: (table, from column prefix, to column prefix, stores names).
+const EDGE_RECORD_TABLES: [(&str, &str, &str, bool); 5] = [
+    ("calls", "caller", "callee", true),
+    ("implements", "impl", "trait", false),
+    ("consumes", "from", "to", true),
+    ("produces", "from", "to", true),
+    ("maps_to", "from", "to", true),
+];
+
+/// Every relation table of the ontology, in listing order.
+///
+/// The tables in [`EDGE_RECORD_TABLES`] store edges as records with
+/// `<column>_id` fields; the others are `RELATE` graph edges.
+pub const EDGE_RELATIONS: [&str; 21] = [
+    "calls",
+    "implements",
+    "contains",
+    "imports",
+    "extends",
+    "uses_type",
+    "returns_type",
+    "has_field",
+    "consumes",
+    "produces",
+    "maps_to",
+    "belongs_to",
+    "exports",
+    "depends_on",
+    "returns",
+    "throws",
+    "awaits",
+    "reads",
+    "writes",
+    "exposes",
+    "tests",
+];
+
+/// A node table listed as entities by [`KnowledgeDb::list_entities`], with
+/// the fields (or SurrealQL expressions) holding each common column.
+struct EntityTable {
+    table: &'static str,
+    kind: &'static str,
+    name: &'static str,
+    qualified_name: &'static str,
+    file_path: &'static str,
+    start_line: &'static str,
+    end_line: &'static str,
+}
+
+impl EntityTable {
+    /// Columns selected into an [`EntityRow`].
+    fn columns(&self) -> String {
+        format!(
+            "string::concat(record::tb(id), ':', <string> record::id(id)) AS id, <string> record::id(id) AS key, {} AS name, {} AS qualified_name, {} AS file_path, {} AS start_line, {} AS end_line, visibility, signature, doc_comment",
+            self.name, self.qualified_name, self.file_path, self.start_line, self.end_line
+        )
+    }
+}
+
+/// A row of [`EntityTable::columns`].
+#[derive(serde::Deserialize)]
+struct EntityRow {
+    id: String,
+    key: String,
+    name: String,
+    qualified_name: Option<String>,
+    file_path: Option<String>,
+    start_line: Option<u32>,
+    end_line: Option<u32>,
+    visibility: Option<String>,
+    signature: Option<String>,
+    doc_comment: Option<String>,
+}
+
+impl EntityRow {
+    fn into_info(self, kind: &str) -> EntityInfo {
+        EntityInfo {
+            id: self.id,
+            key: self.key,
+            kind: kind.to_string(),
+            name: self.name,
+            qualified_name: self.qualified_name,
+            file_path: self.file_path,
+            start_line: self.start_line,
+            end_line: self.end_line,
+            visibility: self.visibility,
+            signature: self.signature,
+            doc_comment: self.doc_comment,
+        }
+    }
+}
+
+/// Node tables of the ontology, in listing order.
+const ENTITY_TABLES: [EntityTable; 10] = [
+    EntityTable {
+        table: "file",
+        kind: "file",
+        name: "name",
+        qualified_name: "path",
+        file_path: "path",
+        start_line: "1",
+        end_line: "lines",
+    },
+    EntityTable {
+        table: "fn_node",
+        kind: "function",
+        name: "name",
+        qualified_name: "qualified_name",
+        file_path: "file_path",
+        start_line: "start_line",
+        end_line: "end_line",
+    },
+    EntityTable {
+        table: "struct_node",
+        kind: "struct",
+        name: "name",
+        qualified_name: "qualified_name",
+        file_path: "file_path",
+        start_line: "start_line",
+        end_line: "end_line",
+    },
+    EntityTable {
+        table: "trait_node",
+        kind: "trait",
+        name: "name",
+        qualified_name: "qualified_name",
+        file_path: "file_path",
+        start_line: "start_line",
+        end_line: "end_line",
+    },
+    EntityTable {
+        table: "impl_node",
+        kind: "impl",
+        name: "target_type",
+        qualified_name: "target_type",
+        file_path: "file_path",
+        start_line: "start_line",
+        end_line: "end_line",
+    },
+    EntityTable {
+        table: "enum_node",
+        kind: "enum",
+        name: "name",
+        qualified_name: "qualified_name",
+        file_path: "file_path",
+        start_line: "start_line",
+        end_line: "end_line",
+    },
+    EntityTable {
+        table: "const_node",
+        kind: "const",
+        name: "name",
+        qualified_name: "qualified_name",
+        file_path: "file_path",
+        start_line: "line",
+        end_line: "line",
+    },
+    EntityTable {
+        table: "schema_node",
+        kind: "schema",
+        name: "name",
+        qualified_name: "qualified_name",
+        file_path: "file_path",
+        start_line: "start_line",
+        end_line: "end_line",
+    },
+    EntityTable {
+        table: "operation_node",
+        kind: "operation",
+        name: "name",
+        qualified_name: "path",
+        file_path: "file_path",
+        start_line: "line",
+        end_line: "line",
+    },
+    EntityTable {
+        table: "doc_node",
+        kind: "document",
+        name: "title",
+        qualified_name: "file_path",
+        file_path: "file_path",
+        start_line: "start_line",
+        end_line: "end_line",
+    },
+];
 
 /// Database connection for the knowledge graph.
 pub struct KnowledgeDb {
@@ -21,6 +241,14 @@ impl KnowledgeDb {
         Ok(Self { db })
     }
 
+    /// Open an empty database held in memory, gone when it is dropped.
+    pub async fn open_in_memory() -> Result<Self, KnowledgeError> {
+        let db = Surreal::new::<Mem>(()).await?;
+        db.use_ns("arq").use_db("knowledge").await?;
+
+        Ok(Self { db })
+    }
+
     /// Initialize the database schema with rich ontology support.
     pub async fn initialize_schema(&self) -> Result<(), KnowledgeError> {
         // ===========================================================================
@@ -38,6 +266,10 @@ impl KnowledgeDb {
                 DEFINE FIELD hash ON file TYPE string;
                 DEFINE FIELD size ON file TYPE int;
                 DEFINE FIELD language ON file TYPE option<string>;
+                DEFINE FIELD lines ON file TYPE option<int>;
+                DEFINE FIELD code_lines ON file TYPE option<int>;
+                DEFINE FIELD comment_lines ON file TYPE option<int>;
+                DEFINE FIELD blank_lines ON file TYPE option<int>;
                 DEFINE FIELD indexed_at ON file TYPE datetime;
                 DEFINE INDEX file_path ON file FIELDS path UNIQUE;
                 "#,
@@ -57,6 +289,7 @@ impl KnowledgeDb {
                 DEFINE INDEX fn_node_name ON fn_node FIELDS name;
                 DEFINE INDEX fn_node_qualified ON fn_node FIELDS qualified_name;
                 DEFINE INDEX fn_node_file ON fn_node FIELDS file_path;
+                DEFINE INDEX fn_node_range ON fn_node FIELDS file_path, start_line, end_line;
                 "#,
             )
             .await?;
@@ -74,6 +307,7 @@ impl KnowledgeDb {
                 DEFINE INDEX struct_name ON struct_node FIELDS name;
                 DEFINE INDEX struct_qualified ON struct_node FIELDS qualified_name;
                 DEFINE INDEX struct_file ON struct_node FIELDS file_path;
+                DEFINE INDEX struct_range ON struct_node FIELDS file_path, start_line, end_line;
                 "#,
             )
             .await?;
@@ -90,6 +324,7 @@ impl KnowledgeDb {
                 DEFINE FIELD end_line ON trait_node TYPE int;
                 DEFINE INDEX trait_name ON trait_node FIELDS name;
                 DEFINE INDEX trait_file ON trait_node FIELDS file_path;
+                DEFINE INDEX trait_range ON trait_node FIELDS file_path, start_line, end_line;
                 "#,
             )
             .await?;
@@ -107,6 +342,7 @@ impl KnowledgeDb {
                 DEFINE INDEX impl_target ON impl_node FIELDS target_type;
                 DEFINE INDEX impl_trait ON impl_node FIELDS trait_name;
                 DEFINE INDEX impl_file ON impl_node FIELDS file_path;
+                DEFINE INDEX impl_range ON impl_node FIELDS file_path, start_line, end_line;
                 "#,
             )
             .await?;
@@ -123,6 +359,7 @@ impl KnowledgeDb {
                 DEFINE FIELD end_line ON enum_node TYPE int;
                 DEFINE INDEX enum_name ON enum_node FIELDS name;
                 DEFINE INDEX enum_file ON enum_node FIELDS file_path;
+                DEFINE INDEX enum_range ON enum_node FIELDS file_path, start_line, end_line;
                 "#,
             )
             .await?;
@@ -139,6 +376,55 @@ impl KnowledgeDb {
                 DEFINE FIELD is_static ON const_node TYPE bool;
                 DEFINE INDEX const_name ON const_node FIELDS name;
                 DEFINE INDEX const_file ON const_node FIELDS file_path;
+                DEFINE INDEX const_range ON const_node FIELDS file_path, line;
+                "#,
+            )
+            .await?;
+
+        // API schema node (GraphQL types, DTOs)
+        self.db
+            .query(
+                r#"
+                DEFINE TABLE schema_node SCHEMALESS;
+                DEFINE FIELD name ON schema_node TYPE string;
+                DEFINE FIELD qualified_name ON schema_node TYPE string;
+                DEFINE FIELD file_path ON schema_node TYPE string;
+                DEFINE FIELD start_line ON schema_node TYPE int;
+                DEFINE FIELD end_line ON schema_node TYPE int;
+                DEFINE INDEX schema_name ON schema_node FIELDS name;
+                DEFINE INDEX schema_file ON schema_node FIELDS file_path;
+                DEFINE INDEX schema_range ON schema_node FIELDS file_path, start_line, end_line;
+                "#,
+            )
+            .await?;
+
+        // API operation node (GraphQL query/mutation/subscription fields)
+        self.db
+            .query(
+                r#"
+                DEFINE TABLE operation_node SCHEMALESS;
+                DEFINE FIELD name ON operation_node TYPE string;
+                DEFINE FIELD path ON operation_node TYPE string;
+                DEFINE FIELD file_path ON operation_node TYPE string;
+                DEFINE FIELD line ON operation_node TYPE int;
+                DEFINE INDEX operation_name ON operation_node FIELDS name;
+                DEFINE INDEX operation_file ON operation_node FIELDS file_path;
+                DEFINE INDEX operation_range ON operation_node FIELDS file_path, line;
+                "#,
+            )
+            .await?;
+
+        // Documentation node (design docs indexed with `--docs`)
+        self.db
+            .query(
+                r#"
+                DEFINE TABLE doc_node SCHEMALESS;
+                DEFINE FIELD title ON doc_node TYPE string;
+                DEFINE FIELD file_path ON doc_node TYPE string;
+                DEFINE FIELD start_line ON doc_node TYPE int;
+                DEFINE FIELD end_line ON doc_node TYPE int;
+                DEFINE INDEX doc_file ON doc_node FIELDS file_path;
+                DEFINE INDEX doc_range ON doc_node FIELDS file_path, start_line, end_line;
                 "#,
             )
             .await?;
@@ -154,6 +440,7 @@ impl KnowledgeDb {
                 DEFINE FIELD file_path ON chunk TYPE string;
                 DEFINE FIELD entity_id ON chunk TYPE option<string>;
                 DEFINE FIELD entity_type ON chunk TYPE string;
+                DEFINE FIELD crate_name ON chunk TYPE option<string>;
                 DEFINE FIELD content ON chunk TYPE string;
                 DEFINE FIELD start_line ON chunk TYPE int;
                 DEFINE FIELD end_line ON chunk TYPE int;
@@ -190,6 +477,10 @@ impl KnowledgeDb {
                 DEFINE FIELD callee_id ON calls TYPE string;
                 DEFINE FIELD caller_name ON calls TYPE string;
                 DEFINE FIELD callee_name ON calls TYPE string;
+                DEFINE FIELD resolution ON calls TYPE string DEFAULT 'resolved';
+                DEFINE FIELD confidence ON calls TYPE float DEFAULT 1.0;
+                DEFINE FIELD valid_from ON calls TYPE option<datetime> DEFAULT time::now();
+                DEFINE FIELD valid_to ON calls TYPE option<datetime>;
                 DEFINE INDEX idx_calls_caller ON calls FIELDS caller_id;
                 DEFINE INDEX idx_calls_callee ON calls FIELDS callee_id;
                 DEFINE INDEX idx_calls_caller_name ON calls FIELDS caller_name;
@@ -222,14 +513,68 @@ impl KnowledgeDb {
             )
             .await?;
 
-        // API edges
+        // API edges - consumes/produces/maps_to as regular tables like calls
+        self.migrate_api_edge_tables().await?;
         self.db
             .query(
                 r#"
                 DEFINE TABLE exposes TYPE RELATION;
-                DEFINE TABLE maps_to TYPE RELATION;
-                DEFINE TABLE consumes TYPE RELATION;
-                DEFINE TABLE produces TYPE RELATION;
+
+                DEFINE TABLE maps_to SCHEMAFULL;
+                DEFINE FIELD from_id ON maps_to TYPE string;
+                DEFINE FIELD to_id ON maps_to TYPE string;
+                DEFINE FIELD from_name ON maps_to TYPE string;
+                DEFINE FIELD to_name ON maps_to TYPE string;
+                DEFINE INDEX idx_maps_to_from ON maps_to FIELDS from_id;
+
+                DEFINE TABLE consumes SCHEMAFULL;
+                DEFINE FIELD from_id ON consumes TYPE string;
+                DEFINE FIELD to_id ON consumes TYPE string;
+                DEFINE FIELD from_name ON consumes TYPE string;
+                DEFINE FIELD to_name ON consumes TYPE string;
+                DEFINE INDEX idx_consumes_from ON consumes FIELDS from_id;
+                DEFINE INDEX idx_consumes_to_name ON consumes FIELDS to_name;
+
+                DEFINE TABLE produces SCHEMAFULL;
+                DEFINE FIELD from_id ON produces TYPE string;
+                DEFINE FIELD to_id ON produces TYPE string;
+                DEFINE FIELD from_name ON produces TYPE string;
+                DEFINE FIELD to_name ON produces TYPE string;
+                DEFINE INDEX idx_produces_from ON produces FIELDS from_id;
+                DEFINE INDEX idx_produces_to_name ON produces FIELDS to_name;
+                "#,
+            )
+            .await?;
+
+        // Co-change edges mined from git history (`arq kg mine-history`)
+        self.db
+            .query(
+                r#"
+                DEFINE TABLE co_changes SCHEMAFULL;
+                DEFINE FIELD file_a ON co_changes TYPE string;
+                DEFINE FIELD file_b ON co_changes TYPE string;
+                DEFINE FIELD count ON co_changes TYPE int;
+                DEFINE FIELD commits_a ON co_changes TYPE int;
+                DEFINE FIELD commits_b ON co_changes TYPE int;
+                DEFINE FIELD weight ON co_changes TYPE float;
+                DEFINE INDEX idx_co_changes_a ON co_changes FIELDS file_a;
+                DEFINE INDEX idx_co_changes_b ON co_changes FIELDS file_b;
+                "#,
+            )
+            .await?;
+
+        // File and directory summaries (`arq kg summarize`)
+        self.db
+            .query(
+                r#"
+                DEFINE TABLE summary SCHEMAFULL;
+                DEFINE FIELD path ON summary TYPE string;
+                DEFINE FIELD kind ON summary TYPE string;
+                DEFINE FIELD summary ON summary TYPE string;
+                DEFINE FIELD hash ON summary TYPE string;
+                DEFINE FIELD updated_at ON summary TYPE datetime VALUE time::now();
+                DEFINE INDEX idx_summary_path ON summary FIELDS path UNIQUE;
+                DEFINE INDEX idx_summary_kind ON summary FIELDS kind;
                 "#,
             )
             .await?;
@@ -285,6 +630,28 @@ impl KnowledgeDb {
         Ok(())
     }
 
+    /// Drop API edge tables that graphs from before GraphQL indexing defined
+    /// as relations, so they can be defined again as regular tables. Nothing
+    /// wrote to them while they were relations.
+    async fn migrate_api_edge_tables(&self) -> Result<(), KnowledgeError> {
+        let info: Option<serde_json::Value> = self.db.query("INFO FOR DB").await?.take(0)?;
+        let tables = info
+            .as_ref()
+            .and_then(|info| info.get("tables"))
+            .and_then(|tables| tables.as_object());
+
+        for table in API_EDGE_TABLES {
+            let is_relation = tables
+                .and_then(|tables| tables.get(table))
+                .and_then(|definition| definition.as_str())
+                .is_some_and(|definition| definition.contains("TYPE RELATION"));
+            if is_relation {
+                self.db.query(format!("REMOVE TABLE {}", table)).await?;
+            }
+        }
+        Ok(())
+    }
+
     /// Check if the database has been initialized.
     pub async fn is_initialized(&self) -> Result<bool, KnowledgeError> {
         let result: Option<serde_json::Value> = self
@@ -296,6 +663,36 @@ impl KnowledgeDb {
         Ok(result.is_some())
     }
 
+    /// Read a metadata value stored as a JSON string.
+    pub async fn get_metadata(&self, key: &str) -> Result<Option<String>, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct MetadataValue {
+            value: String,
+        }
+
+        let result: Option<MetadataValue> = self
+            .db
+            .query("SELECT value FROM metadata WHERE key = $key AND type::is::string(value)")
+            .bind(("key", key.to_string()))
+            .await?
+            .take(0)?;
+        Ok(result.map(|r| r.value))
+    }
+
+    /// Store a metadata value, replacing any previous value for the key.
+    pub async fn set_metadata(&self, key: &str, value: &str) -> Result<(), KnowledgeError> {
+        self.db
+            .query(
+                "DELETE metadata WHERE key = $key;
+                 CREATE metadata SET key = $key, value = $value, updated_at = time::now();",
+            )
+            .bind(("key", key.to_string()))
+            .bind(("value", value.to_string()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
     /// Insert or update a file node.
     pub async fn upsert_file(&self, file: &FileNode) -> Result<(), KnowledgeError> {
         let path = file.path.clone();
@@ -308,6 +705,100 @@ impl KnowledgeDb {
         Ok(())
     }
 
+    /// Update the language and line counts of an indexed file in place,
+    /// keeping its record ID and the edges pointing at it.
+    pub async fn update_file_stats(&self, file: &FileNode) -> Result<(), KnowledgeError> {
+        self.db
+            .query(
+                "UPDATE file SET language = $language, lines = $lines, code_lines = $code_lines, comment_lines = $comment_lines, blank_lines = $blank_lines WHERE path = $path",
+            )
+            .bind(("path", file.path.clone()))
+            .bind(("language", file.language.clone()))
+            .bind(("lines", file.lines))
+            .bind(("code_lines", file.code_lines))
+            .bind(("comment_lines", file.comment_lines))
+            .bind(("blank_lines", file.blank_lines))
+            .await?;
+        Ok(())
+    }
+
+    /// Line counts per language over all indexed files, most code first.
+    pub async fn language_stats(&self) -> Result<Vec<LanguageStats>, KnowledgeError> {
+        self.language_stats_where(|_| true).await
+    }
+
+    /// Line counts per language over the indexed files `keep` accepts, e.g.
+    /// those outside a server's blocked paths, most code first.
+    pub async fn language_stats_where(
+        &self,
+        keep: impl Fn(&str) -> bool,
+    ) -> Result<Vec<LanguageStats>, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct FileRow {
+            path: String,
+            language: Option<String>,
+            lines: Option<u64>,
+            code_lines: Option<u64>,
+            comment_lines: Option<u64>,
+            blank_lines: Option<u64>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct FilePathRow {
+            file_path: String,
+        }
+
+        let files: Vec<FileRow> = self
+            .db
+            .query("SELECT path, language, lines, code_lines, comment_lines, blank_lines FROM file")
+            .await?
+            .take(0)?;
+        let mut stats: Vec<LanguageStats> = Vec::new();
+        for file in files.into_iter().filter(|f| keep(&f.path)) {
+            let language = file.language.unwrap_or_else(|| "unknown".to_string());
+            let index = match stats.iter().position(|s| s.language == language) {
+                Some(index) => index,
+                None => {
+                    stats.push(LanguageStats {
+                        language,
+                        ..Default::default()
+                    });
+                    stats.len() - 1
+                }
+            };
+            let entry = &mut stats[index];
+            entry.files += 1;
+            entry.lines += file.lines.unwrap_or(0);
+            entry.code += file.code_lines.unwrap_or(0);
+            entry.comments += file.comment_lines.unwrap_or(0);
+            entry.blanks += file.blank_lines.unwrap_or(0);
+        }
+
+        // Functions per language, from the extension of their file
+        let function_files: Vec<FilePathRow> = self
+            .db
+            .query("SELECT file_path FROM fn_node")
+            .await?
+            .take(0)?;
+        for row in function_files.into_iter().filter(|r| keep(&r.file_path)) {
+            let extension = Path::new(&row.file_path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("");
+            let language = language_name(extension);
+            if let Some(entry) = stats.iter_mut().find(|s| s.language == language) {
+                entry.functions += 1;
+            }
+        }
+
+        stats.sort_by(|a, b| {
+            b.code
+                .cmp(&a.code)
+                .then_with(|| a.language.cmp(&b.language))
+        });
+        Ok(stats)
+    }
+
     /// Get a file by path.
     pub async fn get_file(&self, path: &str) -> Result<Option<FileNode>, KnowledgeError> {
         let path_owned = path.to_string();
@@ -321,10 +812,31 @@ impl KnowledgeDb {
         Ok(file)
     }
 
+    /// Whether `path` is indexed as a document rather than as code.
+    pub async fn has_document(&self, path: &str) -> Result<bool, KnowledgeError> {
+        let paths: Vec<String> = self
+            .db
+            .query("SELECT VALUE file_path FROM doc_node WHERE file_path = $path LIMIT 1")
+            .bind(("path", path.to_string()))
+            .await?
+            .take(0)?;
+        Ok(!paths.is_empty())
+    }
+
     /// Remove a file and its associated chunks.
+    ///
+    /// Calls made from the file are closed rather than deleted, so
+    /// [`KnowledgeDb::get_dependencies_as_of`] still sees them.
     pub async fn remove_file(&self, path: &str) -> Result<(), KnowledgeError> {
         // Delete in separate queries - SurrealDB 2.0 multi-statement issues
         let path_owned = path.to_string();
+        self.db
+            .query(format!(
+                "UPDATE calls SET valid_to = time::now() WHERE {} AND string::starts_with(caller_id, $prefix)",
+                CURRENT_CALLS
+            ))
+            .bind(("prefix", format!("function:{}:", path)))
+            .await?;
         self.db
             .query("DELETE chunk WHERE file_path = $path")
             .bind(("path", path_owned.clone()))
@@ -337,6 +849,29 @@ impl KnowledgeDb {
             .query("DELETE fn_node WHERE file_path = $path")
             .bind(("path", path_owned.clone()))
             .await?;
+        self.db
+            .query("DELETE schema_node WHERE file_path = $path")
+            .bind(("path", path_owned.clone()))
+            .await?;
+        self.db
+            .query("DELETE operation_node WHERE file_path = $path")
+            .bind(("path", path_owned.clone()))
+            .await?;
+        self.db
+            .query("DELETE doc_node WHERE file_path = $path")
+            .bind(("path", path_owned.clone()))
+            .await?;
+        // Operations of the file, and resolvers linked to functions in it
+        for table in API_EDGE_TABLES {
+            self.db
+                .query(format!(
+                    "DELETE {} WHERE string::starts_with(from_id, $operations) OR string::starts_with(from_id, $functions) OR string::starts_with(to_id, $functions)",
+                    table
+                ))
+                .bind(("operations", format!("operation:{}:", path)))
+                .bind(("functions", format!("function:{}:", path)))
+                .await?;
+        }
         self.db
             .query("DELETE file WHERE path = $path")
             .bind(("path", path_owned))
@@ -408,11 +943,11 @@ impl KnowledgeDb {
         caller_id: &str,
         callee_name: &str,
     ) -> Result<(), KnowledgeError> {
-        // First find if the callee exists
+        // First find if the callee exists (`Type.method` and `Type::method` match `method`)
         let callee: Option<super::models::FunctionNode> = self
             .db
             .query("SELECT * FROM fn_node WHERE name = $name LIMIT 1")
-            .bind(("name", callee_name.to_string()))
+            .bind(("name", QualifiedName::parse(callee_name).name().to_string()))
             .await?
             .take(0)?;
 
@@ -435,7 +970,10 @@ impl KnowledgeDb {
 
         let result: Option<CountResult> = self
             .db
-            .query("SELECT count() FROM calls GROUP ALL")
+            .query(format!(
+                "SELECT count() FROM calls WHERE {} GROUP ALL",
+                CURRENT_CALLS
+            ))
             .await?
             .take(0)?;
 
@@ -469,7 +1007,8 @@ impl KnowledgeDb {
                 end_line,
                 string::slice(content, 0, 200) as preview,
                 entity_id,
-                entity_type
+                entity_type,
+                crate_name
             FROM chunk
             WHERE embedding <|{},COSINE|> $embedding
             ORDER BY score DESC
@@ -489,23 +1028,60 @@ impl KnowledgeDb {
 
     /// Get entities that the given entity depends on (what it calls).
     pub async fn get_dependencies(&self, entity_id: &str) -> Result<Vec<String>, KnowledgeError> {
-        // Extract function name from entity_id (format: "function:path:name" or "fn_node:name")
-        // Also trim backticks that SurrealDB may add for escaping
-        let func_name = entity_id
-            .rsplit(':')
-            .next()
-            .unwrap_or(entity_id)
-            .trim_matches('`');
+        self.dependencies(entity_id, false, None).await
+    }
+
+    /// Get what the given entity called at `as_of`, from the call history.
+    pub async fn get_dependencies_as_of(
+        &self,
+        entity_id: &str,
+        resolved_only: bool,
+        as_of: DateTime<Utc>,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        self.dependencies(entity_id, resolved_only, Some(as_of))
+            .await
+    }
+
+    /// Get dependencies whose callee was resolved to a single definition.
+    ///
+    /// Skips unresolved and ambiguous calls, such as dynamic-dispatch guesses.
+    pub async fn get_resolved_dependencies(
+        &self,
+        entity_id: &str,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        self.dependencies(entity_id, true, None).await
+    }
+
+    async fn dependencies(
+        &self,
+        entity_id: &str,
+        resolved_only: bool,
+        as_of: Option<DateTime<Utc>>,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        // Entity IDs look like "function:path:Type::name" or "fn_node:name"
+        let qualified = QualifiedName::from_entity_id(entity_id);
+        let func_name = qualified.name();
 
         #[derive(serde::Deserialize)]
         struct DepResult {
             callee_name: String,
         }
 
+        // Records written before resolution tracking have no `resolution` field
+        let resolved = if resolved_only {
+            " AND (resolution ?? 'resolved') = 'resolved' AND !string::contains(callee_id, ':?:')"
+        } else {
+            ""
+        };
         let results: Vec<DepResult> = self
             .db
-            .query("SELECT callee_name FROM calls WHERE caller_name = $name")
+            .query(format!(
+                "SELECT callee_name FROM calls WHERE caller_name = $name AND {}{}",
+                calls_in_effect(as_of),
+                resolved
+            ))
             .bind(("name", func_name.to_string()))
+            .bind(("as_of", as_of.map(|t| t.to_rfc3339())))
             .await?
             .take(0)?;
 
@@ -518,13 +1094,26 @@ impl KnowledgeDb {
 
     /// Get entities that depend on the given entity (what calls it).
     pub async fn get_impact(&self, entity_id: &str) -> Result<Vec<String>, KnowledgeError> {
-        // Extract function name from entity_id (format: "function:path:name" or just "name")
-        // Also trim backticks that SurrealDB may add for escaping
-        let func_name = entity_id
-            .rsplit(':')
-            .next()
-            .unwrap_or(entity_id)
-            .trim_matches('`');
+        self.impact(entity_id, None).await
+    }
+
+    /// Get what called the given entity at `as_of`, from the call history.
+    pub async fn get_impact_as_of(
+        &self,
+        entity_id: &str,
+        as_of: DateTime<Utc>,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        self.impact(entity_id, Some(as_of)).await
+    }
+
+    async fn impact(
+        &self,
+        entity_id: &str,
+        as_of: Option<DateTime<Utc>>,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        // Entity IDs look like "function:path:Type::name" or "fn_node:name"
+        let qualified = QualifiedName::from_entity_id(entity_id);
+        let func_name = qualified.name();
 
         #[derive(serde::Deserialize)]
         struct ImpactResult {
@@ -533,8 +1122,12 @@ impl KnowledgeDb {
 
         let results: Vec<ImpactResult> = self
             .db
-            .query("SELECT caller_name FROM calls WHERE callee_name = $name")
+            .query(format!(
+                "SELECT caller_name FROM calls WHERE callee_name = $name AND {}",
+                calls_in_effect(as_of)
+            ))
             .bind(("name", func_name.to_string()))
+            .bind(("as_of", as_of.map(|t| t.to_rfc3339())))
             .await?
             .take(0)?;
 
@@ -545,6 +1138,38 @@ impl KnowledgeDb {
         Ok(names)
     }
 
+    /// Get callers of the given entity that belong to a workspace crate.
+    pub async fn get_impact_in_crate(
+        &self,
+        entity_id: &str,
+        crate_name: &str,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        let qualified = QualifiedName::from_entity_id(entity_id);
+        let func_name = qualified.name();
+
+        #[derive(serde::Deserialize)]
+        struct ImpactResult {
+            caller_name: String,
+        }
+
+        // Caller IDs end in the crate-qualified name: "function:path:crate::name"
+        let results: Vec<ImpactResult> = self
+            .db
+            .query(format!(
+                "SELECT caller_name FROM calls WHERE callee_name = $name AND string::contains(caller_id, $marker) AND {}",
+                CURRENT_CALLS
+            ))
+            .bind(("name", func_name.to_string()))
+            .bind(("marker", format!(":{}::", crate_ident(crate_name))))
+            .await?
+            .take(0)?;
+
+        let mut names: Vec<String> = results.into_iter().map(|r| r.caller_name).collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
     /// List all functions in the database.
     pub async fn list_functions(
         &self,
@@ -555,11 +1180,44 @@ impl KnowledgeDb {
         Ok(results)
     }
 
+    /// List functions defined in a workspace crate.
+    pub async fn list_functions_in_crate(
+        &self,
+        crate_name: &str,
+        limit: usize,
+    ) -> Result<Vec<super::models::FunctionNode>, KnowledgeError> {
+        let query = format!(
+            "SELECT * FROM fn_node WHERE string::starts_with(qualified_name, $prefix) LIMIT {}",
+            limit
+        );
+        let results: Vec<super::models::FunctionNode> = self
+            .db
+            .query(&query)
+            .bind(("prefix", format!("{}::", crate_ident(crate_name))))
+            .await?
+            .take(0)?;
+        Ok(results)
+    }
+
     /// Find a function by name.
     pub async fn find_function_by_name(
         &self,
         name: &str,
     ) -> Result<Option<super::models::FunctionNode>, KnowledgeError> {
+        // Qualified input (`Task::new`, `Task.new`) matches canonical qualified names
+        let qualified = QualifiedName::parse(name);
+        if qualified.segments().len() > 1 {
+            let canonical = qualified.canonical();
+            let result: Option<super::models::FunctionNode> = self
+                .db
+                .query("SELECT * FROM fn_node WHERE qualified_name = $qualified OR string::ends_with(qualified_name, $suffix) LIMIT 1")
+                .bind(("suffix", format!("::{}", canonical)))
+                .bind(("qualified", canonical))
+                .await?
+                .take(0)?;
+            return Ok(result);
+        }
+
         let result: Option<super::models::FunctionNode> = self
             .db
             .query("SELECT * FROM fn_node WHERE name = $name LIMIT 1")
@@ -569,6 +1227,117 @@ impl KnowledgeDb {
         Ok(result)
     }
 
+    /// Functions and structs named `name` as search results, or, when none
+    /// is, those whose name starts with it.
+    ///
+    /// Exact matches score [`EXACT_MATCH_SCORE`]; prefix matches score
+    /// [`PREFIX_MATCH_SCORE`] and come shortest name first. A qualified
+    /// `name` matches by its trailing segments, like
+    /// [`KnowledgeDb::find_function_by_name`].
+    pub async fn search_by_name(
+        &self,
+        name: &QualifiedName,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, KnowledgeError> {
+        let exact = self.entities_named(name, false, limit).await?;
+        if !exact.is_empty() || name.canonical().len() < MIN_PREFIX_LEN {
+            return Ok(exact);
+        }
+        self.entities_named(name, true, limit).await
+    }
+
+    async fn entities_named(
+        &self,
+        name: &QualifiedName,
+        prefix: bool,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct NameHit {
+            path: String,
+            start_line: u32,
+            end_line: u32,
+            preview: Option<String>,
+            name: String,
+            entity_id: String,
+            entity_type: String,
+            crate_name: Option<String>,
+        }
+
+        let condition = match (name.segments().len() > 1, prefix) {
+            (false, false) => "name = $name",
+            (false, true) => "string::starts_with(name, $name)",
+            (true, false) => {
+                "qualified_name = $name OR string::ends_with(qualified_name, '::' + $name)"
+            }
+            (true, true) => {
+                "string::starts_with(qualified_name, $name) OR string::contains(qualified_name, '::' + $name)"
+            }
+        };
+        // Chunks carry the crate, entities don't
+        let crate_name = "(SELECT VALUE crate_name FROM chunk WHERE file_path = $parent.file_path LIMIT 1)[0] AS crate_name";
+        let query = format!(
+            r#"
+            SELECT file_path AS path, start_line, end_line, signature AS preview, name,
+                string::concat('function:', file_path, ':', qualified_name) AS entity_id,
+                'function' AS entity_type, {crate_name}
+            FROM fn_node WHERE {condition} LIMIT {limit};
+            SELECT file_path AS path, start_line, end_line, NONE AS preview, name,
+                string::concat('struct:', file_path, ':', qualified_name) AS entity_id,
+                'struct' AS entity_type, {crate_name}
+            FROM struct_node WHERE {condition} LIMIT {limit};
+            "#,
+        );
+
+        let mut response = self
+            .db
+            .query(query)
+            .bind(("name", name.canonical()))
+            .await?;
+        let mut hits: Vec<NameHit> = response.take(0)?;
+        hits.extend(response.take::<Vec<NameHit>>(1)?);
+        if prefix {
+            hits.sort_by(|a, b| (a.name.len(), &a.path).cmp(&(b.name.len(), &b.path)));
+        }
+
+        let score = if prefix {
+            PREFIX_MATCH_SCORE
+        } else {
+            EXACT_MATCH_SCORE
+        };
+        Ok(hits
+            .into_iter()
+            .take(limit)
+            .map(|hit| SearchResult {
+                path: hit.path,
+                score,
+                start_line: hit.start_line,
+                end_line: hit.end_line,
+                preview: hit.preview,
+                entity_id: Some(hit.entity_id),
+                entity_type: hit.entity_type,
+                crate_name: hit.crate_name,
+                context: None,
+            })
+            .collect())
+    }
+
+    /// Length of the stored embeddings, without loading the embedding model;
+    /// the dimension of the vector index when nothing is indexed yet.
+    pub async fn embedding_dimension(&self) -> Result<usize, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct Dimension {
+            dimension: usize,
+        }
+
+        let stored: Option<Dimension> = self
+            .db
+            .query("SELECT array::len(embedding) AS dimension FROM chunk LIMIT 1")
+            .await?
+            .take(0)?;
+        Ok(stored.map_or(INDEX_DIMENSION, |d| d.dimension))
+    }
+
     /// Get statistics about the indexed data.
     pub async fn get_stats(&self) -> Result<IndexStats, KnowledgeError> {
         // SurrealDB returns count as { count: N }
@@ -597,13 +1366,24 @@ impl KnowledgeDb {
             .query("SELECT count() FROM chunk GROUP ALL")
             .await?
             .take(0)?;
+        let documents: Option<CountResult> = self
+            .db
+            .query("SELECT count() FROM doc_node GROUP ALL")
+            .await?
+            .take(0)?;
 
         Ok(IndexStats {
             files: files.map(|r| r.count as usize).unwrap_or(0),
+            documents: documents.map(|r| r.count as usize).unwrap_or(0),
             structs: structs.map(|r| r.count as usize).unwrap_or(0),
             functions: functions.map(|r| r.count as usize).unwrap_or(0),
             chunks: chunks.map(|r| r.count as usize).unwrap_or(0),
             total_size: 0, // TODO: Calculate from file sizes
+            generated_skipped: 0,
+            oversized_skipped: 0,
+            signatures_only: 0,
+            report: Default::default(),
+            timings: Default::default(),
             last_updated: Some(chrono::Utc::now()),
         })
     }
@@ -695,6 +1475,33 @@ impl KnowledgeDb {
         Ok(id)
     }
 
+    /// Insert an API schema entity.
+    pub async fn insert_schema_entity(
+        &self,
+        s: &super::ontology::nodes::SchemaEntity,
+    ) -> Result<String, KnowledgeError> {
+        let _: Option<serde_json::Value> = self.db.create("schema_node").content(s.clone()).await?;
+
+        let id =
+            s.id.clone()
+                .unwrap_or_else(|| format!("schema_node:{}", s.qualified_name));
+        Ok(id)
+    }
+
+    /// Insert an API operation entity.
+    pub async fn insert_operation_entity(
+        &self,
+        o: &super::ontology::nodes::OperationEntity,
+    ) -> Result<String, KnowledgeError> {
+        let _: Option<serde_json::Value> =
+            self.db.create("operation_node").content(o.clone()).await?;
+
+        let id =
+            o.id.clone()
+                .unwrap_or_else(|| format!("operation_node:{}", o.path));
+        Ok(id)
+    }
+
     /// Create a generic relation between two entities.
     pub async fn create_relation(
         &self,
@@ -710,6 +1517,9 @@ impl KnowledgeDb {
             "implements" => {
                 self.store_implements_edge(from_id, to_id).await?;
             }
+            "consumes" | "produces" | "maps_to" => {
+                self.store_api_edge(relation, from_id, to_id).await?;
+            }
             _ => {
                 // For other relations, try RELATE with properly escaped IDs
                 let from_escaped = Self::escape_record_id(from_id);
@@ -721,64 +1531,114 @@ impl KnowledgeDb {
         Ok(())
     }
 
-    /// Store a call edge as a regular record.
-    pub async fn store_call_edge(
+    /// Insert rows with multi-row `INSERT IGNORE` statements of at most `batch_size` rows.
+    ///
+    /// Rows whose record ID already exists are skipped, as a single `create`
+    /// of a duplicate would fail without affecting other rows.
+    pub async fn insert_batch<T>(
         &self,
-        caller_id: &str,
-        callee_id: &str,
-    ) -> Result<(), KnowledgeError> {
-        #[derive(serde::Serialize)]
-        struct CallRecord {
-            caller_id: String,
-            callee_id: String,
-            caller_name: String,
-            callee_name: String,
+        table: &str,
+        rows: &[T],
+        batch_size: usize,
+    ) -> Result<(), KnowledgeError>
+    where
+        T: serde::Serialize + Clone + 'static,
+    {
+        let query = format!("INSERT IGNORE INTO {} $rows", table);
+        for batch in rows.chunks(batch_size.max(1)) {
+            self.db
+                .query(&query)
+                .bind(("rows", batch.to_vec()))
+                .await?
+                .check()?;
         }
-
-        // Extract names from IDs (format: function:path:name)
-        let caller_name = caller_id
-            .rsplit(':')
-            .next()
-            .unwrap_or(caller_id)
-            .to_string();
-        let callee_name = callee_id
-            .rsplit(':')
-            .next()
-            .unwrap_or(callee_id)
-            .to_string();
-
-        let record = CallRecord {
-            caller_id: caller_id.to_string(),
-            callee_id: callee_id.to_string(),
-            caller_name,
-            callee_name,
-        };
-
-        let _: Option<serde_json::Value> = self.db.create("calls").content(record).await?;
         Ok(())
     }
 
-    /// Store an implements edge as a regular record.
-    pub async fn store_implements_edge(
+    /// Insert code chunks in batches.
+    pub async fn insert_chunks(
         &self,
-        from_id: &str,
-        to_id: &str,
+        chunks: &[CodeChunk],
+        batch_size: usize,
+    ) -> Result<(), KnowledgeError> {
+        self.insert_batch("chunk", chunks, batch_size).await
+    }
+
+    /// Store `(from, relation, to)` edges in batches, grouped by relation.
+    ///
+    /// Record-table edges (calls, implements, API edges) become multi-row
+    /// inserts; graph edges such as `contains` are sent as one multi-statement
+    /// `RELATE` query per batch, where a failing statement does not affect the rest.
+    pub async fn store_edges(
+        &self,
+        edges: &[(&str, &str, &str)],
+        batch_size: usize,
     ) -> Result<(), KnowledgeError> {
-        #[derive(serde::Serialize)]
-        struct ImplementsRecord {
-            impl_id: String,
-            trait_id: String,
+        for (table, from_col, to_col, named) in EDGE_RECORD_TABLES {
+            let rows: Vec<serde_json::Value> = edges
+                .iter()
+                .filter(|(_, relation, _)| *relation == table)
+                .map(|(from, _, to)| match table {
+                    "calls" => call_record(from, to),
+                    _ => edge_record(from_col, from, to_col, to, named),
+                })
+                .collect();
+            self.insert_batch(table, &rows, batch_size).await?;
         }
 
-        let record = ImplementsRecord {
-            impl_id: from_id.to_string(),
-            trait_id: to_id.to_string(),
-        };
+        let relates: Vec<String> = edges
+            .iter()
+            .filter(|(_, relation, _)| !EDGE_RECORD_TABLES.iter().any(|(t, ..)| t == relation))
+            .map(|(from, relation, to)| {
+                format!(
+                    "RELATE {}->{}->{};",
+                    Self::escape_record_id(from),
+                    relation,
+                    Self::escape_record_id(to)
+                )
+            })
+            .collect();
+        for batch in relates.chunks(batch_size.max(1)) {
+            let _ = self.db.query(batch.concat()).await;
+        }
+
+        Ok(())
+    }
 
+    /// Store a call edge as a regular record.
+    pub async fn store_call_edge(
+        &self,
+        caller_id: &str,
+        callee_id: &str,
+    ) -> Result<(), KnowledgeError> {
+        let record = call_record(caller_id, callee_id);
+        let _: Option<serde_json::Value> = self.db.create("calls").content(record).await?;
+        Ok(())
+    }
+
+    /// Store an implements edge as a regular record.
+    pub async fn store_implements_edge(
+        &self,
+        from_id: &str,
+        to_id: &str,
+    ) -> Result<(), KnowledgeError> {
+        let record = edge_record("impl", from_id, "trait", to_id, false);
         let _: Option<serde_json::Value> = self.db.create("implements").content(record).await?;
         Ok(())
     }
 
+    /// Store an API edge (consumes, produces, maps_to) as a regular record.
+    pub async fn store_api_edge(
+        &self,
+        table: &str,
+        from_id: &str,
+        to_id: &str,
+    ) -> Result<(), KnowledgeError> {
+        let record = edge_record("from", from_id, "to", to_id, true);
+        let _: Option<serde_json::Value> = self.db.create(table).content(record).await?;
+        Ok(())
+    }
+
     /// Escape a record ID for use in SurrealDB queries.
     fn escape_record_id(id: &str) -> String {
         // If ID contains special chars, wrap the id part in backticks
@@ -807,6 +1667,9 @@ impl KnowledgeDb {
             "impl_node",
             "enum_node",
             "const_node",
+            "schema_node",
+            "operation_node",
+            "doc_node",
             "chunk",
         ] {
             self.db
@@ -847,12 +1710,625 @@ impl KnowledgeDb {
             impls: count_table(&self.db, "impl_node").await?,
             enums: count_table(&self.db, "enum_node").await?,
             constants: count_table(&self.db, "const_node").await?,
+            schemas: count_table(&self.db, "schema_node").await?,
+            operations: count_table(&self.db, "operation_node").await?,
             chunks: count_table(&self.db, "chunk").await?,
-            calls: count_table(&self.db, "calls").await?,
+            calls: self.count_calls().await?,
             implements: count_table(&self.db, "implements").await?,
+            consumes: count_table(&self.db, "consumes").await?,
+            produces: count_table(&self.db, "produces").await?,
+            languages: self.language_stats().await?,
         })
     }
 
+    // ===========================================================================
+    // WORKSPACE CRATES
+    // ===========================================================================
+
+    /// Link unresolved calls (`function:?:name`) to functions defined in the
+    /// caller's crate or in a workspace crate it depends on.
+    ///
+    /// A call is linked only when exactly one candidate matches; calls with
+    /// several candidates keep their unresolved callee and are marked
+    /// ambiguous with a confidence of one over the candidate count. Returns
+    /// the number of call edges linked.
+    pub async fn resolve_calls(&self, crates: &CrateMap) -> Result<usize, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct FunctionRow {
+            file_path: String,
+            name: String,
+            #[serde(default)]
+            qualified_name: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct CallRow {
+            caller_id: String,
+            callee_id: String,
+            callee_name: String,
+        }
+
+        let functions: Vec<FunctionRow> = self
+            .db
+            .query("SELECT file_path, name, qualified_name FROM fn_node")
+            .await?
+            .take(0)?;
+
+        // Function name -> (owning crate, entity ID)
+        let mut by_name: HashMap<&str, Vec<(&str, String)>> = HashMap::new();
+        for f in &functions {
+            if let Some(info) = crates.crate_for_path(&f.file_path) {
+                by_name.entry(f.name.as_str()).or_default().push((
+                    info.name.as_str(),
+                    format!("function:{}:{}", f.file_path, f.qualified_name),
+                ));
+            }
+        }
+
+        let calls: Vec<CallRow> = self
+            .db
+            .query(format!(
+                "SELECT caller_id, callee_id, callee_name FROM calls WHERE string::starts_with(callee_id, 'function:?:') AND {}",
+                CURRENT_CALLS
+            ))
+            .await?
+            .take(0)?;
+
+        // (unresolved ID, resolved ID) -> caller IDs
+        let mut updates: HashMap<(String, String), Vec<String>> = HashMap::new();
+        // (unresolved ID, candidate count) -> caller IDs
+        let mut ambiguous: HashMap<(String, usize), Vec<String>> = HashMap::new();
+        let mut dependency_updates: HashMap<(String, String), Vec<String>> = HashMap::new();
+        for call in &calls {
+            let Some(caller) =
+                split_entity_id(&call.caller_id).and_then(|(path, _)| crates.crate_for_path(path))
+            else {
+                continue;
+            };
+            let Some(candidates) = by_name.get(call.callee_name.as_str()) else {
+                continue;
+            };
+
+            let in_crate: Vec<&String> = candidates
+                .iter()
+                .filter(|(c, _)| *c == caller.name)
+                .map(|(_, id)| id)
+                .collect();
+            // Names defined in the caller's own crate shadow its dependencies
+            let via_dependency = in_crate.is_empty();
+            let matches: Vec<&String> = if via_dependency {
+                candidates
+                    .iter()
+                    .filter(|(c, _)| caller.dependencies.iter().any(|d| d == *c))
+                    .map(|(_, id)| id)
+                    .collect()
+            } else {
+                in_crate
+            };
+
+            match matches.as_slice() {
+                [resolved] => {
+                    let key = (call.callee_id.clone(), (*resolved).clone());
+                    let callers = if via_dependency {
+                        &mut dependency_updates
+                    } else {
+                        &mut updates
+                    };
+                    callers.entry(key).or_default().push(call.caller_id.clone());
+                }
+                [] => {}
+                candidates => ambiguous
+                    .entry((call.callee_id.clone(), candidates.len()))
+                    .or_default()
+                    .push(call.caller_id.clone()),
+            }
+        }
+
+        let mut updated = 0;
+        for (updates, confidence) in [
+            (updates, CallResolution::Resolved.default_confidence()),
+            (dependency_updates, DEPENDENCY_CALL_CONFIDENCE),
+        ] {
+            for ((unresolved, resolved), callers) in updates {
+                updated += callers.len();
+                self.db
+                    .query(format!(
+                        "UPDATE calls SET callee_id = $resolved, resolution = $resolution, confidence = $confidence WHERE callee_id = $unresolved AND caller_id INSIDE $callers AND {}",
+                        CURRENT_CALLS
+                    ))
+                    .bind(("resolved", resolved))
+                    .bind(("resolution", CallResolution::Resolved.as_str()))
+                    .bind(("confidence", confidence))
+                    .bind(("unresolved", unresolved))
+                    .bind(("callers", callers))
+                    .await?;
+            }
+        }
+
+        for ((unresolved, candidates), callers) in ambiguous {
+            self.db
+                .query(format!(
+                    "UPDATE calls SET resolution = $resolution, confidence = $confidence WHERE callee_id = $unresolved AND caller_id INSIDE $callers AND {}",
+                    CURRENT_CALLS
+                ))
+                .bind(("resolution", CallResolution::Ambiguous.as_str()))
+                .bind(("confidence", 1.0 / candidates as f32))
+                .bind(("unresolved", unresolved))
+                .bind(("callers", callers))
+                .await?;
+        }
+
+        Ok(updated)
+    }
+
+    // ===========================================================================
+    // API LAYER
+    // ===========================================================================
+
+    /// Link GraphQL operations to the resolver functions that implement them.
+    ///
+    /// A resolver is a function named after the operation (`createUser`,
+    /// `create_user`, `resolve_create_user`). When several functions match,
+    /// only those on a Query/Mutation/Subscription/Resolver type are used.
+    /// Each resolver gets an operation MAPS_TO edge and inherits the
+    /// operation's CONSUMES/PRODUCES edges.
+    ///
+    /// Only operations the `changed` files can affect are linked again:
+    /// those declared in them, those named like a function in them, those
+    /// without a resolver, and those sharing a resolver with any of these.
+    /// Returns the number of resolvers linked.
+    pub async fn link_graphql_resolvers(
+        &self,
+        changed: &[String],
+    ) -> Result<usize, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct OperationRow {
+            file_path: String,
+            name: String,
+            path: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct FunctionRow {
+            file_path: String,
+            name: String,
+            #[serde(default)]
+            qualified_name: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct EdgeRow {
+            from_id: String,
+            to_id: String,
+        }
+
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        let operations: Vec<OperationRow> = self
+            .db
+            .query("SELECT file_path, name, path FROM operation_node")
+            .await?
+            .take(0)?;
+        if operations.is_empty() {
+            return Ok(0);
+        }
+        let functions: Vec<FunctionRow> = self
+            .db
+            .query("SELECT file_path, name, qualified_name FROM fn_node")
+            .await?
+            .take(0)?;
+        let links: Vec<EdgeRow> = self
+            .db
+            .query("SELECT from_id, to_id FROM maps_to WHERE string::starts_with(from_id, 'operation:') AND string::starts_with(to_id, 'function:')")
+            .await?
+            .take(0)?;
+
+        let changed: HashSet<&str> = changed.iter().map(String::as_str).collect();
+        let changed_functions: Vec<&FunctionRow> = functions
+            .iter()
+            .filter(|f| changed.contains(f.file_path.as_str()))
+            .collect();
+        let operation_id = |op: &OperationRow| format!("operation:{}:{}", op.file_path, op.path);
+        let linked_ops: HashSet<&str> = links.iter().map(|l| l.from_id.as_str()).collect();
+
+        let mut affected: HashSet<String> = operations
+            .iter()
+            .filter(|op| {
+                changed.contains(op.file_path.as_str())
+                    || changed_functions
+                        .iter()
+                        .any(|f| is_resolver_name(&f.name, &op.name))
+                    || !linked_ops.contains(operation_id(op).as_str())
+            })
+            .map(operation_id)
+            .collect();
+
+        // Resolvers of affected operations lose their inherited edges, so
+        // every other operation mapped to them is linked again as well
+        let mut resolvers: HashSet<&str> = HashSet::new();
+        loop {
+            let before = (affected.len(), resolvers.len());
+            for link in &links {
+                if affected.contains(&link.from_id) {
+                    resolvers.insert(&link.to_id);
+                }
+            }
+            for link in &links {
+                if resolvers.contains(link.to_id.as_str()) {
+                    affected.insert(link.from_id.clone());
+                }
+            }
+            if (affected.len(), resolvers.len()) == before {
+                break;
+            }
+        }
+
+        for op in &affected {
+            self.db
+                .query("DELETE maps_to WHERE from_id = $id")
+                .bind(("id", op.clone()))
+                .await?;
+        }
+        for resolver in &resolvers {
+            for table in ["consumes", "produces"] {
+                self.db
+                    .query(format!("DELETE {} WHERE from_id = $id", table))
+                    .bind(("id", resolver.to_string()))
+                    .await?;
+            }
+        }
+
+        let mut linked = 0;
+        for op in operations
+            .iter()
+            .filter(|op| affected.contains(&operation_id(op)))
+        {
+            let op_id = operation_id(op);
+            let candidates: Vec<&FunctionRow> = functions
+                .iter()
+                .filter(|f| is_resolver_name(&f.name, &op.name))
+                .collect();
+            let on_root: Vec<&FunctionRow> = candidates
+                .iter()
+                .copied()
+                .filter(|f| {
+                    let owner = f.qualified_name.to_lowercase();
+                    ["query", "mutation", "subscription", "resolver"]
+                        .iter()
+                        .any(|root| owner.contains(root))
+                })
+                .collect();
+            let resolvers = match (on_root.is_empty(), candidates.as_slice()) {
+                (false, _) => on_root,
+                (true, [single]) => vec![*single],
+                _ => continue,
+            };
+
+            let mut contracts: Vec<(&str, EdgeRow)> = Vec::new();
+            for table in ["consumes", "produces"] {
+                let edges: Vec<EdgeRow> = self
+                    .db
+                    .query(format!(
+                        "SELECT from_id, to_id FROM {} WHERE from_id = $id",
+                        table
+                    ))
+                    .bind(("id", op_id.clone()))
+                    .await?
+                    .take(0)?;
+                contracts.extend(edges.into_iter().map(|e| (table, e)));
+            }
+
+            for resolver in resolvers {
+                let resolver_id = format!(
+                    "function:{}:{}",
+                    resolver.file_path, resolver.qualified_name
+                );
+                self.store_api_edge("maps_to", &op_id, &resolver_id).await?;
+                for (table, edge) in &contracts {
+                    self.store_api_edge(table, &resolver_id, &edge.to_id)
+                        .await?;
+                }
+                linked += 1;
+            }
+        }
+
+        Ok(linked)
+    }
+
+    // ===========================================================================
+    // GO INTERFACES
+    // ===========================================================================
+
+    /// Link Go types to the interfaces they satisfy.
+    ///
+    /// Go interfaces are satisfied implicitly, so no implements edges come out
+    /// of parsing. A struct implements an interface when the methods declared
+    /// on it (value or pointer receiver) across its package cover every
+    /// method of the interface. Links are recomputed on every run. Returns
+    /// the number of implements edges stored.
+    pub async fn link_go_interfaces(&self) -> Result<usize, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct InterfaceRow {
+            file_path: String,
+            qualified_name: String,
+            #[serde(default)]
+            required_methods: Vec<String>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct TypeRow {
+            file_path: String,
+            name: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct MethodRow {
+            file_path: String,
+            name: String,
+            parent: String,
+        }
+
+        self.db
+            .query("DELETE implements WHERE string::contains(impl_id, '.go:')")
+            .await?;
+
+        let interfaces: Vec<InterfaceRow> = self
+            .db
+            .query("SELECT file_path, qualified_name, required_methods FROM trait_node WHERE string::ends_with(file_path, '.go')")
+            .await?
+            .take(0)?;
+        if interfaces.is_empty() {
+            return Ok(0);
+        }
+        let structs: Vec<TypeRow> = self
+            .db
+            .query(
+                "SELECT file_path, name FROM struct_node WHERE string::ends_with(file_path, '.go')",
+            )
+            .await?
+            .take(0)?;
+        let methods: Vec<MethodRow> = self
+            .db
+            .query("SELECT file_path, name, parent FROM fn_node WHERE string::ends_with(file_path, '.go') AND parent != NONE")
+            .await?
+            .take(0)?;
+
+        let interfaces: Vec<(String, Vec<String>)> = interfaces
+            .into_iter()
+            .map(|i| {
+                (
+                    format!("trait:{}:{}", i.file_path, i.qualified_name),
+                    i.required_methods,
+                )
+            })
+            .collect();
+        let structs: Vec<(String, String)> =
+            structs.into_iter().map(|s| (s.file_path, s.name)).collect();
+        let methods: Vec<(String, String, String)> = methods
+            .into_iter()
+            .map(|m| (m.file_path, m.parent, m.name))
+            .collect();
+
+        let links = go_implementations(&interfaces, &structs, &methods);
+        for (struct_id, interface_id) in &links {
+            self.store_implements_edge(struct_id, interface_id).await?;
+        }
+        Ok(links.len())
+    }
+
+    /// Operations and resolvers that consume or produce the named schema type.
+    pub async fn get_api_impact(&self, type_name: &str) -> Result<Vec<ApiUsage>, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct EdgeRow {
+            from_id: String,
+            from_name: String,
+        }
+
+        let mut usages = Vec::new();
+        for (table, relation) in [
+            ("consumes", ApiRelation::Consumes),
+            ("produces", ApiRelation::Produces),
+        ] {
+            let edges: Vec<EdgeRow> = self
+                .db
+                .query(format!(
+                    "SELECT from_id, from_name FROM {} WHERE to_name = $name",
+                    table
+                ))
+                .bind(("name", type_name.to_string()))
+                .await?
+                .take(0)?;
+            usages.extend(edges.into_iter().map(|e| ApiUsage {
+                entity_id: e.from_id,
+                name: e.from_name,
+                relation,
+            }));
+        }
+
+        usages.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
+        usages.dedup_by(|a, b| a.entity_id == b.entity_id && a.relation == b.relation);
+        Ok(usages)
+    }
+
+    // ===========================================================================
+    // CONSISTENCY CHECKS
+    // ===========================================================================
+
+    /// Cross-check node and edge integrity.
+    ///
+    /// `expected_dimension` is the embedding size produced by the active embedder.
+    pub async fn verify(&self, expected_dimension: usize) -> Result<VerifyReport, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct FunctionKey {
+            file_path: String,
+            name: String,
+            #[serde(default)]
+            qualified_name: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct CallRow {
+            id: String,
+            caller_id: String,
+            callee_id: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct ContainsRow {
+            id: String,
+            parent_table: String,
+            parent_key: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct ChunkRow {
+            id: String,
+            file_path: String,
+            dimension: usize,
+        }
+
+        let files: HashSet<String> = self.list_indexed_files().await?.into_iter().collect();
+        let functions: Vec<FunctionKey> = self
+            .db
+            .query("SELECT file_path, name, qualified_name FROM fn_node")
+            .await?
+            .take(0)?;
+        // Parsers build IDs from either the plain or the qualified name
+        let functions: HashSet<(String, String)> = functions
+            .into_iter()
+            .flat_map(|f| {
+                [
+                    (f.file_path.clone(), f.name),
+                    (f.file_path, f.qualified_name),
+                ]
+            })
+            .collect();
+
+        let function_exists = |id: &str| match split_entity_id(id) {
+            // Unresolved references (external or not yet linked) are not dangling
+            None => true,
+            Some((path, Some(name))) => {
+                files.contains(path) && functions.contains(&(path.to_string(), name.to_string()))
+            }
+            Some((path, None)) => files.contains(path),
+        };
+
+        let mut report = VerifyReport::default();
+
+        // Calls referencing functions that no longer exist
+        let calls: Vec<CallRow> = self
+            .db
+            .query(format!(
+                "SELECT type::string(id) AS id, caller_id, callee_id FROM calls WHERE {}",
+                CURRENT_CALLS
+            ))
+            .await?
+            .take(0)?;
+        for call in calls {
+            let missing = if !function_exists(&call.caller_id) {
+                Some(&call.caller_id)
+            } else if !function_exists(&call.callee_id) {
+                Some(&call.callee_id)
+            } else {
+                None
+            };
+            if let Some(missing) = missing {
+                report.dangling_calls.push(VerifyIssue {
+                    record_id: call.id.clone(),
+                    detail: format!(
+                        "{} -> {} ({} not found)",
+                        call.caller_id, call.callee_id, missing
+                    ),
+                });
+            }
+        }
+
+        // Contains edges whose parent's file is gone
+        let contains: Vec<ContainsRow> = self
+            .db
+            .query(
+                "SELECT type::string(id) AS id, record::tb(in) AS parent_table, <string> record::id(in) AS parent_key FROM contains",
+            )
+            .await?
+            .take(0)?;
+        for edge in contains {
+            let parent = format!("{}:{}", edge.parent_table, edge.parent_key);
+            let parent_file = if edge.parent_table == "file" {
+                Some(edge.parent_key.as_str())
+            } else {
+                split_entity_id(&parent).map(|(path, _)| path)
+            };
+            if let Some(path) = parent_file {
+                if !files.contains(path) {
+                    report.orphan_contains.push(VerifyIssue {
+                        record_id: edge.id,
+                        detail: format!("parent {} not found", parent),
+                    });
+                }
+            }
+        }
+
+        // Chunks without files and embedding dimension mismatches
+        let chunks: Vec<ChunkRow> = self
+            .db
+            .query("SELECT type::string(id) AS id, file_path, array::len(embedding) AS dimension FROM chunk")
+            .await?
+            .take(0)?;
+        for chunk in chunks {
+            if !files.contains(&chunk.file_path) {
+                report.orphan_chunks.push(VerifyIssue {
+                    record_id: chunk.id,
+                    detail: format!("file {} not indexed", chunk.file_path),
+                });
+            } else if chunk.dimension != expected_dimension {
+                report.dimension_mismatches.push(VerifyIssue {
+                    record_id: chunk.id,
+                    detail: format!(
+                        "{} has {} dimensions, expected {}",
+                        chunk.file_path, chunk.dimension, expected_dimension
+                    ),
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Delete the records flagged in a verification report.
+    ///
+    /// Returns the number of records removed.
+    pub async fn prune(&self, report: &VerifyReport) -> Result<usize, KnowledgeError> {
+        let record_ids = |issues: &[&[VerifyIssue]]| -> Vec<String> {
+            issues
+                .iter()
+                .flat_map(|list| list.iter().map(|i| i.record_id.clone()))
+                .collect()
+        };
+
+        for (table, ids) in [
+            ("calls", record_ids(&[&report.dangling_calls])),
+            ("contains", record_ids(&[&report.orphan_contains])),
+            (
+                "chunk",
+                record_ids(&[&report.orphan_chunks, &report.dimension_mismatches]),
+            ),
+        ] {
+            if ids.is_empty() {
+                continue;
+            }
+            self.db
+                .query(format!(
+                    "DELETE {} WHERE type::string(id) INSIDE $ids",
+                    table
+                ))
+                .bind(("ids", ids))
+                .await?;
+        }
+
+        Ok(report.issue_count())
+    }
+
     /// List all struct entities.
     pub async fn list_structs(
         &self,
@@ -902,12 +2378,147 @@ impl KnowledgeDb {
         Ok(results)
     }
 
+    /// Current calls crossing between the files or directories `a` and
+    /// `b`, in either direction.
+    pub async fn calls_between(
+        &self,
+        a: &PathScope,
+        b: &PathScope,
+    ) -> Result<Vec<CrossingCall>, KnowledgeError> {
+        let in_scope = |param: &str, scope: &PathScope| {
+            if scope.as_str().is_empty() {
+                "true".to_string()
+            } else {
+                format!("(file_path = ${param} OR string::starts_with(file_path, ${param} + '/'))")
+            }
+        };
+        let functions: Vec<FunctionRef> = self
+            .db
+            .query(format!(
+                "SELECT name, qualified_name, file_path, start_line AS line FROM fn_node WHERE {} OR {}",
+                in_scope("a", a),
+                in_scope("b", b)
+            ))
+            .bind(("a", a.as_str().to_string()))
+            .bind(("b", b.as_str().to_string()))
+            .await?
+            .take(0)?;
+
+        let ids: Vec<String> = functions.iter().map(FunctionRef::entity_id).collect();
+        let calls: Vec<CallInfo> = self
+            .db
+            .query(format!(
+                "SELECT * FROM calls WHERE caller_id INSIDE $ids AND {}",
+                CURRENT_CALLS
+            ))
+            .bind(("ids", ids))
+            .await?
+            .take(0)?;
+
+        Ok(crossing_calls(a, b, &functions, &calls))
+    }
+
     /// List all call edges.
     pub async fn list_calls(&self) -> Result<Vec<CallInfo>, KnowledgeError> {
-        let results: Vec<CallInfo> = self.db.query("SELECT * FROM calls").await?.take(0)?;
+        let results: Vec<CallInfo> = self
+            .db
+            .query(format!("SELECT * FROM calls WHERE {}", CURRENT_CALLS))
+            .await?
+            .take(0)?;
         Ok(results)
     }
 
+    /// Replace all co-change edges with `pairs`.
+    pub async fn replace_co_changes(
+        &self,
+        pairs: &[CoChange],
+        batch_size: usize,
+    ) -> Result<(), KnowledgeError> {
+        self.db.query("DELETE co_changes").await?.check()?;
+        self.insert_batch("co_changes", pairs, batch_size).await
+    }
+
+    /// Co-change edges including `path`, or all of them, strongest first.
+    pub async fn list_co_changes(
+        &self,
+        path: Option<&str>,
+    ) -> Result<Vec<CoChange>, KnowledgeError> {
+        let filter = match path {
+            Some(_) => "WHERE file_a = $path OR file_b = $path",
+            None => "",
+        };
+        let results: Vec<CoChange> = self
+            .db
+            .query(format!(
+                "SELECT file_a, file_b, count, commits_a, commits_b, weight FROM co_changes {} ORDER BY count DESC, weight DESC",
+                filter
+            ))
+            .bind(("path", path.unwrap_or_default().to_string()))
+            .await?
+            .take(0)?;
+        Ok(results)
+    }
+
+    /// Store summaries, replacing earlier ones for the same paths.
+    pub async fn save_summaries(
+        &self,
+        summaries: &[Summary],
+        batch_size: usize,
+    ) -> Result<(), KnowledgeError> {
+        let paths: Vec<String> = summaries.iter().map(|s| s.path.clone()).collect();
+        self.delete_summaries(&paths).await?;
+        self.insert_batch("summary", summaries, batch_size).await
+    }
+
+    /// Summaries of one kind, or all of them, sorted by path.
+    pub async fn list_summaries(
+        &self,
+        kind: Option<SummaryKind>,
+    ) -> Result<Vec<Summary>, KnowledgeError> {
+        let filter = match kind {
+            Some(_) => "WHERE kind = $kind",
+            None => "",
+        };
+        let results: Vec<Summary> = self
+            .db
+            .query(format!(
+                "SELECT path, kind, summary, hash FROM summary {} ORDER BY path",
+                filter
+            ))
+            .bind(("kind", kind.unwrap_or(SummaryKind::File)))
+            .await?
+            .take(0)?;
+        Ok(results)
+    }
+
+    /// Delete the summaries of `paths`.
+    pub async fn delete_summaries(&self, paths: &[String]) -> Result<(), KnowledgeError> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        self.db
+            .query("DELETE summary WHERE path INSIDE $paths")
+            .bind(("paths", paths.to_vec()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Delete call history closed more than `retention_days` ago; with zero,
+    /// all history goes.
+    ///
+    /// Returns the number of records removed.
+    pub async fn prune_history(&self, retention_days: u64) -> Result<usize, KnowledgeError> {
+        let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
+        let removed: Vec<serde_json::Value> = self
+            .db
+            .query("DELETE calls WHERE valid_to IS NOT NONE AND valid_to <= <datetime> $cutoff RETURN BEFORE")
+            .bind(("cutoff", cutoff.to_rfc3339()))
+            .await?
+            .take(0)?;
+        Ok(removed.len())
+    }
+
     /// List all implements edges (impl -> trait).
     pub async fn list_implements(&self) -> Result<Vec<ImplementsInfo>, KnowledgeError> {
         let results: Vec<ImplementsInfo> =
@@ -915,6 +2526,169 @@ impl KnowledgeDb {
         Ok(results)
     }
 
+    /// List one page of edges of any relation, optionally only those leaving `from`.
+    ///
+    /// Edges are ordered by record ID so pages stay stable between calls.
+    /// With `as_of`, `calls` edges are those in effect at that time; other
+    /// relations keep no history and are always listed as they are now.
+    pub async fn list_edges(
+        &self,
+        relation: &str,
+        from: Option<&str>,
+        offset: usize,
+        limit: usize,
+        as_of: Option<DateTime<Utc>>,
+    ) -> Result<Vec<EdgeInfo>, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct EdgeRow {
+            id: String,
+            source: String,
+            target: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct RelateRow {
+            id: String,
+            source_table: String,
+            source_key: String,
+            target_table: String,
+            target_key: String,
+        }
+
+        if !EDGE_RELATIONS.contains(&relation) {
+            return Err(KnowledgeError::UnknownRelation(relation.to_string()));
+        }
+        if as_of.is_some() && relation != "calls" {
+            return Err(KnowledgeError::NoHistory(relation.to_string()));
+        }
+
+        let page = format!("ORDER BY id LIMIT {} START {}", limit, offset);
+        let record_table = EDGE_RECORD_TABLES.iter().find(|(t, ..)| *t == relation);
+
+        let edges = if let Some((table, from_col, to_col, _)) = record_table {
+            let mut conditions = Vec::new();
+            if from.is_some() {
+                conditions.push(format!("{}_id = $source", from_col));
+            }
+            if *table == "calls" {
+                conditions.push(calls_in_effect(as_of).to_string());
+            }
+            let filter = match conditions.is_empty() {
+                true => String::new(),
+                false => format!("WHERE {}", conditions.join(" AND ")),
+            };
+            let rows: Vec<EdgeRow> = self
+                .db
+                .query(format!(
+                    "SELECT type::string(id) AS id, {}_id AS source, {}_id AS target FROM {} {} {}",
+                    from_col, to_col, table, filter, page
+                ))
+                .bind(("source", from.unwrap_or_default().to_string()))
+                .bind(("as_of", as_of.map(|t| t.to_rfc3339())))
+                .await?
+                .take(0)?;
+            rows.into_iter()
+                .map(|r| EdgeInfo {
+                    id: r.id,
+                    relation: relation.to_string(),
+                    source: r.source,
+                    target: r.target,
+                })
+                .collect()
+        } else {
+            // `in` and `out` are record links; rebuild the `table:key` entity IDs
+            let (from_table, from_key) = from
+                .map(|id| id.split_once(':').unwrap_or(("file", id)))
+                .unwrap_or_default();
+            let filter = match from {
+                Some(_) => "WHERE in = type::thing($from_table, $from_key)",
+                None => "",
+            };
+            let rows: Vec<RelateRow> = self
+                .db
+                .query(format!(
+                    "SELECT type::string(id) AS id, record::tb(in) AS source_table, <string> record::id(in) AS source_key, record::tb(out) AS target_table, <string> record::id(out) AS target_key FROM {} {} {}",
+                    relation, filter, page
+                ))
+                .bind(("from_table", from_table.to_string()))
+                .bind(("from_key", from_key.to_string()))
+                .await?
+                .take(0)?;
+            rows.into_iter()
+                .map(|r| EdgeInfo {
+                    id: r.id,
+                    relation: relation.to_string(),
+                    source: format!("{}:{}", r.source_table, r.source_key),
+                    target: format!("{}:{}", r.target_table, r.target_key),
+                })
+                .collect()
+        };
+        Ok(edges)
+    }
+
+    /// List the entities of every node table with their common columns.
+    ///
+    /// Entities are ordered by table, then by record ID. IDs use the
+    /// `table:key` form of [`EdgeInfo`] endpoints of `RELATE` tables.
+    pub async fn list_entities(&self) -> Result<Vec<EntityInfo>, KnowledgeError> {
+        let mut entities = Vec::new();
+        for spec in &ENTITY_TABLES {
+            let rows: Vec<EntityRow> = self
+                .db
+                .query(format!(
+                    "SELECT {} FROM {} ORDER BY id",
+                    spec.columns(),
+                    spec.table
+                ))
+                .await?
+                .take(0)?;
+            entities.extend(rows.into_iter().map(|r| r.into_info(spec.kind)));
+        }
+        Ok(entities)
+    }
+
+    /// The innermost entity whose line range contains `line` (1-based) of
+    /// `path`, of any node table but `file`.
+    ///
+    /// Every table is queried in one round trip through its
+    /// `(file_path, start, end)` range index; among the candidates the one
+    /// with the smallest range wins, so a method beats its impl block.
+    pub async fn entity_at(
+        &self,
+        path: &str,
+        line: u32,
+    ) -> Result<Option<EntityInfo>, KnowledgeError> {
+        let tables: Vec<&EntityTable> =
+            ENTITY_TABLES.iter().filter(|t| t.table != "file").collect();
+        let query = tables
+            .iter()
+            .map(|spec| {
+                format!(
+                    "SELECT {} FROM {} WHERE {} = $path AND {} <= $line AND {} >= $line;",
+                    spec.columns(),
+                    spec.table,
+                    spec.file_path,
+                    spec.start_line,
+                    spec.end_line
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut response = self
+            .db
+            .query(query)
+            .bind(("path", path.to_string()))
+            .bind(("line", line))
+            .await?;
+        let mut candidates = Vec::new();
+        for (index, spec) in tables.iter().enumerate() {
+            let rows: Vec<EntityRow> = response.take(index)?;
+            candidates.extend(rows.into_iter().map(|r| r.into_info(spec.kind)));
+        }
+        Ok(innermost_entity(candidates))
+    }
+
     /// List all indexed file paths.
     pub async fn list_indexed_files(&self) -> Result<Vec<String>, KnowledgeError> {
         #[derive(serde::Deserialize)]
@@ -926,6 +2700,52 @@ impl KnowledgeDb {
         Ok(results.into_iter().map(|r| r.path).collect())
     }
 
+    /// List indexed file paths with their content hash.
+    pub async fn list_file_hashes(&self) -> Result<Vec<(String, String)>, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct FileHash {
+            path: String,
+            hash: String,
+        }
+
+        let results: Vec<FileHash> = self
+            .db
+            .query("SELECT path, hash FROM file")
+            .await?
+            .take(0)?;
+        Ok(results.into_iter().map(|r| (r.path, r.hash)).collect())
+    }
+
+    /// List indexed file paths with their detected language.
+    pub async fn list_file_languages(
+        &self,
+    ) -> Result<Vec<(String, Option<String>)>, KnowledgeError> {
+        #[derive(serde::Deserialize)]
+        struct FileLanguage {
+            path: String,
+            language: Option<String>,
+        }
+
+        let results: Vec<FileLanguage> = self
+            .db
+            .query("SELECT path, language FROM file")
+            .await?
+            .take(0)?;
+        Ok(results.into_iter().map(|r| (r.path, r.language)).collect())
+    }
+
+    /// List every chunk's location and embedding, for duplicate detection.
+    pub async fn list_chunk_embeddings(&self) -> Result<Vec<ChunkEmbedding>, KnowledgeError> {
+        let results: Vec<ChunkEmbedding> = self
+            .db
+            .query(
+                "SELECT file_path AS path, start_line, end_line, entity_id, embedding FROM chunk",
+            )
+            .await?
+            .take(0)?;
+        Ok(results)
+    }
+
     /// List all function entities (extended).
     pub async fn list_function_entities(
         &self,
@@ -933,11 +2753,83 @@ impl KnowledgeDb {
         // Select all fields except id to avoid SurrealDB Thing deserialization issues
         let results: Vec<super::ontology::nodes::FunctionEntity> = self
             .db
-            .query("SELECT name, qualified_name, file_path, start_line, end_line, signature, parent, visibility, is_async, is_unsafe, generics, parameters, return_type, doc_comment, complexity FROM fn_node")
+            .query("SELECT name, qualified_name, file_path, start_line, end_line, signature, parent, visibility, is_async, is_unsafe, generics, attributes ?? [] AS attributes, parameters, return_type, doc_comment, complexity FROM fn_node")
             .await?
             .take(0)?;
         Ok(results)
     }
+
+    /// List function entities matching metadata predicates.
+    ///
+    /// When `paths` is given, only functions in those files are considered.
+    pub async fn list_functions_matching(
+        &self,
+        filter: &EntityFilter,
+        paths: Option<&[String]>,
+        limit: Option<usize>,
+    ) -> Result<Vec<super::ontology::nodes::FunctionEntity>, KnowledgeError> {
+        let (mut clause, bindings) = filter.to_surql();
+        if paths.is_some() {
+            clause.push_str(" AND file_path IN $paths");
+        }
+        let mut query = format!(
+            "SELECT name, qualified_name, file_path, start_line, end_line, signature, parent, visibility, is_async, is_unsafe, generics, attributes ?? [] AS attributes, parameters, return_type, doc_comment, complexity FROM fn_node WHERE {} ORDER BY file_path, start_line",
+            clause
+        );
+        if let Some(limit) = limit {
+            query.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let mut request = self.db.query(&query);
+        for (name, value) in bindings {
+            request = request.bind((name, value));
+        }
+        if let Some(paths) = paths {
+            request = request.bind(("paths", paths.to_vec()));
+        }
+        let results: Vec<super::ontology::nodes::FunctionEntity> = request.await?.take(0)?;
+        Ok(results)
+    }
+
+    /// List function entities in a file that overlap the given line range.
+    pub async fn list_functions_in_range(
+        &self,
+        path: &str,
+        start_line: u32,
+        end_line: u32,
+    ) -> Result<Vec<super::ontology::nodes::FunctionEntity>, KnowledgeError> {
+        let results: Vec<super::ontology::nodes::FunctionEntity> = self
+            .db
+            .query("SELECT name, qualified_name, file_path, start_line, end_line, signature, parent, visibility, is_async, is_unsafe, generics, attributes ?? [] AS attributes, parameters, return_type, doc_comment, complexity FROM fn_node WHERE file_path = $path AND start_line <= $end AND end_line >= $start")
+            .bind(("path", path.to_string()))
+            .bind(("start", start_line))
+            .bind(("end", end_line))
+            .await?
+            .take(0)?;
+        Ok(results)
+    }
+}
+
+/// A chunk's location and embedding.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ChunkEmbedding {
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub entity_id: Option<String>,
+    pub embedding: Vec<f32>,
+}
+
+impl ChunkEmbedding {
+    /// Where the chunk is.
+    pub fn location(&self) -> super::dupes::ChunkLocation {
+        super::dupes::ChunkLocation {
+            path: self.path.clone(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+            entity_id: self.entity_id.clone(),
+        }
+    }
 }
 
 /// Information about a call edge for API responses.
@@ -953,6 +2845,14 @@ pub struct CallInfo {
     pub callee_name: String,
     #[serde(default)]
     pub call_type: super::ontology::edges::CallType,
+    #[serde(default)]
+    pub resolution: CallResolution,
+    #[serde(default = "default_call_confidence")]
+    pub confidence: f32,
+}
+
+fn default_call_confidence() -> f32 {
+    CallResolution::Resolved.default_confidence()
 }
 
 impl Default for CallInfo {
@@ -963,6 +2863,8 @@ impl Default for CallInfo {
             caller_name: String::new(),
             callee_name: String::new(),
             call_type: super::ontology::edges::CallType::Direct,
+            resolution: CallResolution::Resolved,
+            confidence: default_call_confidence(),
         }
     }
 }
@@ -976,6 +2878,71 @@ pub struct ImplementsInfo {
     pub trait_id: String,
 }
 
+/// An entity of any node table, as listed by [`KnowledgeDb::list_entities`].
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct EntityInfo {
+    /// Record ID (e.g. `fn_node:abc123`)
+    pub id: String,
+    /// Record key, which record-table edges such as `calls` refer to
+    pub key: String,
+    /// Entity kind (`file`, `function`, `struct`, ...)
+    pub kind: String,
+    /// Short name
+    pub name: String,
+    /// Fully qualified name, or the path for files
+    pub qualified_name: Option<String>,
+    /// File defining the entity
+    pub file_path: Option<String>,
+    /// First line
+    pub start_line: Option<u32>,
+    /// Last line
+    pub end_line: Option<u32>,
+    /// Visibility, for entities that record it
+    pub visibility: Option<String>,
+    /// Signature, for functions
+    pub signature: Option<String>,
+    /// Documentation comment
+    pub doc_comment: Option<String>,
+}
+
+/// An edge of any relation, as listed by [`KnowledgeDb::list_edges`].
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct EdgeInfo {
+    /// Record ID of the edge itself
+    pub id: String,
+    /// Relation table (e.g. `contains`, `imports`)
+    pub relation: String,
+    /// Source entity ID
+    pub source: String,
+    /// Target entity ID
+    pub target: String,
+}
+
+/// Line counts of the indexed files of one language.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct LanguageStats {
+    /// Language name (e.g. `rust`), or the extension for other files
+    pub language: String,
+    /// Number of files
+    #[serde(default)]
+    pub files: u64,
+    /// Total lines
+    #[serde(default)]
+    pub lines: u64,
+    /// Lines holding code
+    #[serde(default)]
+    pub code: u64,
+    /// Lines holding only comments
+    #[serde(default)]
+    pub comments: u64,
+    /// Empty lines
+    #[serde(default)]
+    pub blanks: u64,
+    /// Functions defined in files of this language
+    #[serde(default)]
+    pub functions: u64,
+}
+
 /// Extended statistics for the rich ontology.
 #[derive(Debug, Clone, Default)]
 pub struct ExtendedIndexStats {
@@ -986,7 +2953,407 @@ pub struct ExtendedIndexStats {
     pub impls: usize,
     pub enums: usize,
     pub constants: usize,
+    pub schemas: usize,
+    pub operations: usize,
     pub chunks: usize,
     pub calls: usize,
     pub implements: usize,
+    pub consumes: usize,
+    pub produces: usize,
+    /// Line counts per language, most code first
+    pub languages: Vec<LanguageStats>,
+}
+
+/// Whether an API entity takes a schema as input or returns it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiRelation {
+    Consumes,
+    Produces,
+}
+
+/// An operation or resolver that uses a schema type.
+#[derive(Debug, Clone)]
+pub struct ApiUsage {
+    /// `operation:path:Query::user` or `function:path:name`
+    pub entity_id: String,
+    pub name: String,
+    pub relation: ApiRelation,
+}
+
+impl ApiUsage {
+    /// Returns true if this is a resolver function rather than a schema operation.
+    pub fn is_resolver(&self) -> bool {
+        self.entity_id.starts_with("function:")
+    }
+}
+
+/// A single record flagged by [`KnowledgeDb::verify`].
+#[derive(Debug, Clone)]
+pub struct VerifyIssue {
+    /// Record ID of the offending row.
+    pub record_id: String,
+    /// Human-readable description of the problem.
+    pub detail: String,
+}
+
+/// Result of a knowledge graph consistency check.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Call edges whose caller or callee no longer exists.
+    pub dangling_calls: Vec<VerifyIssue>,
+    /// Contains edges whose parent no longer exists.
+    pub orphan_contains: Vec<VerifyIssue>,
+    /// Chunks whose source file is not indexed.
+    pub orphan_chunks: Vec<VerifyIssue>,
+    /// Chunks whose embedding size differs from the embedder's dimension.
+    pub dimension_mismatches: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    /// Total number of flagged records.
+    pub fn issue_count(&self) -> usize {
+        self.dangling_calls.len()
+            + self.orphan_contains.len()
+            + self.orphan_chunks.len()
+            + self.dimension_mismatches.len()
+    }
+
+    /// Whether the graph passed every check.
+    pub fn is_clean(&self) -> bool {
+        self.issue_count() == 0
+    }
+}
+
+/// Build an edge record with `<from>_id` and `<to>_id` columns.
+///
+/// Named edges also store `<from>_name` and `<to>_name`, the innermost name
+/// of each ID's qualified name (format: `kind:path:qualified_name`), for
+/// lookups by name in any language.
+fn edge_record(
+    from_col: &str,
+    from_id: &str,
+    to_col: &str,
+    to_id: &str,
+    named: bool,
+) -> serde_json::Value {
+    let mut record = serde_json::Map::new();
+    record.insert(format!("{}_id", from_col), from_id.into());
+    record.insert(format!("{}_id", to_col), to_id.into());
+    if named {
+        let name = |id: &str| QualifiedName::from_entity_id(id).name().to_string();
+        record.insert(format!("{}_name", from_col), name(from_id).into());
+        record.insert(format!("{}_name", to_col), name(to_id).into());
+    }
+    serde_json::Value::Object(record)
+}
+
+/// Build a call edge record, tagged with the resolution implied by the callee ID.
+fn call_record(caller_id: &str, callee_id: &str) -> serde_json::Value {
+    let mut record = edge_record("caller", caller_id, "callee", callee_id, true);
+    let resolution = CallResolution::for_callee_id(callee_id);
+    record["resolution"] = resolution.as_str().into();
+    record["confidence"] = resolution.default_confidence().into();
+    record
+}
+
+/// Returns true if `function` looks like the resolver for `operation`.
+///
+/// Matching ignores case and underscores, so `createUser`, `create_user`,
+/// `resolve_create_user` and `createUserResolver` all match.
+fn is_resolver_name(function: &str, operation: &str) -> bool {
+    let normalize = |s: &str| s.replace('_', "").to_lowercase();
+    let function = normalize(function);
+    let operation = normalize(operation);
+
+    function == operation
+        || function.strip_prefix("resolve") == Some(operation.as_str())
+        || function.strip_suffix("resolver") == Some(operation.as_str())
+}
+
+/// Pairs of (struct ID, interface ID) for Go structs satisfying interfaces.
+///
+/// `interfaces` are (ID, method names), `structs` are (file, name) and
+/// `methods` are (file, receiver, name). Methods and structs belong to the
+/// same type when they share the package directory and the receiver names
+/// the struct, with or without a pointer. Interfaces without methods are
+/// satisfied by everything and skipped.
+fn go_implementations(
+    interfaces: &[(String, Vec<String>)],
+    structs: &[(String, String)],
+    methods: &[(String, String, String)],
+) -> Vec<(String, String)> {
+    let package = |file: &str| file.rsplit_once('/').map_or("", |(dir, _)| dir).to_string();
+
+    let mut method_sets: HashMap<(String, String), HashSet<&str>> = HashMap::new();
+    for (file, receiver, name) in methods {
+        let receiver = receiver.trim_start_matches('*');
+        let receiver = receiver.split('[').next().unwrap_or(receiver);
+        method_sets
+            .entry((package(file), receiver.to_string()))
+            .or_default()
+            .insert(name.as_str());
+    }
+
+    let mut links = Vec::new();
+    for (file, name) in structs {
+        let Some(method_set) = method_sets.get(&(package(file), name.clone())) else {
+            continue;
+        };
+        for (interface_id, required) in interfaces {
+            if !required.is_empty() && required.iter().all(|m| method_set.contains(m.as_str())) {
+                links.push((format!("struct:{}:{}", file, name), interface_id.clone()));
+            }
+        }
+    }
+    links.sort();
+    links
+}
+
+/// The entity with the narrowest line range, preferring the one starting
+/// later on ties, so the innermost of nested entities wins.
+pub(crate) fn innermost_entity(entities: Vec<EntityInfo>) -> Option<EntityInfo> {
+    entities.into_iter().min_by_key(|e| {
+        let start = e.start_line.unwrap_or(0);
+        let end = e.end_line.unwrap_or(start);
+        (end.saturating_sub(start), std::cmp::Reverse(start))
+    })
+}
+
+/// Split an entity ID (`kind:path:name`) into its file path and name.
+///
+/// Returns `None` for unresolved references such as `function:?:name`.
+pub(crate) fn split_entity_id(id: &str) -> Option<(&str, Option<&str>)> {
+    let (_, rest) = id.split_once(':')?;
+    let (path, name) = match rest.split_once(':') {
+        Some((path, name)) => (path, Some(name)),
+        None => (rest, None),
+    };
+    if path == "?" || path.is_empty() {
+        None
+    } else {
+        Some((path, name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_only_calls_have_history() {
+        let db = KnowledgeDb::open_in_memory().await.unwrap();
+        db.initialize_schema().await.unwrap();
+        let as_of = Some(Utc::now());
+
+        assert!(db.list_edges("calls", None, 0, 10, as_of).await.is_ok());
+        assert!(matches!(
+            db.list_edges("contains", None, 0, 10, as_of).await,
+            Err(KnowledgeError::NoHistory(relation)) if relation == "contains"
+        ));
+        assert!(db.list_edges("contains", None, 0, 10, None).await.is_ok());
+    }
+
+    async fn count_rows(db: &KnowledgeDb, table: &str) -> usize {
+        let rows: Vec<serde_json::Value> = db
+            .db
+            .query(format!("SELECT * FROM {}", table))
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        rows.len()
+    }
+
+    #[tokio::test]
+    async fn test_graphql_resolver_links() {
+        let db = KnowledgeDb::open_in_memory().await.unwrap();
+        // Graphs from before GraphQL indexing defined the edge tables as relations
+        db.db
+            .query("DEFINE TABLE maps_to TYPE RELATION")
+            .await
+            .unwrap();
+        db.initialize_schema().await.unwrap();
+        db.db
+            .query("CREATE operation_node CONTENT { name: 'user', path: 'Query::user', file_path: 'schema.graphql', line: 2 }")
+            .await
+            .unwrap();
+        db.db
+            .query("CREATE fn_node CONTENT { name: 'user', qualified_name: 'QueryRoot.user', file_path: 'src/user.rs', start_line: 1, end_line: 3 }")
+            .await
+            .unwrap();
+
+        // Unrelated changes leave linked operations alone
+        assert_eq!(
+            db.link_graphql_resolvers(&["src/user.rs".to_string()])
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            db.link_graphql_resolvers(&["README.md".to_string()])
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(count_rows(&db, "maps_to").await, 1);
+
+        db.remove_file("src/user.rs").await.unwrap();
+        assert_eq!(count_rows(&db, "maps_to").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_language_stats_where() {
+        let db = KnowledgeDb::open_in_memory().await.unwrap();
+        db.initialize_schema().await.unwrap();
+        for path in ["src/main.rs", "src/lib.rs", "secrets/keys.rs"] {
+            let mut file = FileNode::new(path, "hash", 10);
+            file.code_lines = 5;
+            db.upsert_file(&file).await.unwrap();
+        }
+
+        let all = db.language_stats().await.unwrap();
+        assert_eq!((all[0].files, all[0].code), (3, 15));
+
+        let visible = db
+            .language_stats_where(|path| !path.starts_with("secrets/"))
+            .await
+            .unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!((visible[0].files, visible[0].code), (2, 10));
+    }
+
+    #[test]
+    fn test_innermost_entity() {
+        let entity = |kind: &str, start: u32, end: u32| EntityInfo {
+            kind: kind.to_string(),
+            start_line: Some(start),
+            end_line: Some(end),
+            ..EntityInfo::default()
+        };
+        let innermost = innermost_entity(vec![
+            entity("impl", 10, 80),
+            entity("function", 20, 30),
+            entity("struct", 1, 100),
+        ]);
+        assert_eq!(innermost.unwrap().kind, "function");
+
+        // A one-line constant inside a function
+        let innermost = innermost_entity(vec![entity("function", 20, 30), entity("const", 25, 25)]);
+        assert_eq!(innermost.unwrap().kind, "const");
+        assert!(innermost_entity(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_edge_record() {
+        let record = edge_record(
+            "caller",
+            "function:src/a.rs:run",
+            "callee",
+            "function:?:helper",
+            true,
+        );
+        assert_eq!(record["caller_id"], "function:src/a.rs:run");
+        assert_eq!(record["callee_name"], "helper");
+
+        // Qualified names reduce to the innermost name in any language
+        let record = edge_record(
+            "caller",
+            "function:src/App.java:App.main",
+            "callee",
+            "function:src/lib.rs:Task::new",
+            true,
+        );
+        assert_eq!(record["caller_name"], "main");
+        assert_eq!(record["callee_name"], "new");
+
+        let record = edge_record("impl", "impl:src/a.rs:Foo", "trait", "trait:?:Bar", false);
+        assert_eq!(record["trait_id"], "trait:?:Bar");
+        assert!(record.get("impl_name").is_none());
+    }
+
+    #[test]
+    fn test_call_record_resolution() {
+        let record = call_record("function:src/a.rs:run", "function:src/b.rs:helper");
+        assert_eq!(record["resolution"], "resolved");
+        assert_eq!(record["confidence"], 1.0);
+        assert_eq!(record["callee_name"], "helper");
+
+        let record = call_record("function:src/a.rs:run", "function:?:helper");
+        assert_eq!(record["resolution"], "unresolved");
+        assert_eq!(record["confidence"], 0.0);
+    }
+
+    #[test]
+    fn test_go_implementations() {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let interfaces = vec![
+            (
+                "trait:io/io.go:ReadCloser".to_string(),
+                strings(&["Read", "Close"]),
+            ),
+            ("trait:io/io.go:Any".to_string(), Vec::new()),
+        ];
+        let structs = vec![
+            ("pkg/file.go".to_string(), "File".to_string()),
+            ("pkg/buf.go".to_string(), "Buffer".to_string()),
+            ("other/file.go".to_string(), "File".to_string()),
+        ];
+        let method = |file: &str, receiver: &str, name: &str| {
+            (file.to_string(), receiver.to_string(), name.to_string())
+        };
+        let methods = vec![
+            // Methods may live in another file of the package
+            method("pkg/file.go", "*File", "Read"),
+            method("pkg/file_close.go", "File", "Close"),
+            method("pkg/buf.go", "*Buffer", "Read"),
+            // Same name, different package
+            method("other/file.go", "File", "Read"),
+        ];
+
+        assert_eq!(
+            go_implementations(&interfaces, &structs, &methods),
+            vec![(
+                "struct:pkg/file.go:File".to_string(),
+                "trait:io/io.go:ReadCloser".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_split_entity_id() {
+        assert_eq!(
+            split_entity_id("function:src/lib.rs:Foo::bar"),
+            Some(("src/lib.rs", Some("Foo::bar")))
+        );
+        assert_eq!(
+            split_entity_id("file:src/main.rs"),
+            Some(("src/main.rs", None))
+        );
+        assert_eq!(split_entity_id("function:?:println"), None);
+        assert_eq!(split_entity_id("orphan"), None);
+    }
+
+    #[test]
+    fn test_is_resolver_name() {
+        assert!(is_resolver_name("createUser", "createUser"));
+        assert!(is_resolver_name("create_user", "createUser"));
+        assert!(is_resolver_name("resolve_create_user", "createUser"));
+        assert!(is_resolver_name("CreateUserResolver", "createUser"));
+        assert!(!is_resolver_name("create_user_record", "createUser"));
+    }
+
+    #[test]
+    fn test_verify_report_counts() {
+        let issue = VerifyIssue {
+            record_id: "chunk:1".to_string(),
+            detail: String::new(),
+        };
+        let mut report = VerifyReport::default();
+        assert!(report.is_clean());
+
+        report.orphan_chunks.push(issue.clone());
+        report.dangling_calls.push(issue);
+        assert_eq!(report.issue_count(), 2);
+        assert!(!report.is_clean());
+    }
 }
@@ -0,0 +1,586 @@
+//! Documentation site generated from the knowledge graph.
+//!
+//! Every indexed file that defines entities gets a page listing them in
+//! source order with signatures and doc comments, a mermaid graph of the
+//! calls its functions make, and links to the pages of callers and callees.
+//! An index page lists the modules by directory.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::db::{EdgeInfo, EntityInfo};
+use super::error::KnowledgeError;
+use super::ontology::nodes::Language;
+
+/// Most call edges drawn in a module's graph, to keep it readable.
+const MAX_GRAPH_EDGES: usize = 60;
+
+/// Loads mermaid on HTML pages to render the call graphs.
+const MERMAID_SCRIPT: &str = r#"<script type="module">
+import mermaid from "https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs";
+mermaid.initialize({ startOnLoad: true });
+</script>"#;
+
+/// Stylesheet of HTML pages.
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:60rem;margin:2rem auto;padding:0 1rem;line-height:1.5}pre{background:#f5f5f5;padding:.75rem;overflow-x:auto}h3{margin-top:2rem}.meta{color:#666}";
+
+/// A format the documentation site can be generated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocsFormat {
+    /// Markdown pages with mermaid code blocks
+    Markdown,
+    /// Standalone HTML pages
+    Html,
+}
+
+impl DocsFormat {
+    /// Name used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+        }
+    }
+
+    /// File extension of pages in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for DocsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DocsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" | "htm" => Ok(Self::Html),
+            other => Err(format!(
+                "unknown docs format '{}' (expected markdown or html)",
+                other
+            )),
+        }
+    }
+}
+
+/// Number of pages and entities written to a documentation site.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocsSummary {
+    /// Module pages, not counting the index
+    pub pages: usize,
+    pub entities: usize,
+}
+
+/// One page of the site: an indexed file and the entities it defines.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleDoc {
+    /// File path, relative to the project root
+    pub path: String,
+    /// Entities defined in the file, in source order
+    pub entities: Vec<EntityInfo>,
+}
+
+/// Group entities into modules by file, sorted by path.
+///
+/// File and document entities are left out, as are files defining nothing.
+pub fn group_modules(entities: &[EntityInfo]) -> Vec<ModuleDoc> {
+    let mut modules: BTreeMap<&str, Vec<EntityInfo>> = BTreeMap::new();
+    for entity in entities
+        .iter()
+        .filter(|e| e.kind != "file" && e.kind != "document")
+    {
+        if let Some(path) = entity.file_path.as_deref() {
+            modules.entry(path).or_default().push(entity.clone());
+        }
+    }
+
+    modules
+        .into_iter()
+        .map(|(path, mut entities)| {
+            entities.sort_by(|a, b| {
+                (a.start_line, &a.name, &a.kind).cmp(&(b.start_line, &b.name, &b.kind))
+            });
+            ModuleDoc {
+                path: path.to_string(),
+                entities,
+            }
+        })
+        .collect()
+}
+
+/// Write the site for `modules` to `dir`, linking calls from `edges`.
+///
+/// Edge endpoints must be entity IDs (see
+/// [`super::export::normalize_edges`]); only `calls` edges are used. Pages of
+/// an earlier run are overwritten but not removed.
+pub fn write_site(
+    dir: &Path,
+    format: DocsFormat,
+    modules: &[ModuleDoc],
+    edges: &[EdgeInfo],
+) -> Result<DocsSummary, KnowledgeError> {
+    let site = Site::new(format, modules, edges);
+    let io_err = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| KnowledgeError::Io { path, source }
+    };
+
+    std::fs::create_dir_all(dir).map_err(io_err(dir))?;
+    let index = dir.join(format!("index.{}", format.extension()));
+    std::fs::write(&index, site.render_index()).map_err(io_err(&index))?;
+
+    let mut summary = DocsSummary::default();
+    for module in modules {
+        let page = dir.join(page_file(&module.path, format));
+        std::fs::write(&page, site.render_module(module)).map_err(io_err(&page))?;
+        summary.pages += 1;
+        summary.entities += module.entities.len();
+    }
+    Ok(summary)
+}
+
+/// File name of a module's page: its path flattened into one name.
+pub fn page_file(path: &str, format: DocsFormat) -> String {
+    format!(
+        "{}.{}",
+        path.trim_start_matches("./").replace(['/', '\\'], "__"),
+        format.extension()
+    )
+}
+
+/// Anchor of an entity on its module's page.
+fn anchor(entity: &EntityInfo) -> String {
+    let name: String = entity
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!(
+        "{}-{}-{}",
+        entity.kind,
+        name,
+        entity.start_line.unwrap_or(0)
+    )
+}
+
+/// Display name of a call target that isn't an indexed entity, e.g.
+/// `unwrap` for the unresolved target `:?:unwrap`.
+fn target_name(id: &str) -> &str {
+    id.rsplit(':').next().unwrap_or(id)
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Cross-reference tables shared by all pages.
+struct Site<'a> {
+    format: DocsFormat,
+    modules: &'a [ModuleDoc],
+    /// Entity ID to its module path and entity
+    entities: HashMap<&'a str, (&'a str, &'a EntityInfo)>,
+    /// Caller ID to callee IDs
+    calls: HashMap<&'a str, Vec<&'a str>>,
+    /// Callee ID to caller IDs
+    callers: HashMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> Site<'a> {
+    fn new(format: DocsFormat, modules: &'a [ModuleDoc], edges: &'a [EdgeInfo]) -> Self {
+        let entities = modules
+            .iter()
+            .flat_map(|m| {
+                m.entities
+                    .iter()
+                    .map(|e| (e.id.as_str(), (m.path.as_str(), e)))
+            })
+            .collect();
+
+        let mut calls: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for edge in edges.iter().filter(|e| e.relation == "calls") {
+            calls.entry(&edge.source).or_default().push(&edge.target);
+            callers.entry(&edge.target).or_default().push(&edge.source);
+        }
+        for ids in calls.values_mut().chain(callers.values_mut()) {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+
+        Self {
+            format,
+            modules,
+            entities,
+            calls,
+            callers,
+        }
+    }
+
+    /// Link to an entity from the page of `module`, or its bare name if it
+    /// isn't indexed.
+    fn link(&self, module: &str, id: &str) -> String {
+        let Some((path, entity)) = self.entities.get(id) else {
+            return match self.format {
+                DocsFormat::Markdown => format!("`{}`", target_name(id)),
+                DocsFormat::Html => format!("<code>{}</code>", html_escape(target_name(id))),
+            };
+        };
+
+        let page = if *path == module {
+            String::new()
+        } else {
+            page_file(path, self.format)
+        };
+        match self.format {
+            DocsFormat::Markdown => format!("[`{}`]({}#{})", entity.name, page, anchor(entity)),
+            DocsFormat::Html => format!(
+                "<a href=\"{}#{}\"><code>{}</code></a>",
+                html_escape(&page),
+                anchor(entity),
+                html_escape(&entity.name)
+            ),
+        }
+    }
+
+    fn links(&self, module: &str, ids: Option<&Vec<&str>>) -> Option<String> {
+        let ids = ids.filter(|ids| !ids.is_empty())?;
+        Some(
+            ids.iter()
+                .map(|id| self.link(module, id))
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+
+    /// Mermaid graph of the calls made by the module's entities.
+    fn call_graph(&self, module: &ModuleDoc) -> Option<String> {
+        let mut nodes: Vec<String> = Vec::new();
+        let mut node = |id: &str| match nodes.iter().position(|n| n == id) {
+            Some(i) => i,
+            None => {
+                nodes.push(id.to_string());
+                nodes.len() - 1
+            }
+        };
+
+        let mut edges = Vec::new();
+        for entity in &module.entities {
+            for callee in self.calls.get(entity.id.as_str()).into_iter().flatten() {
+                edges.push((node(&entity.id), node(callee)));
+            }
+        }
+        if edges.is_empty() {
+            return None;
+        }
+        let truncated = edges.len() > MAX_GRAPH_EDGES;
+        edges.truncate(MAX_GRAPH_EDGES);
+
+        let mut graph = String::from("graph LR\n");
+        for (i, id) in nodes.iter().enumerate() {
+            let label = match self.entities.get(id.as_str()) {
+                Some((path, entity)) if *path == module.path => entity.name.clone(),
+                Some((path, entity)) => format!("{}<br/>{}", entity.name, path),
+                None => target_name(id).to_string(),
+            };
+            graph.push_str(&format!("  n{}[\"{}\"]\n", i, label.replace('"', "#quot;")));
+        }
+        for (from, to) in edges {
+            graph.push_str(&format!("  n{} --> n{}\n", from, to));
+        }
+        if truncated {
+            graph.push_str(&format!(
+                "  more[\"... first {} calls shown\"]\n",
+                MAX_GRAPH_EDGES
+            ));
+        }
+        Some(graph)
+    }
+
+    fn render_index(&self) -> String {
+        let mut by_dir: BTreeMap<&str, Vec<&ModuleDoc>> = BTreeMap::new();
+        for module in self.modules {
+            let dir = module.path.rsplit_once('/').map_or(".", |(dir, _)| dir);
+            by_dir.entry(dir).or_default().push(module);
+        }
+
+        match self.format {
+            DocsFormat::Markdown => {
+                let mut md = String::from("# Architecture\n\n");
+                for (dir, modules) in by_dir {
+                    md.push_str(&format!("## `{}`\n\n", dir));
+                    for module in modules {
+                        md.push_str(&format!(
+                            "- [`{}`]({}) - {} entities\n",
+                            module.path,
+                            page_file(&module.path, self.format),
+                            module.entities.len()
+                        ));
+                    }
+                    md.push('\n');
+                }
+                md
+            }
+            DocsFormat::Html => {
+                let mut body = String::from("<h1>Architecture</h1>\n");
+                for (dir, modules) in by_dir {
+                    body.push_str(&format!(
+                        "<h2><code>{}</code></h2>\n<ul>\n",
+                        html_escape(dir)
+                    ));
+                    for module in modules {
+                        body.push_str(&format!(
+                            "<li><a href=\"{}\"><code>{}</code></a> - {} entities</li>\n",
+                            html_escape(&page_file(&module.path, self.format)),
+                            html_escape(&module.path),
+                            module.entities.len()
+                        ));
+                    }
+                    body.push_str("</ul>\n");
+                }
+                html_page("Architecture", &body)
+            }
+        }
+    }
+
+    fn render_module(&self, module: &ModuleDoc) -> String {
+        match self.format {
+            DocsFormat::Markdown => self.render_markdown(module),
+            DocsFormat::Html => self.render_html(module),
+        }
+    }
+
+    fn render_markdown(&self, module: &ModuleDoc) -> String {
+        let language = code_language(&module.path);
+        let mut md = format!("# `{}`\n\n[Index](index.md)\n\n", module.path);
+
+        if let Some(graph) = self.call_graph(module) {
+            md.push_str(&format!("## Call graph\n\n```mermaid\n{}```\n\n", graph));
+        }
+
+        md.push_str("## Entities\n\n");
+        for entity in &module.entities {
+            md.push_str(&format!(
+                "<a id=\"{}\"></a>\n\n### {} `{}`\n\n",
+                anchor(entity),
+                entity.kind,
+                entity.name
+            ));
+            md.push_str(&format!("{}\n\n", meta_line(module, entity)));
+            if let Some(signature) = entity.signature.as_deref().filter(|s| !s.is_empty()) {
+                md.push_str(&format!("```{}\n{}\n```\n\n", language, signature.trim()));
+            }
+            if let Some(doc) = entity.doc_comment.as_deref().filter(|d| !d.is_empty()) {
+                md.push_str(&format!("{}\n\n", doc.trim()));
+            }
+            let id = entity.id.as_str();
+            if let Some(calls) = self.links(&module.path, self.calls.get(id)) {
+                md.push_str(&format!("**Calls:** {}\n\n", calls));
+            }
+            if let Some(callers) = self.links(&module.path, self.callers.get(id)) {
+                md.push_str(&format!("**Called by:** {}\n\n", callers));
+            }
+        }
+        md
+    }
+
+    fn render_html(&self, module: &ModuleDoc) -> String {
+        let mut body = format!(
+            "<p><a href=\"index.html\">Index</a></p>\n<h1><code>{}</code></h1>\n",
+            html_escape(&module.path)
+        );
+
+        if let Some(graph) = self.call_graph(module) {
+            body.push_str(&format!(
+                "<h2>Call graph</h2>\n<pre class=\"mermaid\">\n{}</pre>\n",
+                html_escape(&graph)
+            ));
+        }
+
+        body.push_str("<h2>Entities</h2>\n");
+        for entity in &module.entities {
+            body.push_str(&format!(
+                "<h3 id=\"{}\">{} <code>{}</code></h3>\n<p class=\"meta\">{}</p>\n",
+                anchor(entity),
+                html_escape(&entity.kind),
+                html_escape(&entity.name),
+                html_escape(&meta_line(module, entity))
+            ));
+            if let Some(signature) = entity.signature.as_deref().filter(|s| !s.is_empty()) {
+                body.push_str(&format!(
+                    "<pre><code>{}</code></pre>\n",
+                    html_escape(signature.trim())
+                ));
+            }
+            if let Some(doc) = entity.doc_comment.as_deref().filter(|d| !d.is_empty()) {
+                body.push_str(&format!("<p>{}</p>\n", html_escape(doc.trim())));
+            }
+            let id = entity.id.as_str();
+            if let Some(calls) = self.links(&module.path, self.calls.get(id)) {
+                body.push_str(&format!("<p><strong>Calls:</strong> {}</p>\n", calls));
+            }
+            if let Some(callers) = self.links(&module.path, self.callers.get(id)) {
+                body.push_str(&format!("<p><strong>Called by:</strong> {}</p>\n", callers));
+            }
+        }
+        html_page(&module.path, &format!("{}{}\n", body, MERMAID_SCRIPT))
+    }
+}
+
+/// Location and visibility of an entity, e.g. `src/lib.rs:10-20 · pub`.
+fn meta_line(module: &ModuleDoc, entity: &EntityInfo) -> String {
+    let mut meta = module.path.clone();
+    match (entity.start_line, entity.end_line) {
+        (Some(start), Some(end)) if end > start => meta.push_str(&format!(":{}-{}", start, end)),
+        (Some(start), _) => meta.push_str(&format!(":{}", start)),
+        _ => {}
+    }
+    if let Some(visibility) = entity.visibility.as_deref().filter(|v| !v.is_empty()) {
+        meta.push_str(&format!(" · {}", visibility));
+    }
+    meta
+}
+
+/// Code block language for signatures from the file at `path`.
+fn code_language(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    match Language::from_extension(extension) {
+        Language::Unknown => "",
+        language => language.as_str(),
+    }
+}
+
+fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        html_escape(title),
+        HTML_STYLE,
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(id: &str, name: &str, path: &str, line: u32) -> EntityInfo {
+        EntityInfo {
+            id: id.to_string(),
+            kind: "function".to_string(),
+            name: name.to_string(),
+            file_path: Some(path.to_string()),
+            start_line: Some(line),
+            end_line: Some(line + 5),
+            signature: Some(format!("fn {}()", name)),
+            ..Default::default()
+        }
+    }
+
+    fn call(source: &str, target: &str) -> EdgeInfo {
+        EdgeInfo {
+            id: format!("calls:{}", source),
+            relation: "calls".to_string(),
+            source: source.to_string(),
+            target: target.to_string(),
+        }
+    }
+
+    fn entities() -> Vec<EntityInfo> {
+        vec![
+            EntityInfo {
+                id: "file:main".to_string(),
+                kind: "file".to_string(),
+                name: "main.rs".to_string(),
+                file_path: Some("src/main.rs".to_string()),
+                ..Default::default()
+            },
+            function("fn_node:run", "run", "src/main.rs", 20),
+            function("fn_node:main", "main", "src/main.rs", 1),
+            function("fn_node:load", "load", "src/config/mod.rs", 3),
+        ]
+    }
+
+    #[test]
+    fn test_group_modules() {
+        let modules = group_modules(&entities());
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[0].path, "src/config/mod.rs");
+        let main: Vec<&str> = modules[1]
+            .entities
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(main, vec!["main", "run"]);
+    }
+
+    #[test]
+    fn test_write_markdown_site() {
+        let dir = tempfile::tempdir().unwrap();
+        let modules = group_modules(&entities());
+        let edges = vec![
+            call("fn_node:main", "fn_node:run"),
+            call("fn_node:run", "fn_node:load"),
+            call("fn_node:run", ":?:unwrap"),
+        ];
+
+        let summary = write_site(dir.path(), DocsFormat::Markdown, &modules, &edges).unwrap();
+        assert_eq!(
+            summary,
+            DocsSummary {
+                pages: 2,
+                entities: 3
+            }
+        );
+
+        let index = std::fs::read_to_string(dir.path().join("index.md")).unwrap();
+        assert!(index.contains("[`src/main.rs`](src__main.rs.md)"));
+
+        let page = std::fs::read_to_string(dir.path().join("src__main.rs.md")).unwrap();
+        assert!(page.contains("```mermaid\ngraph LR"));
+        assert!(page.contains("```rust\nfn main()\n```"));
+        assert!(page.contains("**Calls:** [`run`](#function-run-20)"));
+        assert!(page.contains("[`load`](src__config__mod.rs.md#function-load-3)"));
+        assert!(page.contains("`unwrap`"));
+        assert!(page.contains("**Called by:** [`main`](#function-main-1)"));
+    }
+
+    #[test]
+    fn test_write_html_site() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut entities = entities();
+        entities[1].doc_comment = Some("Runs <everything>".to_string());
+        let modules = group_modules(&entities);
+
+        write_site(dir.path(), DocsFormat::Html, &modules, &[]).unwrap();
+        let page = std::fs::read_to_string(dir.path().join("src__main.rs.html")).unwrap();
+        assert!(page.contains("<h3 id=\"function-run-20\">"));
+        assert!(page.contains("Runs &lt;everything&gt;"));
+        assert!(!page.contains("class=\"mermaid\""));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("md".parse::<DocsFormat>(), Ok(DocsFormat::Markdown));
+        assert_eq!("HTML".parse::<DocsFormat>(), Ok(DocsFormat::Html));
+        assert!("pdf".parse::<DocsFormat>().is_err());
+    }
+}
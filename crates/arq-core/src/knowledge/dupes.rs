@@ -0,0 +1,172 @@
+//! Duplicate code detection via embedding similarity.
+//!
+//! Chunks in different files whose embeddings are nearly identical are
+//! likely copy-paste duplicates. Each chunk's nearest neighbours come from
+//! the HNSW index used by semantic search; pairs above the similarity
+//! threshold are then grouped into clusters of mutually duplicated code.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::models::SearchResult;
+
+/// Nearest neighbours in other files kept per chunk when looking for duplicates.
+pub const DUPES_NEIGHBORS: usize = 5;
+
+/// Candidates fetched per chunk. The chunk itself and others in its file
+/// come back too and are dropped, so more than [`DUPES_NEIGHBORS`] are fetched.
+pub const DUPES_CANDIDATES: usize = 4 * DUPES_NEIGHBORS;
+
+/// A chunk of code taking part in a duplication.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChunkLocation {
+    /// File path, relative to the project root.
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Entity the chunk belongs to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+}
+
+impl ChunkLocation {
+    /// Number of lines the chunk spans.
+    pub fn line_count(&self) -> u32 {
+        self.end_line.saturating_sub(self.start_line) + 1
+    }
+}
+
+impl From<&SearchResult> for ChunkLocation {
+    fn from(result: &SearchResult) -> Self {
+        Self {
+            path: result.path.clone(),
+            start_line: result.start_line,
+            end_line: result.end_line,
+            entity_id: result.entity_id.clone(),
+        }
+    }
+}
+
+/// Two chunks in different files with near-identical embeddings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicatePair {
+    pub a: ChunkLocation,
+    pub b: ChunkLocation,
+    /// Cosine similarity of the embeddings.
+    pub similarity: f32,
+}
+
+/// Chunks connected by duplicate pairs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    /// Chunks in the cluster, ordered by path and line.
+    pub chunks: Vec<ChunkLocation>,
+    /// Lowest similarity among the pairs joining the cluster.
+    pub similarity: f32,
+}
+
+/// Group duplicate pairs into clusters.
+///
+/// Pairs sharing a chunk end up in the same cluster, and a pair reported
+/// from both sides counts once. Clusters are ordered by similarity, most
+/// similar first, then by size.
+pub fn cluster_duplicates(pairs: Vec<DuplicatePair>) -> Vec<DuplicateCluster> {
+    let mut index: HashMap<ChunkLocation, usize> = HashMap::new();
+    let mut chunks: Vec<ChunkLocation> = Vec::new();
+    let mut parent: Vec<usize> = Vec::new();
+    let mut edges: Vec<(usize, usize, f32)> = Vec::new();
+
+    let mut id = |chunk: ChunkLocation, parent: &mut Vec<usize>| {
+        *index.entry(chunk.clone()).or_insert_with(|| {
+            chunks.push(chunk);
+            parent.push(parent.len());
+            parent.len() - 1
+        })
+    };
+    for pair in pairs {
+        let a = id(pair.a, &mut parent);
+        let b = id(pair.b, &mut parent);
+        edges.push((a, b, pair.similarity));
+    }
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for &(a, b, _) in &edges {
+        let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+        parent[a] = b;
+    }
+
+    let mut clusters: HashMap<usize, DuplicateCluster> = HashMap::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let root = find(&mut parent, i);
+        clusters
+            .entry(root)
+            .or_insert_with(|| DuplicateCluster {
+                chunks: Vec::new(),
+                similarity: 1.0,
+            })
+            .chunks
+            .push(chunk.clone());
+    }
+    for (a, _, similarity) in edges {
+        let root = find(&mut parent, a);
+        if let Some(cluster) = clusters.get_mut(&root) {
+            cluster.similarity = cluster.similarity.min(similarity);
+        }
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = clusters.into_values().collect();
+    for cluster in &mut clusters {
+        cluster.chunks.sort();
+    }
+    clusters.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then(b.chunks.len().cmp(&a.chunks.len()))
+            .then_with(|| a.chunks.cmp(&b.chunks))
+    });
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(path: &str, start_line: u32) -> ChunkLocation {
+        ChunkLocation {
+            path: path.to_string(),
+            start_line,
+            end_line: start_line + 9,
+            entity_id: None,
+        }
+    }
+
+    fn pair(a: ChunkLocation, b: ChunkLocation, similarity: f32) -> DuplicatePair {
+        DuplicatePair { a, b, similarity }
+    }
+
+    #[test]
+    fn test_cluster_duplicates() {
+        let clusters = cluster_duplicates(vec![
+            pair(chunk("a.rs", 1), chunk("b.rs", 1), 0.99),
+            // Reported again from the other side
+            pair(chunk("b.rs", 1), chunk("a.rs", 1), 0.99),
+            pair(chunk("b.rs", 1), chunk("c.rs", 20), 0.96),
+            pair(chunk("x.rs", 5), chunk("y.rs", 5), 0.98),
+        ]);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].chunks, vec![chunk("x.rs", 5), chunk("y.rs", 5)]);
+        assert_eq!(clusters[0].similarity, 0.98);
+        assert_eq!(
+            clusters[1].chunks,
+            vec![chunk("a.rs", 1), chunk("b.rs", 1), chunk("c.rs", 20)]
+        );
+        assert_eq!(clusters[1].similarity, 0.96);
+    }
+}
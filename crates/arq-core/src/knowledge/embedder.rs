@@ -1,11 +1,31 @@
 //! Embedding generation for semantic search.
+//!
+//! Loading the embedding model takes seconds, so [`LazyEmbedder`] defers it
+//! to the first vector operation: graph queries and stats never pay for it.
+//! A warm daemon (`arq embedder serve`) keeps the model loaded between
+//! commands and answers [`RemoteEmbedder`] requests over a local TCP socket,
+//! one JSON object per line.
 
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
 
 use super::error::KnowledgeError;
 
+/// Dimension of the default BGE-Small model, reported before it is loaded.
+const DEFAULT_DIMENSION: usize = 384;
+
+/// Name of the default model, reported before it is loaded.
+const DEFAULT_MODEL_NAME: &str = "BGESmallENV15";
+
+/// How long to wait for the warm daemon before loading the model locally.
+const DAEMON_CONNECT_TIMEOUT: Duration = Duration::from_millis(250);
+
 /// Trait for embedding generation.
 pub trait Embedder: Send + Sync {
     /// Generate embeddings for a batch of text.
@@ -33,6 +53,12 @@ impl FastEmbedder {
         Self::with_model_and_cache(EmbeddingModel::BGESmallENV15, cache_dir)
     }
 
+    /// Create a new FastEmbed embedder with the default model, cached in
+    /// `cache_dir`.
+    pub fn with_cache_dir(cache_dir: PathBuf) -> Result<Self, KnowledgeError> {
+        Self::with_model_and_cache(EmbeddingModel::BGESmallENV15, cache_dir)
+    }
+
     /// Create a new FastEmbed embedder with a specific model.
     /// Uses `~/.arq/cache/` as the model cache directory.
     #[allow(dead_code)]
@@ -105,6 +131,267 @@ impl Embedder for FastEmbedder {
     }
 }
 
+// ============================================================================
+// Lazy initialization
+// ============================================================================
+
+/// How the embedder became ready, for `arq doctor`.
+#[derive(Debug, Clone, Default)]
+pub struct EmbedderMetrics {
+    /// `local`, or the address of the warm daemon; `None` until first use.
+    pub backend: Option<String>,
+    /// Time to load the model or connect to the daemon.
+    pub init_time: Option<Duration>,
+    /// Why the embedder failed to start, if it did.
+    pub error: Option<String>,
+    /// Texts embedded so far.
+    pub texts_embedded: usize,
+}
+
+/// Embedder that starts on first use: from the warm daemon if one is
+/// configured and running, else by loading the model in-process.
+pub struct LazyEmbedder {
+    daemon: Option<String>,
+    cache_dir: Option<PathBuf>,
+    inner: OnceLock<Result<Box<dyn Embedder>, String>>,
+    metrics: Mutex<EmbedderMetrics>,
+}
+
+impl Default for LazyEmbedder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LazyEmbedder {
+    /// An embedder that loads the default model on first use.
+    pub fn new() -> Self {
+        Self::with_daemon(None)
+    }
+
+    /// An embedder that tries the warm daemon at `daemon` before loading locally.
+    pub fn with_daemon(daemon: Option<String>) -> Self {
+        Self {
+            daemon,
+            cache_dir: None,
+            inner: OnceLock::new(),
+            metrics: Mutex::new(EmbedderMetrics::default()),
+        }
+    }
+
+    /// Cache the model in `dir` instead of `~/.arq/cache/` when loading it
+    /// in-process.
+    pub fn with_cache_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.cache_dir = dir;
+        self
+    }
+
+    /// Start the embedder now rather than on first use.
+    pub fn warm_up(&self) -> Result<(), KnowledgeError> {
+        self.get().map(|_| ())
+    }
+
+    /// Whether the embedder has started.
+    pub fn is_loaded(&self) -> bool {
+        self.inner.get().is_some_and(|inner| inner.is_ok())
+    }
+
+    /// Startup and usage metrics so far.
+    pub fn metrics(&self) -> EmbedderMetrics {
+        self.metrics.lock().map(|m| m.clone()).unwrap_or_default()
+    }
+
+    fn get(&self) -> Result<&dyn Embedder, KnowledgeError> {
+        let inner = self.inner.get_or_init(|| {
+            let started = Instant::now();
+            let result = self.start();
+            if let Ok(mut metrics) = self.metrics.lock() {
+                metrics.init_time = Some(started.elapsed());
+                match &result {
+                    Ok((backend, _)) => metrics.backend = Some(backend.clone()),
+                    Err(e) => metrics.error = Some(e.clone()),
+                }
+            }
+            result.map(|(_, embedder)| embedder)
+        });
+        inner
+            .as_deref()
+            .map_err(|e| KnowledgeError::Embedding(e.clone()))
+    }
+
+    fn start(&self) -> Result<(String, Box<dyn Embedder>), String> {
+        if let Some(addr) = &self.daemon {
+            if let Ok(remote) = RemoteEmbedder::connect(addr) {
+                return Ok((addr.clone(), Box::new(remote)));
+            }
+        }
+        let local = match &self.cache_dir {
+            Some(dir) => FastEmbedder::with_cache_dir(dir.clone()),
+            None => FastEmbedder::new(),
+        };
+        local
+            .map(|local| ("local".to_string(), Box::new(local) as Box<dyn Embedder>))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Embedder for LazyEmbedder {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KnowledgeError> {
+        let embeddings = self.get()?.embed(texts)?;
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.texts_embedded += texts.len();
+        }
+        Ok(embeddings)
+    }
+
+    fn dimension(&self) -> usize {
+        self.get()
+            .map(|e| e.dimension())
+            .unwrap_or(DEFAULT_DIMENSION)
+    }
+
+    fn model_name(&self) -> &str {
+        match self.inner.get() {
+            Some(Ok(embedder)) => embedder.model_name(),
+            _ => DEFAULT_MODEL_NAME,
+        }
+    }
+}
+
+// ============================================================================
+// Warm daemon
+// ============================================================================
+
+/// A request to the warm daemon; no texts asks for its model details.
+#[derive(Debug, Serialize, Deserialize)]
+struct EmbedRequest {
+    texts: Vec<String>,
+}
+
+/// A reply from the warm daemon.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<Vec<f32>>,
+    dimension: usize,
+    model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Embedder backed by a warm daemon started with `arq embedder serve`.
+pub struct RemoteEmbedder {
+    connection: Mutex<BufReader<TcpStream>>,
+    dimension: usize,
+    model_name: String,
+}
+
+impl RemoteEmbedder {
+    /// Connect to the daemon at `addr`, failing fast if none is running.
+    pub fn connect(addr: &str) -> Result<Self, KnowledgeError> {
+        let unreachable = |e: std::io::Error| {
+            KnowledgeError::Embedding(format!("embedder daemon at {}: {}", addr, e))
+        };
+        let socket = addr
+            .to_socket_addrs()
+            .map_err(unreachable)?
+            .next()
+            .ok_or_else(|| KnowledgeError::Embedding(format!("invalid address '{}'", addr)))?;
+        let stream =
+            TcpStream::connect_timeout(&socket, DAEMON_CONNECT_TIMEOUT).map_err(unreachable)?;
+
+        let mut remote = Self {
+            connection: Mutex::new(BufReader::new(stream)),
+            dimension: DEFAULT_DIMENSION,
+            model_name: String::new(),
+        };
+        let hello = remote.request(Vec::new())?;
+        remote.dimension = hello.dimension;
+        remote.model_name = hello.model;
+        Ok(remote)
+    }
+
+    fn request(&self, texts: Vec<String>) -> Result<EmbedResponse, KnowledgeError> {
+        let failed = |e: String| KnowledgeError::Embedding(format!("embedder daemon: {}", e));
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| failed("connection poisoned".to_string()))?;
+
+        let mut line =
+            serde_json::to_string(&EmbedRequest { texts }).map_err(|e| failed(e.to_string()))?;
+        line.push('\n');
+        connection
+            .get_mut()
+            .write_all(line.as_bytes())
+            .map_err(|e| failed(e.to_string()))?;
+
+        let mut reply = String::new();
+        connection
+            .read_line(&mut reply)
+            .map_err(|e| failed(e.to_string()))?;
+        let response: EmbedResponse =
+            serde_json::from_str(&reply).map_err(|e| failed(e.to_string()))?;
+        match response.error {
+            Some(error) => Err(failed(error)),
+            None => Ok(response),
+        }
+    }
+}
+
+impl Embedder for RemoteEmbedder {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KnowledgeError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self.request(texts.to_vec())?.embeddings)
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
+}
+
+/// Answer embedding requests on `listener` until it fails, one thread per
+/// connection. Used by `arq embedder serve` to keep the model warm.
+pub fn serve_embeddings(listener: TcpListener, embedder: Arc<dyn Embedder>) -> std::io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let embedder = Arc::clone(&embedder);
+        std::thread::spawn(move || {
+            let _ = handle_connection(stream, embedder.as_ref());
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, embedder: &dyn Embedder) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let mut response = EmbedResponse {
+            dimension: embedder.dimension(),
+            model: embedder.model_name().to_string(),
+            ..Default::default()
+        };
+        match serde_json::from_str::<EmbedRequest>(&line) {
+            Ok(request) => match embedder.embed(&request.texts) {
+                Ok(embeddings) => response.embeddings = embeddings,
+                Err(e) => response.error = Some(e.to_string()),
+            },
+            Err(e) => response.error = Some(format!("invalid request: {}", e)),
+        }
+        let mut reply = serde_json::to_string(&response).map_err(std::io::Error::other)?;
+        reply.push('\n');
+        writer.write_all(reply.as_bytes())?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,4 +406,45 @@ mod tests {
         let embedder = FastEmbedder::new().expect("Failed to create embedder");
         assert_eq!(embedder.dimension(), 384); // BGE-Small produces 384-dim vectors
     }
+
+    /// Embeds each text as its length, so tests don't need the model.
+    struct LengthEmbedder;
+
+    impl Embedder for LengthEmbedder {
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KnowledgeError> {
+            Ok(texts.iter().map(|t| vec![t.len() as f32]).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        fn model_name(&self) -> &str {
+            "length"
+        }
+    }
+
+    #[test]
+    fn test_remote_embedder() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || serve_embeddings(listener, Arc::new(LengthEmbedder)));
+
+        let remote = RemoteEmbedder::connect(&addr).unwrap();
+        assert_eq!(remote.dimension(), 1);
+        assert_eq!(remote.model_name(), "length");
+        let embeddings = remote
+            .embed(&["ab".to_string(), "abcd".to_string()])
+            .unwrap();
+        assert_eq!(embeddings, vec![vec![2.0], vec![4.0]]);
+
+        // The lazy embedder prefers a running daemon and records how it started
+        let lazy = LazyEmbedder::with_daemon(Some(addr.clone()));
+        assert!(!lazy.is_loaded());
+        assert_eq!(lazy.embed(&["abc".to_string()]).unwrap(), vec![vec![3.0]]);
+        let metrics = lazy.metrics();
+        assert_eq!(metrics.backend, Some(addr));
+        assert!(metrics.init_time.is_some());
+        assert_eq!(metrics.texts_embedded, 1);
+    }
 }
@@ -41,6 +41,34 @@ pub enum KnowledgeError {
     /// Configuration error.
     #[error("Configuration error: {0}")]
     Config(String),
+
+    /// Relation table that does not exist in the ontology.
+    #[error("Unknown relation '{0}'")]
+    UnknownRelation(String),
+
+    /// A past state asked of a relation that keeps no history.
+    #[error("Only calls edges keep history; '{0}' edges can't be queried as of a past time")]
+    NoHistory(String),
+
+    /// A grep pattern that is not a valid regex.
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    /// Writing an export failed.
+    #[error("Export error: {0}")]
+    Export(String),
+
+    /// Reading the git history failed.
+    #[error("Git error: {0}")]
+    Git(String),
+
+    /// Backing up or restoring the database failed.
+    #[error("Backup error: {0}")]
+    Backup(String),
+
+    /// Watching the project for changes failed.
+    #[error("Watch error: {0}")]
+    Watch(String),
 }
 
 impl From<std::io::Error> for KnowledgeError {
@@ -57,3 +85,9 @@ impl From<surrealdb::Error> for KnowledgeError {
         KnowledgeError::Database(err.to_string())
     }
 }
+
+impl From<rusqlite::Error> for KnowledgeError {
+    fn from(err: rusqlite::Error) -> Self {
+        KnowledgeError::Export(err.to_string())
+    }
+}
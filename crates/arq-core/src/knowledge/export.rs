@@ -0,0 +1,334 @@
+//! Export of the knowledge graph for downstream tooling.
+//!
+//! The SQLite export is a normalized snapshot independent of SurrealDB:
+//!
+//! - `entities(id, kind, name, qualified_name, file_path, start_line, end_line,
+//!   visibility, signature, doc_comment)` - one row per node
+//! - `edges(id, relation, source_id, target_id)` - one row per edge; IDs refer
+//!   to `entities.id` except for call targets that were never resolved
+//! - `metadata(key, value)` - export format version and timestamp
+//!
+//! ```sql
+//! SELECT e.name, COUNT(*) AS callers
+//! FROM edges JOIN entities e ON e.id = edges.target_id
+//! WHERE edges.relation = 'calls'
+//! GROUP BY e.id ORDER BY callers DESC LIMIT 10;
+//! ```
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::db::{EdgeInfo, EntityInfo};
+use super::error::KnowledgeError;
+
+/// Version of the exported SQLite schema, bumped on incompatible changes.
+pub const SQLITE_EXPORT_VERSION: u32 = 1;
+
+/// Tables and indexes of the SQLite export.
+const SQLITE_SCHEMA: &str = r#"
+CREATE TABLE metadata (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE entities (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    qualified_name TEXT,
+    file_path TEXT,
+    start_line INTEGER,
+    end_line INTEGER,
+    visibility TEXT,
+    signature TEXT,
+    doc_comment TEXT
+);
+CREATE INDEX idx_entities_kind ON entities(kind);
+CREATE INDEX idx_entities_name ON entities(name);
+CREATE INDEX idx_entities_qualified_name ON entities(qualified_name);
+CREATE INDEX idx_entities_file_path ON entities(file_path);
+
+CREATE TABLE edges (
+    id TEXT PRIMARY KEY,
+    relation TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    target_id TEXT NOT NULL
+);
+CREATE INDEX idx_edges_relation ON edges(relation);
+CREATE INDEX idx_edges_source ON edges(source_id, relation);
+CREATE INDEX idx_edges_target ON edges(target_id, relation);
+"#;
+
+/// A format the knowledge graph can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Normalized SQLite database
+    Sqlite,
+}
+
+impl ExportFormat {
+    /// Name used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sqlite => "sqlite",
+        }
+    }
+
+    /// File extension of exports in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Sqlite => "sqlite",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sqlite" | "sqlite3" => Ok(Self::Sqlite),
+            other => Err(format!(
+                "unknown export format '{}' (expected sqlite)",
+                other
+            )),
+        }
+    }
+}
+
+/// Number of rows written by an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub entities: usize,
+    pub edges: usize,
+}
+
+/// Point edge endpoints given as record keys at the entity IDs they name.
+///
+/// Record-table edges such as `calls` store the record key of an entity,
+/// while `RELATE` edges use its full ID; after this every endpoint that names
+/// an exported entity is its `entities.id`.
+pub fn normalize_edges(entities: &[EntityInfo], edges: &mut [EdgeInfo]) {
+    let ids: HashSet<&str> = entities.iter().map(|e| e.id.as_str()).collect();
+    let by_key: HashMap<&str, &str> = entities
+        .iter()
+        .map(|e| (e.key.as_str(), e.id.as_str()))
+        .collect();
+
+    let resolve = |endpoint: &mut String| {
+        if !ids.contains(endpoint.as_str()) {
+            if let Some(id) = by_key.get(endpoint.as_str()) {
+                *endpoint = id.to_string();
+            }
+        }
+    };
+    for edge in edges {
+        resolve(&mut edge.source);
+        resolve(&mut edge.target);
+    }
+}
+
+/// Write entities and edges to a new SQLite database at `path`.
+///
+/// The database is written to a temporary file next to `path` and renamed
+/// over it once complete, so a failed export leaves an existing file at
+/// `path` as it was and no partial database behind.
+pub fn write_sqlite(
+    path: &Path,
+    entities: &[EntityInfo],
+    edges: &[EdgeInfo],
+) -> Result<ExportSummary, KnowledgeError> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+    let io = |source| KnowledgeError::Io {
+        path: temp.clone(),
+        source,
+    };
+
+    if temp.exists() {
+        std::fs::remove_file(&temp).map_err(io)?;
+    }
+    let result = write_tables(&temp, entities, edges)
+        .and_then(|summary| std::fs::rename(&temp, path).map_err(io).map(|_| summary));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_tables(
+    path: &Path,
+    entities: &[EntityInfo],
+    edges: &[EdgeInfo],
+) -> Result<ExportSummary, KnowledgeError> {
+    let mut conn = Connection::open(path)?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(SQLITE_SCHEMA)?;
+
+    {
+        let mut insert = tx.prepare("INSERT INTO metadata (key, value) VALUES (?1, ?2)")?;
+        insert.execute(params!["export_version", SQLITE_EXPORT_VERSION.to_string()])?;
+        insert.execute(params!["exported_at", chrono::Utc::now().to_rfc3339()])?;
+    }
+
+    let mut summary = ExportSummary::default();
+    {
+        // Node IDs are unique per table, so duplicates only arise from
+        // records listed twice; keep the first
+        let mut insert = tx.prepare(
+            "INSERT OR IGNORE INTO entities (id, kind, name, qualified_name, file_path, start_line, end_line, visibility, signature, doc_comment) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for entity in entities {
+            summary.entities += insert.execute(params![
+                entity.id,
+                entity.kind,
+                entity.name,
+                entity.qualified_name,
+                entity.file_path,
+                entity.start_line,
+                entity.end_line,
+                entity.visibility,
+                entity.signature,
+                entity.doc_comment,
+            ])?;
+        }
+    }
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR IGNORE INTO edges (id, relation, source_id, target_id) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for edge in edges {
+            summary.edges +=
+                insert.execute(params![edge.id, edge.relation, edge.source, edge.target])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(id: &str, kind: &str, name: &str) -> EntityInfo {
+        EntityInfo {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            name: name.to_string(),
+            file_path: Some("src/lib.rs".to_string()),
+            start_line: Some(1),
+            ..Default::default()
+        }
+    }
+
+    fn edge(id: &str, relation: &str, source: &str, target: &str) -> EdgeInfo {
+        EdgeInfo {
+            id: id.to_string(),
+            relation: relation.to_string(),
+            source: source.to_string(),
+            target: target.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_write_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.sqlite");
+        let entities = vec![
+            entity("file:lib", "file", "lib.rs"),
+            entity("fn_node:a", "function", "a"),
+            entity("fn_node:b", "function", "b"),
+        ];
+        let edges = vec![
+            edge("calls:1", "calls", "fn_node:a", "fn_node:b"),
+            edge("contains:1", "contains", "file:lib", "fn_node:a"),
+        ];
+
+        // A second export replaces the first
+        write_sqlite(&path, &entities, &edges).unwrap();
+        let summary = write_sqlite(&path, &entities, &edges).unwrap();
+        assert_eq!(
+            summary,
+            ExportSummary {
+                entities: 3,
+                edges: 2
+            }
+        );
+
+        let conn = Connection::open(&path).unwrap();
+        let callee: String = conn
+            .query_row(
+                "SELECT e.name FROM edges JOIN entities e ON e.id = edges.target_id WHERE edges.relation = 'calls'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(callee, "b");
+
+        let version: String = conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'export_version'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(version, SQLITE_EXPORT_VERSION.to_string());
+    }
+
+    #[test]
+    fn test_failed_export_keeps_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.sqlite");
+        let entities = vec![entity("fn_node:a", "function", "a")];
+        write_sqlite(&path, &entities, &[]).unwrap();
+
+        // The temporary file can't be created where a directory is
+        std::fs::create_dir(dir.path().join("graph.sqlite.tmp")).unwrap();
+        assert!(write_sqlite(&path, &entities, &[]).is_err());
+
+        let conn = Connection::open(&path).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM entities", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_normalize_edges() {
+        let mut function = entity("fn_node:function:src/lib.rs:a", "function", "a");
+        function.key = "function:src/lib.rs:a".to_string();
+        let mut edges = vec![
+            edge("calls:1", "calls", "function:src/lib.rs:a", ":?:unwrap"),
+            edge(
+                "contains:1",
+                "contains",
+                "file:lib",
+                "fn_node:function:src/lib.rs:a",
+            ),
+        ];
+
+        normalize_edges(&[function], &mut edges);
+        assert_eq!(edges[0].source, "fn_node:function:src/lib.rs:a");
+        assert_eq!(edges[0].target, ":?:unwrap");
+        assert_eq!(edges[1].target, "fn_node:function:src/lib.rs:a");
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("SQLite".parse::<ExportFormat>(), Ok(ExportFormat::Sqlite));
+        assert!("csv".parse::<ExportFormat>().is_err());
+    }
+}
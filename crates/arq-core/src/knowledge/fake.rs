@@ -0,0 +1,383 @@
+//! In-memory [`KnowledgeStore`] for tests.
+//!
+//! Code that takes an `Arc<dyn KnowledgeStore>` can be tested without a
+//! database or an embedding model: seed a [`FakeKnowledgeStore`] with the
+//! functions, chunks and calls the test needs and pass it in. Search ranks
+//! chunks by how many query words they contain, so results are
+//! deterministic.
+//!
+//! Available with the `test-util` feature.
+
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use super::db::{innermost_entity, EntityInfo};
+use super::error::KnowledgeError;
+use super::indexer::DOCUMENT_ENTITY_TYPE;
+use super::models::{FunctionNode, IndexStats, SearchResult};
+use super::ontology::nodes::{DocumentEntity, FunctionEntity, Visibility};
+use super::ontology::QualifiedName;
+use super::summary::{Summary, SummaryKind};
+use super::KnowledgeStore;
+
+/// Characters of a chunk returned as the search preview.
+const PREVIEW_CHARS: usize = 200;
+
+/// A searchable chunk of a seeded file.
+#[derive(Debug, Clone)]
+struct FakeChunk {
+    path: String,
+    start_line: u32,
+    end_line: u32,
+    content: String,
+    /// Whether the chunk is a section of an indexed documentation file.
+    document: bool,
+}
+
+#[derive(Debug, Default)]
+struct FakeData {
+    functions: Vec<FunctionEntity>,
+    chunks: Vec<FakeChunk>,
+    /// `(caller, callee)` function names.
+    calls: Vec<(String, String)>,
+    directory_summaries: Vec<Summary>,
+}
+
+/// A [`KnowledgeStore`] held in memory and seeded by the test.
+#[derive(Debug, Default)]
+pub struct FakeKnowledgeStore {
+    data: Mutex<FakeData>,
+}
+
+impl FakeKnowledgeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a public function `name` spanning `start_line..=end_line` of `path`.
+    pub fn with_function(self, path: &str, name: &str, start_line: u32, end_line: u32) -> Self {
+        self.with_function_entity(FunctionEntity {
+            id: Some(format!("function:{}:{}", path, name)),
+            name: name.to_string(),
+            qualified_name: name.to_string(),
+            file_path: path.to_string(),
+            start_line,
+            end_line,
+            signature: format!("fn {}()", name),
+            parent: None,
+            visibility: Visibility::Public,
+            is_async: false,
+            is_unsafe: false,
+            generics: Vec::new(),
+            attributes: Vec::new(),
+            parameters: Vec::new(),
+            return_type: None,
+            doc_comment: None,
+            complexity: None,
+        })
+    }
+
+    /// Add a fully specified function.
+    pub fn with_function_entity(self, function: FunctionEntity) -> Self {
+        self.lock().functions.push(function);
+        self
+    }
+
+    /// Add a chunk of `path` that search can return.
+    pub fn with_chunk(self, path: &str, start_line: u32, end_line: u32, content: &str) -> Self {
+        self.lock().chunks.push(FakeChunk {
+            path: path.to_string(),
+            start_line,
+            end_line,
+            content: content.to_string(),
+            document: false,
+        });
+        self
+    }
+
+    /// Add a section of a documentation file that search can return, as
+    /// `arq init --docs` would index.
+    pub fn with_document_chunk(
+        self,
+        path: &str,
+        start_line: u32,
+        end_line: u32,
+        content: &str,
+    ) -> Self {
+        self.lock().chunks.push(FakeChunk {
+            path: path.to_string(),
+            start_line,
+            end_line,
+            content: content.to_string(),
+            document: true,
+        });
+        self
+    }
+
+    /// Add a call from the function named `caller` to the one named `callee`.
+    pub fn with_call(self, caller: &str, callee: &str) -> Self {
+        self.lock()
+            .calls
+            .push((caller.to_string(), callee.to_string()));
+        self
+    }
+
+    /// Add a directory summary, as `arq kg summarize` would store.
+    pub fn with_directory_summary(self, path: &str, summary: &str) -> Self {
+        self.lock().directory_summaries.push(Summary {
+            path: path.to_string(),
+            kind: SummaryKind::Directory,
+            summary: summary.to_string(),
+            hash: String::new(),
+        });
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, FakeData> {
+        // A panicking test can't leave the seed data half-written
+        self.data.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Name of the function an entity ID (`function:path:name`) or plain name
+/// refers to.
+fn function_name(entity_id: &str) -> String {
+    QualifiedName::from_entity_id(entity_id).name().to_string()
+}
+
+fn to_node(function: &FunctionEntity) -> FunctionNode {
+    FunctionNode {
+        id: None,
+        name: function.name.clone(),
+        file_path: function.file_path.clone(),
+        parent_struct: function.parent.clone(),
+        start_line: function.start_line,
+        end_line: function.end_line,
+        visibility: format!("{:?}", function.visibility).to_lowercase(),
+        is_async: function.is_async,
+        signature: function.signature.clone(),
+        doc_comment: function.doc_comment.clone(),
+    }
+}
+
+#[async_trait]
+impl KnowledgeStore for FakeKnowledgeStore {
+    async fn initialize(&self) -> Result<(), KnowledgeError> {
+        Ok(())
+    }
+
+    async fn is_initialized(&self) -> Result<bool, KnowledgeError> {
+        Ok(true)
+    }
+
+    /// Doesn't read the directory; returns the seeded totals.
+    async fn index_directory(&self, _path: &Path) -> Result<IndexStats, KnowledgeError> {
+        self.get_stats().await
+    }
+
+    fn count_indexable_files(&self, _path: &Path) -> usize {
+        0
+    }
+
+    /// Replaces the file's chunks with one chunk of the whole content.
+    async fn index_file(&self, path: &str, content: &str) -> Result<(), KnowledgeError> {
+        let mut data = self.lock();
+        data.chunks.retain(|c| c.path != path);
+        data.chunks.push(FakeChunk {
+            path: path.to_string(),
+            start_line: 1,
+            end_line: content.lines().count().max(1) as u32,
+            content: content.to_string(),
+            document: false,
+        });
+        Ok(())
+    }
+
+    async fn remove_file(&self, path: &str) -> Result<(), KnowledgeError> {
+        let mut data = self.lock();
+        data.chunks.retain(|c| c.path != path);
+        data.functions.retain(|f| f.file_path != path);
+        Ok(())
+    }
+
+    async fn search_code(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, KnowledgeError> {
+        let words: Vec<String> = query
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let data = self.lock();
+        let mut scored: Vec<(f32, &FakeChunk)> = data
+            .chunks
+            .iter()
+            .filter_map(|chunk| {
+                let content = chunk.content.to_lowercase();
+                let matched = words.iter().filter(|w| content.contains(*w)).count();
+                (matched > 0).then_some((matched as f32 / words.len() as f32, chunk))
+            })
+            .collect();
+        // Stable, so equal scores keep seeding order
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        Ok(scored
+            .into_iter()
+            .take(limit)
+            .map(|(score, chunk)| {
+                let function = data.functions.iter().find(|f| {
+                    !chunk.document
+                        && f.file_path == chunk.path
+                        && f.start_line <= chunk.start_line
+                        && f.end_line >= chunk.end_line
+                });
+                SearchResult {
+                    path: chunk.path.clone(),
+                    score,
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    preview: Some(chunk.content.chars().take(PREVIEW_CHARS).collect()),
+                    entity_id: match function {
+                        Some(f) => f.id.clone(),
+                        None if chunk.document => Some(DocumentEntity::entity_id(&chunk.path)),
+                        None => None,
+                    },
+                    entity_type: if function.is_some() {
+                        "function".to_string()
+                    } else if chunk.document {
+                        DOCUMENT_ENTITY_TYPE.to_string()
+                    } else {
+                        "code".to_string()
+                    },
+                    crate_name: None,
+                    context: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn get_dependencies(&self, entity_id: &str) -> Result<Vec<String>, KnowledgeError> {
+        let name = function_name(entity_id);
+        let mut callees: Vec<String> = self
+            .lock()
+            .calls
+            .iter()
+            .filter(|(caller, _)| *caller == name)
+            .map(|(_, callee)| callee.clone())
+            .collect();
+        callees.sort();
+        callees.dedup();
+        Ok(callees)
+    }
+
+    async fn get_impact(&self, entity_id: &str) -> Result<Vec<String>, KnowledgeError> {
+        let name = function_name(entity_id);
+        let mut callers: Vec<String> = self
+            .lock()
+            .calls
+            .iter()
+            .filter(|(_, callee)| *callee == name)
+            .map(|(caller, _)| caller.clone())
+            .collect();
+        callers.sort();
+        callers.dedup();
+        Ok(callers)
+    }
+
+    async fn get_stats(&self) -> Result<IndexStats, KnowledgeError> {
+        let data = self.lock();
+        let mut files: Vec<&str> = data
+            .chunks
+            .iter()
+            .map(|c| c.path.as_str())
+            .chain(data.functions.iter().map(|f| f.file_path.as_str()))
+            .collect();
+        files.sort();
+        files.dedup();
+
+        Ok(IndexStats {
+            files: files.len(),
+            functions: data.functions.len(),
+            chunks: data.chunks.len(),
+            total_size: data.chunks.iter().map(|c| c.content.len() as u64).sum(),
+            ..IndexStats::default()
+        })
+    }
+
+    async fn list_functions(&self, limit: usize) -> Result<Vec<FunctionNode>, KnowledgeError> {
+        Ok(self
+            .lock()
+            .functions
+            .iter()
+            .take(limit)
+            .map(to_node)
+            .collect())
+    }
+
+    async fn find_function_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<FunctionNode>, KnowledgeError> {
+        Ok(self
+            .lock()
+            .functions
+            .iter()
+            .find(|f| f.name == name)
+            .map(to_node))
+    }
+
+    async fn count_calls(&self) -> Result<usize, KnowledgeError> {
+        Ok(self.lock().calls.len())
+    }
+
+    async fn functions_in_range(
+        &self,
+        path: &str,
+        start_line: u32,
+        end_line: u32,
+    ) -> Result<Vec<FunctionEntity>, KnowledgeError> {
+        Ok(self
+            .lock()
+            .functions
+            .iter()
+            .filter(|f| f.file_path == path && f.start_line <= end_line && f.end_line >= start_line)
+            .cloned()
+            .collect())
+    }
+
+    async fn directory_summaries(&self) -> Result<Vec<Summary>, KnowledgeError> {
+        let mut summaries = self.lock().directory_summaries.clone();
+        summaries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(summaries)
+    }
+
+    async fn entity_at(&self, path: &str, line: u32) -> Result<Option<EntityInfo>, KnowledgeError> {
+        let candidates = self
+            .lock()
+            .functions
+            .iter()
+            .filter(|f| f.file_path == path && f.start_line <= line && f.end_line >= line)
+            .map(|f| EntityInfo {
+                id: format!("function:{}:{}", f.file_path, f.qualified_name),
+                key: f.qualified_name.clone(),
+                kind: "function".to_string(),
+                name: f.name.clone(),
+                qualified_name: Some(f.qualified_name.clone()),
+                file_path: Some(f.file_path.clone()),
+                start_line: Some(f.start_line),
+                end_line: Some(f.end_line),
+                signature: Some(f.signature.clone()),
+                doc_comment: f.doc_comment.clone(),
+                ..EntityInfo::default()
+            })
+            .collect();
+        Ok(innermost_entity(candidates))
+    }
+}
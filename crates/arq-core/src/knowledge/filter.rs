@@ -0,0 +1,130 @@
+//! Structured filters over function metadata for type-aware search.
+//!
+//! Filters compile to SurrealQL predicates on `fn_node`, so they can narrow
+//! semantic search to functions with a given return type, parameter type,
+//! async-ness, visibility or crate.
+
+use serde_json::Value;
+
+use super::ontology::nodes::Visibility;
+use super::workspace::crate_ident;
+
+/// Metadata predicates a function must satisfy to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityFilter {
+    /// Substring of the return type (e.g. `Result<Plan>`), whitespace-insensitive.
+    pub returns: Option<String>,
+    /// Substring of any parameter's type, whitespace-insensitive.
+    pub param_type: Option<String>,
+    /// Only async (or only non-async) functions.
+    pub is_async: Option<bool>,
+    /// Exact visibility.
+    pub visibility: Option<Visibility>,
+    /// Workspace crate that defines the function.
+    pub crate_name: Option<String>,
+}
+
+impl EntityFilter {
+    /// Whether no predicate is set.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// SurrealQL `WHERE` predicates (joined by `AND`) and their bind variables.
+    ///
+    /// Returns `"true"` when the filter is empty.
+    pub(crate) fn to_surql(&self) -> (String, Vec<(&'static str, Value)>) {
+        let mut predicates = Vec::new();
+        let mut bindings = Vec::new();
+
+        if let Some(ref returns) = self.returns {
+            predicates.push(
+                "string::contains(string::lowercase(string::replace(return_type ?? '', ' ', '')), $returns)",
+            );
+            bindings.push(("returns", Value::from(normalize_type(returns))));
+        }
+        if let Some(ref param_type) = self.param_type {
+            predicates.push(
+                "array::len(parameters[WHERE string::contains(string::lowercase(string::replace(type_name, ' ', '')), $param_type)]) > 0",
+            );
+            bindings.push(("param_type", Value::from(normalize_type(param_type))));
+        }
+        if let Some(is_async) = self.is_async {
+            predicates.push("is_async = $is_async");
+            bindings.push(("is_async", Value::from(is_async)));
+        }
+        if let Some(visibility) = self.visibility {
+            predicates.push("visibility = $visibility");
+            bindings.push((
+                "visibility",
+                serde_json::to_value(visibility).unwrap_or(Value::Null),
+            ));
+        }
+        if let Some(ref crate_name) = self.crate_name {
+            predicates.push("string::starts_with(qualified_name, $crate_prefix)");
+            bindings.push((
+                "crate_prefix",
+                Value::from(format!("{}::", crate_ident(crate_name))),
+            ));
+        }
+
+        if predicates.is_empty() {
+            ("true".to_string(), bindings)
+        } else {
+            (predicates.join(" AND "), bindings)
+        }
+    }
+}
+
+/// Lowercase a type and drop whitespace, so `Result<Vec<u8>, E>` matches `result<vec<u8>,e>`.
+pub fn normalize_type(type_name: &str) -> String {
+    type_name
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_type() {
+        assert_eq!(normalize_type("Result<Plan, Error>"), "result<plan,error>");
+        assert_eq!(normalize_type(" &mut  Task "), "&muttask");
+    }
+
+    #[test]
+    fn test_empty_filter() {
+        let filter = EntityFilter::default();
+        assert!(filter.is_empty());
+        let (clause, bindings) = filter.to_surql();
+        assert_eq!(clause, "true");
+        assert!(bindings.is_empty());
+    }
+
+    #[test]
+    fn test_filter_predicates() {
+        let filter = EntityFilter {
+            returns: Some("Result<Plan>".to_string()),
+            is_async: Some(true),
+            visibility: Some(Visibility::Public),
+            crate_name: Some("arq-core".to_string()),
+            ..EntityFilter::default()
+        };
+        let (clause, bindings) = filter.to_surql();
+
+        assert_eq!(clause.matches(" AND ").count(), 3);
+        assert!(clause.contains("is_async = $is_async"));
+        assert_eq!(
+            bindings,
+            vec![
+                ("returns", Value::from("result<plan>")),
+                ("is_async", Value::from(true)),
+                ("visibility", Value::from("public")),
+                ("crate_prefix", Value::from("arq_core::")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Regex search over indexed files, expanded with the knowledge graph.
+//!
+//! `arq grep` matches lines exactly like ripgrep would, but only in files the
+//! graph knows about, and with `--semantic-expand` attaches to each hit the
+//! innermost function enclosing it and that function's direct callers.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::error::KnowledgeError;
+use super::ontology::nodes::FunctionEntity;
+
+/// A line matching a grep pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrepHit {
+    /// File path, relative to the project root.
+    pub path: String,
+    /// 1-based line number.
+    pub line: u32,
+    /// The matching line.
+    pub text: String,
+    /// Function enclosing the line, with semantic expansion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<GrepEntity>,
+}
+
+/// The function enclosing a grep hit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrepEntity {
+    /// Entity ID, as used by `graph deps` and `graph impact`.
+    pub id: String,
+    pub qualified_name: String,
+    pub signature: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Functions calling this one directly.
+    pub callers: Vec<String>,
+}
+
+impl GrepEntity {
+    /// Describes `function`, without callers.
+    pub fn from_function(function: &FunctionEntity) -> Self {
+        Self {
+            id: format!(
+                "function:{}:{}",
+                function.file_path, function.qualified_name
+            ),
+            qualified_name: function.qualified_name.clone(),
+            signature: function.signature.clone(),
+            start_line: function.start_line,
+            end_line: function.end_line,
+            callers: Vec::new(),
+        }
+    }
+}
+
+/// Compile a grep pattern.
+pub fn compile_pattern(pattern: &str) -> Result<Regex, KnowledgeError> {
+    Regex::new(pattern).map_err(|e| KnowledgeError::InvalidPattern(e.to_string()))
+}
+
+/// Lines of `content` matching `regex`, as 1-based line numbers and text.
+pub fn grep_source(regex: &Regex, content: &str) -> Vec<(u32, String)> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| regex.is_match(line))
+        .map(|(i, line)| (i as u32 + 1, line.to_string()))
+        .collect()
+}
+
+/// The innermost of `functions` spanning `line`.
+pub fn enclosing_function(functions: &[FunctionEntity], line: u32) -> Option<&FunctionEntity> {
+    functions
+        .iter()
+        .filter(|f| f.start_line <= line && f.end_line >= line)
+        .min_by_key(|f| f.end_line.saturating_sub(f.start_line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grep_source_line_numbers() {
+        let regex = compile_pattern(r"fn \w+_id").unwrap();
+        let content = "struct Task;\nfn task_id() {}\n\nfn user_id() {}\n";
+        assert_eq!(
+            grep_source(&regex, content),
+            vec![
+                (2, "fn task_id() {}".to_string()),
+                (4, "fn user_id() {}".to_string()),
+            ]
+        );
+        assert!(compile_pattern("(unclosed").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+//! Time-travel queries over the call graph.
+//!
+//! Re-indexing a file closes its call edges (`valid_to`) instead of deleting
+//! them, and new edges record when they appeared (`valid_from`), so the graph
+//! can answer what a function called at an earlier time. Closed edges are
+//! pruned after `knowledge.history_retention_days`.
+//!
+//! Only call edges are versioned. Entities and the other relations are
+//! replaced when a file is re-indexed, so a past query names callers and
+//! callees that may no longer exist, and other relations can't be queried
+//! as of a past time.
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Parse an `--as-of` time: an RFC 3339 timestamp, or a date meaning the
+/// end of that day (UTC).
+pub fn parse_as_of(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|time| time.and_utc())
+        .ok_or_else(|| {
+            format!(
+                "invalid time '{}' (expected YYYY-MM-DD or an RFC 3339 timestamp)",
+                value
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_as_of() {
+        assert_eq!(
+            parse_as_of("2024-06-01").unwrap().to_rfc3339(),
+            "2024-06-01T23:59:59+00:00"
+        );
+        assert_eq!(
+            parse_as_of("2024-06-01T10:00:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-06-01T08:00:00+00:00"
+        );
+        assert!(parse_as_of("last week").is_err());
+    }
+}
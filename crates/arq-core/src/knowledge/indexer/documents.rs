@@ -0,0 +1,444 @@
+//! Documentation parsing and section chunking.
+//!
+//! Folders passed to `arq init --docs` (or listed in `[knowledge] docs`)
+//! are indexed as [`DocumentEntity`] nodes instead of code. Documents are
+//! chunked by section rather than by line count: every heading starts a
+//! chunk, so a search hit is a coherent piece of prose whose location is
+//! its heading. Long sections are split at paragraph breaks, and chunks
+//! below the top level start with the headings leading to them, so they
+//! embed with their context.
+
+use std::path::Path;
+
+use crate::knowledge::models::CodeChunk;
+use crate::knowledge::ontology::nodes::{DocumentEntity, DocumentFormat};
+
+/// Entity type of documentation chunks.
+pub const DOCUMENT_ENTITY_TYPE: &str = "document";
+
+/// Characters that adorn reStructuredText section titles.
+const RST_ADORNMENTS: &str = "=-~^\"'`*+#:._";
+
+/// A section of a document: a heading and the text up to the next one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSection {
+    /// Headings from the top of the document down to this section; empty
+    /// for text before the first heading.
+    pub headings: Vec<String>,
+    /// First line, 1-based: the heading.
+    pub start_line: u32,
+    /// Last line, 1-based.
+    pub end_line: u32,
+    /// Lines of the section after its heading.
+    pub body: Vec<String>,
+    /// Lines of the heading itself (title and adornments).
+    heading_lines: u32,
+}
+
+/// A parsed documentation file.
+#[derive(Debug, Clone)]
+pub struct ParsedDocument {
+    pub entity: DocumentEntity,
+    pub sections: Vec<DocumentSection>,
+}
+
+/// A heading found in a document, at a 0-based line.
+struct Heading {
+    line: usize,
+    /// Lines the heading takes, adornments included.
+    span: usize,
+    level: usize,
+    text: String,
+}
+
+/// Split a document into sections by its headings.
+pub fn parse_document(path: &str, content: &str, format: DocumentFormat) -> ParsedDocument {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings = match format {
+        DocumentFormat::Markdown => markdown_headings(&lines),
+        DocumentFormat::Rst => rst_headings(&lines),
+    };
+
+    let mut sections = Vec::new();
+    let first = headings.first().map_or(lines.len(), |h| h.line);
+    if lines[..first].iter().any(|l| !l.trim().is_empty()) {
+        sections.push(DocumentSection {
+            headings: Vec::new(),
+            start_line: 1,
+            end_line: first as u32,
+            body: lines[..first].iter().map(|l| l.to_string()).collect(),
+            heading_lines: 0,
+        });
+    }
+
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    for (index, heading) in headings.iter().enumerate() {
+        while stack
+            .last()
+            .is_some_and(|(level, _)| *level >= heading.level)
+        {
+            stack.pop();
+        }
+        stack.push((heading.level, heading.text.clone()));
+
+        let end = headings.get(index + 1).map_or(lines.len(), |h| h.line);
+        let body_start = (heading.line + heading.span).min(end);
+        sections.push(DocumentSection {
+            headings: stack.iter().map(|(_, text)| text.clone()).collect(),
+            start_line: heading.line as u32 + 1,
+            end_line: end as u32,
+            body: lines[body_start..end]
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
+            heading_lines: heading.span as u32,
+        });
+    }
+
+    let title = headings
+        .iter()
+        .find(|h| h.level == 1)
+        .or(headings.first())
+        .map(|h| h.text.clone())
+        .unwrap_or_else(|| {
+            Path::new(path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string())
+        });
+
+    ParsedDocument {
+        entity: DocumentEntity {
+            id: None,
+            title,
+            file_path: path.to_string(),
+            format,
+            headings: headings.into_iter().map(|h| h.text).collect(),
+            start_line: 1,
+            end_line: lines.len().max(1) as u32,
+        },
+        sections,
+    }
+}
+
+/// ATX (`## Title`) and setext (`Title` over `===`/`---`) headings outside
+/// fenced code blocks.
+fn markdown_headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index].trim_start();
+        for marker in ["```", "~~~"] {
+            if line.starts_with(marker) {
+                fence = match fence {
+                    Some(open) if open == marker => None,
+                    None => Some(marker),
+                    other => other,
+                };
+            }
+        }
+        if fence.is_some() || line.starts_with("```") || line.starts_with("~~~") {
+            index += 1;
+            continue;
+        }
+
+        let level = line.chars().take_while(|&c| c == '#').count();
+        let rest = &line[level..];
+        if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+            let text = rest.trim().trim_end_matches('#').trim();
+            if !text.is_empty() {
+                headings.push(Heading {
+                    line: index,
+                    span: 1,
+                    level,
+                    text: text.to_string(),
+                });
+            }
+            index += 1;
+            continue;
+        }
+
+        // Setext: a single-line paragraph underlined with `=` or `-`
+        let starts_paragraph = index == 0 || lines[index - 1].trim().is_empty();
+        if starts_paragraph && !line.trim().is_empty() {
+            if let Some(underline) = lines.get(index + 1).map(|l| l.trim()) {
+                let level = if is_run_of(underline, '=') {
+                    Some(1)
+                } else if is_run_of(underline, '-') {
+                    Some(2)
+                } else {
+                    None
+                };
+                if let Some(level) = level {
+                    headings.push(Heading {
+                        line: index,
+                        span: 2,
+                        level,
+                        text: line.trim().to_string(),
+                    });
+                    index += 2;
+                    continue;
+                }
+            }
+        }
+        index += 1;
+    }
+    headings
+}
+
+/// reStructuredText section titles: a line underlined, and optionally
+/// overlined, with a punctuation character at least as long as the title.
+/// Levels follow the order in which adornment styles first appear.
+fn rst_headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut styles: Vec<(char, bool)> = Vec::new();
+    let mut index = 0;
+    while index + 1 < lines.len() {
+        let (overlined, title_index) = match adornment(lines[index]) {
+            Some(c) if index + 2 < lines.len() && adornment(lines[index + 2]) == Some(c) => {
+                (true, index + 1)
+            }
+            _ => (false, index),
+        };
+        let title = lines[title_index];
+        let under = lines[title_index + 1];
+        let is_title = !title.trim().is_empty()
+            && (overlined || !title.starts_with(char::is_whitespace))
+            && adornment(title).is_none()
+            && adornment(under)
+                .is_some_and(|_| under.trim_end().chars().count() >= title.trim().chars().count());
+        if !is_title {
+            index += 1;
+            continue;
+        }
+
+        let style = (adornment(under).unwrap_or('='), overlined);
+        let level = match styles.iter().position(|s| *s == style) {
+            Some(position) => position + 1,
+            None => {
+                styles.push(style);
+                styles.len()
+            }
+        };
+        let span = if overlined { 3 } else { 2 };
+        headings.push(Heading {
+            line: index,
+            span,
+            level,
+            text: title.trim().to_string(),
+        });
+        index += span;
+    }
+    headings
+}
+
+/// The adornment character of a line made of one repeated punctuation
+/// character, such as `=====`.
+fn adornment(line: &str) -> Option<char> {
+    let line = line.trim_end();
+    let first = line.chars().next()?;
+    (RST_ADORNMENTS.contains(first) && line.chars().count() >= 2 && is_run_of(line, first))
+        .then_some(first)
+}
+
+fn is_run_of(text: &str, c: char) -> bool {
+    !text.is_empty() && text.chars().all(|x| x == c)
+}
+
+/// Chunks of a document, one or more per section with text, tagged with
+/// the document's entity ID.
+pub fn chunk_document(document: &ParsedDocument, max_size: usize) -> Vec<CodeChunk> {
+    let path = &document.entity.file_path;
+    let entity_id = DocumentEntity::entity_id(path);
+    let mut chunks = Vec::new();
+
+    for section in &document.sections {
+        if section.body.iter().all(|l| l.trim().is_empty()) {
+            continue;
+        }
+        let heading_end = section.start_line + section.heading_lines;
+        let ancestors = section.headings.len().saturating_sub(1);
+        let breadcrumb = section.headings.join(" > ");
+
+        for (index, (start, end, text)) in paragraph_pieces(section, heading_end, max_size)
+            .into_iter()
+            .enumerate()
+        {
+            // The first piece carries its own heading; later ones need the
+            // full path to make sense on their own
+            let context = if index == 0 {
+                section.headings[..ancestors].join(" > ")
+            } else {
+                breadcrumb.clone()
+            };
+            let heading = if index == 0 && section.heading_lines > 0 {
+                format!(
+                    "{}\n\n",
+                    section.headings.last().cloned().unwrap_or_default()
+                )
+            } else {
+                String::new()
+            };
+            let content = if context.is_empty() {
+                format!("{}{}", heading, text)
+            } else {
+                format!("{}\n\n{}{}", context, heading, text)
+            };
+            let start = if index == 0 {
+                section.start_line
+            } else {
+                start
+            };
+            chunks.push(
+                CodeChunk::new(path.as_str(), content.trim(), start, end)
+                    .with_entity(entity_id.clone(), DOCUMENT_ENTITY_TYPE),
+            );
+        }
+    }
+    chunks
+}
+
+/// The body of a section in pieces of whole paragraphs of at most
+/// `max_size` characters (a longer paragraph is a piece of its own), with
+/// their 1-based line ranges.
+fn paragraph_pieces(
+    section: &DocumentSection,
+    first_line: u32,
+    max_size: usize,
+) -> Vec<(u32, u32, String)> {
+    let mut paragraphs: Vec<(u32, u32, String)> = Vec::new();
+    let mut current: Option<(u32, u32, String)> = None;
+    for (offset, line) in section.body.iter().enumerate() {
+        let number = first_line + offset as u32;
+        if line.trim().is_empty() {
+            paragraphs.extend(current.take());
+            continue;
+        }
+        match current.as_mut() {
+            Some((_, end, text)) => {
+                *end = number;
+                text.push('\n');
+                text.push_str(line);
+            }
+            None => current = Some((number, number, line.to_string())),
+        }
+    }
+    paragraphs.extend(current);
+
+    let mut pieces: Vec<(u32, u32, String)> = Vec::new();
+    for (start, end, text) in paragraphs {
+        match pieces.last_mut() {
+            Some((_, piece_end, piece)) if piece.len() + text.len() + 2 <= max_size => {
+                *piece_end = end;
+                piece.push_str("\n\n");
+                piece.push_str(&text);
+            }
+            _ => pieces.push((start, end, text)),
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESIGN: &str = "Intro before any heading.
+
+# Storage design
+
+Tasks live in the data directory.
+
+## Layout
+
+```
+# not a heading
+```
+
+Files are written atomically.
+
+Locking
+-------
+
+A lock file guards writes.
+";
+
+    #[test]
+    fn test_markdown_sections() {
+        let doc = parse_document("docs/design.md", DESIGN, DocumentFormat::Markdown);
+
+        assert_eq!(doc.entity.title, "Storage design");
+        assert_eq!(
+            doc.entity.headings,
+            vec!["Storage design", "Layout", "Locking"]
+        );
+        let sections: Vec<(Vec<String>, u32, u32)> = doc
+            .sections
+            .iter()
+            .map(|s| (s.headings.clone(), s.start_line, s.end_line))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                (vec![], 1, 2),
+                (vec!["Storage design".to_string()], 3, 6),
+                (
+                    vec!["Storage design".to_string(), "Layout".to_string()],
+                    7,
+                    14
+                ),
+                (
+                    vec!["Storage design".to_string(), "Locking".to_string()],
+                    15,
+                    18
+                ),
+            ]
+        );
+
+        let chunks = chunk_document(&doc, 1000);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|c| c.entity_type == DOCUMENT_ENTITY_TYPE
+            && c.entity_id.as_deref() == Some("document:docs/design.md")));
+        assert_eq!(
+            chunks[3].content,
+            "Storage design\n\nLocking\n\nA lock file guards writes."
+        );
+        assert_eq!((chunks[3].start_line, chunks[3].end_line), (15, 18));
+    }
+
+    #[test]
+    fn test_rst_sections() {
+        let rst = "=====\nGuide\n=====\n\nOverview.\n\nInstall\n=======\n\nRun it.\n\nLinux\n-----\n\nUse apt.\n";
+        let doc = parse_document("docs/guide.rst", rst, DocumentFormat::Rst);
+
+        assert_eq!(doc.entity.title, "Guide");
+        // An overlined title is a different style than the same underline
+        let headings: Vec<Vec<&str>> = doc
+            .sections
+            .iter()
+            .map(|s| s.headings.iter().map(String::as_str).collect())
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                vec!["Guide"],
+                vec!["Guide", "Install"],
+                vec!["Guide", "Install", "Linux"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_sections_split_at_paragraphs() {
+        let paragraph = "word ".repeat(30);
+        let content = format!("# Notes\n\n{p}\n\n{p}\n\n{p}\n", p = paragraph.trim());
+        let doc = parse_document("notes.md", &content, DocumentFormat::Markdown);
+
+        let chunks = chunk_document(&doc, 320);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 5));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (7, 7));
+        assert!(chunks[1].content.starts_with("Notes\n\nword"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Detection of generated and vendored code.
+//!
+//! Minified bundles, protobuf stubs and vendored dependencies match search
+//! queries without being code anyone maintains. Files are recognised by path
+//! pattern, by a "do not edit" style header, or by minified line lengths.
+
+use crate::config::{GeneratedCodeHandling, KnowledgeConfig};
+
+/// Path patterns treated as generated or vendored code.
+///
+/// A trailing `/` matches a directory anywhere in the path; a pattern with
+/// another `/` matches the whole path; anything else matches the file name.
+/// `*` matches any run of characters except `/`, `?` a single character.
+pub const DEFAULT_GENERATED_PATTERNS: &[&str] = &[
+    // Protobuf / gRPC
+    "*_pb2.py",
+    "*_pb2_grpc.py",
+    "*.pb.go",
+    "*.pb.cc",
+    "*.pb.h",
+    // Bundled and minified assets
+    "*.min.js",
+    "*.min.css",
+    "*.bundle.js",
+    // Code generators
+    "*.generated.*",
+    "*_generated.*",
+    "*.g.dart",
+    "*.freezed.dart",
+    "*.designer.cs",
+    "*.g.cs",
+    // Directories
+    "vendor/",
+    "third_party/",
+    "generated/",
+    "__generated__/",
+    "node_modules/",
+];
+
+/// Header markers of generated files, compared case-insensitively.
+const GENERATED_HEADER_MARKERS: &[&str] = &[
+    "do not edit",
+    "@generated",
+    "<auto-generated",
+    "code generated by",
+    "generated by the protocol buffer compiler",
+    "this file was generated",
+    "this file is generated",
+    "this file is automatically generated",
+    "autogenerated file",
+    "auto-generated file",
+];
+
+/// Lines at the top of a file searched for a generated header.
+const HEADER_LINES: usize = 10;
+
+/// Files smaller than this are never considered minified.
+const MINIFIED_MIN_SIZE: usize = 2048;
+
+/// Average line length above which a file is considered minified.
+const MINIFIED_AVG_LINE_LEN: usize = 300;
+
+/// Which files count as generated, and what the indexer does with them.
+#[derive(Debug, Clone)]
+pub struct GeneratedCodePolicy {
+    /// What to do with generated files.
+    pub handling: GeneratedCodeHandling,
+    /// Path patterns treated as generated.
+    patterns: Vec<String>,
+    /// Path patterns never treated as generated, overriding all heuristics.
+    allow: Vec<String>,
+}
+
+impl Default for GeneratedCodePolicy {
+    fn default() -> Self {
+        Self {
+            handling: GeneratedCodeHandling::default(),
+            patterns: DEFAULT_GENERATED_PATTERNS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+impl GeneratedCodePolicy {
+    /// Policy from `[knowledge]` config: the default patterns plus
+    /// `generated_patterns`, minus anything matching `generated_allow`.
+    pub fn from_config(config: &KnowledgeConfig) -> Self {
+        let mut policy = Self {
+            handling: config.generated_code,
+            allow: config.generated_allow.clone(),
+            ..Self::default()
+        };
+        policy
+            .patterns
+            .extend(config.generated_patterns.iter().cloned());
+        policy
+    }
+
+    /// Whether generated files are indexed like any other file.
+    pub fn is_disabled(&self) -> bool {
+        self.handling == GeneratedCodeHandling::Index
+    }
+
+    /// Whether a relative path is allowed regardless of heuristics.
+    fn is_allowed(&self, path: &str) -> bool {
+        self.allow.iter().any(|p| matches_pattern(p, path))
+    }
+
+    /// Whether a relative path matches a generated-code pattern.
+    pub fn is_generated_path(&self, path: &str) -> bool {
+        !self.is_disabled()
+            && !self.is_allowed(path)
+            && self.patterns.iter().any(|p| matches_pattern(p, path))
+    }
+
+    /// Whether a file is generated, by path, header or minified content.
+    pub fn is_generated(&self, path: &str, content: &str) -> bool {
+        if self.is_disabled() || self.is_allowed(path) {
+            return false;
+        }
+        self.patterns.iter().any(|p| matches_pattern(p, path))
+            || has_generated_header(content)
+            || looks_minified(content)
+    }
+}
+
+/// Whether the first lines of a file carry a generated-code marker.
+pub fn has_generated_header(content: &str) -> bool {
+    content.lines().take(HEADER_LINES).any(|line| {
+        let line = line.to_lowercase();
+        GENERATED_HEADER_MARKERS.iter().any(|m| line.contains(m))
+    })
+}
+
+/// Whether a file looks minified (very long average line length).
+pub fn looks_minified(content: &str) -> bool {
+    if content.len() < MINIFIED_MIN_SIZE {
+        return false;
+    }
+    let lines = content.lines().count().max(1);
+    content.len() / lines > MINIFIED_AVG_LINE_LEN
+}
+
+/// Whether a relative path matches a generated-code pattern.
+pub fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./");
+
+    if let Some(dir) = pattern.strip_suffix('/') {
+        // Any directory component, never the file name itself
+        let mut components: Vec<&str> = path.split('/').collect();
+        components.pop();
+        return if dir.contains('/') {
+            path.starts_with(&format!("{}/", dir))
+        } else {
+            components.iter().any(|c| wildcard_match(dir, c))
+        };
+    }
+
+    if pattern.contains('/') {
+        wildcard_match(pattern, path)
+    } else {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        wildcard_match(pattern, file_name)
+    }
+}
+
+/// Match `text` against a pattern with `*` (not crossing `/`), `**`
+/// (crossing `/`) and `?`.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last star, text position it absorbs up to,
+    // and whether it may absorb `/`
+    let mut star: Option<(usize, usize, bool)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                let crosses = pattern.get(p + 1) == Some(&'*');
+                p += if crosses { 2 } else { 1 };
+                star = Some((p, t, crosses));
+            }
+            Some(&c) if c == text[t] || (c == '?' && text[t] != '/') => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Backtrack: let the last `*` absorb one more character
+                Some((star_p, star_t, crosses)) if crosses || text[star_t] != '/' => {
+                    p = star_p;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1, crosses));
+                }
+                _ => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_patterns() {
+        let policy = GeneratedCodePolicy::default();
+        assert!(policy.is_generated_path("api/user_pb2.py"));
+        assert!(policy.is_generated_path("web/static/app.min.js"));
+        assert!(policy.is_generated_path("vendor/github.com/pkg/errors/errors.go"));
+        assert!(policy.is_generated_path("src/generated/schema.ts"));
+        assert!(!policy.is_generated_path("src/vendor.rs"));
+        assert!(!policy.is_generated_path("src/main.rs"));
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches_pattern("*.min.js", "a/b/c.min.js"));
+        assert!(matches_pattern("src/gen/*.rs", "src/gen/a.rs"));
+        assert!(!matches_pattern("src/gen/*.rs", "src/gen/sub/a.rs"));
+        assert!(matches_pattern("build/out/", "build/out/x.js"));
+        assert!(matches_pattern("file?.txt", "file1.txt"));
+        assert!(matches_pattern("src/legacy/**", "src/legacy/a/b.rs"));
+        assert!(matches_pattern("src/**/mod.rs", "src/a/b/mod.rs"));
+        assert!(!matches_pattern("src/**/mod.rs", "src/a/b/lib.rs"));
+    }
+
+    #[test]
+    fn test_content_heuristics() {
+        assert!(has_generated_header(
+            "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n"
+        ));
+        assert!(!has_generated_header("fn main() {}\n"));
+
+        let minified = "var a=1;".repeat(500);
+        assert!(looks_minified(&minified));
+        assert!(!looks_minified(&"let a = 1;\n".repeat(500)));
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let config = KnowledgeConfig {
+            generated_patterns: vec!["*.gen.rs".to_string()],
+            generated_allow: vec!["vendor/".to_string()],
+            ..KnowledgeConfig::default()
+        };
+        let policy = GeneratedCodePolicy::from_config(&config);
+        assert!(policy.is_generated_path("src/schema.gen.rs"));
+        assert!(!policy.is_generated_path("vendor/lib/a.go"));
+
+        let config = KnowledgeConfig {
+            generated_code: GeneratedCodeHandling::Index,
+            ..KnowledgeConfig::default()
+        };
+        let policy = GeneratedCodePolicy::from_config(&config);
+        assert!(!policy.is_generated("a.min.js", ""));
+    }
+}
@@ -2,27 +2,46 @@
 //!
 //! Uses the parser registry for language-specific AST parsing when available,
 //! falling back to regex-based extraction for unsupported languages.
+//!
+//! A directory run walks the tree, then indexes up to `jobs` files at once,
+//! each in its own task, and folds their outcomes into the run's stats. The
+//! time files spend parsing, embedding and in the database is summed up for
+//! the throughput summary of `arq init`.
 
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
 use ignore::WalkBuilder;
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use super::documents::{chunk_document, parse_document};
 use super::extractor::{extract_calls, extract_functions, extract_line_range, extract_structs};
+use super::generated::GeneratedCodePolicy;
 use super::patterns::{CHUNK_OVERLAP, DEFAULT_EXTENSIONS, MAX_CHUNK_SIZE};
+use super::sloc::count_lines;
 use super::{IndexProgress, Indexer};
+use crate::config::{
+    GeneratedCodeHandling, OversizedFileHandling, DEFAULT_HISTORY_RETENTION_DAYS,
+    DEFAULT_INDEX_MAX_FILE_SIZE, DEFAULT_INSERT_BATCH_SIZE,
+};
 use crate::knowledge::db::KnowledgeDb;
 use crate::knowledge::embedder::Embedder;
 use crate::knowledge::error::KnowledgeError;
-use crate::knowledge::models::{CodeChunk, FileNode, IndexStats};
-use crate::knowledge::parser::{ParseResult, ParsedEdge, ParsedNode, ParserRegistry};
+use crate::knowledge::models::{CodeChunk, FileNode, IndexFailure, IndexStats, IndexTimings};
+use crate::knowledge::ontology::nodes::DocumentFormat;
+use crate::knowledge::parser::{ParseResult, ParsedEdge, ParsedNode, Parser, ParserRegistry};
+use crate::knowledge::workspace::{CrateInfo, CrateMap};
 
 /// Generic indexer that works with any language.
 ///
 /// Uses AST-based parsing for supported languages (Rust) and falls back
 /// to regex-based extraction for others.
+#[derive(Clone)]
 pub struct GenericIndexer {
     db: Arc<KnowledgeDb>,
     embedder: Arc<dyn Embedder>,
@@ -30,6 +49,74 @@ pub struct GenericIndexer {
     extensions: Vec<String>,
     /// Whether to use rich ontology parsing (vs legacy regex).
     use_rich_parsing: bool,
+    /// Cargo workspace crates, used to namespace entities by crate.
+    crates: CrateMap,
+    /// Rows per multi-row insert.
+    batch_size: usize,
+    /// Detection and handling of generated/vendored files.
+    generated: GeneratedCodePolicy,
+    /// Days closed call history is kept after a run.
+    history_retention_days: u64,
+    /// Size in bytes above which a file is oversized; zero for no limit.
+    max_file_size: u64,
+    /// What to do with oversized files.
+    oversized: OversizedFileHandling,
+    /// Documentation folders, relative to the indexed root, whose markdown
+    /// and reStructuredText files are indexed as documents.
+    docs: Vec<String>,
+    /// Files indexed at once by a run.
+    jobs: usize,
+    /// Time spent per stage by the current run.
+    clock: Arc<StageClock>,
+    /// Files whose entries the current run replaced or removed.
+    changed_files: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// Extension overrides naming a language without an enabled parser.
+    unmapped_extensions: Vec<(String, String)>,
+}
+
+/// Time the files of a run spend per stage, shared by its jobs.
+#[derive(Debug, Default)]
+struct StageClock {
+    parsing: AtomicU64,
+    embedding: AtomicU64,
+    /// Total time of the files, all stages included.
+    busy: AtomicU64,
+    files: AtomicUsize,
+    chunks: AtomicUsize,
+}
+
+impl StageClock {
+    fn add(counter: &AtomicU64, elapsed: Duration) {
+        counter.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Timings of a run that started at `started`.
+    fn timings(&self, started: Instant, jobs: usize) -> IndexTimings {
+        let load = |counter: &AtomicU64| Duration::from_nanos(counter.load(Ordering::Relaxed));
+        let parsing = load(&self.parsing);
+        let embedding = load(&self.embedding);
+        IndexTimings {
+            elapsed: started.elapsed(),
+            jobs,
+            files: self.files.load(Ordering::Relaxed),
+            chunks: self.chunks.load(Ordering::Relaxed),
+            parsing,
+            embedding,
+            database: load(&self.busy).saturating_sub(parsing + embedding),
+        }
+    }
+}
+
+/// What became of a file passed to the indexer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileOutcome {
+    Indexed,
+    /// Oversized, indexed for the graph only.
+    SignaturesOnly,
+    GeneratedSkipped,
+    OversizedSkipped,
+    /// Indexed as documentation.
+    Document,
 }
 
 impl GenericIndexer {
@@ -41,6 +128,17 @@ impl GenericIndexer {
             parser_registry: ParserRegistry::new(),
             extensions: DEFAULT_EXTENSIONS.iter().map(|s| s.to_string()).collect(),
             use_rich_parsing: true,
+            crates: CrateMap::default(),
+            batch_size: DEFAULT_INSERT_BATCH_SIZE,
+            generated: GeneratedCodePolicy::default(),
+            history_retention_days: DEFAULT_HISTORY_RETENTION_DAYS,
+            max_file_size: DEFAULT_INDEX_MAX_FILE_SIZE,
+            oversized: OversizedFileHandling::default(),
+            docs: Vec::new(),
+            jobs: 1,
+            clock: Arc::default(),
+            changed_files: Arc::default(),
+            unmapped_extensions: Vec::new(),
         }
     }
 
@@ -56,6 +154,17 @@ impl GenericIndexer {
             parser_registry: ParserRegistry::new(),
             extensions,
             use_rich_parsing: true,
+            crates: CrateMap::default(),
+            batch_size: DEFAULT_INSERT_BATCH_SIZE,
+            generated: GeneratedCodePolicy::default(),
+            history_retention_days: DEFAULT_HISTORY_RETENTION_DAYS,
+            max_file_size: DEFAULT_INDEX_MAX_FILE_SIZE,
+            oversized: OversizedFileHandling::default(),
+            docs: Vec::new(),
+            jobs: 1,
+            clock: Arc::default(),
+            changed_files: Arc::default(),
+            unmapped_extensions: Vec::new(),
         }
     }
 
@@ -67,7 +176,238 @@ impl GenericIndexer {
             parser_registry: ParserRegistry::new(),
             extensions: DEFAULT_EXTENSIONS.iter().map(|s| s.to_string()).collect(),
             use_rich_parsing: false,
+            crates: CrateMap::default(),
+            batch_size: DEFAULT_INSERT_BATCH_SIZE,
+            generated: GeneratedCodePolicy::default(),
+            history_retention_days: DEFAULT_HISTORY_RETENTION_DAYS,
+            max_file_size: DEFAULT_INDEX_MAX_FILE_SIZE,
+            oversized: OversizedFileHandling::default(),
+            docs: Vec::new(),
+            jobs: 1,
+            clock: Arc::default(),
+            changed_files: Arc::default(),
+            unmapped_extensions: Vec::new(),
+        }
+    }
+
+    /// Use the given workspace crates when indexing single files.
+    ///
+    /// Directory indexing detects crates from the indexed root on its own.
+    pub fn with_crates(mut self, crates: CrateMap) -> Self {
+        self.crates = crates;
+        self
+    }
+
+    /// Set the number of rows per multi-row insert.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set how generated and vendored files are detected and handled.
+    pub fn with_generated_code(mut self, policy: GeneratedCodePolicy) -> Self {
+        self.generated = policy;
+        self
+    }
+
+    /// Set how many days closed call history is kept; zero keeps none.
+    pub fn with_history_retention(mut self, days: u64) -> Self {
+        self.history_retention_days = days;
+        self
+    }
+
+    /// Set the size above which files are oversized, and what happens to
+    /// them. A `max_file_size` of zero disables the limit.
+    pub fn with_size_limit(mut self, max_file_size: u64, handling: OversizedFileHandling) -> Self {
+        self.max_file_size = max_file_size;
+        self.oversized = handling;
+        self
+    }
+
+    /// Index the markdown and reStructuredText files under `dirs` (relative
+    /// to the indexed root) as documents, chunked by section, instead of as
+    /// code.
+    pub fn with_docs(mut self, dirs: &[String]) -> Self {
+        self.docs = dirs
+            .iter()
+            .map(|dir| {
+                let dir = dir.trim_end_matches('/');
+                let dir = dir.strip_prefix("./").unwrap_or(dir);
+                match dir {
+                    "" => ".".to_string(),
+                    dir => dir.to_string(),
+                }
+            })
+            .collect();
+        self
+    }
+
+    /// Set how many files a run indexes at once; zero uses one per CPU.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = match jobs {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            jobs => jobs,
+        };
+        self
+    }
+
+    /// A copy of this indexer with a fresh clock, for one run.
+    fn for_run(&self) -> Arc<Self> {
+        Arc::new(Self {
+            clock: Arc::default(),
+            changed_files: Arc::default(),
+            ..self.clone()
+        })
+    }
+
+    /// Remove the entries of a file, noting it as changed by the run.
+    async fn remove_file(&self, path: &str) -> Result<(), KnowledgeError> {
+        if let Ok(mut changed) = self.changed_files.lock() {
+            changed.insert(path.to_string());
+        }
+        self.db.remove_file(path).await
+    }
+
+    /// Format of a file, relative to the root, that is indexed as a document.
+    fn document_format(&self, relative_path: &str) -> Option<DocumentFormat> {
+        let format = DocumentFormat::from_path(relative_path)?;
+        self.docs
+            .iter()
+            .any(|dir| dir == "." || relative_path.starts_with(&format!("{}/", dir)))
+            .then_some(format)
+    }
+
+    /// Whether `content` is above the size limit.
+    fn is_oversized(&self, content: &str) -> bool {
+        self.max_file_size > 0 && content.len() as u64 > self.max_file_size
+    }
+
+    /// Register additional parsers, indexing files with their extensions.
+    ///
+    /// A parser replaces any built-in parser for the same extension.
+    pub fn with_parsers(mut self, parsers: &[Arc<dyn Parser>]) -> Self {
+        for parser in parsers {
+            for ext in parser.supported_extensions() {
+                if !self.extensions.iter().any(|e| e == ext) {
+                    self.extensions.push(ext.to_string());
+                }
+            }
+            self.parser_registry.register(Arc::clone(parser));
+        }
+        self
+    }
+
+    /// Leave `disabled_languages` unparsed and unindexed, and parse and index
+    /// the extensions of `extension_overrides` with the parser of the
+    /// language each maps to.
+    ///
+    /// Call after [`Self::with_parsers`] so external parsers can be disabled
+    /// or mapped to as well. Overrides naming a language without an enabled
+    /// parser are skipped (see [`Self::unmapped_extensions`]).
+    pub fn with_languages(
+        mut self,
+        disabled_languages: &[String],
+        extension_overrides: &BTreeMap<String, String>,
+    ) -> Self {
+        for language in disabled_languages {
+            let removed = self.parser_registry.disable_language(language);
+            self.extensions.retain(|ext| !removed.contains(ext));
+        }
+        for (extension, language) in extension_overrides {
+            let extension = extension.trim_start_matches('.').to_lowercase();
+            if self.parser_registry.map_extension(&extension, language) {
+                if !self.extensions.contains(&extension) {
+                    self.extensions.push(extension);
+                }
+            } else {
+                self.unmapped_extensions.push((extension, language.clone()));
+            }
+        }
+        self
+    }
+
+    /// Extensions of `extension_overrides`, and the language each maps to,
+    /// left out by [`Self::with_languages`] because that language has no
+    /// enabled parser.
+    pub fn unmapped_extensions(&self) -> &[(String, String)] {
+        &self.unmapped_extensions
+    }
+
+    /// Documentation files under the docs folders of `root`, in walk order.
+    fn document_files(&self, root: &Path) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
+        for dir in &self.docs {
+            let walker = WalkBuilder::new(root.join(dir))
+                .hidden(true)
+                .git_ignore(true)
+                .build();
+            for entry in walker.flatten() {
+                let file_path = entry.path();
+                if file_path.is_file()
+                    && DocumentFormat::from_path(file_path).is_some()
+                    && seen.insert(file_path.to_path_buf())
+                {
+                    files.push(file_path.to_path_buf());
+                }
+            }
         }
+        files
+    }
+
+    /// Documentation files of `root` as run entries.
+    fn document_entries(&self, root: &Path) -> Vec<(PathBuf, String)> {
+        self.document_files(root)
+            .into_iter()
+            .map(|file_path| {
+                let relative_path = relative_to(root, &file_path);
+                (file_path, relative_path)
+            })
+            .collect()
+    }
+
+    /// Index only the documentation folders of `root`, leaving the code
+    /// index as it is.
+    pub async fn index_documents_with_progress<F>(
+        &self,
+        root: &Path,
+        on_progress: F,
+    ) -> Result<IndexStats, KnowledgeError>
+    where
+        F: Fn(IndexProgress) + Send + Sync,
+    {
+        let started = Instant::now();
+        let run = self.for_run();
+        let mut stats = IndexStats::default();
+        let entries = run.document_entries(root);
+        let total = entries.len();
+        let crates = Arc::new(run.crates.clone());
+        run.index_entries(&crates, entries, &on_progress, 0, total, &mut stats)
+            .await;
+
+        stats.timings = run.clock.timings(started, run.jobs);
+        stats.last_updated = Some(chrono::Utc::now());
+        let db_stats = self.db.get_stats().await?;
+        stats.chunks = db_stats.chunks;
+        stats.structs = db_stats.structs;
+        stats.functions = db_stats.functions;
+        Ok(stats)
+    }
+
+    /// Whether a file under `root` is skipped by its generated-code path pattern.
+    fn is_skipped_path(&self, root: &Path, path: &Path) -> bool {
+        if self.generated.handling != GeneratedCodeHandling::Skip {
+            return false;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.generated
+            .is_generated_path(&relative.to_string_lossy())
+    }
+
+    /// Whether a file under `root` would be indexed by a directory run.
+    pub(crate) fn is_indexable(&self, root: &Path, path: &Path) -> bool {
+        self.document_format(&relative_to(root, path)).is_some()
+            || self.should_index(path) && !self.is_skipped_path(root, path)
     }
 
     /// Check if file extension is in the allowed list.
@@ -141,12 +481,24 @@ impl GenericIndexer {
     /// Index structs and functions, creating graph relations.
     ///
     /// Uses rich AST-based parsing when available, falling back to regex.
-    async fn index_code_entities(&self, path: &str, content: &str) -> Result<(), KnowledgeError> {
+    async fn index_code_entities(
+        &self,
+        path: &str,
+        content: &str,
+        crate_info: Option<&CrateInfo>,
+    ) -> Result<(), KnowledgeError> {
         // Try rich parsing first if enabled
         if self.use_rich_parsing {
             if let Some(parser) = self.parser_registry.parser_for_path(path) {
-                match parser.parse_file(path, content) {
-                    Ok(result) => {
+                let started = Instant::now();
+                let parsed = parser.parse_file(path, content);
+                StageClock::add(&self.clock.parsing, started.elapsed());
+                match parsed {
+                    Ok(mut result) => {
+                        // Namespace Rust entities by crate: `arq_core::KnowledgeGraph`
+                        if let Some(info) = crate_info.filter(|_| path.ends_with(".rs")) {
+                            result.prefix_qualified_names(&info.ident());
+                        }
                         return self.index_rich_entities(result).await;
                     }
                     Err(e) => {
@@ -165,47 +517,78 @@ impl GenericIndexer {
     }
 
     /// Index using rich ontology entities from AST parsing.
+    ///
+    /// Nodes are grouped by table and edges by relation so each group is
+    /// written with a few multi-row inserts instead of one round-trip per row.
+    /// A failed insert is retried row by row, so one bad row only loses
+    /// itself; rows that still fail are reported as the file's failure.
     async fn index_rich_entities(&self, result: ParseResult) -> Result<(), KnowledgeError> {
-        // Insert all nodes
+        let mut tables: HashMap<&str, Vec<serde_json::Value>> = HashMap::new();
         for node in &result.nodes {
-            match node {
-                ParsedNode::Function(f) => {
-                    let _ = self.db.insert_function_entity(f).await;
-                }
-                ParsedNode::Struct(s) => {
-                    let _ = self.db.insert_struct_entity(s).await;
-                }
-                ParsedNode::Trait(t) => {
-                    let _ = self.db.insert_trait_entity(t).await;
-                }
-                ParsedNode::Impl(i) => {
-                    let _ = self.db.insert_impl_entity(i).await;
-                }
-                ParsedNode::Enum(e) => {
-                    let _ = self.db.insert_enum_entity(e).await;
-                }
-                ParsedNode::Constant(c) => {
-                    let _ = self.db.insert_const_entity(c).await;
+            let (table, row) = match node {
+                ParsedNode::Function(f) => ("fn_node", serde_json::to_value(f)),
+                ParsedNode::Struct(s) => ("struct_node", serde_json::to_value(s)),
+                ParsedNode::Trait(t) => ("trait_node", serde_json::to_value(t)),
+                ParsedNode::Impl(i) => ("impl_node", serde_json::to_value(i)),
+                ParsedNode::Enum(e) => ("enum_node", serde_json::to_value(e)),
+                ParsedNode::Constant(c) => ("const_node", serde_json::to_value(c)),
+                ParsedNode::Schema(schema) => ("schema_node", serde_json::to_value(schema)),
+                ParsedNode::Operation(o) => ("operation_node", serde_json::to_value(o)),
+            };
+            if let Ok(row) = row {
+                tables.entry(table).or_default().push(row);
+            }
+        }
+
+        let mut failed = Vec::new();
+        for (table, rows) in &tables {
+            if self
+                .db
+                .insert_batch(table, rows, self.batch_size)
+                .await
+                .is_ok()
+            {
+                continue;
+            }
+            for row in rows {
+                if let Err(e) = self
+                    .db
+                    .insert_batch(table, std::slice::from_ref(row), 1)
+                    .await
+                {
+                    failed.push(format!("{}: {}", table, e));
                 }
             }
         }
 
-        // Insert all edges
-        for edge in &result.edges {
-            let (from, relation, to) = match edge {
-                ParsedEdge::Contains(e) => (&e.from, "contains", &e.to),
-                ParsedEdge::Calls(e) => (&e.from, "calls", &e.to),
-                ParsedEdge::Implements(e) => (&e.from, "implements", &e.to),
-                ParsedEdge::Extends(e) => (&e.from, "extends", &e.to),
-                ParsedEdge::UsesType(e) => (&e.from, "uses_type", &e.to),
-                ParsedEdge::ReturnsType(e) => (&e.from, "returns_type", &e.to),
-                ParsedEdge::HasField(e) => (&e.from, "has_field", &e.to),
-                ParsedEdge::Imports(e) => (&e.from, "imports", &e.to),
-            };
-            let _ = self.db.create_relation(from, relation, to).await;
+        let edges: Vec<(&str, &str, &str)> = result
+            .edges
+            .iter()
+            .map(|edge| match edge {
+                ParsedEdge::Contains(e) => (e.from.as_str(), "contains", e.to.as_str()),
+                ParsedEdge::Calls(e) => (e.from.as_str(), "calls", e.to.as_str()),
+                ParsedEdge::Implements(e) => (e.from.as_str(), "implements", e.to.as_str()),
+                ParsedEdge::Extends(e) => (e.from.as_str(), "extends", e.to.as_str()),
+                ParsedEdge::UsesType(e) => (e.from.as_str(), "uses_type", e.to.as_str()),
+                ParsedEdge::ReturnsType(e) => (e.from.as_str(), "returns_type", e.to.as_str()),
+                ParsedEdge::HasField(e) => (e.from.as_str(), "has_field", e.to.as_str()),
+                ParsedEdge::Imports(e) => (e.from.as_str(), "imports", e.to.as_str()),
+                ParsedEdge::Consumes(e) => (e.from.as_str(), "consumes", e.to.as_str()),
+                ParsedEdge::Produces(e) => (e.from.as_str(), "produces", e.to.as_str()),
+            })
+            .collect();
+        if let Err(e) = self.db.store_edges(&edges, self.batch_size).await {
+            failed.push(format!("edges: {}", e));
         }
 
-        Ok(())
+        match failed.first() {
+            None => Ok(()),
+            Some(first) => Err(KnowledgeError::Database(format!(
+                "{} entity insert(s) failed, first {}",
+                failed.len(),
+                first
+            ))),
+        }
     }
 
     /// Legacy regex-based entity extraction.
@@ -214,8 +597,10 @@ impl GenericIndexer {
         path: &str,
         content: &str,
     ) -> Result<(), KnowledgeError> {
+        let started = Instant::now();
         let structs = extract_structs(content, path);
         let functions = extract_functions(content, path);
+        StageClock::add(&self.clock.parsing, started.elapsed());
 
         // Store structs and create contains relations
         for s in &structs {
@@ -246,23 +631,328 @@ impl GenericIndexer {
     }
 
     /// Generate and store embeddings for code chunks.
-    async fn index_embeddings(&self, path: &str, content: &str) -> Result<(), KnowledgeError> {
+    async fn index_embeddings(
+        &self,
+        path: &str,
+        content: &str,
+        crate_info: Option<&CrateInfo>,
+    ) -> Result<(), KnowledgeError> {
+        let started = Instant::now();
         let mut chunks = Self::chunk_content(content, path);
+        StageClock::add(&self.clock.parsing, started.elapsed());
+        if let Some(info) = crate_info {
+            chunks = chunks
+                .into_iter()
+                .map(|c| c.with_crate(&info.name))
+                .collect();
+        }
 
+        self.store_chunks(chunks).await
+    }
+
+    /// Embed chunks and store them.
+    async fn store_chunks(&self, mut chunks: Vec<CodeChunk>) -> Result<(), KnowledgeError> {
         if chunks.is_empty() {
             return Ok(());
         }
 
         let texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
+        let started = Instant::now();
         let embeddings = self.embedder.embed(&texts)?;
+        StageClock::add(&self.clock.embedding, started.elapsed());
+        self.clock.chunks.fetch_add(chunks.len(), Ordering::Relaxed);
 
         for (chunk, embedding) in chunks.iter_mut().zip(embeddings) {
             chunk.embedding = embedding;
-            self.db.insert_chunk(chunk).await?;
         }
 
+        self.db.insert_chunks(&chunks, self.batch_size).await
+    }
+
+    /// Index a documentation file: a document node and its section chunks.
+    async fn index_document_in(
+        &self,
+        path: &str,
+        content: &str,
+        format: DocumentFormat,
+    ) -> Result<FileOutcome, KnowledgeError> {
+        let hash = Self::compute_hash(content);
+        let file_node = FileNode::new(path, &hash, content.len() as u64);
+        let line_stats = count_lines(content, &file_node.extension);
+        let file_node = file_node.with_line_stats(line_stats);
+
+        // Skip if unchanged, unless it was indexed as code before
+        if let Some(existing) = self.db.get_file(path).await? {
+            if existing.hash == hash && self.db.has_document(path).await? {
+                return Ok(FileOutcome::Document);
+            }
+        }
+
+        self.remove_file(path).await?;
+        self.db.upsert_file(&file_node).await?;
+
+        let started = Instant::now();
+        let document = parse_document(path, content, format);
+        let chunks = chunk_document(&document, MAX_CHUNK_SIZE);
+        StageClock::add(&self.clock.parsing, started.elapsed());
+        self.db
+            .insert_batch(
+                "doc_node",
+                std::slice::from_ref(&document.entity),
+                self.batch_size,
+            )
+            .await?;
+        self.store_chunks(chunks).await?;
+
+        Ok(FileOutcome::Document)
+    }
+
+    /// Index a single file, attributing it to its workspace crate.
+    async fn index_file_in(
+        &self,
+        crates: &CrateMap,
+        path: &str,
+        content: &str,
+    ) -> Result<FileOutcome, KnowledgeError> {
+        let generated = self.generated.is_generated(path, content);
+        if generated && self.generated.handling == GeneratedCodeHandling::Skip {
+            // Drop anything indexed before the file was recognised as generated
+            self.remove_file(path).await?;
+            return Ok(FileOutcome::GeneratedSkipped);
+        }
+        let oversized = self.is_oversized(content);
+        if oversized && self.oversized == OversizedFileHandling::Skip {
+            self.remove_file(path).await?;
+            return Ok(FileOutcome::OversizedSkipped);
+        }
+        let outcome = if oversized {
+            FileOutcome::SignaturesOnly
+        } else {
+            FileOutcome::Indexed
+        };
+
+        let hash = Self::compute_hash(content);
+        let file_node = FileNode::new(path, &hash, content.len() as u64);
+        let line_stats = count_lines(content, &file_node.extension);
+        let file_node = file_node.with_line_stats(line_stats);
+
+        // Skip if unchanged, filling in line counts for files indexed before they were recorded
+        if let Some(existing) = self.db.get_file(path).await? {
+            if existing.hash == hash {
+                if existing.language.is_none() {
+                    self.db.update_file_stats(&file_node).await?;
+                }
+                return Ok(outcome);
+            }
+        }
+
+        // Remove old data and create new file node
+        self.remove_file(path).await?;
+        self.db.upsert_file(&file_node).await?;
+
+        let crate_info = crates.crate_for_path(path);
+
+        // Index code entities (structs, functions, relations), dropping the
+        // file on failure so that the next run indexes it again rather than
+        // skipping it as unchanged
+        if let Err(e) = self.index_code_entities(path, content, crate_info).await {
+            let _ = self.remove_file(path).await;
+            return Err(e);
+        }
+
+        // Generated code stays out of semantic search in graph-only mode, and
+        // oversized files keep only their signatures: chunking and embedding
+        // them would dominate the run
+        if !generated && !oversized {
+            self.index_embeddings(path, content, crate_info).await?;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Read and index one file of a run.
+    async fn index_entry(
+        &self,
+        crates: &CrateMap,
+        file_path: &Path,
+        relative_path: &str,
+    ) -> Result<(FileOutcome, u64), KnowledgeError> {
+        let started = Instant::now();
+        let result = match fs::read_to_string(file_path) {
+            Ok(content) => match self.document_format(relative_path) {
+                Some(format) => {
+                    self.index_document_in(relative_path, &content, format)
+                        .await
+                }
+                None => self.index_file_in(crates, relative_path, &content).await,
+            }
+            .map(|outcome| (outcome, content.len() as u64)),
+            Err(source) => Err(KnowledgeError::Io {
+                path: file_path.to_path_buf(),
+                source,
+            }),
+        };
+        if result.is_err() {
+            // Drop the partial record so the next run doesn't see the file as unchanged
+            let _ = self.remove_file(relative_path).await;
+        }
+        StageClock::add(&self.clock.busy, started.elapsed());
+        self.clock.files.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    /// Add the outcome of a file to `stats`, recording a failure instead of
+    /// stopping the run.
+    fn record_entry(
+        &self,
+        relative_path: String,
+        result: Result<(FileOutcome, u64), KnowledgeError>,
+        stats: &mut IndexStats,
+    ) {
+        match result {
+            Ok((outcome @ (FileOutcome::Indexed | FileOutcome::SignaturesOnly), size)) => {
+                stats.files += 1;
+                stats.total_size += size;
+                if outcome == FileOutcome::SignaturesOnly {
+                    stats.signatures_only += 1;
+                }
+            }
+            Ok((FileOutcome::Document, size)) => {
+                stats.documents += 1;
+                stats.total_size += size;
+            }
+            Ok((FileOutcome::GeneratedSkipped, _)) => stats.generated_skipped += 1,
+            Ok((FileOutcome::OversizedSkipped, _)) => stats.oversized_skipped += 1,
+            Err(e) => stats.report.failures.push(IndexFailure {
+                error: e.to_string(),
+                parser: self
+                    .parser_registry
+                    .parser_for_path(&relative_path)
+                    .map(|p| p.language_name().to_string()),
+                file: relative_path,
+            }),
+        }
+    }
+
+    /// Index `entries` (file path, path relative to the root) with up to
+    /// `jobs` files at once, reporting each file as it starts.
+    ///
+    /// `done` files of `total` were indexed before these.
+    async fn index_entries<F>(
+        self: &Arc<Self>,
+        crates: &Arc<CrateMap>,
+        entries: Vec<(PathBuf, String)>,
+        on_progress: &F,
+        done: usize,
+        total: usize,
+        stats: &mut IndexStats,
+    ) where
+        F: Fn(IndexProgress) + Send + Sync,
+    {
+        let done = &AtomicUsize::new(done);
+        let mut results = stream::iter(entries)
+            .map(|(file_path, relative_path)| {
+                on_progress(IndexProgress {
+                    current_file: relative_path.clone(),
+                    files_done: done.load(Ordering::Relaxed),
+                    files_total: total,
+                });
+                let indexer = Arc::clone(self);
+                let crates = Arc::clone(crates);
+                tokio::spawn(async move {
+                    let result = indexer
+                        .index_entry(&crates, &file_path, &relative_path)
+                        .await;
+                    (relative_path, result)
+                })
+            })
+            .buffer_unordered(self.jobs.max(1));
+
+        while let Some(joined) = results.next().await {
+            let (relative_path, result) =
+                joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            done.fetch_add(1, Ordering::Relaxed);
+            self.record_entry(relative_path, result, stats);
+        }
+    }
+
+    /// Crates of a run over `root`: the configured ones, or those detected.
+    fn run_crates(&self, root: &Path) -> CrateMap {
+        if self.crates.is_empty() {
+            CrateMap::detect(root)
+        } else {
+            self.crates.clone()
+        }
+    }
+
+    /// Link entities across files and fill in the totals once a run's files
+    /// are indexed.
+    async fn finish_run(
+        &self,
+        crates: &CrateMap,
+        stats: &mut IndexStats,
+    ) -> Result<(), KnowledgeError> {
+        // Link calls across crates now that every crate's functions are indexed
+        if !crates.is_empty() {
+            self.db.resolve_calls(crates).await?;
+        }
+
+        // Link GraphQL operations to resolvers now that all code is indexed
+        let changed: Vec<String> = self
+            .changed_files
+            .lock()
+            .map(|changed| changed.iter().cloned().collect())
+            .unwrap_or_default();
+        self.db.link_graphql_resolvers(&changed).await?;
+
+        // Go types satisfy interfaces implicitly; match their method sets
+        self.db.link_go_interfaces().await?;
+
+        // Cap the history left behind by re-indexed files
+        self.db.prune_history(self.history_retention_days).await?;
+
+        stats.last_updated = Some(chrono::Utc::now());
+
+        // Get counts from DB
+        let db_stats = self.db.get_stats().await?;
+        stats.chunks = db_stats.chunks;
+        stats.structs = db_stats.structs;
+        stats.functions = db_stats.functions;
         Ok(())
     }
+
+    /// Index only `files`, given relative to `root`, such as the failures of
+    /// an earlier run. Files that no longer exist are skipped.
+    pub async fn index_files_with_progress<F>(
+        &self,
+        root: &Path,
+        files: &[String],
+        on_progress: F,
+    ) -> Result<IndexStats, KnowledgeError>
+    where
+        F: Fn(IndexProgress) + Send + Sync,
+    {
+        let started = Instant::now();
+        let run = self.for_run();
+        let mut stats = IndexStats::default();
+        let crates = Arc::new(run.run_crates(root));
+
+        let mut entries = Vec::new();
+        for relative_path in files {
+            let file_path = root.join(relative_path);
+            if !file_path.is_file() {
+                run.remove_file(relative_path).await?;
+                continue;
+            }
+            entries.push((file_path, relative_path.clone()));
+        }
+        run.index_entries(&crates, entries, &on_progress, 0, files.len(), &mut stats)
+            .await;
+
+        run.finish_run(&crates, &mut stats).await?;
+        stats.timings = run.clock.timings(started, run.jobs);
+        Ok(stats)
+    }
 }
 
 #[async_trait]
@@ -270,13 +960,19 @@ impl Indexer for GenericIndexer {
     fn count_indexable_files(&self, path: &Path) -> usize {
         let walker = WalkBuilder::new(path).hidden(true).git_ignore(true).build();
 
-        walker
+        let code = walker
             .flatten()
             .filter(|entry| {
                 let file_path = entry.path();
-                file_path.is_file() && self.should_index(file_path)
+                file_path.is_file()
+                    && self.should_index(file_path)
+                    && !self.is_skipped_path(path, file_path)
+                    && self
+                        .document_format(&relative_to(path, file_path))
+                        .is_none()
             })
-            .count()
+            .count();
+        code + self.document_files(path).len()
     }
 
     async fn index_directory(&self, path: &Path) -> Result<IndexStats, KnowledgeError> {
@@ -292,10 +988,14 @@ impl Indexer for GenericIndexer {
     where
         F: Fn(IndexProgress) + Send + Sync,
     {
+        let started = Instant::now();
+        let run = self.for_run();
         let mut stats = IndexStats::default();
         let total = self.count_indexable_files(path);
+        let crates = Arc::new(run.run_crates(path));
 
         let walker = WalkBuilder::new(path).hidden(true).git_ignore(true).build();
+        let mut entries = Vec::new();
 
         for entry in walker.flatten() {
             let file_path = entry.path();
@@ -303,67 +1003,50 @@ impl Indexer for GenericIndexer {
             if !file_path.is_file() || !self.should_index(file_path) {
                 continue;
             }
+            if self.is_skipped_path(path, file_path) {
+                stats.generated_skipped += 1;
+                continue;
+            }
 
-            let relative_path = file_path
-                .strip_prefix(path)
-                .unwrap_or(file_path)
-                .to_string_lossy()
-                .to_string();
-
-            // Report progress
-            on_progress(IndexProgress {
-                current_file: relative_path.clone(),
-                files_done: stats.files,
-                files_total: total,
-            });
-
-            match fs::read_to_string(file_path) {
-                Ok(content) => {
-                    if let Err(e) = self.index_file(&relative_path, &content).await {
-                        eprintln!("Warning: Failed to index {}: {}", relative_path, e);
-                        continue;
-                    }
-                    stats.files += 1;
-                    stats.total_size += content.len() as u64;
-                }
-                Err(e) => {
-                    eprintln!("Warning: Failed to read {}: {}", relative_path, e);
-                }
+            let relative_path = relative_to(path, file_path);
+            // Documentation is indexed after the code
+            if self.document_format(&relative_path).is_some() {
+                continue;
             }
+
+            entries.push((file_path.to_path_buf(), relative_path));
         }
+        let code_files = entries.len();
+        run.index_entries(&crates, entries, &on_progress, 0, total, &mut stats)
+            .await;
 
-        stats.last_updated = Some(chrono::Utc::now());
-
-        // Get counts from DB
-        let db_stats = self.db.get_stats().await?;
-        stats.chunks = db_stats.chunks;
-        stats.structs = db_stats.structs;
-        stats.functions = db_stats.functions;
+        let documents = run.document_entries(path);
+        run.index_entries(
+            &crates,
+            documents,
+            &on_progress,
+            code_files,
+            total,
+            &mut stats,
+        )
+        .await;
 
+        run.finish_run(&crates, &mut stats).await?;
+        stats.timings = run.clock.timings(started, run.jobs);
         Ok(stats)
     }
 
     async fn index_file(&self, path: &str, content: &str) -> Result<(), KnowledgeError> {
-        let hash = Self::compute_hash(content);
-
-        // Skip if unchanged
-        if let Some(existing) = self.db.get_file(path).await? {
-            if existing.hash == hash {
-                return Ok(());
-            }
-        }
-
-        // Remove old data and create new file node
-        self.db.remove_file(path).await?;
-        let file_node = FileNode::new(path, &hash, content.len() as u64);
-        self.db.upsert_file(&file_node).await?;
-
-        // Index code entities (structs, functions, relations)
-        self.index_code_entities(path, content).await?;
-
-        // Index embeddings
-        self.index_embeddings(path, content).await?;
-
-        Ok(())
+        self.index_file_in(&self.crates, path, content)
+            .await
+            .map(|_| ())
     }
 }
+
+/// Path of a file relative to `root`, as stored in the index.
+fn relative_to(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
@@ -1,11 +1,22 @@
 //! Code indexing for the knowledge graph.
 
+mod documents;
 mod extractor;
+mod generated;
 mod generic;
 mod patterns;
-
+mod sloc;
+
+pub use documents::{
+    chunk_document, parse_document, DocumentSection, ParsedDocument, DOCUMENT_ENTITY_TYPE,
+};
+pub use generated::{
+    has_generated_header, looks_minified, matches_pattern, GeneratedCodePolicy,
+    DEFAULT_GENERATED_PATTERNS,
+};
 pub use generic::GenericIndexer;
 pub use patterns::{CHUNK_OVERLAP, DEFAULT_EXTENSIONS, MAX_CHUNK_SIZE};
+pub use sloc::{count_lines, language_name, LineStats};
 
 use async_trait::async_trait;
 use std::path::Path;
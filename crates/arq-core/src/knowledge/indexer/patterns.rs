@@ -13,7 +13,8 @@ pub const DEFAULT_EXTENSIONS: &[&str] = &[
     "html", "css", "scss", // Config
     "yaml", "yml", "toml", "json", // Docs
     "md",   // Database
-    "sql",
+    "sql",  // API schemas
+    "graphql", "gql",
 ];
 
 /// Maximum chunk size in characters.
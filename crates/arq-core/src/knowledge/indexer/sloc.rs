@@ -0,0 +1,178 @@
+//! Line counts per file: code, comment and blank lines.
+//!
+//! A tokei-style count by comment syntax per extension. String literals are
+//! not tokenized, so a comment marker inside a string can misclassify the
+//! rest of that line; totals are meant as an overview, not an exact measure.
+
+use serde::{Deserialize, Serialize};
+
+use crate::knowledge::ontology::nodes::Language;
+
+/// Line counts of a file or group of files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineStats {
+    /// Total lines
+    pub lines: u32,
+    /// Lines holding code, including lines with a trailing comment
+    pub code: u32,
+    /// Lines holding only comments
+    pub comments: u32,
+    /// Empty or whitespace-only lines
+    pub blanks: u32,
+}
+
+/// Comment markers of a language.
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: &'static [(&'static str, &'static str)],
+}
+
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+const HTML_BLOCK: &[(&str, &str)] = &[("<!--", "-->")];
+
+impl CommentSyntax {
+    fn for_extension(extension: &str) -> Self {
+        let (line, block): (
+            &'static [&'static str],
+            &'static [(&'static str, &'static str)],
+        ) = match extension.to_lowercase().as_str() {
+            "rs" | "c" | "h" | "cpp" | "cc" | "cxx" | "hpp" | "hxx" | "go" | "java" | "kt"
+            | "kts" | "scala" | "cs" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "swift"
+            | "scss" | "dart" | "zig" => (&["//"], C_BLOCK),
+            "php" => (&["//", "#"], C_BLOCK),
+            "css" => (&[], C_BLOCK),
+            "fs" => (&["//"], &[("(*", "*)")]),
+            "ml" | "mli" => (&[], &[("(*", "*)")]),
+            "py" | "pyi" | "rb" | "sh" | "bash" | "yaml" | "yml" | "toml" | "ex" | "exs" | "r"
+            | "pl" | "graphql" | "gql" => (&["#"], &[]),
+            "sql" => (&["--"], C_BLOCK),
+            "hs" => (&["--"], &[("{-", "-}")]),
+            "clj" | "cljs" | "lisp" | "el" => (&[";"], &[]),
+            "html" | "htm" | "xml" | "md" | "markdown" => (&[], HTML_BLOCK),
+            "vue" | "svelte" => (&["//"], &[("<!--", "-->"), ("/*", "*/")]),
+            _ => (&[], &[]),
+        };
+        Self { line, block }
+    }
+
+    /// Whether a non-blank line holds code, tracking an open block comment
+    /// across lines in `in_block` (the marker that closes it).
+    fn has_code(&self, mut rest: &str, in_block: &mut Option<&'static str>) -> bool {
+        let mut code = false;
+        loop {
+            if let Some(end) = *in_block {
+                match rest.find(end) {
+                    Some(i) => {
+                        rest = &rest[i + end.len()..];
+                        *in_block = None;
+                    }
+                    None => return code,
+                }
+            }
+
+            rest = rest.trim_start();
+            if rest.is_empty() || self.line.iter().any(|marker| rest.starts_with(marker)) {
+                return code;
+            }
+            if let Some(&(start, end)) = self
+                .block
+                .iter()
+                .find(|(start, _)| rest.starts_with(*start))
+            {
+                rest = &rest[start.len()..];
+                *in_block = Some(end);
+                continue;
+            }
+
+            // Code up to the next comment marker, if any
+            code = true;
+            match self.next_marker(rest) {
+                Some(i) => rest = &rest[i..],
+                None => return true,
+            }
+        }
+    }
+
+    /// Byte offset of the first comment marker after the start of `text`.
+    fn next_marker(&self, text: &str) -> Option<usize> {
+        self.line
+            .iter()
+            .chain(self.block.iter().map(|(start, _)| start))
+            .filter_map(|marker| text.match_indices(marker).map(|(i, _)| i).find(|&i| i > 0))
+            .min()
+    }
+}
+
+/// Count code, comment and blank lines of a file with the given extension.
+///
+/// Files with an unknown extension count every non-blank line as code.
+pub fn count_lines(content: &str, extension: &str) -> LineStats {
+    let syntax = CommentSyntax::for_extension(extension);
+    let mut stats = LineStats::default();
+    let mut in_block = None;
+
+    for line in content.lines() {
+        stats.lines += 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            stats.blanks += 1;
+        } else if syntax.has_code(trimmed, &mut in_block) {
+            stats.code += 1;
+        } else {
+            stats.comments += 1;
+        }
+    }
+    stats
+}
+
+/// Language name recorded for a file: the ontology language when known,
+/// otherwise the lowercase extension.
+pub fn language_name(extension: &str) -> String {
+    match Language::from_extension(extension) {
+        Language::Unknown if !extension.is_empty() => extension.to_lowercase(),
+        language => language.as_str().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_counts() {
+        let source = "//! Module docs\n\nuse std::io;\n\n/* block\n   comment */\nfn main() { // trailing\n    /* inline */ let x = 1;\n}\n";
+        let stats = count_lines(source, "rs");
+        assert_eq!(
+            stats,
+            LineStats {
+                lines: 9,
+                code: 4,
+                comments: 3,
+                blanks: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_hash_comments_and_unknown_extensions() {
+        let stats = count_lines("# comment\nx = 1  # trailing\n\n", "py");
+        assert_eq!((stats.code, stats.comments, stats.blanks), (1, 1, 1));
+
+        let stats = count_lines("# not a comment here\n", "txt");
+        assert_eq!(stats.code, 1);
+    }
+
+    #[test]
+    fn test_block_comment_closing_before_code() {
+        let stats = count_lines("<!-- header\n-->\n<p>hi</p> <!-- note -->\n", "html");
+        assert_eq!((stats.code, stats.comments), (1, 2));
+    }
+
+    #[test]
+    fn test_language_name() {
+        assert_eq!(language_name("rs"), "rust");
+        assert_eq!(language_name("TSX"), "typescript");
+        assert_eq!(language_name("sql"), "sql");
+        assert_eq!(language_name(""), "unknown");
+    }
+}
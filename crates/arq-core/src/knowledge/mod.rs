@@ -9,14 +9,17 @@
 //!
 //! - [`KnowledgeGraph`] - Main facade implementing [`KnowledgeStore`]
 //! - [`KnowledgeDb`] - SurrealDB embedded database with HNSW vector index
-//! - [`Embedder`] - Local embedding generation using fastembed
+//! - [`Embedder`] - Local embedding generation using fastembed, loaded lazily
+//!   by [`LazyEmbedder`] or served warm by `arq embedder serve`
 //! - [`indexer::GenericIndexer`] - Code chunking and indexing
+//! - [`CrateMap`] - Cargo workspace detection for crate-qualified names
 //!
 //! # Storage
 //!
 //! Uses SurrealDB embedded with RocksDB persistence. Stores:
 //! - **Nodes**: File, Struct, Function entities
 //! - **Edges**: CONTAINS, CALLS relations
+//! - **API**: GraphQL schemas and operations with CONSUMES/PRODUCES edges
 //! - **Vectors**: 384-dimension embeddings with HNSW index for similarity search
 //!
 //! # Example
@@ -31,24 +34,80 @@
 //! let results = kg.search_code("authentication handler", 10).await?;
 //! ```
 
+pub mod access;
+pub mod backup;
+pub mod centrality;
+pub mod cochange;
+pub mod coupling;
 mod db;
+pub mod docs;
+pub mod dupes;
 mod embedder;
 mod error;
+pub mod export;
+#[cfg(feature = "test-util")]
+pub mod fake;
+pub mod filter;
+pub mod grep;
+pub mod history;
 pub mod indexer;
 pub mod models;
+pub mod name_search;
 pub mod ontology;
 pub mod parser;
+pub mod perf;
+pub mod summary;
+pub mod test_impact;
+pub mod watch;
+pub mod workspace;
 
-pub use db::{CallInfo, ExtendedIndexStats, ImplementsInfo, KnowledgeDb};
-pub use embedder::Embedder;
+pub use access::PathBlocklist;
+pub use backup::BackupSummary;
+pub use centrality::{CentralityMetric, CentralityScore};
+pub use cochange::{CoChange, MineOptions, MineReport};
+pub use coupling::{CallDirection, CrossingCall, PathScope};
+pub use db::{
+    ApiRelation, ApiUsage, CallInfo, EdgeInfo, EntityInfo, ExtendedIndexStats, ImplementsInfo,
+    KnowledgeDb, LanguageStats, VerifyIssue, VerifyReport, EDGE_RELATIONS,
+};
+pub use docs::{DocsFormat, DocsSummary};
+pub use dupes::{ChunkLocation, DuplicateCluster, DuplicatePair};
+pub use embedder::{
+    serve_embeddings, Embedder, EmbedderMetrics, FastEmbedder, LazyEmbedder, RemoteEmbedder,
+};
 pub use error::KnowledgeError;
+pub use export::{ExportFormat, ExportSummary};
+#[cfg(feature = "test-util")]
+pub use fake::FakeKnowledgeStore;
+pub use filter::EntityFilter;
+pub use grep::{GrepEntity, GrepHit};
+pub use history::parse_as_of;
 pub use indexer::IndexProgress;
-pub use models::{CodeChunk, FileNode, FunctionNode, IndexStats, SearchResult, StructNode};
+pub use models::{
+    CodeChunk, FileNode, FunctionNode, IndexFailure, IndexReport, IndexStats, IndexTimings,
+    SearchContext, SearchResult, StructNode,
+};
 pub use parser::{ParseResult, Parser, ParserRegistry, RustParser};
+pub use perf::{IndexPerf, LatencyPercentiles};
+pub use summary::{Summary, SummaryKind};
+pub use test_impact::{AffectedTest, TestImpact, TestImpactFormat};
+pub use watch::{ChangeScanner, FileChanges, FileWatcher, IndexUpdate, WatchFilter};
+pub use workspace::{CrateInfo, CrateMap};
 
 use async_trait::async_trait;
-use std::path::Path;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Edges fetched per query while exporting the graph.
+const EXPORT_EDGE_PAGE: usize = 5000;
+
+/// Factor a filtered semantic search widens by until enough results pass
+/// the filter.
+const SEARCH_OVERFETCH: usize = 5;
 
 /// Main interface for the knowledge graph.
 ///
@@ -75,6 +134,9 @@ pub trait KnowledgeStore: Send + Sync {
     async fn remove_file(&self, path: &str) -> Result<(), KnowledgeError>;
 
     /// Semantic search for code relevant to a query.
+    ///
+    /// Queries naming a function or struct (`parse_plan`, `Task::new`) may
+    /// be answered by name lookup instead (see [`name_search`]).
     async fn search_code(
         &self,
         query: &str,
@@ -101,24 +163,278 @@ pub trait KnowledgeStore: Send + Sync {
 
     /// Count call relations (for debugging).
     async fn count_calls(&self) -> Result<usize, KnowledgeError>;
+
+    /// Functions in a file that overlap a line range, for re-ranking chunks.
+    ///
+    /// Stores without entity metadata return nothing.
+    async fn functions_in_range(
+        &self,
+        _path: &str,
+        _start_line: u32,
+        _end_line: u32,
+    ) -> Result<Vec<ontology::nodes::FunctionEntity>, KnowledgeError> {
+        Ok(Vec::new())
+    }
+
+    /// Directory summaries from `arq kg summarize`, sorted by path.
+    ///
+    /// Stores without a summary index return nothing.
+    async fn directory_summaries(&self) -> Result<Vec<Summary>, KnowledgeError> {
+        Ok(Vec::new())
+    }
+
+    /// The innermost entity (function, type, impl, constant, ...) containing
+    /// 1-based `line` of `path`, for editor integrations and the file viewer.
+    ///
+    /// Stores without entity metadata return nothing.
+    async fn entity_at(
+        &self,
+        _path: &str,
+        _line: u32,
+    ) -> Result<Option<EntityInfo>, KnowledgeError> {
+        Ok(None)
+    }
 }
 
 /// The main knowledge graph implementation.
 pub struct KnowledgeGraph {
     db: Arc<KnowledgeDb>,
-    embedder: Arc<dyn Embedder>,
+    /// Loads the model on first vector operation, so graph queries skip it.
+    embedder: Arc<LazyEmbedder>,
+    /// Rows per multi-row insert while indexing.
+    insert_batch_size: usize,
+    /// Files indexed at once; zero for one per CPU.
+    index_jobs: usize,
+    /// Detection and handling of generated/vendored files while indexing.
+    generated_code: indexer::GeneratedCodePolicy,
+    /// Parsers registered on top of the built-in ones.
+    extra_parsers: Vec<Arc<dyn parser::Parser>>,
+    /// Days closed call history is kept after indexing.
+    history_retention_days: u64,
+    /// Size above which files are oversized; zero for no limit.
+    max_file_size: u64,
+    /// What indexing does with oversized files.
+    oversized_files: crate::config::OversizedFileHandling,
+    /// Documentation folders indexed as documents rather than code.
+    docs: Vec<String>,
+    /// Languages whose parsers are turned off.
+    disabled_languages: Vec<String>,
+    /// Extensions parsed by another language's parser.
+    extension_overrides: BTreeMap<String, String>,
+    /// Publishes every change to the index.
+    updates: broadcast::Sender<IndexUpdate>,
+    /// Changes published so far.
+    generation: std::sync::atomic::AtomicU64,
+    /// Address of the warm embedder daemon, if configured.
+    embedder_daemon: Option<String>,
+    /// Where the embedding model is cached, if not the default.
+    model_cache: Option<PathBuf>,
+    /// Search latencies waiting to be written to the metadata table.
+    pending_latencies: std::sync::Mutex<perf::PendingLatencies>,
+    /// Workspace root the paths given to `index_file` are relative to.
+    root: PathBuf,
+    /// Crates under `root`, detected on the first `index_file`.
+    crates: std::sync::OnceLock<CrateMap>,
 }
 
 impl KnowledgeGraph {
     /// Create a new knowledge graph with the given database path.
+    ///
+    /// Fails while `arq kg backup` or `arq kg restore` is working on it.
     pub async fn new(db_path: &Path) -> Result<Self, KnowledgeError> {
-        let db = KnowledgeDb::open(db_path).await?;
-        let embedder = embedder::FastEmbedder::new()?;
+        backup::check_not_locked(db_path)?;
+        Ok(Self::with_db(KnowledgeDb::open(db_path).await?))
+    }
 
-        Ok(Self {
+    /// Create an empty, initialized knowledge graph held in memory.
+    ///
+    /// Nothing is written to disk and the graph is gone when dropped, which
+    /// suits one-shot runs such as CI jobs that index and query in the same
+    /// process.
+    pub async fn open_in_memory() -> Result<Self, KnowledgeError> {
+        let kg = Self::with_db(KnowledgeDb::open_in_memory().await?);
+        kg.initialize().await?;
+        Ok(kg)
+    }
+
+    fn with_db(db: KnowledgeDb) -> Self {
+        Self {
             db: Arc::new(db),
-            embedder: Arc::new(embedder),
-        })
+            embedder: Arc::new(LazyEmbedder::new()),
+            insert_batch_size: crate::config::DEFAULT_INSERT_BATCH_SIZE,
+            index_jobs: crate::config::DEFAULT_INDEX_JOBS,
+            generated_code: indexer::GeneratedCodePolicy::default(),
+            extra_parsers: Vec::new(),
+            history_retention_days: crate::config::DEFAULT_HISTORY_RETENTION_DAYS,
+            max_file_size: crate::config::DEFAULT_INDEX_MAX_FILE_SIZE,
+            oversized_files: crate::config::OversizedFileHandling::default(),
+            docs: Vec::new(),
+            disabled_languages: Vec::new(),
+            extension_overrides: BTreeMap::new(),
+            updates: broadcast::channel(watch::UPDATE_CHANNEL_CAPACITY).0,
+            generation: Default::default(),
+            embedder_daemon: None,
+            model_cache: None,
+            pending_latencies: Default::default(),
+            root: PathBuf::from("."),
+            crates: std::sync::OnceLock::new(),
+        }
+    }
+
+    /// Receive an [`IndexUpdate`] for every later change to the index.
+    pub fn subscribe(&self) -> broadcast::Receiver<IndexUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Count of changes made to the index through this graph, so results
+    /// derived from it can be cached until the next change.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    /// Tell subscribers that `files` changed.
+    fn publish(&self, files: Vec<String>) {
+        self.generation
+            .fetch_add(1, std::sync::atomic::Ordering::AcqRel);
+        // Fails only when nobody is subscribed
+        let _ = self.updates.send(IndexUpdate { files });
+    }
+
+    /// Set the number of rows per multi-row insert while indexing.
+    pub fn with_insert_batch_size(mut self, batch_size: usize) -> Self {
+        self.insert_batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set how many files indexing works on at once; zero uses one per CPU.
+    pub fn with_index_jobs(mut self, jobs: usize) -> Self {
+        self.index_jobs = jobs;
+        self
+    }
+
+    /// Set how generated and vendored files are handled while indexing.
+    pub fn with_generated_code(mut self, policy: indexer::GeneratedCodePolicy) -> Self {
+        self.generated_code = policy;
+        self
+    }
+
+    /// Set how many days superseded call edges are kept for `--as-of`
+    /// queries; zero keeps no history.
+    pub fn with_history_retention(mut self, days: u64) -> Self {
+        self.history_retention_days = days;
+        self
+    }
+
+    /// Set the size above which files are indexed according to `handling`
+    /// rather than in full; zero disables the limit.
+    pub fn with_size_limit(
+        mut self,
+        max_file_size: u64,
+        handling: crate::config::OversizedFileHandling,
+    ) -> Self {
+        self.max_file_size = max_file_size;
+        self.oversized_files = handling;
+        self
+    }
+
+    /// Index the markdown and reStructuredText files under `dirs`, relative
+    /// to the indexed root, as documentation.
+    pub fn with_docs(mut self, dirs: Vec<String>) -> Self {
+        self.docs = dirs;
+        self
+    }
+
+    /// Leave `disabled_languages` out of the index, and parse the extensions
+    /// of `extension_overrides` with the parser of the language each maps to.
+    pub fn with_languages(
+        mut self,
+        disabled_languages: Vec<String>,
+        extension_overrides: BTreeMap<String, String>,
+    ) -> Self {
+        self.disabled_languages = disabled_languages;
+        self.extension_overrides = extension_overrides;
+        self
+    }
+
+    /// Extension overrides, and the language each maps to, that are ignored
+    /// because that language has no enabled parser, to warn about once.
+    pub fn unmapped_extension_overrides(&self) -> Vec<(String, String)> {
+        self.indexer().unmapped_extensions().to_vec()
+    }
+
+    /// Use the warm embedder daemon at `addr`, if it is running, instead of
+    /// loading the model in-process.
+    pub fn with_embedder_daemon(mut self, addr: Option<String>) -> Self {
+        self.embedder_daemon = addr;
+        self.embedder = Arc::new(self.lazy_embedder());
+        self
+    }
+
+    /// Cache the embedding model in `dir` (see
+    /// [`crate::StorageConfig::model_cache_path`]) instead of `~/.arq/cache/`.
+    pub fn with_model_cache(mut self, dir: PathBuf) -> Self {
+        self.model_cache = Some(dir);
+        self.embedder = Arc::new(self.lazy_embedder());
+        self
+    }
+
+    /// Set the workspace root that paths given to `index_file` are relative
+    /// to; the working directory by default.
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self.crates = std::sync::OnceLock::new();
+        self
+    }
+
+    fn lazy_embedder(&self) -> LazyEmbedder {
+        LazyEmbedder::with_daemon(self.embedder_daemon.clone())
+            .with_cache_dir(self.model_cache.clone())
+    }
+
+    /// Load the embedding model now rather than on the first vector operation.
+    pub fn warm_up(&self) -> Result<(), KnowledgeError> {
+        self.embedder.warm_up()
+    }
+
+    /// How the embedder started, and how much it has been used.
+    pub fn embedder_metrics(&self) -> EmbedderMetrics {
+        self.embedder.metrics()
+    }
+
+    /// Register the external parsers from `[parsers]` config.
+    pub fn with_parsers(self, config: &crate::config::ParsersConfig) -> Self {
+        self.with_external_parsers(Self::external_parsers(config))
+    }
+
+    /// Register parsers built by [`Self::external_parsers`], for processes
+    /// that open the graph repeatedly and build them only once.
+    pub fn with_external_parsers(mut self, parsers: Vec<Arc<dyn parser::Parser>>) -> Self {
+        self.extra_parsers = parsers;
+        self
+    }
+
+    /// Build the external parsers from `[parsers]` config. Each keeps its
+    /// names for the rest of the process, so build them once per process.
+    pub fn external_parsers(config: &crate::config::ParsersConfig) -> Vec<Arc<dyn parser::Parser>> {
+        config
+            .external
+            .iter()
+            .filter(|p| !p.command.is_empty() && !p.extensions.is_empty())
+            .map(|p| Arc::new(parser::ExternalParser::from_config(p)) as Arc<dyn parser::Parser>)
+            .collect()
+    }
+
+    /// Create an indexer writing to this graph.
+    fn indexer(&self) -> indexer::GenericIndexer {
+        indexer::GenericIndexer::new(Arc::clone(&self.db), self.embedder.clone())
+            .with_batch_size(self.insert_batch_size)
+            .with_jobs(self.index_jobs)
+            .with_generated_code(self.generated_code.clone())
+            .with_history_retention(self.history_retention_days)
+            .with_size_limit(self.max_file_size, self.oversized_files)
+            .with_docs(&self.docs)
+            .with_parsers(&self.extra_parsers)
+            .with_languages(&self.disabled_languages, &self.extension_overrides)
     }
 
     /// Open an existing knowledge graph.
@@ -126,6 +442,120 @@ impl KnowledgeGraph {
         Self::new(db_path).await
     }
 
+    /// Open an existing knowledge graph, paging the vector index in first
+    /// when `warmup` is set (see [`Self::warm_up_index`]).
+    ///
+    /// A failed warmup doesn't fail the open; the first search is just slow.
+    pub async fn open_with_warmup(db_path: &Path, warmup: bool) -> Result<Self, KnowledgeError> {
+        let kg = Self::open(db_path).await?;
+        if warmup {
+            let _ = kg.warm_up_index().await;
+        }
+        Ok(kg)
+    }
+
+    /// Run a probe search so the first real one doesn't wait for the HNSW
+    /// index to be read from disk, returning how long it took.
+    ///
+    /// Doesn't load the embedding model; see [`Self::warm_up`] for that.
+    pub async fn warm_up_index(&self) -> Result<Duration, KnowledgeError> {
+        let dimension = self.db.embedding_dimension().await?;
+        let started = Instant::now();
+        self.db
+            .search_by_embedding(&perf::probe_vector(dimension), 1)
+            .await?;
+        let elapsed = started.elapsed();
+        let _ = self
+            .db
+            .set_metadata(perf::WARMUP_KEY, &elapsed.as_millis().to_string())
+            .await;
+        Ok(elapsed)
+    }
+
+    /// Size, memory and latency figures of the vector index stored at
+    /// `db_path`, for `arq kg status --perf`.
+    ///
+    /// Runs a probe search to time the index as it is now.
+    pub async fn perf_stats(&self, db_path: &Path) -> Result<IndexPerf, KnowledgeError> {
+        let search_latency = self.search_latencies().await?.percentiles();
+        let last_warmup = self
+            .db
+            .get_metadata(perf::WARMUP_KEY)
+            .await?
+            .and_then(|ms| ms.parse().ok())
+            .map(Duration::from_millis);
+        let chunks = self.db.get_stats().await?.chunks;
+
+        let dimension = self.db.embedding_dimension().await?;
+        let started = Instant::now();
+        self.db
+            .search_by_embedding(&perf::probe_vector(dimension), 1)
+            .await?;
+        let probe = started.elapsed();
+
+        Ok(IndexPerf {
+            database_bytes: perf::disk_usage(db_path),
+            chunks,
+            dimension,
+            resident_bytes: perf::resident_memory(),
+            probe,
+            last_warmup,
+            search_latency,
+        })
+    }
+
+    /// Latencies of the recent searches, including those not yet written.
+    async fn search_latencies(&self) -> Result<perf::LatencySamples, KnowledgeError> {
+        let mut samples = self.stored_search_latencies().await?;
+        if let Ok(pending) = self.pending_latencies.lock() {
+            for latency in pending.samples() {
+                samples.record(*latency);
+            }
+        }
+        Ok(samples)
+    }
+
+    async fn stored_search_latencies(&self) -> Result<perf::LatencySamples, KnowledgeError> {
+        Ok(self
+            .db
+            .get_metadata(perf::SEARCH_LATENCY_KEY)
+            .await?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
+
+    /// Add a search latency to the recent ones, writing them in batches (see
+    /// [`perf::PendingLatencies`]). Best effort: processes searching the
+    /// same graph may overwrite each other's batch.
+    async fn record_search_latency(&self, latency: Duration) {
+        let batch = match self.pending_latencies.lock() {
+            Ok(mut pending) if pending.push(latency) => pending.take(),
+            _ => return,
+        };
+        let Ok(mut samples) = self.stored_search_latencies().await else {
+            return;
+        };
+        for latency in batch {
+            samples.record(latency);
+        }
+        if let Ok(json) = serde_json::to_string(&samples) {
+            let _ = self.db.set_metadata(perf::SEARCH_LATENCY_KEY, &json).await;
+        }
+    }
+
+    /// Line counts and function counts per language, most code first.
+    pub async fn language_stats(&self) -> Result<Vec<LanguageStats>, KnowledgeError> {
+        self.db.language_stats().await
+    }
+
+    /// Line counts per language over the indexed files `keep` accepts.
+    pub async fn language_stats_where(
+        &self,
+        keep: impl Fn(&str) -> bool,
+    ) -> Result<Vec<LanguageStats>, KnowledgeError> {
+        self.db.language_stats_where(keep).await
+    }
+
     /// Get extended statistics including rich ontology entity counts.
     pub async fn get_extended_stats(&self) -> Result<ExtendedIndexStats, KnowledgeError> {
         self.db.get_extended_stats().await
@@ -163,16 +593,586 @@ impl KnowledgeGraph {
         self.db.list_calls().await
     }
 
+    /// Calls crossing between the files or directories `a` and `b`, in
+    /// either direction; see [`coupling`].
+    pub async fn calls_between(
+        &self,
+        a: &str,
+        b: &str,
+    ) -> Result<Vec<CrossingCall>, KnowledgeError> {
+        self.db
+            .calls_between(&PathScope::new(a), &PathScope::new(b))
+            .await
+    }
+
     /// List all implements edges (impl -> trait).
     pub async fn list_implements(&self) -> Result<Vec<ImplementsInfo>, KnowledgeError> {
         self.db.list_implements().await
     }
 
+    /// Implements edges of the type or trait/interface called `name`: its
+    /// implementors, or what it implements.
+    pub async fn implementations_of(
+        &self,
+        name: &str,
+    ) -> Result<Vec<ImplementsInfo>, KnowledgeError> {
+        let named = |id: &str| ontology::QualifiedName::from_entity_id(id).name() == name;
+        let mut edges: Vec<ImplementsInfo> = self
+            .db
+            .list_implements()
+            .await?
+            .into_iter()
+            .filter(|e| named(&e.impl_id) || named(&e.trait_id))
+            .collect();
+        edges.sort_by(|a, b| (&a.trait_id, &a.impl_id).cmp(&(&b.trait_id, &b.impl_id)));
+        Ok(edges)
+    }
+
+    /// List one page of edges of any relation in [`EDGE_RELATIONS`],
+    /// optionally only those leaving the entity `from`.
+    ///
+    /// With `as_of`, `calls` edges are those in effect at that time.
+    pub async fn list_edges(
+        &self,
+        relation: &str,
+        from: Option<&str>,
+        offset: usize,
+        limit: usize,
+        as_of: Option<DateTime<Utc>>,
+    ) -> Result<Vec<EdgeInfo>, KnowledgeError> {
+        self.db
+            .list_edges(relation, from, offset, limit, as_of)
+            .await
+    }
+
+    /// Every edge of a relation, fetched page by page.
+    async fn list_all_edges(&self, relation: &str) -> Result<Vec<EdgeInfo>, KnowledgeError> {
+        let mut edges = Vec::new();
+        loop {
+            let page = self
+                .db
+                .list_edges(relation, None, edges.len(), EXPORT_EDGE_PAGE, None)
+                .await?;
+            let done = page.len() < EXPORT_EDGE_PAGE;
+            edges.extend(page);
+            if done {
+                return Ok(edges);
+            }
+        }
+    }
+
+    /// Export every entity and edge to `path` in the given format.
+    pub async fn export(
+        &self,
+        format: ExportFormat,
+        path: &Path,
+    ) -> Result<ExportSummary, KnowledgeError> {
+        let entities = self.db.list_entities().await?;
+        let mut edges = Vec::new();
+        for relation in EDGE_RELATIONS {
+            edges.extend(self.list_all_edges(relation).await?);
+        }
+
+        export::normalize_edges(&entities, &mut edges);
+
+        let path = path.to_path_buf();
+        match format {
+            ExportFormat::Sqlite => {
+                tokio::task::spawn_blocking(move || export::write_sqlite(&path, &entities, &edges))
+                    .await
+                    .map_err(|e| KnowledgeError::Export(e.to_string()))?
+            }
+        }
+    }
+
+    /// Generate a documentation site in `dir`: a page per module with its
+    /// entities, call graph and links between callers and callees.
+    pub async fn generate_docs(
+        &self,
+        format: DocsFormat,
+        dir: &Path,
+    ) -> Result<DocsSummary, KnowledgeError> {
+        let entities = self.db.list_entities().await?;
+        let mut edges = self.list_all_edges("calls").await?;
+        export::normalize_edges(&entities, &mut edges);
+
+        let modules = docs::group_modules(&entities);
+        let dir = dir.to_path_buf();
+        tokio::task::spawn_blocking(move || docs::write_site(&dir, format, &modules, &edges))
+            .await
+            .map_err(|e| KnowledgeError::Export(e.to_string()))?
+    }
+
     /// List all indexed file paths.
     pub async fn list_indexed_files(&self) -> Result<Vec<String>, KnowledgeError> {
         self.db.list_indexed_files().await
     }
 
+    /// Indexed file paths with the hash of their content.
+    pub async fn list_file_hashes(&self) -> Result<Vec<(String, String)>, KnowledgeError> {
+        self.db.list_file_hashes().await
+    }
+
+    /// Stored file and directory summaries, sorted by path.
+    pub async fn list_summaries(&self) -> Result<Vec<Summary>, KnowledgeError> {
+        self.db.list_summaries(None).await
+    }
+
+    /// Store summaries, replacing earlier ones for the same paths.
+    pub async fn save_summaries(&self, summaries: &[Summary]) -> Result<(), KnowledgeError> {
+        self.db
+            .save_summaries(summaries, self.insert_batch_size)
+            .await
+    }
+
+    /// Delete the summaries of `paths`, e.g. of files no longer indexed.
+    pub async fn delete_summaries(&self, paths: &[String]) -> Result<(), KnowledgeError> {
+        self.db.delete_summaries(paths).await
+    }
+
+    /// Remove indexed files matching any of `patterns` or written in any of
+    /// `languages`, with their entities and chunks, leaving the rest intact.
+    ///
+    /// Patterns use the syntax of `knowledge.generated_patterns`, where `**`
+    /// also crosses directories (`src/legacy/**`). Calls made from removed
+    /// files are closed; calls into them from other files are kept, so they
+    /// resolve again once the files are re-indexed. Returns the removed paths.
+    pub async fn clear_files(
+        &self,
+        patterns: &[String],
+        languages: &[String],
+    ) -> Result<Vec<String>, KnowledgeError> {
+        let mut removed: Vec<String> = self
+            .db
+            .list_file_languages()
+            .await?
+            .into_iter()
+            .filter(|(path, language)| {
+                patterns.iter().any(|p| indexer::matches_pattern(p, path))
+                    || language.as_deref().is_some_and(|language| {
+                        languages.iter().any(|l| l.eq_ignore_ascii_case(language))
+                    })
+            })
+            .map(|(path, _)| path)
+            .collect();
+        removed.sort();
+
+        for path in &removed {
+            self.db.remove_file(path).await?;
+            self.db.remove_file_entities(path).await?;
+        }
+        if !removed.is_empty() {
+            self.publish(removed.clone());
+        }
+        Ok(removed)
+    }
+
+    /// Regex search over the indexed files, read from `root`.
+    ///
+    /// Returns at most `limit` matching lines in path order. With `expand`,
+    /// each hit carries the innermost function enclosing it and that
+    /// function's direct callers.
+    pub async fn grep(
+        &self,
+        pattern: &str,
+        root: &Path,
+        expand: bool,
+        limit: usize,
+    ) -> Result<Vec<GrepHit>, KnowledgeError> {
+        let regex = grep::compile_pattern(pattern)?;
+        let mut files = self.db.list_indexed_files().await?;
+        files.sort();
+
+        let mut hits = Vec::new();
+        let mut callers: HashMap<String, Vec<String>> = HashMap::new();
+        for path in files {
+            if hits.len() >= limit {
+                break;
+            }
+            // Files deleted since indexing have nothing to match
+            let Ok(content) = std::fs::read_to_string(root.join(&path)) else {
+                continue;
+            };
+            let matches = grep::grep_source(&regex, &content);
+            if matches.is_empty() {
+                continue;
+            }
+
+            let functions = if expand {
+                self.db.list_functions_in_range(&path, 1, u32::MAX).await?
+            } else {
+                Vec::new()
+            };
+            for (line, text) in matches.into_iter().take(limit - hits.len()) {
+                let mut entity =
+                    grep::enclosing_function(&functions, line).map(GrepEntity::from_function);
+                if let Some(ref mut entity) = entity {
+                    if !callers.contains_key(&entity.id) {
+                        let found = self.db.get_impact(&entity.id).await?;
+                        callers.insert(entity.id.clone(), found);
+                    }
+                    entity.callers = callers[&entity.id].clone();
+                }
+                hits.push(GrepHit {
+                    path: path.clone(),
+                    line,
+                    text,
+                    entity,
+                });
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Semantic search restricted to files owned by a workspace crate.
+    pub async fn search_code_in_crate(
+        &self,
+        query: &str,
+        crate_name: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, KnowledgeError> {
+        let wanted = workspace::crate_ident(crate_name);
+        self.search_code_where(query, limit, |r| {
+            r.crate_name
+                .as_deref()
+                .is_some_and(|c| workspace::crate_ident(c) == wanted)
+        })
+        .await
+    }
+
+    /// Semantic search keeping only the results `keep` accepts, e.g. those
+    /// outside `context.private_paths`.
+    ///
+    /// The vector index cannot filter, so the search widens until `limit`
+    /// results are kept or the index has no more.
+    pub async fn search_code_where(
+        &self,
+        query: &str,
+        limit: usize,
+        keep: impl Fn(&SearchResult) -> bool,
+    ) -> Result<Vec<SearchResult>, KnowledgeError> {
+        let mut fetch = limit * SEARCH_OVERFETCH;
+        loop {
+            let results = self.search_code(query, fetch).await?;
+            let exhausted = results.len() < fetch;
+            let kept: Vec<SearchResult> = results.into_iter().filter(&keep).take(limit).collect();
+            if kept.len() == limit || exhausted {
+                return Ok(kept);
+            }
+            fetch *= SEARCH_OVERFETCH;
+        }
+    }
+
+    /// Semantic search narrowed by function metadata.
+    ///
+    /// Keeps results that overlap a function matching `filter`. With an empty
+    /// query, lists the matching functions instead, one result per function.
+    pub async fn search_code_filtered(
+        &self,
+        query: &str,
+        filter: &EntityFilter,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, KnowledgeError> {
+        if query.trim().is_empty() {
+            let functions = self
+                .db
+                .list_functions_matching(filter, None, Some(limit))
+                .await?;
+            return Ok(functions
+                .into_iter()
+                .map(|f| SearchResult {
+                    path: f.file_path,
+                    score: 1.0,
+                    start_line: f.start_line,
+                    end_line: f.end_line,
+                    preview: Some(f.signature),
+                    entity_id: f.id,
+                    entity_type: "function".to_string(),
+                    crate_name: None,
+                    context: None,
+                })
+                .collect());
+        }
+
+        if filter.is_empty() {
+            return self.search_code(query, limit).await;
+        }
+
+        // The vector index cannot join, so over-fetch and filter afterwards
+        let mut fetch = limit * SEARCH_OVERFETCH;
+        loop {
+            let candidates = self.search_code(query, fetch).await?;
+            let exhausted = candidates.len() < fetch;
+            let mut paths: Vec<String> = candidates.iter().map(|r| r.path.clone()).collect();
+            paths.sort();
+            paths.dedup();
+            let functions = self
+                .db
+                .list_functions_matching(filter, Some(&paths), None)
+                .await?;
+
+            let matching: Vec<SearchResult> = candidates
+                .into_iter()
+                .filter(|r| {
+                    functions.iter().any(|f| {
+                        f.file_path == r.path
+                            && f.start_line <= r.end_line
+                            && f.end_line >= r.start_line
+                    })
+                })
+                .take(limit)
+                .collect();
+            if matching.len() == limit || exhausted {
+                return Ok(matching);
+            }
+            fetch *= SEARCH_OVERFETCH;
+        }
+    }
+
+    /// Attach up to `lines` surrounding lines and the enclosing function's
+    /// signature to each result.
+    ///
+    /// Files are read from `root`, which indexed paths are relative to; a
+    /// file that can no longer be read only gets the signature.
+    pub async fn add_search_context(
+        &self,
+        results: &mut [SearchResult],
+        lines: usize,
+        root: &Path,
+    ) -> Result<(), KnowledgeError> {
+        let mut sources: HashMap<String, Option<String>> = HashMap::new();
+        for result in results.iter_mut() {
+            let source = sources
+                .entry(result.path.clone())
+                .or_insert_with(|| std::fs::read_to_string(root.join(&result.path)).ok());
+            let mut context = match source {
+                Some(source) => {
+                    SearchContext::from_source(source, result.start_line, result.end_line, lines)
+                }
+                None => SearchContext::default(),
+            };
+
+            // The innermost function spanning the whole hit
+            context.signature = self
+                .db
+                .list_functions_in_range(&result.path, result.start_line, result.end_line)
+                .await?
+                .into_iter()
+                .filter(|f| f.start_line <= result.start_line && f.end_line >= result.end_line)
+                .min_by_key(|f| f.end_line.saturating_sub(f.start_line))
+                .map(|f| f.signature);
+            result.context = Some(context);
+        }
+        Ok(())
+    }
+
+    /// List functions defined in a workspace crate.
+    pub async fn list_functions_in_crate(
+        &self,
+        crate_name: &str,
+        limit: usize,
+    ) -> Result<Vec<FunctionNode>, KnowledgeError> {
+        self.db.list_functions_in_crate(crate_name, limit).await
+    }
+
+    /// Get dependencies of an entity whose callee resolved to a single definition.
+    pub async fn get_resolved_dependencies(
+        &self,
+        entity_id: &str,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        self.db.get_resolved_dependencies(entity_id).await
+    }
+
+    /// Get what an entity called at `as_of`, including calls since removed.
+    ///
+    /// Graphs keep the call history of files re-indexed within
+    /// `knowledge.history_retention_days`.
+    pub async fn get_dependencies_as_of(
+        &self,
+        entity_id: &str,
+        resolved_only: bool,
+        as_of: DateTime<Utc>,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        self.db
+            .get_dependencies_as_of(entity_id, resolved_only, as_of)
+            .await
+    }
+
+    /// Get what called an entity at `as_of`, including calls since removed.
+    pub async fn get_impact_as_of(
+        &self,
+        entity_id: &str,
+        as_of: DateTime<Utc>,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        self.db.get_impact_as_of(entity_id, as_of).await
+    }
+
+    /// Get callers of an entity that belong to a workspace crate.
+    pub async fn get_impact_in_crate(
+        &self,
+        entity_id: &str,
+        crate_name: &str,
+    ) -> Result<Vec<String>, KnowledgeError> {
+        self.db.get_impact_in_crate(entity_id, crate_name).await
+    }
+
+    /// Operations and resolvers that consume or produce a schema type.
+    ///
+    /// Used for API-layer impact analysis of GraphQL schemas.
+    pub async fn get_api_impact(&self, type_name: &str) -> Result<Vec<ApiUsage>, KnowledgeError> {
+        self.db.get_api_impact(type_name).await
+    }
+
+    /// Functions ranked highest by a centrality metric over the calls graph.
+    ///
+    /// Scores are cached in the metadata table and recomputed only when the
+    /// calls graph changes.
+    pub async fn top_centrality(
+        &self,
+        metric: CentralityMetric,
+        limit: usize,
+    ) -> Result<Vec<CentralityScore>, KnowledgeError> {
+        let calls = self.db.list_calls().await?;
+        let signature = centrality::graph_signature(&calls);
+        let key = metric.cache_key();
+
+        let cached = self
+            .db
+            .get_metadata(&key)
+            .await?
+            .and_then(|json| serde_json::from_str::<centrality::CachedCentrality>(&json).ok())
+            .filter(|cached| cached.signature == signature);
+
+        let mut scores = match cached {
+            Some(cached) => cached.scores,
+            None => {
+                let scores = centrality::compute(&calls, metric);
+                let entry = centrality::CachedCentrality { signature, scores };
+                // Caching is best effort; a failed write only costs a recompute
+                if let Ok(json) = serde_json::to_string(&entry) {
+                    let _ = self.db.set_metadata(&key, &json).await;
+                }
+                entry.scores
+            }
+        };
+        scores.truncate(limit);
+        Ok(scores)
+    }
+
+    /// Centrality of each file: the summed scores of its functions.
+    pub async fn file_centrality(
+        &self,
+        metric: CentralityMetric,
+    ) -> Result<HashMap<String, f64>, KnowledgeError> {
+        let scores = self.top_centrality(metric, usize::MAX).await?;
+        Ok(centrality::file_scores(&scores))
+    }
+
+    /// Check the graph for dangling edges, orphaned chunks and embedding mismatches.
+    pub async fn verify(&self) -> Result<VerifyReport, KnowledgeError> {
+        self.db.verify(self.embedder.dimension()).await
+    }
+
+    /// Find clusters of near-identical chunks in different files.
+    ///
+    /// Chunks shorter than `min_lines` are skipped, since short snippets such
+    /// as getters look alike without being copied.
+    pub async fn find_duplicates(
+        &self,
+        threshold: f32,
+        min_lines: u32,
+    ) -> Result<Vec<DuplicateCluster>, KnowledgeError> {
+        let chunks = self.db.list_chunk_embeddings().await?;
+        let mut pairs = Vec::new();
+        for chunk in &chunks {
+            let chunk_location = chunk.location();
+            if chunk_location.line_count() < min_lines {
+                continue;
+            }
+            let candidates = self
+                .db
+                .search_by_embedding(&chunk.embedding, dupes::DUPES_CANDIDATES)
+                .await?;
+            let neighbors = candidates
+                .iter()
+                .map(|candidate| (ChunkLocation::from(candidate), candidate.score))
+                .filter(|(location, _)| {
+                    location.path != chunk_location.path && location.line_count() >= min_lines
+                })
+                .take(dupes::DUPES_NEIGHBORS);
+            for (location, similarity) in neighbors {
+                if similarity >= threshold {
+                    pairs.push(DuplicatePair {
+                        a: chunk_location.clone(),
+                        b: location,
+                        similarity,
+                    });
+                }
+            }
+        }
+        Ok(dupes::cluster_duplicates(pairs))
+    }
+
+    /// Mine the git history of `root` for files that change together and
+    /// store them as co-change edges, replacing earlier ones.
+    ///
+    /// Only files in the graph are paired, so lockfiles and docs don't
+    /// show up as coupled to everything.
+    pub async fn mine_history(
+        &self,
+        root: &Path,
+        options: &MineOptions,
+    ) -> Result<MineReport, KnowledgeError> {
+        let commits = cochange::read_git_history(root, options.max_commits)?;
+        let indexed: std::collections::HashSet<String> =
+            self.db.list_indexed_files().await?.into_iter().collect();
+        let (pairs, skipped_commits) =
+            cochange::co_changes(&commits, options, |f| indexed.contains(f));
+        self.db
+            .replace_co_changes(&pairs, self.insert_batch_size)
+            .await?;
+        Ok(MineReport {
+            commits: commits.len(),
+            skipped_commits,
+            pairs: pairs.len(),
+        })
+    }
+
+    /// Files that historically changed together with `path`, strongest
+    /// first.
+    pub async fn co_changes_with(&self, path: &str) -> Result<Vec<CoChange>, KnowledgeError> {
+        self.db.list_co_changes(Some(path)).await
+    }
+
+    /// Tests reaching functions in `changed_files` through at most
+    /// `max_depth` calls, closest first.
+    pub async fn test_impact(
+        &self,
+        changed_files: &[String],
+        max_depth: usize,
+    ) -> Result<TestImpact, KnowledgeError> {
+        let functions = self.db.list_function_entities().await?;
+        let calls = self.db.list_calls().await?;
+        Ok(test_impact::select_tests(
+            &functions,
+            &calls,
+            changed_files,
+            max_depth,
+        ))
+    }
+
+    /// All co-change edges, strongest first.
+    pub async fn list_co_changes(&self) -> Result<Vec<CoChange>, KnowledgeError> {
+        self.db.list_co_changes(None).await
+    }
+
+    /// Remove the records flagged by [`KnowledgeGraph::verify`].
+    ///
+    /// Returns the number of records removed.
+    pub async fn prune(&self, report: &VerifyReport) -> Result<usize, KnowledgeError> {
+        self.db.prune(report).await
+    }
+
     /// Index a directory with progress reporting.
     ///
     /// The callback receives progress updates as files are indexed.
@@ -186,12 +1186,94 @@ impl KnowledgeGraph {
     {
         use indexer::Indexer;
 
-        let indexer =
-            indexer::GenericIndexer::new(Arc::clone(&self.db), Arc::clone(&self.embedder));
+        let indexer = self.indexer();
 
-        indexer
+        let stats = indexer
             .index_directory_with_progress(path, on_progress)
-            .await
+            .await?;
+        self.publish(Vec::new());
+        Ok(stats)
+    }
+
+    /// Index only the documentation folders set with
+    /// [`KnowledgeGraph::with_docs`], leaving the code index as it is.
+    pub async fn index_documents_with_progress<F>(
+        &self,
+        root: &Path,
+        on_progress: F,
+    ) -> Result<IndexStats, KnowledgeError>
+    where
+        F: Fn(IndexProgress) + Send + Sync,
+    {
+        let stats = self
+            .indexer()
+            .index_documents_with_progress(root, on_progress)
+            .await?;
+        self.publish(Vec::new());
+        Ok(stats)
+    }
+
+    /// Re-index only the files of `report` that failed in an earlier run.
+    ///
+    /// The returned stats count just those files; their `report` lists the
+    /// files that failed again.
+    pub async fn retry_failed<F>(
+        &self,
+        root: &Path,
+        report: &IndexReport,
+        on_progress: F,
+    ) -> Result<IndexStats, KnowledgeError>
+    where
+        F: Fn(IndexProgress) + Send + Sync,
+    {
+        let files = report.files();
+        let stats = self
+            .indexer()
+            .index_files_with_progress(root, &files, on_progress)
+            .await?;
+        self.publish(files);
+        Ok(stats)
+    }
+
+    /// Re-index the files of `changes` under `root` that indexing covers,
+    /// and drop removed files, or every file under a removed directory,
+    /// from the graph.
+    pub async fn apply_changes(
+        &self,
+        root: &Path,
+        changes: &FileChanges,
+    ) -> Result<IndexStats, KnowledgeError> {
+        let indexer = self.indexer();
+        let removed = if changes.removed.is_empty() {
+            Vec::new()
+        } else {
+            changes.removed_files(&self.db.list_indexed_files().await?)
+        };
+        let mut files: Vec<String> = changes
+            .modified
+            .iter()
+            .filter(|path| indexer.is_indexable(root, &root.join(path)))
+            .cloned()
+            .collect();
+        // Missing files are removed rather than indexed
+        files.extend(
+            removed
+                .iter()
+                .filter(|path| indexer.is_indexable(root, &root.join(path)))
+                .cloned(),
+        );
+        if files.is_empty() {
+            return Ok(IndexStats::default());
+        }
+
+        let stats = indexer
+            .index_files_with_progress(root, &files, |_| {})
+            .await?;
+        for path in &removed {
+            self.db.remove_file_entities(path).await?;
+        }
+        self.publish(files);
+        Ok(stats)
     }
 }
 
@@ -208,17 +1290,17 @@ impl KnowledgeStore for KnowledgeGraph {
     async fn index_directory(&self, path: &Path) -> Result<IndexStats, KnowledgeError> {
         use indexer::Indexer;
 
-        let indexer =
-            indexer::GenericIndexer::new(Arc::clone(&self.db), Arc::clone(&self.embedder));
+        let indexer = self.indexer();
 
-        indexer.index_directory(path).await
+        let stats = indexer.index_directory(path).await?;
+        self.publish(Vec::new());
+        Ok(stats)
     }
 
     fn count_indexable_files(&self, path: &Path) -> usize {
         use indexer::Indexer;
 
-        let indexer =
-            indexer::GenericIndexer::new(Arc::clone(&self.db), Arc::clone(&self.embedder));
+        let indexer = self.indexer();
 
         indexer.count_indexable_files(path)
     }
@@ -226,14 +1308,18 @@ impl KnowledgeStore for KnowledgeGraph {
     async fn index_file(&self, path: &str, content: &str) -> Result<(), KnowledgeError> {
         use indexer::Indexer;
 
-        let indexer =
-            indexer::GenericIndexer::new(Arc::clone(&self.db), Arc::clone(&self.embedder));
+        let crates = self.crates.get_or_init(|| CrateMap::detect(&self.root));
+        let indexer = self.indexer().with_crates(crates.clone());
 
-        indexer.index_file(path, content).await
+        indexer.index_file(path, content).await?;
+        self.publish(vec![path.to_string()]);
+        Ok(())
     }
 
     async fn remove_file(&self, path: &str) -> Result<(), KnowledgeError> {
-        self.db.remove_file(path).await
+        self.db.remove_file(path).await?;
+        self.publish(vec![path.to_string()]);
+        Ok(())
     }
 
     async fn search_code(
@@ -241,13 +1327,30 @@ impl KnowledgeStore for KnowledgeGraph {
         query: &str,
         limit: usize,
     ) -> Result<Vec<SearchResult>, KnowledgeError> {
+        // Entities named by the query come first; one named exactly by a
+        // qualified name is the answer, without embedding the query
+        let mut named = Vec::new();
+        if let Some(name) = name_search::name_query(query) {
+            named = self.db.search_by_name(&name, limit).await?;
+            let exact = named
+                .first()
+                .is_some_and(|r| r.score == name_search::EXACT_MATCH_SCORE);
+            if exact && name.segments().len() > 1 {
+                return Ok(named);
+            }
+        }
+
         // Generate embedding for query
         let query_embedding = self.embedder.embed(&[query.to_string()])?;
 
-        // Search using vector similarity
-        self.db
+        // Search using vector similarity, timing the index for `kg status --perf`
+        let started = Instant::now();
+        let results = self
+            .db
             .search_by_embedding(&query_embedding[0], limit)
-            .await
+            .await?;
+        self.record_search_latency(started.elapsed()).await;
+        Ok(name_search::merge(named, results, limit))
     }
 
     async fn get_dependencies(&self, entity_id: &str) -> Result<Vec<String>, KnowledgeError> {
@@ -276,4 +1379,45 @@ impl KnowledgeStore for KnowledgeGraph {
     async fn count_calls(&self) -> Result<usize, KnowledgeError> {
         self.db.count_calls().await
     }
+
+    async fn functions_in_range(
+        &self,
+        path: &str,
+        start_line: u32,
+        end_line: u32,
+    ) -> Result<Vec<ontology::nodes::FunctionEntity>, KnowledgeError> {
+        self.db
+            .list_functions_in_range(path, start_line, end_line)
+            .await
+    }
+
+    async fn directory_summaries(&self) -> Result<Vec<Summary>, KnowledgeError> {
+        self.db.list_summaries(Some(SummaryKind::Directory)).await
+    }
+
+    async fn entity_at(&self, path: &str, line: u32) -> Result<Option<EntityInfo>, KnowledgeError> {
+        self.db.entity_at(path, line).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unmapped_extension_overrides() {
+        let overrides = BTreeMap::from([
+            ("mjs".to_string(), "JavaScript".to_string()),
+            (".COB".to_string(), "cobol".to_string()),
+        ]);
+        let kg = KnowledgeGraph::open_in_memory()
+            .await
+            .unwrap()
+            .with_languages(Vec::new(), overrides);
+
+        assert_eq!(
+            kg.unmapped_extension_overrides(),
+            vec![("cob".to_string(), "cobol".to_string())]
+        );
+    }
 }
@@ -1,8 +1,12 @@
 //! Code chunk with embedding for semantic search.
 
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
 use surrealdb::sql::Thing;
 
+use crate::knowledge::error::KnowledgeError;
+
 /// A code chunk with its embedding vector.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeChunk {
@@ -15,6 +19,9 @@ pub struct CodeChunk {
     pub entity_id: Option<String>,
     /// Entity type ("struct", "function", or "file").
     pub entity_type: String,
+    /// Workspace crate that owns the file, if any.
+    #[serde(default)]
+    pub crate_name: Option<String>,
     /// The actual code content.
     pub content: String,
     /// Start line number.
@@ -38,6 +45,7 @@ impl CodeChunk {
             file_path: file_path.into(),
             entity_id: None,
             entity_type: "file".to_string(),
+            crate_name: None,
             content: content.into(),
             start_line,
             end_line,
@@ -56,6 +64,12 @@ impl CodeChunk {
         self
     }
 
+    /// Set the crate that owns this chunk.
+    pub fn with_crate(mut self, crate_name: impl Into<String>) -> Self {
+        self.crate_name = Some(crate_name.into());
+        self
+    }
+
     /// Set the embedding vector.
     pub fn with_embedding(mut self, embedding: Vec<f32>) -> Self {
         self.embedding = embedding;
@@ -80,6 +94,47 @@ pub struct SearchResult {
     pub entity_id: Option<String>,
     /// Entity type.
     pub entity_type: String,
+    /// Workspace crate that owns the file, if any.
+    #[serde(default)]
+    pub crate_name: Option<String>,
+    /// Surrounding lines and enclosing entity, when requested
+    /// (see [`crate::knowledge::KnowledgeGraph::add_search_context`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SearchContext>,
+}
+
+/// Source around a search hit, to judge it without opening the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchContext {
+    /// Lines right before the hit, the last one at `start_line - 1`.
+    pub before: Vec<String>,
+    /// Lines right after the hit, the first one at `end_line + 1`.
+    pub after: Vec<String>,
+    /// Signature of the innermost function enclosing the hit, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl SearchContext {
+    /// Up to `lines` lines of `content` on each side of the 1-based,
+    /// inclusive range `start_line..=end_line`.
+    pub fn from_source(content: &str, start_line: u32, end_line: u32, lines: usize) -> Self {
+        let all: Vec<&str> = content.lines().collect();
+        let start = (start_line.max(1) as usize - 1).min(all.len());
+        let end = (end_line as usize).clamp(start, all.len());
+
+        Self {
+            before: all[start.saturating_sub(lines)..start]
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
+            after: all[end..(end + lines).min(all.len())]
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
+            signature: None,
+        }
+    }
 }
 
 /// Statistics about the knowledge graph index.
@@ -87,6 +142,9 @@ pub struct SearchResult {
 pub struct IndexStats {
     /// Number of indexed files.
     pub files: usize,
+    /// Number of indexed documentation files (see `arq init --docs`).
+    #[serde(default)]
+    pub documents: usize,
     /// Number of indexed structs/classes.
     pub structs: usize,
     /// Number of indexed functions/methods.
@@ -95,6 +153,186 @@ pub struct IndexStats {
     pub chunks: usize,
     /// Total size of indexed files in bytes.
     pub total_size: u64,
+    /// Generated or vendored files left out of the index.
+    #[serde(default)]
+    pub generated_skipped: usize,
+    /// Oversized files left out of the index.
+    #[serde(default)]
+    pub oversized_skipped: usize,
+    /// Oversized files indexed for the graph only, without chunks.
+    #[serde(default)]
+    pub signatures_only: usize,
+    /// Files that could not be read or indexed.
+    #[serde(default)]
+    pub report: IndexReport,
+    /// Where the time of the run went.
+    #[serde(default)]
+    pub timings: IndexTimings,
     /// Last update time.
     pub last_updated: Option<chrono::DateTime<chrono::Utc>>,
 }
+
+/// Where the time of an indexing run went, to tune `arq init --jobs`.
+///
+/// Stage times are summed over files, so with several jobs they add up to
+/// more than `elapsed`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexTimings {
+    /// Wall-clock time of the run.
+    pub elapsed: Duration,
+    /// Files indexed at once.
+    pub jobs: usize,
+    /// Files read, indexed or skipped.
+    pub files: usize,
+    /// Chunks embedded.
+    pub chunks: usize,
+    /// Parsing files into entities and chunks.
+    pub parsing: Duration,
+    /// Embedding chunks.
+    pub embedding: Duration,
+    /// The rest of the work on files: reading them and writing the database.
+    pub database: Duration,
+}
+
+impl IndexTimings {
+    /// Files per second of wall-clock time.
+    pub fn files_per_sec(&self) -> f64 {
+        per_sec(self.files, self.elapsed)
+    }
+
+    /// Chunks embedded per second of wall-clock time.
+    pub fn chunks_per_sec(&self) -> f64 {
+        per_sec(self.chunks, self.elapsed)
+    }
+}
+
+fn per_sec(count: usize, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    count as f64 / elapsed.as_secs_f64()
+}
+
+/// A file that could not be indexed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexFailure {
+    /// File path, relative to the indexed directory.
+    pub file: String,
+    /// Why the file failed.
+    pub error: String,
+    /// Language of the parser for the file, if it has one.
+    #[serde(default)]
+    pub parser: Option<String>,
+}
+
+/// Files that failed during an index run.
+///
+/// Failures don't stop the run; the report is saved so the files can be
+/// retried on their own with `arq init --retry-failed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexReport {
+    pub failures: Vec<IndexFailure>,
+}
+
+impl IndexReport {
+    /// Whether every file was indexed.
+    pub fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Paths of the failed files.
+    pub fn files(&self) -> Vec<String> {
+        self.failures.iter().map(|f| f.file.clone()).collect()
+    }
+
+    /// Load a saved report; a missing file is an empty report.
+    pub fn load(path: &Path) -> Result<Self, KnowledgeError> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(KnowledgeError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        serde_json::from_str(&json).map_err(|e| {
+            KnowledgeError::Corrupted(format!("index report {}: {}", path.display(), e))
+        })
+    }
+
+    /// Save the report to `path`, removing the file if nothing failed.
+    pub fn save(&self, path: &Path) -> Result<(), KnowledgeError> {
+        let io_error = |source| KnowledgeError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if self.is_empty() {
+            return match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error(e)),
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| KnowledgeError::Corrupted(e.to_string()))?;
+        std::fs::write(path, json).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_from_source() {
+        let source = "a\nb\nc\nd\ne\nf";
+        let context = SearchContext::from_source(source, 3, 4, 1);
+        assert_eq!(context.before, vec!["b"]);
+        assert_eq!(context.after, vec!["e"]);
+
+        // Clamped at the edges of the file
+        let context = SearchContext::from_source(source, 1, 6, 5);
+        assert!(context.before.is_empty());
+        assert!(context.after.is_empty());
+
+        let context = SearchContext::from_source(source, 2, 2, 10);
+        assert_eq!(context.before, vec!["a"]);
+        assert_eq!(context.after, vec!["c", "d", "e", "f"]);
+    }
+
+    #[test]
+    fn test_index_timings_rates() {
+        let timings = IndexTimings {
+            elapsed: Duration::from_secs(4),
+            files: 10,
+            chunks: 30,
+            ..IndexTimings::default()
+        };
+        assert_eq!(timings.files_per_sec(), 2.5);
+        assert_eq!(timings.chunks_per_sec(), 7.5);
+        assert_eq!(IndexTimings::default().files_per_sec(), 0.0);
+    }
+
+    #[test]
+    fn test_index_report_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index-failures.json");
+        assert!(IndexReport::load(&path).unwrap().is_empty());
+
+        let report = IndexReport {
+            failures: vec![IndexFailure {
+                file: "src/broken.rs".to_string(),
+                error: "stream did not contain valid UTF-8".to_string(),
+                parser: Some("Rust".to_string()),
+            }],
+        };
+        report.save(&path).unwrap();
+        assert_eq!(IndexReport::load(&path).unwrap(), report);
+        assert_eq!(report.files(), vec!["src/broken.rs"]);
+
+        // Saving an empty report clears the previous one
+        IndexReport::default().save(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
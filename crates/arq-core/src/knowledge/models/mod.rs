@@ -3,5 +3,7 @@
 mod chunk;
 mod node;
 
-pub use chunk::{CodeChunk, IndexStats, SearchResult};
+pub use chunk::{
+    CodeChunk, IndexFailure, IndexReport, IndexStats, IndexTimings, SearchContext, SearchResult,
+};
 pub use node::{FileNode, FunctionNode, StructNode};
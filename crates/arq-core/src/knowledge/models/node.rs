@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::{Datetime, Thing};
 
+use crate::knowledge::indexer::{language_name, LineStats};
+
 /// A file node in the knowledge graph.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileNode {
@@ -19,6 +21,21 @@ pub struct FileNode {
     pub hash: String,
     /// File size in bytes.
     pub size: u64,
+    /// Language, from the file extension.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Total lines.
+    #[serde(default)]
+    pub lines: u32,
+    /// Lines holding code.
+    #[serde(default)]
+    pub code_lines: u32,
+    /// Lines holding only comments.
+    #[serde(default)]
+    pub comment_lines: u32,
+    /// Empty lines.
+    #[serde(default)]
+    pub blank_lines: u32,
     /// When the file was indexed.
     pub indexed_at: Datetime,
 }
@@ -38,6 +55,8 @@ impl FileNode {
             .unwrap_or("")
             .to_string();
 
+        let language = Some(language_name(&extension));
+
         Self {
             id: None,
             path,
@@ -45,9 +64,23 @@ impl FileNode {
             extension,
             hash: hash.into(),
             size,
+            language,
+            lines: 0,
+            code_lines: 0,
+            comment_lines: 0,
+            blank_lines: 0,
             indexed_at: Datetime::default(),
         }
     }
+
+    /// Set the line counts of the file.
+    pub fn with_line_stats(mut self, stats: LineStats) -> Self {
+        self.lines = stats.lines;
+        self.code_lines = stats.code;
+        self.comment_lines = stats.comments;
+        self.blank_lines = stats.blanks;
+        self
+    }
 }
 
 /// A struct/class node in the knowledge graph.
@@ -0,0 +1,126 @@
+//! Name lookup fast path for code search.
+//!
+//! A query such as `parse_plan` or `Task::new` may name an entity, which the
+//! vector index ranks no better than other code about it. [`name_query`]
+//! recognises such queries, so [`KnowledgeStore::search_code`] looks them up
+//! in the name indexes of functions and structs, exact matches before prefix
+//! matches. A qualified name that matches exactly is answered without
+//! embedding the query; otherwise a single word may just as well be a
+//! topic (`authentication`), so the name hits are [`merge`]d ahead of the
+//! vector search results.
+//!
+//! [`KnowledgeStore::search_code`]: super::KnowledgeStore::search_code
+
+use super::models::SearchResult;
+use super::ontology::QualifiedName;
+
+/// Score of a search result whose name is the query.
+pub const EXACT_MATCH_SCORE: f32 = 1.0;
+
+/// Score of a search result whose name starts with the query.
+pub const PREFIX_MATCH_SCORE: f32 = 0.9;
+
+/// Shortest name matched by prefix; shorter queries only match exactly.
+pub const MIN_PREFIX_LEN: usize = 3;
+
+/// The name a search query consists of, if it is a single identifier or a
+/// path of identifiers (`Task::new`, `Task.new`).
+pub fn name_query(query: &str) -> Option<QualifiedName> {
+    let query = query.trim();
+    if query.is_empty() || query.chars().any(char::is_whitespace) {
+        return None;
+    }
+    let segments: Vec<&str> = query.split("::").flat_map(|s| s.split('.')).collect();
+    segments
+        .iter()
+        .all(|segment| is_identifier(segment))
+        .then(|| QualifiedName::parse(query))
+}
+
+/// Name hits followed by the vector search results they don't cover, up to
+/// `limit` results. A result covers another if it spans its lines.
+pub fn merge(
+    named: Vec<SearchResult>,
+    found: Vec<SearchResult>,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let found: Vec<SearchResult> = found
+        .into_iter()
+        .filter(|r| {
+            !named.iter().any(|n| {
+                n.path == r.path && n.start_line <= r.start_line && r.end_line <= n.end_line
+            })
+        })
+        .collect();
+    named.into_iter().chain(found).take(limit).collect()
+}
+
+fn is_identifier(segment: &str) -> bool {
+    let mut chars = segment.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_query() {
+        let name = |q: &str| name_query(q).map(|n| n.canonical());
+        assert_eq!(name("parse_plan"), Some("parse_plan".to_string()));
+        assert_eq!(name(" Task::new "), Some("Task::new".to_string()));
+        assert_eq!(name("Task.new"), Some("Task::new".to_string()));
+        assert_eq!(name("authentication handler"), None);
+        assert_eq!(name("fn()"), None);
+        assert_eq!(name("Task::"), None);
+        assert_eq!(name("2fa"), None);
+        assert_eq!(name(""), None);
+    }
+
+    fn result(path: &str, start_line: u32, end_line: u32, score: f32) -> SearchResult {
+        SearchResult {
+            path: path.to_string(),
+            score,
+            start_line,
+            end_line,
+            preview: None,
+            entity_id: None,
+            entity_type: "chunk".to_string(),
+            crate_name: None,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_vector_results() {
+        let named = vec![result("src/auth.rs", 10, 30, EXACT_MATCH_SCORE)];
+        let found = vec![
+            result("src/auth.rs", 12, 20, 0.8),
+            result("src/login.rs", 1, 15, 0.7),
+            result("src/session.rs", 5, 9, 0.6),
+        ];
+
+        let merged = merge(named, found, 3);
+        let spans: Vec<_> = merged
+            .iter()
+            .map(|r| (r.path.as_str(), r.start_line))
+            .collect();
+        // The chunk inside the named function isn't repeated
+        assert_eq!(
+            spans,
+            vec![
+                ("src/auth.rs", 10),
+                ("src/login.rs", 1),
+                ("src/session.rs", 5)
+            ]
+        );
+
+        assert_eq!(
+            merge(Vec::new(), vec![result("a.rs", 1, 2, 0.5)], 0).len(),
+            0
+        );
+    }
+}
@@ -63,6 +63,48 @@ pub enum CallType {
     Constructor,
 }
 
+/// How confidently a call edge's callee was identified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CallResolution {
+    /// Callee linked to a single defined function
+    #[default]
+    Resolved,
+    /// No definition found (external code or dynamic dispatch)
+    Unresolved,
+    /// Several definitions match the callee name
+    Ambiguous,
+}
+
+impl CallResolution {
+    /// Resolution implied by a callee ID; `function:?:name` is unresolved.
+    pub fn for_callee_id(callee_id: &str) -> Self {
+        if callee_id.contains(":?:") {
+            Self::Unresolved
+        } else {
+            Self::Resolved
+        }
+    }
+
+    /// Default confidence for an edge in this state.
+    pub fn default_confidence(self) -> f32 {
+        match self {
+            Self::Resolved => 1.0,
+            Self::Ambiguous => 0.5,
+            Self::Unresolved => 0.0,
+        }
+    }
+
+    /// Name stored in the `calls` table.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Resolved => "resolved",
+            Self::Unresolved => "unresolved",
+            Self::Ambiguous => "ambiguous",
+        }
+    }
+}
+
 impl CallsEdge {
     pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
         Self {
@@ -6,9 +6,10 @@
 //! ## Modules
 //!
 //! - `nodes/` - Entity types: Code (Function, Struct, Trait), API (Endpoint, Schema),
-//!   Structure (File, Module), Test (TestCase, TestSuite)
+//!   Structure (File, Module), Test (TestCase, TestSuite), Documentation (Document)
 //! - `edges/` - Relationship types: Structural (CONTAINS, IMPORTS), Behavioral (CALLS),
 //!   TypeSystem (IMPLEMENTS, EXTENDS), API (EXPOSES, MAPS_TO)
+//! - `qualified_name` - Canonical `::`-separated entity names across languages
 //!
 //! ## Design Principles
 //!
@@ -19,9 +20,11 @@
 
 pub mod edges;
 pub mod nodes;
+pub mod qualified_name;
 
 pub use edges::*;
 pub use nodes::*;
+pub use qualified_name::QualifiedName;
 
 use serde::{Deserialize, Serialize};
 
@@ -57,6 +60,8 @@ pub enum NodeCategory {
     Structure,
     /// Test entities (test cases, suites)
     Test,
+    /// Documentation entities (design docs, RFCs)
+    Documentation,
 }
 
 /// Categories of edges for filtering and organization.
@@ -52,6 +52,10 @@ pub struct FunctionEntity {
     /// Generic parameters (e.g., ["T", "U: Clone"])
     pub generics: Vec<String>,
 
+    /// Attributes and decorators (e.g., `@staticmethod`, `@app.route("/")`)
+    #[serde(default)]
+    pub attributes: Vec<String>,
+
     /// Parameter types
     pub parameters: Vec<Parameter>,
 
@@ -0,0 +1,72 @@
+//! Documentation entity nodes: design docs, RFCs, guides.
+//!
+//! These represent prose documentation indexed next to the code with
+//! `arq init --docs`, so research can cite it.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// =============================================================================
+// DOCUMENT ENTITY
+// =============================================================================
+
+/// A documentation file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentEntity {
+    /// Unique identifier
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Title: the first top-level heading, or the file name
+    pub title: String,
+
+    /// Relative path from project root
+    pub file_path: String,
+
+    /// Markup format
+    pub format: DocumentFormat,
+
+    /// Section headings, in document order
+    pub headings: Vec<String>,
+
+    /// First line (always 1)
+    pub start_line: u32,
+
+    /// Last line
+    pub end_line: u32,
+}
+
+impl DocumentEntity {
+    /// Entity ID of a document, in the `kind:path` form of other entities.
+    pub fn entity_id(path: &str) -> String {
+        format!("document:{}", path)
+    }
+}
+
+/// Markup format of a documentation file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    Markdown,
+    /// reStructuredText
+    Rst,
+}
+
+impl DocumentFormat {
+    /// Format of a file by its extension, if it is documentation.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "md" | "markdown" | "mdx" => Some(Self::Markdown),
+            "rst" => Some(Self::Rst),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Rst => "rst",
+        }
+    }
+}
@@ -6,14 +6,17 @@
 //! - **API**: Endpoints, Schemas, Operations
 //! - **Structure**: Files, Modules, Packages
 //! - **Test**: Test cases, Test suites
+//! - **Documentation**: Design docs and other prose indexed with `--docs`
 
 mod api;
 mod code;
+mod document;
 mod structure;
 mod test;
 
 pub use api::*;
 pub use code::*;
+pub use document::*;
 pub use structure::*;
 pub use test::*;
 
@@ -62,6 +65,10 @@ pub enum GraphNode {
     TestCase(TestCaseEntity),
     /// A test suite/module
     TestSuite(TestSuiteEntity),
+
+    // === Documentation Nodes ===
+    /// A documentation file
+    Document(DocumentEntity),
 }
 
 impl GraphNode {
@@ -80,6 +87,8 @@ impl GraphNode {
             Self::File(_) | Self::Module(_) | Self::Package(_) => NodeCategory::Structure,
 
             Self::TestCase(_) | Self::TestSuite(_) => NodeCategory::Test,
+
+            Self::Document(_) => NodeCategory::Documentation,
         }
     }
 
@@ -100,6 +109,7 @@ impl GraphNode {
             Self::Package(_) => "Package",
             Self::TestCase(_) => "TestCase",
             Self::TestSuite(_) => "TestSuite",
+            Self::Document(_) => "Document",
         }
     }
 
@@ -120,6 +130,7 @@ impl GraphNode {
             Self::Package(n) => n.id.as_deref(),
             Self::TestCase(n) => n.id.as_deref(),
             Self::TestSuite(n) => n.id.as_deref(),
+            Self::Document(n) => n.id.as_deref(),
         }
     }
 
@@ -140,6 +151,7 @@ impl GraphNode {
             Self::Package(n) => &n.name,
             Self::TestCase(n) => &n.name,
             Self::TestSuite(n) => &n.name,
+            Self::Document(n) => &n.title,
         }
     }
 }
@@ -0,0 +1,202 @@
+//! Canonical qualified names for code entities.
+//!
+//! Languages separate path segments differently (`Type::method` in Rust,
+//! `Class.method` in Java, C#, Go and Python). Entities are stored with the
+//! canonical `::` separator so lookups work the same across languages;
+//! [`QualifiedName::format`] renders a name in a language's own style.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::nodes::Language;
+
+/// A qualified name split into path segments (`["app_core", "Task", "new"]`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct QualifiedName {
+    segments: Vec<String>,
+}
+
+impl QualifiedName {
+    /// Separator used in canonical (stored) qualified names.
+    pub const SEPARATOR: &'static str = "::";
+
+    /// Parse a qualified name written with `::` or `.` separators.
+    ///
+    /// Separators inside generic arguments (`Map<a::B, C>::get`) are kept.
+    pub fn parse(raw: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0usize;
+        let mut chars = raw.trim().chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '<' | '[' | '(' => depth += 1,
+                '>' | ']' | ')' => depth = depth.saturating_sub(1),
+                ':' if depth == 0 && chars.peek() == Some(&':') => {
+                    chars.next();
+                    segments.push(std::mem::take(&mut current));
+                    continue;
+                }
+                '.' if depth == 0 => {
+                    segments.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        segments.push(current);
+
+        Self {
+            segments: segments.into_iter().filter(|s| !s.is_empty()).collect(),
+        }
+    }
+
+    /// Name of `name` inside an optional parent (class, impl target, receiver).
+    pub fn from_parts(parent: Option<&str>, name: &str) -> Self {
+        let mut qualified = parent.map(Self::parse).unwrap_or_default();
+        qualified.segments.extend(Self::parse(name).segments);
+        qualified
+    }
+
+    /// Qualified name part of an entity ID (`function:{path}:{qualified_name}`).
+    ///
+    /// Falls back to parsing the whole string when it is not an entity ID.
+    pub fn from_entity_id(id: &str) -> Self {
+        let id = id.trim_matches('`');
+        let name = match id.split_once(':') {
+            // Skip the table and path; the path never contains "::"
+            Some((_, rest)) if !rest.starts_with(':') => match rest.split_once(':') {
+                Some((_, name)) if !name.starts_with(':') => name,
+                _ => rest,
+            },
+            _ => id,
+        };
+        Self::parse(name.trim_matches('`'))
+    }
+
+    /// Path segments, outermost first.
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// Innermost segment (the entity's own name).
+    pub fn name(&self) -> &str {
+        self.segments.last().map(String::as_str).unwrap_or_default()
+    }
+
+    /// The enclosing name, if any (`Task` for `Task::new`).
+    pub fn parent(&self) -> Option<Self> {
+        (self.segments.len() > 1).then(|| Self {
+            segments: self.segments[..self.segments.len() - 1].to_vec(),
+        })
+    }
+
+    /// This name nested under `prefix` (e.g. a crate name).
+    pub fn with_prefix(&self, prefix: &str) -> Self {
+        Self::from_parts(Some(prefix), &self.canonical())
+    }
+
+    /// Whether this name ends with all segments of `other`.
+    ///
+    /// `app_core::Task::new` matches `Task::new`, `Task.new` and `new`.
+    pub fn ends_with(&self, other: &QualifiedName) -> bool {
+        !other.segments.is_empty() && self.segments.ends_with(&other.segments)
+    }
+
+    /// Canonical form, joined with `::`.
+    pub fn canonical(&self) -> String {
+        self.segments.join(Self::SEPARATOR)
+    }
+
+    /// Rendered with a language's own separator.
+    pub fn format(&self, language: Language) -> String {
+        self.segments.join(separator_for(language))
+    }
+}
+
+impl fmt::Display for QualifiedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.canonical())
+    }
+}
+
+impl FromStr for QualifiedName {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl From<&str> for QualifiedName {
+    fn from(s: &str) -> Self {
+        Self::parse(s)
+    }
+}
+
+/// Path separator a language uses in source code.
+pub fn separator_for(language: Language) -> &'static str {
+    match language {
+        Language::Rust | Language::Cpp | Language::Php => "::",
+        _ => ".",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_separators() {
+        assert_eq!(
+            QualifiedName::parse("Task::new").segments(),
+            ["Task", "new"]
+        );
+        assert_eq!(QualifiedName::parse("Task.new").segments(), ["Task", "new"]);
+        assert_eq!(QualifiedName::parse("run").segments(), ["run"]);
+        assert_eq!(QualifiedName::parse("").segments().len(), 0);
+    }
+
+    #[test]
+    fn test_parse_keeps_generic_arguments() {
+        let name = QualifiedName::parse("Cache<std::string::String>::get");
+        assert_eq!(name.segments(), ["Cache<std::string::String>", "get"]);
+        assert_eq!(name.name(), "get");
+    }
+
+    #[test]
+    fn test_canonical_and_format() {
+        let name = QualifiedName::from_parts(Some("UserService"), "findById");
+        assert_eq!(name.canonical(), "UserService::findById");
+        assert_eq!(name.format(Language::Java), "UserService.findById");
+        assert_eq!(name.format(Language::Rust), "UserService::findById");
+        assert_eq!(name.to_string(), name.canonical());
+    }
+
+    #[test]
+    fn test_from_entity_id() {
+        let rust = QualifiedName::from_entity_id("function:src/lib.rs:app_core::Task::new");
+        assert_eq!(rust.canonical(), "app_core::Task::new");
+
+        let java = QualifiedName::from_entity_id("function:src/App.java:App.main");
+        assert_eq!(java.canonical(), "App::main");
+
+        let unresolved = QualifiedName::from_entity_id("function:?:helper");
+        assert_eq!(unresolved.name(), "helper");
+
+        assert_eq!(QualifiedName::from_entity_id("helper").name(), "helper");
+        assert_eq!(QualifiedName::from_entity_id("Task::new").name(), "new");
+    }
+
+    #[test]
+    fn test_ends_with_and_prefix() {
+        let name = QualifiedName::parse("Task::new").with_prefix("app_core");
+        assert_eq!(name.canonical(), "app_core::Task::new");
+        assert!(name.ends_with(&"Task.new".into()));
+        assert!(name.ends_with(&"new".into()));
+        assert!(!name.ends_with(&"Plan::new".into()));
+        assert_eq!(name.parent().unwrap().canonical(), "app_core::Task");
+    }
+}
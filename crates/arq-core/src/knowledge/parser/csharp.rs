@@ -10,6 +10,7 @@ use crate::knowledge::ontology::nodes::{
     EnumEntity, EnumVariant, FieldInfo, FunctionEntity, Parameter, StructEntity, TraitEntity,
     Visibility,
 };
+use crate::knowledge::ontology::QualifiedName;
 
 /// C# parser using tree-sitter.
 pub struct CSharpParser {
@@ -38,11 +39,7 @@ impl CSharpParser {
             .child_by_field_name("type")
             .map(|n| TreeSitterParser::node_text(&n, content).to_string());
 
-        let qualified_name = if let Some(class) = class_name {
-            format!("{}.{}", class, name)
-        } else {
-            name.clone()
-        };
+        let qualified_name = QualifiedName::from_parts(class_name, &name).canonical();
 
         let modifiers = self.extract_modifiers(node, content);
         let visibility = self.modifiers_to_visibility(&modifiers);
@@ -60,6 +57,7 @@ impl CSharpParser {
             is_async: modifiers.contains(&"async".to_string()),
             is_unsafe: modifiers.contains(&"unsafe".to_string()),
             generics: self.extract_type_parameters(node, content),
+            attributes: Vec::new(),
             parameters: params,
             return_type,
             doc_comment: self.extract_xml_doc(node, content),
@@ -0,0 +1,229 @@
+//! Parsers run as external processes.
+//!
+//! Lets languages without a built-in parser be indexed by any program that
+//! reads a source file on stdin and prints a [`ParseResult`] as JSON:
+//!
+//! ```json
+//! {
+//!   "nodes": [{ "kind": "struct", "name": "Point", "qualified_name": "geo.Point",
+//!               "file_path": "src/geo.zig", "start_line": 3, "end_line": 6 }],
+//!   "edges": [],
+//!   "warnings": []
+//! }
+//! ```
+//!
+//! Nodes and edges are tagged by `kind` (`function`, `struct`, `calls`,
+//! `uses_type`, ...) and carry the fields of the matching ontology type. The
+//! file's relative path is passed in `ARQ_FILE_PATH`.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use super::result::ParseResult;
+use super::traits::{Parser, ParserCapability};
+use crate::config::ExternalParserConfig;
+
+/// How often a running parser is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A parser that delegates to an external command.
+pub struct ExternalParser {
+    command: String,
+    args: Vec<String>,
+    language: &'static str,
+    extensions: Vec<&'static str>,
+    timeout: Duration,
+}
+
+impl ExternalParser {
+    /// Create a parser from its `[[parsers.external]]` config entry.
+    ///
+    /// The language name and extensions live for the rest of the process,
+    /// as the [`Parser`] trait hands them out as `'static`; parsers are built
+    /// once per configuration, so this is bounded.
+    pub fn from_config(config: &ExternalParserConfig) -> Self {
+        let language = config.language.clone().unwrap_or_else(|| {
+            std::path::Path::new(&config.command)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| config.command.clone())
+        });
+        let extensions = config
+            .extensions
+            .iter()
+            .map(|ext| leak(ext.trim_start_matches('.').to_lowercase()))
+            .collect();
+
+        Self {
+            command: config.command.clone(),
+            args: config.args.clone(),
+            language: leak(language),
+            extensions,
+            timeout: Duration::from_secs(config.timeout_secs),
+        }
+    }
+
+    /// Run the command on `content`, returning its stdout.
+    fn run(&self, path: &str, content: &str) -> Result<Vec<u8>, String> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .env("ARQ_FILE_PATH", path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to run '{}': {}", self.command, e))?;
+
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+
+        // Feed stdin and drain the output concurrently so a parser that
+        // writes before it has read everything cannot deadlock
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                // A parser may exit without reading all of its input
+                let _ = stdin.write_all(content.as_bytes());
+            });
+            let out = scope.spawn(move || {
+                let mut buf = Vec::new();
+                stdout.read_to_end(&mut buf).map(|_| buf)
+            });
+            let err = scope.spawn(move || {
+                let mut buf = String::new();
+                let _ = stderr.read_to_string(&mut buf);
+                buf
+            });
+
+            let deadline = Instant::now() + self.timeout;
+            let status = loop {
+                match child.try_wait() {
+                    Ok(Some(status)) => break status,
+                    Ok(None) if Instant::now() >= deadline => {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return Err(format!(
+                            "'{}' timed out after {}s",
+                            self.command,
+                            self.timeout.as_secs()
+                        ));
+                    }
+                    Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                    Err(e) => return Err(format!("failed to wait for '{}': {}", self.command, e)),
+                }
+            };
+
+            let stdout = out
+                .join()
+                .expect("stdout reader panicked")
+                .map_err(|e| format!("failed to read output of '{}': {}", self.command, e))?;
+            let stderr = err.join().expect("stderr reader panicked");
+
+            if !status.success() {
+                return Err(format!(
+                    "'{}' exited with {}: {}",
+                    self.command,
+                    status,
+                    stderr.trim()
+                ));
+            }
+            Ok(stdout)
+        })
+    }
+}
+
+/// Keep a configured name alive for the rest of the process.
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+impl Parser for ExternalParser {
+    fn parse_file(&self, path: &str, content: &str) -> Result<ParseResult, String> {
+        let output = self.run(path, content)?;
+        let mut result: ParseResult = serde_json::from_slice(&output)
+            .map_err(|e| format!("invalid output from '{}': {}", self.command, e))?;
+        if result.file_path.is_empty() {
+            result.file_path = path.to_string();
+        }
+        Ok(result)
+    }
+
+    fn language_name(&self) -> &'static str {
+        self.language
+    }
+
+    fn supported_extensions(&self) -> &[&'static str] {
+        &self.extensions
+    }
+
+    fn capability(&self) -> ParserCapability {
+        ParserCapability::Basic
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::knowledge::parser::ParsedNode;
+
+    fn shell_parser(script: &str) -> ExternalParser {
+        ExternalParser::from_config(&ExternalParserConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            extensions: vec![".Zig".to_string()],
+            language: Some("Zig".to_string()),
+            timeout_secs: 2,
+        })
+    }
+
+    #[test]
+    fn test_parses_json_output() {
+        let parser = shell_parser(
+            r#"cat >/dev/null; printf '{"nodes":[{"kind":"struct","name":"Point","qualified_name":"geo.Point","file_path":"%s","start_line":1,"end_line":3}]}' "$ARQ_FILE_PATH""#,
+        );
+        assert_eq!(parser.language_name(), "Zig");
+        assert_eq!(parser.supported_extensions(), &["zig"]);
+
+        let result = parser
+            .parse_file("src/geo.zig", "const Point = struct {};")
+            .unwrap();
+        assert_eq!(result.file_path, "src/geo.zig");
+        assert_eq!(result.nodes.len(), 1);
+        match &result.nodes[0] {
+            ParsedNode::Struct(s) => {
+                assert_eq!(s.name, "Point");
+                assert_eq!(s.file_path, "src/geo.zig");
+            }
+            other => panic!("expected a struct, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reports_failures() {
+        let parser = shell_parser("echo 'syntax error' >&2; exit 3");
+        let err = parser.parse_file("a.zig", "").unwrap_err();
+        assert!(err.contains("syntax error"), "{}", err);
+
+        let parser = shell_parser("echo not json");
+        assert!(parser.parse_file("a.zig", "").is_err());
+
+        let parser = ExternalParser::from_config(&ExternalParserConfig {
+            command: "arq-no-such-parser".to_string(),
+            ..ExternalParserConfig::default()
+        });
+        assert!(parser.parse_file("a.zig", "").is_err());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let parser = ExternalParser::from_config(&ExternalParserConfig {
+            command: "sleep".to_string(),
+            args: vec!["5".to_string()],
+            timeout_secs: 0,
+            ..ExternalParserConfig::default()
+        });
+        let err = parser.parse_file("a.zig", "").unwrap_err();
+        assert!(err.contains("timed out"), "{}", err);
+    }
+}
@@ -9,6 +9,7 @@ use crate::knowledge::ontology::edges::{CallType, CallsEdge, ContainsEdge};
 use crate::knowledge::ontology::nodes::{
     FieldInfo, FunctionEntity, Parameter, StructEntity, TraitEntity, Visibility,
 };
+use crate::knowledge::ontology::QualifiedName;
 
 /// Go parser using tree-sitter.
 pub struct GoParser {
@@ -42,11 +43,11 @@ impl GoParser {
             result
         });
 
-        let qualified_name = if let Some(ref recv) = receiver {
-            format!("{}.{}", recv.trim_start_matches('*'), name)
-        } else {
-            name.clone()
-        };
+        let qualified_name = QualifiedName::from_parts(
+            receiver.as_deref().map(|r| r.trim_start_matches('*')),
+            &name,
+        )
+        .canonical();
 
         Some(FunctionEntity {
             id: Some(format!("function:{}:{}", path, qualified_name)),
@@ -61,6 +62,7 @@ impl GoParser {
             is_async: false,
             is_unsafe: false,
             generics: Vec::new(),
+            attributes: Vec::new(),
             parameters: params,
             return_type,
             doc_comment: extract_doc_comment(node, content),
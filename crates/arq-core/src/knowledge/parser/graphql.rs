@@ -0,0 +1,592 @@
+//! GraphQL SDL parser.
+//!
+//! Extracts object, input, interface, enum and union types as
+//! [`SchemaEntity`] nodes, and the fields of the root `Query`, `Mutation` and
+//! `Subscription` types as [`OperationEntity`] nodes. Each operation
+//! CONSUMES the types of its arguments and PRODUCES its return type.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use super::result::ParseResult;
+use super::traits::{Parser, ParserCapability};
+use crate::knowledge::ontology::edges::{ConsumesEdge, ContainsEdge, ProducesEdge};
+use crate::knowledge::ontology::nodes::{
+    HttpMethod, OperationEntity, OperationResponse, SchemaEntity, SchemaField, SchemaType,
+    SerializationFormat,
+};
+use crate::knowledge::ontology::QualifiedName;
+
+/// Scalars built into GraphQL, which are not modelled as schema nodes.
+const BUILTIN_SCALARS: &[&str] = &["Int", "Float", "String", "Boolean", "ID"];
+
+fn definition_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"\b(extend\s+)?(type|input|interface|enum|union|schema)\b\s*([A-Za-z_]\w*)?")
+            .expect("valid definition regex")
+    })
+}
+
+fn field_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"([A-Za-z_]\w*)\s*(?:\(([^)]*)\))?\s*:\s*(\[[^\]]*\]\s*!?|[A-Za-z_]\w*\s*!?)")
+            .expect("valid field regex")
+    })
+}
+
+fn directive_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"@\w+(\s*\([^)]*\))?").expect("valid directive regex"))
+}
+
+/// GraphQL schema (SDL) parser.
+pub struct GraphQLParser;
+
+impl GraphQLParser {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for GraphQLParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A type definition found in the SDL.
+struct Definition<'a> {
+    kind: &'a str,
+    name: String,
+    /// Text between the braces (or after `=` for unions)
+    body: &'a str,
+    /// Byte offset of `body` in the cleaned source
+    body_offset: usize,
+    start_line: u32,
+    end_line: u32,
+}
+
+/// A field parsed from a type body.
+struct Field {
+    name: String,
+    args: Vec<(String, String)>,
+    type_name: String,
+    line: u32,
+}
+
+impl Parser for GraphQLParser {
+    fn parse_file(&self, path: &str, content: &str) -> Result<ParseResult, String> {
+        let mut result = ParseResult::new(path);
+        let source = clean_source(content);
+        let definitions = find_definitions(&source);
+
+        // `schema { query: RootQuery }` renames the root operation types
+        let mut roots: HashMap<String, HttpMethod> = HashMap::from([
+            ("Query".to_string(), HttpMethod::Query),
+            ("Mutation".to_string(), HttpMethod::Mutation),
+            ("Subscription".to_string(), HttpMethod::Subscription),
+        ]);
+        for def in definitions.iter().filter(|d| d.kind == "schema") {
+            roots.clear();
+            for field in parse_fields(&source, def) {
+                let method = match field.name.as_str() {
+                    "query" => HttpMethod::Query,
+                    "mutation" => HttpMethod::Mutation,
+                    "subscription" => HttpMethod::Subscription,
+                    _ => continue,
+                };
+                roots.insert(base_type(&field.type_name).to_string(), method);
+            }
+        }
+
+        let local_types: Vec<&str> = definitions
+            .iter()
+            .filter(|d| d.kind != "schema" && !roots.contains_key(&d.name))
+            .map(|d| d.name.as_str())
+            .collect();
+        let schema_id = |type_name: &str| {
+            if local_types.contains(&type_name) {
+                format!("schema:{}:{}", path, type_name)
+            } else {
+                format!("schema:?:{}", type_name)
+            }
+        };
+
+        let file_id = format!("file:{}", path);
+        let mut schemas: Vec<SchemaEntity> = Vec::new();
+
+        for def in &definitions {
+            if def.kind == "schema" {
+                continue;
+            }
+
+            if let Some(&method) = roots.get(&def.name) {
+                for field in parse_fields(&source, def) {
+                    let qualified =
+                        QualifiedName::from_parts(Some(&def.name), &field.name).canonical();
+                    let id = format!("operation:{}:{}", path, qualified);
+                    let return_type = base_type(&field.type_name).to_string();
+
+                    for (_, arg_type) in &field.args {
+                        let arg_base = base_type(arg_type);
+                        if is_builtin_scalar(arg_base) {
+                            continue;
+                        }
+                        let edge = ConsumesEdge::new(&id, schema_id(arg_base));
+                        result.add_consumes(if arg_type.trim_end().ends_with('!') {
+                            edge
+                        } else {
+                            edge.optional()
+                        });
+                    }
+                    if !is_builtin_scalar(&return_type) {
+                        result.add_produces(ProducesEdge::new(&id, schema_id(&return_type)));
+                    }
+
+                    result.add_operation(OperationEntity {
+                        id: Some(id.clone()),
+                        name: field.name.clone(),
+                        operation_id: field.name.clone(),
+                        file_path: path.to_string(),
+                        line: field.line,
+                        method,
+                        path: qualified,
+                        summary: None,
+                        description: None,
+                        tags: Vec::new(),
+                        request_body: field
+                            .args
+                            .iter()
+                            .map(|(_, t)| base_type(t))
+                            .find(|t| !is_builtin_scalar(t))
+                            .map(str::to_string),
+                        responses: vec![OperationResponse {
+                            status_code: 200,
+                            description: None,
+                            schema: Some(return_type),
+                        }],
+                    });
+                    result.add_contains(ContainsEdge::new(&file_id, &id));
+                }
+                continue;
+            }
+
+            let fields: Vec<SchemaField> = match def.kind {
+                "enum" => enum_values(def.body)
+                    .into_iter()
+                    .map(|value| schema_field(value, &def.name))
+                    .collect(),
+                "union" => def
+                    .body
+                    .split('|')
+                    .map(str::trim)
+                    .filter(|member| !member.is_empty())
+                    .map(|member| schema_field(member.to_string(), member))
+                    .collect(),
+                _ => parse_fields(&source, def)
+                    .into_iter()
+                    .map(|f| schema_field(f.name, &f.type_name))
+                    .collect(),
+            };
+
+            // `extend type` in the same file adds fields to the original
+            if let Some(existing) = schemas.iter_mut().find(|s| s.name == def.name) {
+                existing.fields.extend(fields);
+                existing.end_line = existing.end_line.max(def.end_line);
+                continue;
+            }
+
+            schemas.push(SchemaEntity {
+                id: Some(format!("schema:{}:{}", path, def.name)),
+                name: def.name.clone(),
+                qualified_name: def.name.clone(),
+                file_path: path.to_string(),
+                start_line: def.start_line,
+                end_line: def.end_line,
+                schema_type: match def.kind {
+                    "input" => SchemaType::Request,
+                    "type" => SchemaType::Response,
+                    _ => SchemaType::Dto,
+                },
+                fields,
+                source_type: None,
+                validations: Vec::new(),
+                format: SerializationFormat::Json,
+                description: None,
+                example: None,
+            });
+        }
+
+        for schema in schemas {
+            if let Some(ref id) = schema.id {
+                result.add_contains(ContainsEdge::new(&file_id, id));
+            }
+            result.add_schema(schema);
+        }
+
+        Ok(result)
+    }
+
+    fn language_name(&self) -> &'static str {
+        "GraphQL"
+    }
+
+    fn supported_extensions(&self) -> &[&'static str] {
+        &["graphql", "gql"]
+    }
+
+    fn capability(&self) -> ParserCapability {
+        ParserCapability::Basic
+    }
+}
+
+/// Strip comments, descriptions and directives, keeping line breaks so byte
+/// offsets still map to the right line.
+fn clean_source(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '#' => {
+                while let Some(&(_, next)) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    out.push(' ');
+                    chars.next();
+                }
+                out.push(' ');
+            }
+            '"' => {
+                let block = content[i..].starts_with("\"\"\"");
+                let terminator = if block { "\"\"\"" } else { "\"" };
+                let skip = terminator.len() - 1;
+                out.push(' ');
+                for _ in 0..skip {
+                    chars.next();
+                    out.push(' ');
+                }
+                while let Some((j, next)) = chars.next() {
+                    if next == '\\' && !block {
+                        chars.next();
+                        out.push_str("  ");
+                        continue;
+                    }
+                    if content[j..].starts_with(terminator) {
+                        for _ in 0..skip {
+                            chars.next();
+                        }
+                        out.push_str(&" ".repeat(terminator.len()));
+                        break;
+                    }
+                    out.push(if next == '\n' { '\n' } else { ' ' });
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    directive_regex()
+        .replace_all(&out, |caps: &regex::Captures| blank(&caps[0]))
+        .into_owned()
+}
+
+/// Replace everything but newlines with spaces.
+fn blank(text: &str) -> String {
+    text.chars()
+        .map(|c| if c == '\n' { '\n' } else { ' ' })
+        .collect()
+}
+
+fn find_definitions(source: &str) -> Vec<Definition<'_>> {
+    let mut definitions = Vec::new();
+
+    for caps in definition_regex().captures_iter(source) {
+        let whole = caps.get(0).expect("match");
+        // Only definitions at the top level, not `type` used as a field name
+        if depth_at(source, whole.start()) != 0 {
+            continue;
+        }
+
+        let kind = caps.get(2).expect("kind").as_str();
+        let name = caps.get(3).map(|m| m.as_str().to_string());
+        let name = match (kind, name) {
+            ("schema", _) => "schema".to_string(),
+            (_, Some(name)) => name,
+            _ => continue,
+        };
+
+        let rest = &source[whole.end()..];
+        let (body, body_offset, end) = if kind == "union" {
+            // `union SearchResult = User | Post`
+            let Some(eq) = rest.find('=') else { continue };
+            if !rest[..eq].trim().is_empty() {
+                continue;
+            }
+            let after = &rest[eq + 1..];
+            let len = after[..union_members_len(after)].trim_end().len();
+            let start = whole.end() + eq + 1;
+            (&source[start..start + len], start, start + len)
+        } else {
+            let Some(open) = rest.find('{') else { continue };
+            // Anything but `implements A & B` before the brace means no body
+            if !rest[..open]
+                .chars()
+                .all(|c| c.is_alphanumeric() || c.is_whitespace() || "_&,".contains(c))
+            {
+                continue;
+            }
+            let start = whole.end() + open + 1;
+            let Some(close) = matching_brace(&source[start..]) else {
+                continue;
+            };
+            (&source[start..start + close], start, start + close + 1)
+        };
+
+        definitions.push(Definition {
+            kind,
+            name,
+            body,
+            body_offset,
+            start_line: line_at(source, whole.start()),
+            end_line: line_at(source, end.saturating_sub(1)),
+        });
+    }
+
+    definitions
+}
+
+fn parse_fields(source: &str, def: &Definition<'_>) -> Vec<Field> {
+    field_regex()
+        .captures_iter(def.body)
+        .map(|caps| {
+            let name = caps.get(1).expect("name");
+            Field {
+                name: name.as_str().to_string(),
+                args: caps
+                    .get(2)
+                    .map(|args| parse_args(args.as_str()))
+                    .unwrap_or_default(),
+                type_name: caps[3].split_whitespace().collect(),
+                line: line_at(source, def.body_offset + name.start()),
+            }
+        })
+        .collect()
+}
+
+fn parse_args(args: &str) -> Vec<(String, String)> {
+    field_regex()
+        .captures_iter(args)
+        .map(|caps| (caps[1].to_string(), caps[3].split_whitespace().collect()))
+        .collect()
+}
+
+fn enum_values(body: &str) -> Vec<String> {
+    body.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|v| !v.is_empty() && v.chars().all(|c| c.is_alphanumeric() || c == '_'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn schema_field(name: String, type_name: &str) -> SchemaField {
+    let required = type_name.trim_end().ends_with('!');
+    SchemaField {
+        name,
+        type_name: type_name.to_string(),
+        required,
+        nullable: !required,
+        default_value: None,
+        description: None,
+        validation: None,
+        serialized_name: None,
+    }
+}
+
+/// The named type inside list and non-null wrappers: `[User!]!` -> `User`.
+fn base_type(type_name: &str) -> &str {
+    type_name.trim_matches(|c: char| c == '[' || c == ']' || c == '!' || c.is_whitespace())
+}
+
+fn is_builtin_scalar(type_name: &str) -> bool {
+    BUILTIN_SCALARS.contains(&type_name)
+}
+
+/// Length of `= A | B | C`, stopping at the next definition.
+fn union_members_len(text: &str) -> usize {
+    let mut len = 0;
+    let mut expect_member = true;
+    for token in text.split_inclusive(|c: char| c.is_whitespace() || c == '|') {
+        let word = token.trim_matches(|c: char| c.is_whitespace() || c == '|');
+        if !word.is_empty() {
+            if !expect_member {
+                break;
+            }
+            expect_member = false;
+        }
+        if token.contains('|') {
+            expect_member = true;
+        }
+        len += token.len();
+    }
+    len
+}
+
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn depth_at(source: &str, offset: usize) -> i32 {
+    source[..offset].chars().fold(0, |depth, c| match c {
+        '{' => depth + 1,
+        '}' => depth - 1,
+        _ => depth,
+    })
+}
+
+fn line_at(source: &str, offset: usize) -> u32 {
+    source[..offset.min(source.len())].matches('\n').count() as u32 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::parser::{ParsedEdge, ParsedNode};
+
+    const SCHEMA: &str = r#"
+"""A registered user"""
+type User {
+  id: ID!
+  name: String # display name
+  posts(first: Int = 10): [Post!]!
+}
+
+input CreateUserInput {
+  name: String!
+}
+
+enum Role { ADMIN, MEMBER }
+
+union SearchResult = User | Post
+
+type Query {
+  user(id: ID!): User
+  search(term: String!): [SearchResult!]! @deprecated(reason: "use find")
+}
+
+type Mutation {
+  createUser(input: CreateUserInput!): User!
+}
+"#;
+
+    fn parse() -> ParseResult {
+        GraphQLParser::new()
+            .parse_file("schema.graphql", SCHEMA)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_extract_schemas() {
+        let result = parse();
+        let schemas: Vec<&SchemaEntity> = result
+            .nodes
+            .iter()
+            .filter_map(|n| match n {
+                ParsedNode::Schema(s) => Some(s),
+                _ => None,
+            })
+            .collect();
+
+        let names: Vec<&str> = schemas.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["User", "CreateUserInput", "Role", "SearchResult"]
+        );
+
+        let user = schemas[0];
+        assert_eq!(user.start_line, 3);
+        assert_eq!(user.fields.len(), 3);
+        assert!(user.fields[0].required);
+        assert_eq!(user.fields[2].type_name, "[Post!]!");
+        assert_eq!(schemas[1].schema_type, SchemaType::Request);
+        assert_eq!(schemas[2].fields.len(), 2);
+        assert_eq!(schemas[3].fields.len(), 2);
+    }
+
+    #[test]
+    fn test_extract_operations() {
+        let result = parse();
+        let operations: Vec<&OperationEntity> = result
+            .nodes
+            .iter()
+            .filter_map(|n| match n {
+                ParsedNode::Operation(o) => Some(o),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(operations.len(), 3);
+        assert_eq!(operations[0].path, "Query::user");
+        assert_eq!(operations[0].method, HttpMethod::Query);
+        assert_eq!(operations[0].line, 18);
+        assert_eq!(operations[2].name, "createUser");
+        assert_eq!(operations[2].method, HttpMethod::Mutation);
+        assert_eq!(
+            operations[2].request_body.as_deref(),
+            Some("CreateUserInput")
+        );
+    }
+
+    #[test]
+    fn test_consumes_and_produces_edges() {
+        let result = parse();
+
+        assert!(result.edges.iter().any(|e| matches!(e,
+            ParsedEdge::Consumes(c) if c.from == "operation:schema.graphql:Mutation::createUser"
+                && c.to == "schema:schema.graphql:CreateUserInput"
+                && c.required)));
+        assert!(result.edges.iter().any(|e| matches!(e,
+            ParsedEdge::Produces(p) if p.from == "operation:schema.graphql:Query::search"
+                && p.to == "schema:schema.graphql:SearchResult")));
+        // Scalars are not schema nodes
+        assert!(!result.edges.iter().any(|e| matches!(e,
+            ParsedEdge::Consumes(c) if c.from == "operation:schema.graphql:Query::user")));
+    }
+
+    #[test]
+    fn test_custom_root_types_and_external_types() {
+        let sdl = r#"
+schema { query: RootQuery }
+
+type RootQuery {
+  orders: [Order]
+}
+"#;
+        let result = GraphQLParser::new()
+            .parse_file("api/root.gql", sdl)
+            .unwrap();
+
+        assert!(result.nodes.iter().any(|n| matches!(n,
+            ParsedNode::Operation(o) if o.path == "RootQuery::orders")));
+        // Types defined in other files stay unresolved
+        assert!(result.edges.iter().any(|e| matches!(e,
+            ParsedEdge::Produces(p) if p.to == "schema:?:Order")));
+    }
+}
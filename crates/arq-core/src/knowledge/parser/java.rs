@@ -10,6 +10,7 @@ use crate::knowledge::ontology::nodes::{
     EnumEntity, EnumVariant, FieldInfo, FunctionEntity, Parameter, StructEntity, TraitEntity,
     Visibility,
 };
+use crate::knowledge::ontology::QualifiedName;
 
 /// Java parser using tree-sitter.
 pub struct JavaParser {
@@ -38,11 +39,7 @@ impl JavaParser {
             .child_by_field_name("type")
             .map(|n| TreeSitterParser::node_text(&n, content).to_string());
 
-        let qualified_name = if let Some(class) = class_name {
-            format!("{}.{}", class, name)
-        } else {
-            name.clone()
-        };
+        let qualified_name = QualifiedName::from_parts(class_name, &name).canonical();
 
         // Check modifiers for async/static
         let modifiers = self.extract_modifiers(node, content);
@@ -61,6 +58,7 @@ impl JavaParser {
             is_async: modifiers.contains(&"synchronized".to_string()),
             is_unsafe: modifiers.contains(&"native".to_string()),
             generics: self.extract_type_parameters(node, content),
+            attributes: Vec::new(),
             parameters: params,
             return_type,
             doc_comment: self.extract_javadoc(node, content),
@@ -2,7 +2,7 @@
 //!
 //! Provides a `Parser` trait for extracting ontology entities from source code,
 //! with language-specific implementations for Rust (syn), TypeScript/JavaScript,
-//! Python, Go, Java, and C# (tree-sitter).
+//! Python, Go, Java, and C# (tree-sitter), plus GraphQL schema files.
 //!
 //! ## Components
 //!
@@ -18,9 +18,15 @@
 //! - Go (tree-sitter)
 //! - Java (tree-sitter)
 //! - C# (tree-sitter)
+//! - GraphQL SDL (schemas and operations)
+//!
+//! Other languages can be added with external parser processes configured
+//! under `[[parsers.external]]` (see [`ExternalParser`]).
 
 mod csharp;
+mod external;
 mod go;
+mod graphql;
 mod java;
 mod python;
 mod registry;
@@ -31,7 +37,9 @@ mod treesitter;
 mod typescript;
 
 pub use csharp::CSharpParser;
+pub use external::ExternalParser;
 pub use go::GoParser;
+pub use graphql::GraphQLParser;
 pub use java::JavaParser;
 pub use python::PythonParser;
 pub use registry::ParserRegistry;
@@ -1,14 +1,20 @@
 //! Python parser using tree-sitter.
+//!
+//! Methods are qualified by their class (`Class::method`), decorators are
+//! kept as attributes, base classes become `extends` edges and calls are
+//! attributed to the innermost function containing them.
 
+use std::collections::HashMap;
 use tree_sitter::Node;
 
-use super::result::ParseResult;
+use super::result::{ParseResult, ParsedEdge, ParsedNode};
 use super::traits::{Parser, ParserCapability};
 use super::treesitter::TreeSitterParser;
-use crate::knowledge::ontology::edges::{CallType, CallsEdge, ContainsEdge};
+use crate::knowledge::ontology::edges::{CallType, CallsEdge, ContainsEdge, ExtendsEdge};
 use crate::knowledge::ontology::nodes::{
     FieldInfo, FunctionEntity, Parameter, StructEntity, Visibility,
 };
+use crate::knowledge::ontology::QualifiedName;
 
 /// Python parser using tree-sitter.
 pub struct PythonParser {
@@ -26,9 +32,16 @@ impl PythonParser {
         }
     }
 
-    fn extract_function(&self, node: &Node, content: &str, path: &str) -> Option<FunctionEntity> {
+    fn extract_function(
+        &self,
+        node: &Node,
+        content: &str,
+        path: &str,
+        class_name: Option<&str>,
+    ) -> Option<FunctionEntity> {
         let name_node = node.child_by_field_name("name")?;
         let name = TreeSitterParser::node_text(&name_node, content).to_string();
+        let qualified_name = QualifiedName::from_parts(class_name, &name).canonical();
 
         let params = self.extract_parameters(node, content);
         let return_type = node
@@ -38,28 +51,22 @@ impl PythonParser {
         let is_async = node.kind() == "async_function_definition"
             || TreeSitterParser::node_text(node, content).starts_with("async ");
 
-        // Check for decorators
-        let _decorators: Vec<String> = node
-            .children(&mut node.walk())
-            .filter(|c| c.kind() == "decorator")
-            .map(|d| TreeSitterParser::node_text(&d, content).to_string())
-            .collect();
-
         let visibility = self.extract_visibility(&name);
 
         Some(FunctionEntity {
-            id: Some(format!("function:{}:{}", path, name)),
-            name: name.clone(),
-            qualified_name: name,
+            id: Some(format!("function:{}:{}", path, qualified_name)),
+            name,
+            qualified_name,
             file_path: path.to_string(),
             start_line: TreeSitterParser::node_line(node),
             end_line: TreeSitterParser::node_end_line(node),
             signature: self.build_signature(node, content),
-            parent: None,
+            parent: class_name.map(String::from),
             visibility,
             is_async,
             is_unsafe: false,
             generics: Vec::new(),
+            attributes: self.extract_decorators(node, content),
             parameters: params,
             return_type,
             doc_comment: self.extract_docstring(node, content),
@@ -73,18 +80,6 @@ impl PythonParser {
 
         let fields = self.extract_class_fields(node, content);
 
-        // Extract base classes
-        let bases: Vec<String> = node
-            .child_by_field_name("superclasses")
-            .map(|sc| {
-                let mut cursor = sc.walk();
-                sc.children(&mut cursor)
-                    .filter(|c| c.kind() == "identifier" || c.kind() == "attribute")
-                    .map(|c| TreeSitterParser::node_text(&c, content).to_string())
-                    .collect()
-            })
-            .unwrap_or_default();
-
         Some(StructEntity {
             id: Some(format!("struct:{}:{}", path, name)),
             name: name.clone(),
@@ -95,12 +90,48 @@ impl PythonParser {
             visibility: Visibility::Public,
             generics: Vec::new(),
             fields,
-            derives: bases,
-            attributes: Vec::new(),
+            derives: self.extract_bases(node, content),
+            attributes: self.extract_decorators(node, content),
             doc_comment: self.extract_docstring(node, content),
         })
     }
 
+    /// Base classes of a class definition, as written (`Base`, `models.Model`).
+    ///
+    /// Keyword arguments such as `metaclass=ABCMeta` are skipped.
+    fn extract_bases(&self, node: &Node, content: &str) -> Vec<String> {
+        node.child_by_field_name("superclasses")
+            .map(|sc| {
+                let mut cursor = sc.walk();
+                sc.children(&mut cursor)
+                    .filter(|c| c.kind() == "identifier" || c.kind() == "attribute")
+                    .map(|c| TreeSitterParser::node_text(&c, content).to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Decorators of a function or class, without the `@`.
+    ///
+    /// tree-sitter wraps decorated definitions in a `decorated_definition`
+    /// node holding the decorators next to the definition.
+    fn extract_decorators(&self, node: &Node, content: &str) -> Vec<String> {
+        let Some(parent) = node.parent().filter(|p| p.kind() == "decorated_definition") else {
+            return Vec::new();
+        };
+        let mut cursor = parent.walk();
+        parent
+            .children(&mut cursor)
+            .filter(|c| c.kind() == "decorator")
+            .map(|d| {
+                TreeSitterParser::node_text(&d, content)
+                    .trim_start_matches('@')
+                    .trim()
+                    .to_string()
+            })
+            .collect()
+    }
+
     fn extract_parameters(&self, node: &Node, content: &str) -> Vec<Parameter> {
         let mut params = Vec::new();
 
@@ -265,6 +296,8 @@ impl PythonParser {
     }
 
     /// Extract function calls from a node.
+    ///
+    /// Nested functions and classes are skipped; their calls belong to them.
     fn extract_calls(&self, node: &Node, content: &str, caller_id: &str, result: &mut ParseResult) {
         self.extract_calls_recursive(node, content, caller_id, result);
     }
@@ -319,14 +352,26 @@ impl PythonParser {
         // Recurse into children
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
-            self.extract_calls_recursive(&child, content, caller_id, result);
+            if !matches!(
+                child.kind(),
+                "function_definition" | "class_definition" | "decorated_definition"
+            ) {
+                self.extract_calls_recursive(&child, content, caller_id, result);
+            }
         }
     }
 
-    fn process_node(&self, node: Node, content: &str, path: &str, result: &mut ParseResult) {
+    fn process_node(
+        &self,
+        node: Node,
+        content: &str,
+        path: &str,
+        result: &mut ParseResult,
+        class_context: Option<&str>,
+    ) {
         match node.kind() {
             "function_definition" | "async_function_definition" => {
-                if let Some(func) = self.extract_function(&node, content, path) {
+                if let Some(func) = self.extract_function(&node, content, path, class_context) {
                     let id = func.id.clone();
                     result.add_function(func);
                     if let Some(ref func_id) = id {
@@ -338,24 +383,71 @@ impl PythonParser {
                         }
                     }
                 }
+                // Functions nested in a method are not methods themselves
+                self.process_children(node, content, path, result, None);
+                return;
             }
             "class_definition" => {
                 if let Some(class) = self.extract_class(&node, content, path) {
+                    let class_name = class.name.clone();
                     let id = class.id.clone();
+                    let bases = class.derives.clone();
                     result.add_struct(class);
                     if let Some(ref class_id) = id {
                         let file_id = format!("file:{}", path);
                         result.add_contains(ContainsEdge::new(&file_id, class_id));
+                        for base in &bases {
+                            let base_name = base.rsplit('.').next().unwrap_or(base);
+                            let base_id = format!("struct:?:{}", base_name);
+                            result.add_extends(ExtendsEdge::new(class_id, base_id).inheritance());
+                        }
                     }
+
+                    // Process methods within class context
+                    self.process_children(node, content, path, result, Some(&class_name));
+                    return;
                 }
             }
             _ => {}
         }
 
-        // Recursively process children
+        self.process_children(node, content, path, result, class_context);
+    }
+
+    fn process_children(
+        &self,
+        node: Node,
+        content: &str,
+        path: &str,
+        result: &mut ParseResult,
+        class_context: Option<&str>,
+    ) {
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
-            self.process_node(child, content, path, result);
+            self.process_node(child, content, path, result, class_context);
+        }
+    }
+
+    /// Point `extends` edges at base classes defined in the same file.
+    fn resolve_local_bases(result: &mut ParseResult) {
+        let classes: HashMap<String, String> = result
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                ParsedNode::Struct(s) => Some((s.name.clone(), s.id.clone()?)),
+                _ => None,
+            })
+            .collect();
+        for edge in &mut result.edges {
+            if let ParsedEdge::Extends(extends) = edge {
+                let local = extends
+                    .to
+                    .strip_prefix("struct:?:")
+                    .and_then(|name| classes.get(name));
+                if let Some(id) = local {
+                    extends.to = id.clone();
+                }
+            }
         }
     }
 }
@@ -371,7 +463,8 @@ impl Parser for PythonParser {
         let tree = self.base.parse_tree(content)?;
         let mut result = ParseResult::new(path);
 
-        self.process_node(tree.root_node(), content, path, &mut result);
+        self.process_node(tree.root_node(), content, path, &mut result, None);
+        Self::resolve_local_bases(&mut result);
 
         Ok(result)
     }
@@ -388,3 +481,84 @@ impl Parser for PythonParser {
         ParserCapability::Structural
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_methods_calls_and_bases() {
+        let code = r#"
+class Base:
+    def save(self):
+        pass
+
+@dataclass(frozen=True)
+class User(Base, metaclass=Meta):
+    @staticmethod
+    def create(name):
+        def validate():
+            check(name)
+        user = User(name)
+        user.save()
+        return user
+"#;
+        let result = PythonParser::new().parse_file("app.py", code).unwrap();
+
+        let functions: Vec<&FunctionEntity> = result
+            .nodes
+            .iter()
+            .filter_map(|n| match n {
+                ParsedNode::Function(f) => Some(f),
+                _ => None,
+            })
+            .collect();
+        let create = functions.iter().find(|f| f.name == "create").unwrap();
+        assert_eq!(create.id.as_deref(), Some("function:app.py:User::create"));
+        assert_eq!(create.parent.as_deref(), Some("User"));
+        assert_eq!(create.attributes, vec!["staticmethod"]);
+        let validate = functions.iter().find(|f| f.name == "validate").unwrap();
+        assert_eq!(validate.parent, None);
+
+        let user = result
+            .nodes
+            .iter()
+            .find_map(|n| match n {
+                ParsedNode::Struct(s) if s.name == "User" => Some(s),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(user.derives, vec!["Base"]);
+        assert_eq!(user.attributes, vec!["dataclass(frozen=True)"]);
+
+        let mut calls: Vec<(&str, &str)> = result
+            .edges
+            .iter()
+            .filter_map(|e| match e {
+                ParsedEdge::Calls(c) => Some((c.from.as_str(), c.to.as_str())),
+                _ => None,
+            })
+            .collect();
+        calls.sort();
+        assert_eq!(
+            calls,
+            vec![
+                ("function:app.py:User::create", "function:?:User"),
+                ("function:app.py:User::create", "function:?:save"),
+                ("function:app.py:validate", "function:?:check"),
+            ]
+        );
+
+        let extends: Vec<&ExtendsEdge> = result
+            .edges
+            .iter()
+            .filter_map(|e| match e {
+                ParsedEdge::Extends(e) => Some(e),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(extends.len(), 1);
+        assert_eq!(extends[0].from, "struct:app.py:User");
+        assert_eq!(extends[0].to, "struct:app.py:Base");
+    }
+}
@@ -1,10 +1,16 @@
 //! Parser registry for managing language-specific parsers.
+//!
+//! `knowledge.disabled_languages` turns parsers off by language name and
+//! `knowledge.extension_overrides` hands more extensions to a parser, e.g.
+//! `{ "pyi" = "python" }`. Language names are matched ignoring case and
+//! punctuation, with `#` read as "sharp", so `csharp` names the C# parser.
 
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use super::csharp::CSharpParser;
 use super::go::GoParser;
+use super::graphql::GraphQLParser;
 use super::java::JavaParser;
 use super::python::PythonParser;
 use super::rust::RustParser;
@@ -15,6 +21,7 @@ use super::typescript::TypeScriptParser;
 ///
 /// Maps file extensions to their respective parsers.
 /// Automatically registers all built-in parsers on creation.
+#[derive(Clone)]
 pub struct ParserRegistry {
     /// Extension to parser mapping.
     parsers: HashMap<String, Arc<dyn Parser>>,
@@ -35,6 +42,7 @@ impl ParserRegistry {
         registry.register(Arc::new(GoParser::new()));
         registry.register(Arc::new(JavaParser::new()));
         registry.register(Arc::new(CSharpParser::new()));
+        registry.register(Arc::new(GraphQLParser::new()));
 
         registry
     }
@@ -46,6 +54,39 @@ impl ParserRegistry {
         }
     }
 
+    /// Stop parsing `language`, returning the extensions it handled.
+    pub fn disable_language(&mut self, language: &str) -> Vec<String> {
+        let mut removed: Vec<String> = self
+            .parsers
+            .iter()
+            .filter(|(_, parser)| language_matches(parser.language_name(), language))
+            .map(|(ext, _)| ext.clone())
+            .collect();
+        for ext in &removed {
+            self.parsers.remove(ext);
+        }
+        removed.sort();
+        removed
+    }
+
+    /// Parse files with `extension` (without the dot) with the parser for
+    /// `language`. Returns false if no registered parser handles `language`.
+    pub fn map_extension(&mut self, extension: &str, language: &str) -> bool {
+        let parser = self
+            .parsers
+            .values()
+            .find(|parser| language_matches(parser.language_name(), language))
+            .cloned();
+        match parser {
+            Some(parser) => {
+                let extension = extension.trim_start_matches('.').to_lowercase();
+                self.parsers.insert(extension, parser);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Get a parser for the given file extension.
     pub fn parser_for_extension(&self, extension: &str) -> Option<Arc<dyn Parser>> {
         self.parsers.get(&extension.to_lowercase()).cloned()
@@ -86,6 +127,19 @@ impl ParserRegistry {
     }
 }
 
+/// Whether a parser's language name is the configured `language`, e.g.
+/// `C#` and `csharp`.
+fn language_matches(name: &str, language: &str) -> bool {
+    let normalize = |s: &str| -> String {
+        s.replace('#', "sharp")
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    normalize(name) == normalize(language)
+}
+
 impl Default for ParserRegistry {
     fn default() -> Self {
         Self::new()
@@ -130,6 +184,10 @@ mod tests {
 
         // C#
         assert!(registry.can_parse("cs"));
+
+        // GraphQL
+        assert!(registry.can_parse("graphql"));
+        assert!(registry.can_parse("gql"));
     }
 
     #[test]
@@ -144,6 +202,22 @@ mod tests {
         assert!(registry.parser_for_path("unknown.xyz").is_none());
     }
 
+    #[test]
+    fn test_disable_language_and_map_extension() {
+        let mut registry = ParserRegistry::new();
+        assert_eq!(registry.disable_language("csharp"), vec!["cs".to_string()]);
+        assert!(!registry.can_parse("cs"));
+        assert!(!registry.map_extension("cake", "csharp"));
+        assert!(!registry.map_extension("zig", "zig"));
+
+        assert!(registry.map_extension(".PYW", "python"));
+        assert_eq!(
+            registry.parser_for_path("gui.pyw").unwrap().language_name(),
+            "Python"
+        );
+        assert!(registry.can_parse("rs"));
+    }
+
     #[test]
     fn test_case_insensitive() {
         let registry = ParserRegistry::new();
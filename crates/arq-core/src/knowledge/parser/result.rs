@@ -1,17 +1,24 @@
 //! Parse result types containing extracted ontology entities.
 
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
 use crate::knowledge::ontology::edges::{
-    CallsEdge, ContainsEdge, ExtendsEdge, HasFieldEdge, ImplementsEdge, ImportsEdge,
-    ReturnsTypeEdge, UsesTypeEdge,
+    CallsEdge, ConsumesEdge, ContainsEdge, ExtendsEdge, HasFieldEdge, ImplementsEdge, ImportsEdge,
+    ProducesEdge, ReturnsTypeEdge, UsesTypeEdge,
 };
 use crate::knowledge::ontology::nodes::{
-    ConstantEntity, EnumEntity, FunctionEntity, ImplEntity, StructEntity, TraitEntity,
+    ConstantEntity, EnumEntity, FunctionEntity, ImplEntity, OperationEntity, SchemaEntity,
+    StructEntity, TraitEntity,
 };
+use crate::knowledge::ontology::QualifiedName;
 
 /// Result of parsing a source file.
 ///
 /// Contains all extracted nodes (entities) and edges (relations).
-#[derive(Debug, Default)]
+/// External parsers emit this as JSON, with nodes and edges tagged by `kind`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ParseResult {
     /// File path that was parsed.
     pub file_path: String,
@@ -65,6 +72,16 @@ impl ParseResult {
         self.nodes.push(ParsedNode::Constant(c));
     }
 
+    /// Add an API schema entity.
+    pub fn add_schema(&mut self, s: SchemaEntity) {
+        self.nodes.push(ParsedNode::Schema(s));
+    }
+
+    /// Add an API operation entity.
+    pub fn add_operation(&mut self, o: OperationEntity) {
+        self.nodes.push(ParsedNode::Operation(o));
+    }
+
     /// Add a calls edge.
     pub fn add_call(&mut self, edge: CallsEdge) {
         self.edges.push(ParsedEdge::Calls(edge));
@@ -105,11 +122,61 @@ impl ParseResult {
         self.edges.push(ParsedEdge::Imports(edge));
     }
 
+    /// Add a consumes edge.
+    pub fn add_consumes(&mut self, edge: ConsumesEdge) {
+        self.edges.push(ParsedEdge::Consumes(edge));
+    }
+
+    /// Add a produces edge.
+    pub fn add_produces(&mut self, edge: ProducesEdge) {
+        self.edges.push(ParsedEdge::Produces(edge));
+    }
+
     /// Add a parse warning.
     pub fn warn(&mut self, message: impl Into<String>) {
         self.warnings.push(message.into());
     }
 
+    /// Prefix every qualified name, and the IDs built from it, with `prefix::`.
+    ///
+    /// Used to namespace entities by crate in Cargo workspaces. Edges that
+    /// reference a renamed ID are updated to match.
+    pub fn prefix_qualified_names(&mut self, prefix: &str) {
+        let mut renamed: HashMap<String, String> = HashMap::new();
+
+        for node in &mut self.nodes {
+            let (id, qualified_name) = match node {
+                ParsedNode::Function(f) => (&mut f.id, &mut f.qualified_name),
+                ParsedNode::Struct(s) => (&mut s.id, &mut s.qualified_name),
+                ParsedNode::Trait(t) => (&mut t.id, &mut t.qualified_name),
+                ParsedNode::Enum(e) => (&mut e.id, &mut e.qualified_name),
+                ParsedNode::Constant(c) => (&mut c.id, &mut c.qualified_name),
+                ParsedNode::Impl(_) | ParsedNode::Schema(_) | ParsedNode::Operation(_) => continue,
+            };
+
+            let prefixed = QualifiedName::parse(qualified_name)
+                .with_prefix(prefix)
+                .canonical();
+            if let Some(old_id) = id.as_mut() {
+                if let Some(base) = old_id.strip_suffix(qualified_name.as_str()) {
+                    let new_id = format!("{}{}", base, prefixed);
+                    renamed.insert(std::mem::replace(old_id, new_id.clone()), new_id);
+                }
+            }
+            *qualified_name = prefixed;
+        }
+
+        for edge in &mut self.edges {
+            let (from, to) = edge.endpoints_mut();
+            if let Some(new_id) = renamed.get(from.as_str()) {
+                *from = new_id.clone();
+            }
+            if let Some(new_id) = renamed.get(to.as_str()) {
+                *to = new_id.clone();
+            }
+        }
+    }
+
     /// Get statistics about the parse result.
     pub fn stats(&self) -> ParseStats {
         let mut stats = ParseStats::default();
@@ -122,6 +189,8 @@ impl ParseResult {
                 ParsedNode::Impl(_) => stats.impls += 1,
                 ParsedNode::Enum(_) => stats.enums += 1,
                 ParsedNode::Constant(_) => stats.constants += 1,
+                ParsedNode::Schema(_) => stats.schemas += 1,
+                ParsedNode::Operation(_) => stats.operations += 1,
             }
         }
 
@@ -135,6 +204,8 @@ impl ParseResult {
                 ParsedEdge::ReturnsType(_) => stats.returns_type += 1,
                 ParsedEdge::HasField(_) => stats.has_field += 1,
                 ParsedEdge::Imports(_) => stats.imports += 1,
+                ParsedEdge::Consumes(_) => stats.consumes += 1,
+                ParsedEdge::Produces(_) => stats.produces += 1,
             }
         }
 
@@ -144,7 +215,8 @@ impl ParseResult {
 }
 
 /// A parsed node (entity) from the source code.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum ParsedNode {
     Function(FunctionEntity),
     Struct(StructEntity),
@@ -152,6 +224,8 @@ pub enum ParsedNode {
     Impl(ImplEntity),
     Enum(EnumEntity),
     Constant(ConstantEntity),
+    Schema(SchemaEntity),
+    Operation(OperationEntity),
 }
 
 impl ParsedNode {
@@ -164,6 +238,8 @@ impl ParsedNode {
             Self::Impl(i) => i.id.as_deref(),
             Self::Enum(e) => e.id.as_deref(),
             Self::Constant(c) => c.id.as_deref(),
+            Self::Schema(s) => s.id.as_deref(),
+            Self::Operation(o) => o.id.as_deref(),
         }
     }
 
@@ -176,6 +252,8 @@ impl ParsedNode {
             Self::Impl(i) => &i.target_type,
             Self::Enum(e) => &e.name,
             Self::Constant(c) => &c.name,
+            Self::Schema(s) => &s.name,
+            Self::Operation(o) => &o.name,
         }
     }
 
@@ -188,6 +266,8 @@ impl ParsedNode {
             Self::Impl(i) => &i.file_path,
             Self::Enum(e) => &e.file_path,
             Self::Constant(c) => &c.file_path,
+            Self::Schema(s) => &s.file_path,
+            Self::Operation(o) => &o.file_path,
         }
     }
 
@@ -200,6 +280,8 @@ impl ParsedNode {
             Self::Impl(i) => i.start_line,
             Self::Enum(e) => e.start_line,
             Self::Constant(c) => c.line,
+            Self::Schema(s) => s.start_line,
+            Self::Operation(o) => o.line,
         }
     }
 
@@ -212,6 +294,8 @@ impl ParsedNode {
             Self::Impl(i) => i.end_line,
             Self::Enum(e) => e.end_line,
             Self::Constant(c) => c.line,
+            Self::Schema(s) => s.end_line,
+            Self::Operation(o) => o.line,
         }
     }
 
@@ -224,12 +308,15 @@ impl ParsedNode {
             Self::Impl(_) => "impl",
             Self::Enum(_) => "enum",
             Self::Constant(_) => "constant",
+            Self::Schema(_) => "schema",
+            Self::Operation(_) => "operation",
         }
     }
 }
 
 /// A parsed edge (relation) from the source code.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum ParsedEdge {
     Calls(CallsEdge),
     Contains(ContainsEdge),
@@ -239,6 +326,8 @@ pub enum ParsedEdge {
     ReturnsType(ReturnsTypeEdge),
     HasField(HasFieldEdge),
     Imports(ImportsEdge),
+    Consumes(ConsumesEdge),
+    Produces(ProducesEdge),
 }
 
 impl ParsedEdge {
@@ -253,6 +342,8 @@ impl ParsedEdge {
             Self::ReturnsType(e) => &e.from,
             Self::HasField(e) => &e.from,
             Self::Imports(e) => &e.from,
+            Self::Consumes(e) => &e.from,
+            Self::Produces(e) => &e.from,
         }
     }
 
@@ -267,6 +358,24 @@ impl ParsedEdge {
             Self::ReturnsType(e) => &e.to,
             Self::HasField(e) => &e.to,
             Self::Imports(e) => &e.to,
+            Self::Consumes(e) => &e.to,
+            Self::Produces(e) => &e.to,
+        }
+    }
+
+    /// Mutable access to the source and target node IDs.
+    fn endpoints_mut(&mut self) -> (&mut String, &mut String) {
+        match self {
+            Self::Calls(e) => (&mut e.from, &mut e.to),
+            Self::Contains(e) => (&mut e.from, &mut e.to),
+            Self::Implements(e) => (&mut e.from, &mut e.to),
+            Self::Extends(e) => (&mut e.from, &mut e.to),
+            Self::UsesType(e) => (&mut e.from, &mut e.to),
+            Self::ReturnsType(e) => (&mut e.from, &mut e.to),
+            Self::HasField(e) => (&mut e.from, &mut e.to),
+            Self::Imports(e) => (&mut e.from, &mut e.to),
+            Self::Consumes(e) => (&mut e.from, &mut e.to),
+            Self::Produces(e) => (&mut e.from, &mut e.to),
         }
     }
 
@@ -281,6 +390,8 @@ impl ParsedEdge {
             Self::ReturnsType(_) => "returns_type",
             Self::HasField(_) => "has_field",
             Self::Imports(_) => "imports",
+            Self::Consumes(_) => "consumes",
+            Self::Produces(_) => "produces",
         }
     }
 }
@@ -294,6 +405,8 @@ pub struct ParseStats {
     pub impls: usize,
     pub enums: usize,
     pub constants: usize,
+    pub schemas: usize,
+    pub operations: usize,
     pub calls: usize,
     pub contains: usize,
     pub implements: usize,
@@ -302,6 +415,8 @@ pub struct ParseStats {
     pub returns_type: usize,
     pub has_field: usize,
     pub imports: usize,
+    pub consumes: usize,
+    pub produces: usize,
     pub warnings: usize,
 }
 
@@ -314,11 +429,15 @@ impl std::fmt::Display for ParseStats {
         writeln!(f, "  Impls:     {}", self.impls)?;
         writeln!(f, "  Enums:     {}", self.enums)?;
         writeln!(f, "  Constants: {}", self.constants)?;
+        writeln!(f, "  Schemas:   {}", self.schemas)?;
+        writeln!(f, "  Operations:{}", self.operations)?;
         writeln!(f, "Edges:")?;
         writeln!(f, "  Calls:     {}", self.calls)?;
         writeln!(f, "  Contains:  {}", self.contains)?;
         writeln!(f, "  Implements:{}", self.implements)?;
         writeln!(f, "  UsesType:  {}", self.uses_type)?;
+        writeln!(f, "  Consumes:  {}", self.consumes)?;
+        writeln!(f, "  Produces:  {}", self.produces)?;
         if self.warnings > 0 {
             writeln!(f, "Warnings: {}", self.warnings)?;
         }
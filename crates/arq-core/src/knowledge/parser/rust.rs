@@ -26,6 +26,7 @@ use crate::knowledge::ontology::nodes::{
     ComplexityMetrics, ConstantEntity, EnumEntity, EnumVariant, FieldInfo, FunctionEntity,
     ImplEntity, Parameter, StructEntity, TraitEntity, Visibility,
 };
+use crate::knowledge::ontology::QualifiedName;
 
 /// Rust parser using syn for AST-based extraction.
 pub struct RustParser {
@@ -397,6 +398,7 @@ impl<'a> RustVisitor<'a> {
             is_async: item.sig.asyncness.is_some(),
             is_unsafe: item.sig.unsafety.is_some(),
             generics: Self::extract_generics(&item.sig.generics),
+            attributes: Vec::new(),
             parameters: Self::extract_parameters(&item.sig),
             return_type: Self::extract_return_type(&item.sig.output),
             doc_comment: Self::extract_doc_comment(&item.attrs),
@@ -694,11 +696,7 @@ impl<'ast> Visit<'ast> for RustVisitor<'_> {
     fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
         let name = item.sig.ident.to_string();
         let parent = self.current_impl.clone();
-        let qualified_name = if let Some(ref p) = parent {
-            format!("{}::{}", p, name)
-        } else {
-            name.clone()
-        };
+        let qualified_name = QualifiedName::from_parts(parent.as_deref(), &name).canonical();
 
         let id = self.entity_id("function", &qualified_name);
         let start_line = self.line_of(item.sig.ident.span());
@@ -717,6 +715,7 @@ impl<'ast> Visit<'ast> for RustVisitor<'_> {
             is_async: item.sig.asyncness.is_some(),
             is_unsafe: item.sig.unsafety.is_some(),
             generics: Self::extract_generics(&item.sig.generics),
+            attributes: Vec::new(),
             parameters: Self::extract_parameters(&item.sig),
             return_type: Self::extract_return_type(&item.sig.output),
             doc_comment: Self::extract_doc_comment(&item.attrs),
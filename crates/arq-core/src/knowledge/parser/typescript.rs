@@ -73,6 +73,7 @@ impl TypeScriptParser {
             is_async,
             is_unsafe: false,
             generics: self.extract_generics(node, content),
+            attributes: Vec::new(),
             parameters: params,
             return_type,
             doc_comment: extract_doc_comment(node, content),
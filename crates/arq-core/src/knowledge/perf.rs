@@ -0,0 +1,225 @@
+//! Vector search warmup and performance statistics.
+//!
+//! The first search after opening the graph is slow while the database pages
+//! the HNSW index in. [`KnowledgeGraph::warm_up_index`] runs a probe query
+//! up front; with `[knowledge] warmup = true` long-running processes (the
+//! TUI and `arq serve`) do it when they open the graph.
+//!
+//! Every semantic search records its latency, so `arq kg status --perf` can
+//! report percentiles over the last searches next to the index size and the
+//! memory of the process. Latencies are buffered in memory and written to
+//! the metadata table in batches, keeping the write off each search.
+//!
+//! [`KnowledgeGraph::warm_up_index`]: super::KnowledgeGraph::warm_up_index
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Metadata key of the recent search latencies.
+pub const SEARCH_LATENCY_KEY: &str = "perf:search_latency";
+
+/// Metadata key of the duration of the last warmup.
+pub const WARMUP_KEY: &str = "perf:warmup";
+
+/// Search latencies kept for percentiles; older ones are dropped.
+pub const MAX_LATENCY_SAMPLES: usize = 200;
+
+/// Buffered latencies that trigger a write to the metadata table.
+pub const LATENCY_FLUSH_SAMPLES: usize = 20;
+
+/// Age of the oldest buffered latency that triggers a write.
+pub const LATENCY_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Latencies of the most recent searches, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySamples {
+    pub samples_ms: Vec<f64>,
+}
+
+impl LatencySamples {
+    /// Add a search latency, dropping the oldest beyond [`MAX_LATENCY_SAMPLES`].
+    pub fn record(&mut self, latency: Duration) {
+        self.samples_ms.push(latency.as_secs_f64() * 1000.0);
+        if self.samples_ms.len() > MAX_LATENCY_SAMPLES {
+            let excess = self.samples_ms.len() - MAX_LATENCY_SAMPLES;
+            self.samples_ms.drain(..excess);
+        }
+    }
+
+    /// Percentiles of the samples; `None` before the first search.
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        if self.samples_ms.is_empty() {
+            return None;
+        }
+        let mut sorted = self.samples_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+        let at = |p: f64| {
+            // Nearest rank
+            let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+            millis(sorted[rank.clamp(1, sorted.len()) - 1])
+        };
+        Some(LatencyPercentiles {
+            samples: sorted.len(),
+            p50: at(50.0),
+            p90: at(90.0),
+            p99: at(99.0),
+            max: millis(sorted[sorted.len() - 1]),
+        })
+    }
+}
+
+/// Search latencies not yet written to the metadata table.
+#[derive(Debug, Default)]
+pub struct PendingLatencies {
+    samples: Vec<Duration>,
+    /// When the last batch was written; `None` before the first
+    last_flush: Option<Instant>,
+}
+
+impl PendingLatencies {
+    /// Buffer a search latency, returning whether the buffer should now be
+    /// written. The first one of a process is written right away, so that
+    /// one-shot commands like `arq search` still record theirs.
+    pub fn push(&mut self, latency: Duration) -> bool {
+        self.samples.push(latency);
+        match self.last_flush {
+            None => true,
+            Some(at) => {
+                self.samples.len() >= LATENCY_FLUSH_SAMPLES
+                    || at.elapsed() >= LATENCY_FLUSH_INTERVAL
+            }
+        }
+    }
+
+    /// Latencies buffered since the last write.
+    pub fn samples(&self) -> &[Duration] {
+        &self.samples
+    }
+
+    /// Empty the buffer for writing.
+    pub fn take(&mut self) -> Vec<Duration> {
+        self.last_flush = Some(Instant::now());
+        std::mem::take(&mut self.samples)
+    }
+}
+
+/// Search latency percentiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    /// Searches the percentiles are over.
+    pub samples: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Size, memory and latency figures of the vector index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexPerf {
+    /// Bytes used by the database on disk.
+    pub database_bytes: u64,
+    /// Indexed chunks, one vector each.
+    pub chunks: usize,
+    /// Embedding dimension.
+    pub dimension: usize,
+    /// Resident memory of this process after the probe search, where the
+    /// platform reports it.
+    pub resident_bytes: Option<u64>,
+    /// Duration of a probe search just now.
+    pub probe: Duration,
+    /// Duration of the last warmup, if one ran.
+    pub last_warmup: Option<Duration>,
+    /// Latency of recent searches.
+    pub search_latency: Option<LatencyPercentiles>,
+}
+
+impl IndexPerf {
+    /// Estimated size of the raw vectors (`f32` per dimension), before
+    /// index overhead.
+    pub fn vector_bytes(&self) -> u64 {
+        (self.chunks * self.dimension * std::mem::size_of::<f32>()) as u64
+    }
+}
+
+/// A unit vector for probe searches, which only need to touch the index.
+pub fn probe_vector(dimension: usize) -> Vec<f32> {
+    let mut vector = vec![0.0; dimension];
+    if let Some(first) = vector.first_mut() {
+        *first = 1.0;
+    }
+    vector
+}
+
+/// Total size of the files under `path`, or of `path` itself.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Resident memory of this process, on Linux.
+pub fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn millis(ms: f64) -> Duration {
+    Duration::from_micros((ms * 1000.0).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut samples = LatencySamples::default();
+        assert!(samples.percentiles().is_none());
+
+        for ms in 1..=100 {
+            samples.record(Duration::from_millis(ms));
+        }
+        let percentiles = samples.percentiles().unwrap();
+        assert_eq!(percentiles.samples, 100);
+        assert_eq!(percentiles.p50.as_millis(), 50);
+        assert_eq!(percentiles.p90.as_millis(), 90);
+        assert_eq!(percentiles.p99.as_millis(), 99);
+        assert_eq!(percentiles.max.as_millis(), 100);
+
+        for _ in 0..MAX_LATENCY_SAMPLES {
+            samples.record(Duration::from_millis(5));
+        }
+        assert_eq!(samples.samples_ms.len(), MAX_LATENCY_SAMPLES);
+        assert_eq!(samples.percentiles().unwrap().max.as_millis(), 5);
+    }
+
+    #[test]
+    fn test_pending_latencies_flush_in_batches() {
+        let mut pending = PendingLatencies::default();
+        assert!(pending.push(Duration::from_millis(1)));
+        assert_eq!(pending.take().len(), 1);
+
+        for _ in 1..LATENCY_FLUSH_SAMPLES {
+            assert!(!pending.push(Duration::from_millis(1)));
+        }
+        assert_eq!(pending.samples().len(), LATENCY_FLUSH_SAMPLES - 1);
+        assert!(pending.push(Duration::from_millis(1)));
+        assert_eq!(pending.take().len(), LATENCY_FLUSH_SAMPLES);
+        assert!(pending.samples().is_empty());
+    }
+}
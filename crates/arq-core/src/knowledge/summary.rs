@@ -0,0 +1,131 @@
+//! Hierarchical summary index.
+//!
+//! `arq kg summarize` asks the LLM for a short summary of every indexed
+//! file, then folds the summaries of each directory's files and
+//! subdirectories into a directory summary, deepest directories first. On
+//! large repositories research starts from the directory summaries and only
+//! pulls in code where they point, instead of filling the prompt with
+//! search hits from everywhere.
+//!
+//! Every summary records a hash of its input (the file's content hash, or
+//! the hashes of a directory's children), so re-running only summarizes
+//! what changed.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Path of the project root in the summary index.
+pub const ROOT_DIR: &str = ".";
+
+/// What a summary describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryKind {
+    File,
+    Directory,
+}
+
+/// An LLM-written summary of a file or directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// File or directory, relative to the project root; [`ROOT_DIR`] for
+    /// the root.
+    pub path: String,
+    pub kind: SummaryKind,
+    pub summary: String,
+    /// Hash of what was summarized, to skip unchanged entries.
+    pub hash: String,
+}
+
+/// Directory containing `path`, or [`ROOT_DIR`] for top-level entries.
+pub fn parent_dir(path: &str) -> &str {
+    match path.rsplit_once('/') {
+        Some((parent, _)) if !parent.is_empty() => parent,
+        _ => ROOT_DIR,
+    }
+}
+
+/// Nesting depth of a directory; the root is 0.
+pub fn depth(dir: &str) -> usize {
+    if dir == ROOT_DIR {
+        0
+    } else {
+        dir.split('/').count()
+    }
+}
+
+/// Every directory holding one of `files`, including their ancestors and
+/// the root, deepest first.
+///
+/// Summarizing in this order means a directory's subdirectories are always
+/// done before it.
+pub fn directories_bottom_up<'a>(files: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut dirs = BTreeSet::new();
+    for file in files {
+        let mut dir = parent_dir(file);
+        while dirs.insert(dir.to_string()) && dir != ROOT_DIR {
+            dir = parent_dir(dir);
+        }
+    }
+    dirs.insert(ROOT_DIR.to_string());
+
+    let mut dirs: Vec<String> = dirs.into_iter().collect();
+    dirs.sort_by(|a, b| depth(b).cmp(&depth(a)).then_with(|| a.cmp(b)));
+    dirs
+}
+
+/// Direct children of `dir` among `summaries`, sorted by path.
+pub fn children<'a>(dir: &str, summaries: &'a BTreeMap<String, Summary>) -> Vec<&'a Summary> {
+    summaries
+        .values()
+        .filter(|s| s.path != ROOT_DIR && s.path != dir && parent_dir(&s.path) == dir)
+        .collect()
+}
+
+/// Hash of a directory's children, which changes when any of them does.
+pub fn directory_hash(children: &[&Summary]) -> String {
+    let mut hasher = Sha256::new();
+    for child in children {
+        hasher.update(child.path.as_bytes());
+        hasher.update([0]);
+        hasher.update(child.hash.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, hash: &str) -> Summary {
+        Summary {
+            path: path.to_string(),
+            kind: SummaryKind::File,
+            summary: String::new(),
+            hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_directories_bottom_up() {
+        let dirs = directories_bottom_up(["build.rs", "src/lib.rs", "src/storage/file.rs"]);
+        assert_eq!(dirs, vec!["src/storage", "src", "."]);
+        assert_eq!(parent_dir("build.rs"), ROOT_DIR);
+        assert_eq!(parent_dir("src/storage"), "src");
+    }
+
+    #[test]
+    fn test_directory_hash_follows_children() {
+        let mut summaries = BTreeMap::new();
+        for s in [file("src/lib.rs", "a"), file("src/storage/file.rs", "b")] {
+            summaries.insert(s.path.clone(), s);
+        }
+        let before = directory_hash(&children("src", &summaries));
+        assert_eq!(children("src", &summaries).len(), 1);
+
+        summaries.insert("src/lib.rs".to_string(), file("src/lib.rs", "c"));
+        assert_ne!(directory_hash(&children("src", &summaries)), before);
+    }
+}
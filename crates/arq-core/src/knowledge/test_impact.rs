@@ -0,0 +1,403 @@
+//! Impact-driven test selection.
+//!
+//! `arq test-impact` takes the files a change touches, finds the functions
+//! defined in them and walks the calls graph backwards until it reaches
+//! test functions. The result is the tests most likely to catch a
+//! regression, printed as a list or as arguments for `cargo test` and
+//! `pytest -k`, so CI on large repositories can run those first or alone.
+//!
+//! Calls are matched by function name, like `arq graph impact`, so the
+//! selection errs on the side of running a test too many.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+use super::db::CallInfo;
+use super::error::KnowledgeError;
+use super::ontology::nodes::FunctionEntity;
+
+/// Call hops followed back from changed code by default.
+pub const DEFAULT_MAX_DEPTH: usize = 6;
+
+/// Path fragments marking test files across common layouts.
+const TEST_PATH_MARKERS: &[&str] = &[
+    "/tests/",
+    "/test/",
+    "/__tests__/",
+    "_test.",
+    ".test.",
+    ".spec.",
+    "/spec/",
+];
+
+/// Attributes and annotations marking test functions, without `#[`/`@`.
+const TEST_ATTRIBUTES: &[&str] = &["test", "rstest", "test_case", "Test", "ParameterizedTest"];
+
+/// Whether `path` belongs to test code by its location or file name.
+pub fn is_test_path(path: &str) -> bool {
+    let path = format!("/{}", path.replace('\\', "/"));
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+
+    TEST_PATH_MARKERS.iter().any(|m| path.contains(m)) || file_name.starts_with("test_")
+}
+
+/// Whether `function` is a test: marked with a test attribute, or named
+/// like one (`test_*`, Go's `Test*`) in a test file.
+pub fn is_test_function(function: &FunctionEntity) -> bool {
+    let marked = function.attributes.iter().any(|attribute| {
+        let attribute = attribute
+            .trim_start_matches("#[")
+            .trim_start_matches('@')
+            .trim_end_matches(']');
+        let name = attribute.split('(').next().unwrap_or_default();
+        let name = name.rsplit("::").next().unwrap_or_default();
+        TEST_ATTRIBUTES.contains(&name)
+    });
+    marked
+        || (function.name.starts_with("test") || function.name.starts_with("Test"))
+            && is_test_path(&function.file_path)
+}
+
+/// A test likely affected by a change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AffectedTest {
+    /// Test function name.
+    pub name: String,
+    pub qualified_name: String,
+    pub file_path: String,
+    /// Call hops from the changed code; 0 for tests in a changed file.
+    pub distance: usize,
+    /// Changed function the test reaches, if it was found through calls.
+    pub reaches: Option<String>,
+}
+
+/// Tests selected for a set of changed files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestImpact {
+    /// Changed files the selection started from.
+    pub changed_files: Vec<String>,
+    /// Functions defined in the changed files.
+    pub changed_functions: usize,
+    /// Affected tests, closest first.
+    pub tests: Vec<AffectedTest>,
+    /// Changed files that are test files, even without parsed test
+    /// functions (e.g. `describe`/`it` suites).
+    pub changed_test_files: Vec<String>,
+}
+
+impl TestImpact {
+    /// Files holding the affected tests, sorted.
+    pub fn test_files(&self) -> Vec<&str> {
+        let files: BTreeSet<&str> = self
+            .tests
+            .iter()
+            .map(|t| t.file_path.as_str())
+            .chain(self.changed_test_files.iter().map(String::as_str))
+            .collect();
+        files.into_iter().collect()
+    }
+
+    /// Distinct test names, sorted.
+    pub fn test_names(&self) -> Vec<&str> {
+        let names: BTreeSet<&str> = self.tests.iter().map(|t| t.name.as_str()).collect();
+        names.into_iter().collect()
+    }
+
+    /// Test name filters for `cargo test -- <filters>`.
+    pub fn cargo_filters(&self) -> String {
+        self.test_names().join(" ")
+    }
+
+    /// Expression for `pytest -k`.
+    pub fn pytest_expression(&self) -> String {
+        self.test_names().join(" or ")
+    }
+}
+
+/// Select the tests reaching functions in `changed_files` within
+/// `max_depth` reverse call hops.
+pub fn select_tests(
+    functions: &[FunctionEntity],
+    calls: &[CallInfo],
+    changed_files: &[String],
+    max_depth: usize,
+) -> TestImpact {
+    let changed: BTreeSet<&str> = changed_files.iter().map(String::as_str).collect();
+
+    // Functions by name, and callers by callee name
+    let mut by_name: HashMap<&str, Vec<&FunctionEntity>> = HashMap::new();
+    for function in functions {
+        by_name
+            .entry(function.name.as_str())
+            .or_default()
+            .push(function);
+    }
+    let mut callers: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for call in calls {
+        callers
+            .entry(call.callee_name.as_str())
+            .or_default()
+            .insert(call.caller_name.as_str());
+    }
+
+    let mut tests: HashMap<(String, String), AffectedTest> = HashMap::new();
+    let mut add = |function: &FunctionEntity, distance: usize, reaches: Option<&str>| {
+        if !is_test_function(function) {
+            return;
+        }
+        tests
+            .entry((function.file_path.clone(), function.qualified_name.clone()))
+            .or_insert_with(|| AffectedTest {
+                name: function.name.clone(),
+                qualified_name: function.qualified_name.clone(),
+                file_path: function.file_path.clone(),
+                distance,
+                reaches: reaches.map(String::from),
+            });
+    };
+
+    // Breadth-first from every changed function, so each test keeps its
+    // shortest distance
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut queue: VecDeque<(&str, usize, &str)> = VecDeque::new();
+    let mut changed_functions = 0;
+    for function in functions
+        .iter()
+        .filter(|f| changed.contains(f.file_path.as_str()))
+    {
+        changed_functions += 1;
+        add(function, 0, None);
+        if visited.insert(&function.name) {
+            queue.push_back((&function.name, 0, &function.name));
+        }
+    }
+    while let Some((name, distance, origin)) = queue.pop_front() {
+        if distance >= max_depth {
+            continue;
+        }
+        for caller in callers.get(name).into_iter().flatten() {
+            if !visited.insert(caller) {
+                continue;
+            }
+            for function in by_name.get(caller).into_iter().flatten() {
+                add(function, distance + 1, Some(origin));
+            }
+            queue.push_back((caller, distance + 1, origin));
+        }
+    }
+
+    let mut tests: Vec<AffectedTest> = tests.into_values().collect();
+    tests.sort_by(|a, b| {
+        (a.distance, &a.file_path, &a.qualified_name).cmp(&(
+            b.distance,
+            &b.file_path,
+            &b.qualified_name,
+        ))
+    });
+
+    TestImpact {
+        changed_files: changed.iter().map(|f| f.to_string()).collect(),
+        changed_functions,
+        tests,
+        changed_test_files: changed
+            .iter()
+            .filter(|f| is_test_path(f))
+            .map(|f| f.to_string())
+            .collect(),
+    }
+}
+
+/// Files changed in the working tree of `root` relative to `base` (a
+/// commit, branch or `HEAD`), staged or not, plus untracked files that
+/// aren't ignored, as paths relative to `root`.
+///
+/// Paths are read NUL-separated, so git leaves names with non-ASCII
+/// characters, spaces or quotes as they are instead of quoting them.
+pub fn git_changed_files(root: &Path, base: &str) -> Result<Vec<String>, KnowledgeError> {
+    let mut files = git_paths(root, &["diff", "--name-only", "--relative", "-z", base])?;
+    files.extend(git_paths(
+        root,
+        &["ls-files", "--others", "--exclude-standard", "-z"],
+    )?);
+    Ok(files)
+}
+
+/// Run a git command in `root` that lists NUL-separated paths.
+fn git_paths(root: &Path, args: &[&str]) -> Result<Vec<String>, KnowledgeError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(|e| KnowledgeError::Git(format!("failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(KnowledgeError::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// How `arq test-impact` prints the selection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestImpactFormat {
+    /// Tests with their file and distance, for people
+    #[default]
+    List,
+    /// Test files, one per line
+    Files,
+    /// Test name filters for `cargo test --`
+    Cargo,
+    /// A `pytest -k` expression
+    Pytest,
+    /// The full selection as JSON
+    Json,
+}
+
+impl TestImpactFormat {
+    /// Name used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Files => "files",
+            Self::Cargo => "cargo",
+            Self::Pytest => "pytest",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for TestImpactFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TestImpactFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "list" => Ok(Self::List),
+            "files" => Ok(Self::Files),
+            "cargo" => Ok(Self::Cargo),
+            "pytest" => Ok(Self::Pytest),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "unknown format '{}' (expected list, files, cargo, pytest or json)",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::ontology::nodes::Visibility;
+
+    fn function(path: &str, name: &str, attributes: &[&str]) -> FunctionEntity {
+        FunctionEntity {
+            id: None,
+            name: name.to_string(),
+            qualified_name: name.to_string(),
+            file_path: path.to_string(),
+            start_line: 1,
+            end_line: 10,
+            signature: format!("fn {}()", name),
+            parent: None,
+            visibility: Visibility::Private,
+            is_async: false,
+            is_unsafe: false,
+            generics: Vec::new(),
+            attributes: attributes.iter().map(|a| a.to_string()).collect(),
+            parameters: Vec::new(),
+            return_type: None,
+            doc_comment: None,
+            complexity: None,
+        }
+    }
+
+    fn call(caller: &str, callee: &str) -> CallInfo {
+        CallInfo {
+            caller_name: caller.to_string(),
+            callee_name: callee.to_string(),
+            ..CallInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_select_tests_follows_callers() {
+        let functions = vec![
+            function("src/auth.rs", "validate", &[]),
+            function("src/api.rs", "handle", &[]),
+            function("src/api.rs", "test_handle", &["#[tokio::test]"]),
+            function("tests/api.rs", "login_works", &["#[test]"]),
+            function("tests/other.rs", "test_unrelated", &[]),
+        ];
+        let calls = vec![
+            call("handle", "validate"),
+            call("test_handle", "handle"),
+            call("login_works", "handle"),
+            call("test_unrelated", "format"),
+        ];
+
+        let impact = select_tests(&functions, &calls, &["src/auth.rs".to_string()], 6);
+        let tests: Vec<(&str, usize)> = impact
+            .tests
+            .iter()
+            .map(|t| (t.name.as_str(), t.distance))
+            .collect();
+        assert_eq!(tests, vec![("test_handle", 2), ("login_works", 2)]);
+        assert_eq!(impact.changed_functions, 1);
+        assert_eq!(impact.cargo_filters(), "login_works test_handle");
+        assert_eq!(impact.pytest_expression(), "login_works or test_handle");
+        assert_eq!(impact.test_files(), vec!["src/api.rs", "tests/api.rs"]);
+
+        // Too few hops to reach the tests
+        assert!(
+            select_tests(&functions, &calls, &["src/auth.rs".to_string()], 1)
+                .tests
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_git_changed_files_include_untracked() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=arq", "-c", "user.email=arq@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(dir.path().join("straße.rs"), "fn b() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        std::fs::write(dir.path().join("straße.rs"), "fn c() {}\n").unwrap();
+        std::fs::write(dir.path().join("new file.rs"), "fn d() {}\n").unwrap();
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("target/out.rs"), "").unwrap();
+
+        let mut files = git_changed_files(dir.path(), "HEAD").unwrap();
+        files.sort();
+        assert_eq!(files, vec!["new file.rs", "straße.rs"]);
+    }
+}
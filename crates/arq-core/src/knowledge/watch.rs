@@ -0,0 +1,414 @@
+//! Keeping a long-running index current.
+//!
+//! A process that holds the knowledge graph open, such as `arq serve
+//! --watch`, polls the project with a [`ChangeScanner`] and re-indexes what
+//! changed through
+//! [`KnowledgeGraph::apply_changes`](super::KnowledgeGraph::apply_changes).
+//! `arq watch` is notified of changes by the operating system through a
+//! [`FileWatcher`] instead, which waits for rapid saves to settle and leaves
+//! out what `context.exclude_dirs` and `context.exclude_patterns` exclude.
+//! The operating system reports a directory moved in or out as one path, so
+//! the watcher expands it into the files it contains, and a removed path
+//! stands for every indexed file under it.
+//! Every change to the index is published as an [`IndexUpdate`] to
+//! subscribers of [`KnowledgeGraph::subscribe`](super::KnowledgeGraph::subscribe),
+//! e.g. the web UI's live updates.
+
+use ignore::WalkBuilder;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use super::indexer::matches_pattern;
+use super::KnowledgeError;
+use crate::config::ContextConfig;
+
+/// Updates buffered per subscriber before the oldest are dropped.
+pub const UPDATE_CHANNEL_CAPACITY: usize = 64;
+
+/// A change to the index.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexUpdate {
+    /// Re-indexed or removed files, relative to the project root; empty
+    /// when a whole directory was indexed.
+    pub files: Vec<String>,
+}
+
+/// Files modified or removed since the previous scan.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileChanges {
+    /// Files created or modified, relative to the scanned root.
+    pub modified: Vec<String>,
+    /// Files that no longer exist.
+    pub removed: Vec<String>,
+}
+
+impl FileChanges {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    /// Indexed files the removed paths stand for: each path itself, or the
+    /// files under it when a whole directory was removed.
+    pub fn removed_files(&self, indexed: &[String]) -> Vec<String> {
+        let mut files = Vec::new();
+        for path in &self.removed {
+            let prefix = format!("{}/", path.trim_end_matches('/'));
+            let under: Vec<&String> = indexed
+                .iter()
+                .filter(|file| file.starts_with(&prefix))
+                .collect();
+            if under.is_empty() {
+                files.push(path.clone());
+            } else {
+                files.extend(under.into_iter().cloned());
+            }
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Add changes made after these; a file counts as modified or removed
+    /// by its latest change.
+    pub fn merge(&mut self, later: FileChanges) {
+        self.modified.retain(|path| !later.removed.contains(path));
+        self.removed.retain(|path| !later.modified.contains(path));
+        self.modified.extend(later.modified);
+        self.removed.extend(later.removed);
+        for paths in [&mut self.modified, &mut self.removed] {
+            paths.sort();
+            paths.dedup();
+        }
+    }
+}
+
+/// Detects changed files by comparing modification times between scans.
+///
+/// Walks the tree like indexing does, skipping hidden and git-ignored files.
+pub struct ChangeScanner {
+    root: PathBuf,
+    seen: HashMap<String, SystemTime>,
+}
+
+impl ChangeScanner {
+    /// Start watching `root`; files as they are now count as unchanged.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            seen: snapshot(root),
+        }
+    }
+
+    /// Files modified or removed since the previous scan, sorted by path.
+    pub fn scan(&mut self) -> FileChanges {
+        let current = snapshot(&self.root);
+
+        let mut modified: Vec<String> = current
+            .iter()
+            .filter(|(path, mtime)| self.seen.get(*path) != Some(*mtime))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let mut removed: Vec<String> = self
+            .seen
+            .keys()
+            .filter(|path| !current.contains_key(*path))
+            .cloned()
+            .collect();
+        modified.sort();
+        removed.sort();
+
+        self.seen = current;
+        FileChanges { modified, removed }
+    }
+}
+
+/// Paths a [`FileWatcher`] ignores: hidden ones, those under
+/// `context.exclude_dirs` and those matching `context.exclude_patterns`.
+#[derive(Debug, Clone, Default)]
+pub struct WatchFilter {
+    exclude_dirs: Vec<String>,
+    exclude_patterns: Vec<String>,
+}
+
+impl WatchFilter {
+    pub fn from_config(config: &ContextConfig) -> Self {
+        Self {
+            exclude_dirs: config.exclude_dirs.clone(),
+            exclude_patterns: config.exclude_patterns.clone(),
+        }
+    }
+
+    /// Whether the relative `path` is ignored.
+    pub fn excludes(&self, path: &str) -> bool {
+        let path = path.replace('\\', "/");
+        let mut components = path.split('/');
+        let file_name = components.next_back().unwrap_or_default();
+        components.any(|dir| dir.starts_with('.') || self.exclude_dirs.iter().any(|d| d == dir))
+            || file_name.starts_with('.')
+            || self
+                .exclude_patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &path))
+    }
+}
+
+/// Batches of changed files under a directory, as reported by the operating
+/// system.
+pub struct FileWatcher {
+    root: PathBuf,
+    filter: WatchFilter,
+    debounce: Duration,
+    paths: mpsc::UnboundedReceiver<PathBuf>,
+    /// Stops watching when dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    /// Watch `root` recursively; a batch of changes is complete once
+    /// `debounce` passes without another.
+    pub fn new(
+        root: &Path,
+        filter: WatchFilter,
+        debounce: Duration,
+    ) -> Result<Self, KnowledgeError> {
+        let root = root.canonicalize().map_err(|e| KnowledgeError::Io {
+            path: root.to_path_buf(),
+            source: e,
+        })?;
+        let (tx, paths) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                // Reads don't change anything
+                if let Ok(event) = event {
+                    if !event.kind.is_access() {
+                        for path in event.paths {
+                            let _ = tx.send(path);
+                        }
+                    }
+                }
+            })
+            .map_err(|e| KnowledgeError::Watch(e.to_string()))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| KnowledgeError::Watch(format!("{}: {}", root.display(), e)))?;
+
+        Ok(Self {
+            root,
+            filter,
+            debounce,
+            paths,
+            _watcher: watcher,
+        })
+    }
+
+    /// The next batch of changed files that aren't ignored, relative to the
+    /// root. `None` once the watcher has stopped.
+    ///
+    /// Ignored paths neither start a batch nor delay its end, so a build
+    /// writing to `target/` doesn't hold back re-indexing.
+    pub async fn next_changes(&mut self) -> Option<FileChanges> {
+        loop {
+            let mut paths = BTreeSet::new();
+            while paths.is_empty() {
+                let path = self.paths.recv().await?;
+                if is_watched(&self.root, &self.filter, &path) {
+                    paths.insert(path);
+                }
+            }
+            let mut deadline = Instant::now() + self.debounce;
+            while let Ok(Some(path)) = tokio::time::timeout_at(deadline, self.paths.recv()).await {
+                if is_watched(&self.root, &self.filter, &path) {
+                    paths.insert(path);
+                    deadline = Instant::now() + self.debounce;
+                }
+            }
+
+            let changes = classify_changes(&self.root, &self.filter, paths);
+            if !changes.is_empty() {
+                return Some(changes);
+            }
+        }
+    }
+}
+
+/// Whether `path` is under `root` and not ignored.
+fn is_watched(root: &Path, filter: &WatchFilter, path: &Path) -> bool {
+    path.strip_prefix(root).is_ok_and(|relative| {
+        let relative = relative.to_string_lossy();
+        !relative.is_empty() && !filter.excludes(&relative)
+    })
+}
+
+/// Sort changed paths under `root` into modified and removed paths by
+/// whether they still exist, expanding directories into their files and
+/// dropping ignored paths.
+fn classify_changes(
+    root: &Path,
+    filter: &WatchFilter,
+    paths: impl IntoIterator<Item = PathBuf>,
+) -> FileChanges {
+    let mut changes = FileChanges::default();
+    for path in paths {
+        if !is_watched(root, filter, &path) {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().to_string();
+        if path.is_file() {
+            changes.modified.push(relative);
+        } else if path.is_dir() {
+            // Created or moved in with its contents, which report no events
+            changes.modified.extend(
+                snapshot(&path)
+                    .into_keys()
+                    .map(|file| format!("{}/{}", relative, file))
+                    .filter(|file| !filter.excludes(file)),
+            );
+        } else {
+            changes.removed.push(relative);
+        }
+    }
+    for paths in [&mut changes.modified, &mut changes.removed] {
+        paths.sort();
+        paths.dedup();
+    }
+    changes
+}
+
+/// Modification time of every file under `root`, keyed by relative path.
+fn snapshot(root: &Path) -> HashMap<String, SystemTime> {
+    WalkBuilder::new(root)
+        .hidden(true)
+        .git_ignore(true)
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| {
+            let mtime = entry.metadata().ok()?.modified().ok()?;
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some((relative.to_string_lossy().to_string(), mtime))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_scan_reports_modified_and_removed_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("kept.rs"), "fn kept() {}").unwrap();
+        fs::write(dir.path().join("gone.rs"), "fn gone() {}").unwrap();
+
+        let mut scanner = ChangeScanner::new(dir.path());
+        assert!(scanner.scan().is_empty());
+
+        fs::write(dir.path().join("new.rs"), "fn new() {}").unwrap();
+        fs::remove_file(dir.path().join("gone.rs")).unwrap();
+        let changes = scanner.scan();
+        assert_eq!(changes.modified, vec!["new.rs".to_string()]);
+        assert_eq!(changes.removed, vec!["gone.rs".to_string()]);
+        assert!(scanner.scan().is_empty());
+    }
+
+    #[test]
+    fn test_merge_keeps_latest_change() {
+        let mut changes = FileChanges {
+            modified: vec!["a.rs".to_string(), "b.rs".to_string()],
+            removed: vec!["c.rs".to_string()],
+        };
+        changes.merge(FileChanges {
+            modified: vec!["a.rs".to_string(), "c.rs".to_string()],
+            removed: vec!["b.rs".to_string()],
+        });
+        assert_eq!(
+            changes.modified,
+            vec!["a.rs".to_string(), "c.rs".to_string()]
+        );
+        assert_eq!(changes.removed, vec!["b.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_watch_filter_excludes_context_exclusions() {
+        let filter = WatchFilter::from_config(&ContextConfig::default());
+        assert!(!filter.excludes("src/lib.rs"));
+        assert!(filter.excludes("node_modules/left-pad/index.js"));
+        assert!(filter.excludes(".git/index"));
+        assert!(filter.excludes("src/.main.rs.swp"));
+        assert!(filter.excludes("Cargo.lock"));
+    }
+
+    #[test]
+    fn test_classify_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "fn lib() {}").unwrap();
+
+        let changes = classify_changes(
+            dir.path(),
+            &WatchFilter::from_config(&ContextConfig::default()),
+            [
+                dir.path().join("src/lib.rs"),
+                dir.path().join("src/gone.rs"),
+                dir.path().join("app.log"),
+                PathBuf::from("/elsewhere/main.rs"),
+            ],
+        );
+        assert_eq!(changes.modified, vec!["src/lib.rs".to_string()]);
+        assert_eq!(changes.removed, vec!["src/gone.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_classify_changes_expands_directories() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("moved/nested")).unwrap();
+        fs::write(dir.path().join("moved/a.rs"), "fn a() {}").unwrap();
+        fs::write(dir.path().join("moved/nested/b.rs"), "fn b() {}").unwrap();
+        fs::write(dir.path().join("moved/app.log"), "").unwrap();
+
+        let changes = classify_changes(
+            dir.path(),
+            &WatchFilter::from_config(&ContextConfig::default()),
+            [dir.path().join("moved"), dir.path().join("old")],
+        );
+        assert_eq!(
+            changes.modified,
+            vec!["moved/a.rs".to_string(), "moved/nested/b.rs".to_string()]
+        );
+        assert_eq!(changes.removed, vec!["old".to_string()]);
+
+        let indexed = vec![
+            "old/a.rs".to_string(),
+            "old/nested/b.rs".to_string(),
+            "older.rs".to_string(),
+        ];
+        assert_eq!(
+            changes.removed_files(&indexed),
+            vec!["old/a.rs".to_string(), "old/nested/b.rs".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_is_watched_skips_excluded_paths() {
+        let root = Path::new("/project");
+        let filter = WatchFilter::from_config(&ContextConfig::default());
+        assert!(is_watched(root, &filter, Path::new("/project/src/lib.rs")));
+        assert!(!is_watched(
+            root,
+            &filter,
+            Path::new("/project/target/debug/arq")
+        ));
+        assert!(!is_watched(root, &filter, Path::new("/project/.git/index")));
+        assert!(!is_watched(root, &filter, Path::new("/project")));
+        assert!(!is_watched(root, &filter, Path::new("/elsewhere/main.rs")));
+    }
+}
@@ -0,0 +1,196 @@
+//! Cargo workspace detection.
+//!
+//! Maps indexed files to the crate that owns them so entities can be
+//! namespaced by crate (`arq_core::KnowledgeGraph::open`) and call edges
+//! can be resolved across crates in the same workspace.
+
+use std::fs;
+use std::path::Path;
+
+/// A crate in a Cargo workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateInfo {
+    /// Package name as written in Cargo.toml (e.g. `arq-core`)
+    pub name: String,
+    /// Crate directory relative to the workspace root (empty for the root package)
+    pub path: String,
+    /// Other workspace crates this crate depends on
+    pub dependencies: Vec<String>,
+}
+
+impl CrateInfo {
+    /// The crate name as it appears in Rust paths (`arq-core` -> `arq_core`).
+    pub fn ident(&self) -> String {
+        crate_ident(&self.name)
+    }
+}
+
+/// Crates found in a Cargo workspace, keyed by directory.
+#[derive(Debug, Clone, Default)]
+pub struct CrateMap {
+    crates: Vec<CrateInfo>,
+}
+
+impl CrateMap {
+    /// Detect the crates under `root`.
+    ///
+    /// Reads `[workspace].members` (with `dir/*` globs) and the root
+    /// `[package]`. Returns an empty map if `root` is not a Cargo project.
+    pub fn detect(root: &Path) -> Self {
+        let Some(manifest) = read_manifest(&root.join("Cargo.toml")) else {
+            return Self::default();
+        };
+
+        let mut crates = Vec::new();
+        let mut dependencies = Vec::new();
+
+        if let Some(name) = package_name(&manifest) {
+            crates.push(CrateInfo {
+                name,
+                path: String::new(),
+                dependencies: Vec::new(),
+            });
+            dependencies.push(dependency_names(&manifest));
+        }
+
+        if let Some(workspace) = manifest.get("workspace") {
+            let excluded = string_array(workspace.get("exclude"));
+
+            for member in string_array(workspace.get("members")) {
+                for dir in expand_member(root, &member) {
+                    if excluded.contains(&dir) {
+                        continue;
+                    }
+                    let Some(member_manifest) = read_manifest(&root.join(&dir).join("Cargo.toml"))
+                    else {
+                        continue;
+                    };
+                    if let Some(name) = package_name(&member_manifest) {
+                        crates.push(CrateInfo {
+                            name,
+                            path: dir,
+                            dependencies: Vec::new(),
+                        });
+                        dependencies.push(dependency_names(&member_manifest));
+                    }
+                }
+            }
+        }
+
+        // Keep only dependencies on other workspace crates
+        let names: Vec<String> = crates.iter().map(|c| c.name.clone()).collect();
+        for (info, deps) in crates.iter_mut().zip(dependencies) {
+            info.dependencies = deps
+                .into_iter()
+                .filter(|d| *d != info.name && names.contains(d))
+                .collect();
+        }
+
+        Self { crates }
+    }
+
+    /// Build a map from already-known crates.
+    pub fn from_crates(crates: Vec<CrateInfo>) -> Self {
+        Self { crates }
+    }
+
+    /// Returns true if no crates were found.
+    pub fn is_empty(&self) -> bool {
+        self.crates.is_empty()
+    }
+
+    /// All crates in the workspace.
+    pub fn crates(&self) -> &[CrateInfo] {
+        &self.crates
+    }
+
+    /// Look up a crate by package name or Rust identifier.
+    pub fn get(&self, name: &str) -> Option<&CrateInfo> {
+        let ident = crate_ident(name);
+        self.crates.iter().find(|c| c.ident() == ident)
+    }
+
+    /// The crate that owns the file at `path` (relative to the workspace root).
+    ///
+    /// Nested crates win over the root package.
+    pub fn crate_for_path(&self, path: &str) -> Option<&CrateInfo> {
+        let path = path.trim_start_matches("./");
+        self.crates
+            .iter()
+            .filter(|c| {
+                c.path.is_empty()
+                    || path
+                        .strip_prefix(c.path.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|c| c.path.len())
+    }
+}
+
+/// Convert a package name to the identifier used in Rust paths.
+pub fn crate_ident(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn read_manifest(path: &Path) -> Option<toml::Value> {
+    let content = fs::read_to_string(path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+fn package_name(manifest: &toml::Value) -> Option<String> {
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn string_array(value: Option<&toml::Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.trim_end_matches('/').to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Package names of all dependencies, honoring `package = "..."` renames.
+fn dependency_names(manifest: &toml::Value) -> Vec<String> {
+    ["dependencies", "dev-dependencies", "build-dependencies"]
+        .iter()
+        .filter_map(|section| manifest.get(section)?.as_table())
+        .flat_map(|table| {
+            table.iter().map(|(key, spec)| {
+                spec.get("package")
+                    .and_then(|p| p.as_str())
+                    .unwrap_or(key)
+                    .to_string()
+            })
+        })
+        .collect()
+}
+
+/// Expand a workspace member pattern into member directories.
+///
+/// Supports plain paths and a trailing `*` component (`crates/*`).
+fn expand_member(root: &Path, member: &str) -> Vec<String> {
+    let Some(parent) = member.strip_suffix("/*") else {
+        return vec![member.to_string()];
+    };
+
+    let Ok(entries) = fs::read_dir(root.join(parent)) else {
+        return Vec::new();
+    };
+
+    let mut dirs: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.path().join("Cargo.toml").is_file())
+        .map(|e| format!("{}/{}", parent, e.file_name().to_string_lossy()))
+        .collect();
+    dirs.sort();
+    dirs
+}
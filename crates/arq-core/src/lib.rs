@@ -1,26 +1,39 @@
 pub mod agent;
 pub mod config;
 pub mod context;
+pub mod error;
+pub mod events;
+pub mod hooks;
 pub mod knowledge;
 pub mod llm;
 pub mod manager;
 pub mod phase;
 pub mod planning;
+pub mod prompts;
 pub mod research;
+pub mod stats;
 pub mod storage;
 pub mod task;
 
 pub use config::{
-    Config, ConfigError, ContextConfig, KnowledgeConfig, LLMConfig, ResearchConfig, StorageConfig,
+    Config, ConfigError, ConfigOrigin, ConfigOrigins, ContentGuard, ContextConfig,
+    EnrichmentConfig, ExternalParserConfig, FileOrder, GeneratedCodeHandling, HooksConfig,
+    KnowledgeConfig, LLMConfig, OversizedFileHandling, ParsersConfig, PhasePromptConfig,
+    PlanningConfig, PromptsConfig, RerankWeights, ResearchConfig, ServeConfig, StorageConfig,
+    StorageLayout, TreeSource, TuiConfig,
 };
 pub use context::{Context, ContextBuilder, ContextError};
+pub use error::ArqError;
+pub use hooks::{HookError, HookEvent, Hooks};
 pub use knowledge::{
-    IndexProgress, IndexStats, KnowledgeError, KnowledgeGraph, KnowledgeStore, SearchResult,
+    IndexProgress, IndexReport, IndexStats, KnowledgeError, KnowledgeGraph, KnowledgeStore,
+    SearchResult,
 };
-pub use llm::{ClaudeClient, LLMError, OpenAIClient, Provider, StreamChunk, LLM};
+pub use llm::{ClaudeClient, LLMError, OpenAIClient, Provider, StreamChunk, TokenUsage, LLM};
 pub use manager::{ManagerError, TaskManager};
 pub use phase::Phase;
-pub use planning::Plan;
+pub use planning::{Plan, PlanningError, PlanningProgress, PlanningRunner};
 pub use research::{ResearchDoc, ResearchError, ResearchProgress, ResearchRunner};
+pub use stats::TaskStats;
 pub use storage::{FileStorage, Storage, StorageError};
-pub use task::{Task, TaskError, TaskSummary};
+pub use task::{clean_task_name, suggest_task_name, Task, TaskError, TaskSummary, UsageRecord};
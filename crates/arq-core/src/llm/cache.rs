@@ -0,0 +1,224 @@
+//! Cache of LLM responses for repeated identical calls.
+//!
+//! Entries are keyed by a hash of the model and the full prompt, so any
+//! change to the prompt is simply a miss. Each entry is a JSON file under
+//! the project data directory; entries older than the TTL are ignored and
+//! overwritten on the next call. Responses carry whole research documents,
+//! so entries are encrypted with `storage.encrypt` (see [`PrivateFiles`]).
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::{CancellationToken, LLMError, StreamChunk, LLM};
+use crate::storage::PrivateFiles;
+
+/// A cached response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    /// Key the response was stored under (see [`response_cache_key`]).
+    pub key: String,
+    /// Full response text.
+    pub response: String,
+    /// When the response was received.
+    pub created_at: DateTime<Utc>,
+}
+
+/// File-backed response cache, one entry per key.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    files: PrivateFiles,
+}
+
+impl ResponseCache {
+    /// Creates a cache storing plaintext entries in `dir` that never expire.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: None,
+            files: PrivateFiles::default(),
+        }
+    }
+
+    /// Reads and writes entries through `files`, encrypting them if enabled.
+    pub fn with_encryption(mut self, files: PrivateFiles) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Ignores entries older than `ttl`; zero keeps them forever.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = (!ttl.is_zero()).then_some(ttl);
+        self
+    }
+
+    /// Cached response for `key`, unless missing or expired.
+    pub fn load(&self, key: &str) -> Option<String> {
+        let json = self.files.read(&self.entry_path(key)).ok()?;
+        let cached: CachedResponse = serde_json::from_str(&json).ok()?;
+        if cached.key != key {
+            return None;
+        }
+        if let Some(ttl) = self.ttl {
+            let age = Utc::now().signed_duration_since(cached.created_at);
+            if age.to_std().is_ok_and(|age| age > ttl) {
+                return None;
+            }
+        }
+        Some(cached.response)
+    }
+
+    /// Store the response for `key`, replacing any previous entry.
+    pub fn store(&self, key: &str, response: &str) -> io::Result<()> {
+        let entry = CachedResponse {
+            key: key.to_string(),
+            response: response.to_string(),
+            created_at: Utc::now(),
+        };
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        std::fs::create_dir_all(&self.dir)?;
+        self.files
+            .write(&self.entry_path(key), json.as_bytes())
+            .map_err(io::Error::other)
+    }
+
+    /// Remove the entry for `key`, if any.
+    pub fn remove(&self, key: &str) -> io::Result<()> {
+        match std::fs::remove_file(self.entry_path(key)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        // Keys are hex hashes; keep anything else from escaping the cache dir
+        let name: String = key
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+/// Key identifying a call: the model it goes to and its full prompt.
+///
+/// `model` should name the provider as well, e.g. `openai:gpt-4o`. Calls
+/// without a system message are keyed apart from ones with an empty one.
+pub fn response_cache_key(model: &str, system: Option<&str>, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    match system {
+        Some(system) => {
+            hasher.update(b"\0system\0");
+            hasher.update(system.as_bytes());
+        }
+        None => hasher.update(b"\0none\0"),
+    }
+    hasher.update(b"\0prompt\0");
+    hasher.update(prompt.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Answers repeated identical calls from a [`ResponseCache`].
+///
+/// Failed and cancelled calls are never stored. Failing to write an entry
+/// doesn't fail the call. Callers evict a response that turned out unusable
+/// (see [`LLM::evict`]) so that retrying asks the provider again.
+pub struct Cache<L: LLM> {
+    inner: L,
+    cache: ResponseCache,
+    model: String,
+}
+
+impl<L: LLM> Cache<L> {
+    /// Caches responses of `inner`, which sends requests to `model`.
+    pub fn new(inner: L, cache: ResponseCache, model: impl Into<String>) -> Self {
+        Self {
+            inner,
+            cache,
+            model: model.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl<L: LLM> LLM for Cache<L> {
+    async fn complete(&self, prompt: &str) -> Result<String, LLMError> {
+        let key = response_cache_key(&self.model, None, prompt);
+        if let Some(response) = self.cache.load(&key) {
+            return Ok(response);
+        }
+        let response = self.inner.complete(prompt).await?;
+        let _ = self.cache.store(&key, &response);
+        Ok(response)
+    }
+
+    async fn complete_with_system(&self, system: &str, prompt: &str) -> Result<String, LLMError> {
+        let key = response_cache_key(&self.model, Some(system), prompt);
+        if let Some(response) = self.cache.load(&key) {
+            return Ok(response);
+        }
+        let response = self.inner.complete_with_system(system, prompt).await?;
+        let _ = self.cache.store(&key, &response);
+        Ok(response)
+    }
+
+    async fn stream_complete(
+        &self,
+        system: &str,
+        prompt: &str,
+        tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
+    ) -> Result<(), LLMError> {
+        let key = response_cache_key(&self.model, Some(system), prompt);
+        if let Some(response) = self.cache.load(&key) {
+            let _ = tx.send(StreamChunk::text(response));
+            let _ = tx.send(StreamChunk::done());
+            return Ok(());
+        }
+
+        // Collect the streamed text while forwarding it
+        let (collector_tx, mut collector_rx) = mpsc::unbounded_channel::<StreamChunk>();
+        let collect = async {
+            let mut response = String::new();
+            while let Some(chunk) = collector_rx.recv().await {
+                if !chunk.is_final {
+                    response.push_str(&chunk.text);
+                }
+                let _ = tx.send(chunk);
+            }
+            response
+        };
+        let (result, response) = tokio::join!(
+            self.inner
+                .stream_complete(system, prompt, collector_tx, cancel.clone()),
+            collect
+        );
+        result?;
+
+        if !cancel.is_cancelled() {
+            let _ = self.cache.store(&key, &response);
+        }
+        Ok(())
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn evict(&self, system: Option<&str>, prompt: &str) {
+        let _ = self
+            .cache
+            .remove(&response_cache_key(&self.model, system, prompt));
+        self.inner.evict(system, prompt)
+    }
+}
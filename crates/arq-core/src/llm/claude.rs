@@ -4,19 +4,31 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
-use super::{LLMError, StreamChunk, LLM};
+use super::{CancellationToken, LLMError, StreamChunk, LLM};
 use crate::config::{
     DEFAULT_ANTHROPIC_API_VERSION, DEFAULT_ANTHROPIC_MODEL, DEFAULT_ANTHROPIC_URL,
     DEFAULT_MAX_TOKENS,
 };
 
+/// Times a stream that drops mid-response is resumed before giving up.
+const MAX_STREAM_RESUMES: u32 = 2;
+
 /// Claude API client.
+///
+/// Streams that drop before `message_stop` are resumed: the request is sent
+/// again with the text received so far as an assistant prefill, and the
+/// continuation is forwarded on the same channel, so callers see one
+/// uninterrupted answer.
 pub struct ClaudeClient {
     api_key: String,
     api_url: String,
     api_version: String,
     model: String,
     max_tokens: u32,
+    /// Sampling temperature; the API default when unset
+    temperature: Option<f32>,
+    /// Extra headers sent with every request
+    headers: Vec<(String, String)>,
     client: Client,
 }
 
@@ -29,6 +41,8 @@ impl ClaudeClient {
             api_version: DEFAULT_ANTHROPIC_API_VERSION.to_string(),
             model: DEFAULT_ANTHROPIC_MODEL.to_string(),
             max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: None,
+            headers: Vec::new(),
             client: Client::new(),
         }
     }
@@ -51,6 +65,18 @@ impl ClaudeClient {
         self
     }
 
+    /// Sets the sampling temperature (`None` keeps the API default).
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Adds headers sent with every request (e.g. organization or attribution).
+    pub fn with_headers(mut self, headers: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
     /// Sets the API URL (for proxies or enterprise deployments).
     pub fn with_api_url(mut self, url: impl Into<String>) -> Self {
         self.api_url = url.into();
@@ -64,15 +90,16 @@ impl ClaudeClient {
     }
 
     async fn send_request(&self, request: &ClaudeRequest) -> Result<String, LLMError> {
-        let response = self
+        let mut req = self
             .client
             .post(&self.api_url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", &self.api_version)
-            .header("content-type", "application/json")
-            .json(request)
-            .send()
-            .await?;
+            .header("content-type", "application/json");
+        for (name, value) in &self.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        let response = req.json(request).send().await?;
 
         let status = response.status();
 
@@ -110,21 +137,32 @@ impl ClaudeClient {
         Ok(text)
     }
 
-    /// Send a streaming request and forward chunks through the channel.
+    /// Send a streaming request and forward text chunks through the channel,
+    /// appending them to `answer`.
+    ///
+    /// Failures before the response starts are errors; a stream that ends
+    /// without `message_stop` is reported as [`StreamEnd::Dropped`].
     async fn send_streaming_request(
         &self,
         request: &ClaudeRequest,
-        tx: mpsc::UnboundedSender<StreamChunk>,
-    ) -> Result<(), LLMError> {
-        let response = self
+        tx: &mpsc::UnboundedSender<StreamChunk>,
+        cancel: &CancellationToken,
+        answer: &mut Answer,
+    ) -> Result<StreamEnd, LLMError> {
+        let mut req = self
             .client
             .post(&self.api_url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", &self.api_version)
-            .header("content-type", "application/json")
-            .json(request)
-            .send()
-            .await?;
+            .header("content-type", "application/json");
+        for (name, value) in &self.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        // Dropping the request future on cancellation aborts the connection
+        let response = tokio::select! {
+            _ = cancel.cancelled() => return Err(LLMError::Cancelled),
+            response = req.json(request).send() => response?,
+        };
 
         let status = response.status();
 
@@ -144,8 +182,18 @@ impl ClaudeClient {
         let mut stream = response.bytes_stream();
         let mut buffer = String::new();
 
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result.map_err(|e| LLMError::Network(e.to_string()))?;
+        loop {
+            let chunk_result = tokio::select! {
+                _ = cancel.cancelled() => return Err(LLMError::Cancelled),
+                next = stream.next() => match next {
+                    Some(chunk_result) => chunk_result,
+                    None => return Ok(StreamEnd::Dropped("stream ended early".to_string())),
+                },
+            };
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(e) => return Ok(StreamEnd::Dropped(e.to_string())),
+            };
             let chunk_str = String::from_utf8_lossy(&chunk);
             buffer.push_str(&chunk_str);
 
@@ -154,16 +202,69 @@ impl ClaudeClient {
                 let event_data = buffer[..pos].to_string();
                 buffer = buffer[pos + 2..].to_string();
 
-                // Parse SSE event
-                if let Some(text) = parse_claude_sse_event(&event_data) {
-                    let _ = tx.send(StreamChunk::text(text));
+                match parse_claude_sse_event(&event_data) {
+                    Some(SseEvent::Text(text)) => {
+                        if let Some(text) = answer.push(&text) {
+                            let _ = tx.send(StreamChunk::text(text));
+                        }
+                    }
+                    Some(SseEvent::Stop) => return Ok(StreamEnd::Complete),
+                    Some(SseEvent::Error(message)) => return Ok(StreamEnd::Dropped(message)),
+                    None => {}
                 }
             }
         }
+    }
+}
 
-        // Send final chunk
-        let _ = tx.send(StreamChunk::done());
-        Ok(())
+/// How a streaming response ended.
+enum StreamEnd {
+    /// `message_stop` arrived.
+    Complete,
+    /// The connection closed or failed first, for the given reason.
+    Dropped(String),
+}
+
+/// Text streamed so far, across resumed requests.
+#[derive(Default)]
+struct Answer {
+    text: String,
+    /// Drop leading whitespace of the continuation: the prefill had its
+    /// trailing whitespace trimmed (the API rejects it), and the model
+    /// writes it again.
+    strip_whitespace: bool,
+}
+
+impl Answer {
+    /// Record a text delta, returning what to forward.
+    fn push(&mut self, delta: &str) -> Option<String> {
+        let delta = if self.strip_whitespace {
+            delta.trim_start()
+        } else {
+            delta
+        };
+        if delta.is_empty() {
+            return None;
+        }
+        self.strip_whitespace = false;
+        self.text.push_str(delta);
+        Some(delta.to_string())
+    }
+
+    /// Messages continuing `prompt` from the text so far.
+    fn resume_messages(&mut self, prompt: &str) -> Vec<Message> {
+        let prefill = self.text.trim_end();
+        self.strip_whitespace = prefill.len() < self.text.len();
+        vec![
+            Message {
+                role: "user".to_string(),
+                content: prompt.to_string(),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: prefill.to_string(),
+            },
+        ]
     }
 }
 
@@ -173,6 +274,7 @@ impl LLM for ClaudeClient {
         let request = ClaudeRequest {
             model: self.model.clone(),
             max_tokens: self.max_tokens,
+            temperature: self.temperature,
             system: None,
             messages: vec![Message {
                 role: "user".to_string(),
@@ -188,6 +290,7 @@ impl LLM for ClaudeClient {
         let request = ClaudeRequest {
             model: self.model.clone(),
             max_tokens: self.max_tokens,
+            temperature: self.temperature,
             system: Some(system.to_string()),
             messages: vec![Message {
                 role: "user".to_string(),
@@ -204,10 +307,12 @@ impl LLM for ClaudeClient {
         system: &str,
         prompt: &str,
         tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
     ) -> Result<(), LLMError> {
-        let request = ClaudeRequest {
+        let mut request = ClaudeRequest {
             model: self.model.clone(),
             max_tokens: self.max_tokens,
+            temperature: self.temperature,
             system: Some(system.to_string()),
             messages: vec![Message {
                 role: "user".to_string(),
@@ -216,7 +321,32 @@ impl LLM for ClaudeClient {
             stream: Some(true),
         };
 
-        self.send_streaming_request(&request, tx).await
+        let mut answer = Answer::default();
+        let mut resumes = 0;
+        loop {
+            let reason = match self
+                .send_streaming_request(&request, &tx, &cancel, &mut answer)
+                .await
+            {
+                Ok(StreamEnd::Complete) => break,
+                Ok(StreamEnd::Dropped(reason)) => reason,
+                // A resumed request failing to connect is another drop
+                Err(e) if resumes > 0 && e.is_transient() => e.to_string(),
+                Err(e) => return Err(e),
+            };
+            // Nothing to continue from: fail so the request is retried whole
+            if answer.text.trim().is_empty() || resumes == MAX_STREAM_RESUMES {
+                return Err(LLMError::Network(format!(
+                    "stream interrupted after {} resumes: {}",
+                    resumes, reason
+                )));
+            }
+            resumes += 1;
+            request.messages = answer.resume_messages(prompt);
+        }
+
+        let _ = tx.send(StreamChunk::done());
+        Ok(())
     }
 
     fn supports_streaming(&self) -> bool {
@@ -229,6 +359,8 @@ struct ClaudeRequest {
     model: String,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<String>,
     messages: Vec<Message>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -254,14 +386,24 @@ struct ContentBlock {
     text: String,
 }
 
-/// Parse a Claude SSE event and extract text from content_block_delta events.
+/// An SSE event of a Claude stream that matters to the client.
+enum SseEvent {
+    /// Text of a `content_block_delta`.
+    Text(String),
+    /// `message_stop`: the response is complete.
+    Stop,
+    /// An `error` event, such as `overloaded_error`, ending the stream.
+    Error(String),
+}
+
+/// Parse a Claude SSE event.
 ///
 /// Claude streaming format:
 /// ```text
 /// event: content_block_delta
 /// data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}
 /// ```
-fn parse_claude_sse_event(event_data: &str) -> Option<String> {
+fn parse_claude_sse_event(event_data: &str) -> Option<SseEvent> {
     let mut event_type = None;
     let mut data_line = None;
 
@@ -273,9 +415,15 @@ fn parse_claude_sse_event(event_data: &str) -> Option<String> {
         }
     }
 
-    // Only process content_block_delta events
-    if event_type != Some("content_block_delta") {
-        return None;
+    match event_type {
+        Some("content_block_delta") => {}
+        Some("message_stop") => return Some(SseEvent::Stop),
+        Some("error") => {
+            return Some(SseEvent::Error(
+                data_line.unwrap_or("error event").to_string(),
+            ))
+        }
+        _ => return None,
     }
 
     let data = data_line?;
@@ -297,6 +445,6 @@ fn parse_claude_sse_event(event_data: &str) -> Option<String> {
     if parsed.delta.text.is_empty() {
         None
     } else {
-        Some(parsed.delta.text)
+        Some(SseEvent::Text(parsed.delta.text))
     }
 }
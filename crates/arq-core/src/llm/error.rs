@@ -26,6 +26,21 @@ pub enum LLMError {
 
     #[error("Unknown provider: {0}")]
     UnknownProvider(String),
+
+    #[error("Request cancelled")]
+    Cancelled,
+}
+
+impl LLMError {
+    /// Whether the request may succeed if sent again: rate limits, network
+    /// failures and server errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LLMError::RateLimited | LLMError::Network(_) => true,
+            LLMError::ApiError { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
 }
 
 impl From<reqwest::Error> for LLMError {
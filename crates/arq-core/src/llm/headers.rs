@@ -0,0 +1,89 @@
+//! Extra HTTP headers sent with LLM requests.
+//!
+//! Some deployments need headers beyond authentication: OpenAI organization
+//! and project scoping, or OpenRouter app attribution. Well-known headers are
+//! derived per provider from environment variables; `llm.extra_headers` in
+//! the config adds to or overrides them.
+
+use std::collections::BTreeMap;
+
+/// OpenAI organization header.
+pub const OPENAI_ORGANIZATION_HEADER: &str = "OpenAI-Organization";
+
+/// OpenAI project header.
+pub const OPENAI_PROJECT_HEADER: &str = "OpenAI-Project";
+
+/// OpenRouter app URL header, used for attribution.
+pub const OPENROUTER_REFERER_HEADER: &str = "HTTP-Referer";
+
+/// OpenRouter app name header, used for attribution.
+pub const OPENROUTER_TITLE_HEADER: &str = "X-Title";
+
+/// App name reported to OpenRouter when none is configured.
+pub const DEFAULT_OPENROUTER_TITLE: &str = "arq";
+
+/// OpenAI organization and project headers.
+pub fn openai_headers(organization: Option<&str>, project: Option<&str>) -> Vec<(String, String)> {
+    [
+        (OPENAI_ORGANIZATION_HEADER, organization),
+        (OPENAI_PROJECT_HEADER, project),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| value.map(|v| (name.to_string(), v.to_string())))
+    .collect()
+}
+
+/// OpenRouter attribution headers.
+pub fn openrouter_headers(referer: Option<&str>, title: Option<&str>) -> Vec<(String, String)> {
+    [
+        (OPENROUTER_REFERER_HEADER, referer),
+        (OPENROUTER_TITLE_HEADER, title),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| value.map(|v| (name.to_string(), v.to_string())))
+    .collect()
+}
+
+/// Well-known headers for a provider, read from the environment.
+///
+/// - `openai`: `OPENAI_ORG_ID` and `OPENAI_PROJECT_ID`
+/// - `openrouter`: `OPENROUTER_REFERER` and `OPENROUTER_TITLE` (defaults to "arq")
+pub fn well_known_headers(provider: &str) -> Vec<(String, String)> {
+    let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    match provider {
+        "openai" => openai_headers(
+            env("OPENAI_ORG_ID").as_deref(),
+            env("OPENAI_PROJECT_ID").as_deref(),
+        ),
+        "openrouter" => openrouter_headers(
+            env("OPENROUTER_REFERER").as_deref(),
+            Some(
+                env("OPENROUTER_TITLE")
+                    .as_deref()
+                    .unwrap_or(DEFAULT_OPENROUTER_TITLE),
+            ),
+        ),
+        _ => Vec::new(),
+    }
+}
+
+/// Merge configured headers over well-known ones.
+///
+/// Header names compare case-insensitively; a configured header with an empty
+/// value removes the well-known header of the same name.
+pub fn merge_headers(
+    well_known: Vec<(String, String)>,
+    extra: &BTreeMap<String, String>,
+) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = well_known
+        .into_iter()
+        .filter(|(name, _)| !extra.keys().any(|k| k.eq_ignore_ascii_case(name)))
+        .collect();
+    headers.extend(
+        extra
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name.clone(), value.clone())),
+    );
+    headers
+}
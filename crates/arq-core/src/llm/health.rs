@@ -0,0 +1,201 @@
+//! Provider health checks for `arq llm ping`.
+//!
+//! A research run only fails at the LLM call, after context has been
+//! gathered, so a wrong key or base URL can cost minutes. Pinging sends a
+//! tiny prompt to each provider and reports whether it was reachable,
+//! whether the key was accepted, how long the reply took and whether the
+//! response streamed.
+
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use super::{CancellationToken, LLMError, Provider, StreamChunk, LLM};
+use crate::config::LLMConfig;
+
+/// System prompt of a ping.
+pub const PING_SYSTEM_PROMPT: &str = "You are a health check. Answer as briefly as possible.";
+
+/// Prompt of a ping.
+pub const PING_PROMPT: &str = "Reply with the single word: pong";
+
+/// Outcome of a ping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingStatus {
+    /// The provider answered.
+    Ok,
+    /// The API key is missing or was rejected.
+    AuthFailed,
+    /// The provider could not be reached or did not answer in time.
+    Unreachable,
+    /// The provider answered with an error, e.g. an unknown model.
+    Failed,
+}
+
+impl PingStatus {
+    /// Classify the error of a failed ping.
+    pub fn from_error(error: &LLMError) -> Self {
+        match error {
+            LLMError::MissingApiKey => Self::AuthFailed,
+            LLMError::ApiError { status, .. } if *status == 401 || *status == 403 => {
+                Self::AuthFailed
+            }
+            LLMError::Network(_) => Self::Unreachable,
+            _ => Self::Failed,
+        }
+    }
+
+    /// Short label for reports.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::AuthFailed => "auth failed",
+            Self::Unreachable => "unreachable",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Health of one provider.
+#[derive(Debug, Clone)]
+pub struct PingReport {
+    pub provider: String,
+    pub model: String,
+    pub status: PingStatus,
+    /// Time until the complete reply arrived.
+    pub latency: Option<Duration>,
+    /// Time until the first streamed chunk arrived.
+    pub first_chunk: Option<Duration>,
+    /// Whether the reply streamed; `None` if the ping failed before trying.
+    pub streaming: Option<bool>,
+    /// What went wrong, for failed pings or a failed streaming attempt.
+    pub error: Option<String>,
+}
+
+impl PingReport {
+    pub fn is_ok(&self) -> bool {
+        self.status == PingStatus::Ok
+    }
+}
+
+/// Ping the provider of `config`, giving each request `timeout`.
+///
+/// The response cache is bypassed so the latency is the provider's.
+pub async fn ping_provider(config: &LLMConfig, timeout: Duration) -> PingReport {
+    let mut report = PingReport {
+        provider: config.provider.clone(),
+        model: config.model_or_default(),
+        status: PingStatus::Ok,
+        latency: None,
+        first_chunk: None,
+        streaming: None,
+        error: None,
+    };
+
+    let llm = match Provider::build_from_config(config) {
+        Ok(llm) => llm,
+        Err(e) => {
+            report.status = PingStatus::from_error(&e);
+            report.error = Some(e.to_string());
+            return report;
+        }
+    };
+
+    let start = Instant::now();
+    let reply = tokio::time::timeout(
+        timeout,
+        llm.complete_with_system(PING_SYSTEM_PROMPT, PING_PROMPT),
+    )
+    .await;
+    match reply {
+        Ok(Ok(_)) => report.latency = Some(start.elapsed()),
+        Ok(Err(e)) => {
+            report.status = PingStatus::from_error(&e);
+            report.error = Some(e.to_string());
+            return report;
+        }
+        Err(_) => {
+            report.status = PingStatus::Unreachable;
+            report.error = Some(format!("no reply within {}s", timeout.as_secs()));
+            return report;
+        }
+    }
+
+    if !llm.supports_streaming() {
+        report.streaming = Some(false);
+        return report;
+    }
+    match ping_streaming(llm.as_ref(), timeout).await {
+        Ok(first_chunk) => {
+            report.streaming = Some(first_chunk.is_some());
+            report.first_chunk = first_chunk;
+        }
+        Err(e) => {
+            report.streaming = Some(false);
+            report.error = Some(format!("streaming failed: {}", e));
+        }
+    }
+    report
+}
+
+/// Stream the ping prompt, returning when the first text chunk arrived.
+async fn ping_streaming(llm: &dyn LLM, timeout: Duration) -> Result<Option<Duration>, LLMError> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<StreamChunk>();
+    let cancel = CancellationToken::new();
+    let start = Instant::now();
+
+    let receive = async {
+        let mut first_chunk = None;
+        while let Some(chunk) = rx.recv().await {
+            if first_chunk.is_none() && !chunk.is_final && !chunk.text.is_empty() {
+                first_chunk = Some(start.elapsed());
+            }
+        }
+        first_chunk
+    };
+    let stream = async {
+        let result = tokio::time::timeout(
+            timeout,
+            llm.stream_complete(PING_SYSTEM_PROMPT, PING_PROMPT, tx, cancel.clone()),
+        )
+        .await;
+        match result {
+            Ok(result) => result,
+            Err(_) => {
+                cancel.cancel();
+                Err(LLMError::Network(format!(
+                    "no streamed reply within {}s",
+                    timeout.as_secs()
+                )))
+            }
+        }
+    };
+
+    let (result, first_chunk) = tokio::join!(stream, receive);
+    result.map(|_| first_chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_from_error() {
+        let api_error = |status| LLMError::ApiError {
+            status,
+            message: String::new(),
+        };
+        assert_eq!(
+            PingStatus::from_error(&LLMError::MissingApiKey),
+            PingStatus::AuthFailed
+        );
+        assert_eq!(
+            PingStatus::from_error(&api_error(401)),
+            PingStatus::AuthFailed
+        );
+        assert_eq!(PingStatus::from_error(&api_error(404)), PingStatus::Failed);
+        assert_eq!(
+            PingStatus::from_error(&LLMError::Network("refused".to_string())),
+            PingStatus::Unreachable
+        );
+    }
+}
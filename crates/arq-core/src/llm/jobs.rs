@@ -0,0 +1,172 @@
+//! Shared job queue for enrichment pipelines.
+//!
+//! Enrichment (summaries, plan risk, file overviews) makes many small LLM
+//! calls. Rather than each pipeline spawning its own tasks, they hand their
+//! work items to a [`JobQueue`], which bounds how many calls run at once,
+//! retries transient failures and reports progress:
+//!
+//! ```ignore
+//! use arq_core::llm::JobQueue;
+//!
+//! let queue = JobQueue::from_config(&config.enrichment)
+//!     .with_progress(|p| eprintln!("{}/{}", p.done, p.total));
+//! let summaries = queue
+//!     .run(files, |file| async move { summarize(&llm, &file).await })
+//!     .await;
+//! ```
+
+use futures::stream::{self, StreamExt};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::middleware::RetryPolicy;
+use super::{CancellationToken, LLMError};
+use crate::config::EnrichmentConfig;
+
+/// Progress of a queue run, reported after each job finishes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobProgress {
+    /// Index of the finished job in the input
+    pub index: usize,
+    /// Jobs finished so far, including this one
+    pub done: usize,
+    /// Jobs in the run
+    pub total: usize,
+    /// Error message if the job failed after its retries
+    pub error: Option<String>,
+}
+
+/// Runs LLM jobs with bounded concurrency, retries and progress reporting.
+#[derive(Clone)]
+pub struct JobQueue {
+    concurrency: usize,
+    retry: RetryPolicy,
+    cancel: CancellationToken,
+    progress: Option<Arc<dyn Fn(&JobProgress) + Send + Sync>>,
+}
+
+impl JobQueue {
+    /// A queue running at most `concurrency` jobs at once.
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            retry: RetryPolicy::default(),
+            cancel: CancellationToken::new(),
+            progress: None,
+        }
+    }
+
+    /// A queue with the concurrency and retries of `[enrichment]` config.
+    pub fn from_config(config: &EnrichmentConfig) -> Self {
+        Self::new(config.concurrency).with_retry(RetryPolicy {
+            max_retries: config.max_retries,
+            ..RetryPolicy::default()
+        })
+    }
+
+    /// Sets how jobs failing with a transient error are retried.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Stops starting jobs when `token` is cancelled; unstarted jobs fail
+    /// with [`LLMError::Cancelled`].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Reports every finished job to `progress`.
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&JobProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Runs `job` on every item, returning the results in input order.
+    ///
+    /// A failing job doesn't stop the others; its error takes its place in
+    /// the results.
+    pub async fn run<T, R, F, Fut>(&self, items: Vec<T>, job: F) -> Vec<Result<R, LLMError>>
+    where
+        T: Clone,
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<R, LLMError>>,
+    {
+        let total = items.len();
+        let done = AtomicUsize::new(0);
+        let job = &job;
+        let done = &done;
+
+        let mut results: Vec<(usize, Result<R, LLMError>)> =
+            stream::iter(items.into_iter().enumerate())
+                .map(|(index, item)| async move {
+                    let result = self.run_one(item, job).await;
+                    if let Some(ref progress) = self.progress {
+                        progress(&JobProgress {
+                            index,
+                            done: done.fetch_add(1, Ordering::SeqCst) + 1,
+                            total,
+                            error: result.as_ref().err().map(ToString::to_string),
+                        });
+                    }
+                    (index, result)
+                })
+                .buffer_unordered(self.concurrency)
+                .collect()
+                .await;
+
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Runs `job` on batches of up to `batch_size` items, for prompts that
+    /// enrich several small items in one call.
+    ///
+    /// Returns one result per batch, in input order.
+    pub async fn run_batched<T, R, F, Fut>(
+        &self,
+        items: Vec<T>,
+        batch_size: usize,
+        job: F,
+    ) -> Vec<Result<R, LLMError>>
+    where
+        T: Clone,
+        F: Fn(Vec<T>) -> Fut,
+        Fut: Future<Output = Result<R, LLMError>>,
+    {
+        let batches: Vec<Vec<T>> = items.chunks(batch_size.max(1)).map(<[T]>::to_vec).collect();
+        self.run(batches, job).await
+    }
+
+    /// Runs one job, retrying transient failures with backoff.
+    async fn run_one<T, R, F, Fut>(&self, item: T, job: &F) -> Result<R, LLMError>
+    where
+        T: Clone,
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<R, LLMError>>,
+    {
+        let mut retry = 0;
+        loop {
+            if self.cancel.is_cancelled() {
+                return Err(LLMError::Cancelled);
+            }
+            let error = match job(item.clone()).await {
+                Err(e) => e,
+                ok => return ok,
+            };
+            if retry >= self.retry.max_retries || !error.is_transient() {
+                return Err(error);
+            }
+            tokio::select! {
+                _ = self.cancel.cancelled() => return Err(LLMError::Cancelled),
+                _ = tokio::time::sleep(self.retry.backoff(retry)) => {}
+            }
+            retry += 1;
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Composable middleware around an [`LLM`].
+//!
+//! Cross-cutting behaviors wrap a client instead of living inside it, so
+//! they apply to every provider and stack in any order:
+//!
+//! ```ignore
+//! use arq_core::llm::{LLMStack, Provider, ResponseCache, RetryPolicy};
+//!
+//! let llm = LLMStack::new(Provider::build_from_config(&config.llm)?)
+//!     .with_cache(ResponseCache::new(".arq-cache"), "openai:gpt-4o")
+//!     .with_rate_limit(50)
+//!     .with_retry(RetryPolicy::default())
+//!     .with_logging(|call| eprintln!("{} in {:?}", call.method, call.elapsed))
+//!     .build();
+//! ```
+//!
+//! Each `with_*` call wraps the stack built so far, so the last layer added
+//! is the outermost: above, logging sees one call per request including its
+//! retries, every retry waits for the rate limit, and cache hits skip both.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex};
+
+use super::cache::{Cache, ResponseCache};
+use super::{CancellationToken, LLMError, StreamChunk, LLM};
+
+/// Builds an LLM client from a base client and middleware layers.
+pub struct LLMStack {
+    llm: Arc<dyn LLM>,
+}
+
+impl LLMStack {
+    /// Starts a stack from the client that sends the requests.
+    pub fn new(base: impl LLM + 'static) -> Self {
+        Self {
+            llm: Arc::new(base),
+        }
+    }
+
+    /// Answers repeated identical calls to `model` from `cache`.
+    pub fn with_cache(self, cache: ResponseCache, model: impl Into<String>) -> Self {
+        let model = model.into();
+        self.with_layer(|llm| Cache::new(llm, cache, model))
+    }
+
+    /// Retries transient failures with exponential backoff.
+    pub fn with_retry(self, policy: RetryPolicy) -> Self {
+        self.with_layer(|llm| Retry::new(llm, policy))
+    }
+
+    /// Spaces requests evenly to at most `requests_per_minute`.
+    pub fn with_rate_limit(self, requests_per_minute: u32) -> Self {
+        self.with_layer(|llm| RateLimit::new(llm, requests_per_minute))
+    }
+
+    /// Reports every call to `log` once it finishes.
+    pub fn with_logging(self, log: impl Fn(&LLMCall) + Send + Sync + 'static) -> Self {
+        self.with_layer(|llm| Logging::new(llm, log))
+    }
+
+    /// Wraps the stack in a custom middleware.
+    pub fn with_layer<M, F>(self, layer: F) -> Self
+    where
+        M: LLM + 'static,
+        F: FnOnce(Arc<dyn LLM>) -> M,
+    {
+        Self {
+            llm: Arc::new(layer(self.llm)),
+        }
+    }
+
+    /// Finishes the stack.
+    pub fn build(self) -> Arc<dyn LLM> {
+        self.llm
+    }
+}
+
+// ============================================================================
+// Retry
+// ============================================================================
+
+/// How failed requests are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each further retry
+    pub initial_backoff: Duration,
+    /// Upper bound on the wait between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (starting at 0).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+/// Retries requests that failed with a transient error
+/// (see [`LLMError::is_transient`]).
+///
+/// A stream is only retried if it failed before any text arrived, so the
+/// receiver never sees a response twice.
+pub struct Retry<L: LLM> {
+    inner: L,
+    policy: RetryPolicy,
+}
+
+impl<L: LLM> Retry<L> {
+    pub fn new(inner: L, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    /// Waits before retry number `retry`, or returns false if the caller
+    /// should give up with `error`.
+    async fn should_retry(
+        &self,
+        retry: u32,
+        error: &LLMError,
+        cancel: Option<&CancellationToken>,
+    ) -> bool {
+        if retry >= self.policy.max_retries || !error.is_transient() {
+            return false;
+        }
+        let backoff = tokio::time::sleep(self.policy.backoff(retry));
+        match cancel {
+            Some(cancel) => tokio::select! {
+                _ = cancel.cancelled() => false,
+                _ = backoff => true,
+            },
+            None => {
+                backoff.await;
+                true
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<L: LLM> LLM for Retry<L> {
+    async fn complete(&self, prompt: &str) -> Result<String, LLMError> {
+        let mut retry = 0;
+        loop {
+            let error = match self.inner.complete(prompt).await {
+                Err(e) => e,
+                ok => return ok,
+            };
+            if !self.should_retry(retry, &error, None).await {
+                return Err(error);
+            }
+            retry += 1;
+        }
+    }
+
+    async fn complete_with_system(&self, system: &str, prompt: &str) -> Result<String, LLMError> {
+        let mut retry = 0;
+        loop {
+            let error = match self.inner.complete_with_system(system, prompt).await {
+                Err(e) => e,
+                ok => return ok,
+            };
+            if !self.should_retry(retry, &error, None).await {
+                return Err(error);
+            }
+            retry += 1;
+        }
+    }
+
+    async fn stream_complete(
+        &self,
+        system: &str,
+        prompt: &str,
+        tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
+    ) -> Result<(), LLMError> {
+        let mut retry = 0;
+        loop {
+            // Forward chunks through a channel of our own to see whether any arrived
+            let (attempt_tx, mut attempt_rx) = mpsc::unbounded_channel::<StreamChunk>();
+            let forward = async {
+                let mut received = false;
+                while let Some(chunk) = attempt_rx.recv().await {
+                    received = true;
+                    let _ = tx.send(chunk);
+                }
+                received
+            };
+            let (result, received) = tokio::join!(
+                self.inner
+                    .stream_complete(system, prompt, attempt_tx, cancel.clone()),
+                forward
+            );
+
+            let error = match result {
+                Err(e) => e,
+                ok => return ok,
+            };
+            if received || !self.should_retry(retry, &error, Some(&cancel)).await {
+                return Err(error);
+            }
+            retry += 1;
+        }
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn evict(&self, system: Option<&str>, prompt: &str) {
+        self.inner.evict(system, prompt)
+    }
+}
+
+// ============================================================================
+// Rate limit
+// ============================================================================
+
+/// Spaces requests evenly so no more than a given number start per minute.
+pub struct RateLimit<L: LLM> {
+    inner: L,
+    interval: Duration,
+    /// Earliest time the next request may start
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl<L: LLM> RateLimit<L> {
+    /// Limits `inner` to `requests_per_minute`; zero disables the limit.
+    pub fn new(inner: L, requests_per_minute: u32) -> Self {
+        let interval = match requests_per_minute {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(60) / n,
+        };
+        Self {
+            inner,
+            interval,
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    /// Waits for the next free slot and reserves it.
+    async fn acquire(&self) {
+        let wait_until = {
+            let mut next_slot = self.next_slot.lock().await;
+            let now = Instant::now();
+            let start = next_slot.map_or(now, |slot| slot.max(now));
+            *next_slot = Some(start + self.interval);
+            start
+        };
+        tokio::time::sleep_until(wait_until.into()).await;
+    }
+}
+
+#[async_trait]
+impl<L: LLM> LLM for RateLimit<L> {
+    async fn complete(&self, prompt: &str) -> Result<String, LLMError> {
+        self.acquire().await;
+        self.inner.complete(prompt).await
+    }
+
+    async fn complete_with_system(&self, system: &str, prompt: &str) -> Result<String, LLMError> {
+        self.acquire().await;
+        self.inner.complete_with_system(system, prompt).await
+    }
+
+    async fn stream_complete(
+        &self,
+        system: &str,
+        prompt: &str,
+        tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
+    ) -> Result<(), LLMError> {
+        tokio::select! {
+            _ = cancel.cancelled() => return Err(LLMError::Cancelled),
+            _ = self.acquire() => {}
+        }
+        self.inner.stream_complete(system, prompt, tx, cancel).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn evict(&self, system: Option<&str>, prompt: &str) {
+        self.inner.evict(system, prompt)
+    }
+}
+
+// ============================================================================
+// Logging
+// ============================================================================
+
+/// A finished LLM call, as reported by [`Logging`].
+#[derive(Debug, Clone)]
+pub struct LLMCall {
+    /// Trait method called: `complete`, `complete_with_system` or `stream_complete`
+    pub method: &'static str,
+    /// Characters in the system and user prompts
+    pub prompt_chars: usize,
+    /// Characters in the response (streamed text for streams)
+    pub response_chars: usize,
+    /// Time from the request to its result
+    pub elapsed: Duration,
+    /// Error message if the call failed
+    pub error: Option<String>,
+}
+
+/// Reports every call to a callback, e.g. for audit logs or metrics.
+pub struct Logging<L: LLM> {
+    inner: L,
+    log: Box<dyn Fn(&LLMCall) + Send + Sync>,
+}
+
+impl<L: LLM> Logging<L> {
+    pub fn new(inner: L, log: impl Fn(&LLMCall) + Send + Sync + 'static) -> Self {
+        Self {
+            inner,
+            log: Box::new(log),
+        }
+    }
+
+    fn report<T>(
+        &self,
+        method: &'static str,
+        prompt_chars: usize,
+        response_chars: usize,
+        started: Instant,
+        result: &Result<T, LLMError>,
+    ) {
+        (self.log)(&LLMCall {
+            method,
+            prompt_chars,
+            response_chars,
+            elapsed: started.elapsed(),
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+    }
+}
+
+#[async_trait]
+impl<L: LLM> LLM for Logging<L> {
+    async fn complete(&self, prompt: &str) -> Result<String, LLMError> {
+        let started = Instant::now();
+        let result = self.inner.complete(prompt).await;
+        let response_chars = result.as_ref().map_or(0, |r| r.chars().count());
+        self.report(
+            "complete",
+            prompt.chars().count(),
+            response_chars,
+            started,
+            &result,
+        );
+        result
+    }
+
+    async fn complete_with_system(&self, system: &str, prompt: &str) -> Result<String, LLMError> {
+        let started = Instant::now();
+        let result = self.inner.complete_with_system(system, prompt).await;
+        let response_chars = result.as_ref().map_or(0, |r| r.chars().count());
+        self.report(
+            "complete_with_system",
+            system.chars().count() + prompt.chars().count(),
+            response_chars,
+            started,
+            &result,
+        );
+        result
+    }
+
+    async fn stream_complete(
+        &self,
+        system: &str,
+        prompt: &str,
+        tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
+    ) -> Result<(), LLMError> {
+        let started = Instant::now();
+        let (counted_tx, mut counted_rx) = mpsc::unbounded_channel::<StreamChunk>();
+        let forward = async {
+            let mut chars = 0;
+            while let Some(chunk) = counted_rx.recv().await {
+                chars += chunk.text.chars().count();
+                let _ = tx.send(chunk);
+            }
+            chars
+        };
+        let (result, response_chars) = tokio::join!(
+            self.inner
+                .stream_complete(system, prompt, counted_tx, cancel),
+            forward
+        );
+        self.report(
+            "stream_complete",
+            system.chars().count() + prompt.chars().count(),
+            response_chars,
+            started,
+            &result,
+        );
+        result
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn evict(&self, system: Option<&str>, prompt: &str) {
+        self.inner.evict(system, prompt)
+    }
+}
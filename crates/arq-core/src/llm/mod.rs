@@ -1,14 +1,29 @@
+mod cache;
 mod claude;
 mod error;
+pub mod headers;
+mod health;
+mod jobs;
+mod middleware;
+mod models;
 mod openai;
 mod provider;
+mod usage;
 
+pub use cache::{response_cache_key, Cache, CachedResponse, ResponseCache};
 pub use claude::ClaudeClient;
 pub use error::LLMError;
+pub use health::{ping_provider, PingReport, PingStatus};
+pub use jobs::{JobProgress, JobQueue};
+pub use middleware::{LLMCall, LLMStack, Logging, RateLimit, Retry, RetryPolicy};
+pub use models::{list_models, models_endpoint, parse_model_list};
 pub use openai::OpenAIClient;
 pub use provider::Provider;
+pub use tokio_util::sync::CancellationToken;
+pub use usage::{estimate_tokens, TokenUsage};
 
 use async_trait::async_trait;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
 /// A chunk of streamed response from an LLM.
@@ -78,6 +93,10 @@ pub trait LLM: Send + Sync {
     /// Sends chunks through the provided channel as they arrive.
     /// The final chunk will have `is_final: true`.
     ///
+    /// Cancelling `cancel` drops the in-flight request, closing the
+    /// connection so the provider stops generating, and returns
+    /// [`LLMError::Cancelled`].
+    ///
     /// Default implementation falls back to non-streaming and sends
     /// the entire response as a single chunk.
     async fn stream_complete(
@@ -85,9 +104,13 @@ pub trait LLM: Send + Sync {
         system: &str,
         prompt: &str,
         tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
     ) -> Result<(), LLMError> {
         // Default: fall back to non-streaming
-        let response = self.complete_with_system(system, prompt).await?;
+        let response = tokio::select! {
+            _ = cancel.cancelled() => return Err(LLMError::Cancelled),
+            response = self.complete_with_system(system, prompt) => response?,
+        };
         let _ = tx.send(StreamChunk::text(response));
         let _ = tx.send(StreamChunk::done());
         Ok(())
@@ -97,6 +120,12 @@ pub trait LLM: Send + Sync {
     fn supports_streaming(&self) -> bool {
         false
     }
+
+    /// Drop any cached response to this call (`system: None` for
+    /// [`LLM::complete`]), so the next identical call reaches the provider.
+    ///
+    /// Does nothing for clients without a response cache.
+    fn evict(&self, _system: Option<&str>, _prompt: &str) {}
 }
 
 /// Blanket implementation for boxed trait objects.
@@ -115,11 +144,46 @@ impl LLM for Box<dyn LLM> {
         system: &str,
         prompt: &str,
         tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
     ) -> Result<(), LLMError> {
-        (**self).stream_complete(system, prompt, tx).await
+        (**self).stream_complete(system, prompt, tx, cancel).await
     }
 
     fn supports_streaming(&self) -> bool {
         (**self).supports_streaming()
     }
+
+    fn evict(&self, system: Option<&str>, prompt: &str) {
+        (**self).evict(system, prompt)
+    }
+}
+
+/// Blanket implementation for shared trait objects, e.g. from [`LLMStack`].
+#[async_trait]
+impl LLM for Arc<dyn LLM> {
+    async fn complete(&self, prompt: &str) -> Result<String, LLMError> {
+        (**self).complete(prompt).await
+    }
+
+    async fn complete_with_system(&self, system: &str, prompt: &str) -> Result<String, LLMError> {
+        (**self).complete_with_system(system, prompt).await
+    }
+
+    async fn stream_complete(
+        &self,
+        system: &str,
+        prompt: &str,
+        tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
+    ) -> Result<(), LLMError> {
+        (**self).stream_complete(system, prompt, tx, cancel).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        (**self).supports_streaming()
+    }
+
+    fn evict(&self, system: Option<&str>, prompt: &str) {
+        (**self).evict(system, prompt)
+    }
 }
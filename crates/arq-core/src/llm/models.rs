@@ -0,0 +1,126 @@
+//! Model discovery through each provider's listing endpoint.
+
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::LLMError;
+use crate::config::{LLMConfig, DEFAULT_ANTHROPIC_API_VERSION, DEFAULT_OLLAMA_URL};
+
+/// Anthropic model listing endpoint.
+const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
+
+/// Fetch the models offered by the configured provider, sorted by name.
+///
+/// Uses `GET /models` for OpenAI-compatible providers (including OpenRouter),
+/// `GET /api/tags` for Ollama, and the Anthropic models API for Anthropic.
+pub async fn list_models(config: &LLMConfig) -> Result<Vec<String>, LLMError> {
+    let url = models_endpoint(config);
+    let mut req = Client::new().get(&url);
+
+    if let Some(key) = config.api_key_or_env().filter(|k| !k.is_empty()) {
+        req = match config.provider.as_str() {
+            "anthropic" | "claude" => req.header("x-api-key", key).header(
+                "anthropic-version",
+                config
+                    .api_version
+                    .as_deref()
+                    .unwrap_or(DEFAULT_ANTHROPIC_API_VERSION),
+            ),
+            _ => req.header("authorization", format!("Bearer {}", key)),
+        };
+    }
+    for (name, value) in config.request_headers() {
+        req = req.header(name, value);
+    }
+
+    let response = req.send().await?;
+    let status = response.status();
+
+    if status == 429 {
+        return Err(LLMError::RateLimited);
+    }
+
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(LLMError::ApiError {
+            status: status.as_u16(),
+            message: error_text,
+        });
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| LLMError::Network(format!("Failed to read response body: {}", e)))?;
+
+    parse_model_list(&config.provider, &body)
+}
+
+/// URL of the model listing endpoint for the configured provider.
+pub fn models_endpoint(config: &LLMConfig) -> String {
+    match config.provider.as_str() {
+        "anthropic" | "claude" => ANTHROPIC_MODELS_URL.to_string(),
+        "ollama" => {
+            // Ollama's native API lives beside the OpenAI-compatible `/v1` prefix
+            let host = config
+                .base_url
+                .clone()
+                .or_else(|| std::env::var("OLLAMA_HOST").ok())
+                .unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string());
+            let host = host.trim_end_matches('/');
+            format!("{}/api/tags", host.strip_suffix("/v1").unwrap_or(host))
+        }
+        _ => format!(
+            "{}/models",
+            config.base_url_or_default().trim_end_matches('/')
+        ),
+    }
+}
+
+/// Parse a model listing response into sorted, de-duplicated model names.
+pub fn parse_model_list(provider: &str, body: &str) -> Result<Vec<String>, LLMError> {
+    let parse_error = |e: serde_json::Error| {
+        let excerpt = match body.char_indices().nth(500) {
+            Some((end, _)) => &body[..end],
+            None => body,
+        };
+        LLMError::ParseError(format!(
+            "Failed to parse model list: {}. Response: {}",
+            e, excerpt
+        ))
+    };
+
+    let mut models: Vec<String> = if provider == "ollama" {
+        let tags: OllamaTags = serde_json::from_str(body).map_err(parse_error)?;
+        tags.models.into_iter().map(|m| m.name).collect()
+    } else {
+        let list: ModelList = serde_json::from_str(body).map_err(parse_error)?;
+        list.data.into_iter().map(|m| m.id).collect()
+    };
+
+    models.sort();
+    models.dedup();
+    Ok(models)
+}
+
+/// `{"data": [{"id": ...}]}` as returned by OpenAI, OpenRouter and Anthropic.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// `{"models": [{"name": ...}]}` as returned by Ollama.
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+}
@@ -4,7 +4,7 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
-use super::{LLMError, StreamChunk, LLM};
+use super::{CancellationToken, LLMError, StreamChunk, LLM};
 use crate::config::{
     DEFAULT_MAX_TOKENS, DEFAULT_OLLAMA_URL, DEFAULT_OPENAI_MODEL, DEFAULT_OPENAI_URL,
     DEFAULT_OPENROUTER_URL,
@@ -28,6 +28,10 @@ pub struct OpenAIClient {
     base_url: String,
     model: String,
     max_tokens: u32,
+    /// Sampling temperature; the API default when unset
+    temperature: Option<f32>,
+    /// Extra headers sent with every request
+    headers: Vec<(String, String)>,
     client: Client,
 }
 
@@ -48,6 +52,8 @@ impl OpenAIClient {
             api_key: api_key.into(),
             model: model.into(),
             max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: None,
+            headers: Vec::new(),
             client: Client::new(),
         }
     }
@@ -97,6 +103,23 @@ impl OpenAIClient {
         self
     }
 
+    /// Sets the sampling temperature (`None` keeps the API default).
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Adds headers sent with every request (e.g. organization or attribution).
+    pub fn with_headers(mut self, headers: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Scopes requests to an OpenAI organization and project.
+    pub fn with_organization(self, organization: &str, project: Option<&str>) -> Self {
+        self.with_headers(super::headers::openai_headers(Some(organization), project))
+    }
+
     async fn send_request(
         &self,
         messages: Vec<ChatMessage>,
@@ -119,6 +142,7 @@ impl OpenAIClient {
             model: self.model.clone(),
             messages: all_messages,
             max_tokens: Some(self.max_tokens),
+            temperature: self.temperature,
             stream: None,
         };
 
@@ -133,6 +157,9 @@ impl OpenAIClient {
         if !self.api_key.is_empty() {
             req = req.header("authorization", format!("Bearer {}", self.api_key));
         }
+        for (name, value) in &self.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
 
         let response = req.json(&request).send().await?;
 
@@ -182,6 +209,7 @@ impl OpenAIClient {
         messages: Vec<ChatMessage>,
         system: Option<&str>,
         tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
     ) -> Result<(), LLMError> {
         let mut all_messages = Vec::new();
 
@@ -200,6 +228,7 @@ impl OpenAIClient {
             model: self.model.clone(),
             messages: all_messages,
             max_tokens: Some(self.max_tokens),
+            temperature: self.temperature,
             stream: Some(true),
         };
 
@@ -214,8 +243,15 @@ impl OpenAIClient {
         if !self.api_key.is_empty() {
             req = req.header("authorization", format!("Bearer {}", self.api_key));
         }
+        for (name, value) in &self.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
 
-        let response = req.json(&request).send().await?;
+        // Dropping the request future on cancellation aborts the connection
+        let response = tokio::select! {
+            _ = cancel.cancelled() => return Err(LLMError::Cancelled),
+            response = req.json(&request).send() => response?,
+        };
 
         let status = response.status();
 
@@ -235,7 +271,14 @@ impl OpenAIClient {
         let mut stream = response.bytes_stream();
         let mut buffer = String::new();
 
-        while let Some(chunk_result) = stream.next().await {
+        loop {
+            let chunk_result = tokio::select! {
+                _ = cancel.cancelled() => return Err(LLMError::Cancelled),
+                next = stream.next() => match next {
+                    Some(chunk_result) => chunk_result,
+                    None => break,
+                },
+            };
             let chunk = chunk_result.map_err(|e| LLMError::Network(e.to_string()))?;
             let chunk_str = String::from_utf8_lossy(&chunk);
             buffer.push_str(&chunk_str);
@@ -285,6 +328,7 @@ impl LLM for OpenAIClient {
         system: &str,
         prompt: &str,
         tx: mpsc::UnboundedSender<StreamChunk>,
+        cancel: CancellationToken,
     ) -> Result<(), LLMError> {
         let messages = vec![ChatMessage {
             role: "user".to_string(),
@@ -292,7 +336,7 @@ impl LLM for OpenAIClient {
             ..Default::default()
         }];
 
-        self.send_streaming_request(messages, Some(system), tx)
+        self.send_streaming_request(messages, Some(system), tx, cancel)
             .await
     }
 
@@ -308,6 +352,8 @@ struct ChatRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
 }
 
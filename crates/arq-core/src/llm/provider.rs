@@ -1,8 +1,12 @@
-use super::{ClaudeClient, LLMError, OpenAIClient, LLM};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{ClaudeClient, LLMError, LLMStack, OpenAIClient, ResponseCache, LLM};
 use crate::config::{
-    LLMConfig, DEFAULT_ANTHROPIC_MODEL, DEFAULT_OLLAMA_MODEL, DEFAULT_OLLAMA_URL,
+    LLMConfig, StorageConfig, DEFAULT_ANTHROPIC_MODEL, DEFAULT_OLLAMA_MODEL, DEFAULT_OLLAMA_URL,
     DEFAULT_OPENAI_MODEL, DEFAULT_OPENAI_URL,
 };
+use crate::storage::PrivateFiles;
 
 /// LLM Provider configuration.
 #[derive(Debug, Clone)]
@@ -37,10 +41,13 @@ impl Default for Provider {
 
 impl Provider {
     /// Creates a provider from LLMConfig.
+    ///
+    /// The API key comes from config, the OS keyring or environment
+    /// (see [`LLMConfig::api_key_or_env`]).
     pub fn from_config(config: &LLMConfig) -> Self {
         match config.provider.as_str() {
             "anthropic" | "claude" => Provider::Anthropic {
-                api_key: config.api_key.clone(),
+                api_key: config.api_key_or_env(),
                 model: config.model.clone(),
             },
             "ollama" => Provider::Ollama {
@@ -52,14 +59,57 @@ impl Provider {
             },
             _ => Provider::OpenAI {
                 base_url: config.base_url.clone(),
-                api_key: config.api_key.clone(),
+                api_key: config.api_key_or_env(),
                 model: config.model.clone(),
             },
         }
     }
 
+    /// Creates an LLM client from config, sending the configured extra headers
+    /// and temperature.
+    pub fn build_from_config(config: &LLMConfig) -> Result<Box<dyn LLM>, LLMError> {
+        Self::from_config(config).build_with_options(config.request_headers(), config.temperature)
+    }
+
+    /// Creates an LLM client from config, answering repeated identical calls
+    /// from the project's response cache when `cache` is enabled.
+    pub fn build_cached(
+        config: &LLMConfig,
+        storage: &StorageConfig,
+    ) -> Result<Arc<dyn LLM>, LLMError> {
+        let llm = Self::build_from_config(config)?;
+        if !config.cache {
+            return Ok(Arc::from(llm));
+        }
+
+        let cache = ResponseCache::new(storage.llm_cache_path())
+            .with_ttl(Duration::from_secs(config.cache_ttl_secs))
+            .with_encryption(PrivateFiles::from_config(storage));
+        Ok(LLMStack::new(llm)
+            .with_cache(cache, cache_identity(config))
+            .build())
+    }
+
     /// Creates an LLM client from the provider configuration.
     pub fn build(self) -> Result<Box<dyn LLM>, LLMError> {
+        self.build_with_headers(Vec::new())
+    }
+
+    /// Creates an LLM client that sends `headers` with every request.
+    pub fn build_with_headers(
+        self,
+        headers: Vec<(String, String)>,
+    ) -> Result<Box<dyn LLM>, LLMError> {
+        self.build_with_options(headers, None)
+    }
+
+    /// Creates an LLM client that sends `headers` with every request and
+    /// samples at `temperature` (`None`: the provider's default).
+    pub fn build_with_options(
+        self,
+        headers: Vec<(String, String)>,
+        temperature: Option<f32>,
+    ) -> Result<Box<dyn LLM>, LLMError> {
         match self {
             Provider::OpenAI {
                 base_url,
@@ -81,7 +131,11 @@ impl Provider {
                     .or_else(|| std::env::var("OPENAI_MODEL").ok())
                     .unwrap_or_else(|| DEFAULT_OPENAI_MODEL.to_string());
 
-                Ok(Box::new(OpenAIClient::new(base, key, mdl)))
+                Ok(Box::new(
+                    OpenAIClient::new(base, key, mdl)
+                        .with_headers(headers)
+                        .with_temperature(temperature),
+                ))
             }
 
             Provider::Anthropic { api_key, model } => {
@@ -93,7 +147,12 @@ impl Provider {
                     .or_else(|| std::env::var("ANTHROPIC_MODEL").ok())
                     .unwrap_or_else(|| DEFAULT_ANTHROPIC_MODEL.to_string());
 
-                Ok(Box::new(ClaudeClient::new(key).with_model(mdl)))
+                Ok(Box::new(
+                    ClaudeClient::new(key)
+                        .with_model(mdl)
+                        .with_headers(headers)
+                        .with_temperature(temperature),
+                ))
             }
 
             Provider::Ollama { base_url, model } => {
@@ -102,7 +161,11 @@ impl Provider {
                     .map(|h| format!("{}/v1", h.trim_end_matches('/')))
                     .unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string());
 
-                Ok(Box::new(OpenAIClient::new(base, "", model)))
+                Ok(Box::new(
+                    OpenAIClient::new(base, "", model)
+                        .with_headers(headers)
+                        .with_temperature(temperature),
+                ))
             }
         }
     }
@@ -192,3 +255,21 @@ impl Provider {
         .build()
     }
 }
+
+/// What a cached response depends on besides the prompt: the provider, the
+/// model and every parameter sent with the request.
+fn cache_identity(config: &LLMConfig) -> String {
+    let mut identity = format!(
+        "{}:{} base_url={} api_version={:?} max_tokens={} temperature={:?}",
+        config.provider,
+        config.model_or_default(),
+        config.base_url_or_default(),
+        config.api_version,
+        config.max_tokens,
+        config.temperature,
+    );
+    for (name, value) in config.request_headers() {
+        identity.push_str(&format!(" {}={}", name, value));
+    }
+    identity
+}
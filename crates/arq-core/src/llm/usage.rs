@@ -0,0 +1,79 @@
+//! Token usage accounting and cost estimation.
+//!
+//! Providers are not asked for exact usage, so counts are estimated from text
+//! length (about four characters per token), which is close enough for a
+//! running session total.
+
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// Average characters per token for English text and code.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// USD price per million tokens: (model name prefix, input, output).
+///
+/// Matched by prefix, so dated model versions share a row.
+const MODEL_PRICING: &[(&str, f64, f64)] = &[
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+];
+
+/// Estimate the number of tokens in a piece of text.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Tokens sent to and received from an LLM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl TokenUsage {
+    /// Estimate usage for one call from its prompts and response.
+    pub fn estimate(system: &str, prompt: &str, response: &str) -> Self {
+        Self {
+            input_tokens: estimate_tokens(system) + estimate_tokens(prompt),
+            output_tokens: estimate_tokens(response),
+        }
+    }
+
+    /// Total tokens in both directions.
+    pub fn total(&self) -> usize {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// Estimated cost in USD for the given provider and model.
+    ///
+    /// Local providers are free; returns `None` for models without known pricing.
+    pub fn estimated_cost(&self, provider: &str, model: &str) -> Option<f64> {
+        if provider == "ollama" {
+            return Some(0.0);
+        }
+
+        // OpenRouter model names carry a vendor prefix (`anthropic/claude-...`)
+        let model = model.rsplit('/').next().unwrap_or(model);
+        let (_, input, output) = MODEL_PRICING
+            .iter()
+            .find(|(prefix, _, _)| model.starts_with(prefix))?;
+
+        Some((self.input_tokens as f64 * input + self.output_tokens as f64 * output) / 1_000_000.0)
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
@@ -4,7 +4,7 @@ use crate::phase::Phase;
 use crate::planning::Plan;
 use crate::research::ResearchDoc;
 use crate::storage::{Storage, StorageError};
-use crate::task::{Task, TaskSummary};
+use crate::task::{Task, TaskSummary, UsageRecord};
 
 /// Manages tasks and their lifecycle.
 ///
@@ -28,6 +28,24 @@ impl<S: Storage> TaskManager<S> {
         Ok(task)
     }
 
+    /// Creates a new task with an explicit name and persists it.
+    pub fn create_named_task(&mut self, prompt: &str, name: &str) -> Result<Task, ManagerError> {
+        let task = Task::with_name(prompt, name);
+        self.storage.save_task(&task)?;
+        self.storage.set_current_task_id(Some(&task.id))?;
+        Ok(task)
+    }
+
+    /// Renames a task and persists it.
+    pub fn rename_task(&mut self, id: &str, name: &str) -> Result<Task, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
+        let mut task = self.storage.load_task(id)?;
+        task.rename(name)
+            .map_err(|e| ManagerError::TaskError(e.to_string()))?;
+        self.storage.save_task(&task)?;
+        Ok(task)
+    }
+
     /// Gets a task by ID.
     pub fn get_task(&self, id: &str) -> Result<Task, ManagerError> {
         Ok(self.storage.load_task(id)?)
@@ -60,6 +78,31 @@ impl<S: Storage> TaskManager<S> {
         Ok(self.storage.list_tasks()?)
     }
 
+    /// Finds tasks by ID prefix or by a case-insensitive substring of
+    /// their name, ID matches first.
+    ///
+    /// A full task ID matches only that task.
+    pub fn find_tasks(&self, query: &str) -> Result<Vec<TaskSummary>, ManagerError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tasks = self.list_tasks()?;
+        if let Some(task) = tasks.iter().find(|t| t.id == query) {
+            return Ok(vec![task.clone()]);
+        }
+
+        let needle = query.to_lowercase();
+        let (mut matching, rest): (Vec<_>, Vec<_>) =
+            tasks.into_iter().partition(|t| t.id.starts_with(query));
+        matching.extend(
+            rest.into_iter()
+                .filter(|t| t.name.to_lowercase().contains(&needle)),
+        );
+        Ok(matching)
+    }
+
     /// Deletes a task by ID.
     pub fn delete_task(&mut self, id: &str) -> Result<(), ManagerError> {
         self.storage.delete_task(id)?;
@@ -72,6 +115,7 @@ impl<S: Storage> TaskManager<S> {
         task_id: &str,
         doc: ResearchDoc,
     ) -> Result<Task, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
         let mut task = self.storage.load_task(task_id)?;
         task.set_research_doc(doc.clone())
             .map_err(|e| ManagerError::TaskError(e.to_string()))?;
@@ -80,18 +124,86 @@ impl<S: Storage> TaskManager<S> {
         Ok(task)
     }
 
+    /// Records LLM usage for a task and persists it.
+    pub fn record_usage(
+        &mut self,
+        task_id: &str,
+        record: UsageRecord,
+    ) -> Result<Task, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
+        let mut task = self.storage.load_task(task_id)?;
+        task.record_usage(record);
+        self.storage.save_task(&task)?;
+        Ok(task)
+    }
+
+    /// Sets the files kept out of a task's research context and persists them.
+    pub fn set_excluded_context(
+        &mut self,
+        task_id: &str,
+        files: Vec<String>,
+    ) -> Result<Task, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
+        let mut task = self.storage.load_task(task_id)?;
+        task.set_excluded_context(files);
+        self.storage.save_task(&task)?;
+        Ok(task)
+    }
+
+    /// Adds labels to a task and persists it.
+    pub fn add_labels(&mut self, task_id: &str, labels: &[String]) -> Result<Task, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
+        let mut task = self.storage.load_task(task_id)?;
+        task.add_labels(labels);
+        self.storage.save_task(&task)?;
+        Ok(task)
+    }
+
+    /// Removes labels from a task and persists it.
+    pub fn remove_labels(
+        &mut self,
+        task_id: &str,
+        labels: &[String],
+    ) -> Result<Task, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
+        let mut task = self.storage.load_task(task_id)?;
+        task.remove_labels(labels);
+        self.storage.save_task(&task)?;
+        Ok(task)
+    }
+
+    /// Lists the tasks having `label`, compared case-insensitively.
+    pub fn list_tasks_with_label(&self, label: &str) -> Result<Vec<TaskSummary>, ManagerError> {
+        Ok(self
+            .list_tasks()?
+            .into_iter()
+            .filter(|t| t.has_label(label))
+            .collect())
+    }
+
+    /// Loads every stored task, most recently updated first.
+    pub fn load_all_tasks(&self) -> Result<Vec<Task>, ManagerError> {
+        self.list_tasks()?
+            .iter()
+            .map(|summary| self.get_task(&summary.id))
+            .collect()
+    }
+
     /// Sets the plan for a task and persists it.
     pub fn set_plan(&mut self, task_id: &str, plan: Plan) -> Result<Task, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
         let mut task = self.storage.load_task(task_id)?;
         task.set_plan(plan.clone())
             .map_err(|e| ManagerError::TaskError(e.to_string()))?;
-        self.storage.save_task(&task)?;
+        // The plan is validated on save, before the task refers to it
         self.storage.save_plan(task_id, &plan)?;
+        self.storage.save_task(&task)?;
         Ok(task)
     }
 
     /// Advances a task to the next phase.
     pub fn advance_phase(&mut self, task_id: &str) -> Result<Phase, ManagerError> {
+        let _guard = self.storage.lock_writes()?;
         let mut task = self.storage.load_task(task_id)?;
 
         if !task.can_advance() {
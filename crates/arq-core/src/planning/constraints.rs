@@ -0,0 +1,84 @@
+//! Project constraints on generated plans (see [`PlanningConfig`]).
+//!
+//! Protected paths and the step budget are checked against every generated
+//! plan; conventions can only be stated to the planner, not verified.
+
+use std::fmt;
+
+use crate::config::PlanningConfig;
+use crate::knowledge::indexer::matches_pattern;
+use crate::planning::plan::Plan;
+
+/// A way a plan breaks the configured constraints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    /// The plan creates or modifies a protected path.
+    ProtectedPath { path: String, pattern: String },
+    /// The plan has more steps than the budget allows.
+    TooManySteps { steps: usize, max: usize },
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProtectedPath { path, pattern } => {
+                write!(f, "{} is protected (matches '{}')", path, pattern)
+            }
+            Self::TooManySteps { steps, max } => {
+                write!(f, "{} steps exceed the budget of {}", steps, max)
+            }
+        }
+    }
+}
+
+/// Check a plan against the configured constraints.
+///
+/// Returns every violation found, in plan order; empty if the plan complies.
+pub fn check_constraints(plan: &Plan, config: &PlanningConfig) -> Vec<ConstraintViolation> {
+    let paths = plan
+        .files_to_create
+        .iter()
+        .map(|f| f.path.as_str())
+        .chain(plan.files_to_modify.iter().map(|f| f.path.as_str()));
+
+    let mut violations: Vec<ConstraintViolation> = paths
+        .filter_map(|path| {
+            config
+                .protected_paths
+                .iter()
+                .find(|pattern| matches_pattern(pattern, path))
+                .map(|pattern| ConstraintViolation::ProtectedPath {
+                    path: path.to_string(),
+                    pattern: pattern.clone(),
+                })
+        })
+        .collect();
+
+    let steps = plan.total_files_affected();
+    if let Some(max) = config.max_steps.filter(|&max| steps > max) {
+        violations.push(ConstraintViolation::TooManySteps { steps, max });
+    }
+    violations
+}
+
+/// The constraints as a prompt section, or `None` if none are configured.
+pub fn format_constraints(config: &PlanningConfig) -> Option<String> {
+    if config.is_empty() {
+        return None;
+    }
+
+    let mut rules = Vec::new();
+    for pattern in &config.protected_paths {
+        rules.push(format!(
+            "- Never create or modify files matching `{}`",
+            pattern
+        ));
+    }
+    for convention in &config.conventions {
+        rules.push(format!("- {}", convention.trim()));
+    }
+    if let Some(max) = config.max_steps {
+        rules.push(format!("- Create or modify at most {} files in total", max));
+    }
+    Some(rules.join("\n"))
+}
@@ -1,3 +1,14 @@
+pub mod constraints;
 mod plan;
+pub mod prompts;
+mod runner;
+pub mod scaffold;
+mod schema;
 
-pub use plan::{Complexity, FileModification, FileSpec, FunctionSignature, Plan};
+pub use constraints::{check_constraints, ConstraintViolation};
+pub use plan::{Complexity, FileModification, FileSpec, FunctionSignature, Plan, PlanStep, Risk};
+pub use runner::{
+    parse_plan, PlanningError, PlanningMessage, PlanningProgress, PlanningRole, PlanningRunner,
+};
+pub use scaffold::{scaffold_tests, TestConventions, TestFramework, TestStub};
+pub use schema::{PlanSchemaError, SchemaIssue, MISSING_APPROACH, PLAN_SCHEMA_VERSION};
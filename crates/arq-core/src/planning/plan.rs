@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
 
+use super::schema::{self, PlanSchemaError, LEGACY_PLAN_SCHEMA_VERSION, PLAN_SCHEMA_VERSION};
+
 /// The output of the Planning phase.
 ///
 /// A specification that defines exactly what the Agent phase will implement.
 /// This serves as a contract between user approval and code generation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Plan {
+    /// Version of the plan schema (see [`PLAN_SCHEMA_VERSION`])
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
     /// Name of the task this plan is for
     pub task_name: String,
     /// The selected approach (from options presented to user)
@@ -18,18 +23,35 @@ pub struct Plan {
     pub files_to_modify: Vec<FileModification>,
     /// Dependencies to add (package names)
     pub dependencies_to_add: Vec<String>,
+    /// Ordered implementation steps
+    #[serde(default)]
+    pub steps: Vec<PlanStep>,
+    /// Conditions the finished change must meet
+    #[serde(default)]
+    pub acceptance_criteria: Vec<String>,
+    /// What could go wrong, and how to mitigate it
+    #[serde(default)]
+    pub risks: Vec<Risk>,
+}
+
+fn legacy_schema_version() -> u32 {
+    LEGACY_PLAN_SCHEMA_VERSION
 }
 
 impl Plan {
     /// Creates a new empty plan for a task.
     pub fn new(task_name: impl Into<String>, approach: impl Into<String>) -> Self {
         Self {
+            schema_version: PLAN_SCHEMA_VERSION,
             task_name: task_name.into(),
             approach: approach.into(),
             complexity: Complexity::Medium,
             files_to_create: Vec::new(),
             files_to_modify: Vec::new(),
             dependencies_to_add: Vec::new(),
+            steps: Vec::new(),
+            acceptance_criteria: Vec::new(),
+            risks: Vec::new(),
         }
     }
 
@@ -38,9 +60,34 @@ impl Plan {
         serde_yaml::to_string(self)
     }
 
-    /// Parses a plan from YAML format.
-    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml::Error> {
-        serde_yaml::from_str(yaml)
+    /// Parses a plan from YAML format, upgrading older schema versions and
+    /// validating the result.
+    pub fn from_yaml(yaml: &str) -> Result<Self, PlanSchemaError> {
+        let mut plan: Plan = serde_yaml::from_str(yaml)?;
+        plan.upgrade()?;
+        plan.validate()?;
+        Ok(plan)
+    }
+
+    /// Upgrades the plan to [`PLAN_SCHEMA_VERSION`]. Version 1 plans get a
+    /// step per file and a plan without an approach gets
+    /// [`MISSING_APPROACH`](schema::MISSING_APPROACH); plans newer than this
+    /// version of Arq are rejected.
+    pub fn upgrade(&mut self) -> Result<(), PlanSchemaError> {
+        schema::upgrade(self)
+    }
+
+    /// Checks that the plan is complete and its steps are consistent:
+    /// unique step IDs, dependencies on existing steps and no cycles.
+    pub fn validate(&self) -> Result<(), PlanSchemaError> {
+        schema::validate(self)
+    }
+
+    /// Adds a step per file when the plan has no steps.
+    pub fn ensure_steps(&mut self) {
+        if self.steps.is_empty() {
+            self.steps = schema::steps_from_files(self);
+        }
     }
 
     /// Returns the total number of files affected by this plan.
@@ -49,6 +96,31 @@ impl Plan {
     }
 }
 
+/// A step of the implementation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Identifier other steps refer to in `depends_on`
+    pub id: String,
+    /// What the step does
+    pub description: String,
+    /// Files the step creates or changes
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Steps that must be done first
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// A risk of the plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Risk {
+    /// What could go wrong
+    pub description: String,
+    /// How the plan guards against it
+    #[serde(default)]
+    pub mitigation: Option<String>,
+}
+
 /// Complexity rating for a plan.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -59,6 +131,15 @@ pub enum Complexity {
 }
 
 impl Complexity {
+    /// Parse a complexity name; unknown names fall back to [`Complexity::Medium`].
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "low" => Complexity::Low,
+            "high" => Complexity::High,
+            _ => Complexity::Medium,
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Complexity::Low => "Low",
@@ -76,6 +157,7 @@ pub struct FileSpec {
     /// Description of the file's purpose
     pub description: String,
     /// Functions/exports this file will contain
+    #[serde(default)]
     pub exports: Vec<FunctionSignature>,
 }
 
@@ -89,8 +171,10 @@ pub struct FileModification {
     /// Description of what changes will be made
     pub description: String,
     /// Code to add (imports, function calls, etc.)
+    #[serde(default)]
     pub additions: Vec<String>,
     /// Code to remove (if any)
+    #[serde(default)]
     pub removals: Vec<String>,
 }
 
@@ -102,5 +186,6 @@ pub struct FunctionSignature {
     /// Full signature (parameters and return type)
     pub signature: String,
     /// Description of behavior
+    #[serde(default)]
     pub behavior: Vec<String>,
 }
@@ -0,0 +1,92 @@
+use crate::config::{
+    PlanningConfig, DEFAULT_PLANNING_CHAT_SYSTEM_PROMPT, DEFAULT_PLANNING_SYSTEM_PROMPT,
+};
+use crate::research::ResearchDoc;
+
+use super::constraints::format_constraints;
+use super::runner::{PlanningMessage, PlanningRole};
+
+/// System prompt for planner chat turns.
+pub const PLANNING_CHAT_SYSTEM_PROMPT: &str = DEFAULT_PLANNING_CHAT_SYSTEM_PROMPT;
+
+/// System prompt for generating the structured plan.
+pub const PLANNING_SYSTEM_PROMPT: &str = DEFAULT_PLANNING_SYSTEM_PROMPT;
+
+/// Builds the user prompt for a planner chat turn.
+///
+/// The approved research and project constraints are pinned ahead of the
+/// conversation so every turn sees them, however long it grows.
+pub fn build_planning_chat_prompt(
+    research: &ResearchDoc,
+    constraints: &PlanningConfig,
+    history: &[PlanningMessage],
+    message: &str,
+) -> String {
+    format!(
+        r#"## Approved Research
+
+{research}
+{constraints}
+## Planning Conversation
+
+{history}
+
+## Developer's Message
+
+{message}"#,
+        research = research.to_markdown(),
+        constraints = constraints_section(constraints),
+        history = format_history(history),
+    )
+}
+
+/// Builds the user prompt asking for the structured plan.
+pub fn build_plan_prompt(
+    research: &ResearchDoc,
+    constraints: &PlanningConfig,
+    history: &[PlanningMessage],
+) -> String {
+    format!(
+        r#"## Approved Research
+
+{research}
+{constraints}
+## Planning Conversation
+
+{history}
+
+Produce the implementation plan for this task, following the decisions made in the conversation above."#,
+        research = research.to_markdown(),
+        constraints = constraints_section(constraints),
+        history = format_history(history),
+    )
+}
+
+/// The project constraints section, or a blank line if there are none.
+fn constraints_section(constraints: &PlanningConfig) -> String {
+    match format_constraints(constraints) {
+        Some(rules) => format!(
+            "\n## Project Constraints\n\nThese are non-negotiable; if the developer asks for something that breaks them, say so.\n\n{}\n\n",
+            rules
+        ),
+        None => "\n".to_string(),
+    }
+}
+
+/// Renders the conversation so far, oldest message first.
+fn format_history(history: &[PlanningMessage]) -> String {
+    if history.is_empty() {
+        return "(no messages yet)".to_string();
+    }
+    history
+        .iter()
+        .map(|message| {
+            let speaker = match message.role {
+                PlanningRole::User => "Developer",
+                PlanningRole::Assistant => "Architect",
+            };
+            format!("**{}:** {}", speaker, message.content.trim())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
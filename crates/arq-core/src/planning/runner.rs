@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use crate::config::PlanningConfig;
+use crate::llm::{CancellationToken, LLMError, StreamChunk, TokenUsage, LLM};
+use crate::planning::constraints::{check_constraints, ConstraintViolation};
+use crate::planning::plan::{Complexity, FileModification, FileSpec, Plan, PlanStep, Risk};
+use crate::planning::prompts::{
+    build_plan_prompt, build_planning_chat_prompt, PLANNING_CHAT_SYSTEM_PROMPT,
+    PLANNING_SYSTEM_PROMPT,
+};
+use crate::planning::schema::PlanSchemaError;
+use crate::prompts::localize;
+use crate::research::{extract_json, ResearchDoc};
+
+/// Progress events during a planning run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PlanningProgress {
+    /// The run has started
+    Started,
+    /// Calling LLM
+    CallingLLM,
+    /// Estimated tokens used by the LLM call
+    TokenUsage(TokenUsage),
+    /// Parsing the generated plan
+    ParsingResponse,
+    /// The run completed successfully
+    Complete,
+}
+
+/// Who sent a message in the planning conversation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanningRole {
+    User,
+    Assistant,
+}
+
+/// A message in the planning conversation.
+#[derive(Debug, Clone)]
+pub struct PlanningMessage {
+    pub role: PlanningRole,
+    pub content: String,
+}
+
+impl PlanningMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: PlanningRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: PlanningRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Runs the planning phase for a task, with its approved research pinned.
+pub struct PlanningRunner<L: LLM> {
+    llm: L,
+    research: ResearchDoc,
+    constraints: PlanningConfig,
+    chat_system_prompt: String,
+    output_language: Option<String>,
+    refresh: bool,
+    cancel: CancellationToken,
+}
+
+impl<L: LLM> PlanningRunner<L> {
+    /// Creates a new planning runner for the approved research.
+    pub fn new(llm: L, research: ResearchDoc) -> Self {
+        Self {
+            llm,
+            research,
+            constraints: PlanningConfig::default(),
+            chat_system_prompt: PLANNING_CHAT_SYSTEM_PROMPT.to_string(),
+            output_language: None,
+            refresh: false,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Gives the planner project constraints and rejects generated plans
+    /// that break them.
+    pub fn with_constraints(mut self, constraints: PlanningConfig) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Replaces the system prompt of the planning conversation, e.g. with the
+    /// planning persona from [`crate::prompts::for_phase`].
+    ///
+    /// Plan generation keeps its built-in prompt, which defines the plan format.
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.chat_system_prompt = prompt.into();
+        self
+    }
+
+    /// Has the model write replies and the plan's prose in `language`,
+    /// keeping identifiers, paths and the plan format as they are.
+    pub fn with_output_language(mut self, language: Option<String>) -> Self {
+        self.output_language = language;
+        self
+    }
+
+    /// Asks the model for a fresh plan instead of a cached response, e.g.
+    /// when the developer regenerates a plan they didn't approve.
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Stops the LLM call, and the request billing, when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Non-streaming completion that is dropped when the run is cancelled.
+    async fn complete(&self, system: &str, prompt: &str) -> Result<String, LLMError> {
+        tokio::select! {
+            _ = self.cancel.cancelled() => Err(LLMError::Cancelled),
+            response = self.llm.complete_with_system(system, prompt) => response,
+        }
+    }
+
+    /// Replies to a developer message about the plan structure.
+    ///
+    /// `history` holds the earlier messages of the conversation. The reply is
+    /// streamed through `stream_tx` as it arrives and returned in full.
+    pub async fn chat_streaming(
+        &self,
+        history: &[PlanningMessage],
+        message: &str,
+        progress_tx: mpsc::UnboundedSender<PlanningProgress>,
+        stream_tx: mpsc::UnboundedSender<StreamChunk>,
+    ) -> Result<String, PlanningError> {
+        let _ = progress_tx.send(PlanningProgress::Started);
+        let system_prompt = localize(&self.chat_system_prompt, self.output_language.as_deref());
+        let prompt =
+            build_planning_chat_prompt(&self.research, &self.constraints, history, message);
+
+        let _ = progress_tx.send(PlanningProgress::CallingLLM);
+        let response = if self.llm.supports_streaming() {
+            // Collect chunks while forwarding them to stream_tx
+            let (collector_tx, mut collector_rx) = mpsc::unbounded_channel::<StreamChunk>();
+            let collect_handle = tokio::spawn(async move {
+                let mut full_response = String::new();
+                while let Some(chunk) = collector_rx.recv().await {
+                    if !chunk.is_final {
+                        full_response.push_str(&chunk.text);
+                    }
+                    let _ = stream_tx.send(chunk);
+                }
+                full_response
+            });
+
+            self.llm
+                .stream_complete(&system_prompt, &prompt, collector_tx, self.cancel.clone())
+                .await?;
+            collect_handle.await.unwrap_or_default()
+        } else {
+            let response = self.complete(&system_prompt, &prompt).await?;
+            let _ = stream_tx.send(StreamChunk::text(response.clone()));
+            let _ = stream_tx.send(StreamChunk::done());
+            response
+        };
+
+        let _ = progress_tx.send(PlanningProgress::TokenUsage(TokenUsage::estimate(
+            &system_prompt,
+            &prompt,
+            &response,
+        )));
+        let _ = progress_tx.send(PlanningProgress::Complete);
+        Ok(response)
+    }
+
+    /// Generates the structured plan from the research and the conversation.
+    ///
+    /// Fails with [`PlanningError::ConstraintViolation`] if the plan breaks
+    /// the project constraints.
+    pub async fn generate_plan(
+        &self,
+        task_name: &str,
+        history: &[PlanningMessage],
+        progress_tx: mpsc::UnboundedSender<PlanningProgress>,
+    ) -> Result<Plan, PlanningError> {
+        let _ = progress_tx.send(PlanningProgress::Started);
+        let prompt = build_plan_prompt(&self.research, &self.constraints, history);
+
+        let _ = progress_tx.send(PlanningProgress::CallingLLM);
+        let system_prompt = localize(PLANNING_SYSTEM_PROMPT, self.output_language.as_deref());
+        if self.refresh {
+            self.llm.evict(Some(&system_prompt), &prompt);
+        }
+        let response = self.complete(&system_prompt, &prompt).await?;
+        let _ = progress_tx.send(PlanningProgress::TokenUsage(TokenUsage::estimate(
+            &system_prompt,
+            &prompt,
+            &response,
+        )));
+
+        let _ = progress_tx.send(PlanningProgress::ParsingResponse);
+        // A rejected response must not be served again from the cache
+        let plan = parse_plan(task_name, &response).inspect_err(|_| {
+            self.llm.evict(Some(&system_prompt), &prompt);
+        })?;
+        let violations = check_constraints(&plan, &self.constraints);
+        if !violations.is_empty() {
+            self.llm.evict(Some(&system_prompt), &prompt);
+            return Err(PlanningError::ConstraintViolation(violations));
+        }
+
+        let _ = progress_tx.send(PlanningProgress::Complete);
+        Ok(plan)
+    }
+}
+
+/// Parses an LLM response into a plan for the named task.
+///
+/// The response may be wrapped in a markdown code block or surrounded by
+/// text; missing lists are treated as empty, and a plan without steps gets
+/// one per file. The plan must pass schema validation.
+pub fn parse_plan(task_name: &str, response: &str) -> Result<Plan, PlanningError> {
+    let json_str = extract_json(response);
+    let parsed: PlanResponse = serde_json::from_str(json_str).map_err(|e| {
+        let excerpt = match json_str.char_indices().nth(500) {
+            Some((end, _)) => &json_str[..end],
+            None => json_str,
+        };
+        PlanningError::ParseError(format!(
+            "Failed to parse plan as JSON: {}. Response: {}",
+            e, excerpt
+        ))
+    })?;
+
+    let mut plan = Plan::new(task_name, parsed.approach);
+    plan.complexity = parsed
+        .complexity
+        .as_deref()
+        .map(Complexity::parse)
+        .unwrap_or(Complexity::Medium);
+    plan.files_to_create = parsed.files_to_create;
+    plan.files_to_modify = parsed.files_to_modify;
+    plan.dependencies_to_add = parsed.dependencies_to_add;
+    plan.steps = parsed.steps;
+    plan.acceptance_criteria = parsed.acceptance_criteria;
+    plan.risks = parsed.risks;
+    plan.ensure_steps();
+    plan.validate()?;
+    Ok(plan)
+}
+
+/// Plan structure from LLM.
+#[derive(Debug, serde::Deserialize)]
+struct PlanResponse {
+    approach: String,
+    // Parsed leniently so an unexpected value doesn't fail the whole plan
+    #[serde(default)]
+    complexity: Option<String>,
+    #[serde(default)]
+    files_to_create: Vec<FileSpec>,
+    #[serde(default)]
+    files_to_modify: Vec<FileModification>,
+    #[serde(default)]
+    dependencies_to_add: Vec<String>,
+    #[serde(default)]
+    steps: Vec<PlanStep>,
+    #[serde(default)]
+    acceptance_criteria: Vec<String>,
+    #[serde(default)]
+    risks: Vec<Risk>,
+}
+
+/// Errors that can occur during planning.
+#[derive(Debug, Error)]
+pub enum PlanningError {
+    #[error("LLM error: {0}")]
+    LLM(#[from] LLMError),
+
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
+    #[error(transparent)]
+    Schema(#[from] PlanSchemaError),
+
+    #[error("Plan violates project constraints: {}", format_violations(.0))]
+    ConstraintViolation(Vec<ConstraintViolation>),
+}
+
+fn format_violations(violations: &[ConstraintViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
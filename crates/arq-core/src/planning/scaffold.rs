@@ -0,0 +1,502 @@
+//! Failing test stubs drafted from a plan.
+//!
+//! Each plan step that touches source code gets a test file following the
+//! project's existing conventions: the framework declared in its manifests
+//! and the layout of test files already in the knowledge graph.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+use super::{FunctionSignature, Plan};
+
+/// A test framework detected from project manifests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestFramework {
+    /// Rust `#[test]` functions run by `cargo test`
+    Cargo,
+    Pytest,
+    Unittest,
+    /// Go `testing` package
+    Go,
+    Jest,
+    Vitest,
+    Mocha,
+    JUnit,
+    XUnit,
+    NUnit,
+}
+
+impl TestFramework {
+    /// Source file extensions this framework tests.
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo => &["rs"],
+            Self::Pytest | Self::Unittest => &["py"],
+            Self::Go => &["go"],
+            Self::Jest | Self::Vitest | Self::Mocha => &["ts", "tsx", "js", "jsx", "mjs"],
+            Self::JUnit => &["java"],
+            Self::XUnit | Self::NUnit => &["cs"],
+        }
+    }
+
+    /// Framework used for a language when its manifests name none.
+    fn fallback(extension: &str) -> Option<Self> {
+        match extension {
+            "rs" => Some(Self::Cargo),
+            "py" => Some(Self::Pytest),
+            "go" => Some(Self::Go),
+            "ts" | "tsx" | "js" | "jsx" | "mjs" => Some(Self::Jest),
+            "java" => Some(Self::JUnit),
+            "cs" => Some(Self::XUnit),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo test",
+            Self::Pytest => "pytest",
+            Self::Unittest => "unittest",
+            Self::Go => "go test",
+            Self::Jest => "jest",
+            Self::Vitest => "vitest",
+            Self::Mocha => "mocha",
+            Self::JUnit => "junit",
+            Self::XUnit => "xunit",
+            Self::NUnit => "nunit",
+        }
+    }
+}
+
+/// How the project writes tests, used to place and shape the stubs.
+#[derive(Debug, Clone, Default)]
+pub struct TestConventions {
+    /// Frameworks declared in the project's manifests
+    pub frameworks: Vec<TestFramework>,
+    /// Existing test files, relative to the project root
+    pub test_files: Vec<String>,
+}
+
+impl TestConventions {
+    /// Detect conventions from manifests under `root` and the indexed file list.
+    pub fn detect(root: &Path, indexed_files: &[String]) -> Self {
+        let read = |name: &str| std::fs::read_to_string(root.join(name)).unwrap_or_default();
+        let mut frameworks = Vec::new();
+
+        if root.join("Cargo.toml").exists() {
+            frameworks.push(TestFramework::Cargo);
+        }
+
+        let package_json = read("package.json");
+        for (dependency, framework) in [
+            ("\"vitest\"", TestFramework::Vitest),
+            ("\"jest\"", TestFramework::Jest),
+            ("\"mocha\"", TestFramework::Mocha),
+        ] {
+            if package_json.contains(dependency) {
+                frameworks.push(framework);
+                break;
+            }
+        }
+
+        let python = [
+            "pyproject.toml",
+            "setup.cfg",
+            "requirements.txt",
+            "requirements-dev.txt",
+        ]
+        .iter()
+        .map(|name| read(name))
+        .collect::<String>();
+        if python.contains("pytest") {
+            frameworks.push(TestFramework::Pytest);
+        } else if !python.is_empty() {
+            frameworks.push(TestFramework::Unittest);
+        }
+
+        if root.join("go.mod").exists() {
+            frameworks.push(TestFramework::Go);
+        }
+
+        let java = read("pom.xml") + &read("build.gradle") + &read("build.gradle.kts");
+        if java.contains("junit") {
+            frameworks.push(TestFramework::JUnit);
+        }
+
+        let dotnet = indexed_files
+            .iter()
+            .filter(|f| f.ends_with(".csproj"))
+            .map(|f| read(f))
+            .collect::<String>();
+        if dotnet.contains("NUnit") {
+            frameworks.push(TestFramework::NUnit);
+        } else if dotnet.contains("xunit") {
+            frameworks.push(TestFramework::XUnit);
+        }
+
+        Self {
+            frameworks,
+            test_files: indexed_files
+                .iter()
+                .filter(|f| is_test_path(f))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Framework for a source file: the declared one, or the language default.
+    pub fn framework_for(&self, path: &str) -> Option<TestFramework> {
+        let extension = extension(path);
+        self.frameworks
+            .iter()
+            .copied()
+            .find(|f| f.extensions().contains(&extension))
+            .or_else(|| TestFramework::fallback(extension))
+    }
+
+    /// Whether existing test files sit under a directory with this name.
+    fn has_test_dir(&self, dir: &str) -> bool {
+        self.test_files
+            .iter()
+            .any(|f| f.split('/').any(|component| component == dir))
+    }
+
+    /// Whether existing JS/TS tests use `.spec.` rather than `.test.`.
+    fn prefers_spec(&self) -> bool {
+        let spec = self
+            .test_files
+            .iter()
+            .filter(|f| f.contains(".spec."))
+            .count();
+        let test = self
+            .test_files
+            .iter()
+            .filter(|f| f.contains(".test."))
+            .count();
+        spec > test
+    }
+
+    /// Path of the test file for a source file, following the conventions.
+    pub fn test_path_for(&self, source: &str, framework: TestFramework) -> String {
+        let source = source.trim_start_matches("./");
+        let (dir, file) = source.rsplit_once('/').unwrap_or(("", source));
+        let (stem, ext) = file.rsplit_once('.').unwrap_or((file, ""));
+        let join = |dir: &str, file: String| {
+            if dir.is_empty() {
+                file
+            } else {
+                format!("{}/{}", dir, file)
+            }
+        };
+
+        match framework {
+            TestFramework::Cargo => {
+                // Integration tests next to the crate's `src/`
+                let crate_root = source
+                    .rsplit_once("src/")
+                    .map(|(root, _)| root.trim_end_matches('/'))
+                    .unwrap_or("");
+                join(
+                    &join(crate_root, "tests".to_string()),
+                    format!("{}_tests.rs", stem),
+                )
+            }
+            TestFramework::Pytest | TestFramework::Unittest => {
+                let file = format!("test_{}.py", stem);
+                if self.has_test_dir("tests") {
+                    join("tests", file)
+                } else {
+                    join(dir, file)
+                }
+            }
+            TestFramework::Go => join(dir, format!("{}_test.go", stem)),
+            TestFramework::Jest | TestFramework::Vitest | TestFramework::Mocha => {
+                let kind = if self.prefers_spec() { "spec" } else { "test" };
+                let file = format!("{}.{}.{}", stem, kind, ext);
+                if self.has_test_dir("__tests__") {
+                    join(&join(dir, "__tests__".to_string()), file)
+                } else {
+                    join(dir, file)
+                }
+            }
+            TestFramework::JUnit => {
+                let dir = dir.replacen("src/main/", "src/test/", 1);
+                join(&dir, format!("{}Test.java", stem))
+            }
+            TestFramework::XUnit | TestFramework::NUnit => {
+                // Sibling `<Project>.Tests` project
+                let (project, rest) = dir.split_once('/').unwrap_or((dir, ""));
+                let project = if project.is_empty() {
+                    "Tests".to_string()
+                } else {
+                    format!("{}.Tests", project)
+                };
+                join(
+                    &join(&project, rest.to_string()),
+                    format!("{}Tests.cs", stem),
+                )
+            }
+        }
+    }
+}
+
+/// A drafted test file for one plan step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestStub {
+    /// Source file of the plan step this stub tests
+    pub source: String,
+    /// Path of the test file, relative to the project root
+    pub path: String,
+    /// Framework the stub is written for
+    pub framework: TestFramework,
+    /// Test code; every test fails until implemented
+    pub code: String,
+}
+
+impl TestStub {
+    /// Whether `path` is relative and stays inside the directory stubs are
+    /// written to. Plan paths come from the model, so they may not.
+    pub fn has_safe_path(&self) -> bool {
+        !self.path.is_empty()
+            && Path::new(&self.path)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    }
+}
+
+/// A test case to stub: its name and what it checks.
+struct Case {
+    name: String,
+    description: String,
+}
+
+/// Draft failing test stubs for every source file in the plan.
+///
+/// Steps on files that are already tests, or in languages without a known
+/// test framework, are skipped.
+pub fn scaffold_tests(plan: &Plan, conventions: &TestConventions) -> Vec<TestStub> {
+    let created = plan.files_to_create.iter().map(|f| {
+        let cases = if f.exports.is_empty() {
+            vec![Case {
+                name: stem(&f.path).to_string(),
+                description: f.description.clone(),
+            }]
+        } else {
+            f.exports.iter().map(export_case).collect()
+        };
+        (f.path.as_str(), cases)
+    });
+    let modified = plan.files_to_modify.iter().map(|f| {
+        let case = Case {
+            name: stem(&f.path).to_string(),
+            description: f.description.clone(),
+        };
+        (f.path.as_str(), vec![case])
+    });
+
+    let mut stubs: Vec<TestStub> = Vec::new();
+    for (source, cases) in created.chain(modified) {
+        if is_test_path(source) {
+            continue;
+        }
+        let Some(framework) = conventions.framework_for(source) else {
+            continue;
+        };
+        let path = conventions.test_path_for(source, framework);
+
+        // Several steps may map to the same test file
+        if let Some(stub) = stubs.iter_mut().find(|s| s.path == path) {
+            stub.code = render(framework, &path, source, &cases, Some(&stub.code));
+            continue;
+        }
+        stubs.push(TestStub {
+            source: source.to_string(),
+            code: render(framework, &path, source, &cases, None),
+            path,
+            framework,
+        });
+    }
+    stubs
+}
+
+fn export_case(export: &FunctionSignature) -> Case {
+    let mut description = export.signature.clone();
+    for behavior in &export.behavior {
+        description.push_str("; ");
+        description.push_str(behavior);
+    }
+    Case {
+        name: export.name.clone(),
+        description,
+    }
+}
+
+/// Render test cases, appending them to `existing` code when given.
+fn render(
+    framework: TestFramework,
+    path: &str,
+    source: &str,
+    cases: &[Case],
+    existing: Option<&str>,
+) -> String {
+    let mut code = match existing {
+        Some(code) => code.to_string(),
+        None => header(framework, path, source),
+    };
+    let body: String = cases
+        .iter()
+        .map(|case| test_case(framework, case))
+        .collect();
+
+    // Class-based frameworks close their class after the cases
+    match closing(framework) {
+        Some(close) => {
+            let insert_at = code.rfind(close).unwrap_or(code.len());
+            code.insert_str(insert_at, &body);
+        }
+        None => code.push_str(&body),
+    }
+    code
+}
+
+fn header(framework: TestFramework, path: &str, source: &str) -> String {
+    let class = pascal_case(stem(path));
+    match framework {
+        TestFramework::Cargo => format!("//! Tests for `{}`.\n", source),
+        TestFramework::Pytest => format!("\"\"\"Tests for {}.\"\"\"\n\nimport pytest\n", source),
+        TestFramework::Unittest => format!(
+            "\"\"\"Tests for {}.\"\"\"\n\nimport unittest\n\n\nclass {}(unittest.TestCase):\n\nif __name__ == \"__main__\":\n    unittest.main()\n",
+            source, class
+        ),
+        TestFramework::Go => {
+            let package = source
+                .rsplit_once('/')
+                .map_or("main", |(dir, _)| dir.rsplit('/').next().unwrap_or(dir));
+            format!("package {}\n\nimport \"testing\"\n", package)
+        }
+        TestFramework::Jest | TestFramework::Mocha => format!("// Tests for {}\n", source),
+        TestFramework::Vitest => format!(
+            "// Tests for {}\nimport {{ describe, it }} from \"vitest\";\n",
+            source
+        ),
+        TestFramework::JUnit => format!(
+            "import static org.junit.jupiter.api.Assertions.fail;\n\nimport org.junit.jupiter.api.Test;\n\n/** Tests for {}. */\nclass {} {{\n}}\n",
+            source, class
+        ),
+        TestFramework::XUnit => format!(
+            "using Xunit;\n\n/// <summary>Tests for {}.</summary>\npublic class {}\n{{\n}}\n",
+            source, class
+        ),
+        TestFramework::NUnit => format!(
+            "using NUnit.Framework;\n\n/// <summary>Tests for {}.</summary>\n[TestFixture]\npublic class {}\n{{\n}}\n",
+            source, class
+        ),
+    }
+}
+
+/// Text closing the test class, before which new cases are inserted.
+fn closing(framework: TestFramework) -> Option<&'static str> {
+    match framework {
+        TestFramework::Unittest => Some("\nif __name__"),
+        TestFramework::JUnit | TestFramework::XUnit | TestFramework::NUnit => Some("}\n"),
+        _ => None,
+    }
+}
+
+fn test_case(framework: TestFramework, case: &Case) -> String {
+    let snake = snake_case(&case.name);
+    let pascal = pascal_case(&case.name);
+    // Quoted with escapes ({:?}), which these languages' string literals share
+    let todo = format!("TODO: {}", case.description);
+    match framework {
+        // `todo!` takes a format string
+        TestFramework::Cargo => format!(
+            "\n#[test]\nfn test_{}() {{\n    todo!({:?});\n}}\n",
+            snake,
+            todo.replace('{', "{{").replace('}', "}}")
+        ),
+        TestFramework::Pytest => format!(
+            "\n\ndef test_{}():\n    pytest.fail({:?})\n",
+            snake, todo
+        ),
+        TestFramework::Unittest => format!(
+            "    def test_{}(self):\n        self.fail({:?})\n\n",
+            snake, todo
+        ),
+        TestFramework::Go => format!(
+            "\nfunc Test{}(t *testing.T) {{\n\tt.Fatal({:?})\n}}\n",
+            pascal, todo
+        ),
+        TestFramework::Jest | TestFramework::Vitest | TestFramework::Mocha => format!(
+            "\ndescribe({:?}, () => {{\n  it(\"works as planned\", () => {{\n    throw new Error({:?});\n  }});\n}});\n",
+            case.name, todo
+        ),
+        TestFramework::JUnit => format!(
+            "    @Test\n    void test{}() {{\n        fail({:?});\n    }}\n",
+            pascal, todo
+        ),
+        TestFramework::XUnit => format!(
+            "    [Fact]\n    public void {}_WorksAsPlanned()\n    {{\n        Assert.Fail({:?});\n    }}\n",
+            pascal, todo
+        ),
+        TestFramework::NUnit => format!(
+            "    [Test]\n    public void {}_WorksAsPlanned()\n    {{\n        Assert.Fail({:?});\n    }}\n",
+            pascal, todo
+        ),
+    }
+}
+
+/// Whether a path looks like a test file in any supported language.
+pub fn is_test_path(path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let stem = file.split('.').next().unwrap_or(file);
+    path.split('/')
+        .any(|c| matches!(c, "tests" | "test" | "__tests__" | "spec"))
+        || file.contains(".test.")
+        || file.contains(".spec.")
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+}
+
+fn extension(path: &str) -> &str {
+    path.rsplit_once('.').map_or("", |(_, ext)| ext)
+}
+
+fn stem(path: &str) -> &str {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.split('.').next().unwrap_or(file)
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 && !out.ends_with('_') {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else if c.is_alphanumeric() {
+            out.push(c);
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    out.trim_matches('_').to_string()
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
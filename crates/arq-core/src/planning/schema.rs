@@ -0,0 +1,337 @@
+//! Versioned schema of `plan.yaml`.
+//!
+//! Plans carry a `schema_version`. Version 1 plans, written before the field
+//! existed, only list files; loading one upgrades it to the current version
+//! by deriving a step per file. Plans from a newer Arq are rejected rather
+//! than silently losing the fields this version doesn't know. Stored plans
+//! saved without an approach get a placeholder, so they keep loading; newly
+//! generated plans must state one.
+//!
+//! Validation reports every problem with its position: the line and column
+//! of a YAML syntax error, or the field path (`steps[2].depends_on[0]`) of
+//! a plan that parses but doesn't hold together.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use thiserror::Error;
+
+use super::plan::{Plan, PlanStep};
+
+/// Schema version written by this version of Arq.
+pub const PLAN_SCHEMA_VERSION: u32 = 2;
+
+/// Version of plans written before `schema_version` existed.
+pub(crate) const LEGACY_PLAN_SCHEMA_VERSION: u32 = 1;
+
+/// Approach given to stored plans that were saved without one.
+pub const MISSING_APPROACH: &str = "(no approach recorded)";
+
+/// A problem with a plan, at a field path such as `steps[1].id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl SchemaIssue {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SchemaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Errors from loading or validating a plan.
+#[derive(Debug, Error)]
+pub enum PlanSchemaError {
+    #[error("{}", format_syntax(.line, .column, .message))]
+    Syntax {
+        /// 1-based line of the error, when known.
+        line: Option<usize>,
+        /// 1-based column of the error, when known.
+        column: Option<usize>,
+        message: String,
+    },
+
+    #[error("Plan schema version {found} is newer than supported version {supported}; upgrade Arq to use this plan")]
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    #[error("Invalid plan: {}", format_issues(.0))]
+    Invalid(Vec<SchemaIssue>),
+}
+
+impl From<serde_yaml::Error> for PlanSchemaError {
+    fn from(e: serde_yaml::Error) -> Self {
+        let location = e.location();
+        let message = e.to_string();
+        // serde_yaml appends the location to the message; it is reported
+        // separately
+        let message = match message.rfind(" at line ") {
+            Some(at) if location.is_some() => message[..at].to_string(),
+            _ => message,
+        };
+        PlanSchemaError::Syntax {
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+            message,
+        }
+    }
+}
+
+fn format_syntax(line: &Option<usize>, column: &Option<usize>, message: &str) -> String {
+    match (line, column) {
+        (Some(line), Some(column)) => {
+            format!(
+                "Plan YAML error at line {}, column {}: {}",
+                line, column, message
+            )
+        }
+        _ => format!("Plan YAML error: {}", message),
+    }
+}
+
+fn format_issues(issues: &[SchemaIssue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Bring a plan to [`PLAN_SCHEMA_VERSION`], rejecting newer versions.
+pub(crate) fn upgrade(plan: &mut Plan) -> Result<(), PlanSchemaError> {
+    if plan.schema_version > PLAN_SCHEMA_VERSION {
+        return Err(PlanSchemaError::UnsupportedVersion {
+            found: plan.schema_version,
+            supported: PLAN_SCHEMA_VERSION,
+        });
+    }
+    if plan.schema_version <= LEGACY_PLAN_SCHEMA_VERSION {
+        // Version 1 had no steps: one per file, in the order listed
+        if plan.steps.is_empty() {
+            plan.steps = steps_from_files(plan);
+        }
+        plan.schema_version = 2;
+    }
+    if plan.approach.trim().is_empty() {
+        plan.approach = MISSING_APPROACH.to_string();
+    }
+    Ok(())
+}
+
+/// One step per file of the plan, for plans without steps.
+pub(crate) fn steps_from_files(plan: &Plan) -> Vec<PlanStep> {
+    let created = plan
+        .files_to_create
+        .iter()
+        .map(|f| (format!("Create {}", f.path), &f.path, &f.description));
+    let modified = plan
+        .files_to_modify
+        .iter()
+        .map(|f| (format!("Modify {}", f.path), &f.path, &f.description));
+    created
+        .chain(modified)
+        .enumerate()
+        .map(|(index, (action, path, description))| PlanStep {
+            id: format!("step-{}", index + 1),
+            description: if description.trim().is_empty() {
+                action
+            } else {
+                format!("{}: {}", action, description.trim())
+            },
+            targets: vec![path.clone()],
+            depends_on: Vec::new(),
+        })
+        .collect()
+}
+
+/// Check that a plan is complete and consistent.
+pub(crate) fn validate(plan: &Plan) -> Result<(), PlanSchemaError> {
+    let mut issues = Vec::new();
+
+    if plan.schema_version != PLAN_SCHEMA_VERSION {
+        issues.push(SchemaIssue::new(
+            "schema_version",
+            format!(
+                "expected {}, found {}",
+                PLAN_SCHEMA_VERSION, plan.schema_version
+            ),
+        ));
+    }
+    if plan.task_name.trim().is_empty() {
+        issues.push(SchemaIssue::new("task_name", "must not be empty"));
+    }
+    if plan.approach.trim().is_empty() {
+        issues.push(SchemaIssue::new("approach", "must not be empty"));
+    }
+    for (index, file) in plan.files_to_create.iter().enumerate() {
+        if file.path.trim().is_empty() {
+            issues.push(SchemaIssue::new(
+                format!("files_to_create[{}].path", index),
+                "must not be empty",
+            ));
+        }
+    }
+    for (index, file) in plan.files_to_modify.iter().enumerate() {
+        if file.path.trim().is_empty() {
+            issues.push(SchemaIssue::new(
+                format!("files_to_modify[{}].path", index),
+                "must not be empty",
+            ));
+        }
+    }
+    for (index, criterion) in plan.acceptance_criteria.iter().enumerate() {
+        if criterion.trim().is_empty() {
+            issues.push(SchemaIssue::new(
+                format!("acceptance_criteria[{}]", index),
+                "must not be empty",
+            ));
+        }
+    }
+    for (index, risk) in plan.risks.iter().enumerate() {
+        if risk.description.trim().is_empty() {
+            issues.push(SchemaIssue::new(
+                format!("risks[{}].description", index),
+                "must not be empty",
+            ));
+        }
+    }
+
+    validate_steps(&plan.steps, &mut issues);
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(PlanSchemaError::Invalid(issues))
+    }
+}
+
+fn validate_steps(steps: &[PlanStep], issues: &mut Vec<SchemaIssue>) {
+    let mut first_index: HashMap<&str, usize> = HashMap::new();
+    for (index, step) in steps.iter().enumerate() {
+        let id = step.id.trim();
+        if id.is_empty() {
+            issues.push(SchemaIssue::new(
+                format!("steps[{}].id", index),
+                "must not be empty",
+            ));
+        } else if let Some(first) = first_index.get(id) {
+            issues.push(SchemaIssue::new(
+                format!("steps[{}].id", index),
+                format!(
+                    "duplicate step '{}' (first defined at steps[{}])",
+                    id, first
+                ),
+            ));
+        } else {
+            first_index.insert(id, index);
+        }
+        if step.description.trim().is_empty() {
+            issues.push(SchemaIssue::new(
+                format!("steps[{}].description", index),
+                "must not be empty",
+            ));
+        }
+        for (target_index, target) in step.targets.iter().enumerate() {
+            if target.trim().is_empty() {
+                issues.push(SchemaIssue::new(
+                    format!("steps[{}].targets[{}]", index, target_index),
+                    "must not be empty",
+                ));
+            }
+        }
+    }
+
+    let mut dangling = false;
+    for (index, step) in steps.iter().enumerate() {
+        for (dep_index, dependency) in step.depends_on.iter().enumerate() {
+            let path = format!("steps[{}].depends_on[{}]", index, dep_index);
+            if dependency.trim() == step.id.trim() {
+                issues.push(SchemaIssue::new(path, "a step cannot depend on itself"));
+                dangling = true;
+            } else if !first_index.contains_key(dependency.trim()) {
+                issues.push(SchemaIssue::new(
+                    path,
+                    format!("unknown step '{}'", dependency),
+                ));
+                dangling = true;
+            }
+        }
+    }
+
+    if !dangling {
+        if let Some(index) = find_cycle(steps, &first_index) {
+            issues.push(SchemaIssue::new(
+                format!("steps[{}].depends_on", index),
+                format!("step '{}' is part of a dependency cycle", steps[index].id),
+            ));
+        }
+    }
+}
+
+/// Index of a step on a dependency cycle, if there is one.
+fn find_cycle(steps: &[PlanStep], index_of: &HashMap<&str, usize>) -> Option<usize> {
+    // Kahn's algorithm: whatever can't be ordered is on or behind a cycle
+    let mut pending = vec![0usize; steps.len()];
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); steps.len()];
+    for (index, step) in steps.iter().enumerate() {
+        let unique: HashSet<&str> = step.depends_on.iter().map(|d| d.trim()).collect();
+        for dependency in unique {
+            if let Some(&dep) = index_of.get(dependency) {
+                dependents[dep].push(index);
+                pending[index] += 1;
+            }
+        }
+    }
+
+    let mut ready: Vec<usize> = (0..steps.len()).filter(|&i| pending[i] == 0).collect();
+    while let Some(index) = ready.pop() {
+        for &dependent in &dependents[index] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                ready.push(dependent);
+            }
+        }
+    }
+
+    (0..steps.len()).find(|&i| pending[i] > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(id: &str, depends_on: &[&str]) -> PlanStep {
+        PlanStep {
+            id: id.to_string(),
+            description: format!("Do {}", id),
+            targets: Vec::new(),
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    fn index_of(steps: &[PlanStep]) -> HashMap<&str, usize> {
+        steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| (step.id.as_str(), index))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let ordered = vec![step("a", &[]), step("b", &["a"]), step("c", &["a", "b"])];
+        assert_eq!(find_cycle(&ordered, &index_of(&ordered)), None);
+
+        let cyclic = vec![step("a", &["c"]), step("b", &["a"]), step("c", &["b"])];
+        assert_eq!(find_cycle(&cyclic, &index_of(&cyclic)), Some(0));
+    }
+}
@@ -0,0 +1,143 @@
+//! Per-phase personas: the system prompt and sampling temperature used for
+//! a phase's LLM calls.
+//!
+//! Each phase talks to the model in its own role: the researcher answers
+//! questions from code, the planner designs the change, the agent reviews
+//! changes against the plan. Prompts and temperatures can be overridden per
+//! phase in `[prompts.<phase>]`:
+//!
+//! ```toml
+//! [prompts.research]
+//! temperature = 0.2
+//!
+//! [prompts.planning]
+//! system_prompt = "You are a pragmatic architect..."
+//! temperature = 0.7
+//! ```
+//!
+//! `research.output_language` has every phase write its prose in another
+//! language; [`localize`] adds the instruction to a phase's system prompts.
+
+use crate::config::{
+    Config, LLMConfig, PhasePromptConfig, DEFAULT_CONFORMANCE_SYSTEM_PROMPT,
+    DEFAULT_PLANNING_CHAT_SYSTEM_PROMPT, DEFAULT_RESEARCH_SYSTEM_PROMPT,
+};
+use crate::Phase;
+
+/// The system prompt and temperature for one phase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Persona {
+    pub phase: Phase,
+    pub system_prompt: String,
+    /// Sampling temperature; `None` keeps the provider's default.
+    pub temperature: Option<f32>,
+}
+
+impl Persona {
+    /// `llm` with this persona's temperature, for building the phase's client.
+    pub fn llm_config(&self, llm: &LLMConfig) -> LLMConfig {
+        LLMConfig {
+            temperature: self.temperature.or(llm.temperature),
+            ..llm.clone()
+        }
+    }
+}
+
+/// Resolve the persona for `phase` from `config`.
+///
+/// Prompts come from `[prompts.<phase>]`, falling back to the built-in
+/// prompt of the phase (for research, `research.system_prompt` is honored
+/// too). `Complete` uses the agent persona.
+pub fn for_phase(phase: Phase, config: &Config) -> Persona {
+    let (overrides, default_prompt): (&PhasePromptConfig, &str) = match phase {
+        Phase::Research => (
+            &config.prompts.research,
+            config
+                .research
+                .system_prompt
+                .as_deref()
+                .unwrap_or(DEFAULT_RESEARCH_SYSTEM_PROMPT),
+        ),
+        Phase::Planning => (
+            &config.prompts.planning,
+            DEFAULT_PLANNING_CHAT_SYSTEM_PROMPT,
+        ),
+        Phase::Agent | Phase::Complete => {
+            (&config.prompts.agent, DEFAULT_CONFORMANCE_SYSTEM_PROMPT)
+        }
+    };
+
+    Persona {
+        phase,
+        system_prompt: overrides
+            .system_prompt
+            .clone()
+            .unwrap_or_else(|| default_prompt.to_string()),
+        temperature: overrides.temperature,
+    }
+}
+
+/// `system_prompt` with an instruction to write prose in `language`, if one
+/// is set; see [`output_language_instruction`].
+pub fn localize(system_prompt: &str, language: Option<&str>) -> String {
+    match language.map(str::trim).filter(|l| !l.is_empty()) {
+        Some(language) => format!(
+            "{}\n\n{}",
+            system_prompt,
+            output_language_instruction(language)
+        ),
+        None => system_prompt.to_string(),
+    }
+}
+
+/// Instruction to write prose in `language`, a code such as `de` or `ja` or
+/// a language name.
+///
+/// Identifiers, paths and the keys and enum values of structured responses
+/// stay as they are, so responses still parse and references still resolve.
+pub fn output_language_instruction(language: &str) -> String {
+    format!(
+        "OUTPUT LANGUAGE: Write all prose (summaries, explanations, approaches, \
+         descriptions, replies) in {}. Keep code identifiers, file paths, commands, \
+         code blocks, task names, JSON and YAML keys and enum values (such as \
+         complexity, severity or status values) exactly as they are, untranslated.",
+        language_name(language)
+    )
+}
+
+/// English name of a language code, or `language` itself if it isn't one
+/// of the common codes.
+fn language_name(language: &str) -> &str {
+    match language.to_lowercase().replace('_', "-").as_str() {
+        "ar" => "Arabic",
+        "cs" => "Czech",
+        "da" => "Danish",
+        "de" => "German",
+        "el" => "Greek",
+        "en" => "English",
+        "es" => "Spanish",
+        "fi" => "Finnish",
+        "fr" => "French",
+        "he" => "Hebrew",
+        "hi" => "Hindi",
+        "id" => "Indonesian",
+        "it" => "Italian",
+        "ja" => "Japanese",
+        "ko" => "Korean",
+        "nl" => "Dutch",
+        "no" | "nb" => "Norwegian",
+        "pl" => "Polish",
+        "pt" => "Portuguese",
+        "pt-br" => "Brazilian Portuguese",
+        "ro" => "Romanian",
+        "ru" => "Russian",
+        "sv" => "Swedish",
+        "th" => "Thai",
+        "tr" => "Turkish",
+        "uk" => "Ukrainian",
+        "vi" => "Vietnamese",
+        "zh" | "zh-cn" | "zh-hans" => "Simplified Chinese",
+        "zh-tw" | "zh-hant" => "Traditional Chinese",
+        _ => language,
+    }
+}
@@ -0,0 +1,153 @@
+//! Cache of assembled research context between runs of the same task.
+//!
+//! Gathering context (tree and file reads, or knowledge graph retrieval) is
+//! repeated on every run and refinement. Entries are stored per task as JSON
+//! and carry a key derived from the codebase hash, the retrieval queries and
+//! the settings that shape the context; a stale key is simply a miss.
+//! Entries quote code, so they are encrypted with `storage.encrypt` (see
+//! [`PrivateFiles`]).
+
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{ContentGuard, RerankWeights};
+use crate::research::document::Source;
+use crate::storage::PrivateFiles;
+
+/// A cached context entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedContext {
+    /// Key the context was assembled under (see [`context_cache_key`]).
+    pub key: String,
+    /// Context string passed to the research prompt.
+    pub context: String,
+    /// Sources the context was built from.
+    pub sources: Vec<Source>,
+    /// Keys of the chunks or files in the context, for the sent context log.
+    #[serde(default)]
+    pub chunks: Vec<String>,
+    /// Private files whose matches were withheld from the context.
+    #[serde(default)]
+    pub withheld: Vec<String>,
+    /// When the context was assembled.
+    pub created_at: DateTime<Utc>,
+}
+
+/// File-backed context cache, one entry per task.
+#[derive(Debug, Clone)]
+pub struct ContextCache {
+    dir: PathBuf,
+    files: PrivateFiles,
+}
+
+impl ContextCache {
+    /// Creates a cache storing plaintext entries in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            files: PrivateFiles::default(),
+        }
+    }
+
+    /// Reads and writes entries through `files`, encrypting them if enabled.
+    pub fn with_encryption(mut self, files: PrivateFiles) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Cached context for a task, if it was assembled under `key`.
+    pub fn load(&self, task_id: &str, key: &str) -> Option<CachedContext> {
+        let json = self.files.read(&self.entry_path(task_id)).ok()?;
+        let cached: CachedContext = serde_json::from_str(&json).ok()?;
+        (cached.key == key).then_some(cached)
+    }
+
+    /// Store context for a task, replacing any previous entry.
+    pub fn store(
+        &self,
+        task_id: &str,
+        key: String,
+        context: &str,
+        sources: &[Source],
+        chunks: &[String],
+        withheld: &[String],
+    ) -> io::Result<()> {
+        let entry = CachedContext {
+            key,
+            context: context.to_string(),
+            sources: sources.to_vec(),
+            chunks: chunks.to_vec(),
+            withheld: withheld.to_vec(),
+            created_at: Utc::now(),
+        };
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        std::fs::create_dir_all(&self.dir)?;
+        self.files
+            .write(&self.entry_path(task_id), json.as_bytes())
+            .map_err(io::Error::other)
+    }
+
+    /// Remove the cached entry for a task, if any.
+    pub fn invalidate(&self, task_id: &str) -> io::Result<()> {
+        match std::fs::remove_file(self.entry_path(task_id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_path(&self, task_id: &str) -> PathBuf {
+        // Task IDs are UUIDs; keep anything else from escaping the cache dir
+        let name: String = task_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+/// Key identifying the inputs that shape assembled context.
+pub fn context_cache_key(
+    codebase_hash: &str,
+    queries: &[&str],
+    uses_knowledge_graph: bool,
+    content_guard: ContentGuard,
+    rerank: &RerankWeights,
+    excluded_files: &[String],
+    private_paths: &[String],
+    include_tests: bool,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(codebase_hash.as_bytes());
+    for query in queries {
+        hasher.update(b"\0");
+        hasher.update(query.as_bytes());
+    }
+    hasher.update(
+        format!(
+            "\0{}\0{:?}\0{:?}\0{}",
+            uses_knowledge_graph, content_guard, rerank, include_tests
+        )
+        .as_bytes(),
+    );
+    for pattern in excluded_files {
+        hasher.update(b"\x01");
+        hasher.update(pattern.as_bytes());
+    }
+    for pattern in private_paths {
+        hasher.update(b"\x02");
+        hasher.update(pattern.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
@@ -0,0 +1,254 @@
+//! Side-by-side comparison of research produced by two providers.
+//!
+//! Running the same research prompt against two models and lining up their
+//! summaries, approaches and cited files makes it easier to judge which model
+//! to trust for a given codebase.
+
+use std::collections::BTreeSet;
+
+use crate::research::document::ResearchDoc;
+
+/// Research output from one provider.
+#[derive(Debug, Clone)]
+pub struct ComparedResearch {
+    /// Provider label as given on the command line (e.g. `anthropic`, `openai:gpt-4o`)
+    pub label: String,
+    pub doc: ResearchDoc,
+}
+
+/// Files cited by each side of a comparison.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileOverlap {
+    pub shared: Vec<String>,
+    pub left_only: Vec<String>,
+    pub right_only: Vec<String>,
+}
+
+/// Two research documents for the same task.
+#[derive(Debug, Clone)]
+pub struct ResearchComparison {
+    pub left: ComparedResearch,
+    pub right: ComparedResearch,
+}
+
+impl ResearchComparison {
+    pub fn new(left: ComparedResearch, right: ComparedResearch) -> Self {
+        Self { left, right }
+    }
+
+    /// Files cited in findings by both, or only one, of the providers.
+    pub fn file_overlap(&self) -> FileOverlap {
+        let left = cited_files(&self.left.doc);
+        let right = cited_files(&self.right.doc);
+
+        FileOverlap {
+            shared: left.intersection(&right).cloned().collect(),
+            left_only: left.difference(&right).cloned().collect(),
+            right_only: right.difference(&left).cloned().collect(),
+        }
+    }
+
+    /// Renders the comparison as two text columns of `width` characters each.
+    pub fn render_side_by_side(&self, width: usize) -> String {
+        let width = width.max(20);
+        let mut out = String::new();
+
+        out.push_str(&columns(&self.left.label, &self.right.label, width));
+        out.push_str(&format!("{}-+-{}\n", "-".repeat(width), "-".repeat(width)));
+
+        let sections: [(&str, fn(&ResearchDoc) -> String); 3] = [
+            ("Summary", |d| d.summary.clone()),
+            ("Suggested Approach", |d| d.suggested_approach.clone()),
+            ("Findings", |d| {
+                d.codebase_analysis
+                    .iter()
+                    .map(|f| format!("- {}", f.title))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }),
+        ];
+
+        for (title, extract) in sections {
+            out.push_str(&format!("\n{}\n", title.to_uppercase()));
+            out.push_str(&columns(
+                &extract(&self.left.doc),
+                &extract(&self.right.doc),
+                width,
+            ));
+        }
+
+        let overlap = self.file_overlap();
+        out.push_str("\nCITED FILES\n");
+        out.push_str(&columns(
+            &overlap.left_only.join("\n"),
+            &overlap.right_only.join("\n"),
+            width,
+        ));
+        if !overlap.shared.is_empty() {
+            out.push_str(&format!("\nCited by both: {}\n", overlap.shared.join(", ")));
+        }
+
+        out
+    }
+
+    /// Renders the comparison as markdown, with a line diff of the approaches.
+    pub fn to_markdown(&self) -> String {
+        let (l, r) = (&self.left, &self.right);
+        let mut md = format!(
+            "# Research Comparison: {}\n\n{} vs {}\n\n",
+            l.doc.task_name, l.label, r.label
+        );
+
+        md.push_str(&format!(
+            "## Summary\n\n### {}\n\n{}\n\n### {}\n\n{}\n\n",
+            l.label, l.doc.summary, r.label, r.doc.summary
+        ));
+
+        md.push_str("## Suggested Approach (diff)\n\n```diff\n");
+        for line in diff_lines(&l.doc.suggested_approach, &r.doc.suggested_approach) {
+            md.push_str(&line.to_string());
+            md.push('\n');
+        }
+        md.push_str("```\n\n");
+
+        let overlap = self.file_overlap();
+        md.push_str("## Cited Files\n\n");
+        md.push_str(&format!("- Both: {}\n", list_or_none(&overlap.shared)));
+        md.push_str(&format!(
+            "- Only {}: {}\n",
+            l.label,
+            list_or_none(&overlap.left_only)
+        ));
+        md.push_str(&format!(
+            "- Only {}: {}\n",
+            r.label,
+            list_or_none(&overlap.right_only)
+        ));
+
+        md
+    }
+}
+
+/// One line of a line-based diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Left(String),
+    Right(String),
+}
+
+impl std::fmt::Display for DiffLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffLine::Same(line) => write!(f, "  {}", line),
+            DiffLine::Left(line) => write!(f, "- {}", line),
+            DiffLine::Right(line) => write!(f, "+ {}", line),
+        }
+    }
+}
+
+/// Line diff of two texts using a longest common subsequence.
+pub fn diff_lines(left: &str, right: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = left.lines().map(str::trim_end).collect();
+    let b: Vec<&str> = right.lines().map(str::trim_end).collect();
+
+    // lcs[i][j] = LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            diff.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Left(a[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Right(b[j].to_string()));
+            j += 1;
+        }
+    }
+    diff.extend(a[i..].iter().map(|l| DiffLine::Left(l.to_string())));
+    diff.extend(b[j..].iter().map(|l| DiffLine::Right(l.to_string())));
+
+    diff
+}
+
+fn cited_files(doc: &ResearchDoc) -> BTreeSet<String> {
+    doc.codebase_analysis
+        .iter()
+        .flat_map(|f| f.related_files.iter())
+        .map(|p| p.trim().trim_matches('`').to_string())
+        .collect()
+}
+
+fn list_or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        "(none)".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+/// Lay out two texts as wrapped columns separated by ` | `.
+fn columns(left: &str, right: &str, width: usize) -> String {
+    let left = wrap(left, width);
+    let right = wrap(right, width);
+    let rows = left.len().max(right.len());
+
+    let mut out = String::new();
+    for row in 0..rows {
+        let l = left.get(row).map(String::as_str).unwrap_or("");
+        let r = right.get(row).map(String::as_str).unwrap_or("");
+        let pad = width.saturating_sub(l.chars().count());
+        out.push_str(&format!("{}{} | {}\n", l, " ".repeat(pad), r).trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Greedy word wrap that preserves explicit line breaks. Words longer than
+/// the width (paths, URLs) are split across lines.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut current = String::new();
+        let words = paragraph.split_whitespace().flat_map(|word| {
+            let chars: Vec<char> = word.chars().collect();
+            chars
+                .chunks(width)
+                .map(|piece| piece.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        });
+        for word in words {
+            let needed = if current.is_empty() {
+                word.chars().count()
+            } else {
+                current.chars().count() + 1 + word.chars().count()
+            };
+            if needed > width && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(&word);
+        }
+        lines.push(current);
+    }
+
+    lines
+}
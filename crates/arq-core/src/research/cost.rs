@@ -0,0 +1,178 @@
+//! Cost guardrail for research prompts.
+//!
+//! Smart context can grow a research prompt to hundreds of thousands of
+//! tokens, which on a large model costs dollars per call. With
+//! `research.confirm_cost_above` set, the runner estimates the cost of the
+//! assembled prompt before sending it: its tokens at the model's input price
+//! plus a full response (`llm.max_tokens`) at the output price. Above the
+//! threshold it sends a [`CostConfirmation`] with the breakdown through the
+//! progress channel and waits for the answer. A declined or unanswered
+//! confirmation stops the run with [`ResearchError::CostDeclined`] before
+//! anything is sent.
+//!
+//! Models without known pricing, and local models, are never held back.
+//!
+//! [`ResearchError::CostDeclined`]: super::ResearchError::CostDeclined
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::config::Config;
+use crate::llm::TokenUsage;
+
+use super::stats::{format_tokens, PromptStats};
+
+/// Largest sources named in the breakdown of a confirmation.
+const BREAKDOWN_TOP_FILES: usize = 5;
+
+/// Prompts estimated to cost more than `threshold` USD need confirmation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostLimit {
+    pub threshold: f64,
+    pub provider: String,
+    pub model: String,
+    /// Longest response the model is allowed, priced in full.
+    pub max_output_tokens: usize,
+}
+
+impl CostLimit {
+    /// The limit of `research.confirm_cost_above` for the configured model,
+    /// if one is set.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let threshold = config.research.confirm_cost_above?;
+        Some(Self {
+            threshold,
+            provider: config.llm.provider.clone(),
+            model: config.llm.model_or_default(),
+            max_output_tokens: config.llm.max_tokens as usize,
+        })
+    }
+
+    /// The estimate for a prompt, if it costs more than the threshold.
+    pub fn check(&self, system: &str, prompt: &str) -> Option<CostEstimate> {
+        let stats = PromptStats::from_prompt(system, prompt);
+        let usage = TokenUsage {
+            input_tokens: stats.total(),
+            output_tokens: self.max_output_tokens,
+        };
+        let cost = usage.estimated_cost(&self.provider, &self.model)?;
+        (cost > self.threshold).then(|| CostEstimate {
+            model: self.model.clone(),
+            stats,
+            usage,
+            cost,
+            threshold: self.threshold,
+        })
+    }
+}
+
+/// Estimated cost of a research prompt over the limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub model: String,
+    pub stats: PromptStats,
+    /// Prompt tokens, and the response tokens priced in.
+    pub usage: TokenUsage,
+    /// Estimated cost in USD.
+    pub cost: f64,
+    pub threshold: f64,
+}
+
+impl CostEstimate {
+    /// Lines explaining the estimate, e.g.
+    /// `Estimated cost $6.31 with claude-opus-4 (limit $5.00)`.
+    pub fn breakdown(&self) -> Vec<String> {
+        vec![
+            format!(
+                "Estimated cost ${:.2} with {} (limit ${:.2})",
+                self.cost, self.model, self.threshold
+            ),
+            format!(
+                "{} input tokens, up to {} output tokens",
+                format_tokens(self.usage.input_tokens),
+                format_tokens(self.usage.output_tokens)
+            ),
+            format!("Prompt: {}", self.stats.summary(BREAKDOWN_TOP_FILES)),
+        ]
+    }
+}
+
+/// A request to confirm sending a prompt over the cost limit.
+///
+/// Clones share the reply; the first answer counts. Dropping every clone
+/// without answering declines. Only the estimate is serialized, so a
+/// deserialized confirmation (from a recorded TUI session) has no one to
+/// answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostConfirmation {
+    pub estimate: CostEstimate,
+    #[serde(skip)]
+    reply: Arc<Mutex<Option<oneshot::Sender<bool>>>>,
+}
+
+impl CostConfirmation {
+    pub(crate) fn new(estimate: CostEstimate) -> (Self, oneshot::Receiver<bool>) {
+        let (tx, rx) = oneshot::channel();
+        let confirmation = Self {
+            estimate,
+            reply: Arc::new(Mutex::new(Some(tx))),
+        };
+        (confirmation, rx)
+    }
+
+    /// Send the prompt (`true`) or stop the run (`false`).
+    pub fn answer(&self, send: bool) {
+        let reply = self.reply.lock().ok().and_then(|mut reply| reply.take());
+        if let Some(reply) = reply {
+            let _ = reply.send(send);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(threshold: f64, provider: &str) -> CostLimit {
+        CostLimit {
+            threshold,
+            provider: provider.to_string(),
+            model: "claude-opus-4-20250514".to_string(),
+            max_output_tokens: 2000,
+        }
+    }
+
+    #[test]
+    fn test_cost_limit_check() {
+        // 400k characters with the newline: 100k input tokens, $1.50 on opus,
+        // and $0.15 for 2k output tokens
+        let prompt = "x".repeat(399_999);
+        let estimate = limit(1.0, "anthropic").check("", &prompt).unwrap();
+        assert_eq!(estimate.usage.input_tokens, 100_000);
+        assert!((estimate.cost - 1.65).abs() < 1e-9);
+        assert_eq!(
+            estimate.breakdown()[0],
+            "Estimated cost $1.65 with claude-opus-4-20250514 (limit $1.00)"
+        );
+
+        assert!(limit(2.0, "anthropic").check("", &prompt).is_none());
+        assert!(limit(0.0, "ollama").check("", &prompt).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cost_confirmation() {
+        let estimate = limit(0.0, "anthropic").check("", "prompt").unwrap();
+
+        let (confirmation, reply) = CostConfirmation::new(estimate.clone());
+        confirmation.clone().answer(true);
+        confirmation.answer(false);
+        assert_eq!(reply.await, Ok(true));
+
+        // Unanswered
+        let (confirmation, reply) = CostConfirmation::new(estimate);
+        drop(confirmation);
+        assert!(reply.await.is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Self-critique pass over a generated research document.
+//!
+//! With `research.self_critique = true`, the model reviews its own research
+//! against a rubric: is the file inventory complete, are findings backed by
+//! concrete line references, are dependencies missing? It either returns a
+//! corrected document or reports its confidence and the gaps it could not
+//! close, which are appended to the research as "Confidence & Gaps".
+
+use serde::Deserialize;
+
+use crate::research::document::{Confidence, ConfidenceGaps, ResearchDoc};
+use crate::research::runner::{extract_json, ResearchResponse};
+
+/// System prompt for the self-critique pass.
+pub const CRITIQUE_SYSTEM_PROMPT: &str = r#"You are reviewing a research document that was written to answer a developer's question about their codebase. Check it against this rubric:
+
+1. File inventory: every file in the provided code that matters for the question is mentioned in a finding's related_files.
+2. Line references: findings point at concrete locations (path:line) rather than describing code vaguely.
+3. Dependencies: internal modules and external crates the change relies on are all listed.
+4. Accuracy: every claim is supported by the provided code; nothing is invented.
+
+Respond with JSON only:
+{
+  "confidence": "high" | "medium" | "low",
+  "revised": null or a corrected research document with the same fields as the original (summary, findings, dependencies, suggested_approach),
+  "gaps": ["what remains uncertain or could not be verified from the provided code"]
+}
+
+Set "revised" only if you can fix problems using the provided code. List in "gaps" what you could not fix, including anything that needs code you were not shown."#;
+
+/// Builds the user prompt for the self-critique pass.
+pub fn build_critique_prompt(task_prompt: &str, context: &str, research_json: &str) -> String {
+    format!(
+        r#"## Developer's Question
+
+{task_prompt}
+
+## Research Document
+
+```json
+{research_json}
+```
+
+## Code the Research Was Based On
+
+{context}
+
+Review the research document against the rubric and respond with the JSON described."#
+    )
+}
+
+/// The model's review of its research.
+#[derive(Debug, Deserialize)]
+pub struct CritiqueResponse {
+    #[serde(default)]
+    confidence: Option<String>,
+    #[serde(default)]
+    revised: Option<ResearchResponse>,
+    #[serde(default)]
+    gaps: Vec<String>,
+}
+
+/// Parses a self-critique response.
+pub fn parse_critique(response: &str) -> Result<CritiqueResponse, serde_json::Error> {
+    serde_json::from_str(extract_json(response))
+}
+
+/// Applies a critique to `doc`: takes over the revised content, if any, and
+/// records the confidence and remaining gaps.
+///
+/// Sources are kept from `doc`, since the critique saw the same context.
+pub fn apply_critique(doc: ResearchDoc, critique: CritiqueResponse) -> ResearchDoc {
+    let mut doc = match critique.revised {
+        Some(revised) => {
+            let mut revised = revised.into_doc(&doc.task_name);
+            revised.sources = doc.sources;
+            revised
+        }
+        None => doc,
+    };
+
+    let gaps: Vec<String> = critique
+        .gaps
+        .into_iter()
+        .map(|g| g.trim().to_string())
+        .filter(|g| !g.is_empty())
+        .collect();
+    doc.confidence_gaps = Some(ConfidenceGaps {
+        confidence: critique
+            .confidence
+            .as_deref()
+            .map(Confidence::parse)
+            .unwrap_or_default(),
+        gaps,
+    });
+    doc
+}
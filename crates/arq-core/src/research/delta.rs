@@ -0,0 +1,137 @@
+//! Delta-encoded research refinement.
+//!
+//! A refinement used to resend the task prompt with the whole context, so
+//! every correction cost as much as the first run. Now the prompt carries
+//! the previous research document, the correction and only the chunks that
+//! retrieval found for the correction which the model hasn't seen yet.
+//!
+//! What was sent is tracked per task in a [`SentContextLog`]: a full run
+//! replaces the task's entry, each refinement adds its new chunks to it.
+//! Chunks are keyed by [`chunk_key`], files gathered without the knowledge
+//! graph by their path. Entries are encrypted with `storage.encrypt` (see
+//! [`PrivateFiles`]).
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::research::document::ResearchDoc;
+use crate::storage::PrivateFiles;
+
+/// A correction to research that was already done.
+#[derive(Debug, Clone)]
+pub struct Refinement {
+    /// The research being corrected.
+    pub previous: ResearchDoc,
+    /// What the developer wants changed.
+    pub correction: String,
+}
+
+impl Refinement {
+    pub fn new(previous: ResearchDoc, correction: impl Into<String>) -> Self {
+        Self {
+            previous,
+            correction: correction.into(),
+        }
+    }
+}
+
+/// Key of a retrieved chunk in the sent context log.
+pub fn chunk_key(path: &str, start_line: u32, end_line: u32) -> String {
+    format!("{}:{}-{}", path, start_line, end_line)
+}
+
+/// Context sent to the model for a task so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SentContext {
+    /// Keys of the chunks and files sent.
+    pub chunks: BTreeSet<String>,
+    /// When the entry last changed.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// File-backed log of the context sent per task, one entry per task.
+#[derive(Debug, Clone)]
+pub struct SentContextLog {
+    dir: PathBuf,
+    files: PrivateFiles,
+}
+
+impl SentContextLog {
+    /// Creates a log storing plaintext entries in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            files: PrivateFiles::default(),
+        }
+    }
+
+    /// Reads and writes entries through `files`, encrypting them if enabled.
+    pub fn with_encryption(mut self, files: PrivateFiles) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Keys sent for a task; empty when nothing was recorded.
+    pub fn load(&self, task_id: &str) -> BTreeSet<String> {
+        self.files
+            .read(&self.entry_path(task_id))
+            .ok()
+            .and_then(|json| serde_json::from_str::<SentContext>(&json).ok())
+            .map(|entry| entry.chunks)
+            .unwrap_or_default()
+    }
+
+    /// Store the keys sent for a task, replacing any previous entry.
+    pub fn store(&self, task_id: &str, chunks: BTreeSet<String>) -> io::Result<()> {
+        let entry = SentContext {
+            chunks,
+            updated_at: Some(Utc::now()),
+        };
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        std::fs::create_dir_all(&self.dir)?;
+        self.files
+            .write(&self.entry_path(task_id), json.as_bytes())
+            .map_err(io::Error::other)
+    }
+
+    /// Add keys to what was sent for a task.
+    pub fn extend(
+        &self,
+        task_id: &str,
+        chunks: impl IntoIterator<Item = String>,
+    ) -> io::Result<()> {
+        let mut sent = self.load(task_id);
+        sent.extend(chunks);
+        self.store(task_id, sent)
+    }
+
+    /// Forget what was sent for a task, so the next refinement resends all
+    /// of its context.
+    pub fn clear(&self, task_id: &str) -> io::Result<()> {
+        match std::fs::remove_file(self.entry_path(task_id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_path(&self, task_id: &str) -> PathBuf {
+        // Task IDs are UUIDs; keep anything else from escaping the log dir
+        let name: String = task_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::research::grounding::UngroundedReference;
+
 /// The output of the Research phase.
 ///
 /// Contains validated understanding of the codebase and context
@@ -18,6 +20,15 @@ pub struct ResearchDoc {
     pub suggested_approach: String,
     /// Sources referenced during research
     pub sources: Vec<Source>,
+    /// Confidence and open gaps reported by the self-critique pass, if it ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence_gaps: Option<ConfidenceGaps>,
+    /// Cited files or lines that don't match the codebase
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ungrounded: Vec<UngroundedReference>,
+    /// Questions about requirements the task leaves open, asked after research
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub follow_ups: Vec<FollowUp>,
 }
 
 impl ResearchDoc {
@@ -30,11 +41,36 @@ impl ResearchDoc {
             dependencies: Vec::new(),
             suggested_approach: String::new(),
             sources: Vec::new(),
+            confidence_gaps: None,
+            ungrounded: Vec::new(),
+            follow_ups: Vec::new(),
         }
     }
 
+    /// Findings at or above `min_severity`, most severe first.
+    ///
+    /// The sort is stable, so findings of equal severity keep their order.
+    pub fn findings_by_severity(&self, min_severity: Severity) -> Vec<&Finding> {
+        let mut findings: Vec<&Finding> = self
+            .codebase_analysis
+            .iter()
+            .filter(|f| f.severity >= min_severity)
+            .collect();
+        findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+        findings
+    }
+
     /// Converts the research document to markdown format.
     pub fn to_markdown(&self) -> String {
+        self.to_markdown_filtered(Severity::Info)
+    }
+
+    /// Converts the research document to markdown, keeping only findings
+    /// at or above `min_severity`.
+    ///
+    /// Findings are grouped by category and sorted by severity, most severe
+    /// first. Documents whose findings are all uncategorized keep a flat list.
+    pub fn to_markdown_filtered(&self, min_severity: Severity) -> String {
         let mut md = String::new();
 
         md.push_str(&format!("# Research: {}\n\n", self.task_name));
@@ -44,10 +80,37 @@ impl ResearchDoc {
         md.push_str("\n\n");
 
         md.push_str("## Codebase Analysis\n\n");
-        for finding in &self.codebase_analysis {
-            md.push_str(&format!("### {}\n\n", finding.title));
-            md.push_str(&finding.description);
-            md.push_str("\n\n");
+        let findings = self.findings_by_severity(min_severity);
+        if findings
+            .iter()
+            .all(|f| f.category == FindingCategory::General)
+        {
+            for finding in findings {
+                md.push_str(&format!("### {}\n\n", finding.title));
+                md.push_str(&finding.description);
+                md.push_str("\n\n");
+            }
+        } else {
+            for category in FindingCategory::ALL {
+                let group: Vec<&Finding> = findings
+                    .iter()
+                    .copied()
+                    .filter(|f| f.category == category)
+                    .collect();
+                if group.is_empty() {
+                    continue;
+                }
+                md.push_str(&format!("### {}\n\n", category.display_name()));
+                for finding in group {
+                    md.push_str(&format!(
+                        "#### [{}] {}\n\n",
+                        finding.severity.as_str(),
+                        finding.title
+                    ));
+                    md.push_str(&finding.description);
+                    md.push_str("\n\n");
+                }
+            }
         }
 
         md.push_str("## Dependencies\n\n");
@@ -68,6 +131,37 @@ impl ResearchDoc {
         md.push_str(&self.suggested_approach);
         md.push_str("\n\n");
 
+        if let Some(ref critique) = self.confidence_gaps {
+            md.push_str("## Confidence & Gaps\n\n");
+            md.push_str(&format!(
+                "**Confidence:** {}\n\n",
+                critique.confidence.as_str()
+            ));
+            for gap in &critique.gaps {
+                md.push_str(&format!("- {}\n", gap));
+            }
+            if !critique.gaps.is_empty() {
+                md.push('\n');
+            }
+        }
+
+        if !self.ungrounded.is_empty() {
+            md.push_str("## Unverified References\n\n");
+            for issue in &self.ungrounded {
+                md.push_str(&format!(
+                    "- `{}` in \"{}\": {}",
+                    issue.reference,
+                    issue.finding,
+                    issue.problem.describe()
+                ));
+                if let Some(ref corrected) = issue.corrected_to {
+                    md.push_str(&format!(", replaced with `{}`", corrected));
+                }
+                md.push('\n');
+            }
+            md.push('\n');
+        }
+
         md.push_str("## Sources\n\n");
         for source in &self.sources {
             md.push_str(&format!(
@@ -81,8 +175,24 @@ impl ResearchDoc {
     }
 }
 
+/// A question about a requirement the task leaves open, with suggested
+/// answers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowUp {
+    pub question: String,
+    #[serde(default)]
+    pub answers: Vec<String>,
+}
+
+impl FollowUp {
+    /// The correction that refines research with `answer` to this question.
+    pub fn correction(&self, answer: &str) -> String {
+        format!("{}\nAnswer: {}", self.question, answer)
+    }
+}
+
 /// A finding from codebase analysis.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Finding {
     /// Title of the finding
     pub title: String,
@@ -90,6 +200,149 @@ pub struct Finding {
     pub description: String,
     /// File paths related to this finding
     pub related_files: Vec<String>,
+    /// What kind of finding this is
+    #[serde(default)]
+    pub category: FindingCategory,
+    /// How much the finding matters for the task
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// Category of a research finding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FindingCategory {
+    /// How the relevant code is structured
+    Architecture,
+    /// Something that could break or go wrong
+    Risk,
+    /// A dependency the task relies on or affects
+    Dependency,
+    /// Missing or insufficient test coverage
+    TestGap,
+    /// Uncategorized finding
+    #[default]
+    General,
+}
+
+impl FindingCategory {
+    /// All categories, in the order they are rendered.
+    pub const ALL: [FindingCategory; 5] = [
+        FindingCategory::Architecture,
+        FindingCategory::Risk,
+        FindingCategory::Dependency,
+        FindingCategory::TestGap,
+        FindingCategory::General,
+    ];
+
+    /// Parse a category name, accepting `test_gap` and `test gap` spellings.
+    ///
+    /// Unknown names fall back to [`FindingCategory::General`].
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().replace(['_', ' '], "-").as_str() {
+            "architecture" => Self::Architecture,
+            "risk" => Self::Risk,
+            "dependency" | "dependencies" => Self::Dependency,
+            "test-gap" | "testing" => Self::TestGap,
+            _ => Self::General,
+        }
+    }
+
+    /// Heading used when rendering findings.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Architecture => "Architecture",
+            Self::Risk => "Risks",
+            Self::Dependency => "Dependencies",
+            Self::TestGap => "Test Gaps",
+            Self::General => "Other Findings",
+        }
+    }
+}
+
+/// Severity of a research finding, ordered from least to most severe.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Context only
+    #[default]
+    Info,
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    /// Parse a severity name; unknown names fall back to [`Severity::Info`].
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "low" => Self::Low,
+            "medium" | "moderate" => Self::Medium,
+            "high" | "critical" => Self::High,
+            _ => Self::Info,
+        }
+    }
+
+    /// Lowercase name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    /// The next more severe level, wrapping from `High` back to `Info`.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Info => Self::Low,
+            Self::Low => Self::Medium,
+            Self::Medium => Self::High,
+            Self::High => Self::Info,
+        }
+    }
+}
+
+/// The self-critique verdict on a research document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfidenceGaps {
+    /// How well the research answers the question
+    pub confidence: Confidence,
+    /// What remains uncertain or unverified
+    pub gaps: Vec<String>,
+}
+
+/// Confidence in a research document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Confidence {
+    /// Parse a confidence level; unknown names fall back to [`Confidence::Medium`].
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "low" => Self::Low,
+            "high" => Self::High,
+            _ => Self::Medium,
+        }
+    }
+
+    /// Lowercase name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
 }
 
 /// A dependency identified during research.
@@ -127,6 +380,8 @@ pub enum SourceType {
     Git,
     /// A knowledge graph search result (semantic search)
     KnowledgeGraph,
+    /// A documentation file indexed into the knowledge graph
+    Documentation,
 }
 
 impl SourceType {
@@ -138,6 +393,7 @@ impl SourceType {
             SourceType::Confluence => "Confluence",
             SourceType::Git => "Git",
             SourceType::KnowledgeGraph => "Knowledge Graph",
+            SourceType::Documentation => "Documentation",
         }
     }
 }
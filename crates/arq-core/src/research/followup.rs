@@ -0,0 +1,79 @@
+//! Follow-up questions about ambiguous requirements.
+//!
+//! With `research.follow_up_questions = true` (the default), a short call
+//! after research in the TUI asks the model which requirements the task
+//! leaves open: up to three targeted questions whose answers would change
+//! the approach, each with a few suggested answers, drawn from the gaps and
+//! assumptions in the research. They are stored on the document; the TUI
+//! offers the suggested answers as quick replies while the research awaits
+//! validation, and picking one refines the research with it.
+
+use serde::Deserialize;
+
+use crate::research::document::FollowUp;
+use crate::research::runner::extract_json;
+
+/// Most follow-up questions kept from a response.
+pub const MAX_FOLLOW_UPS: usize = 3;
+
+/// Most suggested answers kept per question.
+pub const MAX_ANSWERS: usize = 3;
+
+/// System prompt for the follow-up pass.
+pub const FOLLOW_UP_SYSTEM_PROMPT: &str = r#"You are helping a developer settle the requirements of a task after research into their codebase. Read the research document and find what the task leaves ambiguous: behavior it does not specify, choices between approaches the code supports equally, assumptions the research had to make, and gaps it reports.
+
+Ask at most 3 targeted questions whose answers would change the implementation. Do not ask about anything the research already answers from the code. For each question, suggest 2 or 3 short, distinct answers the developer is likely to pick.
+
+Respond with JSON only:
+{
+  "questions": [
+    {"question": "...", "answers": ["...", "..."]}
+  ]
+}
+
+Respond with an empty "questions" list if nothing is ambiguous."#;
+
+/// Builds the user prompt for the follow-up pass.
+pub fn build_follow_up_prompt(task_prompt: &str, research_json: &str) -> String {
+    format!(
+        r#"## Developer's Task
+
+{task_prompt}
+
+## Research Document
+
+```json
+{research_json}
+```
+
+List the follow-up questions as JSON."#
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowUpResponse {
+    #[serde(default)]
+    questions: Vec<FollowUp>,
+}
+
+/// Parses a follow-up response, dropping blank questions and answers and
+/// keeping at most [`MAX_FOLLOW_UPS`] questions of [`MAX_ANSWERS`] answers.
+pub fn parse_follow_ups(response: &str) -> Result<Vec<FollowUp>, serde_json::Error> {
+    let response: FollowUpResponse = serde_json::from_str(extract_json(response))?;
+    Ok(response
+        .questions
+        .into_iter()
+        .filter_map(|follow_up| {
+            let question = follow_up.question.trim().to_string();
+            let answers: Vec<String> = follow_up
+                .answers
+                .iter()
+                .map(|a| a.trim().to_string())
+                .filter(|a| !a.is_empty())
+                .take(MAX_ANSWERS)
+                .collect();
+            (!question.is_empty()).then_some(FollowUp { question, answers })
+        })
+        .take(MAX_FOLLOW_UPS)
+        .collect())
+}
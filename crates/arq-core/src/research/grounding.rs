@@ -0,0 +1,170 @@
+//! Grounding check for research answers.
+//!
+//! Models sometimes cite files that don't exist or line ranges past the end
+//! of a file, and a plan built on such a finding starts from a false
+//! premise. After the response is parsed, every file a finding cites is
+//! checked against the working tree; references that don't hold up are
+//! recorded on the document and rendered under "Unverified References".
+//! With `research.correct_references`, each is also replaced by the closest
+//! match from the knowledge graph.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::knowledge::{KnowledgeError, KnowledgeStore};
+use crate::research::document::ResearchDoc;
+
+/// Search results considered when looking for a replacement reference.
+const CORRECTION_CANDIDATES: usize = 5;
+
+/// A file cited by a finding, optionally narrowed to a line range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReference {
+    pub path: String,
+    pub start_line: Option<u32>,
+    pub end_line: Option<u32>,
+}
+
+impl FileReference {
+    /// Parse `path`, `path:12`, `path:12-40` or `path#L12-L40`.
+    pub fn parse(reference: &str) -> Self {
+        let reference = reference.trim().trim_matches('`');
+        let (path, lines) = match reference.rsplit_once(['#', ':']) {
+            Some((path, lines)) if is_line_range(lines) => (path, Some(lines)),
+            _ => (reference, None),
+        };
+        let (start_line, end_line) = lines
+            .map(|lines| {
+                let lines = lines.replace('L', "");
+                let (start, end) = lines.split_once('-').unwrap_or((&lines, &lines));
+                (start.parse().ok(), end.parse().ok())
+            })
+            .unwrap_or((None, None));
+
+        Self {
+            path: path.trim_start_matches("./").to_string(),
+            start_line,
+            end_line,
+        }
+    }
+}
+
+/// Whether `text` looks like `12`, `12-40` or `L12-L40`.
+fn is_line_range(text: &str) -> bool {
+    !text.is_empty()
+        && text.chars().any(|c| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == 'L')
+}
+
+/// Why a reference could not be verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GroundingProblem {
+    /// No file at the cited path.
+    MissingFile,
+    /// The cited lines run past the end of the file.
+    LinesOutOfRange { line_count: usize },
+}
+
+impl GroundingProblem {
+    /// Short description for reports.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::MissingFile => "file does not exist".to_string(),
+            Self::LinesOutOfRange { line_count } => {
+                format!("file has only {} lines", line_count)
+            }
+        }
+    }
+}
+
+/// A reference from the research that doesn't match the codebase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UngroundedReference {
+    /// Title of the finding citing the reference.
+    pub finding: String,
+    /// The reference as the model wrote it.
+    pub reference: String,
+    pub problem: GroundingProblem,
+    /// What the reference was replaced with, if it was corrected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_to: Option<String>,
+}
+
+/// Check every file reference of `doc`'s findings against the files under
+/// `root`.
+pub fn check_grounding(doc: &ResearchDoc, root: &Path) -> Vec<UngroundedReference> {
+    let mut ungrounded = Vec::new();
+    for finding in &doc.codebase_analysis {
+        for reference in &finding.related_files {
+            if let Some(problem) = verify(&FileReference::parse(reference), root) {
+                ungrounded.push(UngroundedReference {
+                    finding: finding.title.clone(),
+                    reference: reference.clone(),
+                    problem,
+                    corrected_to: None,
+                });
+            }
+        }
+    }
+    ungrounded
+}
+
+fn verify(reference: &FileReference, root: &Path) -> Option<GroundingProblem> {
+    let path = root.join(&reference.path);
+    if !path.is_file() {
+        return Some(GroundingProblem::MissingFile);
+    }
+    let last = reference.end_line.max(reference.start_line)?;
+    // Unreadable (e.g. binary) files are left alone: the file exists
+    let line_count = std::fs::read_to_string(&path).ok()?.lines().count();
+    (last as usize > line_count).then_some(GroundingProblem::LinesOutOfRange { line_count })
+}
+
+/// Replace each ungrounded reference in `doc` by the nearest chunk the
+/// knowledge graph finds for it, recording the replacement.
+///
+/// The search combines the finding title with the cited path. A chunk of
+/// the cited file wins, then one from a file with the same name, then the
+/// best hit overall.
+pub async fn correct_references(
+    doc: &mut ResearchDoc,
+    ungrounded: &mut [UngroundedReference],
+    kg: &dyn KnowledgeStore,
+) -> Result<(), KnowledgeError> {
+    for issue in ungrounded.iter_mut() {
+        let cited = FileReference::parse(&issue.reference);
+        let query = format!("{} {}", issue.finding, cited.path);
+        let results = kg.search_code(&query, CORRECTION_CANDIDATES).await?;
+        let file_name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+        let best = results
+            .iter()
+            .find(|r| r.path == cited.path)
+            .or_else(|| {
+                results
+                    .iter()
+                    .find(|r| file_name(&r.path) == file_name(&cited.path))
+            })
+            .or_else(|| results.first());
+        let Some(best) = best else {
+            continue;
+        };
+
+        let corrected = format!("{}:{}-{}", best.path, best.start_line, best.end_line);
+        for finding in doc
+            .codebase_analysis
+            .iter_mut()
+            .filter(|f| f.title == issue.finding)
+        {
+            for reference in finding.related_files.iter_mut() {
+                if *reference == issue.reference {
+                    *reference = corrected.clone();
+                }
+            }
+        }
+        issue.corrected_to = Some(corrected);
+    }
+    Ok(())
+}
@@ -1,6 +1,33 @@
+pub mod cache;
+pub mod compare;
+pub mod cost;
+pub mod critique;
+pub mod delta;
 mod document;
+pub mod followup;
+pub mod grounding;
+mod offline;
 pub mod prompts;
+pub mod rerank;
 mod runner;
+pub mod sanitize;
+pub mod sections;
+pub mod stats;
+pub mod summaries;
 
-pub use document::{Dependency, Finding, ResearchDoc, Source, SourceType};
+pub use cache::{CachedContext, ContextCache};
+pub use compare::{ComparedResearch, ResearchComparison};
+pub use cost::{CostConfirmation, CostEstimate, CostLimit};
+pub use delta::{Refinement, SentContextLog};
+pub use document::{
+    Confidence, ConfidenceGaps, Dependency, Finding, FindingCategory, FollowUp, ResearchDoc,
+    Severity, Source, SourceType,
+};
+pub use grounding::{GroundingProblem, UngroundedReference};
+pub use offline::{offline_research, structure_outline};
 pub use runner::{ResearchError, ResearchProgress, ResearchRunner};
+pub use sections::{DocSection, SectionParser};
+pub use stats::{PromptStats, SourceTokens};
+pub use summaries::{project_map, summarize_index, SummarizeReport};
+
+pub(crate) use runner::extract_json;
@@ -0,0 +1,202 @@
+//! Offline research built from the knowledge graph alone.
+//!
+//! Produces a deterministic "research skeleton" without calling an LLM:
+//! relevant files from semantic search, call dependencies and impact for
+//! the functions those chunks cover, and an outline of where they live.
+
+use std::collections::BTreeMap;
+
+use crate::config::RerankWeights;
+use crate::knowledge::{KnowledgeError, KnowledgeStore, SearchResult};
+use crate::research::document::{Dependency, Finding, ResearchDoc, Source, SourceType};
+use crate::research::rerank::search_reranked;
+use crate::research::runner::SMART_CONTEXT_RESULTS;
+use crate::Task;
+
+/// Maximum number of matched functions expanded with dependencies and impact.
+const MAX_OFFLINE_FUNCTIONS: usize = 10;
+
+/// A function covered by a search result, with its call relationships.
+struct MatchedFunction {
+    name: String,
+    file_path: String,
+    start_line: u32,
+    calls: Vec<String>,
+    called_by: Vec<String>,
+}
+
+/// Build a research skeleton for a task from the knowledge graph.
+///
+/// Output depends only on the indexed graph, so repeated runs give the same document.
+pub async fn offline_research(
+    task: &Task,
+    store: &dyn KnowledgeStore,
+    weights: &RerankWeights,
+) -> Result<ResearchDoc, KnowledgeError> {
+    let results = search_reranked(store, &task.prompt, SMART_CONTEXT_RESULTS, weights).await?;
+    let files = relevant_files(&results);
+
+    let mut functions: Vec<MatchedFunction> = Vec::new();
+    for result in &results {
+        let covered = store
+            .functions_in_range(&result.path, result.start_line, result.end_line)
+            .await
+            .unwrap_or_default();
+        for function in covered {
+            if functions.len() >= MAX_OFFLINE_FUNCTIONS {
+                break;
+            }
+            if functions
+                .iter()
+                .any(|f| f.name == function.name && f.file_path == function.file_path)
+            {
+                continue;
+            }
+            let entity_id = function.id.clone().unwrap_or_else(|| function.name.clone());
+            functions.push(MatchedFunction {
+                calls: store.get_dependencies(&entity_id).await?,
+                called_by: store.get_impact(&entity_id).await?,
+                name: function.name,
+                file_path: function.file_path,
+                start_line: function.start_line,
+            });
+        }
+    }
+
+    let mut doc = ResearchDoc::new(&task.name);
+    doc.summary = format!(
+        "Offline research skeleton for \"{}\": {} relevant file(s) and {} matched function(s) \
+         from the knowledge graph. No LLM was used, so findings list code locations and call \
+         relationships without interpretation.",
+        task.prompt,
+        files.len(),
+        functions.len()
+    );
+
+    for (path, ranges) in &files {
+        doc.codebase_analysis.push(Finding {
+            title: format!("Relevant file: {}", path),
+            description: format!("Matched lines {}.", ranges.join(", ")),
+            related_files: vec![path.clone()],
+            ..Default::default()
+        });
+    }
+
+    for function in &functions {
+        doc.codebase_analysis.push(Finding {
+            title: format!("Function: {}", function.name),
+            description: format!(
+                "Defined in {}:{}.\n\nCalls: {}\n\nCalled by: {}",
+                function.file_path,
+                function.start_line,
+                list_or_none(&function.calls),
+                list_or_none(&function.called_by)
+            ),
+            related_files: vec![function.file_path.clone()],
+            ..Default::default()
+        });
+    }
+
+    if !files.is_empty() {
+        doc.codebase_analysis.push(Finding {
+            title: "Structure outline".to_string(),
+            description: structure_outline(files.iter().map(|(path, _)| path.as_str())),
+            related_files: files.iter().map(|(path, _)| path.clone()).collect(),
+            ..Default::default()
+        });
+    }
+
+    doc.dependencies = internal_dependencies(&functions);
+
+    doc.suggested_approach = match files.first() {
+        Some((path, _)) => format!(
+            "Start from {} and follow the call relationships above. Run 'arq research' \
+             with an LLM configured for a suggested approach.",
+            path
+        ),
+        None => "No relevant code was found in the knowledge graph. Run 'arq init' to \
+                 index the codebase, or run 'arq research' with an LLM configured."
+            .to_string(),
+    };
+
+    doc.sources = files
+        .iter()
+        .map(|(path, _)| Source {
+            source_type: SourceType::File,
+            location: path.clone(),
+        })
+        .collect();
+
+    Ok(doc)
+}
+
+/// Files in search result order, each with its matched line ranges.
+fn relevant_files(results: &[SearchResult]) -> Vec<(String, Vec<String>)> {
+    let mut files: Vec<(String, Vec<String>)> = Vec::new();
+
+    for result in results {
+        let range = format!("{}-{}", result.start_line, result.end_line);
+        match files.iter_mut().find(|(path, _)| *path == result.path) {
+            Some((_, ranges)) => {
+                if !ranges.contains(&range) {
+                    ranges.push(range);
+                }
+            }
+            None => files.push((result.path.clone(), vec![range])),
+        }
+    }
+
+    files
+}
+
+/// Callees of the matched functions, with the functions that call them.
+fn internal_dependencies(functions: &[MatchedFunction]) -> Vec<Dependency> {
+    let mut callers: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for function in functions {
+        for callee in &function.calls {
+            let entry = callers.entry(callee.as_str()).or_default();
+            if !entry.contains(&function.name.as_str()) {
+                entry.push(&function.name);
+            }
+        }
+    }
+
+    callers
+        .into_iter()
+        .map(|(callee, callers)| Dependency {
+            name: callee.to_string(),
+            description: format!("Called by {}", callers.join(", ")),
+            is_external: false,
+        })
+        .collect()
+}
+
+/// Markdown outline of files grouped by directory, sorted by path.
+pub fn structure_outline<'a>(paths: impl IntoIterator<Item = &'a str>) -> String {
+    let mut dirs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for path in paths {
+        let (dir, file) = path.rsplit_once('/').unwrap_or((".", path));
+        let files = dirs.entry(dir).or_default();
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+
+    let mut outline = String::new();
+    for (dir, mut files) in dirs {
+        files.sort_unstable();
+        outline.push_str(&format!("- {}/\n", dir));
+        for file in files {
+            outline.push_str(&format!("  - {}\n", file));
+        }
+    }
+    outline.trim_end().to_string()
+}
+
+fn list_or_none(names: &[String]) -> String {
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
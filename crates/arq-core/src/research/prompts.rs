@@ -25,3 +25,43 @@ pub fn build_research_prompt(task_prompt: &str, context: &str) -> String {
 IMPORTANT: Your response must DIRECTLY answer the developer's question above. Use the code provided as evidence. Do NOT give a generic codebase overview - focus specifically on answering their question with concrete details from the code."#
     )
 }
+
+/// Builds the user prompt for refining research after a correction.
+///
+/// Carries the previous research as JSON and only the context the model
+/// hasn't seen yet; `new_context` is empty when retrieval found nothing new.
+pub fn build_refinement_prompt(
+    task_prompt: &str,
+    previous_research: &str,
+    correction: &str,
+    new_context: &str,
+) -> String {
+    let new_context = if new_context.trim().is_empty() {
+        "(No code beyond what the previous research was based on.)"
+    } else {
+        new_context
+    };
+    format!(
+        r#"## Developer's Question
+
+{task_prompt}
+
+## Previous Research
+
+```json
+{previous_research}
+```
+
+## Developer's Correction
+
+{correction}
+
+## Additional Code from Codebase
+
+Code retrieved for the correction that was not part of the context of the previous research:
+
+{new_context}
+
+Update the previous research to address the correction. Keep findings that still hold, fix or drop the ones the correction disputes, and use the additional code as evidence. Reply with the complete updated research in the same JSON format."#
+    )
+}
@@ -0,0 +1,129 @@
+//! Re-ranking of semantic search results using entity metadata.
+//!
+//! Cosine similarity alone favours short, keyword-dense chunks such as getters
+//! and test fixtures. Scores are adjusted using the functions that overlap each
+//! chunk: public and more complex code is boosted, tests and trivial one-liners
+//! are penalized.
+
+use std::collections::HashSet;
+
+use crate::config::RerankWeights;
+use crate::knowledge::ontology::nodes::{FunctionEntity, Visibility};
+use crate::knowledge::test_impact::is_test_path;
+use crate::knowledge::{KnowledgeError, KnowledgeStore, SearchResult};
+
+/// Search candidates fetched per returned result when re-ranking.
+const RERANK_OVERSAMPLE: usize = 2;
+
+/// Complexity points above which the boost stops growing.
+const MAX_COMPLEXITY_POINTS: u32 = 10;
+
+/// Functions at or below this many lines (with no branches) count as trivial.
+const TRIVIAL_MAX_LOC: u32 = 3;
+
+/// Semantic search, re-ranked by entity metadata when weights are set.
+///
+/// Fetches extra candidates so that demoted chunks (tests, getters) can be
+/// replaced by core logic ranked just below them.
+pub async fn search_reranked(
+    store: &dyn KnowledgeStore,
+    query: &str,
+    limit: usize,
+    weights: &RerankWeights,
+) -> Result<Vec<SearchResult>, KnowledgeError> {
+    if !weights.is_enabled() {
+        return store.search_code(query, limit).await;
+    }
+
+    let candidates = store.search_code(query, limit * RERANK_OVERSAMPLE).await?;
+
+    let mut scored = Vec::with_capacity(candidates.len());
+    for result in candidates {
+        let functions = store
+            .functions_in_range(&result.path, result.start_line, result.end_line)
+            .await
+            .unwrap_or_default();
+        scored.push((adjusted_score(&result, &functions, weights), result));
+    }
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, result)| result)
+        .collect())
+}
+
+/// Merge result lists from several queries, dropping duplicate chunks.
+///
+/// Lists are interleaved round-robin so the top hits of every query (e.g. a
+/// user correction next to the original prompt) stay near the front.
+pub fn merge_results(lists: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+    let mut iters: Vec<_> = lists.into_iter().map(Vec::into_iter).collect();
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+
+    loop {
+        let mut exhausted = true;
+        for iter in &mut iters {
+            if let Some(result) = iter.next() {
+                exhausted = false;
+                if seen.insert((result.path.clone(), result.start_line, result.end_line)) {
+                    merged.push(result);
+                }
+            }
+        }
+        if exhausted {
+            return merged;
+        }
+    }
+}
+
+/// Similarity of a search result adjusted by the metadata of the functions it overlaps.
+pub fn adjusted_score(
+    result: &SearchResult,
+    functions: &[FunctionEntity],
+    weights: &RerankWeights,
+) -> f32 {
+    let mut score = result.score;
+
+    if is_test_code(&result.path, functions) {
+        score -= weights.test_penalty;
+    }
+
+    if functions.is_empty() {
+        return score;
+    }
+
+    if functions.iter().any(|f| f.visibility == Visibility::Public) {
+        score += weights.public_boost;
+    }
+
+    let max_cyclomatic = functions
+        .iter()
+        .filter_map(|f| f.complexity.as_ref())
+        .map(|c| c.cyclomatic)
+        .max()
+        .unwrap_or(1);
+    let points = max_cyclomatic.saturating_sub(1).min(MAX_COMPLEXITY_POINTS);
+    score += weights.complexity_boost * points as f32;
+
+    if functions.iter().all(is_trivial) {
+        score -= weights.trivial_penalty;
+    }
+
+    score
+}
+
+/// Whether a chunk belongs to test code, by path or by the functions it contains.
+pub fn is_test_code(path: &str, functions: &[FunctionEntity]) -> bool {
+    is_test_path(path)
+        || (!functions.is_empty() && functions.iter().all(|f| f.name.starts_with("test")))
+}
+
+/// A function with no branches and only a few lines, such as a getter.
+fn is_trivial(function: &FunctionEntity) -> bool {
+    let loc = function.end_line.saturating_sub(function.start_line) + 1;
+    let cyclomatic = function.complexity.as_ref().map_or(1, |c| c.cyclomatic);
+    loc <= TRIVIAL_MAX_LOC && cyclomatic <= 1
+}
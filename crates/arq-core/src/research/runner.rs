@@ -1,16 +1,40 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::mpsc;
 
+use crate::config::{ContentGuard, RerankWeights};
 use crate::context::{ContextBuilder, ContextError};
-use crate::knowledge::{KnowledgeError, KnowledgeStore, SearchResult};
-use crate::llm::{LLMError, StreamChunk, LLM};
-use crate::research::document::{Dependency, Finding, ResearchDoc, Source, SourceType};
-use crate::research::prompts::{build_research_prompt, RESEARCH_SYSTEM_PROMPT};
+use crate::knowledge::indexer::{matches_pattern, DOCUMENT_ENTITY_TYPE};
+use crate::knowledge::{KnowledgeError, KnowledgeStore, PathBlocklist, SearchResult};
+use crate::llm::{CancellationToken, LLMError, StreamChunk, TokenUsage, LLM};
+use crate::prompts::localize;
+use crate::research::cache::{context_cache_key, ContextCache};
+use crate::research::cost::{CostConfirmation, CostLimit};
+use crate::research::critique::{
+    apply_critique, build_critique_prompt, parse_critique, CRITIQUE_SYSTEM_PROMPT,
+};
+use crate::research::delta::{chunk_key, Refinement, SentContextLog};
+use crate::research::document::{
+    Dependency, Finding, FindingCategory, ResearchDoc, Severity, Source, SourceType,
+};
+use crate::research::followup::{
+    build_follow_up_prompt, parse_follow_ups, FOLLOW_UP_SYSTEM_PROMPT,
+};
+use crate::research::grounding::{check_grounding, correct_references};
+use crate::research::prompts::{
+    build_refinement_prompt, build_research_prompt, get_research_system_prompt,
+};
+use crate::research::rerank::{is_test_code, merge_results, search_reranked};
+use crate::research::sanitize::{context_prompt, guarded_system_prompt, wrap_untrusted};
+use crate::research::sections::{DocSection, SectionParser};
+use crate::research::stats::PromptStats;
+use crate::research::summaries::project_map;
 use crate::Task;
 
 /// Progress events during research.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ResearchProgress {
     /// Research has started
     Started,
@@ -20,21 +44,69 @@ pub enum ResearchProgress {
     SearchingKnowledgeGraph,
     /// Found results from knowledge graph
     KnowledgeGraphResults { count: usize },
+    /// Reused context cached by an earlier run, with this many sources
+    ContextFromCache { count: usize },
+    /// Matches in these files were left out of the prompt because they are
+    /// under `context.private_paths`
+    PrivateContextWithheld { files: Vec<String> },
     /// Calling LLM for analysis
     CallingLLM,
+    /// Estimated composition of the prompt sent to the LLM
+    PromptStats(PromptStats),
+    /// The prompt costs more than the limit; the LLM is called once this is
+    /// answered with `true`
+    ConfirmCost(CostConfirmation),
+    /// Estimated tokens used by the LLM call
+    TokenUsage(TokenUsage),
+    /// A section of the response streamed in completely, ahead of the
+    /// parsed document
+    Section(DocSection),
     /// Parsing the LLM response
     ParsingResponse,
+    /// Reviewing the research against the self-critique rubric
+    SelfCritique,
+    /// Asking follow-up questions about ambiguous requirements
+    FollowUpQuestions,
     /// Research completed successfully
     Complete,
     /// An error occurred
     Error(String),
 }
 
+/// Number of search results included in smart context.
+pub(crate) const SMART_CONTEXT_RESULTS: usize = 15;
+
+/// Context gathered for a prompt.
+struct GatheredContext {
+    context: String,
+    sources: Vec<Source>,
+    /// Keys of the chunks, or of the files, in the context
+    chunks: Vec<String>,
+    /// Private files whose matches were left out
+    withheld: Vec<String>,
+}
+
 /// Runs the research phase for a task.
 pub struct ResearchRunner<L: LLM> {
     llm: L,
     context_builder: ContextBuilder,
     knowledge_store: Option<Arc<dyn KnowledgeStore>>,
+    content_guard: ContentGuard,
+    rerank: RerankWeights,
+    context_queries: Vec<String>,
+    context_cache: Option<ContextCache>,
+    sent_context: Option<SentContextLog>,
+    refinement: Option<Refinement>,
+    excluded_files: Vec<String>,
+    private_paths: PathBlocklist,
+    include_tests: bool,
+    system_prompt: Option<String>,
+    self_critique: bool,
+    follow_ups: bool,
+    correct_references: bool,
+    output_language: Option<String>,
+    cost_limit: Option<CostLimit>,
+    cancel: CancellationToken,
 }
 
 impl<L: LLM> ResearchRunner<L> {
@@ -44,6 +116,22 @@ impl<L: LLM> ResearchRunner<L> {
             llm,
             context_builder,
             knowledge_store: None,
+            content_guard: ContentGuard::default(),
+            rerank: RerankWeights::default(),
+            context_queries: Vec::new(),
+            context_cache: None,
+            sent_context: None,
+            refinement: None,
+            excluded_files: Vec::new(),
+            private_paths: PathBlocklist::default(),
+            include_tests: false,
+            system_prompt: None,
+            self_critique: false,
+            follow_ups: false,
+            correct_references: false,
+            output_language: None,
+            cost_limit: None,
+            cancel: CancellationToken::new(),
         }
     }
 
@@ -57,40 +145,299 @@ impl<L: LLM> ResearchRunner<L> {
             llm,
             context_builder,
             knowledge_store: Some(knowledge_store),
+            content_guard: ContentGuard::default(),
+            rerank: RerankWeights::default(),
+            context_queries: Vec::new(),
+            context_cache: None,
+            sent_context: None,
+            refinement: None,
+            excluded_files: Vec::new(),
+            private_paths: PathBlocklist::default(),
+            include_tests: false,
+            system_prompt: None,
+            self_critique: false,
+            follow_ups: false,
+            correct_references: false,
+            output_language: None,
+            cost_limit: None,
+            cancel: CancellationToken::new(),
         }
     }
 
-    /// Runs research for the given task.
-    pub async fn run(&self, task: &Task) -> Result<ResearchDoc, ResearchError> {
-        // 1. Gather context - use knowledge graph if available, otherwise fall back to file scan
-        let (context_str, sources) = if let Some(ref kg) = self.knowledge_store {
-            self.gather_smart_context(kg, &task.prompt).await?
+    /// Sets how retrieved code is guarded against prompt injection.
+    pub fn with_content_guard(mut self, guard: ContentGuard) -> Self {
+        self.content_guard = guard;
+        self
+    }
+
+    /// Sets the weights used to re-rank semantic search results.
+    pub fn with_rerank(mut self, weights: RerankWeights) -> Self {
+        self.rerank = weights;
+        self
+    }
+
+    /// Sets the semantic search queries used to gather context.
+    ///
+    /// By default the task prompt is the only query. Refinements pass the
+    /// original prompt and the user's correction so that code the correction
+    /// mentions is retrieved too; results of all queries are merged.
+    pub fn with_context_queries(mut self, queries: Vec<String>) -> Self {
+        self.context_queries = queries
+            .into_iter()
+            .filter(|q| !q.trim().is_empty())
+            .collect();
+        self
+    }
+
+    /// Caches assembled context between runs of the same task.
+    ///
+    /// Cached context is reused while the codebase, queries and settings
+    /// that shaped it are unchanged.
+    pub fn with_context_cache(mut self, cache: ContextCache) -> Self {
+        self.context_cache = Some(cache);
+        self
+    }
+
+    /// Records the context sent for each task, so that refinements only send
+    /// what the model hasn't seen yet.
+    pub fn with_sent_context(mut self, log: SentContextLog) -> Self {
+        self.sent_context = Some(log);
+        self
+    }
+
+    /// Refines earlier research after a correction instead of starting over.
+    ///
+    /// The prompt carries the previous research, the correction and the
+    /// context retrieved for the correction minus what was already sent for
+    /// the task (see [`Self::with_sent_context`]). Without a sent context
+    /// log, all retrieved context is included.
+    pub fn with_refinement(mut self, refinement: Refinement) -> Self {
+        self.refinement = Some(refinement);
+        self
+    }
+
+    /// Replaces the built-in research system prompt, e.g. with the research
+    /// persona from [`crate::prompts::for_phase`].
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Keeps files matching any of `patterns` out of the context sent to the
+    /// LLM, e.g. the exclusions a developer chose for the task.
+    ///
+    /// Patterns use the syntax of `knowledge.generated_patterns`; a plain
+    /// relative path excludes that file.
+    pub fn with_excluded_files(mut self, patterns: Vec<String>) -> Self {
+        self.excluded_files = patterns;
+        self
+    }
+
+    /// Never sends code under `private_paths` to the LLM: it stays indexed
+    /// and searchable locally, but matches in it are withheld from the
+    /// context, and reported with [`ResearchProgress::PrivateContextWithheld`].
+    pub fn with_private_paths(mut self, private_paths: PathBlocklist) -> Self {
+        self.private_paths = private_paths;
+        self
+    }
+
+    /// Includes test files in the context. They are left out by default
+    /// since they rarely explain the code and crowd out what does.
+    pub fn with_include_tests(mut self, include: bool) -> Self {
+        self.include_tests = include;
+        self
+    }
+
+    /// Whether `path` was excluded from the context.
+    fn is_excluded(&self, path: &str) -> bool {
+        self.excluded_files
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path))
+            || (!self.include_tests && is_test_code(path, &[]))
+    }
+
+    /// Whether a search result is excluded from the context, also leaving out
+    /// files the knowledge graph only knows test functions in.
+    async fn is_result_excluded(
+        &self,
+        kg: &Arc<dyn KnowledgeStore>,
+        result: &SearchResult,
+    ) -> bool {
+        if self.is_excluded(&result.path) {
+            return true;
+        }
+        if self.include_tests {
+            return false;
+        }
+        let functions = kg
+            .functions_in_range(&result.path, result.start_line, result.end_line)
+            .await
+            .unwrap_or_default();
+        is_test_code(&result.path, &functions)
+    }
+
+    /// Files the context for `task` would be gathered from, before exclusions,
+    /// for reviewing them before the LLM call.
+    ///
+    /// Test files are left out unless included with [`Self::with_include_tests`],
+    /// and private files always are.
+    pub async fn context_candidates(&self, task: &Task) -> Result<Vec<String>, ResearchError> {
+        let left_out = |path: &str| {
+            !self.include_tests && is_test_code(path, &[]) || self.private_paths.blocks(path)
+        };
+        if let Some(ref kg) = self.knowledge_store {
+            let queries = self.retrieval_queries(task);
+            let results = self.search_reranked(kg, &queries).await?;
+            if !results.is_empty() {
+                let mut files: Vec<String> = Vec::new();
+                for result in results {
+                    if !left_out(&result.path) && !files.contains(&result.path) {
+                        files.push(result.path);
+                    }
+                }
+                return Ok(files);
+            }
+        }
+        let context = self.context_builder.gather()?;
+        Ok(context
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .filter(|path| !left_out(path))
+            .collect())
+    }
+
+    /// Has the model review its research against a rubric in a second call,
+    /// fixing what it can and reporting its confidence and remaining gaps.
+    pub fn with_self_critique(mut self, enabled: bool) -> Self {
+        self.self_critique = enabled;
+        self
+    }
+
+    /// Asks the model for follow-up questions about requirements the task
+    /// leaves open in a second call, stored on the research.
+    pub fn with_follow_ups(mut self, enabled: bool) -> Self {
+        self.follow_ups = enabled;
+        self
+    }
+
+    /// Replace references that fail the grounding check with the nearest
+    /// knowledge graph match instead of only flagging them.
+    pub fn with_correct_references(mut self, enabled: bool) -> Self {
+        self.correct_references = enabled;
+        self
+    }
+
+    /// Has the model write its prose in `language`, keeping identifiers and
+    /// paths as they are; `None` keeps the prompts' language.
+    pub fn with_output_language(mut self, language: Option<String>) -> Self {
+        self.output_language = language;
+        self
+    }
+
+    /// Asks for confirmation before sending a prompt that costs more than
+    /// `limit`, through [`ResearchProgress::ConfirmCost`]. Runs without a
+    /// progress channel can't ask and fail with
+    /// [`ResearchError::CostDeclined`] instead.
+    pub fn with_cost_limit(mut self, limit: Option<CostLimit>) -> Self {
+        self.cost_limit = limit;
+        self
+    }
+
+    /// Stops the LLM call, and the request billing, when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Queries for smart context: the configured queries, or the task prompt.
+    fn retrieval_queries<'a>(&'a self, task: &'a Task) -> Vec<&'a str> {
+        if self.context_queries.is_empty() {
+            vec![task.prompt.as_str()]
         } else {
-            let context = self.context_builder.gather()?;
-            let sources: Vec<Source> = context
-                .files
-                .iter()
-                .map(|f| Source {
-                    source_type: SourceType::File,
-                    location: f.path.clone(),
-                })
-                .collect();
-            (context.to_prompt_string(), sources)
+            self.context_queries.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// The research system prompt, including the untrusted content notice.
+    fn system_prompt(&self) -> String {
+        localize(
+            &guarded_system_prompt(
+                get_research_system_prompt(self.system_prompt.as_deref()),
+                self.content_guard,
+            ),
+            self.output_language.as_deref(),
+        )
+    }
+
+    /// Non-streaming completion that is dropped when the run is cancelled.
+    async fn complete(&self, system: &str, prompt: &str) -> Result<String, LLMError> {
+        tokio::select! {
+            _ = self.cancel.cancelled() => Err(LLMError::Cancelled),
+            response = self.llm.complete_with_system(system, prompt) => response,
+        }
+    }
+
+    /// Returns once the prompt may be sent: it is within the cost limit, or
+    /// sending it was confirmed through `progress_tx`.
+    async fn confirm_cost(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress_tx: Option<&mpsc::UnboundedSender<ResearchProgress>>,
+    ) -> Result<(), ResearchError> {
+        let Some(estimate) = self
+            .cost_limit
+            .as_ref()
+            .and_then(|limit| limit.check(system, prompt))
+        else {
+            return Ok(());
+        };
+        let declined = ResearchError::CostDeclined {
+            cost: estimate.cost,
+            threshold: estimate.threshold,
+        };
+        let Some(tx) = progress_tx else {
+            return Err(declined);
+        };
+
+        let (confirmation, reply) = CostConfirmation::new(estimate);
+        if tx
+            .send(ResearchProgress::ConfirmCost(confirmation))
+            .is_err()
+        {
+            return Err(declined);
+        }
+        let confirmed = tokio::select! {
+            _ = self.cancel.cancelled() => return Err(LLMError::Cancelled.into()),
+            reply = reply => reply.unwrap_or(false),
         };
+        if confirmed {
+            Ok(())
+        } else {
+            Err(declined)
+        }
+    }
 
-        // 2. Build prompt
-        let prompt = build_research_prompt(&task.prompt, &context_str);
+    /// Runs research for the given task.
+    pub async fn run(&self, task: &Task) -> Result<ResearchDoc, ResearchError> {
+        // 1-2. Gather context and build the prompt
+        let (prompt, context_str, sources) = self.prepare(task, None).await?;
 
         // 3. Call LLM
-        let response = self
-            .llm
-            .complete_with_system(RESEARCH_SYSTEM_PROMPT, &prompt)
-            .await?;
+        let system_prompt = self.system_prompt();
+        self.confirm_cost(&system_prompt, &prompt, None).await?;
+        let response = self.complete(&system_prompt, &prompt).await?;
 
-        // 4. Parse response into ResearchDoc
-        let doc = self.parse_response(&task.name, &response, sources)?;
+        // 4. Parse response into ResearchDoc; an unparseable one isn't cached
+        let doc = self
+            .parse_response(&task.name, &response, sources)
+            .inspect_err(|_| self.llm.evict(Some(&system_prompt), &prompt))?;
 
-        Ok(doc)
+        // 5. Review it, if enabled, and check its references
+        let doc = self.critique(task, &context_str, doc, None).await?;
+        let doc = self.ground(doc).await;
+        self.ask_follow_ups(task, doc, None).await
     }
 
     /// Runs research with progress callbacks.
@@ -103,41 +450,35 @@ impl<L: LLM> ResearchRunner<L> {
     ) -> Result<ResearchDoc, ResearchError> {
         let _ = progress_tx.send(ResearchProgress::Started);
 
-        // 1. Gather context
-        let (context_str, sources) = if let Some(ref kg) = self.knowledge_store {
-            let _ = progress_tx.send(ResearchProgress::SearchingKnowledgeGraph);
-            let result = self.gather_smart_context(kg, &task.prompt).await?;
-            // Count sources for progress
-            let count = result.1.len();
-            let _ = progress_tx.send(ResearchProgress::KnowledgeGraphResults { count });
-            result
-        } else {
-            let _ = progress_tx.send(ResearchProgress::GatheringContext);
-            let context = self.context_builder.gather()?;
-            let sources: Vec<Source> = context
-                .files
-                .iter()
-                .map(|f| Source {
-                    source_type: SourceType::File,
-                    location: f.path.clone(),
-                })
-                .collect();
-            (context.to_prompt_string(), sources)
-        };
-
-        // 2. Build prompt
-        let prompt = build_research_prompt(&task.prompt, &context_str);
+        // 1-2. Gather context and build the prompt
+        let (prompt, context_str, sources) = self.prepare(task, Some(&progress_tx)).await?;
 
         // 3. Call LLM
         let _ = progress_tx.send(ResearchProgress::CallingLLM);
-        let response = self
-            .llm
-            .complete_with_system(RESEARCH_SYSTEM_PROMPT, &prompt)
+        let system_prompt = self.system_prompt();
+        let _ = progress_tx.send(ResearchProgress::PromptStats(PromptStats::from_prompt(
+            &system_prompt,
+            &prompt,
+        )));
+        self.confirm_cost(&system_prompt, &prompt, Some(&progress_tx))
             .await?;
+        let response = self.complete(&system_prompt, &prompt).await?;
+        let _ = progress_tx.send(ResearchProgress::TokenUsage(TokenUsage::estimate(
+            &system_prompt,
+            &prompt,
+            &response,
+        )));
 
         // 4. Parse response
         let _ = progress_tx.send(ResearchProgress::ParsingResponse);
-        let doc = self.parse_response(&task.name, &response, sources)?;
+        let doc = self
+            .parse_response(&task.name, &response, sources)
+            .inspect_err(|_| self.llm.evict(Some(&system_prompt), &prompt))?;
+        let doc = self
+            .critique(task, &context_str, doc, Some(&progress_tx))
+            .await?;
+        let doc = self.ground(doc).await;
+        let doc = self.ask_follow_ups(task, doc, Some(&progress_tx)).await?;
 
         let _ = progress_tx.send(ResearchProgress::Complete);
         Ok(doc)
@@ -154,119 +495,413 @@ impl<L: LLM> ResearchRunner<L> {
     ) -> Result<ResearchDoc, ResearchError> {
         let _ = progress_tx.send(ResearchProgress::Started);
 
-        // 1. Gather context
-        let (context_str, sources) = if let Some(ref kg) = self.knowledge_store {
-            let _ = progress_tx.send(ResearchProgress::SearchingKnowledgeGraph);
-            let result = self.gather_smart_context(kg, &task.prompt).await?;
-            let count = result.1.len();
-            let _ = progress_tx.send(ResearchProgress::KnowledgeGraphResults { count });
-            result
-        } else {
-            let _ = progress_tx.send(ResearchProgress::GatheringContext);
-            let context = self.context_builder.gather()?;
-            let sources: Vec<Source> = context
-                .files
-                .iter()
-                .map(|f| Source {
-                    source_type: SourceType::File,
-                    location: f.path.clone(),
-                })
-                .collect();
-            (context.to_prompt_string(), sources)
-        };
-
-        // 2. Build prompt
-        let prompt = build_research_prompt(&task.prompt, &context_str);
+        // 1-2. Gather context and build the prompt
+        let (prompt, context_str, sources) = self.prepare(task, Some(&progress_tx)).await?;
 
         // 3. Stream LLM response
         let _ = progress_tx.send(ResearchProgress::CallingLLM);
+        let system_prompt = self.system_prompt();
+        let _ = progress_tx.send(ResearchProgress::PromptStats(PromptStats::from_prompt(
+            &system_prompt,
+            &prompt,
+        )));
+        self.confirm_cost(&system_prompt, &prompt, Some(&progress_tx))
+            .await?;
 
-        // Collect streamed response
-        let response = if self.llm.supports_streaming() {
+        // Collect streamed response, reporting sections as they complete
+        let sections = if self.llm.supports_streaming() {
             // Use streaming - collect chunks while forwarding to stream_tx
             let (collector_tx, mut collector_rx) = mpsc::unbounded_channel::<StreamChunk>();
 
             // Spawn task to forward chunks and collect full response
             let stream_tx_clone = stream_tx.clone();
+            let section_tx = progress_tx.clone();
             let collect_handle = tokio::spawn(async move {
-                let mut full_response = String::new();
+                let mut sections = SectionParser::new();
                 while let Some(chunk) = collector_rx.recv().await {
                     if !chunk.is_final {
-                        full_response.push_str(&chunk.text);
+                        for section in sections.feed(&chunk.text) {
+                            let _ = section_tx.send(ResearchProgress::Section(section));
+                        }
                     }
                     // Forward to TUI
                     let _ = stream_tx_clone.send(chunk);
                 }
-                full_response
+                sections
             });
 
             // Start streaming
             self.llm
-                .stream_complete(RESEARCH_SYSTEM_PROMPT, &prompt, collector_tx)
+                .stream_complete(&system_prompt, &prompt, collector_tx, self.cancel.clone())
                 .await?;
 
             // Wait for collection to complete
             collect_handle.await.unwrap_or_default()
         } else {
             // Non-streaming fallback
-            let response = self
-                .llm
-                .complete_with_system(RESEARCH_SYSTEM_PROMPT, &prompt)
-                .await?;
+            let response = self.complete(&system_prompt, &prompt).await?;
+            let mut sections = SectionParser::new();
+            for section in sections.feed(&response) {
+                let _ = progress_tx.send(ResearchProgress::Section(section));
+            }
             // Send as single chunk
-            let _ = stream_tx.send(StreamChunk::text(response.clone()));
+            let _ = stream_tx.send(StreamChunk::text(response));
             let _ = stream_tx.send(StreamChunk::done());
-            response
+            sections
         };
+        let response = sections.text().to_string();
 
-        // 4. Parse response
+        let _ = progress_tx.send(ResearchProgress::TokenUsage(TokenUsage::estimate(
+            &system_prompt,
+            &prompt,
+            &response,
+        )));
+
+        // 4. Parse response; complete sections already are the document
         let _ = progress_tx.send(ResearchProgress::ParsingResponse);
-        let doc = self.parse_response(&task.name, &response, sources)?;
+        let doc = match sections.finish(&task.name) {
+            Some(mut doc) => {
+                doc.sources = sources;
+                doc
+            }
+            None => self
+                .parse_response(&task.name, &response, sources)
+                .inspect_err(|_| self.llm.evict(Some(&system_prompt), &prompt))?,
+        };
+        let doc = self
+            .critique(task, &context_str, doc, Some(&progress_tx))
+            .await?;
+        let doc = self.ground(doc).await;
+        let doc = self.ask_follow_ups(task, doc, Some(&progress_tx)).await?;
 
         let _ = progress_tx.send(ResearchProgress::Complete);
         Ok(doc)
     }
 
+    /// Runs the self-critique pass over `doc` if it is enabled.
+    ///
+    /// The pass is best effort: if the review fails or can't be parsed, the
+    /// research is returned unchanged. Only cancellation is an error.
+    async fn critique(
+        &self,
+        task: &Task,
+        context: &str,
+        doc: ResearchDoc,
+        progress_tx: Option<&mpsc::UnboundedSender<ResearchProgress>>,
+    ) -> Result<ResearchDoc, ResearchError> {
+        if !self.self_critique {
+            return Ok(doc);
+        }
+        if let Some(tx) = progress_tx {
+            let _ = tx.send(ResearchProgress::SelfCritique);
+        }
+
+        let research_json = serde_json::to_string_pretty(&doc).unwrap_or_default();
+        let prompt = build_critique_prompt(&task.prompt, context, &research_json);
+        let system_prompt = localize(CRITIQUE_SYSTEM_PROMPT, self.output_language.as_deref());
+        let response = match self.complete(&system_prompt, &prompt).await {
+            Ok(response) => response,
+            Err(LLMError::Cancelled) => return Err(LLMError::Cancelled.into()),
+            Err(_) => return Ok(doc),
+        };
+        if let Some(tx) = progress_tx {
+            let _ = tx.send(ResearchProgress::TokenUsage(TokenUsage::estimate(
+                &system_prompt,
+                &prompt,
+                &response,
+            )));
+        }
+
+        Ok(match parse_critique(&response) {
+            Ok(critique) => apply_critique(doc, critique),
+            Err(_) => doc,
+        })
+    }
+
+    /// Asks for follow-up questions about `doc` if enabled.
+    ///
+    /// Best effort like [`Self::critique`]: if the call fails or can't be
+    /// parsed, the research has no follow-up questions.
+    async fn ask_follow_ups(
+        &self,
+        task: &Task,
+        mut doc: ResearchDoc,
+        progress_tx: Option<&mpsc::UnboundedSender<ResearchProgress>>,
+    ) -> Result<ResearchDoc, ResearchError> {
+        if !self.follow_ups {
+            return Ok(doc);
+        }
+        if let Some(tx) = progress_tx {
+            let _ = tx.send(ResearchProgress::FollowUpQuestions);
+        }
+
+        let research_json = serde_json::to_string_pretty(&doc).unwrap_or_default();
+        let prompt = build_follow_up_prompt(&task.prompt, &research_json);
+        let system_prompt = localize(FOLLOW_UP_SYSTEM_PROMPT, self.output_language.as_deref());
+        let response = match self.complete(&system_prompt, &prompt).await {
+            Ok(response) => response,
+            Err(LLMError::Cancelled) => return Err(LLMError::Cancelled.into()),
+            Err(_) => return Ok(doc),
+        };
+        if let Some(tx) = progress_tx {
+            let _ = tx.send(ResearchProgress::TokenUsage(TokenUsage::estimate(
+                &system_prompt,
+                &prompt,
+                &response,
+            )));
+        }
+
+        doc.follow_ups = parse_follow_ups(&response).unwrap_or_default();
+        Ok(doc)
+    }
+
+    /// Flags the files and lines cited by `doc` that don't exist, replacing
+    /// them with knowledge graph matches if enabled.
+    ///
+    /// Correction is best effort: if the search fails, references stay
+    /// flagged only.
+    async fn ground(&self, mut doc: ResearchDoc) -> ResearchDoc {
+        let mut ungrounded = check_grounding(&doc, self.context_builder.root_path());
+        if let (true, Some(kg)) = (self.correct_references, &self.knowledge_store) {
+            let mut corrected = doc.clone();
+            let mut issues = ungrounded.clone();
+            if correct_references(&mut corrected, &mut issues, kg.as_ref())
+                .await
+                .is_ok()
+            {
+                doc = corrected;
+                ungrounded = issues;
+            }
+        }
+        doc.ungrounded = ungrounded;
+        doc
+    }
+
+    /// Gathers context and builds the user prompt, returning the prompt, the
+    /// context in it and its sources.
+    ///
+    /// A refinement only gathers context that wasn't sent for the task
+    /// before; its sources include those of the previous research.
+    async fn prepare(
+        &self,
+        task: &Task,
+        progress_tx: Option<&mpsc::UnboundedSender<ResearchProgress>>,
+    ) -> Result<(String, String, Vec<Source>), ResearchError> {
+        let Some(refinement) = &self.refinement else {
+            let (context, sources) = self.gather_context(task, progress_tx).await?;
+            let prompt = build_research_prompt(&task.prompt, &context);
+            return Ok((prompt, context, sources));
+        };
+
+        // Not cached: the delta depends on what earlier refinements sent
+        let sent = self
+            .sent_context
+            .as_ref()
+            .map(|log| log.load(&task.id))
+            .unwrap_or_default();
+        let gathered = self.gather_uncached(task, &sent, progress_tx).await?;
+        if let Some(log) = &self.sent_context {
+            // Best effort; a failed write only means resending these chunks
+            let _ = log.extend(&task.id, gathered.chunks);
+        }
+
+        let previous = serde_json::to_string_pretty(&refinement.previous).unwrap_or_default();
+        let prompt = build_refinement_prompt(
+            &task.prompt,
+            &previous,
+            &refinement.correction,
+            &gathered.context,
+        );
+        let mut sources = refinement.previous.sources.clone();
+        for source in gathered.sources {
+            if !sources.iter().any(|s| s.location == source.location) {
+                sources.push(source);
+            }
+        }
+        Ok((prompt, gathered.context, sources))
+    }
+
+    /// Gathers context for a task, from the cache when possible.
+    ///
+    /// Uses the knowledge graph if available, otherwise scans files. Progress
+    /// is reported when a channel is given. What was gathered replaces the
+    /// task's entry in the sent context log.
+    async fn gather_context(
+        &self,
+        task: &Task,
+        progress_tx: Option<&mpsc::UnboundedSender<ResearchProgress>>,
+    ) -> Result<(String, Vec<Source>), ResearchError> {
+        let progress = |event: ResearchProgress| {
+            if let Some(tx) = progress_tx {
+                let _ = tx.send(event);
+            }
+        };
+        let record_sent = |chunks: &[String]| {
+            if let Some(log) = &self.sent_context {
+                let _ = log.store(&task.id, chunks.iter().cloned().collect());
+            }
+        };
+
+        let queries = self.retrieval_queries(task);
+        let cache_key = self.context_cache.as_ref().map(|_| {
+            context_cache_key(
+                &self.context_builder.codebase_hash(),
+                &queries,
+                self.knowledge_store.is_some(),
+                self.content_guard,
+                &self.rerank,
+                &self.excluded_files,
+                self.private_paths.patterns(),
+                self.include_tests,
+            )
+        });
+        if let (Some(cache), Some(key)) = (&self.context_cache, &cache_key) {
+            if let Some(cached) = cache.load(&task.id, key) {
+                progress(ResearchProgress::ContextFromCache {
+                    count: cached.sources.len(),
+                });
+                if !cached.withheld.is_empty() {
+                    progress(ResearchProgress::PrivateContextWithheld {
+                        files: cached.withheld,
+                    });
+                }
+                record_sent(&cached.chunks);
+                return Ok((cached.context, cached.sources));
+            }
+        }
+
+        let gathered = self
+            .gather_uncached(task, &BTreeSet::new(), progress_tx)
+            .await?;
+        record_sent(&gathered.chunks);
+
+        if let (Some(cache), Some(key)) = (&self.context_cache, cache_key) {
+            // Caching is best effort; a failed write only costs a recompute
+            let _ = cache.store(
+                &task.id,
+                key,
+                &gathered.context,
+                &gathered.sources,
+                &gathered.chunks,
+                &gathered.withheld,
+            );
+        }
+
+        Ok((gathered.context, gathered.sources))
+    }
+
+    /// Gathers context for a task, leaving out the chunks and files in `sent`.
+    ///
+    /// Uses the knowledge graph if available, otherwise scans files.
+    async fn gather_uncached(
+        &self,
+        task: &Task,
+        sent: &BTreeSet<String>,
+        progress_tx: Option<&mpsc::UnboundedSender<ResearchProgress>>,
+    ) -> Result<GatheredContext, ResearchError> {
+        let progress = |event: ResearchProgress| {
+            if let Some(tx) = progress_tx {
+                let _ = tx.send(event);
+            }
+        };
+
+        let gathered = if let Some(ref kg) = self.knowledge_store {
+            progress(ResearchProgress::SearchingKnowledgeGraph);
+            let queries = self.retrieval_queries(task);
+            let gathered = self.gather_smart_context(kg, &queries, sent).await?;
+            let count = gathered.sources.len();
+            progress(ResearchProgress::KnowledgeGraphResults { count });
+            gathered
+        } else {
+            progress(ResearchProgress::GatheringContext);
+            self.gather_files(sent)?
+        };
+        if !gathered.withheld.is_empty() {
+            progress(ResearchProgress::PrivateContextWithheld {
+                files: gathered.withheld.clone(),
+            });
+        }
+        Ok(gathered)
+    }
+
     /// Gathers smart context using the knowledge graph.
     ///
     /// This method:
     /// 1. Performs semantic search to find relevant code
     /// 2. Expands results using graph traversal (dependencies & impact)
-    /// 3. Builds rich context showing code AND its connections
+    /// 3. Builds rich context showing code AND its connections, preceded by
+    ///    the summaries of the directories involved when the graph has them
+    ///    and followed by the matching documentation sections
+    ///
+    /// Chunks in `sent` are left out, and so is the project map unless
+    /// `sent` is empty. Private chunks are withheld, and so are private
+    /// entities from the graph connections.
     async fn gather_smart_context(
         &self,
         kg: &Arc<dyn KnowledgeStore>,
-        query: &str,
-    ) -> Result<(String, Vec<Source>), ResearchError> {
+        queries: &[&str],
+        sent: &BTreeSet<String>,
+    ) -> Result<GatheredContext, ResearchError> {
         // 1. Semantic search to find relevant code chunks
-        let results: Vec<SearchResult> = kg.search_code(query, 15).await?;
+        let mut results = Vec::new();
+        let mut withheld = Vec::new();
+        for result in self.search_reranked(kg, queries).await? {
+            if self.is_result_excluded(kg, &result).await {
+                continue;
+            }
+            if !self.private_paths.blocks(&result.path) {
+                results.push(result);
+            } else if !withheld.contains(&result.path) {
+                withheld.push(result.path);
+            }
+        }
 
         if results.is_empty() {
             // Fall back to regular context gathering if no results
-            let context = self.context_builder.gather()?;
-            let sources: Vec<Source> = context
-                .files
-                .iter()
-                .map(|f| Source {
-                    source_type: SourceType::File,
-                    location: f.path.clone(),
-                })
-                .collect();
-            return Ok((context.to_prompt_string(), sources));
+            let mut gathered = self.gather_files(sent)?;
+            for path in withheld {
+                if !gathered.withheld.contains(&path) {
+                    gathered.withheld.push(path);
+                }
+            }
+            return Ok(gathered);
         }
+        results.retain(|r| !sent.contains(&chunk_key(&r.path, r.start_line, r.end_line)));
+        if results.is_empty() {
+            // Everything found was sent before
+            return Ok(GatheredContext {
+                context: String::new(),
+                sources: Vec::new(),
+                chunks: Vec::new(),
+                withheld,
+            });
+        }
+        let visible = |ids: Vec<String>| -> Vec<String> {
+            ids.into_iter()
+                .filter(|id| !self.private_paths.blocks_entity(id))
+                .take(5)
+                .collect()
+        };
 
         let mut context_parts = Vec::new();
+        let mut doc_parts = Vec::new();
         let mut sources = Vec::new();
         let mut seen_files = std::collections::HashSet::new();
         let mut graph_context = Vec::new();
 
         // 2. Process search results and gather graph connections
         for result in &results {
+            let is_document = result.entity_type == DOCUMENT_ENTITY_TYPE;
+
             // Track source files
             if !seen_files.contains(&result.path) {
                 seen_files.insert(result.path.clone());
                 sources.push(Source {
-                    source_type: SourceType::KnowledgeGraph,
+                    source_type: if is_document {
+                        SourceType::Documentation
+                    } else {
+                        SourceType::KnowledgeGraph
+                    },
                     location: format!(
                         "{}:{}-{} (score: {:.2})",
                         result.path, result.start_line, result.end_line, result.score
@@ -276,10 +911,24 @@ impl<L: LLM> ResearchRunner<L> {
 
             // Add code preview
             if let Some(ref preview) = result.preview {
-                context_parts.push(format!(
-                    "### {} (lines {}-{})\n```\n{}\n```",
-                    result.path, result.start_line, result.end_line, preview
-                ));
+                let location = format!("{}:{}-{}", result.path, result.start_line, result.end_line);
+                let part = format!(
+                    "### {} (lines {}-{})\n{}",
+                    result.path,
+                    result.start_line,
+                    result.end_line,
+                    wrap_untrusted(&location, preview, self.content_guard)
+                );
+                if is_document {
+                    doc_parts.push(part);
+                } else {
+                    context_parts.push(part);
+                }
+            }
+
+            // Documents have no graph connections
+            if is_document {
+                continue;
             }
 
             // 3. Graph expansion - get dependencies and impact for entities
@@ -287,30 +936,25 @@ impl<L: LLM> ResearchRunner<L> {
                 let entity_name = &result.entity_type;
 
                 // Get what this entity depends on (calls)
-                if let Ok(deps) = kg.get_dependencies(entity_id).await {
+                if let Ok(deps) = kg.get_dependencies(entity_id).await.map(visible) {
                     if !deps.is_empty() {
                         graph_context.push(format!(
                             "- **{}** `{}` calls: {}",
                             entity_name,
                             entity_id,
-                            deps.iter().take(5).cloned().collect::<Vec<_>>().join(", ")
+                            deps.join(", ")
                         ));
                     }
                 }
 
                 // Get what depends on this entity (callers / impact)
-                if let Ok(impact) = kg.get_impact(entity_id).await {
+                if let Ok(impact) = kg.get_impact(entity_id).await.map(visible) {
                     if !impact.is_empty() {
                         graph_context.push(format!(
                             "- **{}** `{}` is called by: {}",
                             entity_name,
                             entity_id,
-                            impact
-                                .iter()
-                                .take(5)
-                                .cloned()
-                                .collect::<Vec<_>>()
-                                .join(", ")
+                            impact.join(", ")
                         ));
                     }
                 }
@@ -318,10 +962,29 @@ impl<L: LLM> ResearchRunner<L> {
         }
 
         // 4. Build final context string
-        let mut context_str = format!(
-            "## Relevant Code (semantic search)\n\n{}\n",
-            context_parts.join("\n\n")
-        );
+        let mut context_str = String::new();
+        // The summary index is optional; without it research works as before
+        let summaries = if sent.is_empty() {
+            kg.directory_summaries().await.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        if let Some(map) = project_map(&summaries, results.iter().map(|r| r.path.as_str())) {
+            context_str.push_str(&map);
+            context_str.push('\n');
+        }
+        if !context_parts.is_empty() || doc_parts.is_empty() {
+            context_str.push_str(&format!(
+                "## Relevant Code (semantic search)\n\n{}\n",
+                context_parts.join("\n\n")
+            ));
+        }
+        if !doc_parts.is_empty() {
+            context_str.push_str(&format!(
+                "\n## Relevant Documentation\n\n{}\n",
+                doc_parts.join("\n\n")
+            ));
+        }
 
         // Add graph relationships if found
         if !graph_context.is_empty() {
@@ -331,7 +994,67 @@ impl<L: LLM> ResearchRunner<L> {
             ));
         }
 
-        Ok((context_str, sources))
+        let chunks = results
+            .iter()
+            .map(|r| chunk_key(&r.path, r.start_line, r.end_line))
+            .collect();
+        Ok(GatheredContext {
+            context: context_str,
+            sources,
+            chunks,
+            withheld,
+        })
+    }
+
+    /// Gathers context by scanning files, leaving out excluded ones and those
+    /// in `sent`, and withholding private ones.
+    fn gather_files(&self, sent: &BTreeSet<String>) -> Result<GatheredContext, ResearchError> {
+        let mut context = self.context_builder.gather()?;
+        context
+            .files
+            .retain(|f| !self.is_excluded(&f.path) && !sent.contains(&f.path));
+        let withheld = context
+            .files
+            .iter()
+            .filter(|f| self.private_paths.blocks(&f.path))
+            .map(|f| f.path.clone())
+            .collect();
+        context
+            .files
+            .retain(|f| !self.private_paths.blocks(&f.path));
+        let sources: Vec<Source> = context
+            .files
+            .iter()
+            .map(|f| Source {
+                source_type: SourceType::File,
+                location: f.path.clone(),
+            })
+            .collect();
+        let chunks = context.files.iter().map(|f| f.path.clone()).collect();
+        Ok(GatheredContext {
+            context: context_prompt(&context, self.content_guard),
+            sources,
+            chunks,
+            withheld,
+        })
+    }
+
+    /// Semantic search for smart context, re-ranked by entity metadata.
+    ///
+    /// With several queries, each contributes up to [`SMART_CONTEXT_RESULTS`]
+    /// chunks and the lists are merged.
+    async fn search_reranked(
+        &self,
+        kg: &Arc<dyn KnowledgeStore>,
+        queries: &[&str],
+    ) -> Result<Vec<SearchResult>, ResearchError> {
+        let mut lists = Vec::with_capacity(queries.len());
+        for query in queries {
+            lists.push(
+                search_reranked(kg.as_ref(), query, SMART_CONTEXT_RESULTS, &self.rerank).await?,
+            );
+        }
+        Ok(merge_results(lists))
     }
 
     /// Parses the LLM response into a ResearchDoc.
@@ -353,32 +1076,7 @@ impl<L: LLM> ResearchRunner<L> {
             ))
         })?;
 
-        // Convert to ResearchDoc
-        let mut doc = ResearchDoc::new(task_name);
-        doc.summary = parsed.summary;
-        doc.suggested_approach = parsed.suggested_approach;
-
-        // Convert findings
-        doc.codebase_analysis = parsed
-            .findings
-            .into_iter()
-            .map(|f| Finding {
-                title: f.title,
-                description: f.description,
-                related_files: f.related_files,
-            })
-            .collect();
-
-        // Convert dependencies
-        doc.dependencies = parsed
-            .dependencies
-            .into_iter()
-            .map(|d| Dependency {
-                name: d.name,
-                description: d.description,
-                is_external: d.is_external,
-            })
-            .collect();
+        let mut doc = parsed.into_doc(task_name);
 
         // Use provided sources
         doc.sources = sources;
@@ -389,30 +1087,82 @@ impl<L: LLM> ResearchRunner<L> {
 
 /// Response structure from LLM.
 #[derive(Debug, serde::Deserialize)]
-struct ResearchResponse {
+pub(crate) struct ResearchResponse {
     summary: String,
     findings: Vec<FindingResponse>,
     dependencies: Vec<DependencyResponse>,
     suggested_approach: String,
 }
 
+impl ResearchResponse {
+    /// Converts the response into a research document without sources.
+    pub(crate) fn into_doc(self, task_name: &str) -> ResearchDoc {
+        let mut doc = ResearchDoc::new(task_name);
+        doc.summary = self.summary;
+        doc.suggested_approach = self.suggested_approach;
+
+        doc.codebase_analysis = self.findings.into_iter().map(Finding::from).collect();
+        doc.dependencies = self
+            .dependencies
+            .into_iter()
+            .map(Dependency::from)
+            .collect();
+        doc
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
-struct FindingResponse {
+pub(crate) struct FindingResponse {
     title: String,
     description: String,
     #[serde(default)]
     related_files: Vec<String>,
+    // Parsed leniently so an unexpected value doesn't fail the whole response
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    severity: Option<String>,
+}
+
+impl From<FindingResponse> for Finding {
+    fn from(f: FindingResponse) -> Self {
+        Finding {
+            title: f.title,
+            description: f.description,
+            related_files: f.related_files,
+            category: f
+                .category
+                .as_deref()
+                .map(FindingCategory::parse)
+                .unwrap_or_default(),
+            severity: f
+                .severity
+                .as_deref()
+                .map(Severity::parse)
+                .unwrap_or_default(),
+        }
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
-struct DependencyResponse {
+pub(crate) struct DependencyResponse {
     name: String,
     description: String,
     is_external: bool,
 }
 
+impl From<DependencyResponse> for Dependency {
+    fn from(d: DependencyResponse) -> Self {
+        Dependency {
+            name: d.name,
+            description: d.description,
+            is_external: d.is_external,
+        }
+    }
+}
+
 /// Extracts JSON from a response that might be wrapped in markdown code blocks or have extra text.
-fn extract_json(response: &str) -> &str {
+pub(crate) fn extract_json(response: &str) -> &str {
     let trimmed = response.trim();
 
     // Check for ```json ... ``` or ``` ... ```
@@ -454,4 +1204,7 @@ pub enum ResearchError {
 
     #[error("Parse error: {0}")]
     ParseError(String),
+
+    #[error("Research not sent: estimated cost ${cost:.2} is over the ${threshold:.2} limit")]
+    CostDeclined { cost: f64, threshold: f64 },
 }
@@ -0,0 +1,135 @@
+//! Prompt injection mitigation for retrieved code.
+//!
+//! Code pulled from the codebase is untrusted input to the LLM. Depending on
+//! the configured [`ContentGuard`], each piece of code is wrapped in a
+//! delimited `<untrusted_content>` block and lines that look like
+//! instructions to the model are redacted (or the whole chunk is dropped).
+
+use std::sync::OnceLock;
+
+use regex::RegexSet;
+
+use crate::config::{ContentGuard, UNTRUSTED_CONTENT_NOTICE};
+use crate::context::Context;
+
+/// Replacement for a redacted line.
+pub const REDACTED_LINE: &str = "[redacted: possible prompt injection]";
+
+const OPEN_TAG: &str = "<untrusted_content";
+const CLOSE_TAG: &str = "</untrusted_content>";
+
+/// Patterns that look like instructions aimed at the model rather than code.
+const SUSPICIOUS_PATTERNS: &[&str] = &[
+    // "ignore all previous instructions", "disregard the above"
+    r"(?i)\b(ignore|disregard|forget|override)\b.{0,30}\b(previous|prior|above|earlier|your)\b.{0,20}\b(instructions?|prompts?|rules|directions|context)\b",
+    // Role reassignment
+    r"(?i)\byou are now\b",
+    r"(?i)\b(pretend|act) (to be|as if you|as an?)\b.{0,40}\b(ai|assistant|model|system)\b",
+    // Fake instruction headers
+    r"(?i)\b(new|updated|real|actual) (system )?instructions?\s*:",
+    r"(?i)^\s*(//|#|/\*|\*|--)\s*(system|assistant)\s*:\s",
+    // Chat template control tokens
+    r"(?i)<\|?(im_start|im_end|system|endoftext)\|?>|\[/?INST\]|</?system>",
+    // Attempts to exfiltrate the prompt
+    r"(?i)\b(reveal|print|output|repeat|leak)\b.{0,20}\b(system prompt|your instructions|hidden instructions)\b",
+    // Attempts to hide things from the developer
+    r"(?i)\bdo not (tell|mention|reveal|inform)\b.{0,20}\b(the )?(user|developer|human)\b",
+];
+
+fn suspicious_patterns() -> &'static RegexSet {
+    static SET: OnceLock<RegexSet> = OnceLock::new();
+    SET.get_or_init(|| RegexSet::new(SUSPICIOUS_PATTERNS).expect("valid injection patterns"))
+}
+
+/// Returns true if the line looks like an instruction aimed at the model.
+pub fn is_suspicious(line: &str) -> bool {
+    suspicious_patterns().is_match(line)
+}
+
+/// Returns the system prompt with the untrusted content notice appended
+/// when the guard is enabled.
+pub fn guarded_system_prompt(system_prompt: &str, guard: ContentGuard) -> String {
+    match guard {
+        ContentGuard::Off => system_prompt.to_string(),
+        ContentGuard::Standard | ContentGuard::Strict => {
+            format!("{}{}", system_prompt, UNTRUSTED_CONTENT_NOTICE)
+        }
+    }
+}
+
+/// Formats a piece of retrieved code for inclusion in a prompt.
+///
+/// `source` identifies where the code came from (e.g. `src/lib.rs:10-40`).
+pub fn wrap_untrusted(source: &str, content: &str, guard: ContentGuard) -> String {
+    match guard {
+        ContentGuard::Off => format!("```\n{}\n```", content),
+        ContentGuard::Standard | ContentGuard::Strict => {
+            let (cleaned, redacted) = redact(content);
+            let body = if redacted > 0 && guard == ContentGuard::Strict {
+                format!(
+                    "[omitted: {} line(s) looked like prompt injection]",
+                    redacted
+                )
+            } else {
+                format!("```\n{}\n```", cleaned)
+            };
+            format!(
+                "{} source=\"{}\">\n{}\n{}",
+                OPEN_TAG,
+                escape_attribute(source),
+                body,
+                CLOSE_TAG
+            )
+        }
+    }
+}
+
+/// Formats gathered file context for a prompt, guarding each file.
+pub fn context_prompt(context: &Context, guard: ContentGuard) -> String {
+    if guard == ContentGuard::Off {
+        return context.to_prompt_string();
+    }
+
+    let mut result = String::new();
+
+    result.push_str("## Directory Structure\n\n```\n");
+    result.push_str(&context.structure);
+    result.push_str("```\n\n");
+
+    result.push_str("## File Contents\n\n");
+
+    for file in &context.files {
+        result.push_str(&format!("### {}\n\n", file.path));
+        result.push_str(&wrap_untrusted(&file.path, &file.content, guard));
+        result.push_str("\n\n");
+    }
+
+    result
+}
+
+/// Replace suspicious lines and neutralize block delimiters.
+///
+/// Returns the cleaned content and the number of lines redacted.
+fn redact(content: &str) -> (String, usize) {
+    let mut redacted = 0;
+
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            if is_suspicious(line) {
+                redacted += 1;
+                REDACTED_LINE.to_string()
+            } else {
+                // Content must not be able to close (or fake) its own block
+                line.replace(CLOSE_TAG, "</untrusted-content>")
+                    .replace(OPEN_TAG, "<untrusted-content")
+            }
+        })
+        .collect();
+
+    (lines.join("\n"), redacted)
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('"', "'").replace(['<', '>', '\n'], " ")
+}
@@ -0,0 +1,316 @@
+//! Research sections parsed while the response streams.
+//!
+//! The research response is a JSON object whose top-level fields are the
+//! sections of the document: `summary`, `findings`, `dependencies` and
+//! `suggested_approach`. [`SectionParser`] scans the text as it streams in
+//! and yields each section, and each finding and dependency, as soon as its
+//! closing quote or brace arrives, so the TUI can fill in the document's
+//! structure while the rest is still being generated. Text around the
+//! object, such as a code fence, is skipped.
+//!
+//! Once the object is closed with every section in it, the sections are the
+//! document: [`SectionParser::finish`] assembles it and the runner only
+//! checks that nothing is missing instead of parsing the whole response
+//! again. A truncated or malformed stream falls back to the full parse and
+//! its error.
+
+use serde::{Deserialize, Serialize};
+
+use super::document::{Dependency, Finding, ResearchDoc};
+use super::runner::{DependencyResponse, FindingResponse};
+
+/// A section of the research document, complete as streamed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DocSection {
+    Summary(String),
+    /// One entry of `findings`
+    Finding(Finding),
+    /// One entry of `dependencies`
+    Dependency(Dependency),
+    Approach(String),
+}
+
+impl DocSection {
+    /// Field of the response the section comes from, e.g. `summary`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Summary(_) => "summary",
+            Self::Finding(_) => "findings",
+            Self::Dependency(_) => "dependencies",
+            Self::Approach(_) => "suggested_approach",
+        }
+    }
+
+    /// Add the section to a document being assembled.
+    pub fn apply_to(self, doc: &mut ResearchDoc) {
+        match self {
+            Self::Summary(summary) => doc.summary = summary,
+            Self::Finding(finding) => doc.codebase_analysis.push(finding),
+            Self::Dependency(dependency) => doc.dependencies.push(dependency),
+            Self::Approach(approach) => doc.suggested_approach = approach,
+        }
+    }
+}
+
+/// Scans a streamed research response for complete sections.
+#[derive(Debug, Default)]
+pub struct SectionParser {
+    /// Everything streamed so far
+    text: String,
+    /// Bytes of `text` already scanned
+    scanned: usize,
+    /// Nesting of the JSON value being scanned; 1 inside the response object
+    depth: usize,
+    started: bool,
+    closed: bool,
+    in_string: bool,
+    escaped: bool,
+    /// Whether the next string in the response object is a field name
+    expecting_key: bool,
+    /// Field of the response object being read
+    key: String,
+    key_start: Option<usize>,
+    /// Start of the current field's value
+    value_start: Option<usize>,
+    /// Start of the current entry of an array field
+    element_start: Option<usize>,
+    /// An entry could not be parsed; leave it to the full parse
+    malformed: bool,
+    summary: Option<String>,
+    suggested_approach: Option<String>,
+    findings: Vec<Finding>,
+    dependencies: Vec<Dependency>,
+    findings_closed: bool,
+    dependencies_closed: bool,
+}
+
+impl SectionParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything streamed so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Add a streamed chunk, returning the sections it completes.
+    pub fn feed(&mut self, chunk: &str) -> Vec<DocSection> {
+        self.text.push_str(chunk);
+        let mut sections = Vec::new();
+        while self.scanned < self.text.len() && !self.closed {
+            let i = self.scanned;
+            self.scanned += 1;
+            // Multi-byte characters never contain ASCII bytes, so scanning
+            // bytes is safe and only stops at JSON punctuation
+            self.scan(i, self.text.as_bytes()[i], &mut sections);
+        }
+        sections
+    }
+
+    /// The document assembled from the sections, if the response object
+    /// closed with every section in it.
+    pub fn finish(self, task_name: &str) -> Option<ResearchDoc> {
+        if !self.closed || self.malformed || !self.findings_closed || !self.dependencies_closed {
+            return None;
+        }
+        let mut doc = ResearchDoc::new(task_name);
+        doc.summary = self.summary?;
+        doc.suggested_approach = self.suggested_approach?;
+        doc.codebase_analysis = self.findings;
+        doc.dependencies = self.dependencies;
+        Some(doc)
+    }
+
+    fn scan(&mut self, i: usize, byte: u8, sections: &mut Vec<DocSection>) {
+        if !self.started {
+            if byte == b'{' {
+                self.started = true;
+                self.depth = 1;
+                self.expecting_key = true;
+            }
+            return;
+        }
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                self.string_closed(i, sections);
+            }
+            return;
+        }
+
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                if self.depth == 1 {
+                    if self.expecting_key {
+                        self.key_start = Some(i);
+                    } else {
+                        self.value_start = Some(i);
+                    }
+                }
+            }
+            b'{' | b'[' => {
+                if self.depth == 1 {
+                    self.value_start = Some(i);
+                } else if self.depth == 2 && byte == b'{' && self.value_is_array() {
+                    self.element_start = Some(i);
+                }
+                self.depth += 1;
+            }
+            b'}' | b']' => {
+                self.depth -= 1;
+                match self.depth {
+                    0 => self.closed = true,
+                    1 => self.value_closed(),
+                    2 => self.element_closed(i, sections),
+                    _ => {}
+                }
+            }
+            b',' if self.depth == 1 => self.expecting_key = true,
+            b':' if self.depth == 1 => self.expecting_key = false,
+            _ => {}
+        }
+    }
+
+    fn value_is_array(&self) -> bool {
+        self.value_start
+            .is_some_and(|start| self.text.as_bytes()[start] == b'[')
+    }
+
+    /// A string ended at `i`: a field name, or a field's text value.
+    fn string_closed(&mut self, i: usize, sections: &mut Vec<DocSection>) {
+        if self.depth != 1 {
+            return;
+        }
+        if let Some(start) = self.key_start.take() {
+            self.key = serde_json::from_str(&self.text[start..=i]).unwrap_or_default();
+            return;
+        }
+        let Some(start) = self.value_start.take() else {
+            return;
+        };
+        let Ok(value) = serde_json::from_str::<String>(&self.text[start..=i]) else {
+            self.malformed = true;
+            return;
+        };
+        let section = match self.key.as_str() {
+            "summary" => DocSection::Summary(value),
+            "suggested_approach" => DocSection::Approach(value),
+            _ => return,
+        };
+        self.complete(section, sections);
+    }
+
+    /// The current field's array or object value closed.
+    fn value_closed(&mut self) {
+        let is_array = self.value_is_array();
+        self.value_start = None;
+        match self.key.as_str() {
+            "findings" => self.findings_closed = is_array,
+            "dependencies" => self.dependencies_closed = is_array,
+            _ => {}
+        }
+    }
+
+    /// An entry of an array field closed at `i`.
+    fn element_closed(&mut self, i: usize, sections: &mut Vec<DocSection>) {
+        let Some(start) = self.element_start.take() else {
+            return;
+        };
+        let entry = &self.text[start..=i];
+        let section = match self.key.as_str() {
+            "findings" => serde_json::from_str::<FindingResponse>(entry)
+                .map(|finding| DocSection::Finding(finding.into())),
+            "dependencies" => serde_json::from_str::<DependencyResponse>(entry)
+                .map(|dependency| DocSection::Dependency(dependency.into())),
+            _ => return,
+        };
+        match section {
+            Ok(section) => self.complete(section, sections),
+            Err(_) => self.malformed = true,
+        }
+    }
+
+    /// Keep a completed section for the document and report it.
+    fn complete(&mut self, section: DocSection, sections: &mut Vec<DocSection>) {
+        match &section {
+            DocSection::Summary(summary) => self.summary = Some(summary.clone()),
+            DocSection::Finding(finding) => self.findings.push(finding.clone()),
+            DocSection::Dependency(dependency) => self.dependencies.push(dependency.clone()),
+            DocSection::Approach(approach) => self.suggested_approach = Some(approach.clone()),
+        }
+        sections.push(section);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::research::Severity;
+
+    const RESPONSE: &str = r#"```json
+{
+  "summary": "Routing lives in \"src/router.rs\"",
+  "findings": [
+    {"title": "Router", "description": "Matches {paths}", "related_files": ["src/router.rs"]},
+    {"title": "Auth", "description": "None yet", "severity": "high"}
+  ],
+  "dependencies": [{"name": "axum", "description": "HTTP", "is_external": true}],
+  "suggested_approach": "Add middleware ✓"
+}
+```"#;
+
+    #[test]
+    fn test_sections_stream_in_as_completed() {
+        let mut parser = SectionParser::new();
+        let mut names = Vec::new();
+        // Feed a few bytes at a time, splitting multi-byte characters' strings
+        let mut rest = RESPONSE;
+        while !rest.is_empty() {
+            let mut end = rest.len().min(7);
+            while !rest.is_char_boundary(end) {
+                end += 1;
+            }
+            for section in parser.feed(&rest[..end]) {
+                names.push(section.name());
+            }
+            rest = &rest[end..];
+        }
+        assert_eq!(
+            names,
+            vec![
+                "summary",
+                "findings",
+                "findings",
+                "dependencies",
+                "suggested_approach"
+            ]
+        );
+        assert_eq!(parser.text(), RESPONSE);
+
+        let doc = parser.finish("routing").unwrap();
+        assert_eq!(doc.summary, "Routing lives in \"src/router.rs\"");
+        assert_eq!(doc.codebase_analysis.len(), 2);
+        assert_eq!(doc.codebase_analysis[1].severity, Severity::High);
+        assert_eq!(doc.dependencies[0].name, "axum");
+        assert_eq!(doc.suggested_approach, "Add middleware ✓");
+    }
+
+    #[test]
+    fn test_incomplete_response_is_left_to_full_parse() {
+        let mut parser = SectionParser::new();
+        let sections = parser.feed(&RESPONSE[..RESPONSE.find("\"dependencies\"").unwrap()]);
+        assert_eq!(sections.len(), 3);
+        assert!(parser.finish("routing").is_none());
+
+        // An entry missing a required field
+        let mut parser = SectionParser::new();
+        parser.feed(&RESPONSE.replace("\"title\": \"Auth\", ", ""));
+        assert!(parser.finish("routing").is_none());
+    }
+}
@@ -0,0 +1,178 @@
+//! Token breakdown of a research prompt.
+//!
+//! Context is usually most of a research prompt, and it's hard to tell from
+//! the total alone whether the directory tree, one huge file or the system
+//! prompt is eating the budget. [`PromptStats::from_prompt`] splits the
+//! assembled prompt along its section headings, so it works the same for
+//! scanned files, knowledge graph chunks and cached context.
+
+use serde::{Deserialize, Serialize};
+
+use crate::llm::estimate_tokens;
+
+/// Heading of the directory tree section.
+const TREE_HEADING: &str = "## Directory Structure";
+
+/// Heading of the graph relationships section of smart context.
+const RELATIONSHIPS_HEADING: &str = "## Code Relationships";
+
+/// Estimated tokens of one source file in the prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceTokens {
+    /// File path, relative to the project root.
+    pub path: String,
+    /// Tokens of every chunk of the file, including its heading.
+    pub tokens: usize,
+}
+
+/// Estimated composition of the prompt sent to the LLM.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptStats {
+    /// The system prompt.
+    pub system_tokens: usize,
+    /// The directory structure tree.
+    pub tree_tokens: usize,
+    /// Call relationships from the knowledge graph.
+    pub relationship_tokens: usize,
+    /// Source files, largest first.
+    pub sources: Vec<SourceTokens>,
+    /// The question, instructions and section headings.
+    pub other_tokens: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Tree,
+    Relationships,
+    Source(usize),
+    Other,
+}
+
+impl PromptStats {
+    /// Break down `prompt`, as built by
+    /// [`build_research_prompt`](super::prompts::build_research_prompt).
+    pub fn from_prompt(system: &str, prompt: &str) -> Self {
+        let mut tree = String::new();
+        let mut relationships = String::new();
+        let mut other = String::new();
+        let mut sources: Vec<(String, String)> = Vec::new();
+
+        let mut section = Section::Other;
+        let mut in_fence = false;
+        for line in prompt.lines() {
+            if !in_fence {
+                if let Some(heading) = line.strip_prefix("### ") {
+                    let path = source_path(heading);
+                    let index = match sources.iter().position(|(p, _)| p == path) {
+                        Some(index) => index,
+                        None => {
+                            sources.push((path.to_string(), String::new()));
+                            sources.len() - 1
+                        }
+                    };
+                    section = Section::Source(index);
+                } else if line.starts_with("## ") {
+                    section = if line.starts_with(TREE_HEADING) {
+                        Section::Tree
+                    } else if line.starts_with(RELATIONSHIPS_HEADING) {
+                        Section::Relationships
+                    } else {
+                        Section::Other
+                    };
+                }
+            }
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+
+            let text = match section {
+                Section::Tree => &mut tree,
+                Section::Relationships => &mut relationships,
+                Section::Source(index) => &mut sources[index].1,
+                Section::Other => &mut other,
+            };
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        let mut sources: Vec<SourceTokens> = sources
+            .into_iter()
+            .map(|(path, text)| SourceTokens {
+                path,
+                tokens: estimate_tokens(&text),
+            })
+            .collect();
+        sources.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.path.cmp(&b.path)));
+
+        Self {
+            system_tokens: estimate_tokens(system),
+            tree_tokens: estimate_tokens(&tree),
+            relationship_tokens: estimate_tokens(&relationships),
+            sources,
+            other_tokens: estimate_tokens(&other),
+        }
+    }
+
+    /// Tokens of all source files.
+    pub fn source_tokens(&self) -> usize {
+        self.sources.iter().map(|s| s.tokens).sum()
+    }
+
+    /// Tokens of the whole prompt, system prompt included.
+    pub fn total(&self) -> usize {
+        self.system_tokens
+            + self.tree_tokens
+            + self.relationship_tokens
+            + self.source_tokens()
+            + self.other_tokens
+    }
+
+    /// One-line summary naming the largest sources, e.g.
+    /// `12.3k tokens: 8.1k in 6 files (src/db.rs 4.0k), tree 1.2k, system 0.9k`.
+    pub fn summary(&self, top: usize) -> String {
+        let mut parts = vec![format!(
+            "{} in {} files",
+            format_tokens(self.source_tokens()),
+            self.sources.len()
+        )];
+        if top > 0 && !self.sources.is_empty() {
+            let largest: Vec<String> = self
+                .sources
+                .iter()
+                .take(top)
+                .map(|s| format!("{} {}", s.path, format_tokens(s.tokens)))
+                .collect();
+            parts[0].push_str(&format!(" ({})", largest.join(", ")));
+        }
+        if self.tree_tokens > 0 {
+            parts.push(format!("tree {}", format_tokens(self.tree_tokens)));
+        }
+        if self.relationship_tokens > 0 {
+            parts.push(format!("graph {}", format_tokens(self.relationship_tokens)));
+        }
+        parts.push(format!("system {}", format_tokens(self.system_tokens)));
+        parts.push(format!("other {}", format_tokens(self.other_tokens)));
+        format!(
+            "{} tokens: {}",
+            format_tokens(self.total()),
+            parts.join(", ")
+        )
+    }
+}
+
+/// File path of a source heading (`src/db.rs (lines 10-40)` -> `src/db.rs`).
+fn source_path(heading: &str) -> &str {
+    heading
+        .split_once(" (lines ")
+        .map_or(heading, |(path, _)| path)
+        .trim()
+}
+
+/// Token count for display, e.g. `850` or `12.3k`.
+pub(crate) fn format_tokens(tokens: usize) -> String {
+    if tokens >= 1000 {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    } else {
+        tokens.to_string()
+    }
+}
@@ -0,0 +1,264 @@
+//! Building the hierarchical summary index and using it in research.
+//!
+//! [`summarize_index`] is the map-reduce behind `arq kg summarize`: each
+//! indexed file is summarized on its own, then each directory from the
+//! summaries of its children, deepest first, so the root ends up with a
+//! summary of the whole project. Calls go through a [`JobQueue`]; entries
+//! whose input hasn't changed since the last run are skipped.
+//!
+//! Smart context opens with a [`project_map`]: the summaries of the
+//! top-level directories and of the directories holding the code found by
+//! search, which orients the model without sending more code.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::config::ContentGuard;
+use crate::knowledge::summary::{
+    children, depth, directories_bottom_up, directory_hash, parent_dir, ROOT_DIR,
+};
+use crate::knowledge::{KnowledgeError, KnowledgeGraph, Summary, SummaryKind};
+use crate::llm::{JobQueue, LLMError, LLM};
+use crate::research::sanitize::wrap_untrusted;
+
+/// System prompt for summarizing one file.
+pub const FILE_SUMMARY_SYSTEM_PROMPT: &str = "You summarize source files for an index that helps developers find their way around a codebase. In 2-3 sentences, say what the file is responsible for and name its most important types and functions. Reply with the summary only, no preamble or formatting.";
+
+/// System prompt for summarizing a directory from its children.
+pub const DIRECTORY_SUMMARY_SYSTEM_PROMPT: &str = "You summarize directories of a codebase for an index that helps developers find their way around it. You are given summaries of the directory's files and subdirectories. In 2-4 sentences, say what the directory as a whole is responsible for and which entries matter most. Reply with the summary only, no preamble or formatting.";
+
+/// Characters of a file sent for summarization; the rest is cut off.
+pub const MAX_SUMMARY_INPUT_CHARS: usize = 16_000;
+
+/// Directory summaries in a project map, at most.
+pub const PROJECT_MAP_MAX_DIRS: usize = 30;
+
+/// Heading of the project map in smart context.
+pub const PROJECT_MAP_HEADING: &str = "## Project Map (directory summaries)";
+
+/// Outcome of [`summarize_index`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SummarizeReport {
+    /// Files summarized in this run.
+    pub files: usize,
+    /// Directories summarized in this run.
+    pub directories: usize,
+    /// Files and directories whose summary was still current.
+    pub unchanged: usize,
+    /// Summaries deleted because their file or directory is gone.
+    pub removed: usize,
+    /// `(path, error)` of entries that couldn't be summarized.
+    pub failures: Vec<(String, String)>,
+}
+
+/// Summarize the files indexed in `kg`, then their directories, storing
+/// the results in the graph.
+///
+/// File contents are read from under `root`. A file that fails keeps its
+/// previous summary, if any, and is retried on the next run.
+pub async fn summarize_index<L: LLM>(
+    llm: &L,
+    kg: &KnowledgeGraph,
+    root: &Path,
+    queue: &JobQueue,
+    guard: ContentGuard,
+) -> Result<SummarizeReport, KnowledgeError> {
+    let mut report = SummarizeReport::default();
+    let files = kg.list_file_hashes().await?;
+    let dirs = directories_bottom_up(files.iter().map(|(path, _)| path.as_str()));
+
+    // Forget summaries of files and directories that no longer exist
+    let current: HashSet<&str> = files
+        .iter()
+        .map(|(path, _)| path.as_str())
+        .chain(dirs.iter().map(String::as_str))
+        .collect();
+    let mut summaries = BTreeMap::new();
+    let mut removed = Vec::new();
+    for summary in kg.list_summaries().await? {
+        if current.contains(summary.path.as_str()) {
+            summaries.insert(summary.path.clone(), summary);
+        } else {
+            removed.push(summary.path);
+        }
+    }
+    kg.delete_summaries(&removed).await?;
+    report.removed = removed.len();
+
+    // Map: files whose content changed
+    let mut stale = Vec::new();
+    for (path, hash) in &files {
+        if summaries.get(path).is_some_and(|s| &s.hash == hash) {
+            report.unchanged += 1;
+            continue;
+        }
+        match tokio::fs::read_to_string(root.join(path)).await {
+            Ok(content) => stale.push((path.clone(), hash.clone(), content)),
+            Err(e) => report.failures.push((path.clone(), e.to_string())),
+        }
+    }
+    let paths: Vec<String> = stale.iter().map(|(path, ..)| path.clone()).collect();
+    let results = queue
+        .run(stale, |(path, hash, content)| async move {
+            let prompt = file_summary_prompt(&path, &content, guard);
+            let summary = llm
+                .complete_with_system(FILE_SUMMARY_SYSTEM_PROMPT, &prompt)
+                .await?;
+            Ok(Summary {
+                path,
+                kind: SummaryKind::File,
+                summary: summary.trim().to_string(),
+                hash,
+            })
+        })
+        .await;
+    let done = collect(results, &paths, &mut report);
+    report.files = done.len();
+    kg.save_summaries(&done).await?;
+    summaries.extend(done.into_iter().map(|s| (s.path.clone(), s)));
+
+    // Reduce: directories one level at a time, so children are current
+    let mut level_start = 0;
+    while level_start < dirs.len() {
+        let level = depth(&dirs[level_start]);
+        let level_end = dirs[level_start..]
+            .iter()
+            .position(|dir| depth(dir) != level)
+            .map_or(dirs.len(), |offset| level_start + offset);
+
+        let mut stale = Vec::new();
+        for dir in &dirs[level_start..level_end] {
+            let children = children(dir, &summaries);
+            if children.is_empty() {
+                continue;
+            }
+            let hash = directory_hash(&children);
+            if summaries.get(dir).is_some_and(|s| s.hash == hash) {
+                report.unchanged += 1;
+            } else {
+                stale.push((dir.clone(), hash, directory_summary_prompt(dir, &children)));
+            }
+        }
+        let paths: Vec<String> = stale.iter().map(|(path, ..)| path.clone()).collect();
+        let results = queue
+            .run(stale, |(path, hash, prompt)| async move {
+                let summary = llm
+                    .complete_with_system(DIRECTORY_SUMMARY_SYSTEM_PROMPT, &prompt)
+                    .await?;
+                Ok(Summary {
+                    path,
+                    kind: SummaryKind::Directory,
+                    summary: summary.trim().to_string(),
+                    hash,
+                })
+            })
+            .await;
+        let done = collect(results, &paths, &mut report);
+        report.directories += done.len();
+        kg.save_summaries(&done).await?;
+        summaries.extend(done.into_iter().map(|s| (s.path.clone(), s)));
+
+        level_start = level_end;
+    }
+
+    Ok(report)
+}
+
+/// Successful summaries of a queue run; failures go to the report.
+fn collect(
+    results: Vec<Result<Summary, LLMError>>,
+    paths: &[String],
+    report: &mut SummarizeReport,
+) -> Vec<Summary> {
+    let mut done = Vec::new();
+    for (result, path) in results.into_iter().zip(paths) {
+        match result {
+            Ok(summary) => done.push(summary),
+            Err(e) => report.failures.push((path.clone(), e.to_string())),
+        }
+    }
+    done
+}
+
+/// User prompt asking for a summary of one file.
+pub fn file_summary_prompt(path: &str, content: &str, guard: ContentGuard) -> String {
+    let content = match content.char_indices().nth(MAX_SUMMARY_INPUT_CHARS) {
+        Some((end, _)) => &content[..end],
+        None => content,
+    };
+    format!(
+        "## File: {}\n\n{}\n\nSummarize this file.",
+        path,
+        wrap_untrusted(path, content, guard)
+    )
+}
+
+/// User prompt asking for a summary of `dir` from its children's summaries.
+pub fn directory_summary_prompt(dir: &str, children: &[&Summary]) -> String {
+    let entries: Vec<String> = children
+        .iter()
+        .map(|child| match child.kind {
+            SummaryKind::File => format!("- `{}`: {}", child.path, child.summary),
+            SummaryKind::Directory => format!("- `{}/`: {}", child.path, child.summary),
+        })
+        .collect();
+    format!(
+        "## Directory: {}\n\n{}\n\nSummarize this directory.",
+        display_dir(dir),
+        entries.join("\n")
+    )
+}
+
+/// Project map for smart context: the root and top-level directories, plus
+/// every directory containing one of `paths`, shallowest first.
+///
+/// Returns `None` when no summary applies, e.g. before `arq kg summarize`
+/// has run.
+pub fn project_map<'a>(
+    summaries: &[Summary],
+    paths: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let mut wanted: HashSet<&str> = HashSet::new();
+    for path in paths {
+        let mut dir = parent_dir(path);
+        while wanted.insert(dir) && dir != ROOT_DIR {
+            dir = parent_dir(dir);
+        }
+    }
+
+    let mut selected: Vec<&Summary> = summaries
+        .iter()
+        .filter(|s| s.kind == SummaryKind::Directory)
+        .filter(|s| depth(&s.path) <= 1 || wanted.contains(s.path.as_str()))
+        .collect();
+    if selected.is_empty() {
+        return None;
+    }
+    selected.sort_by(|a, b| {
+        depth(&a.path)
+            .cmp(&depth(&b.path))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    selected.truncate(PROJECT_MAP_MAX_DIRS);
+    // Back in path order, so subdirectories follow their parent
+    selected.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let entries: Vec<String> = selected
+        .iter()
+        .map(|s| format!("- `{}`: {}", display_dir(&s.path), s.summary))
+        .collect();
+    Some(format!(
+        "{}\n\n{}\n",
+        PROJECT_MAP_HEADING,
+        entries.join("\n")
+    ))
+}
+
+/// `dir` with a trailing slash, as shown to the model.
+fn display_dir(dir: &str) -> String {
+    if dir == ROOT_DIR {
+        "./".to_string()
+    } else {
+        format!("{}/", dir)
+    }
+}
@@ -0,0 +1,139 @@
+//! Activity analytics across stored tasks.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::llm::TokenUsage;
+use crate::phase::Phase;
+use crate::research::SourceType;
+use crate::task::Task;
+
+/// Number of most-referenced files kept in [`TaskStats::top_files`].
+pub const TOP_FILES_LIMIT: usize = 10;
+
+/// Usage totals for a single task.
+#[derive(Debug, Clone)]
+pub struct TaskUsageSummary {
+    pub name: String,
+    pub phase: Phase,
+    pub tokens: TokenUsage,
+    /// Estimated cost in USD; `None` if any run used a model without known pricing
+    pub cost: Option<f64>,
+}
+
+/// Summary of activity across all tasks.
+#[derive(Debug, Clone, Default)]
+pub struct TaskStats {
+    /// Total number of tasks
+    pub total_tasks: usize,
+    /// Number of tasks in each phase, in workflow order
+    pub tasks_per_phase: Vec<(Phase, usize)>,
+    /// Number of recorded research runs
+    pub research_runs: usize,
+    /// Mean duration of recorded research runs
+    pub average_research_duration: Option<Duration>,
+    /// Per-task usage, for tasks with recorded runs
+    pub task_usage: Vec<TaskUsageSummary>,
+    /// Files most often referenced by research docs, with reference counts
+    pub top_files: Vec<(String, usize)>,
+}
+
+impl TaskStats {
+    /// Compute statistics from task metadata and usage records.
+    pub fn from_tasks(tasks: &[Task]) -> Self {
+        let tasks_per_phase = [
+            Phase::Research,
+            Phase::Planning,
+            Phase::Agent,
+            Phase::Complete,
+        ]
+        .into_iter()
+        .map(|phase| (phase, tasks.iter().filter(|t| t.phase == phase).count()))
+        .collect();
+
+        let research_durations: Vec<u64> = tasks
+            .iter()
+            .flat_map(|t| &t.usage)
+            .filter(|r| r.phase == Phase::Research)
+            .map(|r| r.duration_ms)
+            .collect();
+        let average_research_duration = (!research_durations.is_empty()).then(|| {
+            Duration::from_millis(
+                research_durations.iter().sum::<u64>() / research_durations.len() as u64,
+            )
+        });
+
+        let task_usage = tasks
+            .iter()
+            .filter(|t| !t.usage.is_empty())
+            .map(|t| TaskUsageSummary {
+                name: t.name.clone(),
+                phase: t.phase,
+                tokens: t.total_usage(),
+                cost: t
+                    .usage
+                    .iter()
+                    .map(|r| r.estimated_cost())
+                    .sum::<Option<f64>>(),
+            })
+            .collect();
+
+        Self {
+            total_tasks: tasks.len(),
+            tasks_per_phase,
+            research_runs: research_durations.len(),
+            average_research_duration,
+            task_usage,
+            top_files: top_referenced_files(tasks),
+        }
+    }
+
+    /// Total tokens across all tasks.
+    pub fn total_tokens(&self) -> TokenUsage {
+        let mut total = TokenUsage::default();
+        for usage in &self.task_usage {
+            total += usage.tokens;
+        }
+        total
+    }
+
+    /// Total estimated cost across all tasks, if every run had known pricing.
+    pub fn total_cost(&self) -> Option<f64> {
+        self.task_usage.iter().map(|u| u.cost).sum()
+    }
+}
+
+/// Count how many research docs reference each file, most referenced first.
+///
+/// A file counts once per task, whether it appears as a source or in findings.
+fn top_referenced_files(tasks: &[Task]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+
+    for doc in tasks.iter().filter_map(|t| t.research_doc.as_ref()) {
+        let mut files: Vec<&str> = doc
+            .sources
+            .iter()
+            .filter(|s| matches!(s.source_type, SourceType::File))
+            .map(|s| s.location.as_str())
+            .chain(
+                doc.codebase_analysis
+                    .iter()
+                    .flat_map(|f| f.related_files.iter().map(String::as_str)),
+            )
+            .collect();
+        files.sort_unstable();
+        files.dedup();
+
+        for file in files {
+            *counts.entry(file).or_default() += 1;
+        }
+    }
+
+    let mut top: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(file, count)| (file.to_string(), count))
+        .collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(TOP_FILES_LIMIT);
+    top
+}
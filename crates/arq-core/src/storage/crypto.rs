@@ -0,0 +1,231 @@
+//! Encryption at rest for task data.
+//!
+//! Tasks embed their research documents, which quote source code, so with
+//! `storage.encrypt` the files under the data directory are sealed with
+//! AES-256-GCM. The key comes from `ARQ_STORAGE_KEY` (64 hex characters) or
+//! the OS keyring; if neither has one, a key is generated and stored in the
+//! keyring the first time something is encrypted.
+//!
+//! [`PrivateFiles`] applies this to any file of internal data: task
+//! metadata and agent logs, and the research context cache, the LLM
+//! response cache and the sent context log, which hold code and research
+//! as well.
+//!
+//! Encrypted files start with a fixed header, followed by the nonce and the
+//! sealed contents. Files without it are read as plaintext, so enabling
+//! encryption doesn't break existing data, which is encrypted as it is
+//! rewritten.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::config::{secrets, StorageConfig};
+
+use super::error::StorageError;
+use super::file::write_atomic;
+
+/// Environment variable holding the storage key as hex.
+pub const STORAGE_KEY_ENV: &str = "ARQ_STORAGE_KEY";
+
+/// Header of an encrypted file.
+const MAGIC: &[u8] = b"ARQENC1\n";
+
+/// Key length of AES-256.
+const KEY_LEN: usize = 32;
+
+/// A key for encrypting task data.
+pub struct StorageKey {
+    key: LessSafeKey,
+}
+
+impl StorageKey {
+    /// Key from 64 hex characters.
+    pub fn from_hex(hex_key: &str) -> Result<Self, StorageError> {
+        let bytes = hex::decode(hex_key.trim())
+            .map_err(|e| StorageError::Encryption(format!("invalid storage key: {}", e)))?;
+        if bytes.len() != KEY_LEN {
+            return Err(StorageError::Encryption(format!(
+                "invalid storage key: expected {} hex characters, got {}",
+                KEY_LEN * 2,
+                hex_key.trim().len()
+            )));
+        }
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| StorageError::Encryption("invalid storage key".to_string()))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// A new random key, with its hex form.
+    pub fn generate() -> Result<(Self, String), StorageError> {
+        let mut bytes = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| StorageError::Encryption("failed to generate a key".to_string()))?;
+        let hex_key = hex::encode(bytes);
+        Ok((Self::from_hex(&hex_key)?, hex_key))
+    }
+
+    /// The configured key: `ARQ_STORAGE_KEY`, then the keyring.
+    ///
+    /// With `create`, a missing key is generated and saved to the keyring.
+    pub fn load(create: bool) -> Result<Self, StorageError> {
+        if let Some(hex_key) = std::env::var(STORAGE_KEY_ENV)
+            .ok()
+            .filter(|k| !k.trim().is_empty())
+        {
+            return Self::from_hex(&hex_key);
+        }
+        if let Some(hex_key) = secrets::get_storage_key() {
+            return Self::from_hex(&hex_key);
+        }
+        if !create {
+            return Err(StorageError::Encryption(format!(
+                "data is encrypted but no key was found; set {} or restore the \
+                 keyring entry",
+                STORAGE_KEY_ENV
+            )));
+        }
+
+        let (key, hex_key) = Self::generate()?;
+        secrets::set_storage_key(&hex_key).map_err(|e| {
+            StorageError::Encryption(format!(
+                "no storage key found and it could not be saved ({}); set {}",
+                e, STORAGE_KEY_ENV
+            ))
+        })?;
+        Ok(key)
+    }
+
+    /// Seal `plaintext` under a fresh nonce.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| StorageError::Encryption("failed to generate a nonce".to_string()))?;
+
+        let mut sealed = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| StorageError::Encryption("failed to encrypt".to_string()))?;
+
+        let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + sealed.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    /// Open contents written by [`encrypt`](Self::encrypt).
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let body = data
+            .strip_prefix(MAGIC)
+            .filter(|body| body.len() >= NONCE_LEN)
+            .ok_or_else(|| StorageError::Encryption("not an encrypted file".to_string()))?;
+        let (nonce, sealed) = body.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| StorageError::Encryption("invalid nonce".to_string()))?;
+
+        let mut sealed = sealed.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| {
+                StorageError::Encryption(
+                    "decryption failed; the file was encrypted with another key or is corrupt"
+                        .to_string(),
+                )
+            })?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+/// Whether `data` was written encrypted.
+pub(super) fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Reads and writes files of internal data, encrypted when
+/// `storage.encrypt` is set.
+///
+/// The key is looked up on first use and shared by clones.
+#[derive(Clone, Default)]
+pub struct PrivateFiles {
+    encrypt: bool,
+    key: Arc<OnceLock<StorageKey>>,
+}
+
+impl fmt::Debug for PrivateFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivateFiles")
+            .field("encrypt", &self.encrypt)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PrivateFiles {
+    /// Files encrypted when `encrypt` is set, otherwise plaintext.
+    pub fn new(encrypt: bool) -> Self {
+        Self {
+            encrypt,
+            key: Arc::default(),
+        }
+    }
+
+    /// Files encrypted as `storage.encrypt` says.
+    pub fn from_config(config: &StorageConfig) -> Self {
+        Self::new(config.encrypt)
+    }
+
+    /// Uses `key` for encryption instead of looking one up.
+    pub fn with_key(self, key: StorageKey) -> Self {
+        let _ = self.key.set(key);
+        self
+    }
+
+    /// The encryption key, creating one if `create` and none is configured.
+    fn key(&self, create: bool) -> Result<&StorageKey, StorageError> {
+        if let Some(key) = self.key.get() {
+            return Ok(key);
+        }
+        let key = StorageKey::load(create)?;
+        Ok(self.key.get_or_init(|| key))
+    }
+
+    /// Replace the file at `path` atomically, encrypted if enabled.
+    pub fn write(&self, path: &Path, contents: &[u8]) -> Result<(), StorageError> {
+        if self.encrypt {
+            write_atomic(path, &self.key(true)?.encrypt(contents)?)
+        } else {
+            write_atomic(path, contents)
+        }
+    }
+
+    /// Read the file at `path`, decrypting it if it was encrypted.
+    ///
+    /// Encrypted files are read even with encryption disabled, so turning
+    /// it off doesn't lose access to existing data.
+    pub fn read(&self, path: &Path) -> Result<String, StorageError> {
+        let data = std::fs::read(path).map_err(|e| StorageError::io(path, e))?;
+        let data = if is_encrypted(&data) {
+            self.key(false)?.decrypt(&data)?
+        } else {
+            data
+        };
+        String::from_utf8(data).map_err(|e| {
+            StorageError::io(
+                path,
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+            )
+        })
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 use thiserror::Error;
 
+use crate::planning::PlanSchemaError;
+
 /// Errors that can occur during storage operations.
 #[derive(Debug, Error)]
 pub enum StorageError {
@@ -20,8 +22,27 @@ pub enum StorageError {
     #[error("YAML serialization error: {0}")]
     Yaml(#[from] serde_yaml::Error),
 
+    #[error(transparent)]
+    Plan(#[from] PlanSchemaError),
+
     #[error("Invalid task directory: {0}")]
     InvalidDirectory(PathBuf),
+
+    #[error("Invalid task ID '{id}' in {}", path.display())]
+    InvalidTaskId { id: String, path: PathBuf },
+
+    #[error("Data directory is locked by {holder} ({path})")]
+    Locked { path: PathBuf, holder: String },
+
+    #[error("Data directory {path} belongs to user '{owner}', not '{user}'")]
+    NotOwner {
+        path: PathBuf,
+        owner: String,
+        user: String,
+    },
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
 }
 
 impl StorageError {
@@ -1,12 +1,20 @@
 use std::fs;
-use std::path::PathBuf;
-
-use crate::config::{StorageConfig, DEFAULT_CURRENT_FILE};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicUsize;
+use std::time::Duration;
+
+use crate::agent::{ExecutionItem, ExecutionResult};
+use crate::config::{
+    current_user, StorageConfig, DEFAULT_AGENT_LOG_FILE, DEFAULT_AGENT_RESULTS_FILE,
+    DEFAULT_CURRENT_FILE, DEFAULT_LOCK_FILE, DEFAULT_MIRROR_TASK_FILE, DEFAULT_OWNER_FILE,
+};
 use crate::planning::Plan;
 use crate::research::ResearchDoc;
 use crate::task::{Task, TaskSummary};
 
+use super::crypto::{PrivateFiles, StorageKey};
 use super::error::StorageError;
+use super::lock::StorageGuard;
 use super::Storage;
 
 /// File-based storage implementation.
@@ -16,16 +24,39 @@ use super::Storage;
 /// project/.arq/                    # User-visible outputs
 ///   research-doc.md                # Current task's research
 ///   plan.yaml                      # Current task's plan
+///   tasks/{task-id}/               # With `storage.mirror_to_repo`:
+///     task.yaml                    #   Task metadata (importable)
+///     research-doc.md              #   Research document
+///     plan.yaml                    #   Plan
+///     agent-log.md                 #   Agent execution log
 ///
 /// ~/.arq/projects/{hash}/          # Internal data
+///   owner                          # User that owns this directory
+///   .lock                          # Held while a write is in progress
 ///   current                        # Current task ID
 ///   tasks/{task-id}/
 ///     task.json                    # Task metadata
+///     agent-log.md                 # Agent execution log
 /// ```
+///
+/// Writes take the lock file and replace files atomically, and every access
+/// checks the owner marker, so a data directory shared between users fails
+/// with a clear error instead of being silently overwritten. With
+/// `storage.shared` set, each user gets their own `users/{user}/` namespace.
+/// With `storage.layout = "local"` the internal data moves to `.arq/data/`
+/// in the project (see [`StorageConfig::project_dir`]).
+///
+/// With `storage.encrypt` set, task metadata and agent logs under the data
+/// directory are encrypted (see [`PrivateFiles`]). The project's `.arq/`
+/// outputs and the repository mirror stay readable.
 pub struct FileStorage {
-    /// Base path for internal data (~/.arq/projects/{hash}/)
+    /// Base path for internal data (~/.arq/projects/{hash}/ or .arq/data/)
     base_path: PathBuf,
     config: StorageConfig,
+    /// Reads and writes internal data, encrypted if enabled.
+    files: PrivateFiles,
+    /// Guards of the write lock alive, so that nested ones don't wait for it.
+    lock_depth: AtomicUsize,
 }
 
 impl Default for FileStorage {
@@ -37,15 +68,34 @@ impl Default for FileStorage {
 impl FileStorage {
     /// Creates a new FileStorage with default config, using project-specific directory.
     pub fn new() -> Self {
-        let config = StorageConfig::default();
-        let base_path = config.project_dir();
-        Self { base_path, config }
+        Self::with_config(StorageConfig::default())
     }
 
     /// Creates a new FileStorage with custom configuration.
     pub fn with_config(config: StorageConfig) -> Self {
         let base_path = config.project_dir();
-        Self { base_path, config }
+        Self {
+            base_path,
+            files: PrivateFiles::from_config(&config),
+            config,
+            lock_depth: AtomicUsize::new(0),
+        }
+    }
+
+    /// Uses `key` for encryption instead of looking one up.
+    pub fn with_key(mut self, key: StorageKey) -> Self {
+        self.files = self.files.with_key(key);
+        self
+    }
+
+    /// Writes a file of internal data, encrypted if enabled.
+    fn write_private(&self, path: &Path, contents: &[u8]) -> Result<(), StorageError> {
+        self.files.write(path, contents)
+    }
+
+    /// Reads a file of internal data, decrypting it if it was encrypted.
+    fn read_private(&self, path: &Path) -> Result<String, StorageError> {
+        self.files.read(path)
     }
 
     /// Returns the path to the tasks directory.
@@ -92,6 +142,57 @@ impl FileStorage {
         self.base_path.join(DEFAULT_CURRENT_FILE)
     }
 
+    /// Returns the path to the owner marker file.
+    fn owner_file(&self) -> PathBuf {
+        self.base_path.join(DEFAULT_OWNER_FILE)
+    }
+
+    /// Fails if the data directory is owned by another user.
+    fn check_owner(&self) -> Result<(), StorageError> {
+        let path = self.owner_file();
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let owner = fs::read_to_string(&path).map_err(|e| StorageError::io(&path, e))?;
+        let owner = owner.trim();
+        let user = current_user();
+        if owner.is_empty() || owner == user {
+            Ok(())
+        } else {
+            Err(StorageError::NotOwner {
+                path: self.base_path.clone(),
+                owner: owner.to_string(),
+                user,
+            })
+        }
+    }
+
+    /// Checks ownership and takes the write lock, claiming the directory if
+    /// new. Held already by a caller, e.g. [`Storage::lock_writes`], it
+    /// doesn't wait.
+    fn lock(&self) -> Result<StorageGuard<'_>, StorageError> {
+        if !self.base_path.exists() {
+            self.config
+                .create_project_dir()
+                .map_err(|e| StorageError::io(&self.base_path, e))?;
+        }
+        self.check_owner()?;
+
+        let lock = StorageGuard::acquire(
+            self.base_path.join(DEFAULT_LOCK_FILE),
+            Duration::from_millis(self.config.lock_timeout_ms),
+            &self.lock_depth,
+        )?;
+
+        let owner = self.owner_file();
+        if !owner.exists() {
+            write_atomic(&owner, current_user().as_bytes())?;
+        }
+
+        Ok(lock)
+    }
+
     /// Ensures the tasks directory exists.
     fn ensure_tasks_dir(&self) -> Result<(), StorageError> {
         let dir = self.tasks_dir();
@@ -101,6 +202,114 @@ impl FileStorage {
         Ok(())
     }
 
+    /// Writes a file into a task's repository mirror, if mirroring is enabled.
+    fn mirror_file(&self, id: &str, file_name: &str, contents: &[u8]) -> Result<(), StorageError> {
+        if !self.config.mirror_to_repo {
+            return Ok(());
+        }
+        let dir = self.config.mirror_task_path(id);
+        fs::create_dir_all(&dir).map_err(|e| StorageError::io(&dir, e))?;
+        write_atomic(&dir.join(file_name), contents)
+    }
+
+    /// Mirrors a task's metadata and artifacts into the repository.
+    fn mirror_task(&self, task: &Task) -> Result<(), StorageError> {
+        if !self.config.mirror_to_repo {
+            return Ok(());
+        }
+        let yaml = serde_yaml::to_string(task)?;
+        self.mirror_file(&task.id, DEFAULT_MIRROR_TASK_FILE, yaml.as_bytes())?;
+        if let Some(ref doc) = task.research_doc {
+            let markdown = doc.to_markdown();
+            self.mirror_file(&task.id, &self.config.research_file, markdown.as_bytes())?;
+        }
+        if let Some(ref plan) = task.plan {
+            let yaml = plan.to_yaml()?;
+            self.mirror_file(&task.id, &self.config.plan_file, yaml.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Results of the edits recorded in a task's agent log, oldest first.
+    pub fn load_agent_log(&self, task_id: &str) -> Result<Vec<ExecutionResult>, StorageError> {
+        let path = self.task_dir(task_id).join(DEFAULT_AGENT_RESULTS_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_str(&self.read_private(&path)?)?)
+    }
+
+    /// Appends the results of an agent run to the task's agent log
+    /// (mirrored when enabled).
+    pub fn save_agent_log(
+        &self,
+        task_id: &str,
+        results: &[ExecutionResult],
+    ) -> Result<(), StorageError> {
+        let _lock = self.lock()?;
+        self.ensure_task_dir(task_id)?;
+
+        let mut logged = self.load_agent_log(task_id)?;
+        logged.extend_from_slice(results);
+        self.write_private(
+            &self.task_dir(task_id).join(DEFAULT_AGENT_RESULTS_FILE),
+            serde_json::to_string_pretty(&logged)?.as_bytes(),
+        )?;
+        let markdown = agent_log_markdown(&logged);
+        self.write_private(
+            &self.task_dir(task_id).join(DEFAULT_AGENT_LOG_FILE),
+            markdown.as_bytes(),
+        )?;
+        self.mirror_file(task_id, DEFAULT_AGENT_LOG_FILE, markdown.as_bytes())
+    }
+
+    /// Imports tasks from the repository mirror (`.arq/tasks/*/task.yaml`).
+    ///
+    /// A mirrored task is imported when it is unknown locally or was updated
+    /// more recently than the local copy. Returns the imported task IDs.
+    ///
+    /// The mirror is committed, so anyone can edit it: a task whose ID isn't
+    /// a task ID, or doesn't name its directory, is rejected before its ID
+    /// becomes a path.
+    pub fn import_mirrored_tasks(&self) -> Result<Vec<String>, StorageError> {
+        let mirror = self.config.mirror_tasks_path();
+        if !mirror.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut imported = Vec::new();
+        let entries = fs::read_dir(&mirror).map_err(|e| StorageError::io(&mirror, e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| StorageError::io(&mirror, e))?;
+            let path = entry.path().join(DEFAULT_MIRROR_TASK_FILE);
+            if !path.is_file() {
+                continue;
+            }
+
+            let yaml = fs::read_to_string(&path).map_err(|e| StorageError::io(&path, e))?;
+            let mut task: Task = serde_yaml::from_str(&yaml)?;
+            if !Task::is_valid_id(&task.id) || entry.file_name() != task.id.as_str() {
+                return Err(StorageError::InvalidTaskId { id: task.id, path });
+            }
+            upgrade_plan(&mut task)?;
+            let is_newer = match self.load_task(&task.id) {
+                Ok(local) => local.updated_at < task.updated_at,
+                Err(StorageError::TaskNotFound(_)) => true,
+                Err(e) => return Err(e),
+            };
+            if is_newer {
+                let _lock = self.lock()?;
+                self.ensure_task_dir(&task.id)?;
+                let json = serde_json::to_string_pretty(&task)?;
+                self.write_private(&self.task_file(&task.id), json.as_bytes())?;
+                imported.push(task.id);
+            }
+        }
+
+        imported.sort();
+        Ok(imported)
+    }
+
     /// Ensures a task's directory exists.
     fn ensure_task_dir(&self, id: &str) -> Result<(), StorageError> {
         self.ensure_tasks_dir()?;
@@ -114,28 +323,32 @@ impl FileStorage {
 
 impl Storage for FileStorage {
     fn save_task(&self, task: &Task) -> Result<(), StorageError> {
+        let _lock = self.lock()?;
         self.ensure_task_dir(&task.id)?;
 
         let path = self.task_file(&task.id);
         let json = serde_json::to_string_pretty(task)?;
-        fs::write(&path, json).map_err(|e| StorageError::io(&path, e))?;
+        self.write_private(&path, json.as_bytes())?;
 
-        Ok(())
+        self.mirror_task(task)
     }
 
     fn load_task(&self, id: &str) -> Result<Task, StorageError> {
+        self.check_owner()?;
         let path = self.task_file(id);
         if !path.exists() {
             return Err(StorageError::TaskNotFound(id.to_string()));
         }
 
-        let json = fs::read_to_string(&path).map_err(|e| StorageError::io(&path, e))?;
-        let task: Task = serde_json::from_str(&json)?;
+        let json = self.read_private(&path)?;
+        let mut task: Task = serde_json::from_str(&json)?;
+        upgrade_plan(&mut task)?;
 
         Ok(task)
     }
 
     fn list_tasks(&self) -> Result<Vec<TaskSummary>, StorageError> {
+        self.check_owner()?;
         let tasks_dir = self.tasks_dir();
         if !tasks_dir.exists() {
             return Ok(Vec::new());
@@ -171,7 +384,15 @@ impl Storage for FileStorage {
             return Err(StorageError::TaskNotFound(id.to_string()));
         }
 
-        fs::remove_dir_all(&dir).map_err(|e| StorageError::io(&dir, e))?;
+        {
+            let _lock = self.lock()?;
+            fs::remove_dir_all(&dir).map_err(|e| StorageError::io(&dir, e))?;
+
+            let mirror = self.config.mirror_task_path(id);
+            if self.config.mirror_to_repo && mirror.exists() {
+                fs::remove_dir_all(&mirror).map_err(|e| StorageError::io(&mirror, e))?;
+            }
+        }
 
         // Clear current if this was the current task
         if let Ok(Some(current_id)) = self.get_current_task_id() {
@@ -183,27 +404,34 @@ impl Storage for FileStorage {
         Ok(())
     }
 
-    fn save_research_doc(&self, _task_id: &str, doc: &ResearchDoc) -> Result<(), StorageError> {
+    fn save_research_doc(&self, task_id: &str, doc: &ResearchDoc) -> Result<(), StorageError> {
+        let _lock = self.lock()?;
         self.ensure_local_arq_dir()?;
 
         let path = self.research_doc_file();
         let markdown = doc.to_markdown();
-        fs::write(&path, markdown).map_err(|e| StorageError::io(&path, e))?;
+        write_atomic(&path, markdown.as_bytes())?;
 
-        Ok(())
+        self.mirror_file(task_id, &self.config.research_file, markdown.as_bytes())
     }
 
-    fn save_plan(&self, _task_id: &str, plan: &Plan) -> Result<(), StorageError> {
+    fn save_plan(&self, task_id: &str, plan: &Plan) -> Result<(), StorageError> {
+        let _lock = self.lock()?;
         self.ensure_local_arq_dir()?;
 
+        let mut plan = plan.clone();
+        plan.upgrade()?;
+        plan.validate()?;
+
         let path = self.plan_file();
         let yaml = plan.to_yaml()?;
-        fs::write(&path, yaml).map_err(|e| StorageError::io(&path, e))?;
+        write_atomic(&path, yaml.as_bytes())?;
 
-        Ok(())
+        self.mirror_file(task_id, &self.config.plan_file, yaml.as_bytes())
     }
 
     fn get_current_task_id(&self) -> Result<Option<String>, StorageError> {
+        self.check_owner()?;
         let path = self.current_file();
         if !path.exists() {
             return Ok(None);
@@ -222,18 +450,13 @@ impl Storage for FileStorage {
     }
 
     fn set_current_task_id(&self, id: Option<&str>) -> Result<(), StorageError> {
+        // Creates the base directory if needed
+        let _lock = self.lock()?;
         let path = self.current_file();
 
-        // Ensure base directory exists
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
-            }
-        }
-
         match id {
             Some(id) => {
-                fs::write(&path, id).map_err(|e| StorageError::io(&path, e))?;
+                write_atomic(&path, id.as_bytes())?;
             }
             None => {
                 if path.exists() {
@@ -244,4 +467,62 @@ impl Storage for FileStorage {
 
         Ok(())
     }
+
+    fn lock_writes(&self) -> Result<StorageGuard<'_>, StorageError> {
+        self.lock()
+    }
+}
+
+/// Upgrade the plan of a loaded task to the current schema version, failing
+/// for plans written by a newer version of Arq.
+fn upgrade_plan(task: &mut Task) -> Result<(), StorageError> {
+    if let Some(plan) = task.plan.as_mut() {
+        plan.upgrade()?;
+    }
+    Ok(())
+}
+
+/// Render agent execution results as a Markdown log.
+fn agent_log_markdown(results: &[ExecutionResult]) -> String {
+    let mut out = String::from("# Agent Log\n");
+    for (i, result) in results.iter().enumerate() {
+        let (action, description) = match &result.item {
+            ExecutionItem::Create { description, .. } => ("Create", description),
+            ExecutionItem::Modify { description, .. } => ("Modify", description),
+        };
+        out.push_str(&format!(
+            "\n## {}. {} `{}` ({:?})\n\n{}\n",
+            i + 1,
+            action,
+            result.item.path(),
+            result.conformance,
+            description
+        ));
+        if !result.deviations.is_empty() {
+            out.push_str("\n**Deviations:**\n\n");
+            for deviation in &result.deviations {
+                out.push_str(&format!("- {}\n", deviation));
+            }
+        }
+        out.push_str(&format!(
+            "\n```\n{}\n```\n",
+            result.generated_code.trim_end()
+        ));
+    }
+    out
+}
+
+/// Write a file via a temporary sibling and rename, so readers never see a partial write.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), StorageError> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    fs::write(&tmp, contents).map_err(|e| StorageError::io(&tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        StorageError::io(path, e)
+    })
 }
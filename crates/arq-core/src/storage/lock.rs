@@ -0,0 +1,167 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{current_user, DEFAULT_LOCK_STALE_SECS};
+
+use super::error::StorageError;
+
+/// Interval between attempts to take a held lock.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Exclusive lock on a data directory, held while the guard is alive.
+///
+/// Uses a lock file created with `O_EXCL`, which (unlike advisory `flock`)
+/// is honoured by NFS. The file records the holder so contention errors can
+/// name it. Locks older than [`DEFAULT_LOCK_STALE_SECS`] are assumed to be
+/// left behind by a crashed process and are broken.
+#[derive(Debug)]
+pub struct StorageLock {
+    path: PathBuf,
+}
+
+impl StorageLock {
+    /// Acquire the lock at `path`, waiting up to `timeout` for other holders.
+    ///
+    /// Called from async code, the wait happens off the runtime's worker
+    /// (see [`tokio::task::block_in_place`]), so other tasks keep running.
+    pub fn acquire(path: impl Into<PathBuf>, timeout: Duration) -> Result<Self, StorageError> {
+        let path = path.into();
+        match Self::try_acquire(&path)? {
+            Some(lock) => Ok(lock),
+            None if timeout.is_zero() => Err(Self::locked(path)),
+            None => match tokio::runtime::Handle::try_current() {
+                Ok(handle)
+                    if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread =>
+                {
+                    tokio::task::block_in_place(|| Self::wait(path, timeout))
+                }
+                _ => Self::wait(path, timeout),
+            },
+        }
+    }
+
+    /// Take the lock if it is free or stale; `None` while someone holds it.
+    fn try_acquire(path: &Path) -> Result<Option<Self>, StorageError> {
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    let holder = format!("{} (pid {})", current_user(), std::process::id());
+                    file.write_all(holder.as_bytes())
+                        .map_err(|e| StorageError::io(path, e))?;
+                    return Ok(Some(Self {
+                        path: path.to_path_buf(),
+                    }));
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if is_stale(path) {
+                        let _ = fs::remove_file(path);
+                        continue;
+                    }
+                    return Ok(None);
+                }
+                Err(e) => return Err(StorageError::io(path, e)),
+            }
+        }
+    }
+
+    /// Retry until the lock is free or `timeout` passes.
+    fn wait(path: PathBuf, timeout: Duration) -> Result<Self, StorageError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            std::thread::sleep(LOCK_RETRY_INTERVAL);
+            if let Some(lock) = Self::try_acquire(&path)? {
+                return Ok(lock);
+            }
+            if Instant::now() >= deadline {
+                return Err(Self::locked(path));
+            }
+        }
+    }
+
+    /// The error for a lock held by someone else.
+    fn locked(path: PathBuf) -> StorageError {
+        let holder = fs::read_to_string(&path)
+            .map(|h| h.trim().to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+        StorageError::Locked { path, holder }
+    }
+
+    /// Whether someone holds the lock at `path`, ignoring stale locks.
+    pub fn is_held(path: &Path) -> bool {
+        path.exists() && !is_stale(path)
+    }
+
+    /// Mark the lock as still in use, so that holding it for longer than
+    /// [`DEFAULT_LOCK_STALE_SECS`] doesn't make it look abandoned.
+    pub fn refresh(&self) {
+        let holder = format!("{} (pid {})", current_user(), std::process::id());
+        let _ = fs::write(&self.path, holder);
+    }
+
+    /// Path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StorageLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Write access to a storage backend, held until dropped.
+///
+/// Guards nest: one taken while another guard of the same storage is alive
+/// holds nothing itself, and the outer guard keeps the lock. That lets a
+/// load, change and save hold the lock throughout while each save still
+/// takes it when called on its own.
+#[derive(Debug, Default)]
+pub struct StorageGuard<'a> {
+    _lock: Option<StorageLock>,
+    depth: Option<&'a AtomicUsize>,
+}
+
+impl<'a> StorageGuard<'a> {
+    /// Take the lock at `path`, unless a guard counted by `depth` holds it.
+    pub(crate) fn acquire(
+        path: impl Into<PathBuf>,
+        timeout: Duration,
+        depth: &'a AtomicUsize,
+    ) -> Result<Self, StorageError> {
+        let lock = match depth.load(Ordering::SeqCst) {
+            0 => Some(StorageLock::acquire(path, timeout)?),
+            _ => None,
+        };
+        depth.fetch_add(1, Ordering::SeqCst);
+        Ok(Self {
+            _lock: lock,
+            depth: Some(depth),
+        })
+    }
+
+    /// A guard of storage that other processes don't share.
+    pub fn unlocked() -> Self {
+        Self::default()
+    }
+}
+
+impl Drop for StorageGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(depth) = self.depth {
+            depth.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Whether a lock file is old enough to have been abandoned.
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > Duration::from_secs(DEFAULT_LOCK_STALE_SECS))
+}
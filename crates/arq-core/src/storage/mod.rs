@@ -1,8 +1,12 @@
+mod crypto;
 mod error;
 mod file;
+mod lock;
 
+pub use crypto::{PrivateFiles, StorageKey, STORAGE_KEY_ENV};
 pub use error::StorageError;
-pub use file::FileStorage;
+pub use file::{write_atomic, FileStorage};
+pub use lock::{StorageGuard, StorageLock};
 
 use crate::planning::Plan;
 use crate::research::ResearchDoc;
@@ -28,7 +32,8 @@ pub trait Storage {
     /// Saves a research document for a task.
     fn save_research_doc(&self, task_id: &str, doc: &ResearchDoc) -> Result<(), StorageError>;
 
-    /// Saves a plan for a task.
+    /// Saves a plan for a task. Plans are upgraded to the current schema
+    /// version and must pass validation.
     fn save_plan(&self, task_id: &str, plan: &Plan) -> Result<(), StorageError>;
 
     /// Gets the current task ID (if set).
@@ -36,4 +41,11 @@ pub trait Storage {
 
     /// Sets the current task ID.
     fn set_current_task_id(&self, id: Option<&str>) -> Result<(), StorageError>;
+
+    /// Keeps other processes from writing until the guard is dropped, so a
+    /// task can be loaded, changed and saved without losing their updates.
+    /// Saves made while holding it don't wait for it.
+    fn lock_writes(&self) -> Result<StorageGuard<'_>, StorageError> {
+        Ok(StorageGuard::unlocked())
+    }
 }
@@ -2,6 +2,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::config::{
+    DEFAULT_TASK_NAME_SYSTEM_PROMPT, DEFAULT_TASK_NAME_TIMEOUT_SECS, DEFAULT_TASK_NAME_WORDS,
+};
+use crate::llm::{TokenUsage, LLM};
 use crate::phase::Phase;
 use crate::planning::Plan;
 use crate::research::ResearchDoc;
@@ -14,7 +18,7 @@ use crate::research::ResearchDoc;
 pub struct Task {
     /// Unique identifier for this task
     pub id: String,
-    /// Human-readable name derived from the prompt
+    /// Human-readable name, suggested by the LLM or derived from the prompt
     pub name: String,
     /// The original user prompt describing what they want to do
     pub prompt: String,
@@ -28,6 +32,15 @@ pub struct Task {
     pub research_doc: Option<ResearchDoc>,
     /// Plan specification, populated after Planning phase completes
     pub plan: Option<Plan>,
+    /// LLM usage recorded for this task, one entry per completed run
+    #[serde(default)]
+    pub usage: Vec<UsageRecord>,
+    /// Files kept out of the research context, reused by refinements
+    #[serde(default)]
+    pub excluded_context: Vec<String>,
+    /// Free-form labels for grouping and filtering tasks
+    #[serde(default)]
+    pub labels: Vec<String>,
 }
 
 impl Task {
@@ -48,16 +61,37 @@ impl Task {
             updated_at: now,
             research_doc: None,
             plan: None,
+            usage: Vec::new(),
+            excluded_context: Vec::new(),
+            labels: Vec::new(),
         }
     }
 
+    /// Whether `id` has the form of a task ID: a hyphenated UUID, which is
+    /// also a safe directory name.
+    pub fn is_valid_id(id: &str) -> bool {
+        Uuid::try_parse(id).is_ok_and(|uuid| uuid.hyphenated().to_string() == id)
+    }
+
+    /// Creates a new task with an explicit name.
+    ///
+    /// Falls back to a name derived from the prompt if `name` is blank.
+    pub fn with_name(prompt: impl Into<String>, name: impl Into<String>) -> Self {
+        let mut task = Self::new(prompt);
+        let name = name.into();
+        if !name.trim().is_empty() {
+            task.name = name.trim().to_string();
+        }
+        task
+    }
+
     /// Derives a task name from the prompt.
     ///
     /// Takes the first few words and converts to kebab-case.
     fn derive_name(prompt: &str) -> String {
         prompt
             .split_whitespace()
-            .take(5)
+            .take(DEFAULT_TASK_NAME_WORDS)
             .collect::<Vec<_>>()
             .join("-")
             .to_lowercase()
@@ -66,6 +100,17 @@ impl Task {
             .collect()
     }
 
+    /// Renames the task.
+    pub fn rename(&mut self, name: &str) -> Result<(), TaskError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(TaskError::EmptyName);
+        }
+        self.name = name.to_string();
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
     /// Attempts to advance to the next phase.
     ///
     /// Returns true if advancement was successful, false if already complete
@@ -125,6 +170,68 @@ impl Task {
         Ok(())
     }
 
+    /// Records LLM usage for a completed run.
+    pub fn record_usage(&mut self, record: UsageRecord) {
+        self.usage.push(record);
+        self.updated_at = Utc::now();
+    }
+
+    /// Sets the files to keep out of the research context.
+    pub fn set_excluded_context(&mut self, files: Vec<String>) {
+        self.excluded_context = files;
+        self.updated_at = Utc::now();
+    }
+
+    /// Adds labels, skipping blank ones and ones the task already has
+    /// (compared case-insensitively).
+    ///
+    /// Returns the labels that were added.
+    pub fn add_labels<S: AsRef<str>>(&mut self, labels: &[S]) -> Vec<String> {
+        let mut added = Vec::new();
+        for label in labels {
+            let label = label.as_ref().trim();
+            if !label.is_empty() && !has_label(&self.labels, label) {
+                self.labels.push(label.to_string());
+                added.push(label.to_string());
+            }
+        }
+        if !added.is_empty() {
+            self.updated_at = Utc::now();
+        }
+        added
+    }
+
+    /// Removes labels, compared case-insensitively.
+    ///
+    /// Returns the labels that were removed.
+    pub fn remove_labels<S: AsRef<str>>(&mut self, labels: &[S]) -> Vec<String> {
+        let (removed, kept): (Vec<String>, Vec<String>) =
+            std::mem::take(&mut self.labels).into_iter().partition(|l| {
+                labels
+                    .iter()
+                    .any(|r| l.eq_ignore_ascii_case(r.as_ref().trim()))
+            });
+        self.labels = kept;
+        if !removed.is_empty() {
+            self.updated_at = Utc::now();
+        }
+        removed
+    }
+
+    /// Whether the task has `label`, compared case-insensitively.
+    pub fn has_label(&self, label: &str) -> bool {
+        has_label(&self.labels, label)
+    }
+
+    /// Total tokens used by this task across all runs.
+    pub fn total_usage(&self) -> TokenUsage {
+        let mut total = TokenUsage::default();
+        for record in &self.usage {
+            total += record.tokens;
+        }
+        total
+    }
+
     /// Converts the task to a summary (for listings).
     pub fn to_summary(&self) -> TaskSummary {
         TaskSummary {
@@ -133,10 +240,94 @@ impl Task {
             phase: self.phase,
             created_at: self.created_at,
             updated_at: self.updated_at,
+            labels: self.labels.clone(),
         }
     }
 }
 
+/// Whether `labels` contains `label`, compared case-insensitively.
+fn has_label(labels: &[String], label: &str) -> bool {
+    labels.iter().any(|l| l.eq_ignore_ascii_case(label.trim()))
+}
+
+/// LLM usage for one run of a phase (e.g. a research run).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Phase the run belonged to
+    pub phase: Phase,
+    /// Provider that served the run
+    pub provider: String,
+    /// Model that served the run
+    pub model: String,
+    /// Estimated tokens used
+    pub tokens: TokenUsage,
+    /// Wall-clock time spent waiting for the run, in milliseconds
+    pub duration_ms: u64,
+    /// When the run finished
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl UsageRecord {
+    /// Creates a record for a run that just finished.
+    pub fn new(
+        phase: Phase,
+        provider: impl Into<String>,
+        model: impl Into<String>,
+        tokens: TokenUsage,
+        duration: std::time::Duration,
+    ) -> Self {
+        Self {
+            phase,
+            provider: provider.into(),
+            model: model.into(),
+            tokens,
+            duration_ms: duration.as_millis() as u64,
+            recorded_at: Utc::now(),
+        }
+    }
+
+    /// Estimated cost in USD, if the model's pricing is known.
+    pub fn estimated_cost(&self) -> Option<f64> {
+        self.tokens.estimated_cost(&self.provider, &self.model)
+    }
+}
+
+/// Ask the LLM for a short kebab-case name for a task prompt.
+///
+/// Returns `None` if the call fails, times out or the reply is unusable, in
+/// which case callers should keep the name derived from the prompt.
+pub async fn suggest_task_name(llm: &dyn LLM, prompt: &str) -> Option<String> {
+    let reply = tokio::time::timeout(
+        std::time::Duration::from_secs(DEFAULT_TASK_NAME_TIMEOUT_SECS),
+        llm.complete_with_system(DEFAULT_TASK_NAME_SYSTEM_PROMPT, prompt),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    clean_task_name(&reply)
+}
+
+/// Normalize a suggested task name to kebab-case.
+///
+/// Uses the first non-empty line, drops quotes and punctuation, and keeps at
+/// most [`DEFAULT_TASK_NAME_WORDS`] words. Returns `None` if nothing remains.
+pub fn clean_task_name(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let words: Vec<String> = line
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .map(|w| {
+            w.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|w| !w.is_empty())
+        .take(DEFAULT_TASK_NAME_WORDS)
+        .collect();
+
+    (!words.is_empty()).then(|| words.join("-"))
+}
+
 /// A lightweight summary of a task for listings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskSummary {
@@ -145,10 +336,22 @@ pub struct TaskSummary {
     pub phase: Phase,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+impl TaskSummary {
+    /// Whether the task has `label`, compared case-insensitively.
+    pub fn has_label(&self, label: &str) -> bool {
+        has_label(&self.labels, label)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum TaskError {
     #[error("Wrong phase: expected {expected:?}, got {actual:?}")]
     WrongPhase { expected: Phase, actual: Phase },
+
+    #[error("Task name cannot be empty")]
+    EmptyName,
 }
@@ -0,0 +1,171 @@
+use arq_core::agent::{
+    parse_file_content, parse_unified_diff, unified_diff, AgentError, AgentProgress, AgentRunner,
+    ChangeKind, ConformanceStatus, ExecutionItem,
+};
+use arq_core::knowledge::PathBlocklist;
+use arq_core::planning::{FileModification, FileSpec};
+use arq_core::{LLMError, Plan, LLM};
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+/// Replies with a fixed response to every prompt.
+struct FixedLLM(&'static str);
+
+#[async_trait]
+impl LLM for FixedLLM {
+    async fn complete(&self, _prompt: &str) -> Result<String, LLMError> {
+        Ok(self.0.to_string())
+    }
+
+    async fn complete_with_system(&self, _system: &str, _prompt: &str) -> Result<String, LLMError> {
+        Ok(self.0.to_string())
+    }
+}
+
+fn plan() -> Plan {
+    let mut plan = Plan::new("Add auth", "New auth module");
+    plan.files_to_create.push(FileSpec {
+        path: "src/auth.rs".to_string(),
+        description: "Authentication".to_string(),
+        exports: Vec::new(),
+    });
+    plan.files_to_modify.push(FileModification {
+        path: "src/lib.rs".to_string(),
+        line: None,
+        description: "Register module".to_string(),
+        additions: vec!["mod auth;".to_string()],
+        removals: Vec::new(),
+    });
+    plan
+}
+
+#[test]
+fn test_unified_diff_of_new_file() {
+    let diff = unified_diff("src/auth.rs", None, "fn login() {}\n");
+    assert_eq!(
+        diff,
+        "diff --git a/src/auth.rs b/src/auth.rs\n\
+         new file mode 100644\n\
+         --- /dev/null\n\
+         +++ b/src/auth.rs\n\
+         @@ -0,0 +1,1 @@\n\
+         +fn login() {}\n"
+    );
+
+    let changes = parse_unified_diff(&diff);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind, ChangeKind::Added);
+}
+
+#[test]
+fn test_unified_diff_hunks() {
+    let original: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+    let content = original
+        .replace("line 2\n", "line 2\nline 2b\n")
+        .replace("line 18\n", "");
+    let diff = unified_diff("src/lib.rs", Some(&original), &content);
+
+    // Changes far apart get a hunk each, with three lines of context
+    let hunks: Vec<_> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+    assert_eq!(hunks, vec!["@@ -1,5 +1,6 @@", "@@ -15,6 +16,5 @@"]);
+    assert!(diff.contains("\n+line 2b\n"));
+    assert!(diff.contains("\n-line 18\n"));
+    assert!(!diff.contains("line 10"));
+
+    assert!(unified_diff("src/lib.rs", Some(&original), &original).ends_with("+++ b/src/lib.rs\n"));
+}
+
+#[test]
+fn test_parse_file_content() {
+    let response = "Here is the file:\n```rust\nfn main() {}\n```\nDone.";
+    assert_eq!(parse_file_content(response).unwrap(), "fn main() {}\n");
+
+    // Nested fences in markdown files are kept
+    let response = "```markdown\n# Usage\n\n```sh\narq run\n```\n```";
+    assert_eq!(
+        parse_file_content(response).unwrap(),
+        "# Usage\n\n```sh\narq run\n```\n"
+    );
+
+    assert_eq!(
+        parse_file_content("fn main() {}").unwrap(),
+        "fn main() {}\n"
+    );
+    assert!(matches!(
+        parse_file_content("```\n```"),
+        Err(AgentError::EmptyResponse)
+    ));
+}
+
+#[tokio::test]
+async fn test_generate_and_apply_edit() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+    std::fs::write(temp_dir.path().join("src/lib.rs"), "mod config;\n").unwrap();
+
+    let runner = AgentRunner::new(
+        FixedLLM("```rust\nmod config;\nmod auth;\n```"),
+        plan(),
+        temp_dir.path(),
+    );
+    let items = runner.items();
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[0], ExecutionItem::Create { .. }));
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let edit = runner.generate_edit(&items[1], &tx).await.unwrap();
+    assert_eq!(edit.original.as_deref(), Some("mod config;\n"));
+    assert!(edit.change().diff.contains("\n+mod auth;\n"));
+    assert!(matches!(
+        rx.recv().await,
+        Some(AgentProgress::GeneratingEdit { path }) if path == "src/lib.rs"
+    ));
+    assert!(matches!(
+        rx.recv().await,
+        Some(AgentProgress::TokenUsage(_))
+    ));
+
+    let result = edit.result(runner.plan());
+    assert_eq!(result.conformance, ConformanceStatus::Passed);
+    assert!(result.deviations.is_empty());
+
+    edit.apply(temp_dir.path()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(temp_dir.path().join("src/lib.rs")).unwrap(),
+        "mod config;\nmod auth;\n"
+    );
+
+    // Files to create get their directories
+    let edit = runner.generate_edit(&items[0], &tx).await.unwrap();
+    assert!(edit.original.is_none());
+    edit.apply(temp_dir.path()).unwrap();
+    assert!(temp_dir.path().join("src/auth.rs").is_file());
+}
+
+#[tokio::test]
+async fn test_generate_edit_refuses_unsafe_paths() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+    std::fs::write(temp_dir.path().join("src/lib.rs"), "mod config;\n").unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel();
+
+    let runner = AgentRunner::new(FixedLLM("```\n\n```"), plan(), temp_dir.path())
+        .with_private_paths(PathBlocklist::new(vec!["src/lib.rs".to_string()]));
+    let modify = ExecutionItem::Modify {
+        path: "src/lib.rs".to_string(),
+        description: String::new(),
+    };
+    assert!(matches!(
+        runner.generate_edit(&modify, &tx).await,
+        Err(AgentError::PrivatePath(path)) if path == "src/lib.rs"
+    ));
+
+    let outside = ExecutionItem::Create {
+        path: "../escape.rs".to_string(),
+        description: String::new(),
+    };
+    assert!(matches!(
+        runner.generate_edit(&outside, &tx).await,
+        Err(AgentError::InvalidPath(_))
+    ));
+}
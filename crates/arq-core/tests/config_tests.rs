@@ -2,7 +2,7 @@ use arq_core::config::{
     DEFAULT_ANTHROPIC_MODEL, DEFAULT_DATA_DIR, DEFAULT_LLM_PROVIDER, DEFAULT_MAX_FILE_SIZE,
     DEFAULT_OLLAMA_MODEL, DEFAULT_OPENAI_MODEL,
 };
-use arq_core::{Config, LLMConfig};
+use arq_core::{Config, ConfigOrigin, ContentGuard, LLMConfig};
 
 #[test]
 fn test_default_config() {
@@ -58,3 +58,253 @@ fn test_model_or_default() {
     config.model = Some("custom-model".to_string());
     assert_eq!(config.model_or_default(), "custom-model");
 }
+
+#[test]
+fn test_content_guard_config() {
+    assert_eq!(
+        Config::default().research.content_guard,
+        ContentGuard::Standard
+    );
+
+    let config: Config = toml::from_str("[research]\ncontent_guard = \"strict\"\n").unwrap();
+    assert_eq!(config.research.content_guard, ContentGuard::Strict);
+}
+
+#[test]
+fn test_for_provider() {
+    let config = LLMConfig {
+        provider: "anthropic".to_string(),
+        model: Some("claude-custom".to_string()),
+        api_key: Some("sk-test".to_string()),
+        ..Default::default()
+    };
+
+    let same = config.for_provider("anthropic");
+    assert_eq!(same.model, Some("claude-custom".to_string()));
+    assert_eq!(same.api_key, Some("sk-test".to_string()));
+
+    let other = config.for_provider("openai:gpt-4o");
+    assert_eq!(other.provider, "openai");
+    assert_eq!(other.model, Some("gpt-4o".to_string()));
+    assert_eq!(other.api_key, None);
+
+    assert_eq!(config.for_provider("ollama").model, None);
+}
+
+#[test]
+fn test_save_available_models() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("arq.toml");
+    std::fs::write(
+        &path,
+        "[llm]\n# Local models only\nprovider = \"ollama\"\n\n[context]\nmax_file_size = 2048\n",
+    )
+    .unwrap();
+
+    let models = vec!["llama3".to_string(), "qwen2.5-coder".to_string()];
+    Config::save_available_models(&path, &models).unwrap();
+
+    let config = Config::from_file(&path).unwrap();
+    assert_eq!(config.llm.available_models, models);
+    assert_eq!(config.llm.provider, "ollama");
+    assert_eq!(config.context.max_file_size, 2048);
+    // Comments are kept
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("# Local models only"));
+}
+
+#[test]
+fn test_keyring_account() {
+    use arq_core::config::secrets::keyring_account;
+
+    assert_eq!(keyring_account("claude"), "anthropic");
+    assert_eq!(keyring_account(" OpenAI "), "openai");
+    assert_eq!(keyring_account("openrouter"), "openrouter");
+}
+
+#[test]
+fn test_profile_overrides_sections() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("arq.toml");
+    std::fs::write(
+        &path,
+        r#"
+[llm]
+provider = "anthropic"
+max_tokens = 8192
+
+[context]
+max_file_size = 2048
+
+[profile.local.llm]
+provider = "ollama"
+model = "qwen2.5-coder"
+
+[profile.local.knowledge]
+search_limit = 5
+"#,
+    )
+    .unwrap();
+
+    let config = Config::from_file(&path).unwrap();
+    assert_eq!(config.llm.provider, "anthropic");
+    assert_eq!(config.llm.model, None);
+
+    let config = Config::from_file_with_profile(&path, Some("local")).unwrap();
+    assert_eq!(config.llm.provider, "ollama");
+    assert_eq!(config.llm.model, Some("qwen2.5-coder".to_string()));
+    assert_eq!(config.llm.max_tokens, 8192);
+    assert_eq!(config.context.max_file_size, 2048);
+    assert_eq!(config.knowledge.search_limit, 5);
+
+    let err = Config::from_file_with_profile(&path, Some("prod")).unwrap_err();
+    assert!(err.to_string().contains("available: local"), "{}", err);
+}
+
+#[test]
+fn test_workspace_config_inheritance() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let repo = temp_dir.path().join("repo");
+    let package = repo.join("crates").join("api");
+    std::fs::create_dir_all(&package).unwrap();
+    std::fs::create_dir(repo.join(".git")).unwrap();
+    let user = temp_dir.path().join("config.toml");
+    std::fs::write(&user, "[llm]\nprovider = \"openai\"\nmax_tokens = 1024\n").unwrap();
+    std::fs::write(
+        repo.join("arq.toml"),
+        "[llm]\nprovider = \"anthropic\"\n\n[profile.local.llm]\nprovider = \"ollama\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        package.join("arq.toml"),
+        "[context]\nmax_file_size = 2048\n",
+    )
+    .unwrap();
+    // Above the repository root, so never inherited
+    std::fs::write(temp_dir.path().join("arq.toml"), "[llm]\nmax_tokens = 1\n").unwrap();
+
+    let files = Config::discover_files(&package, Some(&user)).unwrap();
+    assert_eq!(
+        files,
+        vec![
+            user.clone(),
+            repo.join("arq.toml"),
+            package.join("arq.toml")
+        ]
+    );
+
+    let (config, origins) = Config::from_files_with_profile(&files, None).unwrap();
+    assert_eq!(config.llm.provider, "anthropic");
+    assert_eq!(config.llm.max_tokens, 1024);
+    assert_eq!(config.context.max_file_size, 2048);
+    assert_eq!(
+        origins.get("llm.provider"),
+        Some(&ConfigOrigin::File(repo.join("arq.toml")))
+    );
+    assert_eq!(
+        origins.get("llm.max_tokens"),
+        Some(&ConfigOrigin::File(user))
+    );
+    assert_eq!(origins.get("knowledge.search_limit"), None);
+    let rendered = config.render(Some(&origins));
+    assert!(rendered.contains(&format!(
+        "provider = \"anthropic\"  # {}",
+        repo.join("arq.toml").display()
+    )));
+    assert!(
+        rendered.contains("search_limit = 20  # default"),
+        "{}",
+        rendered
+    );
+
+    // The package can select a profile the repository root defines
+    let (config, origins) = Config::from_files_with_profile(&files, Some("local")).unwrap();
+    assert_eq!(config.llm.provider, "ollama");
+    assert_eq!(
+        origins.get("llm.provider").map(ToString::to_string),
+        Some(format!(
+            "{} (profile local)",
+            repo.join("arq.toml").display()
+        ))
+    );
+}
+
+#[test]
+fn test_root_config_stops_inheritance() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let package = temp_dir.path().join("api");
+    std::fs::create_dir(&package).unwrap();
+    std::fs::write(temp_dir.path().join("arq.toml"), "[llm]\nmax_tokens = 1\n").unwrap();
+    std::fs::write(package.join("arq.toml"), "root = true\n").unwrap();
+    let user = temp_dir.path().join("config.toml");
+    std::fs::write(&user, "[llm]\nmax_tokens = 2\n").unwrap();
+
+    let files = Config::discover_files(&package, Some(&user)).unwrap();
+    assert_eq!(files, vec![package.join("arq.toml")]);
+    let (config, origins) = Config::from_files_with_profile(&files, None).unwrap();
+    assert_eq!(config.llm.max_tokens, LLMConfig::default().max_tokens);
+    assert!(origins.is_empty());
+}
+
+#[test]
+fn test_phase_personas() {
+    use arq_core::config::{DEFAULT_CONFORMANCE_SYSTEM_PROMPT, DEFAULT_RESEARCH_SYSTEM_PROMPT};
+    use arq_core::{prompts, Phase};
+
+    let mut config: Config = toml::from_str(
+        r#"
+[prompts.planning]
+system_prompt = "You are a pragmatic architect."
+temperature = 0.7
+
+[prompts.research]
+temperature = 0.2
+"#,
+    )
+    .unwrap();
+
+    let research = prompts::for_phase(Phase::Research, &config);
+    assert_eq!(research.system_prompt, DEFAULT_RESEARCH_SYSTEM_PROMPT);
+    assert_eq!(research.temperature, Some(0.2));
+    assert_eq!(research.llm_config(&config.llm).temperature, Some(0.2));
+
+    let planning = prompts::for_phase(Phase::Planning, &config);
+    assert_eq!(planning.system_prompt, "You are a pragmatic architect.");
+    assert_eq!(planning.temperature, Some(0.7));
+
+    let agent = prompts::for_phase(Phase::Agent, &config);
+    assert_eq!(agent.system_prompt, DEFAULT_CONFORMANCE_SYSTEM_PROMPT);
+    assert_eq!(agent.temperature, None);
+
+    // The older `research.system_prompt` still applies
+    config.research.system_prompt = Some("Answer briefly.".to_string());
+    let research = prompts::for_phase(Phase::Research, &config);
+    assert_eq!(research.system_prompt, "Answer briefly.");
+}
+
+#[test]
+fn test_output_language() {
+    use arq_core::prompts::localize;
+
+    let config: Config = toml::from_str(
+        r#"
+[research]
+output_language = "de"
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.research.output_language.as_deref(), Some("de"));
+
+    let prompt = localize(
+        "You are a researcher.",
+        config.research.output_language.as_deref(),
+    );
+    assert!(prompt.starts_with("You are a researcher."));
+    assert!(prompt.contains("in German"));
+    assert!(prompt.contains("code identifiers"));
+
+    // Names are used as given; unset keeps the prompt
+    assert!(localize("Plan.", Some("Esperanto")).contains("in Esperanto"));
+    assert_eq!(localize("Plan.", None), "Plan.");
+    assert_eq!(localize("Plan.", Some(" ")), "Plan.");
+}
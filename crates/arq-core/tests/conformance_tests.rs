@@ -0,0 +1,188 @@
+use arq_core::agent::{
+    check_scope, parse_unified_diff, untracked_changes, ChangeKind, ConformanceStatus,
+    DeviationKind,
+};
+use arq_core::planning::{FileModification, FileSpec, FunctionSignature};
+use arq_core::Plan;
+
+const DIFF: &str = r#"diff --git a/src/auth.rs b/src/auth.rs
+new file mode 100644
+index 0000000..1111111
+--- /dev/null
++++ b/src/auth.rs
+@@ -0,0 +1,3 @@
++pub fn login(user: &str) -> bool {
++    !user.is_empty()
++}
+diff --git a/src/lib.rs b/src/lib.rs
+index 2222222..3333333 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,3 @@ fn setup()
+ mod config;
++mod auth;
+diff --git a/src/old.rs b/src/old.rs
+deleted file mode 100644
+index 4444444..0000000
+--- a/src/old.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn legacy() {}
+"#;
+
+fn plan() -> Plan {
+    let mut plan = Plan::new("Add auth", "New auth module");
+    plan.files_to_create.push(FileSpec {
+        path: "src/auth.rs".to_string(),
+        description: "Authentication".to_string(),
+        exports: vec![FunctionSignature {
+            name: "login".to_string(),
+            signature: "pub fn login(user: &str) -> bool".to_string(),
+            behavior: vec!["Rejects empty users".to_string()],
+        }],
+    });
+    plan.files_to_modify.push(FileModification {
+        path: "src/lib.rs".to_string(),
+        line: None,
+        description: "Register module".to_string(),
+        additions: vec!["mod auth;".to_string()],
+        removals: Vec::new(),
+    });
+    plan
+}
+
+#[test]
+fn test_parse_unified_diff() {
+    let changes = parse_unified_diff(DIFF);
+
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0].path, "src/auth.rs");
+    assert_eq!(changes[0].kind, ChangeKind::Added);
+    assert_eq!(changes[1].path, "src/lib.rs");
+    assert_eq!(changes[1].kind, ChangeKind::Modified);
+    assert_eq!(changes[2].path, "src/old.rs");
+    assert_eq!(changes[2].kind, ChangeKind::Deleted);
+}
+
+#[test]
+fn test_changed_symbols() {
+    let changes = parse_unified_diff(DIFF);
+
+    assert_eq!(changes[0].changed_symbols(), vec!["login"]);
+    assert_eq!(changes[1].changed_symbols(), vec!["setup"]);
+    assert_eq!(changes[2].changed_symbols(), vec!["legacy"]);
+}
+
+#[test]
+fn test_out_of_scope_change_fails() {
+    let changes = parse_unified_diff(DIFF);
+    let report = check_scope(&plan(), &changes);
+
+    assert_eq!(report.status, ConformanceStatus::Failed);
+    assert!(!report.is_committable());
+    assert_eq!(report.deviations.len(), 1);
+    assert_eq!(report.deviations[0].path, "src/old.rs");
+    assert_eq!(report.deviations[0].kind, DeviationKind::OutOfScope);
+}
+
+#[test]
+fn test_in_scope_changes_pass() {
+    let changes: Vec<_> = parse_unified_diff(DIFF)
+        .into_iter()
+        .filter(|c| c.path != "src/old.rs")
+        .collect();
+    let report = check_scope(&plan(), &changes);
+
+    assert_eq!(report.status, ConformanceStatus::Passed);
+    assert!(report.deviations.is_empty());
+}
+
+#[test]
+fn test_missing_step_and_export() {
+    let mut plan = plan();
+    plan.files_to_create[0].exports[0].name = "logout".to_string();
+
+    let changes: Vec<_> = parse_unified_diff(DIFF)
+        .into_iter()
+        .filter(|c| c.path == "src/auth.rs")
+        .collect();
+    let report = check_scope(&plan, &changes);
+
+    assert_eq!(report.status, ConformanceStatus::Failed);
+    assert!(report
+        .deviations
+        .iter()
+        .any(|d| d.kind == DeviationKind::MissingExport));
+    assert!(report
+        .deviations
+        .iter()
+        .any(|d| d.kind == DeviationKind::MissingStep && d.path == "src/lib.rs"));
+}
+
+#[test]
+fn test_untracked_files_count_as_added() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+    std::fs::write(
+        temp_dir.path().join("src/auth.rs"),
+        "pub fn login(user: &str) -> bool {\n    !user.is_empty()\n}\n",
+    )
+    .unwrap();
+    std::fs::write(temp_dir.path().join("src/extra.rs"), "fn helper() {}\n").unwrap();
+
+    // The plan's new file, not yet staged, is in scope
+    let mut changes: Vec<_> = parse_unified_diff(DIFF)
+        .into_iter()
+        .filter(|c| c.path == "src/lib.rs")
+        .collect();
+    changes.extend(untracked_changes(
+        temp_dir.path(),
+        &["src/auth.rs".to_string()],
+    ));
+    assert_eq!(changes[1].kind, ChangeKind::Added);
+    assert_eq!(changes[1].changed_symbols(), vec!["login"]);
+    let report = check_scope(&plan(), &changes);
+    assert_eq!(report.status, ConformanceStatus::Passed);
+    assert!(report.deviations.is_empty());
+
+    // A new file outside the plan fails the check
+    changes.extend(untracked_changes(
+        temp_dir.path(),
+        &["src/extra.rs".to_string()],
+    ));
+    let report = check_scope(&plan(), &changes);
+    assert_eq!(report.status, ConformanceStatus::Failed);
+    assert_eq!(report.deviations.len(), 1);
+    assert_eq!(report.deviations[0].path, "src/extra.rs");
+    assert_eq!(report.deviations[0].kind, DeviationKind::OutOfScope);
+}
+
+#[test]
+fn test_arq_files_are_ignored() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join(".arq/data")).unwrap();
+    std::fs::write(temp_dir.path().join(".arq/plan.yaml"), "version: 1\n").unwrap();
+    std::fs::write(temp_dir.path().join(".arq/data/task.json"), "{}\n").unwrap();
+
+    let untracked = untracked_changes(
+        temp_dir.path(),
+        &[
+            ".arq/plan.yaml".to_string(),
+            ".arq/data/task.json".to_string(),
+        ],
+    );
+    assert!(untracked.is_empty());
+
+    // Tracked ones, e.g. a mirrored plan in a subdirectory, are ignored too
+    let diff = DIFF.replace("src/old.rs", "app/.arq/plan.yaml");
+    let changes: Vec<_> = parse_unified_diff(&diff)
+        .into_iter()
+        .filter(|c| c.path != "src/old.rs")
+        .collect();
+    assert!(changes.iter().any(|c| c.path == "app/.arq/plan.yaml"));
+    let report = check_scope(&plan(), &changes);
+    assert!(report
+        .deviations
+        .iter()
+        .all(|d| d.path != "app/.arq/plan.yaml"));
+}
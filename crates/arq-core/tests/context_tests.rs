@@ -1,4 +1,4 @@
-use arq_core::ContextBuilder;
+use arq_core::{ContextBuilder, ContextConfig, FileOrder, TreeSource};
 use std::fs::{self, File};
 use std::io::Write;
 use tempfile::TempDir;
@@ -66,3 +66,155 @@ fn test_custom_config() {
     let context = builder.gather().unwrap();
     assert!(context.files.iter().any(|f| f.path.ends_with(".custom")));
 }
+
+#[test]
+fn test_total_size_cutoff_is_deterministic() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    // 200 files of 10 bytes each, plus one over the per-file limit
+    for i in 0..200 {
+        fs::write(root.join(format!("f{:03}.rs", i)), "fn a() {}\n").unwrap();
+    }
+    fs::write(root.join("big.rs"), "x".repeat(500)).unwrap();
+
+    let builder = ContextBuilder::new(root)
+        .max_file_size(100)
+        .max_total_size(255);
+    let first = builder.gather().unwrap();
+    let second = builder.gather().unwrap();
+
+    // The oversized file is skipped, and reading stops before exceeding the total
+    assert_eq!(first.files.len(), 25);
+    assert!(!first.files.iter().any(|f| f.path == "big.rs"));
+
+    let paths = |c: &arq_core::Context| c.files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+    assert_eq!(paths(&first), paths(&second));
+}
+
+#[test]
+fn test_priority_order_drops_least_important_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/a_large.rs"), "x".repeat(60)).unwrap();
+    fs::write(root.join("src/b_small.rs"), "x".repeat(10)).unwrap();
+    fs::write(root.join("src/c_core.rs"), "x".repeat(50)).unwrap();
+    fs::write(root.join("src/lib.rs"), "x".repeat(40)).unwrap();
+
+    let paths = |builder: ContextBuilder| {
+        builder
+            .max_total_size(100)
+            .gather()
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .collect::<Vec<_>>()
+    };
+
+    // Entry point first, then smallest
+    assert_eq!(
+        paths(ContextBuilder::new(root)),
+        vec!["src/lib.rs", "src/b_small.rs", "src/c_core.rs"]
+    );
+
+    // Central files come right after entry points
+    let priorities = [("src/c_core.rs".to_string(), 3.0)].into_iter().collect();
+    assert_eq!(
+        paths(ContextBuilder::new(root).with_priorities(priorities)),
+        vec!["src/lib.rs", "src/c_core.rs", "src/b_small.rs"]
+    );
+
+    let config = ContextConfig {
+        file_order: FileOrder::Walk,
+        ..ContextConfig::default()
+    };
+    let walked = paths(ContextBuilder::with_config(root, config));
+    assert!(walked.len() < 4);
+}
+
+#[test]
+fn test_codebase_hash_tracks_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("lib.rs"), "fn a() {}").unwrap();
+
+    let builder = ContextBuilder::new(root);
+    let original = builder.codebase_hash();
+    assert_eq!(builder.codebase_hash(), original);
+
+    // Edits and new files change the hash
+    fs::write(root.join("lib.rs"), "fn a() { b() }").unwrap();
+    let edited = builder.codebase_hash();
+    assert_ne!(edited, original);
+
+    fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+    assert_ne!(builder.codebase_hash(), edited);
+}
+
+#[test]
+fn test_tree_limits() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    fs::create_dir_all(root.join("a/b/c")).unwrap();
+    File::create(root.join("a/b/c/deep.rs")).unwrap();
+    for i in 0..5 {
+        File::create(root.join(format!("file{}.rs", i))).unwrap();
+    }
+
+    let config = ContextConfig {
+        tree_max_depth: 2,
+        tree_max_entries: 3,
+        ..ContextConfig::default()
+    };
+    let tree = ContextBuilder::with_config(root, config)
+        .gather()
+        .unwrap()
+        .structure;
+
+    assert!(tree.contains("b/ …"));
+    assert!(!tree.contains("deep.rs"));
+    assert!(tree.contains("└── … 3 more entries"));
+}
+
+#[test]
+fn test_tree_from_gathered_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    fs::create_dir(root.join("src")).unwrap();
+    File::create(root.join("src/lib.rs")).unwrap();
+    File::create(root.join("notes.bin")).unwrap();
+
+    let config = ContextConfig {
+        tree_source: TreeSource::Gathered,
+        ..ContextConfig::default()
+    };
+    let tree = ContextBuilder::with_config(root, config)
+        .gather()
+        .unwrap()
+        .structure;
+
+    assert_eq!(tree, "└── src/\n    └── lib.rs\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tree_shows_non_utf8_names() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    let name = OsStr::from_bytes(b"caf\xe9\nname.rs");
+    if File::create(root.join(name)).is_err() {
+        // Filesystem rejects non-UTF-8 names
+        return;
+    }
+
+    let tree = ContextBuilder::new(root).gather().unwrap().structure;
+    assert_eq!(tree, "└── caf\\xe9\\nname.rs\n");
+}
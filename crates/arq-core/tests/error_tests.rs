@@ -0,0 +1,90 @@
+use arq_core::agent::AgentError;
+use arq_core::error::exit_code;
+use arq_core::{ArqError, ConfigError, KnowledgeError, LLMError, ManagerError, ResearchError};
+use arq_core::{Phase, StorageError};
+
+#[test]
+fn test_exit_codes_are_distinct_per_category() {
+    let codes = [
+        exit_code::GENERAL,
+        exit_code::USAGE,
+        exit_code::CONFIG,
+        exit_code::LLM,
+        exit_code::KNOWLEDGE,
+        exit_code::STORAGE,
+        exit_code::IO,
+    ];
+
+    for (i, a) in codes.iter().enumerate() {
+        for b in &codes[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+}
+
+#[test]
+fn test_exit_code_mapping() {
+    let config: ArqError = ConfigError::Invalid("bad".to_string()).into();
+    assert_eq!(config.exit_code(), exit_code::CONFIG);
+
+    let llm: ArqError = LLMError::MissingApiKey.into();
+    assert_eq!(llm.exit_code(), exit_code::LLM);
+
+    let kg: ArqError = KnowledgeError::NotInitialized.into();
+    assert_eq!(kg.exit_code(), exit_code::KNOWLEDGE);
+
+    let storage: ArqError = StorageError::TaskNotFound("abc".to_string()).into();
+    assert_eq!(storage.exit_code(), exit_code::STORAGE);
+
+    let usage = ArqError::usage("No current task.");
+    assert_eq!(usage.exit_code(), exit_code::USAGE);
+
+    let message: ArqError = "Changes do not conform.".into();
+    assert_eq!(message.exit_code(), exit_code::GENERAL);
+
+    assert_eq!(ArqError::other("boom").exit_code(), exit_code::GENERAL);
+}
+
+#[test]
+fn test_nested_errors_map_to_inner_category() {
+    let research: ArqError = ResearchError::LLM(LLMError::RateLimited).into();
+    assert_eq!(research.exit_code(), exit_code::LLM);
+
+    let research: ArqError = ResearchError::Knowledge(KnowledgeError::NotInitialized).into();
+    assert_eq!(research.exit_code(), exit_code::KNOWLEDGE);
+
+    let manager: ArqError =
+        ManagerError::Storage(StorageError::TaskNotFound("abc".to_string())).into();
+    assert_eq!(manager.exit_code(), exit_code::STORAGE);
+
+    let manager: ArqError = ManagerError::CannotAdvance {
+        phase: Phase::Research,
+        reason: "no research".to_string(),
+    }
+    .into();
+    assert_eq!(manager.exit_code(), exit_code::USAGE);
+}
+
+#[test]
+fn test_hints() {
+    let missing_key: ArqError = LLMError::MissingApiKey.into();
+    assert!(missing_key.hint().unwrap().contains("OPENAI_API_KEY"));
+
+    let not_init: ArqError = KnowledgeError::NotInitialized.into();
+    assert!(not_init.hint().unwrap().contains("arq init"));
+
+    let not_found: ArqError = StorageError::TaskNotFound("abc".to_string()).into();
+    assert!(not_found.hint().unwrap().contains("arq list"));
+
+    let private: ArqError = AgentError::PrivatePath(".env".to_string()).into();
+    assert_eq!(private.exit_code(), exit_code::USAGE);
+    assert!(private.hint().unwrap().contains("context.private_paths"));
+
+    assert!(ArqError::other("boom").hint().is_none());
+}
+
+#[test]
+fn test_display_is_transparent() {
+    let err: ArqError = KnowledgeError::NotInitialized.into();
+    assert_eq!(err.to_string(), KnowledgeError::NotInitialized.to_string());
+}
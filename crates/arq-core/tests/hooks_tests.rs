@@ -0,0 +1,78 @@
+use arq_core::{Config, HookError, HookEvent, Hooks, HooksConfig, Task};
+
+#[test]
+fn test_hooks_from_toml() {
+    let config: Config = toml::from_str(
+        "[hooks]\npre_research = \"./check.sh\"\npost_plan = \"  \"\ntimeout_secs = 5\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.hooks.command(HookEvent::PreResearch),
+        Some("./check.sh")
+    );
+    // Blank commands count as unset
+    assert_eq!(config.hooks.command(HookEvent::PostPlan), None);
+    assert_eq!(config.hooks.command(HookEvent::PostAgentStep), None);
+    assert_eq!(config.hooks.timeout_secs, 5);
+}
+
+#[test]
+fn test_blocking_events() {
+    assert!(HookEvent::PreResearch.is_blocking());
+    assert!(HookEvent::PrePlan.is_blocking());
+    assert!(!HookEvent::PostResearch.is_blocking());
+    assert!(!HookEvent::PostAgentStep.is_blocking());
+}
+
+#[tokio::test]
+async fn test_unconfigured_hook_is_skipped() {
+    let task = Task::new("Add login");
+    let ran = Hooks::default()
+        .run(HookEvent::PreResearch, &task, &[])
+        .await
+        .unwrap();
+    assert!(!ran);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_hook_receives_task_env() {
+    let task = Task::with_name("Add login", "add-login");
+    let hooks = Hooks::new(HooksConfig {
+        post_research: Some(
+            "test \"$ARQ_HOOK\" = post_research && test \"$ARQ_TASK_NAME\" = add-login \
+             && test \"$ARQ_TASK_PHASE\" = Research && test \"$ARQ_ARTIFACT_PATH\" = doc.md"
+                .to_string(),
+        ),
+        ..HooksConfig::default()
+    });
+
+    let ran = hooks
+        .run(
+            HookEvent::PostResearch,
+            &task,
+            &[("ARQ_ARTIFACT_PATH", "doc.md".to_string())],
+        )
+        .await
+        .unwrap();
+    assert!(ran);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_failing_hook_reports_status_and_stderr() {
+    let task = Task::new("Add login");
+    let hooks = Hooks::new(HooksConfig {
+        pre_research: Some("echo 'tree is dirty' >&2; exit 3".to_string()),
+        ..HooksConfig::default()
+    });
+
+    match hooks.run(HookEvent::PreResearch, &task, &[]).await {
+        Err(HookError::Failed { status, stderr, .. }) => {
+            assert_eq!(status, 3);
+            assert_eq!(stderr, "tree is dirty");
+        }
+        other => panic!("expected hook failure, got {:?}", other),
+    }
+}
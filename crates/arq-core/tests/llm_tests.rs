@@ -1,10 +1,16 @@
 use arq_core::config::DEFAULT_OLLAMA_MODEL;
-use arq_core::llm::Provider;
-use arq_core::{ClaudeClient, LLMConfig, LLMError, OpenAIClient};
+use arq_core::llm::headers::{merge_headers, openai_headers, openrouter_headers};
+use arq_core::llm::{models_endpoint, parse_model_list, Provider};
+use arq_core::{ClaudeClient, LLMConfig, LLMError, OpenAIClient, TokenUsage};
 
 // Claude client tests
 mod claude {
     use super::*;
+    use arq_core::llm::{CancellationToken, LLM};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
 
     #[test]
     fn test_client_creation() {
@@ -28,6 +34,107 @@ mod claude {
         let result = ClaudeClient::from_env();
         assert!(matches!(result, Err(LLMError::MissingApiKey)));
     }
+
+    fn delta(text: &str) -> String {
+        format!(
+            "event: content_block_delta\ndata: {}\n\n",
+            serde_json::json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": text}})
+        )
+    }
+
+    const MESSAGE_STOP: &str = "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+
+    /// Answers each connection with the next SSE body, then closes it,
+    /// recording the request bodies.
+    async fn serve_streams(bodies: Vec<String>) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/messages", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            for body in bodies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Read the headers, then the body by its content length
+                let body_start = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+                let length: usize = headers
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .and_then(|v| v.trim().parse().ok())
+                    .unwrap_or(0);
+                while request.len() < body_start + length {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::from_slice(&request[body_start..]).unwrap());
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn test_stream_resumes_after_drop() {
+        // The first stream drops before message_stop
+        let (url, requests) = serve_streams(vec![
+            format!("{}{}", delta("Hello wor"), delta("ld. ")),
+            format!("{}{}", delta(" More text."), MESSAGE_STOP),
+        ])
+        .await;
+        let client = ClaudeClient::new("test-key").with_api_url(url);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        client
+            .stream_complete("system", "Explain", tx, CancellationToken::new())
+            .await
+            .unwrap();
+        let mut text = String::new();
+        while let Some(chunk) = rx.recv().await {
+            if chunk.is_final {
+                break;
+            }
+            text.push_str(&chunk.text);
+        }
+        assert_eq!(text, "Hello world. More text.");
+
+        // The resumed request prefills the partial answer, minus trailing whitespace
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let messages = &requests[1]["messages"];
+        assert_eq!(messages[0]["content"], "Explain");
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["content"], "Hello world.");
+    }
+
+    #[tokio::test]
+    async fn test_stream_gives_up_without_text() {
+        let (url, _) = serve_streams(vec![String::new()]).await;
+        let client = ClaudeClient::new("test-key").with_api_url(url);
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let result = client
+            .stream_complete("system", "Explain", tx, CancellationToken::new())
+            .await;
+        assert!(matches!(result, Err(LLMError::Network(_))));
+    }
 }
 
 // OpenAI client tests
@@ -100,10 +207,512 @@ mod provider {
             api_key: None,
             max_tokens: 4096,
             api_version: None,
-            available_models: Vec::new(),
+            ..Default::default()
         };
 
         let provider = Provider::from_config(&config);
         assert!(matches!(provider, Provider::Ollama { model, .. } if model == "codellama"));
     }
 }
+
+// Extra header tests
+mod headers {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_well_known_header_helpers() {
+        assert_eq!(
+            openai_headers(Some("org-1"), None),
+            vec![("OpenAI-Organization".to_string(), "org-1".to_string())]
+        );
+        assert_eq!(
+            openrouter_headers(Some("https://example.com"), Some("arq")).len(),
+            2
+        );
+        assert!(openai_headers(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_merge_headers() {
+        let well_known = openai_headers(Some("org-1"), Some("proj-1"));
+        let mut extra = BTreeMap::new();
+        extra.insert("openai-project".to_string(), String::new());
+        extra.insert("X-Team".to_string(), "platform".to_string());
+
+        let merged = merge_headers(well_known, &extra);
+        assert_eq!(
+            merged,
+            vec![
+                ("OpenAI-Organization".to_string(), "org-1".to_string()),
+                ("X-Team".to_string(), "platform".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extra_headers_from_toml() {
+        let config: LLMConfig =
+            toml::from_str("provider = \"ollama\"\n[extra_headers]\nX-Title = \"my-app\"\n")
+                .unwrap();
+        assert_eq!(
+            config.request_headers(),
+            vec![("X-Title".to_string(), "my-app".to_string())]
+        );
+    }
+}
+
+// Token usage tests
+mod usage {
+    use super::*;
+
+    #[test]
+    fn test_estimate_and_accumulate() {
+        let mut usage = TokenUsage::estimate("system", "12345678", "abcd");
+        assert_eq!(usage.input_tokens, 2 + 2);
+        assert_eq!(usage.output_tokens, 1);
+
+        usage += TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+        };
+        assert_eq!(usage.total(), 20);
+    }
+
+    #[test]
+    fn test_estimated_cost() {
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 1_000_000,
+        };
+
+        assert_eq!(usage.estimated_cost("ollama", "codellama"), Some(0.0));
+        assert_eq!(
+            usage.estimated_cost("anthropic", "claude-sonnet-4-20250514"),
+            Some(18.0)
+        );
+        assert_eq!(
+            usage.estimated_cost("openrouter", "anthropic/claude-sonnet-4"),
+            Some(18.0)
+        );
+        assert_eq!(usage.estimated_cost("openai", "unknown-model"), None);
+    }
+}
+
+// Model listing tests
+mod models {
+    use super::*;
+
+    fn config(provider: &str, base_url: Option<&str>) -> LLMConfig {
+        LLMConfig {
+            provider: provider.to_string(),
+            base_url: base_url.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_models_endpoint() {
+        assert_eq!(
+            models_endpoint(&config("openai", None)),
+            "https://api.openai.com/v1/models"
+        );
+        assert_eq!(
+            models_endpoint(&config("openrouter", None)),
+            "https://openrouter.ai/api/v1/models"
+        );
+        assert_eq!(
+            models_endpoint(&config("anthropic", None)),
+            "https://api.anthropic.com/v1/models"
+        );
+        assert_eq!(
+            models_endpoint(&config("ollama", Some("http://gpu-box:11434/v1/"))),
+            "http://gpu-box:11434/api/tags"
+        );
+    }
+
+    #[test]
+    fn test_parse_openai_model_list() {
+        let body = r#"{"object":"list","data":[{"id":"gpt-4o","object":"model"},{"id":"gpt-4o-mini"},{"id":"gpt-4o"}]}"#;
+        let models = parse_model_list("openai", body).unwrap();
+        assert_eq!(models, vec!["gpt-4o", "gpt-4o-mini"]);
+    }
+
+    #[test]
+    fn test_parse_ollama_tags() {
+        let body = r#"{"models":[{"name":"qwen2.5-coder:7b","size":1},{"name":"llama3:latest"}]}"#;
+        let models = parse_model_list("ollama", body).unwrap();
+        assert_eq!(models, vec!["llama3:latest", "qwen2.5-coder:7b"]);
+    }
+
+    #[test]
+    fn test_parse_invalid_model_list() {
+        let result = parse_model_list("openai", "<html>not json</html>");
+        assert!(matches!(result, Err(LLMError::ParseError(_))));
+
+        // Long bodies are cut short on a character boundary
+        let result = parse_model_list("openai", &"ü".repeat(600));
+        assert!(matches!(result, Err(LLMError::ParseError(m)) if m.ends_with(&"ü".repeat(500))));
+    }
+}
+
+mod middleware {
+    use super::*;
+    use arq_core::llm::{CancellationToken, LLMStack, RetryPolicy, StreamChunk, LLM};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// Fails the first `failures` calls with `error`, then answers "ok".
+    struct FlakyLLM {
+        failures: u32,
+        error: fn() -> LLMError,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl FlakyLLM {
+        fn new(failures: u32, error: fn() -> LLMError) -> (Self, Arc<AtomicU32>) {
+            let calls = Arc::new(AtomicU32::new(0));
+            let llm = Self {
+                failures,
+                error,
+                calls: calls.clone(),
+            };
+            (llm, calls)
+        }
+    }
+
+    #[async_trait]
+    impl LLM for FlakyLLM {
+        async fn complete(&self, _prompt: &str) -> Result<String, LLMError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                Err((self.error)())
+            } else {
+                Ok("ok".to_string())
+            }
+        }
+
+        async fn complete_with_system(
+            &self,
+            _system: &str,
+            prompt: &str,
+        ) -> Result<String, LLMError> {
+            self.complete(prompt).await
+        }
+
+        async fn stream_complete(
+            &self,
+            _system: &str,
+            prompt: &str,
+            tx: mpsc::UnboundedSender<StreamChunk>,
+            _cancel: CancellationToken,
+        ) -> Result<(), LLMError> {
+            let response = self.complete(prompt).await?;
+            let _ = tx.send(StreamChunk::text(response));
+            let _ = tx.send(StreamChunk::done());
+            Ok(())
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+    }
+
+    fn fast_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+        assert_eq!(policy.backoff(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(LLMError::RateLimited.is_transient());
+        assert!(LLMError::ApiError {
+            status: 503,
+            message: String::new()
+        }
+        .is_transient());
+        assert!(!LLMError::ApiError {
+            status: 400,
+            message: String::new()
+        }
+        .is_transient());
+        assert!(!LLMError::Cancelled.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_retry_recovers_from_transient_errors() {
+        let (base, calls) = FlakyLLM::new(2, || LLMError::RateLimited);
+        let llm = LLMStack::new(base).with_retry(fast_retries(3)).build();
+
+        assert_eq!(llm.complete("hi").await.unwrap(), "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let (base, calls) = FlakyLLM::new(5, || LLMError::RateLimited);
+        let llm = LLMStack::new(base).with_retry(fast_retries(2)).build();
+        assert!(matches!(
+            llm.complete("hi").await,
+            Err(LLMError::RateLimited)
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let (base, calls) = FlakyLLM::new(1, || LLMError::MissingApiKey);
+        let llm = LLMStack::new(base).with_retry(fast_retries(3)).build();
+        assert!(llm.complete("hi").await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_stream_before_first_chunk() {
+        let (base, calls) = FlakyLLM::new(1, || LLMError::Network("reset".to_string()));
+        let llm = LLMStack::new(base).with_retry(fast_retries(1)).build();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        llm.stream_complete("", "hi", tx, CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap().text, "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_logging_sees_one_call_per_request() {
+        let (base, _) = FlakyLLM::new(1, || LLMError::RateLimited);
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let sink = logged.clone();
+        let llm = LLMStack::new(base)
+            .with_rate_limit(0)
+            .with_retry(fast_retries(1))
+            .with_logging(move |call| sink.lock().unwrap().push(call.clone()))
+            .build();
+
+        llm.complete_with_system("sys", "hello").await.unwrap();
+        let logged = logged.lock().unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].method, "complete_with_system");
+        assert_eq!(logged[0].prompt_chars, 8);
+        assert_eq!(logged[0].response_chars, 2);
+        assert!(logged[0].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stack_is_usable_as_llm() {
+        async fn ask(llm: impl LLM) -> String {
+            llm.complete("hi").await.unwrap()
+        }
+
+        let (base, _) = FlakyLLM::new(0, || LLMError::RateLimited);
+        let llm: Arc<dyn LLM> = LLMStack::new(base).build();
+        assert!(llm.supports_streaming());
+        assert_eq!(ask(llm.clone()).await, "ok");
+        assert_eq!(ask(llm).await, "ok");
+    }
+}
+
+mod jobs {
+    use super::*;
+    use arq_core::llm::{JobProgress, JobQueue, RetryPolicy};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_results_keep_input_order_within_concurrency() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let progress = Arc::new(Mutex::new(Vec::<JobProgress>::new()));
+        let reported = Arc::clone(&progress);
+        let queue =
+            JobQueue::new(2).with_progress(move |p| reported.lock().unwrap().push(p.clone()));
+
+        let results = queue
+            .run((0..6u64).collect(), |n| {
+                let running = Arc::clone(&running);
+                let peak = Arc::clone(&peak);
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    // Later items finish first
+                    tokio::time::sleep(Duration::from_millis(12 - 2 * n)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(n * 10)
+                }
+            })
+            .await;
+
+        let values: Vec<u64> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(values, vec![0, 10, 20, 30, 40, 50]);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        let progress = progress.lock().unwrap();
+        assert_eq!(progress.len(), 6);
+        assert_eq!(progress.last().unwrap().done, 6);
+        assert!(progress.iter().all(|p| p.total == 6 && p.error.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let queue = JobQueue::new(4).with_retry(fast_retry());
+
+        let results = queue
+            .run(vec!["flaky", "broken"], |item| {
+                let attempts = Arc::clone(&attempts);
+                async move {
+                    match item {
+                        "flaky" if attempts.fetch_add(1, Ordering::SeqCst) < 2 => {
+                            Err(LLMError::RateLimited)
+                        }
+                        "flaky" => Ok("done"),
+                        _ => Err(LLMError::ParseError("bad".to_string())),
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(results[0].as_ref().unwrap(), &"done");
+        assert!(matches!(results[1], Err(LLMError::ParseError(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_batches() {
+        let queue = JobQueue::new(2);
+        let results = queue
+            .run_batched((1..=5).collect(), 2, |batch: Vec<u32>| async move {
+                Ok(batch.iter().sum::<u32>())
+            })
+            .await;
+        let sums: Vec<u32> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(sums, vec![3, 7, 5]);
+    }
+}
+
+mod cache {
+    use super::*;
+    use arq_core::llm::{response_cache_key, LLMStack, ResponseCache, LLM};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Answers with the number of calls made so far.
+    struct CountingLLM(Arc<AtomicU32>);
+
+    #[async_trait]
+    impl LLM for CountingLLM {
+        async fn complete(&self, _prompt: &str) -> Result<String, LLMError> {
+            Ok(format!(
+                "call {}",
+                self.0.fetch_add(1, Ordering::SeqCst) + 1
+            ))
+        }
+
+        async fn complete_with_system(
+            &self,
+            _system: &str,
+            prompt: &str,
+        ) -> Result<String, LLMError> {
+            self.complete(prompt).await
+        }
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let key = response_cache_key("openai:gpt-4o", Some("sys"), "hi");
+        assert_eq!(key, response_cache_key("openai:gpt-4o", Some("sys"), "hi"));
+        assert_ne!(
+            key,
+            response_cache_key("openai:gpt-4o-mini", Some("sys"), "hi")
+        );
+        assert_ne!(key, response_cache_key("openai:gpt-4o", Some("sys"), "hi!"));
+        assert_ne!(
+            response_cache_key("openai:gpt-4o", None, "hi"),
+            response_cache_key("openai:gpt-4o", Some(""), "hi")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repeated_calls_hit_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let calls = Arc::new(AtomicU32::new(0));
+        let llm = LLMStack::new(CountingLLM(calls.clone()))
+            .with_cache(ResponseCache::new(dir.path()), "openai:gpt-4o")
+            .build();
+
+        assert_eq!(
+            llm.complete_with_system("sys", "a").await.unwrap(),
+            "call 1"
+        );
+        assert_eq!(
+            llm.complete_with_system("sys", "a").await.unwrap(),
+            "call 1"
+        );
+        assert_eq!(
+            llm.complete_with_system("sys", "b").await.unwrap(),
+            "call 2"
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_evicted_calls_reach_the_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let calls = Arc::new(AtomicU32::new(0));
+        let llm = LLMStack::new(CountingLLM(calls.clone()))
+            .with_cache(ResponseCache::new(dir.path()), "openai:gpt-4o")
+            .build();
+
+        llm.complete_with_system("sys", "a").await.unwrap();
+        llm.evict(Some("sys"), "a");
+        assert_eq!(
+            llm.complete_with_system("sys", "a").await.unwrap(),
+            "call 2"
+        );
+        assert_eq!(
+            llm.complete_with_system("sys", "a").await.unwrap(),
+            "call 2"
+        );
+    }
+
+    #[test]
+    fn test_expired_entries_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::new(dir.path());
+        cache.store("abc", "response").unwrap();
+        assert_eq!(cache.load("abc").as_deref(), Some("response"));
+        assert_eq!(cache.load("other"), None);
+
+        let cache = cache.with_ttl(Duration::from_nanos(1));
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(cache.load("abc"), None);
+    }
+}
@@ -1,4 +1,8 @@
-use arq_core::{FileStorage, Phase, ResearchDoc, StorageConfig, TaskManager};
+use arq_core::research::{Finding, Source, SourceType};
+use arq_core::{
+    FileStorage, Phase, ResearchDoc, StorageConfig, TaskManager, TaskStats, TokenUsage, UsageRecord,
+};
+use std::time::Duration;
 use tempfile::TempDir;
 
 fn create_test_manager() -> (TaskManager<FileStorage>, TempDir) {
@@ -28,6 +32,23 @@ fn test_create_task() {
     assert_eq!(current.unwrap().id, task.id);
 }
 
+#[test]
+fn test_create_named_task_and_rename() {
+    let (mut manager, _temp) = create_test_manager();
+
+    let task = manager
+        .create_named_task("Add user authentication", "oauth-login")
+        .unwrap();
+    assert_eq!(task.name, "oauth-login");
+
+    let renamed = manager.rename_task(&task.id, "login-flow").unwrap();
+    assert_eq!(renamed.name, "login-flow");
+    assert_eq!(manager.get_task(&task.id).unwrap().name, "login-flow");
+    assert_eq!(manager.list_tasks().unwrap()[0].name, "login-flow");
+
+    assert!(manager.rename_task(&task.id, "").is_err());
+}
+
 #[test]
 fn test_list_tasks() {
     let (mut manager, _temp) = create_test_manager();
@@ -50,6 +71,61 @@ fn test_delete_task() {
     assert_eq!(tasks.len(), 0);
 }
 
+#[test]
+fn test_list_tasks_with_label() {
+    let (mut manager, _temp) = create_test_manager();
+
+    let api = manager.create_task("Add API endpoint").unwrap();
+    let ui = manager.create_task("Fix button").unwrap();
+    manager
+        .add_labels(&api.id, &["backend".to_string(), "urgent".to_string()])
+        .unwrap();
+    manager
+        .add_labels(&ui.id, &["frontend".to_string()])
+        .unwrap();
+
+    let found = manager.list_tasks_with_label("Backend").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, api.id);
+    assert_eq!(found[0].labels, vec!["backend", "urgent"]);
+
+    let task = manager
+        .remove_labels(&api.id, &["backend".to_string()])
+        .unwrap();
+    assert_eq!(task.labels, vec!["urgent"]);
+    assert!(manager.list_tasks_with_label("backend").unwrap().is_empty());
+}
+
+#[test]
+fn test_find_tasks_by_id_prefix_or_name() {
+    let (mut manager, _temp) = create_test_manager();
+
+    let login = manager
+        .create_named_task("Add login", "oauth-login")
+        .unwrap();
+    let logout = manager
+        .create_named_task("Add logout", "oauth-logout")
+        .unwrap();
+    manager.create_named_task("Fix cache", "cache-fix").unwrap();
+
+    let found = manager.find_tasks("OAUTH").unwrap();
+    assert_eq!(found.len(), 2);
+
+    let found = manager.find_tasks("login").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, login.id);
+
+    let found = manager.find_tasks(&logout.id[..8]).unwrap();
+    assert_eq!(found[0].id, logout.id);
+
+    // A full ID matches only that task
+    let found = manager.find_tasks(&logout.id).unwrap();
+    assert_eq!(found.len(), 1);
+
+    assert!(manager.find_tasks("missing").unwrap().is_empty());
+    assert!(manager.find_tasks("  ").unwrap().is_empty());
+}
+
 #[test]
 fn test_set_research_doc() {
     let (mut manager, _temp) = create_test_manager();
@@ -80,3 +156,59 @@ fn test_advance_phase() {
     let updated = manager.get_current_task().unwrap().unwrap();
     assert_eq!(updated.phase, Phase::Planning);
 }
+
+#[test]
+fn test_usage_records_and_stats() {
+    let (mut manager, _temp) = create_test_manager();
+
+    let first = manager.create_task("First task").unwrap();
+    let second = manager.create_task("Second task").unwrap();
+
+    let mut doc = ResearchDoc::new("first");
+    doc.sources.push(Source {
+        source_type: SourceType::File,
+        location: "src/lib.rs".to_string(),
+    });
+    doc.codebase_analysis.push(Finding {
+        title: "Entry point".to_string(),
+        description: String::new(),
+        related_files: vec!["src/lib.rs".to_string(), "src/main.rs".to_string()],
+        ..Default::default()
+    });
+    manager.set_research_doc(&first.id, doc.clone()).unwrap();
+    manager.set_research_doc(&second.id, doc).unwrap();
+    manager.advance_phase(&second.id).unwrap();
+
+    let tokens = TokenUsage {
+        input_tokens: 1000,
+        output_tokens: 200,
+    };
+    for (id, secs) in [(&first.id, 2), (&second.id, 4)] {
+        let record = UsageRecord::new(
+            Phase::Research,
+            "ollama",
+            "llama3",
+            tokens,
+            Duration::from_secs(secs),
+        );
+        manager.record_usage(id, record).unwrap();
+    }
+
+    let tasks = manager.load_all_tasks().unwrap();
+    let stats = TaskStats::from_tasks(&tasks);
+
+    assert_eq!(stats.total_tasks, 2);
+    assert_eq!(stats.tasks_per_phase[0], (Phase::Research, 1));
+    assert_eq!(stats.tasks_per_phase[1], (Phase::Planning, 1));
+    assert_eq!(stats.research_runs, 2);
+    assert_eq!(
+        stats.average_research_duration,
+        Some(Duration::from_secs(3))
+    );
+    assert_eq!(stats.total_tokens().total(), 2400);
+    assert_eq!(stats.total_cost(), Some(0.0));
+
+    // Files count once per research doc, however often they are cited
+    assert_eq!(stats.top_files[0], ("src/lib.rs".to_string(), 2));
+    assert_eq!(stats.top_files[1], ("src/main.rs".to_string(), 2));
+}
@@ -18,3 +18,421 @@ fn test_plan_roundtrip() {
     assert_eq!(loaded.task_name, plan.task_name);
     assert_eq!(loaded.approach, plan.approach);
 }
+
+mod schema {
+    use arq_core::planning::{parse_plan, PlanSchemaError, MISSING_APPROACH, PLAN_SCHEMA_VERSION};
+    use arq_core::Plan;
+
+    fn issues(yaml: &str) -> Vec<String> {
+        match Plan::from_yaml(yaml).unwrap_err() {
+            PlanSchemaError::Invalid(issues) => issues.iter().map(|i| i.to_string()).collect(),
+            other => panic!("expected validation issues, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_legacy_plan_is_upgraded() {
+        let yaml = r#"
+task_name: auth
+approach: Add a JWT handler
+complexity: low
+files_to_create:
+  - path: src/auth.rs
+    description: JWT validation
+files_to_modify:
+  - path: src/router.rs
+    line: null
+    description: Wrap routes
+dependencies_to_add: []
+"#;
+        let plan = Plan::from_yaml(yaml).unwrap();
+
+        assert_eq!(plan.schema_version, PLAN_SCHEMA_VERSION);
+        let steps: Vec<(&str, &str)> = plan
+            .steps
+            .iter()
+            .map(|s| (s.id.as_str(), s.description.as_str()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("step-1", "Create src/auth.rs: JWT validation"),
+                ("step-2", "Modify src/router.rs: Wrap routes"),
+            ]
+        );
+        assert!(plan.acceptance_criteria.is_empty());
+    }
+
+    #[test]
+    fn test_stored_plan_without_approach_loads() {
+        let yaml = r#"
+task_name: auth
+approach: ""
+files_to_create: []
+files_to_modify: []
+"#;
+        let plan = Plan::from_yaml(yaml).unwrap();
+        assert_eq!(plan.approach, MISSING_APPROACH);
+
+        // A newly generated plan must still state its approach
+        let response = r#"{"approach": "", "files_to_create": [{"path": "src/auth.rs", "description": "JWT"}]}"#;
+        assert!(parse_plan("auth", response).is_err());
+    }
+
+    #[test]
+    fn test_newer_plan_is_rejected() {
+        let mut plan = Plan::new("auth", "Add a JWT handler");
+        plan.schema_version = PLAN_SCHEMA_VERSION + 1;
+        let err = Plan::from_yaml(&plan.to_yaml().unwrap()).unwrap_err();
+        assert!(matches!(
+            err,
+            PlanSchemaError::UnsupportedVersion { found, .. } if found == PLAN_SCHEMA_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_syntax_error_position() {
+        let yaml = "schema_version: 2\ntask_name: auth\napproach: [unclosed\n";
+        let err = Plan::from_yaml(yaml).unwrap_err();
+        let PlanSchemaError::Syntax { line, column, .. } = &err else {
+            panic!("expected a syntax error, got {}", err);
+        };
+        assert!(line.is_some() && column.is_some());
+        assert!(err.to_string().starts_with("Plan YAML error at line "));
+    }
+
+    #[test]
+    fn test_step_issues_have_paths() {
+        let yaml = r#"
+schema_version: 2
+task_name: auth
+approach: Add a JWT handler
+complexity: medium
+files_to_create: []
+files_to_modify: []
+dependencies_to_add: []
+steps:
+  - id: handler
+    description: Add the handler
+    depends_on: [routes]
+  - id: routes
+    description: Wrap routes
+    depends_on: [handler]
+  - id: routes
+    description: ""
+    depends_on: [tests]
+acceptance_criteria: ["Expired tokens are rejected"]
+risks:
+  - description: Clock skew rejects valid tokens
+    mitigation: Allow 30 seconds of leeway
+"#;
+        assert_eq!(
+            issues(yaml),
+            vec![
+                "steps[2].id: duplicate step 'routes' (first defined at steps[1])",
+                "steps[2].description: must not be empty",
+                "steps[2].depends_on[0]: unknown step 'tests'",
+            ]
+        );
+
+        let cyclic = yaml
+            .replace("depends_on: [tests]", "depends_on: []")
+            .replace(
+                "  - id: routes\n    description: \"\"",
+                "  - id: docs\n    description: Document the header",
+            );
+        assert_eq!(
+            issues(&cyclic),
+            vec!["steps[0].depends_on: step 'handler' is part of a dependency cycle"]
+        );
+    }
+}
+
+mod scaffold {
+    use arq_core::planning::{
+        scaffold_tests, FileModification, FileSpec, FunctionSignature, TestConventions,
+        TestFramework,
+    };
+    use arq_core::Plan;
+
+    fn plan() -> Plan {
+        let mut plan = Plan::new("Auth", "Add a JWT handler");
+        plan.files_to_create.push(FileSpec {
+            path: "crates/auth/src/jwt.rs".to_string(),
+            description: "JWT validation".to_string(),
+            exports: vec![FunctionSignature {
+                name: "validateToken".to_string(),
+                signature: "fn validate_token(token: &str) -> Result<Claims>".to_string(),
+                behavior: vec!["rejects expired tokens".to_string()],
+            }],
+        });
+        plan.files_to_modify.push(FileModification {
+            path: "web/src/login.ts".to_string(),
+            line: None,
+            description: "Send the token".to_string(),
+            additions: Vec::new(),
+            removals: Vec::new(),
+        });
+        plan.files_to_modify.push(FileModification {
+            path: "crates/auth/tests/jwt_tests.rs".to_string(),
+            line: None,
+            description: "Already a test".to_string(),
+            additions: Vec::new(),
+            removals: Vec::new(),
+        });
+        plan
+    }
+
+    #[test]
+    fn test_stubs_follow_conventions() {
+        let conventions = TestConventions {
+            frameworks: vec![TestFramework::Cargo, TestFramework::Vitest],
+            test_files: vec!["web/src/app.spec.ts".to_string()],
+        };
+        let stubs = scaffold_tests(&plan(), &conventions);
+
+        assert_eq!(stubs.len(), 2);
+        assert_eq!(stubs[0].path, "crates/auth/tests/jwt_tests.rs");
+        assert!(stubs[0].code.contains("fn test_validate_token()"));
+        assert!(stubs[0].code.contains("todo!(\"TODO: fn validate_token"));
+        assert_eq!(stubs[1].path, "web/src/login.spec.ts");
+        assert_eq!(stubs[1].framework, TestFramework::Vitest);
+        assert!(stubs[1].code.contains("throw new Error"));
+    }
+
+    #[test]
+    fn test_stub_descriptions_are_escaped() {
+        let mut plan = Plan::new("escape", "Quote descriptions");
+        plan.files_to_modify.push(FileModification {
+            path: "src/lib.rs".to_string(),
+            line: None,
+            description: "Return \"{ok}\" \\ done\nnext".to_string(),
+            additions: Vec::new(),
+            removals: Vec::new(),
+        });
+        let stubs = scaffold_tests(&plan, &TestConventions::default());
+
+        assert!(stubs[0]
+            .code
+            .contains(r#"todo!("TODO: Return \"{{ok}}\" \\ done\nnext");"#));
+        assert!(stubs[0].has_safe_path());
+    }
+
+    #[test]
+    fn test_stub_paths_stay_inside() {
+        let stub = |path: &str| TestStub {
+            source: "src/lib.rs".to_string(),
+            path: path.to_string(),
+            framework: TestFramework::Cargo,
+            code: String::new(),
+        };
+        assert!(stub("tests/lib_tests.rs").has_safe_path());
+        assert!(stub("./tests/lib_tests.rs").has_safe_path());
+        assert!(!stub("../outside/lib_tests.rs").has_safe_path());
+        assert!(!stub("tests/../../lib_tests.rs").has_safe_path());
+        assert!(!stub("/etc/lib_tests.rs").has_safe_path());
+        assert!(!stub("").has_safe_path());
+    }
+
+    #[test]
+    fn test_language_defaults() {
+        let conventions = TestConventions::default();
+        assert_eq!(
+            conventions.framework_for("pkg/api/handler.go"),
+            Some(TestFramework::Go)
+        );
+        assert_eq!(
+            conventions.test_path_for("pkg/api/handler.go", TestFramework::Go),
+            "pkg/api/handler_test.go"
+        );
+        assert_eq!(
+            conventions.test_path_for("src/main/java/com/app/User.java", TestFramework::JUnit),
+            "src/test/java/com/app/UserTest.java"
+        );
+        assert_eq!(conventions.framework_for("README.md"), None);
+    }
+}
+
+mod runner {
+    use arq_core::planning::prompts::{build_plan_prompt, build_planning_chat_prompt};
+    use arq_core::planning::{
+        check_constraints, parse_plan, Complexity, ConstraintViolation, PlanningError,
+        PlanningMessage, PlanningProgress, PlanningRunner,
+    };
+    use arq_core::{LLMError, PlanningConfig, ResearchDoc, LLM};
+    use async_trait::async_trait;
+    use tokio::sync::mpsc;
+
+    /// Replies with a fixed response to every prompt.
+    struct FixedLLM(&'static str);
+
+    #[async_trait]
+    impl LLM for FixedLLM {
+        async fn complete(&self, _prompt: &str) -> Result<String, LLMError> {
+            Ok(self.0.to_string())
+        }
+
+        async fn complete_with_system(
+            &self,
+            _system: &str,
+            _prompt: &str,
+        ) -> Result<String, LLMError> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    fn research() -> ResearchDoc {
+        let mut doc = ResearchDoc::new("add-auth");
+        doc.summary = "Requests are routed in src/router.rs".to_string();
+        doc.suggested_approach = "Add middleware before the router".to_string();
+        doc
+    }
+
+    const PLAN_JSON: &str = r#"Here is the plan:
+```json
+{
+  "approach": "Add JWT middleware",
+  "complexity": "LOW",
+  "files_to_create": [
+    {
+      "path": "src/auth.rs",
+      "description": "JWT validation",
+      "exports": [{ "name": "verify", "signature": "fn verify(token: &str) -> bool" }]
+    }
+  ],
+  "files_to_modify": [
+    { "path": "src/router.rs", "line": null, "description": "Wrap routes" }
+  ]
+}
+```"#;
+
+    #[test]
+    fn test_parse_plan_lenient() {
+        let plan = parse_plan("add-auth", PLAN_JSON).unwrap();
+
+        assert_eq!(plan.task_name, "add-auth");
+        assert_eq!(plan.approach, "Add JWT middleware");
+        assert_eq!(plan.complexity, Complexity::Low);
+        assert_eq!(plan.files_to_create[0].exports[0].name, "verify");
+        assert!(plan.files_to_create[0].exports[0].behavior.is_empty());
+        assert_eq!(plan.files_to_modify[0].line, None);
+        assert!(plan.files_to_modify[0].additions.is_empty());
+        assert!(plan.dependencies_to_add.is_empty());
+        // Steps derived from the files when the response has none
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[1].targets, vec!["src/router.rs"]);
+    }
+
+    #[test]
+    fn test_parse_plan_rejects_invalid() {
+        let err = parse_plan("add-auth", "I need more details first.").unwrap_err();
+        assert!(matches!(err, PlanningError::ParseError(_)));
+
+        // Long responses are cut short on a character boundary
+        let err = parse_plan("add-auth", &"é".repeat(600)).unwrap_err();
+        assert!(matches!(err, PlanningError::ParseError(m) if m.ends_with(&"é".repeat(500))));
+    }
+
+    #[test]
+    fn test_prompts_pin_research_and_conversation() {
+        let history = vec![
+            PlanningMessage::user("Should tokens be refreshed?"),
+            PlanningMessage::assistant("Yes, add a refresh endpoint."),
+        ];
+
+        let chat = build_planning_chat_prompt(
+            &research(),
+            &PlanningConfig::default(),
+            &history,
+            "Keep it in one file",
+        );
+        assert!(chat.contains("Requests are routed in src/router.rs"));
+        assert!(chat.contains("**Developer:** Should tokens be refreshed?"));
+        assert!(chat.contains("**Architect:** Yes, add a refresh endpoint."));
+        assert!(chat.ends_with("Keep it in one file"));
+
+        assert!(!chat.contains("## Project Constraints"));
+
+        let generate = build_plan_prompt(&research(), &constraints(), &[]);
+        assert!(generate.contains("Add middleware before the router"));
+        assert!(generate.contains("(no messages yet)"));
+        assert!(generate.contains("- Never create or modify files matching `src/router.rs`"));
+        assert!(generate.contains("- All new endpoints need OpenAPI entries"));
+        assert!(generate.contains("- Create or modify at most 1 files in total"));
+    }
+
+    fn constraints() -> PlanningConfig {
+        PlanningConfig {
+            protected_paths: vec!["src/router.rs".to_string(), "migrations/".to_string()],
+            conventions: vec!["All new endpoints need OpenAPI entries".to_string()],
+            max_steps: Some(1),
+        }
+    }
+
+    #[test]
+    fn test_check_constraints() {
+        let plan = parse_plan("add-auth", PLAN_JSON).unwrap();
+        assert!(check_constraints(&plan, &PlanningConfig::default()).is_empty());
+
+        let violations = check_constraints(&plan, &constraints());
+        assert_eq!(
+            violations,
+            vec![
+                ConstraintViolation::ProtectedPath {
+                    path: "src/router.rs".to_string(),
+                    pattern: "src/router.rs".to_string(),
+                },
+                ConstraintViolation::TooManySteps { steps: 2, max: 1 },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_plan_rejects_violations() {
+        let runner =
+            PlanningRunner::new(FixedLLM(PLAN_JSON), research()).with_constraints(constraints());
+        let (progress_tx, _progress_rx) = mpsc::unbounded_channel();
+
+        let err = runner
+            .generate_plan("add-auth", &[], progress_tx)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PlanningError::ConstraintViolation(ref v) if v.len() == 2));
+        assert!(err.to_string().contains("src/router.rs is protected"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_plan() {
+        let runner = PlanningRunner::new(FixedLLM(PLAN_JSON), research());
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+
+        let plan = runner
+            .generate_plan("add-auth", &[], progress_tx)
+            .await
+            .unwrap();
+        assert_eq!(plan.total_files_affected(), 2);
+
+        let mut events = Vec::new();
+        while let Ok(event) = progress_rx.try_recv() {
+            events.push(event);
+        }
+        assert!(matches!(events.last(), Some(PlanningProgress::Complete)));
+    }
+
+    #[tokio::test]
+    async fn test_chat_streams_reply() {
+        let runner = PlanningRunner::new(FixedLLM("Split it into two steps."), research());
+        let (progress_tx, _progress_rx) = mpsc::unbounded_channel();
+        let (stream_tx, mut stream_rx) = mpsc::unbounded_channel();
+
+        let reply = runner
+            .chat_streaming(&[], "How many steps?", progress_tx, stream_tx)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Split it into two steps.");
+
+        let first = stream_rx.recv().await.unwrap();
+        assert_eq!(first.text, reply);
+        assert!(stream_rx.recv().await.unwrap().is_final);
+    }
+}
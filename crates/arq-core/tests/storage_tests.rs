@@ -1,4 +1,12 @@
-use arq_core::{FileStorage, Storage, StorageConfig, Task};
+use arq_core::agent::{ConformanceStatus, ExecutionItem, ExecutionResult};
+use arq_core::config::current_user;
+use arq_core::llm::ResponseCache;
+use arq_core::planning::{FileSpec, PlanSchemaError, PlanStep, PLAN_SCHEMA_VERSION};
+use arq_core::research::cache::ContextCache;
+use arq_core::research::SentContextLog;
+use arq_core::storage::{PrivateFiles, StorageKey, StorageLock};
+use arq_core::{FileStorage, Plan, Storage, StorageConfig, StorageError, StorageLayout, Task};
+use std::time::Duration;
 use tempfile::TempDir;
 
 fn create_test_storage() -> (FileStorage, TempDir, StorageConfig) {
@@ -77,6 +85,7 @@ fn test_custom_config() {
         research_file: "research.md".to_string(),
         plan_file: "implementation.yaml".to_string(),
         project_root: Some(temp_dir.path().to_path_buf()),
+        ..StorageConfig::default()
     };
 
     let storage = FileStorage::with_config(config.clone());
@@ -91,3 +100,351 @@ fn test_custom_config() {
         .join("metadata.json");
     assert!(custom_path.exists());
 }
+
+#[test]
+fn test_shared_data_dir_is_namespaced_per_user() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = StorageConfig {
+        data_dir: temp_dir.path().to_string_lossy().to_string(),
+        shared: true,
+        project_root: Some(temp_dir.path().to_path_buf()),
+        ..StorageConfig::default()
+    };
+
+    let user_dir = temp_dir.path().join("users").join(current_user());
+    assert!(config.project_dir().starts_with(&user_dir));
+
+    let storage = FileStorage::with_config(config.clone());
+    let task = Task::new("Shared task");
+    storage.save_task(&task).unwrap();
+    assert!(config.task_path(&task.id).exists());
+}
+
+#[test]
+fn test_local_layout_keeps_data_in_project() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = StorageConfig {
+        layout: StorageLayout::Local,
+        project_root: Some(temp_dir.path().to_path_buf()),
+        ..StorageConfig::default()
+    };
+
+    let data = temp_dir.path().join(".arq").join("data");
+    assert_eq!(config.project_dir(), data);
+    assert_eq!(config.tasks_path(), data.join("tasks"));
+    assert_eq!(config.model_cache_path(), data.join("cache"));
+
+    let storage = FileStorage::with_config(config.clone());
+    let task = Task::new("Local task");
+    storage.save_task(&task).unwrap();
+    assert!(config.task_path(&task.id).starts_with(&data));
+    assert!(config.task_path(&task.id).exists());
+    assert_eq!(storage.load_task(&task.id).unwrap().id, task.id);
+
+    // Internal data stays out of the repository
+    let ignore = std::fs::read_to_string(data.join(".gitignore")).unwrap();
+    assert_eq!(ignore.trim(), "*");
+}
+
+#[test]
+fn test_data_dir_owned_by_another_user() {
+    let (storage, _temp, config) = create_test_storage();
+    let task = Task::new("Owned task");
+    storage.save_task(&task).unwrap();
+
+    // The first write claims the directory for the current user
+    let owner_file = config.project_dir().join("owner");
+    assert_eq!(
+        std::fs::read_to_string(&owner_file).unwrap(),
+        current_user()
+    );
+
+    std::fs::write(&owner_file, "someone-else").unwrap();
+    assert!(matches!(
+        storage.load_task(&task.id),
+        Err(StorageError::NotOwner { owner, .. }) if owner == "someone-else"
+    ));
+    assert!(matches!(
+        storage.save_task(&task),
+        Err(StorageError::NotOwner { .. })
+    ));
+}
+
+#[test]
+fn test_write_fails_while_locked() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = StorageConfig {
+        data_dir: temp_dir.path().to_string_lossy().to_string(),
+        lock_timeout_ms: 100,
+        project_root: Some(temp_dir.path().to_path_buf()),
+        ..StorageConfig::default()
+    };
+    let storage = FileStorage::with_config(config.clone());
+    std::fs::create_dir_all(config.project_dir()).unwrap();
+
+    let lock =
+        StorageLock::acquire(config.project_dir().join(".lock"), Duration::from_secs(1)).unwrap();
+    let task = Task::new("Contended task");
+    assert!(matches!(
+        storage.save_task(&task),
+        Err(StorageError::Locked { .. })
+    ));
+
+    // Dropping the guard releases the lock
+    drop(lock);
+    storage.save_task(&task).unwrap();
+    assert!(storage.load_task(&task.id).is_ok());
+}
+
+#[test]
+fn test_write_guard_spans_saves() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = StorageConfig {
+        data_dir: temp_dir.path().to_string_lossy().to_string(),
+        lock_timeout_ms: 100,
+        project_root: Some(temp_dir.path().to_path_buf()),
+        ..StorageConfig::default()
+    };
+    let storage = FileStorage::with_config(config.clone());
+    let other = FileStorage::with_config(config);
+    let mut task = Task::new("Guarded task");
+
+    let guard = storage.lock_writes().unwrap();
+    // Saves under the guard don't wait for it, even nested ones
+    storage.save_task(&task).unwrap();
+    {
+        let _inner = storage.lock_writes().unwrap();
+        task.add_labels(&["inner".to_string()]);
+        storage.save_task(&task).unwrap();
+    }
+    // Another process still waits until the outer guard is dropped
+    assert!(matches!(
+        other.save_task(&task),
+        Err(StorageError::Locked { .. })
+    ));
+
+    drop(guard);
+    other.save_task(&task).unwrap();
+}
+
+#[test]
+fn test_plan_schema_on_save_and_load() {
+    let (storage, temp, _config) = create_test_storage();
+
+    // A plan referring to a step that doesn't exist is rejected
+    let mut plan = Plan::new("Auth", "Add a JWT handler");
+    plan.steps.push(PlanStep {
+        id: "step-1".to_string(),
+        description: "Add the handler".to_string(),
+        targets: vec!["src/auth.rs".to_string()],
+        depends_on: vec!["step-0".to_string()],
+    });
+    let err = storage.save_plan("task", &plan).unwrap_err();
+    assert!(matches!(
+        err,
+        StorageError::Plan(PlanSchemaError::Invalid(_))
+    ));
+    assert!(!temp.path().join(".arq").join("plan.yaml").exists());
+
+    plan.steps[0].depends_on.clear();
+    storage.save_plan("task", &plan).unwrap();
+    assert!(temp.path().join(".arq").join("plan.yaml").exists());
+
+    // Plans stored before schema versions are upgraded on load
+    let mut legacy = Plan::new("Auth", "Add a JWT handler");
+    legacy.schema_version = 1;
+    legacy.files_to_create.push(FileSpec {
+        path: "src/auth.rs".to_string(),
+        description: "JWT validation".to_string(),
+        exports: Vec::new(),
+    });
+    let mut task = Task::new("Legacy plan");
+    task.plan = Some(legacy);
+    storage.save_task(&task).unwrap();
+
+    let plan = storage.load_task(&task.id).unwrap().plan.unwrap();
+    assert_eq!(plan.schema_version, PLAN_SCHEMA_VERSION);
+    assert_eq!(plan.steps.len(), 1);
+    assert_eq!(plan.steps[0].targets, vec!["src/auth.rs"]);
+
+    // Plans from a newer version are rejected
+    task.plan.as_mut().unwrap().schema_version = PLAN_SCHEMA_VERSION + 1;
+    storage.save_task(&task).unwrap();
+    assert!(matches!(
+        storage.load_task(&task.id),
+        Err(StorageError::Plan(
+            PlanSchemaError::UnsupportedVersion { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_agent_log_is_appended() {
+    let (storage, _temp, _config) = create_test_storage();
+    let task = Task::new("Agent task");
+    storage.save_task(&task).unwrap();
+    assert!(storage.load_agent_log(&task.id).unwrap().is_empty());
+
+    let result = |path: &str| ExecutionResult {
+        item: ExecutionItem::Modify {
+            path: path.to_string(),
+            description: "Update".to_string(),
+        },
+        generated_code: String::new(),
+        conformance: ConformanceStatus::Passed,
+        deviations: Vec::new(),
+    };
+    storage.save_agent_log(&task.id, &[result("a.rs")]).unwrap();
+    storage.save_agent_log(&task.id, &[result("b.rs")]).unwrap();
+
+    let logged: Vec<String> = storage
+        .load_agent_log(&task.id)
+        .unwrap()
+        .iter()
+        .map(|r| r.item.path().to_string())
+        .collect();
+    assert_eq!(logged, vec!["a.rs".to_string(), "b.rs".to_string()]);
+}
+
+#[test]
+fn test_mirror_to_repo() {
+    let (_storage, temp, mut config) = create_test_storage();
+    config.mirror_to_repo = true;
+    let storage = FileStorage::with_config(config.clone());
+
+    let task = Task::new("Mirrored task");
+    storage.save_task(&task).unwrap();
+    storage.save_agent_log(&task.id, &[]).unwrap();
+
+    let mirror = temp.path().join(".arq").join("tasks").join(&task.id);
+    assert!(mirror.join("task.yaml").exists());
+    assert!(mirror.join("agent-log.md").exists());
+
+    storage.delete_task(&task.id).unwrap();
+    assert!(!mirror.exists());
+}
+
+#[test]
+fn test_import_rejects_traversal_ids() {
+    let (storage, temp, _config) = create_test_storage();
+    let mut task = Task::new("Escaping task");
+    let mirror = temp.path().join(".arq").join("tasks");
+
+    for id in ["../../escaped", "not-a-uuid", ""] {
+        task.id = id.to_string();
+        let dir = mirror.join("entry");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("task.yaml"), serde_yaml::to_string(&task).unwrap()).unwrap();
+        assert!(matches!(
+            storage.import_mirrored_tasks(),
+            Err(StorageError::InvalidTaskId { .. })
+        ));
+    }
+
+    // A valid ID in another task's directory
+    task.id = Task::new("Other").id;
+    std::fs::write(
+        mirror.join("entry").join("task.yaml"),
+        serde_yaml::to_string(&task).unwrap(),
+    )
+    .unwrap();
+    assert!(matches!(
+        storage.import_mirrored_tasks(),
+        Err(StorageError::InvalidTaskId { .. })
+    ));
+    assert!(!temp.path().join("escaped").exists());
+    assert!(storage.list_tasks().unwrap().is_empty());
+}
+
+#[test]
+fn test_import_mirrored_tasks() {
+    let (_storage, temp, mut config) = create_test_storage();
+    config.mirror_to_repo = true;
+    let task = Task::new("Shared task");
+    FileStorage::with_config(config.clone())
+        .save_task(&task)
+        .unwrap();
+
+    // A checkout with only the repository mirror, e.g. a teammate's clone
+    let other = TempDir::new().unwrap();
+    let clone_config = StorageConfig {
+        data_dir: other.path().to_string_lossy().to_string(),
+        project_root: Some(temp.path().to_path_buf()),
+        ..StorageConfig::default()
+    };
+    let clone = FileStorage::with_config(clone_config);
+
+    assert_eq!(
+        clone.import_mirrored_tasks().unwrap(),
+        vec![task.id.clone()]
+    );
+    assert_eq!(clone.load_task(&task.id).unwrap().prompt, "Shared task");
+    assert!(clone.import_mirrored_tasks().unwrap().is_empty());
+}
+
+#[test]
+fn test_encrypted_storage() {
+    let (_storage, _temp, mut config) = create_test_storage();
+    let (_, hex_key) = StorageKey::generate().unwrap();
+    let key = || StorageKey::from_hex(&hex_key).unwrap();
+
+    config.encrypt = true;
+    let storage = FileStorage::with_config(config.clone()).with_key(key());
+    let task = Task::new("fn proprietary_algorithm()");
+    storage.save_task(&task).unwrap();
+
+    let raw = std::fs::read(config.task_path(&task.id).join("task.json")).unwrap();
+    assert!(!String::from_utf8_lossy(&raw).contains("proprietary_algorithm"));
+    assert_eq!(storage.load_task(&task.id).unwrap().prompt, task.prompt);
+
+    // Existing encrypted data stays readable with encryption turned off
+    config.encrypt = false;
+    let plain = FileStorage::with_config(config.clone()).with_key(key());
+    assert_eq!(plain.load_task(&task.id).unwrap().prompt, task.prompt);
+
+    let (wrong_key, _) = StorageKey::generate().unwrap();
+    let wrong = FileStorage::with_config(config).with_key(wrong_key);
+    assert!(matches!(
+        wrong.load_task(&task.id),
+        Err(StorageError::Encryption(_))
+    ));
+}
+
+#[test]
+fn test_encrypted_caches() {
+    let temp = TempDir::new().unwrap();
+    let (key, _) = StorageKey::generate().unwrap();
+    let files = PrivateFiles::new(true).with_key(key);
+    let contents = |dir: &str| -> Vec<String> {
+        std::fs::read_dir(temp.path().join(dir))
+            .unwrap()
+            .map(|entry| {
+                String::from_utf8_lossy(&std::fs::read(entry.unwrap().path()).unwrap()).into_owned()
+            })
+            .collect()
+    };
+
+    let context = ContextCache::new(temp.path().join("context")).with_encryption(files.clone());
+    context
+        .store("task-1", "key".to_string(), "fn secret() {}", &[], &[], &[])
+        .unwrap();
+    assert_eq!(
+        context.load("task-1", "key").unwrap().context,
+        "fn secret() {}"
+    );
+
+    let responses = ResponseCache::new(temp.path().join("llm")).with_encryption(files.clone());
+    responses.store("abc", "# Research: secret").unwrap();
+    assert_eq!(responses.load("abc").as_deref(), Some("# Research: secret"));
+
+    let sent = SentContextLog::new(temp.path().join("sent")).with_encryption(files);
+    sent.store("task-1", ["src/secret.rs:1-2".to_string()].into())
+        .unwrap();
+    assert!(sent.load("task-1").contains("src/secret.rs:1-2"));
+
+    for dir in ["context", "llm", "sent"] {
+        let written = contents(dir);
+        assert_eq!(written.len(), 1);
+        assert!(!written[0].contains("secret"));
+    }
+}
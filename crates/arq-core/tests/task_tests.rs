@@ -1,4 +1,4 @@
-use arq_core::{Phase, Task};
+use arq_core::{clean_task_name, Phase, Task, TaskError};
 
 #[test]
 fn test_new_task() {
@@ -21,6 +21,47 @@ fn test_derive_name() {
     assert_eq!(short_task.name, "fix-bug");
 }
 
+#[test]
+fn test_with_name() {
+    let task = Task::with_name("Add user authentication", "  oauth-login  ");
+    assert_eq!(task.name, "oauth-login");
+
+    // Blank names fall back to the derived name
+    let task = Task::with_name("Add user authentication", " ");
+    assert_eq!(task.name, "add-user-authentication");
+}
+
+#[test]
+fn test_rename() {
+    let mut task = Task::new("Add user authentication");
+    let before = task.updated_at;
+
+    task.rename("Login via OAuth").unwrap();
+    assert_eq!(task.name, "Login via OAuth");
+    assert!(task.updated_at >= before);
+
+    assert!(matches!(task.rename("   "), Err(TaskError::EmptyName)));
+    assert_eq!(task.name, "Login via OAuth");
+}
+
+#[test]
+fn test_clean_task_name() {
+    assert_eq!(
+        clean_task_name("\"Add OAuth Login.\"").as_deref(),
+        Some("add-oauth-login")
+    );
+    assert_eq!(
+        clean_task_name("\n  fix-cache_eviction\nExplanation follows").as_deref(),
+        Some("fix-cache-eviction")
+    );
+    assert_eq!(
+        clean_task_name("one two three four five six seven").as_deref(),
+        Some("one-two-three-four-five")
+    );
+    assert_eq!(clean_task_name("  \"...\"  "), None);
+    assert_eq!(clean_task_name(""), None);
+}
+
 #[test]
 fn test_to_summary() {
     let task = Task::new("Test task");
@@ -31,6 +72,22 @@ fn test_to_summary() {
     assert_eq!(summary.phase, task.phase);
 }
 
+#[test]
+fn test_labels() {
+    let mut task = Task::new("Test task");
+
+    let added = task.add_labels(&["backend", " urgent ", "", "Backend"]);
+    assert_eq!(added, vec!["backend", "urgent"]);
+    assert_eq!(task.labels, vec!["backend", "urgent"]);
+    assert!(task.has_label("BACKEND"));
+    assert!(task.to_summary().has_label("urgent"));
+
+    let removed = task.remove_labels(&["Urgent", "missing"]);
+    assert_eq!(removed, vec!["urgent"]);
+    assert_eq!(task.labels, vec!["backend"]);
+    assert!(!task.has_label("urgent"));
+}
+
 #[test]
 fn test_cannot_advance_without_research_doc() {
     let task = Task::new("Test task");
@@ -38,3 +95,14 @@ fn test_cannot_advance_without_research_doc() {
     // Task is in Research phase with no research_doc
     assert!(!task.can_advance());
 }
+
+#[test]
+fn test_is_valid_id() {
+    assert!(Task::is_valid_id(&Task::new("Task").id));
+    assert!(!Task::is_valid_id("../../escaped"));
+    assert!(!Task::is_valid_id("abc"));
+    assert!(!Task::is_valid_id(""));
+    // Other UUID spellings aren't task IDs
+    assert!(!Task::is_valid_id("{67e55044-10b1-426f-9247-bb680e5fe0c8}"));
+    assert!(!Task::is_valid_id("67E55044-10B1-426F-9247-BB680E5FE0C8"));
+}
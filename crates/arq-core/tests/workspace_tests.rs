@@ -0,0 +1,106 @@
+use arq_core::knowledge::parser::ParsedNode;
+use arq_core::knowledge::{CrateMap, Parser, RustParser};
+use std::fs;
+use tempfile::TempDir;
+
+fn write(root: &std::path::Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn workspace() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    write(
+        root,
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"crates/*\", \"tools/gen\"]\n",
+    );
+    write(
+        root,
+        "crates/app-core/Cargo.toml",
+        "[package]\nname = \"app-core\"\n\n[dependencies]\nserde = \"1\"\n",
+    );
+    write(
+        root,
+        "crates/app-cli/Cargo.toml",
+        "[package]\nname = \"app-cli\"\n\n[dependencies]\ncore = { path = \"../app-core\", package = \"app-core\" }\n",
+    );
+    write(root, "tools/gen/Cargo.toml", "[package]\nname = \"gen\"\n");
+
+    temp_dir
+}
+
+#[test]
+fn test_detect_workspace_members() {
+    let temp_dir = workspace();
+    let crates = CrateMap::detect(temp_dir.path());
+
+    let mut names: Vec<&str> = crates.crates().iter().map(|c| c.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["app-cli", "app-core", "gen"]);
+}
+
+#[test]
+fn test_workspace_dependencies_only() {
+    let temp_dir = workspace();
+    let crates = CrateMap::detect(temp_dir.path());
+
+    assert_eq!(
+        crates.get("app-cli").unwrap().dependencies,
+        vec!["app-core"]
+    );
+    assert!(crates.get("app_core").unwrap().dependencies.is_empty());
+}
+
+#[test]
+fn test_crate_for_path() {
+    let temp_dir = workspace();
+    let crates = CrateMap::detect(temp_dir.path());
+
+    let owner = crates.crate_for_path("crates/app-core/src/lib.rs").unwrap();
+    assert_eq!(owner.name, "app-core");
+    assert_eq!(owner.ident(), "app_core");
+
+    assert!(crates
+        .crate_for_path("crates/app-core-extra/lib.rs")
+        .is_none());
+    assert!(crates.crate_for_path("README.md").is_none());
+}
+
+#[test]
+fn test_not_a_cargo_project() {
+    let temp_dir = TempDir::new().unwrap();
+    assert!(CrateMap::detect(temp_dir.path()).is_empty());
+}
+
+#[test]
+fn test_prefix_qualified_names() {
+    let parser = RustParser::new();
+    let mut result = parser
+        .parse_file("src/lib.rs", "fn helper() {}\nfn caller() { helper(); }\n")
+        .unwrap();
+
+    result.prefix_qualified_names("app_core");
+
+    let ids: Vec<&str> = result.nodes.iter().filter_map(ParsedNode::id).collect();
+    assert!(ids.contains(&"function:src/lib.rs:app_core::caller"));
+
+    let qualified: Vec<&str> = result
+        .nodes
+        .iter()
+        .filter_map(|n| match n {
+            ParsedNode::Function(f) => Some(f.qualified_name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert!(qualified.contains(&"app_core::helper"));
+
+    // Edges follow the renamed IDs
+    assert!(result
+        .edges
+        .iter()
+        .any(|e| e.from() == "function:src/lib.rs:app_core::caller"));
+}